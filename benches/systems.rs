@@ -0,0 +1,196 @@
+// systems.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Fixed ticks of the plant scaled up in sensors, valves or bottles, and of a
+//! large scene of many belts. Each tick is timed as a whole by criterion, and
+//! the profiler's breakdown into sensor feedback, Modbus sync and physics is
+//! printed after each run.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::{Group, SolverGroups};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+use modusim::components::{
+    bottle::{Bottle, BottleSpec},
+    profiler::SystemProfile,
+    station::StationId,
+};
+use modusim::scenario::{ConveyorConfig, Scenario, SensorConfig, ValveConfig};
+use modusim::testing::{Simulation, SimulationBuilder};
+
+const ENTITY_COUNTS: [usize; 3] = [10, 100, 1000];
+const FIRST_ADDRESS: u16 = 0x0100; // Clear of the default line's registers
+const BELT_START: f32 = -600.0;
+const BELT_LENGTH: f32 = 900.0;
+const WARM_UP_TICKS: u32 = 60;
+const LARGE_CONVEYORS: usize = 50; // A large plant that should still run at 60 FPS
+const LARGE_SENSORS_PER_CONVEYOR: usize = 6;
+const LARGE_BOTTLES_PER_CONVEYOR: usize = 10;
+const LARGE_BELT_SIZE: [f32; 2] = [600.0, 40.0];
+const LARGE_ROW_SPACING: f32 = 150.0;
+
+/// Adds `count` bottle sensors spread along the belt.
+fn add_sensors(scenario: &mut Scenario, count: usize) {
+    let template = scenario.sensors[0].clone();
+    scenario.sensors.extend((0..count).map(|i| SensorConfig {
+        name: format!("bench_sensor{i}"),
+        discrete_input: FIRST_ADDRESS + i as u16,
+        position: [BELT_START + BELT_LENGTH * i as f32 / count as f32, -100.0],
+        ..template.clone()
+    }));
+}
+
+/// Adds `count` closed valves along the belt.
+fn add_valves(scenario: &mut Scenario, count: usize) {
+    let template = scenario.valves[0].clone();
+    scenario.valves.extend((0..count).map(|i| ValveConfig {
+        name: format!("bench_valve{i}"),
+        open_coil: FIRST_ADDRESS + i as u16,
+        spawn_rate_holding: FIRST_ADDRESS + i as u16,
+        position: [BELT_START + BELT_LENGTH * i as f32 / count as f32, 70.0],
+        flow_feedback: None,
+        ..template.clone()
+    }));
+}
+
+/// Drops a bottle with its center at `position`.
+fn drop_bottle(sim: &mut Simulation, position: Vec2) {
+    sim.world_mut().spawn((
+        Bottle::new(position, BottleSpec::default()),
+        SolverGroups::new(Group::GROUP_1, Group::GROUP_2),
+        StationId(0),
+    ));
+}
+
+/// Drops `count` bottles onto the belt in rows stacked above it.
+fn drop_bottles(sim: &mut Simulation, count: usize) {
+    let spec = BottleSpec::default();
+    let columns = (BELT_LENGTH / (spec.width + 10.0)) as usize;
+    for i in 0..count {
+        let (row, column) = (i / columns, i % columns);
+        let position = Vec2::new(
+            BELT_START + column as f32 * (spec.width + 10.0),
+            -40.0 + row as f32 * (spec.height + 20.0),
+        );
+        drop_bottle(sim, position);
+    }
+}
+
+/// Prints the profiler's breakdown of the ticks `sim` has run.
+fn print_profile(name: &str, sim: &Simulation) {
+    let profile = sim.resource::<SystemProfile>();
+    let sections: Vec<String> = profile
+        .iter()
+        .map(|(section, time)| format!("{section} {:.3} ms", time.average))
+        .collect();
+    println!("{name}: {}", sections.join(", "));
+}
+
+/// Times single ticks of the simulation built for each entity count.
+fn bench_ticks(c: &mut Criterion, name: &str, build: impl Fn(usize) -> Simulation) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(20);
+    for count in ENTITY_COUNTS {
+        let mut sim = build(count);
+        sim.advance(WARM_UP_TICKS);
+        group.bench_function(BenchmarkId::from_parameter(count), |b| {
+            b.iter(|| sim.advance(1))
+        });
+        print_profile(&format!("{name}/{count}"), &sim);
+    }
+    group.finish();
+}
+
+fn sensor_feedback(c: &mut Criterion) {
+    bench_ticks(c, "sensor_feedback", |count| {
+        let mut scenario = Scenario::default();
+        add_sensors(&mut scenario, count);
+        let mut sim = SimulationBuilder::new().scenario(scenario).build();
+        drop_bottles(&mut sim, 20);
+        sim
+    });
+}
+
+fn modbus_sync(c: &mut Criterion) {
+    bench_ticks(c, "modbus_sync", |count| {
+        let mut scenario = Scenario::default();
+        add_sensors(&mut scenario, count);
+        add_valves(&mut scenario, count);
+        SimulationBuilder::new().scenario(scenario).build()
+    });
+}
+
+fn physics(c: &mut Criterion) {
+    bench_ticks(c, "physics", |count| {
+        let mut sim = SimulationBuilder::new().build();
+        drop_bottles(&mut sim, count);
+        sim
+    });
+}
+
+/// Two columns of belts stacked above each other, each with sensors along it
+/// and bottles dropped onto it. A tick well under 16 ms leaves room for the
+/// plant to keep up at 60 FPS, but nothing here checks it does.
+fn large_scene(c: &mut Criterion) {
+    let mut scenario = Scenario::default();
+    let conveyor = scenario.conveyors[0].clone();
+    let sensor = scenario.sensors[0].clone();
+    let mut belts = Vec::new();
+    for i in 0..LARGE_CONVEYORS {
+        let center = Vec2::new(
+            if i % 2 == 0 { -330.0 } else { 330.0 },
+            -400.0 + (i / 2) as f32 * LARGE_ROW_SPACING,
+        );
+        let address = FIRST_ADDRESS + i as u16;
+        scenario.conveyors.push(ConveyorConfig {
+            name: format!("bench_conveyor{i}"),
+            run_coil: address,
+            speed_holding: address,
+            position: center.into(),
+            size: LARGE_BELT_SIZE,
+            speed_feedback: None,
+            ..conveyor.clone()
+        });
+
+        let surface = center.y + LARGE_BELT_SIZE[1] / 2.0;
+        let spacing = LARGE_BELT_SIZE[0] / LARGE_SENSORS_PER_CONVEYOR as f32;
+        let first = center.x - (LARGE_BELT_SIZE[0] - spacing) / 2.0;
+        for j in 0..LARGE_SENSORS_PER_CONVEYOR {
+            let index = i * LARGE_SENSORS_PER_CONVEYOR + j;
+            scenario.sensors.push(SensorConfig {
+                name: format!("bench_sensor{index}"),
+                discrete_input: FIRST_ADDRESS + index as u16,
+                position: [first + j as f32 * spacing, surface + 30.0],
+                ..sensor.clone()
+            });
+        }
+        belts.push((center.x, surface));
+    }
+
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    let spec = BottleSpec::default();
+    let pitch = spec.width + 5.0;
+    for (x, surface) in belts {
+        let first = x - pitch * (LARGE_BOTTLES_PER_CONVEYOR - 1) as f32 / 2.0;
+        for j in 0..LARGE_BOTTLES_PER_CONVEYOR {
+            let position = Vec2::new(first + j as f32 * pitch, surface + spec.height / 2.0 + 5.0);
+            drop_bottle(&mut sim, position);
+        }
+    }
+    sim.advance(WARM_UP_TICKS);
+
+    let name = format!(
+        "{LARGE_CONVEYORS}_conveyors_{}_sensors_{}_bottles",
+        LARGE_CONVEYORS * LARGE_SENSORS_PER_CONVEYOR,
+        LARGE_CONVEYORS * LARGE_BOTTLES_PER_CONVEYOR
+    );
+    let mut group = c.benchmark_group("large_scene");
+    group.sample_size(20);
+    group.bench_function(&name, |b| b.iter(|| sim.advance(1)));
+    group.finish();
+    print_profile(&format!("large_scene/{name}"), &sim);
+}
+
+criterion_group!(benches, sensor_feedback, modbus_sync, physics, large_scene);
+criterion_main!(benches);
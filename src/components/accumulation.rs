@@ -0,0 +1,281 @@
+// accumulation.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    bottle::{Bottle, BottlePosition, BottleSpec},
+    conveyor::ConveyorState,
+    modbus::ModbusState,
+    station::StationId,
+};
+
+// >>> Constants <<<
+const DEFAULT_MIN_GAP: f32 = 10.0;
+const ZONE_CONTROL_COIL: u16 = 0x0009;
+const STOPPED_SPEED: f32 = 5.0; // Bottles slower than this count as stopped
+const ZONE_EYE_SIZE: Vec2 = Vec2::new(4.0, 30.0);
+const ZONE_CLEAR_COLOR: Color = Color::srgb(0.2, 0.8, 0.2);
+const ZONE_BLOCKED_COLOR: Color = Color::srgb(0.9, 0.6, 0.1);
+
+// >>> Components <<<
+/// Marks a bottle the belt is currently slipping under.
+#[derive(Component)]
+pub struct Accumulated;
+
+/// A section of belt ending at a photo-eye, stopped independently in zone control mode.
+#[derive(Component)]
+pub struct ConveyorZone {
+    pub start_x: f32,
+    pub end_x: f32, // Position of the zone's photo-eye
+    pub blocked_discrete_address: u16,
+    pub running_discrete_address: u16,
+    pub is_blocked: bool,
+    pub is_running: bool,
+}
+
+impl ConveyorZone {
+    fn contains(&self, x: f32) -> bool {
+        x >= self.start_x && x <= self.end_x
+    }
+}
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct AccumulationSettings {
+    pub min_gap: f32,
+    pub zone_control: bool,
+    pub zone_control_coil_address: u16,
+}
+
+impl Default for AccumulationSettings {
+    fn default() -> Self {
+        Self {
+            min_gap: DEFAULT_MIN_GAP,
+            zone_control: false,
+            zone_control_coil_address: ZONE_CONTROL_COIL,
+        }
+    }
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct ConveyorZoneBundle {
+    zone: ConveyorZone,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl ConveyorZone {
+    /// Creates an accumulation zone along the conveyor.
+    ///
+    /// # Parameters
+    /// * `start_x` - Upstream end of the zone
+    /// * `end_x` - Downstream end of the zone, where its photo-eye sits
+    /// * `eye_y` - Height of the photo-eye
+    /// * `blocked_discrete_address` - Discrete input set while the photo-eye is blocked
+    /// * `running_discrete_address` - Discrete input set while the zone is driving
+    ///
+    /// # Return
+    /// The conveyor zone bundle
+    pub fn new(
+        start_x: f32,
+        end_x: f32,
+        eye_y: f32,
+        blocked_discrete_address: u16,
+        running_discrete_address: u16,
+    ) -> ConveyorZoneBundle {
+        ConveyorZoneBundle {
+            zone: ConveyorZone {
+                start_x,
+                end_x,
+                blocked_discrete_address,
+                running_discrete_address,
+                is_blocked: false,
+                is_running: true,
+            },
+            sprite: Sprite::from_color(ZONE_CLEAR_COLOR, ZONE_EYE_SIZE),
+            transform: Transform::from_xyz(end_x, eye_y, 1.0),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn handle_accumulation_input(
+    mut settings: ResMut<AccumulationSettings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyZ) {
+        settings.zone_control = !settings.zone_control;
+        info!("Zone control: {}", settings.zone_control);
+    }
+}
+
+pub fn update_accumulation(
+    mut commands: Commands,
+    settings: Res<AccumulationSettings>,
+    conveyors: Query<(&ConveyorState, &StationId)>,
+    mut zones: Query<(&mut ConveyorZone, &StationId)>,
+    bottles: Query<
+        (
+            Entity,
+            &Transform,
+            &BottlePosition,
+            &BottleSpec,
+            &Velocity,
+            &StationId,
+            Has<Accumulated>,
+        ),
+        With<Bottle>,
+    >,
+) {
+    // Every station accumulates along its own line
+    let stations: BTreeSet<StationId> = conveyors
+        .iter()
+        .map(|(_, station)| *station)
+        .chain(zones.iter().map(|(_, station)| *station))
+        .collect();
+
+    for station in stations {
+        // Bottles ordered downstream first
+        let mut line: Vec<(Entity, f32, f32, f32, bool)> = bottles
+            .iter()
+            .filter(|(.., bottle_station, _)| **bottle_station == station)
+            .map(|(entity, transform, position, spec, velocity, _, is_accumulated)| {
+                (
+                    entity,
+                    position.world_center(transform).x,
+                    spec.width,
+                    velocity.linvel.x,
+                    is_accumulated,
+                )
+            })
+            .collect();
+        line.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        // A zone stops once its photo-eye is blocked while the zone downstream is also blocked
+        let mut station_zones: Vec<Mut<ConveyorZone>> = zones
+            .iter_mut()
+            .filter(|(_, zone_station)| **zone_station == station)
+            .map(|(zone, _)| zone)
+            .collect();
+        station_zones.sort_by(|a, b| b.end_x.total_cmp(&a.end_x));
+
+        let mut downstream_blocked = false;
+        for zone in station_zones.iter_mut() {
+            let is_blocked = line
+                .iter()
+                .any(|(_, x, width, _, _)| (x - zone.end_x).abs() < width / 2.0);
+            let is_running = !(settings.zone_control && is_blocked && downstream_blocked);
+
+            if zone.is_blocked != is_blocked || zone.is_running != is_running {
+                zone.is_blocked = is_blocked;
+                zone.is_running = is_running;
+            }
+            downstream_blocked = is_blocked;
+        }
+
+        let is_conveyor_running = conveyors
+            .iter()
+            .any(|(conveyor_state, conveyor_station)| {
+                *conveyor_station == station && conveyor_state.is_running
+            });
+        if !is_conveyor_running {
+            continue;
+        }
+
+        // Hold bottles in stopped zones or too close to a stopped bottle ahead
+        let mut ahead: Option<(f32, bool)> = None; // (upstream edge, is stopped)
+        for &(entity, x, width, speed, was_accumulated) in line.iter() {
+            let in_stopped_zone = station_zones
+                .iter()
+                .any(|zone| !zone.is_running && zone.contains(x));
+            let too_close = ahead.is_some_and(|(ahead_edge, ahead_stopped)| {
+                ahead_stopped && ahead_edge - (x + width / 2.0) < settings.min_gap
+            });
+            let is_accumulated = in_stopped_zone || too_close;
+
+            if is_accumulated != was_accumulated {
+                if is_accumulated {
+                    commands.entity(entity).insert(Accumulated);
+                } else {
+                    commands.entity(entity).remove::<Accumulated>();
+                }
+            }
+
+            ahead = Some((x - width / 2.0, is_accumulated || speed.abs() < STOPPED_SPEED));
+        }
+    }
+}
+
+pub fn update_zone_visuals(mut zones: Query<(&ConveyorZone, &mut Sprite), Changed<ConveyorZone>>) {
+    for (zone, mut sprite) in zones.iter_mut() {
+        sprite.color = if zone.is_blocked {
+            ZONE_BLOCKED_COLOR
+        } else {
+            ZONE_CLEAR_COLOR
+        };
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_zones_to_modbus(
+    zones: Query<&ConveyorZone, Changed<ConveyorZone>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+        for zone in zones.iter() {
+            discretes.insert(zone.blocked_discrete_address, zone.is_blocked);
+            discretes.insert(zone.running_discrete_address, zone.is_running);
+        }
+    }
+}
+
+pub fn sync_accumulation_to_modbus(
+    settings: Res<AccumulationSettings>,
+    modbus_state: Res<ModbusState>,
+) {
+    if settings.is_changed() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(settings.zone_control_coil_address, settings.zone_control);
+        }
+    }
+}
+
+pub fn sync_modbus_to_accumulation(
+    mut settings: ResMut<AccumulationSettings>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(coils) = modbus_state.coils.lock() {
+        if let Some(&coil_state) = coils.get(&settings.zone_control_coil_address) {
+            if settings.zone_control != coil_state {
+                settings.zone_control = coil_state;
+                info!("Zone control set to: {}", coil_state);
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct AccumulationPlugin;
+
+impl Plugin for AccumulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AccumulationSettings::default())
+            .add_systems(Update, (handle_accumulation_input, update_zone_visuals))
+            .add_systems(
+                FixedUpdate,
+                (
+                    sync_accumulation_to_modbus,
+                    sync_modbus_to_accumulation,
+                    update_accumulation,
+                    sync_zones_to_modbus,
+                )
+                    .chain(),
+            );
+    }
+}
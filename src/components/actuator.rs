@@ -0,0 +1,295 @@
+// actuator.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use super::{
+    alarm::{Alarm, AlarmEvent},
+    modbus::ModbusState,
+    pneumatics::Pneumatics,
+    simulation::SimTime,
+    tag_sync::sync_tags,
+    tags::DeviceName,
+    valve::{ValveState, update_ball_spawner_timer},
+};
+
+// >>> Components <<<
+/// Where a valve's actuator is in its stroke.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValveTravel {
+    #[default]
+    Closed,
+    Opening,
+    Open,
+    Closing,
+}
+
+/// Actuator moving a valve between its end positions over a travel time,
+/// with limit switches at both ends.
+///
+/// The open coil commands the valve; liquid flows from the moment it starts
+/// opening until it is fully closed again. A stroke taking longer than
+/// `timeout` seconds faults the valve until it next reaches the commanded end.
+#[derive(Component, Debug, Clone)]
+pub struct ValveActuator {
+    pub open_discrete_address: u16,
+    pub closed_discrete_address: u16,
+    pub fault_discrete_address: u16,
+    pub open_time: f32,  // Seconds from fully closed to fully open
+    pub close_time: f32, // Seconds from fully open to fully closed
+    pub timeout: f32,    // Seconds
+    pub position: f32,   // 0 fully closed, 1 fully open
+    pub travel: ValveTravel,
+    pub is_stuck: bool, // Seized actuator, which stops moving until freed
+    pub is_faulted: bool,
+    travelling_for: f32,
+}
+
+impl ValveActuator {
+    /// Creates a closed valve actuator.
+    ///
+    /// # Parameters
+    /// * `open_discrete_address` - Discrete input of the fully-open limit switch
+    /// * `closed_discrete_address` - Discrete input of the fully-closed limit switch
+    /// * `fault_discrete_address` - Discrete input set while the stroke has timed out
+    /// * `open_time` - Seconds to travel from fully closed to fully open
+    /// * `close_time` - Seconds to travel from fully open to fully closed
+    /// * `timeout` - Seconds a stroke may take before the valve faults
+    ///
+    /// # Return
+    /// The valve actuator component
+    pub fn new(
+        open_discrete_address: u16,
+        closed_discrete_address: u16,
+        fault_discrete_address: u16,
+        open_time: f32,
+        close_time: f32,
+        timeout: f32,
+    ) -> Self {
+        Self {
+            open_discrete_address,
+            closed_discrete_address,
+            fault_discrete_address,
+            open_time,
+            close_time,
+            timeout,
+            position: 0.0,
+            travel: ValveTravel::Closed,
+            is_stuck: false,
+            is_faulted: false,
+            travelling_for: 0.0,
+        }
+    }
+
+    pub fn is_fully_open(&self) -> bool {
+        self.position >= 1.0
+    }
+
+    pub fn is_fully_closed(&self) -> bool {
+        self.position <= 0.0
+    }
+
+    /// Snaps the valve fully closed and clears its faults.
+    pub fn reset(&mut self) {
+        self.position = 0.0;
+        self.travel = ValveTravel::Closed;
+        self.is_stuck = false;
+        self.is_faulted = false;
+        self.travelling_for = 0.0;
+    }
+}
+
+/// Whether liquid passes the valve: while it is commanded open, or for an
+/// actuated valve, until it is fully closed.
+pub fn is_passing(valve_state: &ValveState, actuator: Option<&ValveActuator>) -> bool {
+    actuator.map_or(valve_state.is_open, |actuator| !actuator.is_fully_closed())
+}
+
+// >>> Resources <<<
+/// Valves whose actuators are seized, keyed by device name.
+#[derive(Resource, Default)]
+pub struct ValveFaults {
+    seized: HashSet<String>,
+}
+
+impl ValveFaults {
+    pub fn seize(&mut self, valve: &str) {
+        self.seized.insert(valve.to_string());
+    }
+
+    /// # Return
+    /// Whether the valve was seized
+    pub fn free(&mut self, valve: &str) -> bool {
+        self.seized.remove(valve)
+    }
+
+    pub fn clear_all(&mut self) {
+        self.seized.clear();
+    }
+
+    pub fn is_seized(&self, valve: &str) -> bool {
+        self.seized.contains(valve)
+    }
+}
+
+// >>> Systems <<<
+pub fn register_valve_actuators(
+    actuators: Query<&ValveActuator, Added<ValveActuator>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for actuator in actuators.iter() {
+        if let Ok(mut discrete_inputs) = modbus_state.discrete_inputs.lock() {
+            discrete_inputs.insert(actuator.open_discrete_address, actuator.is_fully_open());
+            discrete_inputs.insert(actuator.closed_discrete_address, actuator.is_fully_closed());
+            discrete_inputs.insert(actuator.fault_discrete_address, actuator.is_faulted);
+        }
+
+        info!(
+            "Registered valve actuator on closed discrete input: {:x?}",
+            actuator.closed_discrete_address
+        );
+    }
+}
+
+/// Seizes and frees the actuators of the valves named in [`ValveFaults`].
+pub fn apply_valve_faults(
+    faults: Res<ValveFaults>,
+    mut actuators: Query<(&mut ValveActuator, &DeviceName)>,
+) {
+    for (mut actuator, DeviceName(name)) in actuators.iter_mut() {
+        let is_stuck = faults.is_seized(name);
+        if actuator.is_stuck != is_stuck {
+            actuator.is_stuck = is_stuck;
+            info!(
+                "Valve {} {}",
+                name,
+                if is_stuck { "seized" } else { "freed" }
+            );
+        }
+    }
+}
+
+/// Moves each actuator towards the end its valve is commanded to, timing the
+/// stroke. With pneumatics, each stroke draws air and the actuator slows with
+/// the header pressure, stalling when it runs low.
+pub fn update_valve_travel(
+    time: Res<SimTime>,
+    mut valves: Query<(&ValveState, &mut ValveActuator)>,
+    mut pneumatics: Option<ResMut<Pneumatics>>,
+) {
+    let delta = time.delta_secs();
+
+    for (valve_state, mut actuator) in valves.iter_mut() {
+        let (target, travel_time, travel) = if valve_state.is_open {
+            (1.0, actuator.open_time, ValveTravel::Opening)
+        } else {
+            (0.0, actuator.close_time, ValveTravel::Closing)
+        };
+
+        if actuator.position == target {
+            let end = if valve_state.is_open {
+                ValveTravel::Open
+            } else {
+                ValveTravel::Closed
+            };
+            if actuator.travel != end || actuator.is_faulted {
+                actuator.travel = end;
+                actuator.travelling_for = 0.0;
+                actuator.is_faulted = false;
+            }
+            continue;
+        }
+
+        // A reversed command starts a new stroke
+        if actuator.travel != travel {
+            actuator.travel = travel;
+            actuator.travelling_for = 0.0;
+            if let Some(pneumatics) = pneumatics.as_deref_mut() {
+                pneumatics.stroke();
+            }
+        }
+        actuator.travelling_for += delta;
+
+        let speed = pneumatics.as_deref().map_or(1.0, Pneumatics::speed_factor);
+        if !actuator.is_stuck && speed > 0.0 {
+            let step = if travel_time > 0.0 {
+                delta * speed / travel_time
+            } else {
+                1.0
+            };
+            actuator.position = if target > actuator.position {
+                (actuator.position + step).min(target)
+            } else {
+                (actuator.position - step).max(target)
+            };
+        }
+
+        if !actuator.is_faulted && actuator.travelling_for > actuator.timeout {
+            actuator.is_faulted = true;
+            warn!(
+                "Valve {} travel timed out",
+                actuator.closed_discrete_address
+            );
+        }
+    }
+}
+
+pub fn monitor_valve_travel_alarms(
+    actuators: Query<&ValveActuator>,
+    mut alarm_events: EventWriter<AlarmEvent>,
+    mut was_faulted: Local<bool>,
+) {
+    let is_faulted = actuators.iter().any(|actuator| actuator.is_faulted);
+    if *was_faulted != is_faulted {
+        *was_faulted = is_faulted;
+        alarm_events.write(if is_faulted {
+            AlarmEvent::Raise(Alarm::ValveTravel)
+        } else {
+            AlarmEvent::Clear(Alarm::ValveTravel)
+        });
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_valve_actuators_to_modbus(
+    actuators: Query<&ValveActuator, Changed<ValveActuator>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if actuators.is_empty() {
+        return;
+    }
+
+    if let Ok(mut discrete_inputs) = modbus_state.discrete_inputs.lock() {
+        for actuator in actuators.iter() {
+            discrete_inputs.insert(actuator.open_discrete_address, actuator.is_fully_open());
+            discrete_inputs.insert(actuator.closed_discrete_address, actuator.is_fully_closed());
+            discrete_inputs.insert(actuator.fault_discrete_address, actuator.is_faulted);
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Valve actuator travel times, limit switches, stroke timeouts and seized
+/// actuators.
+pub struct ActuatorPlugin;
+
+impl Plugin for ActuatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ValveFaults>().add_systems(
+            FixedUpdate,
+            (
+                register_valve_actuators,
+                apply_valve_faults,
+                update_valve_travel,
+                monitor_valve_travel_alarms,
+                sync_valve_actuators_to_modbus,
+            )
+                .chain()
+                .after(sync_tags)
+                .before(update_ball_spawner_timer),
+        );
+    }
+}
@@ -0,0 +1,342 @@
+// alarm.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{hmi::HmiPanel, modbus::ModbusState};
+
+// >>> Constants <<<
+const ALARM_WORD_INPUT: u16 = 0x0005;
+const ALARM_WORD_COUNT: u16 = 1; // 16 alarms per input register
+const ALARM_ACK_COIL: u16 = 0x000B;
+const UNACKED_COLOR: Color = Color::srgb(0.8, 0.1, 0.1);
+const ACKED_COLOR: Color = Color::srgb(0.8, 0.5, 0.1);
+
+// >>> Alarms <<<
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Alarm {
+    JamDetected,
+    SensorFault,
+    BallLimitExceeded,
+    HopperEmpty,
+    TransferBlocked,
+    CommsLoss,
+    BottleTipped,
+    Spill,
+    MotorOverload,
+    ValveTravel,
+    LowAirPressure,
+    /// Raised by a machine registered from another crate. ModuSim's own
+    /// alarms count up from bit 0, so machines should count down from bit 15.
+    Machine {
+        bit: u16,
+        description: &'static str,
+    },
+}
+
+impl Alarm {
+    /// Bit of the alarm in the Modbus alarm words.
+    pub fn bit(&self) -> u16 {
+        match self {
+            Alarm::JamDetected => 0,
+            Alarm::SensorFault => 1,
+            Alarm::BallLimitExceeded => 2,
+            Alarm::HopperEmpty => 3,
+            Alarm::TransferBlocked => 4,
+            Alarm::CommsLoss => 5,
+            Alarm::BottleTipped => 6,
+            Alarm::Spill => 7,
+            Alarm::MotorOverload => 8,
+            Alarm::ValveTravel => 9,
+            Alarm::LowAirPressure => 10,
+            Alarm::Machine { bit, .. } => *bit,
+        }
+    }
+
+    /// Lower values are more urgent.
+    pub fn priority(&self) -> u8 {
+        match self {
+            Alarm::JamDetected => 1,
+            Alarm::SensorFault => 1,
+            Alarm::CommsLoss => 1,
+            Alarm::MotorOverload => 1,
+            Alarm::ValveTravel => 1,
+            Alarm::LowAirPressure => 1,
+            Alarm::HopperEmpty => 2,
+            Alarm::TransferBlocked => 2,
+            Alarm::BottleTipped => 2,
+            Alarm::Machine { .. } => 2,
+            Alarm::Spill => 2,
+            Alarm::BallLimitExceeded => 3,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Alarm::JamDetected => "Jam detected",
+            Alarm::SensorFault => "Sensor fault",
+            Alarm::BallLimitExceeded => "Max ball count exceeded",
+            Alarm::HopperEmpty => "Hopper empty",
+            Alarm::TransferBlocked => "Conveyor transfer blocked",
+            Alarm::CommsLoss => "PLC comms lost",
+            Alarm::BottleTipped => "Bottle tipped over",
+            Alarm::Spill => "Liquid spill",
+            Alarm::MotorOverload => "Conveyor motor overload",
+            Alarm::ValveTravel => "Valve travel timeout",
+            Alarm::LowAirPressure => "Low air pressure",
+            Alarm::Machine { description, .. } => *description,
+        }
+    }
+}
+
+// >>> Events <<<
+#[derive(Event, Debug, Clone, Copy)]
+pub enum AlarmEvent {
+    Raise(Alarm),
+    Clear(Alarm),
+}
+
+// >>> Components <<<
+#[derive(Component)]
+pub struct AlarmBanner;
+
+#[derive(Component)]
+pub struct AlarmBannerText;
+
+// >>> Resources <<<
+#[derive(Debug, Clone)]
+pub struct AlarmEntry {
+    pub alarm: Alarm,
+    pub is_active: bool,
+    pub is_acknowledged: bool,
+    pub raised_at: f64, // Seconds since startup
+}
+
+#[derive(Resource)]
+pub struct AlarmState {
+    entries: Vec<AlarmEntry>, // Ordered by priority, then age
+    pub word_input_address: u16,
+    pub word_count: u16,
+    pub ack_coil_address: u16,
+}
+
+impl Default for AlarmState {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            word_input_address: ALARM_WORD_INPUT,
+            word_count: ALARM_WORD_COUNT,
+            ack_coil_address: ALARM_ACK_COIL,
+        }
+    }
+}
+
+impl AlarmState {
+    pub fn entries(&self) -> &[AlarmEntry] {
+        &self.entries
+    }
+
+    pub fn is_active(&self, alarm: Alarm) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.alarm == alarm && entry.is_active)
+    }
+
+    pub fn raise(&mut self, alarm: Alarm, now: f64) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.alarm == alarm) {
+            if !entry.is_active {
+                entry.is_active = true;
+                entry.is_acknowledged = false;
+                entry.raised_at = now;
+            }
+        } else {
+            self.entries.push(AlarmEntry {
+                alarm,
+                is_active: true,
+                is_acknowledged: false,
+                raised_at: now,
+            });
+        }
+
+        self.entries.sort_by(|a, b| {
+            a.alarm
+                .priority()
+                .cmp(&b.alarm.priority())
+                .then(a.raised_at.total_cmp(&b.raised_at))
+        });
+    }
+
+    /// Marks the alarm as returned to normal; it stays listed until acknowledged.
+    pub fn clear(&mut self, alarm: Alarm) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.alarm == alarm) {
+            entry.is_active = false;
+        }
+        self.entries
+            .retain(|entry| entry.is_active || !entry.is_acknowledged);
+    }
+
+    pub fn acknowledge_all(&mut self) {
+        for entry in self.entries.iter_mut() {
+            entry.is_acknowledged = true;
+        }
+        self.entries.retain(|entry| entry.is_active);
+    }
+
+    /// Alarm bitfield, one bit per listed alarm.
+    pub fn words(&self) -> Vec<u16> {
+        let mut words = vec![0u16; self.word_count as usize];
+        for entry in self.entries.iter() {
+            let bit = entry.alarm.bit();
+            if let Some(word) = words.get_mut((bit / 16) as usize) {
+                *word |= 1 << (bit % 16);
+            }
+        }
+        words
+    }
+}
+
+// >>> Systems <<<
+pub fn setup_alarm_banner(mut commands: Commands) {
+    commands
+        .spawn((
+            AlarmBanner,
+            HmiPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                width: Val::Percent(100.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(UNACKED_COLOR),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                AlarmBannerText,
+                Text::new(""),
+                TextFont {
+                    font_size: 18.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+pub fn handle_alarm_events(
+    time: Res<Time>,
+    mut alarm_events: EventReader<AlarmEvent>,
+    mut alarm_state: ResMut<AlarmState>,
+) {
+    for alarm_event in alarm_events.read() {
+        match *alarm_event {
+            AlarmEvent::Raise(alarm) => {
+                if !alarm_state.is_active(alarm) {
+                    warn!("Alarm raised: {}", alarm.description());
+                    alarm_state.raise(alarm, time.elapsed_secs_f64());
+                }
+            }
+            AlarmEvent::Clear(alarm) => {
+                if alarm_state.is_active(alarm) {
+                    info!("Alarm cleared: {}", alarm.description());
+                    alarm_state.clear(alarm);
+                }
+            }
+        }
+    }
+}
+
+pub fn handle_alarm_input(
+    mut alarm_state: ResMut<AlarmState>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyA) && !alarm_state.entries().is_empty() {
+        alarm_state.acknowledge_all();
+        info!("Alarms acknowledged");
+    }
+}
+
+pub fn update_alarm_banner(
+    alarm_state: Res<AlarmState>,
+    mut banners: Query<(&mut BackgroundColor, &mut Visibility), With<AlarmBanner>>,
+    mut texts: Query<&mut Text, With<AlarmBannerText>>,
+) {
+    if !alarm_state.is_changed() {
+        return;
+    }
+
+    let entries = alarm_state.entries();
+    let is_unacknowledged = entries.iter().any(|entry| !entry.is_acknowledged);
+
+    for (mut background, mut visibility) in banners.iter_mut() {
+        *visibility = if entries.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Visible
+        };
+        background.0 = if is_unacknowledged {
+            UNACKED_COLOR
+        } else {
+            ACKED_COLOR
+        };
+    }
+
+    for mut text in texts.iter_mut() {
+        text.0 = match entries.first() {
+            Some(entry) if entries.len() > 1 => {
+                format!("{} (+{} more)", entry.alarm.description(), entries.len() - 1)
+            }
+            Some(entry) => entry.alarm.description().to_string(),
+            None => String::new(),
+        };
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_alarms_to_modbus(alarm_state: Res<AlarmState>, modbus_state: Res<ModbusState>) {
+    if alarm_state.is_changed() {
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            for (i, word) in alarm_state.words().into_iter().enumerate() {
+                inputs.insert(alarm_state.word_input_address + i as u16, word);
+            }
+        }
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.entry(alarm_state.ack_coil_address).or_insert(false);
+        }
+    }
+}
+
+pub fn sync_modbus_to_alarms(mut alarm_state: ResMut<AlarmState>, modbus_state: Res<ModbusState>) {
+    if let Ok(mut coils) = modbus_state.coils.lock() {
+        // Acknowledge is a command coil and resets itself once handled
+        if coils.get(&alarm_state.ack_coil_address) == Some(&true) {
+            coils.insert(alarm_state.ack_coil_address, false);
+            alarm_state.acknowledge_all();
+            info!("Alarms acknowledged via Modbus");
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct AlarmPlugin;
+
+impl Plugin for AlarmPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AlarmState::default())
+            .add_event::<AlarmEvent>()
+            .add_systems(Startup, setup_alarm_banner)
+            .add_systems(Update, (handle_alarm_input, update_alarm_banner).chain())
+            .add_systems(
+                FixedUpdate,
+                (
+                    handle_alarm_events,
+                    sync_modbus_to_alarms,
+                    sync_alarms_to_modbus,
+                )
+                    .chain(),
+            );
+    }
+}
@@ -0,0 +1,256 @@
+// arbitration.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use super::{
+    modbus::ModbusState,
+    simulation::{SimTime, advance_sim_time},
+    tags::{Tag, TagKind},
+};
+
+// >>> Provenance <<<
+/// Kind of writer behind a coil or holding register write, lowest priority
+/// first.
+///
+/// The plant's own logic (interlocks, control loops, self-resetting command
+/// coils, the timeline) is never arbitrated. An HMI write holds off remote
+/// writes for the configured hold time. Remote writes by Modbus, S7, DNP3
+/// and web API clients hold off nobody. Forces override all three.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WriteSource {
+    Remote,
+    Hmi,
+    Logic,
+}
+
+impl WriteSource {
+    pub fn description(&self) -> &'static str {
+        match self {
+            WriteSource::Remote => "remote",
+            WriteSource::Hmi => "hmi",
+            WriteSource::Logic => "logic",
+        }
+    }
+}
+
+/// Who wrote a value: the kind of writer and which one, e.g. a client's
+/// address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOrigin {
+    pub source: WriteSource,
+    pub writer: String,
+}
+
+impl WriteOrigin {
+    pub fn remote(writer: impl Into<String>) -> Self {
+        Self {
+            source: WriteSource::Remote,
+            writer: writer.into(),
+        }
+    }
+
+    pub fn hmi(writer: impl Into<String>) -> Self {
+        Self {
+            source: WriteSource::Hmi,
+            writer: writer.into(),
+        }
+    }
+
+    pub fn logic(writer: impl Into<String>) -> Self {
+        Self {
+            source: WriteSource::Logic,
+            writer: writer.into(),
+        }
+    }
+}
+
+/// The last write that changed a coil or holding register.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteRecord {
+    pub origin: WriteOrigin,
+    pub value: u16,    // 0 or 1 for coils
+    pub time: f64,     // Simulated seconds
+    pub rejected: u32, // Lower-priority writes held off since
+}
+
+/// Provenance of every coil and holding register, and the rule arbitrating
+/// between their writers. Shared with the protocol servers through
+/// [`ModbusState`].
+#[derive(Debug, Default)]
+pub struct Arbitration {
+    pub hmi_hold: f64, // Seconds an HMI write holds off remote writes
+    pub now: f64,      // Simulated seconds, stamped on writes
+    records: HashMap<(TagKind, u16), WriteRecord>,
+}
+
+impl Arbitration {
+    /// Arbitrates a write to a coil or holding register, recording it if it
+    /// changes the value.
+    ///
+    /// # Parameters
+    /// * `kind` - Table written
+    /// * `address` - Address written
+    /// * `current` - The value it holds now
+    /// * `value` - The value written
+    /// * `origin` - Who is writing
+    ///
+    /// # Return
+    /// Whether the write may go ahead
+    pub fn admit(
+        &mut self,
+        kind: TagKind,
+        address: u16,
+        current: u16,
+        value: u16,
+        origin: &WriteOrigin,
+    ) -> bool {
+        if current == value {
+            return true;
+        }
+
+        if let Some(record) = self.records.get_mut(&(kind, address)) {
+            let is_held = record.origin.source == WriteSource::Hmi
+                && origin.source < WriteSource::Hmi
+                && self.now - record.time < self.hmi_hold;
+            if is_held {
+                record.rejected += 1;
+                if record.rejected == 1 {
+                    warn!(
+                        "{} write of {} by {} {} held off by {} {}",
+                        kind.abbreviation(),
+                        address,
+                        origin.source.description(),
+                        origin.writer,
+                        record.origin.source.description(),
+                        record.origin.writer
+                    );
+                }
+                return false;
+            }
+        }
+
+        self.records.insert(
+            (kind, address),
+            WriteRecord {
+                origin: origin.clone(),
+                value,
+                time: self.now,
+                rejected: 0,
+            },
+        );
+        true
+    }
+
+    /// Attributes a value nobody recorded writing to the plant's logic.
+    fn observe(&mut self, kind: TagKind, address: u16, value: u16) {
+        if self
+            .records
+            .get(&(kind, address))
+            .is_some_and(|record| record.value == value)
+        {
+            return;
+        }
+
+        self.records.insert(
+            (kind, address),
+            WriteRecord {
+                origin: WriteOrigin::logic("plant"),
+                value,
+                time: self.now,
+                rejected: 0,
+            },
+        );
+    }
+
+    pub fn record(&self, kind: TagKind, address: u16) -> Option<&WriteRecord> {
+        self.records.get(&(kind, address))
+    }
+}
+
+/// Describes who last changed a tag, e.g. `by hmi mouse at 12.3s`.
+pub fn provenance_line(tag: &Tag, modbus_state: &ModbusState) -> Option<String> {
+    let arbitration = modbus_state.arbitration.lock().ok()?;
+    let record = arbitration.record(tag.kind, tag.address)?;
+
+    let mut line = format!(
+        "by {} {} at {:.1}s",
+        record.origin.source.description(),
+        record.origin.writer,
+        record.time
+    );
+    if record.rejected > 0 {
+        line += &format!(" ({} held off)", record.rejected);
+    }
+    Some(line)
+}
+
+// >>> Resources <<<
+/// Arbitration rule of the scenario, from its `[arbitration]` table.
+#[derive(Resource, Debug, Clone)]
+pub struct ArbitrationSettings {
+    pub hmi_hold: f64, // Seconds an HMI write holds off remote writes
+}
+
+// >>> Systems <<<
+/// Stamps writes with the simulated clock and applies the scenario's rule.
+pub fn update_arbitration(
+    time: Res<SimTime>,
+    settings: Option<Res<ArbitrationSettings>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut arbitration) = modbus_state.arbitration.lock() {
+        arbitration.now = time.elapsed_secs_f64();
+        arbitration.hmi_hold = settings.map_or(0.0, |settings| settings.hmi_hold);
+    }
+}
+
+/// Attributes every change to a coil or holding register that no writer
+/// recorded, i.e. made by the plant itself, to its logic. Only addresses
+/// whose value moved since the last tick are looked up, and forced addresses
+/// are left alone, keeping the provenance of the value underneath.
+pub fn track_logic_writes(
+    modbus_state: Res<ModbusState>,
+    mut last_values: Local<HashMap<(TagKind, u16), u16>>,
+) {
+    let forced: HashSet<(TagKind, u16)> = match modbus_state.forces.lock() {
+        Ok(forces) => forces.keys().copied().collect(),
+        Err(_) => return,
+    };
+    let Some(banks) = modbus_state.lock_banks() else {
+        return;
+    };
+    let Ok(mut arbitration) = modbus_state.arbitration.lock() else {
+        return;
+    };
+
+    let coils = banks
+        .coils
+        .iter()
+        .map(|(&address, &value)| ((TagKind::Coil, address), value as u16));
+    let holdings = banks
+        .holding_registers
+        .iter()
+        .map(|(&address, &value)| ((TagKind::HoldingRegister, address), value));
+    for (key, value) in coils.chain(holdings) {
+        if last_values.insert(key, value) == Some(value) || forced.contains(&key) {
+            continue;
+        }
+        arbitration.observe(key.0, key.1, value);
+    }
+}
+
+// >>> Plugin <<<
+/// Write priority between the plant's logic, the HMI and remote clients, and
+/// the provenance of every coil and holding register.
+pub struct ArbitrationPlugin;
+
+impl Plugin for ArbitrationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedFirst, update_arbitration.after(advance_sim_time))
+            .add_systems(FixedLast, track_logic_writes);
+    }
+}
@@ -0,0 +1,229 @@
+// barcode.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+
+use super::{
+    bottle::{BOTTLE_WIDTH, Bottle, SerialNumber},
+    modbus::ModbusState,
+    simulation::SimRng,
+};
+
+// >>> Constants <<<
+pub const SERIAL_WORDS: u16 = 2; // Input registers per serial number, high word first
+const SCAN_ZONE_HEIGHT: f32 = 20.0;
+const SCANNER_COLOR: Color = Color::srgb(0.9, 0.3, 0.2);
+
+// >>> Components <<<
+/// Barcode scanner reading the serial number of the bottle in front of it
+/// on each rising edge of its trigger coil.
+///
+/// A good read publishes the serial across `SERIAL_WORDS` input registers
+/// from `serial_input_address`, high word first, and sets the read OK bit. A
+/// no-read, or a trigger with no bottle in front, clears both.
+#[derive(Component)]
+pub struct BarcodeScanner {
+    pub trigger_coil_address: u16,
+    pub read_ok_discrete_address: u16,
+    pub serial_input_address: u16, // First register of the serial block
+    pub no_read_rate: f32,         // Probability a read fails
+    pub last_serial: Option<u32>,
+    bottle_in_zone: Option<Entity>,
+    last_trigger: bool,
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct BarcodeScannerBundle {
+    scanner: BarcodeScanner,
+    collider: Collider,
+    collider_sensor: bevy_rapier2d::geometry::Sensor,
+    active_events: ActiveEvents,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl BarcodeScanner {
+    /// Creates a barcode scanner with nothing read yet.
+    ///
+    /// # Parameters
+    /// * `trigger_coil_address` - Coil whose rising edge starts a read
+    /// * `read_ok_discrete_address` - Discrete input set when the last read succeeded
+    /// * `serial_input_address` - First input register of the serial number block
+    /// * `no_read_rate` - Probability that a read fails
+    /// * `position` - Position of the scan zone
+    ///
+    /// # Return
+    /// The barcode scanner bundle
+    pub fn new(
+        trigger_coil_address: u16,
+        read_ok_discrete_address: u16,
+        serial_input_address: u16,
+        no_read_rate: f32,
+        position: Vec2,
+    ) -> BarcodeScannerBundle {
+        let zone_size = Vec2::new(BOTTLE_WIDTH + 20.0, SCAN_ZONE_HEIGHT);
+
+        BarcodeScannerBundle {
+            scanner: BarcodeScanner {
+                trigger_coil_address,
+                read_ok_discrete_address,
+                serial_input_address,
+                no_read_rate,
+                last_serial: None,
+                bottle_in_zone: None,
+                last_trigger: false,
+            },
+            collider: Collider::cuboid(zone_size.x / 2.0, zone_size.y / 2.0),
+            collider_sensor: bevy_rapier2d::geometry::Sensor,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            sprite: Sprite::from_color(SCANNER_COLOR.with_alpha(0.4), zone_size),
+            transform: Transform::from_translation(position.extend(0.0)),
+        }
+    }
+
+    /// The serial split into its input register words, high word first.
+    pub fn serial_words(serial: Option<u32>) -> [u16; SERIAL_WORDS as usize] {
+        let serial = serial.unwrap_or(0);
+        [(serial >> 16) as u16, serial as u16]
+    }
+}
+
+// >>> Systems <<<
+pub fn register_barcode_scanners(
+    scanners: Query<&BarcodeScanner, Added<BarcodeScanner>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for scanner in scanners.iter() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(scanner.trigger_coil_address, false);
+        }
+        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+            discretes.insert(scanner.read_ok_discrete_address, false);
+        }
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            for i in 0..SERIAL_WORDS {
+                inputs.insert(scanner.serial_input_address + i, 0);
+            }
+        }
+
+        info!(
+            "Registered barcode scanner on trigger coil: {:x?}",
+            scanner.trigger_coil_address
+        );
+    }
+}
+
+pub fn track_scan_zones(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut scanners: Query<&mut BarcodeScanner>,
+    bottles: Query<(), With<Bottle>>,
+) {
+    for collision_event in collision_events.read() {
+        let (e1, e2, is_started) = match collision_event {
+            CollisionEvent::Started(e1, e2, _) => (*e1, *e2, true),
+            CollisionEvent::Stopped(e1, e2, _) => (*e1, *e2, false),
+        };
+
+        let (scanner_entity, bottle_entity) = if scanners.contains(e1) {
+            (e1, e2)
+        } else if scanners.contains(e2) {
+            (e2, e1)
+        } else {
+            continue;
+        };
+
+        if !bottles.contains(bottle_entity) {
+            continue;
+        }
+
+        let mut scanner = scanners.get_mut(scanner_entity).unwrap();
+        if is_started {
+            scanner.bottle_in_zone = Some(bottle_entity);
+        } else if scanner.bottle_in_zone == Some(bottle_entity) {
+            scanner.bottle_in_zone = None;
+        }
+    }
+}
+
+/// Reads the bottle in front of each scanner whose trigger coil just rose.
+pub fn trigger_barcode_scanners(
+    mut scanners: Query<&mut BarcodeScanner>,
+    bottles: Query<&SerialNumber, With<Bottle>>,
+    modbus_state: Res<ModbusState>,
+    mut rng: ResMut<SimRng>,
+) {
+    let Ok(coils) = modbus_state.coils.lock() else {
+        return;
+    };
+    let mut reads = Vec::new();
+
+    for mut scanner in scanners.iter_mut() {
+        let trigger = coils
+            .get(&scanner.trigger_coil_address)
+            .copied()
+            .unwrap_or(false);
+
+        if trigger && !scanner.last_trigger {
+            let serial = scanner
+                .bottle_in_zone
+                .and_then(|bottle| bottles.get(bottle).ok())
+                .map(|SerialNumber(serial)| *serial)
+                .filter(|_| rng.random::<f32>() >= scanner.no_read_rate);
+
+            match serial {
+                Some(serial) => info!(
+                    "Barcode scanner {:x?} read serial {serial}",
+                    scanner.trigger_coil_address
+                ),
+                None => warn!(
+                    "Barcode scanner {:x?} no read",
+                    scanner.trigger_coil_address
+                ),
+            }
+            scanner.last_serial = serial;
+            reads.push((
+                scanner.read_ok_discrete_address,
+                scanner.serial_input_address,
+                serial,
+            ));
+        }
+
+        if scanner.last_trigger != trigger {
+            scanner.last_trigger = trigger;
+        }
+    }
+    drop(coils);
+
+    for (read_ok_address, serial_address, serial) in reads {
+        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+            discretes.insert(read_ok_address, serial.is_some());
+        }
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            for (i, word) in BarcodeScanner::serial_words(serial).into_iter().enumerate() {
+                inputs.insert(serial_address + i as u16, word);
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Barcode scanners publishing bottle serial numbers for track and trace.
+pub struct BarcodePlugin;
+
+impl Plugin for BarcodePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_barcode_scanners,
+                track_scan_zones,
+                trigger_barcode_scanners,
+            )
+                .chain(),
+        );
+    }
+}
@@ -2,16 +2,32 @@
 // Copyright (C) 2025 deciphr
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::{path::Path, time::Duration};
+
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use super::conveyor::ConveyorState;
+use super::{
+    conveyor::{Conveyor, ConveyorShape, ConveyorState, belt_infeed, belt_low_point},
+    diverter::{Diverted, Sorted},
+    gamepad,
+    gantry::Handled,
+    modbus::ModbusState,
+    simulation::{SimRng, SimTime, WorldBounds},
+    station::StationId,
+    valve::{BALL_RADIUS, WATER_COLOR},
+    variability::VariabilityConfig,
+};
 
 // >>> Constants <<<
 pub const BOTTLE_HEIGHT: f32 = 100.0;
 pub const BOTTLE_WIDTH: f32 = 50.0;
 const BOTTLE_THICKNESS: f32 = 5.0;
 const BOTTLE_COLOR: Color = Color::srgb(0.8, 0.8, 0.8);
+const PRODUCT_SELECTION_HOLDING: u16 = 0x0002;
+const INFEED_DROP_HEIGHT: f32 = 100.0; // Bottles are dropped onto the belt from above
 
 // >>> Components <<<
 #[derive(Component)]
@@ -20,29 +36,443 @@ pub struct Bottle;
 #[derive(Component)]
 pub struct BottlePosition(pub Vec2);
 
-// >>> Resources <<<
-#[derive(Resource)]
+/// Physical variant of a bottle.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+pub struct BottleSpec {
+    pub name: String,
+    pub height: f32,
+    pub width: f32,
+    pub mass: f32,
+    pub color: Color,
+}
+
+impl Default for BottleSpec {
+    fn default() -> Self {
+        Self {
+            name: "standard".to_string(),
+            height: BOTTLE_HEIGHT,
+            width: BOTTLE_WIDTH,
+            mass: 1.0,
+            color: BOTTLE_COLOR,
+        }
+    }
+}
+
+/// Balls of water merged into the bottle.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct FillLevel(pub u32);
+
+/// Liquid drawn inside a bottle, sized from its [`FillLevel`].
+#[derive(Component)]
+pub struct LiquidLevel;
+
+/// Marks a bottle that has had a cap applied.
+#[derive(Component)]
+pub struct Capped;
+
+/// Serial number a bottle is given when it is put on the line, as printed on
+/// its barcode.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct SerialNumber(pub u32);
+
+impl BottlePosition {
+    /// World-space center of the bottle, accounting for its collider offset.
+    pub fn world_center(&self, transform: &Transform) -> Vec2 {
+        transform.transform_point(self.0.extend(0.0)).truncate()
+    }
+
+    /// Checks whether a world-space point lies inside the bottle's outline.
+    pub fn contains(&self, transform: &Transform, spec: &BottleSpec, point: Vec2) -> bool {
+        let local = transform
+            .compute_affine()
+            .inverse()
+            .transform_point3(point.extend(0.0))
+            .truncate()
+            - self.0;
+
+        local.x.abs() < spec.width / 2.0 && local.y.abs() < spec.height / 2.0
+    }
+
+    /// World-space height of the liquid surface in an upright bottle, or of
+    /// the inside of its base when empty.
+    pub fn liquid_surface(
+        &self,
+        transform: &Transform,
+        spec: &BottleSpec,
+        fill_level: &FillLevel,
+    ) -> f32 {
+        let floor = self.world_center(transform).y - spec.height / 2.0 + BOTTLE_THICKNESS / 2.0;
+        floor + liquid_height(spec, fill_level)
+    }
+}
+
+/// How the gaps between bottles put on an infeed are drawn.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InfeedProfile {
+    /// One bottle every interval.
+    #[default]
+    Steady,
+    /// Random arrivals averaging one bottle per interval.
+    Poisson,
+    /// `count` bottles an interval apart, then `gap` seconds until the next burst.
+    Bursts { count: u32, gap: f32 },
+    /// Gaps in seconds taken in turn and repeated, ignoring the interval.
+    Scripted { gaps: Vec<f32> },
+}
+
+/// Paces bottles onto the infeed of the conveyor it is attached to while
+/// the conveyor runs and the infeed is enabled.
+#[derive(Component)]
 pub struct BottleSpawner {
     timer: Timer,
+    interval: f32, // Seconds, before any jitter
+    profile: InfeedProfile,
+    spawned: usize, // Bottles since the profile started, to place them in a burst or script
+    pub is_enabled: bool,
+    pub is_held: bool, // Held off in maintenance mode, whether enabled or not
 }
 
 impl Default for BottleSpawner {
     fn default() -> Self {
         Self {
             timer: Timer::from_seconds(2.0, TimerMode::Repeating),
+            interval: 2.0,
+            profile: InfeedProfile::default(),
+            spawned: 0,
+            is_enabled: true,
+            is_held: false,
+        }
+    }
+}
+
+impl BottleSpawner {
+    /// Seconds between bottles, before any jitter.
+    pub fn interval(&self) -> f32 {
+        self.interval
+    }
+
+    pub fn set_interval(&mut self, seconds: f32) {
+        self.interval = seconds;
+        self.timer.set_duration(Duration::from_secs_f32(self.gap()));
+        self.timer.reset();
+    }
+
+    pub fn profile(&self) -> &InfeedProfile {
+        &self.profile
+    }
+
+    /// Switches to a new profile, starting it from its first gap.
+    pub fn set_profile(&mut self, profile: InfeedProfile) {
+        self.profile = profile;
+        self.spawned = 0;
+        self.timer.set_duration(Duration::from_secs_f32(self.gap()));
+        self.timer.reset();
+    }
+
+    /// Gap before the next bottle, or its mean for random arrivals, never
+    /// negative whatever the profile asks for.
+    fn gap(&self) -> f32 {
+        let gap = match &self.profile {
+            InfeedProfile::Steady | InfeedProfile::Poisson => self.interval,
+            InfeedProfile::Bursts { count, gap } => {
+                if self.spawned > 0 && self.spawned % (*count).max(1) as usize == 0 {
+                    *gap
+                } else {
+                    self.interval
+                }
+            }
+            InfeedProfile::Scripted { gaps } => {
+                if gaps.is_empty() {
+                    self.interval
+                } else {
+                    gaps[self.spawned % gaps.len()]
+                }
+            }
+        };
+        gap.max(0.0)
+    }
+
+    /// Times the bottle after the one just spawned, stretched or shortened by
+    /// the spawn jitter.
+    ///
+    /// # Parameters
+    /// * `jitter` - Fraction the gap varies by
+    /// * `rng` - The plant's random source
+    pub fn schedule_next(&mut self, jitter: f32, rng: &mut SimRng) {
+        self.spawned += 1;
+
+        let gap = match self.profile {
+            // Exponential gaps, drawn by inverting the distribution
+            InfeedProfile::Poisson => -self.interval * (1.0 - rng.random::<f32>()).ln(),
+            _ => self.gap(),
+        };
+        let factor = VariabilityConfig::factor(jitter, rng);
+        self.timer
+            .set_duration(Duration::from_secs_f32((gap * factor).max(0.0)));
+    }
+}
+
+// >>> Resources <<<
+/// Identifies the collider a pooled bottle can be reused for: its variant and
+/// its collider offset to the nearest pixel.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PoolKey {
+    variant: String,
+    offset: IVec2,
+}
+
+impl PoolKey {
+    fn new(spec: &BottleSpec, position: Vec2) -> Self {
+        Self {
+            variant: spec.name.clone(),
+            offset: position.round().as_ivec2(),
+        }
+    }
+}
+
+/// Bottles taken off the line, kept with only their colliders for reuse.
+///
+/// A bottle's collider is built from its spec and spawn position, so only a
+/// pooled bottle of the same variant and offset can be recycled.
+#[derive(Resource, Default)]
+pub struct BottlePool {
+    free: Vec<(Entity, PoolKey, Vec2)>,
+}
+
+impl BottlePool {
+    /// Takes a bottle off the line and parks it in the pool, stripped of
+    /// everything but its collider.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity, spec: &BottleSpec, position: Vec2) {
+        commands
+            .entity(entity)
+            .despawn_related::<Children>()
+            .retain::<(Collider, Transform, GlobalTransform)>()
+            .insert((ColliderDisabled, Visibility::Hidden));
+        self.free
+            .push((entity, PoolKey::new(spec, position), position));
+    }
+
+    /// Puts a pooled bottle back on the line as new, or spawns a new one if
+    /// none match.
+    pub fn acquire(&mut self, commands: &mut Commands, position: Vec2, spec: BottleSpec) -> Entity {
+        let key = PoolKey::new(&spec, position);
+        let pooled = self
+            .free
+            .iter()
+            .position(|(_, pooled_key, _)| *pooled_key == key);
+
+        match pooled {
+            Some(index) => {
+                // Positioned where its collider was built, which is within a pixel
+                let (entity, _, pooled_position) = self.free.swap_remove(index);
+                commands
+                    .entity(entity)
+                    .remove::<ColliderDisabled>()
+                    .insert(Bottle::body(pooled_position, spec));
+                entity
+            }
+            None => commands.spawn(Bottle::new(position, spec)).id(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+}
+
+/// Source of bottle serial numbers, counting up from 1.
+#[derive(Resource)]
+pub struct SerialCounter {
+    pub next: u32,
+}
+
+impl Default for SerialCounter {
+    fn default() -> Self {
+        Self { next: 1 }
+    }
+}
+
+/// Bottle variants available to the spawner and the one currently selected.
+#[derive(Resource)]
+pub struct ProductSelection {
+    pub catalog: Vec<BottleSpec>,
+    pub selected: usize,
+    pub holding_address: u16,
+}
+
+impl Default for ProductSelection {
+    fn default() -> Self {
+        Self {
+            catalog: vec![
+                BottleSpec::default(),
+                BottleSpec {
+                    name: "tall".to_string(),
+                    height: 130.0,
+                    width: 40.0,
+                    mass: 1.2,
+                    color: Color::srgb(0.6, 0.9, 0.6),
+                },
+                BottleSpec {
+                    name: "wide".to_string(),
+                    height: 80.0,
+                    width: 70.0,
+                    mass: 1.5,
+                    color: Color::srgb(0.9, 0.7, 0.5),
+                },
+            ],
+            selected: 0,
+            holding_address: PRODUCT_SELECTION_HOLDING,
+        }
+    }
+}
+
+impl ProductSelection {
+    pub fn selected_spec(&self) -> BottleSpec {
+        self.catalog.get(self.selected).cloned().unwrap_or_default()
+    }
+}
+
+/// How bottles ride the conveyor, applied to each bottle as it enters the line.
+///
+/// The defaults match Rapier's, so an unset field leaves bottles as before.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BottlePhysics {
+    pub mass: Option<f32>, // Overrides every product's own mass
+    pub friction: f32,
+    pub restitution: f32,
+    pub center_of_gravity: Option<f32>, // Height above the base, as a fraction of the bottle's height
+    pub tip_angle: f32, // Degrees from upright beyond which a bottle counts as tipped over
+}
+
+impl Default for BottlePhysics {
+    fn default() -> Self {
+        Self {
+            mass: None,
+            friction: 0.5,
+            restitution: 0.0,
+            center_of_gravity: None,
+            tip_angle: 60.0,
+        }
+    }
+}
+
+impl BottlePhysics {
+    /// Mass, and centre of gravity if set, of a bottle of the given variant.
+    ///
+    /// # Parameters
+    /// * `position` - The bottle's collider offset
+    /// * `spec` - Variant of the bottle
+    ///
+    /// # Return
+    /// The bottle's collider mass properties
+    pub fn mass_properties(&self, position: Vec2, spec: &BottleSpec) -> ColliderMassProperties {
+        let mass = self.mass.unwrap_or(spec.mass);
+        match self.center_of_gravity {
+            Some(height) => ColliderMassProperties::MassProperties(MassProperties {
+                local_center_of_mass: position + Vec2::Y * (height - 0.5) * spec.height,
+                mass,
+                principal_inertia: mass * (spec.width.powi(2) + spec.height.powi(2)) / 12.0,
+            }),
+            None => ColliderMassProperties::Mass(mass),
+        }
+    }
+}
+
+/// Timestamped bottle arrivals replayed instead of the fixed spawn timer.
+///
+/// Arrival times are in seconds of conveyor running time, so the schedule
+/// pauses with the line just like the timer does.
+#[derive(Resource)]
+pub struct ArrivalSchedule {
+    arrivals: Vec<f32>,
+    next: usize,
+    elapsed: f32,
+}
+
+impl ArrivalSchedule {
+    /// Loads a schedule from a CSV file whose first column is the arrival time in seconds.
+    pub fn from_csv(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        let mut arrivals = Vec::new();
+
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let field = line.split(',').next().unwrap_or_default().trim();
+            match field.parse::<f32>() {
+                Ok(time) => arrivals.push(time),
+                Err(_) if line_number == 0 => continue, // Header row
+                Err(_) => anyhow::bail!(
+                    "Invalid arrival time on line {}: {}",
+                    line_number + 1,
+                    field
+                ),
+            }
+        }
+
+        arrivals.sort_by(|a, b| a.total_cmp(b));
+
+        Ok(Self {
+            arrivals,
+            next: 0,
+            elapsed: 0.0,
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.arrivals.len()
+    }
+
+    /// Advances the schedule and returns how many bottles have come due, so
+    /// none are lost to close arrivals or a long frame.
+    fn tick(&mut self, delta: f32) -> usize {
+        if self.is_finished() {
+            return 0;
         }
+
+        self.elapsed += delta;
+        let due = self.arrivals[self.next..]
+            .iter()
+            .take_while(|&&arrival| arrival <= self.elapsed)
+            .count();
+        self.next += due;
+        if due > 0 && self.is_finished() {
+            info!("Arrival schedule complete");
+        }
+
+        due
     }
 }
 
 // >>> Bundles <<<
+/// Everything a bottle starts with but its collider.
 #[derive(Bundle)]
 pub struct BottleBundle {
     bottle: Bottle,
     position: BottlePosition,
+    spec: BottleSpec,
+    fill_level: FillLevel,
     transform: Transform,
     global_transform: GlobalTransform,
     rigid_body: RigidBody,
-    collider: Collider,
+    velocity: Velocity,
+    mass: ColliderMassProperties,
     ccd: Ccd,
     sleeping: Sleeping,
     visibility: Visibility,
@@ -50,75 +480,117 @@ pub struct BottleBundle {
 }
 
 impl Bottle {
-    pub fn new(position: Vec2) -> impl Bundle {
+    pub fn new(position: Vec2, spec: BottleSpec) -> impl Bundle {
+        let collider = Bottle::collider(position, &spec);
+        (Bottle::body(position, spec), collider)
+    }
+
+    /// Fresh state for a bottle, whether newly spawned or taken from the pool.
+    fn body(position: Vec2, spec: BottleSpec) -> BottleBundle {
         BottleBundle {
             bottle: Bottle,
             position: BottlePosition(position),
+            mass: ColliderMassProperties::Mass(spec.mass),
+            spec,
+            fill_level: FillLevel::default(),
             transform: Transform::default(),
             global_transform: GlobalTransform::default(),
             rigid_body: RigidBody::Dynamic,
-            collider: Collider::compound(vec![
-                (
-                    Vec2::new((-BOTTLE_WIDTH / 2.0) + position.x, position.y),
-                    0.0,
-                    Collider::cuboid(BOTTLE_THICKNESS / 2.0, BOTTLE_HEIGHT / 2.0),
-                ),
-                (
-                    Vec2::new(position.x, (-BOTTLE_HEIGHT / 2.0) + position.y),
-                    0.0,
-                    Collider::cuboid(BOTTLE_WIDTH / 2.0, BOTTLE_THICKNESS / 2.0),
-                ),
-                (
-                    Vec2::new((BOTTLE_WIDTH / 2.0) + position.x, position.y),
-                    0.0,
-                    Collider::cuboid(BOTTLE_THICKNESS / 2.0, BOTTLE_HEIGHT / 2.0),
-                ),
-            ]),
+            velocity: Velocity::zero(),
             ccd: Ccd::enabled(),
             sleeping: Sleeping::disabled(),
             visibility: Visibility::Visible,
             inherited_visibility: InheritedVisibility::default(),
         }
     }
+
+    /// Open-topped outline of the bottle, offset by its position.
+    fn collider(position: Vec2, spec: &BottleSpec) -> Collider {
+        let (width, height) = (spec.width, spec.height);
+
+        Collider::compound(vec![
+            (
+                Vec2::new((-width / 2.0) + position.x, position.y),
+                0.0,
+                Collider::cuboid(BOTTLE_THICKNESS / 2.0, height / 2.0),
+            ),
+            (
+                Vec2::new(position.x, (-height / 2.0) + position.y),
+                0.0,
+                Collider::cuboid(width / 2.0, BOTTLE_THICKNESS / 2.0),
+            ),
+            (
+                Vec2::new((width / 2.0) + position.x, position.y),
+                0.0,
+                Collider::cuboid(BOTTLE_THICKNESS / 2.0, height / 2.0),
+            ),
+        ])
+    }
 }
 
 // >>> Systems <<<
+pub fn apply_bottle_physics(
+    mut commands: Commands,
+    bottles: Query<(Entity, &BottlePosition, &BottleSpec), Added<Bottle>>,
+    physics: Res<BottlePhysics>,
+) {
+    for (entity, BottlePosition(position), spec) in bottles.iter() {
+        commands.entity(entity).insert((
+            physics.mass_properties(*position, spec),
+            Friction::coefficient(physics.friction),
+            Restitution::coefficient(physics.restitution),
+        ));
+    }
+}
+
 pub fn add_bottle_sprite(
     mut commands: Commands,
-    query: Query<(Entity, &BottlePosition), Added<Bottle>>,
+    query: Query<(Entity, &BottlePosition, &BottleSpec), Added<Bottle>>,
 ) {
-    for (entity, BottlePosition(position)) in query.iter() {
+    for (entity, BottlePosition(position), spec) in query.iter() {
         commands.entity(entity).with_children(|parent| {
             parent.spawn((
                 Transform::from_translation(
-                    Vec2::new(position.x - BOTTLE_WIDTH / 2.0, position.y).extend(0.0),
+                    Vec2::new(position.x - spec.width / 2.0, position.y).extend(0.0),
+                ),
+                GlobalTransform::default(),
+                Sprite {
+                    color: spec.color,
+                    custom_size: Some(Vec2::new(BOTTLE_THICKNESS, spec.height)),
+                    ..Default::default()
+                },
+            ));
+            parent.spawn((
+                Transform::from_translation(
+                    Vec2::new(position.x, position.y - spec.height / 2.0).extend(0.0),
                 ),
                 GlobalTransform::default(),
                 Sprite {
-                    color: BOTTLE_COLOR,
-                    custom_size: Some(Vec2::new(BOTTLE_THICKNESS, BOTTLE_HEIGHT)),
+                    color: spec.color,
+                    custom_size: Some(Vec2::new(spec.width, BOTTLE_THICKNESS)),
                     ..Default::default()
                 },
             ));
             parent.spawn((
                 Transform::from_translation(
-                    Vec2::new(position.x, position.y - BOTTLE_HEIGHT / 2.0).extend(0.0),
+                    Vec2::new(position.x + spec.width / 2.0, position.y).extend(0.0),
                 ),
                 GlobalTransform::default(),
                 Sprite {
-                    color: BOTTLE_COLOR,
-                    custom_size: Some(Vec2::new(BOTTLE_WIDTH, BOTTLE_THICKNESS)),
+                    color: spec.color,
+                    custom_size: Some(Vec2::new(BOTTLE_THICKNESS, spec.height)),
                     ..Default::default()
                 },
             ));
             parent.spawn((
+                LiquidLevel,
                 Transform::from_translation(
-                    Vec2::new(position.x + BOTTLE_WIDTH / 2.0, position.y).extend(0.0),
+                    Vec2::new(position.x, position.y - spec.height / 2.0).extend(0.1),
                 ),
                 GlobalTransform::default(),
                 Sprite {
-                    color: BOTTLE_COLOR,
-                    custom_size: Some(Vec2::new(BOTTLE_THICKNESS, BOTTLE_HEIGHT)),
+                    color: WATER_COLOR,
+                    custom_size: Some(Vec2::new(spec.width - BOTTLE_THICKNESS, 0.0)),
                     ..Default::default()
                 },
             ));
@@ -126,47 +598,221 @@ pub fn add_bottle_sprite(
     }
 }
 
+/// Height of the liquid in a bottle, each merged ball adding its area.
+fn liquid_height(spec: &BottleSpec, fill_level: &FillLevel) -> f32 {
+    let inner_width = spec.width - BOTTLE_THICKNESS;
+    let inner_height = spec.height - BOTTLE_THICKNESS;
+    let ball_area = std::f32::consts::PI * BALL_RADIUS * BALL_RADIUS;
+    (fill_level.0 as f32 * ball_area / inner_width).min(inner_height)
+}
+
+/// Raises each bottle's liquid as balls merge into it, conserving the balls' area.
+pub fn update_liquid_levels(
+    bottles: Query<(&FillLevel, &BottlePosition, &BottleSpec, &Children), Changed<FillLevel>>,
+    mut liquids: Query<(&mut Sprite, &mut Transform), With<LiquidLevel>>,
+) {
+    for (fill_level, BottlePosition(position), spec, children) in bottles.iter() {
+        let inner_width = spec.width - BOTTLE_THICKNESS;
+        let height = liquid_height(spec, fill_level);
+        let floor = position.y - spec.height / 2.0 + BOTTLE_THICKNESS / 2.0;
+
+        for &child in children {
+            if let Ok((mut sprite, mut transform)) = liquids.get_mut(child) {
+                sprite.custom_size = Some(Vec2::new(inner_width, height));
+                transform.translation.y = floor + height / 2.0;
+            }
+        }
+    }
+}
+
+/// Spawns a bottle at the upstream end of a conveyor, recycling one from the pool if possible.
+///
+/// # Parameters
+/// * `infeed` - Upstream end of the belt surface
+/// * `station` - Station the conveyor belongs to
+/// * `spec` - Variant of the bottle
+pub fn spawn_infeed_bottle(
+    commands: &mut Commands,
+    pool: &mut BottlePool,
+    infeed: Vec2,
+    station: StationId,
+    spec: BottleSpec,
+) {
+    let position = infeed + Vec2::new(spec.width / 2.0, INFEED_DROP_HEIGHT + spec.height);
+
+    let entity = pool.acquire(commands, position, spec);
+    commands
+        .entity(entity)
+        .insert((SolverGroups::new(Group::GROUP_1, Group::GROUP_2), station));
+}
+
 pub fn spawn_bottle_on_input(
     mut commands: Commands,
     keyboard: Res<ButtonInput<KeyCode>>,
-    window: Query<&Window>,
+    gamepads: Query<&Gamepad>,
+    // Only infeed conveyors take new bottles; chained ones are fed by the belt before them
+    conveyors: Query<
+        (&Transform, &Sprite, &ConveyorShape, &StationId),
+        (With<Conveyor>, With<BottleSpawner>),
+    >,
+    product_selection: Res<ProductSelection>,
+    mut pool: ResMut<BottlePool>,
 ) {
-    let window = window.single();
-    let width = window.unwrap().resolution.width();
-
-    if keyboard.just_pressed(KeyCode::Enter) {
-        commands
-            .spawn(Bottle::new(Vec2::new(
-                -width / 2.0 + BOTTLE_WIDTH / 2.0,
-                BOTTLE_HEIGHT,
-            )))
-            .insert(SolverGroups::new(Group::GROUP_1, Group::GROUP_2));
+    if keyboard.just_pressed(KeyCode::Enter)
+        || gamepad::just_pressed(&gamepads, gamepad::BOTTLE_SPAWN)
+    {
+        for (transform, sprite, shape, station) in conveyors.iter() {
+            spawn_infeed_bottle(
+                &mut commands,
+                &mut pool,
+                belt_infeed(transform, sprite, shape),
+                *station,
+                product_selection.selected_spec(),
+            );
+        }
         info!("Spawned a new bottle!");
     }
 }
 
+pub fn handle_product_input(
+    mut product_selection: ResMut<ProductSelection>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyP) && !product_selection.catalog.is_empty() {
+        product_selection.selected = (product_selection.selected + 1) % product_selection.catalog.len();
+        info!("Product selected: {}", product_selection.selected_spec().name);
+    }
+}
+
 pub fn spawn_bottles(
-    time: Res<Time>,
+    time: Res<SimTime>,
     mut commands: Commands,
-    window: Query<&Window>,
-    mut bottle_spawner: ResMut<BottleSpawner>,
-    conveyor_state: Res<ConveyorState>,
+    mut conveyors: Query<
+        (
+            &Transform,
+            &Sprite,
+            &ConveyorShape,
+            &ConveyorState,
+            &mut BottleSpawner,
+            &StationId,
+        ),
+        With<Conveyor>,
+    >,
+    schedule: Option<ResMut<ArrivalSchedule>>,
+    product_selection: Res<ProductSelection>,
+    mut pool: ResMut<BottlePool>,
+    variability: Res<VariabilityConfig>,
+    mut rng: ResMut<SimRng>,
 ) {
-    if !conveyor_state.is_running {
+    if !conveyors.iter().any(|(_, _, _, conveyor_state, _, _)| conveyor_state.is_running) {
         return;
     }
 
-    bottle_spawner.timer.tick(time.delta());
-    if bottle_spawner.timer.just_finished() {
-        let window = window.single();
-        let width = window.unwrap().resolution.width();
+    // A shared arrival schedule feeds every station at once
+    let scheduled = schedule.map(|mut schedule| schedule.tick(time.delta_secs()));
 
-        commands
-            .spawn(Bottle::new(Vec2::new(
-                -width / 2.0 + BOTTLE_WIDTH / 2.0,
-                BOTTLE_HEIGHT,
-            )))
-            .insert(SolverGroups::new(Group::GROUP_1, Group::GROUP_2));
+    for (transform, sprite, shape, conveyor_state, mut bottle_spawner, station) in
+        conveyors.iter_mut()
+    {
+        if !conveyor_state.is_running || !bottle_spawner.is_enabled || bottle_spawner.is_held {
+            continue;
+        }
+
+        let due = scheduled.unwrap_or_else(|| {
+            bottle_spawner.timer.tick(time.delta());
+            let is_due = bottle_spawner.timer.just_finished();
+            if is_due {
+                bottle_spawner.schedule_next(variability.spawn_jitter, &mut rng);
+            }
+            is_due as usize
+        });
+
+        // Bottles due together are stacked, to drop one after another rather than into each other
+        let infeed = belt_infeed(transform, sprite, shape);
+        let spec = product_selection.selected_spec();
+        for index in 0..due {
+            spawn_infeed_bottle(
+                &mut commands,
+                &mut pool,
+                infeed + Vec2::Y * spec.height * index as f32,
+                *station,
+                spec.clone(),
+            );
+        }
+    }
+}
+
+/// Gives each bottle put on the line, new or pooled, the next serial number.
+pub fn assign_serial_numbers(
+    mut commands: Commands,
+    bottles: Query<Entity, Added<Bottle>>,
+    mut counter: ResMut<SerialCounter>,
+) {
+    for entity in bottles.iter() {
+        commands.entity(entity).insert(SerialNumber(counter.next));
+        counter.next = counter.next.wrapping_add(1).max(1);
+    }
+}
+
+/// Takes bottles off the line once they leave the world bounds or fall off their station's conveyor.
+pub fn cleanup_lost_bottles(
+    mut commands: Commands,
+    mut pool: ResMut<BottlePool>,
+    bounds: Res<WorldBounds>,
+    conveyors: Query<(&Transform, &Sprite, &ConveyorShape, &StationId), With<Conveyor>>,
+    bottles: Query<
+        (Entity, &Transform, &BottlePosition, &BottleSpec, &StationId),
+        (With<Bottle>, Without<Diverted>, Without<Sorted>, Without<Handled>),
+    >,
+) {
+    for (entity, transform, position, spec, station) in bottles.iter() {
+        // Bottles sorted into lanes or placed by a gantry legitimately sit below the belt
+        let belt_top = conveyors
+            .iter()
+            .filter(|(_, _, _, conveyor_station)| *conveyor_station == station)
+            .map(|(transform, sprite, shape, _)| belt_low_point(transform, sprite, shape))
+            .filter(|top| top.is_finite())
+            .reduce(f32::min);
+
+        let center = position.world_center(transform);
+        let is_off_conveyor = belt_top.is_some_and(|top| center.y < top - spec.height);
+
+        if !bounds.contains(center) || is_off_conveyor {
+            pool.release(&mut commands, entity, spec, position.0);
+            info!("Removed bottle {:?} that left the line", entity);
+        }
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_product_selection_to_modbus(
+    product_selection: Res<ProductSelection>,
+    modbus_state: Res<ModbusState>,
+) {
+    if product_selection.is_changed() {
+        if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+            holdings.insert(product_selection.holding_address, product_selection.selected as u16);
+        }
+    }
+}
+
+pub fn sync_modbus_to_product_selection(
+    mut product_selection: ResMut<ProductSelection>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(holdings) = modbus_state.holding_registers.lock() {
+        if let Some(&holding_state) = holdings.get(&product_selection.holding_address) {
+            let selected = holding_state as usize;
+            if product_selection.selected != selected {
+                if selected < product_selection.catalog.len() {
+                    product_selection.selected = selected;
+                    info!("Product selected: {}", product_selection.selected_spec().name);
+                } else {
+                    // Bounce invalid selections back to the current product
+                    product_selection.set_changed();
+                }
+            }
+        }
     }
 }
 
@@ -175,10 +821,33 @@ pub struct BottlePlugin;
 
 impl Plugin for BottlePlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(BottleSpawner::default())
+        app.insert_resource(ProductSelection::default())
+        .init_resource::<BottlePool>()
+        .init_resource::<BottlePhysics>()
+        .init_resource::<SerialCounter>()
+        .register_type::<BottleSpec>()
+        .register_type::<FillLevel>()
+        .register_type::<SerialNumber>()
         .add_systems(
             Update,
-            (spawn_bottles, spawn_bottle_on_input, add_bottle_sprite),
+            (
+                spawn_bottle_on_input,
+                add_bottle_sprite,
+                update_liquid_levels,
+                handle_product_input,
+            ),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                sync_product_selection_to_modbus,
+                sync_modbus_to_product_selection,
+                spawn_bottles,
+                assign_serial_numbers,
+                apply_bottle_physics,
+                cleanup_lost_bottles,
+            )
+                .chain(),
         );
     }
 }
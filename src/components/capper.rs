@@ -0,0 +1,335 @@
+// capper.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    bottle::{Bottle, BottlePosition, BottleSpec, Capped},
+    hopper::{CAP_COLOR, CAP_HEIGHT, CAP_WIDTH, Cap},
+    modbus::ModbusState,
+    station::StationId,
+};
+
+// >>> Constants <<<
+const CHUTE_WIDTH: f32 = CAP_WIDTH + 8.0;
+const CHUTE_MOUTH: f32 = 30.0; // Open height above the stacked caps
+const CHUTE_COLOR: Color = Color::srgba(0.5, 0.5, 0.55, 0.5);
+const CAPPER_ZONE_SIZE: Vec2 = Vec2::new(20.0, 20.0);
+const CAPPER_COLOR: Color = Color::srgb(0.3, 0.35, 0.5);
+
+// >>> Components <<<
+/// Gravity chute catching loose caps and stacking them for the capper of its
+/// station, bottom cap first.
+#[derive(Component)]
+pub struct CapFeeder {
+    pub present_discrete_address: u16,
+    pub capacity: usize,
+    pub caps: Vec<Entity>,
+}
+
+impl CapFeeder {
+    pub fn is_cap_present(&self) -> bool {
+        !self.caps.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.caps.len() >= self.capacity
+    }
+}
+
+/// Marks a cap stacked in a feeder.
+#[derive(Component)]
+pub struct Fed;
+
+/// Presses the next cap from its station's feeder onto each bottle passing
+/// through its zone.
+#[derive(Component)]
+pub struct Capper {
+    pub enable_coil_address: u16,
+    pub is_enabled: bool,
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct CapFeederBundle {
+    feeder: CapFeeder,
+    collider: Collider,
+    collider_sensor: bevy_rapier2d::geometry::Sensor,
+    active_events: ActiveEvents,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+#[derive(Bundle)]
+pub struct CapperBundle {
+    capper: Capper,
+    collider: Collider,
+    collider_sensor: bevy_rapier2d::geometry::Sensor,
+    active_events: ActiveEvents,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl CapFeeder {
+    /// Creates an empty cap chute, sized to hold `capacity` caps.
+    ///
+    /// # Parameters
+    /// * `present_discrete_address` - Discrete input set while a cap waits at the outlet
+    /// * `capacity` - Caps the chute holds before further caps fall past
+    /// * `position` - Centre of the chute's outlet
+    ///
+    /// # Return
+    /// The cap feeder bundle
+    pub fn new(present_discrete_address: u16, capacity: usize, position: Vec2) -> CapFeederBundle {
+        let size = Vec2::new(CHUTE_WIDTH, capacity as f32 * CAP_HEIGHT + CHUTE_MOUTH);
+
+        CapFeederBundle {
+            feeder: CapFeeder {
+                present_discrete_address,
+                capacity,
+                caps: Vec::new(),
+            },
+            collider: Collider::cuboid(size.x / 2.0, size.y / 2.0),
+            collider_sensor: bevy_rapier2d::geometry::Sensor,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            sprite: Sprite::from_color(CHUTE_COLOR, size),
+            transform: Transform::from_translation(
+                (position + Vec2::Y * size.y / 2.0).extend(-0.1),
+            ),
+        }
+    }
+
+    /// Centre of the stacked cap in `slot`, counted up from the outlet.
+    fn slot_position(&self, transform: &Transform, slot: usize) -> Vec2 {
+        let height = self.capacity as f32 * CAP_HEIGHT + CHUTE_MOUTH;
+        let outlet = transform.translation.truncate() - Vec2::Y * height / 2.0;
+        outlet + Vec2::Y * (slot as f32 + 0.5) * CAP_HEIGHT
+    }
+}
+
+impl Capper {
+    /// Creates a disabled capper.
+    ///
+    /// # Parameters
+    /// * `enable_coil_address` - Coil energizing the capper
+    /// * `position` - Position of the capping zone
+    ///
+    /// # Return
+    /// The capper bundle
+    pub fn new(enable_coil_address: u16, position: Vec2) -> CapperBundle {
+        CapperBundle {
+            capper: Capper {
+                enable_coil_address,
+                is_enabled: false,
+            },
+            collider: Collider::cuboid(CAPPER_ZONE_SIZE.x / 2.0, CAPPER_ZONE_SIZE.y / 2.0),
+            collider_sensor: bevy_rapier2d::geometry::Sensor,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            sprite: Sprite::from_color(CAPPER_COLOR, CAPPER_ZONE_SIZE),
+            transform: Transform::from_translation(position.extend(0.0)),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_cap_feeders(
+    feeders: Query<&CapFeeder, Added<CapFeeder>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for feeder in feeders.iter() {
+        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+            discretes.insert(feeder.present_discrete_address, feeder.is_cap_present());
+        }
+
+        info!(
+            "Registered cap feeder on present discrete input: {:x?}",
+            feeder.present_discrete_address
+        );
+    }
+}
+
+pub fn register_cappers(cappers: Query<&Capper, Added<Capper>>, modbus_state: Res<ModbusState>) {
+    for capper in cappers.iter() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(capper.enable_coil_address, capper.is_enabled);
+        }
+
+        info!(
+            "Registered capper on enable coil: {:x?}",
+            capper.enable_coil_address
+        );
+    }
+}
+
+/// Takes loose caps falling into a chute out of the physics world and stacks
+/// them, until the chute is full.
+pub fn catch_caps(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut feeders: Query<&mut CapFeeder>,
+    caps: Query<(), (With<Cap>, Without<Fed>)>,
+) {
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(e1, e2, _) = collision_event else {
+            continue;
+        };
+
+        let (feeder_entity, cap) = if feeders.contains(*e1) {
+            (*e1, *e2)
+        } else if feeders.contains(*e2) {
+            (*e2, *e1)
+        } else {
+            continue;
+        };
+
+        // A cap can touch the chute twice before its collider is disabled
+        let Ok(mut feeder) = feeders.get_mut(feeder_entity) else {
+            continue;
+        };
+        if !caps.contains(cap) || feeder.is_full() || feeder.caps.contains(&cap) {
+            continue;
+        }
+
+        feeder.caps.push(cap);
+        commands.entity(cap).insert((
+            Fed,
+            RigidBody::KinematicPositionBased,
+            Velocity::zero(),
+            ColliderDisabled,
+        ));
+    }
+}
+
+/// Settles stacked caps into their slots, dropping any taken off the line.
+pub fn stack_caps(
+    mut feeders: Query<(&mut CapFeeder, &Transform)>,
+    mut caps: Query<&mut Transform, (With<Fed>, Without<CapFeeder>)>,
+) {
+    for (mut feeder, feeder_transform) in feeders.iter_mut() {
+        if feeder.caps.iter().any(|cap| !caps.contains(*cap)) {
+            feeder.caps.retain(|cap| caps.contains(*cap));
+        }
+
+        for (slot, cap) in feeder.caps.iter().enumerate() {
+            if let Ok(mut transform) = caps.get_mut(*cap) {
+                let position = feeder.slot_position(feeder_transform, slot);
+                transform.translation = position.extend(transform.translation.z);
+                transform.rotation = Quat::IDENTITY;
+            }
+        }
+    }
+}
+
+/// Draws a cap on top of a bottle and marks it capped.
+///
+/// # Parameters
+/// * `bottle` - The bottle's entity commands
+/// * `position` - The bottle's collider offset
+/// * `spec` - Variant of the bottle
+pub fn attach_cap(bottle: &mut EntityCommands, position: Vec2, spec: &BottleSpec) {
+    bottle.insert(Capped);
+
+    let offset = position + Vec2::Y * (spec.height + CAP_HEIGHT) / 2.0;
+    bottle.with_children(|parent| {
+        parent.spawn((
+            Transform::from_translation(offset.extend(0.1)),
+            GlobalTransform::default(),
+            Sprite::from_color(CAP_COLOR, Vec2::new(CAP_WIDTH.min(spec.width), CAP_HEIGHT)),
+        ));
+    });
+}
+
+pub fn apply_caps(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    cappers: Query<(&Capper, &StationId)>,
+    mut feeders: Query<(&mut CapFeeder, &StationId)>,
+    bottles: Query<(&BottlePosition, &BottleSpec), (With<Bottle>, Without<Capped>)>,
+) {
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(e1, e2, _) = collision_event else {
+            continue;
+        };
+
+        let ((capper, station), bottle_entity) = if let Ok(capper) = cappers.get(*e1) {
+            (capper, *e2)
+        } else if let Ok(capper) = cappers.get(*e2) {
+            (capper, *e1)
+        } else {
+            continue;
+        };
+
+        if !capper.is_enabled {
+            continue;
+        }
+
+        let Ok((BottlePosition(position), spec)) = bottles.get(bottle_entity) else {
+            continue;
+        };
+
+        let cap = feeders
+            .iter_mut()
+            .find(|(feeder, id)| *id == station && feeder.is_cap_present())
+            .map(|(mut feeder, _)| feeder.caps.remove(0));
+        let Some(cap) = cap else {
+            warn!("Capper {} has no cap to apply", capper.enable_coil_address);
+            continue;
+        };
+
+        commands.entity(cap).despawn();
+        attach_cap(&mut commands.entity(bottle_entity), *position, spec);
+
+        info!("Capper {} applied cap", capper.enable_coil_address);
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_modbus_to_cappers(mut cappers: Query<&mut Capper>, modbus_state: Res<ModbusState>) {
+    if let Ok(coils) = modbus_state.coils.lock() {
+        for mut capper in cappers.iter_mut() {
+            if let Some(&coil_state) = coils.get(&capper.enable_coil_address) {
+                if capper.is_enabled != coil_state {
+                    capper.is_enabled = coil_state;
+                    info!(
+                        "Capper {} set to: {}",
+                        capper.enable_coil_address, coil_state
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub fn sync_cap_feeders_to_modbus(
+    feeders: Query<&CapFeeder, Changed<CapFeeder>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+        for feeder in feeders.iter() {
+            discretes.insert(feeder.present_discrete_address, feeder.is_cap_present());
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct CapperPlugin;
+
+impl Plugin for CapperPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_cap_feeders,
+                register_cappers,
+                sync_modbus_to_cappers,
+                catch_caps,
+                stack_caps,
+                apply_caps,
+                sync_cap_feeders_to_modbus,
+            )
+                .chain(),
+        );
+    }
+}
@@ -0,0 +1,259 @@
+// controls.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    arbitration::{WriteOrigin, provenance_line},
+    conveyor::{Conveyor, ConveyorState},
+    forcing::ForcesReleased,
+    modbus::ModbusState,
+    mode::{OperatingMode, local_control_allowed},
+    overlay::{FORCED_COLOR, register_line},
+    tags::{DeviceName, RegisterMap, Tag, TagKind, TagRegistry},
+    valve::{Valve, ValveState},
+};
+
+// >>> Constants <<<
+const POPUP_COLOR: Color = Color::srgba(0.1, 0.1, 0.15, 0.9);
+const POPUP_FONT_SIZE: f32 = 12.0;
+const POPUP_OFFSET: Vec2 = Vec2::new(12.0, 12.0); // Keeps the popup clear of the pointer
+
+// >>> Components <<<
+/// Panel showing the name and live registers of the device last
+/// right-clicked.
+#[derive(Component, Default)]
+pub struct DevicePopup {
+    pub device: Option<Entity>,
+    rows_for: Option<Entity>, // Device the register rows were built for
+}
+
+#[derive(Component)]
+pub struct DevicePopupText;
+
+/// One register of the popup's device. Clicking a coil or discrete input
+/// cycles its force through on, off and released.
+#[derive(Component)]
+pub struct PopupRow {
+    pub tag: Tag,
+}
+
+// >>> Systems <<<
+pub fn setup_device_popup(mut commands: Commands) {
+    commands
+        .spawn((
+            DevicePopup::default(),
+            Node {
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(POPUP_COLOR),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                DevicePopupText,
+                Text::new(""),
+                TextFont {
+                    font_size: POPUP_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// The device a clicked entity belongs to, e.g. a curved conveyor for one of
+/// its belt segments.
+pub fn clicked_device(
+    entity: Entity,
+    devices: &Query<(), With<RegisterMap>>,
+    parents: &Query<&ChildOf>,
+) -> Option<Entity> {
+    if devices.contains(entity) {
+        return Some(entity);
+    }
+    parents
+        .get(entity)
+        .ok()
+        .map(ChildOf::parent)
+        .filter(|parent| devices.contains(*parent))
+}
+
+/// Steps a coil or discrete input from released to forced on, forced off and
+/// back to released.
+fn cycle_force(tag: &Tag, modbus_state: &ModbusState) -> bool {
+    match modbus_state.forced(tag.kind, tag.address) {
+        None => {
+            modbus_state.force(tag.kind, tag.address, true);
+            false
+        }
+        Some(true) => {
+            modbus_state.force(tag.kind, tag.address, false);
+            false
+        }
+        Some(false) => modbus_state.release(tag.kind, tag.address),
+    }
+}
+
+/// Left-clicking or tapping a conveyor starts or stops it and a valve opens
+/// or closes it, unless the line is in auto mode. Right-clicking any device,
+/// or tapping one without a toggle or in auto, opens its popup. Clicking a
+/// bit register in the popup cycles its force; clicking the rest of the popup
+/// closes it.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_device_clicks(
+    mut click_events: EventReader<Pointer<Click>>,
+    devices: Query<(), With<RegisterMap>>,
+    parents: Query<&ChildOf>,
+    mut conveyors: Query<(&Conveyor, &mut ConveyorState)>,
+    mut valves: Query<(&Valve, &mut ValveState)>,
+    mut popups: Query<(Entity, &mut DevicePopup, &mut Node, &mut Visibility)>,
+    popup_texts: Query<(), With<DevicePopupText>>,
+    rows: Query<&PopupRow>,
+    modbus_state: Res<ModbusState>,
+    mut released_events: EventWriter<ForcesReleased>,
+    mode: Option<Res<OperatingMode>>,
+) {
+    for click in click_events.read() {
+        let Ok((popup_entity, mut popup, mut node, mut visibility)) = popups.single_mut() else {
+            return;
+        };
+
+        if let Ok(row) = rows.get(click.target) {
+            if row.tag.kind.is_bit() && cycle_force(&row.tag, &modbus_state) {
+                released_events.write(ForcesReleased);
+            }
+            continue;
+        }
+
+        if click.target == popup_entity || popup_texts.contains(click.target) {
+            popup.device = None;
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+
+        let Some(device) = clicked_device(click.target, &devices, &parents) else {
+            continue;
+        };
+
+        // In auto a toggle is rejected and the popup opens instead, to force
+        // the I/O deliberately
+        let is_toggle = conveyors.contains(device) || valves.contains(device);
+        let is_rejected = click.button == PointerButton::Primary
+            && is_toggle
+            && !local_control_allowed(mode.as_deref());
+        if click.button == PointerButton::Primary && !is_rejected {
+            let origin = WriteOrigin::hmi("mouse");
+            if let Ok((conveyor, mut conveyor_state)) = conveyors.get_mut(device) {
+                conveyor_state.is_running = !conveyor_state.is_running;
+                info!("Conveyor clicked, running: {}", conveyor_state.is_running);
+                let is_running = conveyor_state.is_running as u16;
+                modbus_state.write(TagKind::Coil, conveyor.coil_address, is_running, &origin);
+                continue;
+            }
+            if let Ok((valve, mut valve_state)) = valves.get_mut(device) {
+                valve_state.is_open = !valve_state.is_open;
+                info!("Valve clicked, open: {}", valve_state.is_open);
+                let is_open = valve_state.is_open as u16;
+                modbus_state.write(TagKind::Coil, valve.coil_address, is_open, &origin);
+                continue;
+            }
+        } else if click.button != PointerButton::Secondary && !is_rejected {
+            continue;
+        }
+
+        let position = click.pointer_location.position + POPUP_OFFSET;
+        node.left = Val::Px(position.x);
+        node.top = Val::Px(position.y);
+        popup.device = Some(device);
+        *visibility = Visibility::Visible;
+    }
+}
+
+/// Keeps the popup's register values live, hiding it if its device is
+/// despawned, e.g. by a scenario reload.
+pub fn update_device_popup(
+    mut commands: Commands,
+    mut popups: Query<(Entity, &mut DevicePopup, &mut Visibility)>,
+    mut texts: Query<&mut Text, (With<DevicePopupText>, Without<PopupRow>)>,
+    mut rows: Query<(Entity, &PopupRow, &mut Text, &mut TextColor), Without<DevicePopupText>>,
+    devices: Query<(&RegisterMap, Option<&DeviceName>)>,
+    modbus_state: Res<ModbusState>,
+    tags: Res<TagRegistry>,
+) {
+    for (popup_entity, mut popup, mut visibility) in popups.iter_mut() {
+        let Some(device) = popup.device else {
+            continue;
+        };
+        let Ok((register_map, name)) = devices.get(device) else {
+            popup.device = None;
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        for mut text in texts.iter_mut() {
+            text.0 = name.map_or("Device".to_string(), |name| name.0.clone());
+        }
+
+        // Rebuild the rows when another device is shown
+        if popup.rows_for != Some(device) {
+            popup.rows_for = Some(device);
+            for (row, ..) in rows.iter() {
+                commands.entity(row).despawn();
+            }
+            commands.entity(popup_entity).with_children(|parent| {
+                for tag in &register_map.0 {
+                    parent.spawn((
+                        PopupRow { tag: tag.clone() },
+                        Text::new(""),
+                        TextFont {
+                            font_size: POPUP_FONT_SIZE,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                }
+            });
+            continue;
+        }
+
+        for (_, row, mut text, mut color) in rows.iter_mut() {
+            text.0 = format!(
+                "{}  {}",
+                row.tag.name,
+                register_line(&row.tag, &modbus_state)
+            );
+            if let Some(scaled) = tags.scaled_line(&row.tag, &modbus_state) {
+                text.0 += &format!(" ({scaled})");
+            }
+            if let Some(provenance) = provenance_line(&row.tag, &modbus_state) {
+                text.0 += &format!("  {provenance}");
+            }
+            let is_forced = modbus_state.forced(row.tag.kind, row.tag.address).is_some();
+            color.set_if_neq(TextColor(if is_forced {
+                FORCED_COLOR
+            } else {
+                Color::WHITE
+            }));
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Mouse and touch controls for kiosks without a keyboard: click a conveyor
+/// or valve to toggle it and right-click a device for its live registers.
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        // Registered here too so the plugin runs headless, without picking
+        app.add_event::<Pointer<Click>>()
+            .add_event::<ForcesReleased>()
+            .add_systems(Startup, setup_device_popup)
+            .add_systems(Update, (handle_device_clicks, update_device_popup).chain());
+    }
+}
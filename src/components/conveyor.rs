@@ -2,27 +2,85 @@
 // Copyright (C) 2025 deciphr
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use super::modbus::ModbusState;
+use super::{
+    alarm::{Alarm, AlarmEvent},
+    arbitration::WriteOrigin,
+    bottle::{Bottle, BottlePosition, BottleSpawner, InfeedProfile},
+    gamepad,
+    modbus::{ModbusState, RegisterBanks},
+    mode::{OperatingMode, local_control_allowed},
+    physics::SurfaceMotion,
+    tag_sync::sync_tags,
+    tags::{TagKind, TagRegistry},
+};
 
-use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 // >>> Constants <<<
 const CONVEYOR_SPEED: f32 = 100.0;
+const BELT_STRIPE_SPACING: f32 = 40.0;
+const BELT_STRIPE_WIDTH: f32 = 6.0;
+const BELT_STRIPE_SLANT: f32 = 0.35; // Radians the stripes lean into the direction of travel
+const BELT_STRIPE_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+const CURVE_SEGMENT_LENGTH: f32 = 20.0; // Approximate length of each straight piece of a curved belt
+const CURVE_SEGMENT_OVERLAP: f32 = 2.0; // Closes the gaps between pieces on the outside of the bend
+const TRANSFER_RANGE: f32 = 30.0; // How close to a belt's end a bottle waits for the transfer
+const BELT_CONTACT: f32 = 5.0; // How far a bottle's base may sit from the belt surface
 
 // >>> Components <<<
-#[derive(Component)]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct Conveyor {
     pub coil_address: u16,
     pub holding_address: u16,
 }
 
-// >>> Resources <<<
-#[derive(Resource)]
+/// Tracks how far the belt surface has travelled, for drawing it.
+#[derive(Component, Default)]
+pub struct ConveyorBelt {
+    pub offset: f32,
+    pub length: f32,
+}
+
+/// A stripe painted on the belt surface.
+#[derive(Component)]
+pub struct BeltStripe {
+    pub index: usize,
+}
+
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
 pub struct ConveyorState {
     pub is_running: bool,
     pub speed: f32,
+    pub jog: f32, // Signed speed of a jog of the stopped belt, see `JogControl`
+}
+
+impl ConveyorState {
+    /// Whether the belt surface is actually moving items along, either way.
+    pub fn is_moving(&self) -> bool {
+        self.belt_speed() != 0.0
+    }
+
+    /// Whether the motor drives the belt, running or jogging.
+    pub fn is_driven(&self) -> bool {
+        self.is_running || self.jog != 0.0
+    }
+
+    /// Speed the belt is driven at, before any ripple.
+    pub fn belt_speed(&self) -> f32 {
+        if self.is_running {
+            self.speed
+        } else {
+            self.jog
+        }
+    }
+
+    /// Speed the belt surface is driven at, including any ripple.
+    pub fn surface_speed(&self, ripple: &SpeedRipple) -> f32 {
+        self.belt_speed() * ripple.0
+    }
 }
 
 impl Default for ConveyorState {
@@ -30,15 +88,135 @@ impl Default for ConveyorState {
         ConveyorState {
             is_running: true,
             speed: CONVEYOR_SPEED,
+            jog: 0.0,
         }
     }
 }
 
+/// Path of the belt surface.
+///
+/// A straight belt runs along its transform's x axis, so rotating the
+/// transform inclines it. A curved belt's transform sits at the centre of the
+/// bend: its centreline runs at `radius` from `start` through `sweep` radians,
+/// counter-clockwise when `sweep` is positive, which is also the direction of
+/// travel at positive speed.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConveyorShape {
+    #[default]
+    Straight,
+    Curved {
+        radius: f32,
+        start: f32,
+        sweep: f32,
+        thickness: f32,
+    },
+}
+
+impl ConveyorShape {
+    /// Motion of the belt surface at `speed`. Inclined and curved belts move
+    /// along their surface rather than along x.
+    pub fn surface_motion(&self, transform: &GlobalTransform, speed: f32) -> SurfaceMotion {
+        match *self {
+            ConveyorShape::Straight => SurfaceMotion::Linear(transform.right().xy() * speed),
+            ConveyorShape::Curved { sweep, .. } => SurfaceMotion::Around {
+                center: transform.translation().xy(),
+                speed: speed * sweep.signum(),
+            },
+        }
+    }
+
+    /// Centre, rotation and size of each straight piece approximating a curved
+    /// belt, relative to the centre of the bend. Straight belts have none.
+    pub fn segments(&self) -> Vec<(Vec2, f32, Vec2)> {
+        let ConveyorShape::Curved {
+            radius,
+            start,
+            sweep,
+            thickness,
+        } = *self
+        else {
+            return Vec::new();
+        };
+
+        let count = curve_segment_count(radius, sweep);
+        let step = sweep / count as f32;
+        let length = 2.0 * radius * (step.abs() / 2.0).sin() + CURVE_SEGMENT_OVERLAP;
+
+        (0..count)
+            .map(|index| {
+                let angle = start + step * (index as f32 + 0.5);
+                (
+                    Vec2::from_angle(angle) * radius,
+                    angle + std::f32::consts::FRAC_PI_2,
+                    Vec2::new(length, thickness),
+                )
+            })
+            .collect()
+    }
+
+    /// Points along the belt's top surface, relative to the conveyor's transform.
+    fn surface_points(&self, sprite: &Sprite) -> Vec<Vec2> {
+        match *self {
+            ConveyorShape::Straight => {
+                let size = sprite.custom_size.unwrap_or_default();
+                vec![
+                    Vec2::new(-size.x / 2.0, size.y / 2.0),
+                    Vec2::new(size.x / 2.0, size.y / 2.0),
+                ]
+            }
+            ConveyorShape::Curved {
+                radius,
+                start,
+                sweep,
+                thickness,
+            } => {
+                let outer = radius + thickness / 2.0;
+                let count = curve_segment_count(radius, sweep);
+                (0..=count)
+                    .map(|index| {
+                        Vec2::from_angle(start + sweep * index as f32 / count as f32) * outer
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn curve_segment_count(radius: f32, sweep: f32) -> usize {
+    (sweep.abs() * radius / CURVE_SEGMENT_LENGTH)
+        .ceil()
+        .max(1.0) as usize
+}
+
+/// Hands items off the downstream end of this conveyor onto the next one.
+///
+/// The downstream conveyor must be running before this one delivers onto it;
+/// a bottle waiting at the transfer while it is stopped raises an alarm.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ConveyorTransfer {
+    pub downstream: Entity,
+}
+
+/// Factor on the belt's surface speed, varied by process noise.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SpeedRipple(pub f32);
+
+impl Default for SpeedRipple {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
 // >>> Bundles <<<
 #[derive(Bundle)]
 pub struct ConveyorBundle {
     conveyor: Conveyor,
+    state: ConveyorState,
+    ripple: SpeedRipple,
+    shape: ConveyorShape,
+    spawner: BottleSpawner,
     collider: Collider,
+    surface: SurfaceMotion,
     active_hooks: ActiveHooks,
     sprite: Sprite,
     transform: Transform,
@@ -57,7 +235,12 @@ impl Conveyor {
                 coil_address,
                 holding_address,
             },
+            state: ConveyorState::default(),
+            ripple: SpeedRipple::default(),
+            shape: ConveyorShape::Straight,
+            spawner: BottleSpawner::default(),
             collider: Collider::cuboid(width / 2.0, height / 2.0),
+            surface: SurfaceMotion::default(),
             active_hooks: ActiveHooks::MODIFY_SOLVER_CONTACTS,
             sprite: Sprite::from_color(Color::BLACK, Vec2::new(width, height)),
             transform: Transform::from_translation(position.extend(0.0)),
@@ -65,100 +248,362 @@ impl Conveyor {
     }
 }
 
-// >>> Systems <<<
-#[derive(SystemParam)]
-pub struct ConveyorPhysicsHook<'w> {
-    conveyor_state: Res<'w, ConveyorState>,
+impl ConveyorBundle {
+    /// Tilts the belt, rising towards its downstream end for positive angles.
+    pub fn with_incline(mut self, angle: f32) -> Self {
+        self.transform.rotation = Quat::from_rotation_z(angle);
+        self
+    }
+
+    /// Bends the belt into an arc around the conveyor's position, keeping its
+    /// height as the belt thickness. See [`ConveyorShape::Curved`].
+    pub fn with_curve(mut self, radius: f32, start: f32, sweep: f32) -> Self {
+        let thickness = self.sprite.custom_size.map_or(0.0, |size| size.y);
+        self.shape = ConveyorShape::Curved {
+            radius,
+            start,
+            sweep,
+            thickness,
+        };
+        self.collider = Collider::compound(
+            self.shape
+                .segments()
+                .into_iter()
+                .map(|(center, rotation, size)| {
+                    (
+                        center,
+                        rotation,
+                        Collider::cuboid(size.x / 2.0, size.y / 2.0),
+                    )
+                })
+                .collect(),
+        );
+        // The belt is drawn piece by piece instead
+        self.sprite = Sprite::from_color(Color::NONE, Vec2::ZERO);
+        self
+    }
+
+    /// Paces new bottles onto the belt by `profile` instead of steadily.
+    pub fn with_infeed_profile(mut self, profile: InfeedProfile) -> Self {
+        self.spawner.set_profile(profile);
+        self
+    }
+
+    /// Moves the belt so the upstream end of its surface lands on `point`,
+    /// whatever its incline or bend, e.g. to take items off the belt before it.
+    pub fn with_infeed_at(mut self, point: Vec2) -> Self {
+        let infeed = belt_infeed(&self.transform, &self.sprite, &self.shape);
+        self.transform.translation += (point - infeed).extend(0.0);
+        self
+    }
+
+    /// Downstream end of the belt surface, where items are handed off.
+    pub fn outfeed(&self) -> Vec2 {
+        belt_outfeed(&self.transform, &self.sprite, &self.shape)
+    }
 }
 
-impl BevyPhysicsHooks for ConveyorPhysicsHook<'_> {
-    fn modify_solver_contacts(&self, context: ContactModificationContextView) {
-        if self.conveyor_state.is_running {
-            for solver_contact in &mut *context.raw.solver_contacts {
-                solver_contact.tangent_velocity.x = self.conveyor_state.speed;
-            }
+/// Upstream end of the belt surface, where new bottles are placed.
+pub fn belt_infeed(transform: &Transform, sprite: &Sprite, shape: &ConveyorShape) -> Vec2 {
+    let points = shape.surface_points(sprite);
+    let start = points.first().copied().unwrap_or_default();
+    transform.transform_point(start.extend(0.0)).truncate()
+}
+
+/// Downstream end of the belt surface, where items are handed off.
+pub fn belt_outfeed(transform: &Transform, sprite: &Sprite, shape: &ConveyorShape) -> Vec2 {
+    let points = shape.surface_points(sprite);
+    let end = points.last().copied().unwrap_or_default();
+    transform.transform_point(end.extend(0.0)).truncate()
+}
+
+/// Lowest point of the belt's top surface in world space.
+pub fn belt_low_point(transform: &Transform, sprite: &Sprite, shape: &ConveyorShape) -> f32 {
+    shape
+        .surface_points(sprite)
+        .into_iter()
+        .map(|point| transform.transform_point(point.extend(0.0)).y)
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// World-space height of the belt's top surface at `x`, if the belt spans it.
+pub fn belt_surface_height(
+    transform: &Transform,
+    sprite: &Sprite,
+    shape: &ConveyorShape,
+    x: f32,
+) -> Option<f32> {
+    let points: Vec<Vec2> = shape
+        .surface_points(sprite)
+        .into_iter()
+        .map(|point| transform.transform_point(point.extend(0.0)).truncate())
+        .collect();
+
+    points.windows(2).find_map(|segment| {
+        let (a, b) = (segment[0], segment[1]);
+        if x < a.x.min(b.x) || x > a.x.max(b.x) {
+            return None;
+        }
+        let t = if a.x == b.x {
+            0.0
         } else {
-            for solver_contact in &mut *context.raw.solver_contacts {
-                solver_contact.tangent_velocity.x = 0.0;
-            }
+            (x - a.x) / (b.x - a.x)
+        };
+        Some(a.y.lerp(b.y, t))
+    })
+}
+
+/// Checks whether a bottle with its centre at `center` stands on the belt.
+pub fn stands_on_belt(
+    transform: &Transform,
+    sprite: &Sprite,
+    shape: &ConveyorShape,
+    center: Vec2,
+    height: f32,
+) -> bool {
+    belt_surface_height(transform, sprite, shape, center.x)
+        .is_some_and(|surface| (center.y - height / 2.0 - surface).abs() < BELT_CONTACT)
+}
+
+// >>> Systems <<<
+pub fn add_conveyor_belt(
+    mut commands: Commands,
+    query: Query<(Entity, &Sprite, &ConveyorShape), Added<Conveyor>>,
+) {
+    for (entity, sprite, shape) in query.iter() {
+        if let ConveyorShape::Curved { .. } = shape {
+            commands.entity(entity).with_children(|parent| {
+                for (center, rotation, size) in shape.segments() {
+                    parent.spawn((
+                        Sprite::from_color(Color::BLACK, size),
+                        Transform::from_translation(center.extend(0.0))
+                            .with_rotation(Quat::from_rotation_z(rotation)),
+                    ));
+                }
+            });
+            continue;
         }
+
+        let Some(size) = sprite.custom_size else {
+            continue;
+        };
+
+        let stripe_count = (size.x / BELT_STRIPE_SPACING).floor() as usize;
+        commands
+            .entity(entity)
+            .insert(ConveyorBelt {
+                offset: 0.0,
+                length: stripe_count as f32 * BELT_STRIPE_SPACING,
+            })
+            .with_children(|parent| {
+                for index in 0..stripe_count {
+                    parent.spawn((
+                        BeltStripe { index },
+                        Sprite::from_color(
+                            BELT_STRIPE_COLOR,
+                            Vec2::new(BELT_STRIPE_WIDTH, size.y * 0.8),
+                        ),
+                        Transform::from_xyz(0.0, 0.0, 0.1),
+                    ));
+                }
+            });
     }
 }
 
-pub fn handle_conveyor_input(
-    mut conveyor_state: ResMut<ConveyorState>,
-    keyboard: Res<ButtonInput<KeyCode>>,
+/// Scrolls the belt stripes at the conveyor's speed so a moving belt is visible even when empty.
+/// Belts out of view keep their stripes where they are, as in large scenes most are.
+pub fn animate_conveyor_belts(
+    time: Res<Time>,
+    mut belts: Query<(
+        &mut ConveyorBelt,
+        &ConveyorState,
+        &Children,
+        &ViewVisibility,
+    )>,
+    mut stripes: Query<(&BeltStripe, &mut Transform)>,
 ) {
-    if keyboard.just_pressed(KeyCode::Space) {
-        conveyor_state.is_running = !conveyor_state.is_running;
-        info!(
-            "Conveyor: {}",
-            if conveyor_state.is_running {
-                "Running"
-            } else {
-                "Stopped"
-            }
-        );
+    for (mut belt, conveyor_state, children, view_visibility) in belts.iter_mut() {
+        let speed = conveyor_state.belt_speed();
+
+        if belt.length <= 0.0 {
+            continue;
+        }
+        belt.offset = (belt.offset + speed * time.delta_secs()).rem_euclid(belt.length);
+        if !view_visibility.get() {
+            continue;
+        }
+
+        for &child in children {
+            let Ok((stripe, mut transform)) = stripes.get_mut(child) else {
+                continue;
+            };
+            let x = (stripe.index as f32 * BELT_STRIPE_SPACING + belt.offset).rem_euclid(belt.length);
+            transform.translation.x = x - belt.length / 2.0 + BELT_STRIPE_SPACING / 2.0;
+            transform.rotation = Quat::from_rotation_z(-BELT_STRIPE_SLANT * speed.signum());
+        }
     }
+}
 
-    if keyboard.just_pressed(KeyCode::ArrowUp) {
-        conveyor_state.speed += 10.0;
-        info!("Conveyor speed: {}", conveyor_state.speed);
+/// Moves each belt's surface at its own conveyor's speed, for the physics
+/// step to carry the items on it along.
+pub fn update_belt_surfaces(
+    mut conveyors: Query<(
+        &ConveyorState,
+        &SpeedRipple,
+        &ConveyorShape,
+        &GlobalTransform,
+        &mut SurfaceMotion,
+    )>,
+) {
+    for (conveyor_state, ripple, shape, transform, mut surface) in conveyors.iter_mut() {
+        let speed = if conveyor_state.is_driven() {
+            conveyor_state.surface_speed(ripple)
+        } else {
+            0.0
+        };
+        surface.set_if_neq(shape.surface_motion(transform, speed));
     }
+}
 
-    if keyboard.just_pressed(KeyCode::ArrowDown) {
-        conveyor_state.speed = conveyor_state.speed - 10.0;
-        info!("Conveyor speed: {}", conveyor_state.speed);
+/// Raises an alarm while a running conveyor delivers a bottle to a stopped one.
+pub fn monitor_conveyor_transfers(
+    transfers: Query<(
+        &ConveyorTransfer,
+        &ConveyorState,
+        &Transform,
+        &Sprite,
+        &ConveyorShape,
+    )>,
+    conveyors: Query<&ConveyorState>,
+    bottles: Query<(&Transform, &BottlePosition), With<Bottle>>,
+    mut alarm_events: EventWriter<AlarmEvent>,
+    mut was_blocked: Local<bool>,
+) {
+    let is_blocked = transfers
+        .iter()
+        .filter(|(transfer, conveyor_state, ..)| {
+            conveyor_state.is_moving()
+                && conveyors
+                    .get(transfer.downstream)
+                    .is_ok_and(|downstream| !downstream.is_moving())
+        })
+        .any(|(_, _, transform, sprite, shape)| {
+            let outfeed = belt_outfeed(transform, sprite, shape);
+            bottles.iter().any(|(transform, position)| {
+                position.world_center(transform).distance(outfeed) < TRANSFER_RANGE
+            })
+        });
+
+    if *was_blocked != is_blocked {
+        *was_blocked = is_blocked;
+        alarm_events.write(if is_blocked {
+            AlarmEvent::Raise(Alarm::TransferBlocked)
+        } else {
+            AlarmEvent::Clear(Alarm::TransferBlocked)
+        });
     }
 }
 
-// >>> Modbus Synchronization <<<
-pub fn sync_conveyor_to_modbus(
-    conveyors: Query<&Conveyor>,
-    conveyor_state: Res<ConveyorState>,
+/// Keyboard and gamepad controls drive every station's conveyors, writing
+/// their registers as the HMI.
+pub fn handle_conveyor_input(
+    mut conveyors: Query<(&Conveyor, &mut ConveyorState)>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mode: Option<Res<OperatingMode>>,
     modbus_state: Res<ModbusState>,
+    tags: Res<TagRegistry>,
 ) {
-    if conveyor_state.is_changed() {
-        if let Ok(mut coils) = modbus_state.coils.lock() {
-            for conveyor in conveyors.iter() {
-                coils.insert(conveyor.coil_address, conveyor_state.is_running);
-            }
+    let toggle = keyboard.just_pressed(KeyCode::Space)
+        || gamepad::just_pressed(&gamepads, gamepad::CONVEYOR_TOGGLE);
+    let speed_up = keyboard.just_pressed(KeyCode::ArrowUp)
+        || gamepad::just_pressed(&gamepads, gamepad::SPEED_UP);
+    let speed_down = keyboard.just_pressed(KeyCode::ArrowDown)
+        || gamepad::just_pressed(&gamepads, gamepad::SPEED_DOWN);
+    if !(toggle || speed_up || speed_down) || !local_control_allowed(mode.as_deref()) {
+        return;
+    }
+
+    let origin = WriteOrigin::hmi("local controls");
+    for (conveyor, mut conveyor_state) in conveyors.iter_mut() {
+        if toggle {
+            conveyor_state.is_running = !conveyor_state.is_running;
+            info!(
+                "Conveyor: {}",
+                if conveyor_state.is_running {
+                    "Running"
+                } else {
+                    "Stopped"
+                }
+            );
         }
-        if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
-            for conveyor in conveyors.iter() {
-                holdings.insert(conveyor.holding_address, conveyor_state.speed as u16);
-                info!("Conveyor {}'s speed set to: {}", conveyor.holding_address, conveyor_state.speed);
-            }
+
+        if speed_up {
+            conveyor_state.speed += 10.0;
+            info!("Conveyor speed: {}", conveyor_state.speed);
+        }
+
+        if speed_down {
+            conveyor_state.speed = conveyor_state.speed - 10.0;
+            info!("Conveyor speed: {}", conveyor_state.speed);
         }
+
+        let is_running = conveyor_state.is_running as u16;
+        modbus_state.write(TagKind::Coil, conveyor.coil_address, is_running, &origin);
+        tags.write_value(
+            &modbus_state,
+            TagKind::HoldingRegister,
+            conveyor.holding_address,
+            conveyor_state.speed,
+            &origin,
+        );
     }
 }
 
-pub fn sync_modbus_to_conveyor(
-    conveyors: Query<&Conveyor>,
-    mut conveyor_state: ResMut<ConveyorState>,
-    modbus_state: Res<ModbusState>,
+// >>> Modbus Synchronization <<<
+/// Publishes a conveyor's run state and speed, as part of the tag-sync stage.
+pub fn write_conveyor_registers(
+    banks: &mut RegisterBanks,
+    tags: &TagRegistry,
+    conveyor: &Conveyor,
+    conveyor_state: &ConveyorState,
 ) {
-    if let Ok(coils) = modbus_state.coils.lock() {
-        for conveyor in conveyors.iter() {
-            if let Some(&coil_state) = coils.get(&conveyor.coil_address) {
-                if conveyor_state.is_running != coil_state {
-                    conveyor_state.is_running = coil_state;
-                    println!("Conveyor {} set to: {}", conveyor.coil_address, coil_state);
-                    break;
-                }
-            }
+    banks
+        .coils
+        .insert(conveyor.coil_address, conveyor_state.is_running);
+    tags.insert_value(
+        &mut banks.holding_registers,
+        TagKind::HoldingRegister,
+        conveyor.holding_address,
+        conveyor_state.speed,
+    );
+    info!("Conveyor {}'s speed set to: {}", conveyor.holding_address, conveyor_state.speed);
+}
+
+/// Follows a conveyor's run coil and speed register, as part of the tag-sync
+/// stage.
+pub fn read_conveyor_registers(
+    banks: &RegisterBanks,
+    tags: &TagRegistry,
+    conveyor: &Conveyor,
+    conveyor_state: &mut Mut<ConveyorState>,
+) {
+    if let Some(&coil_state) = banks.coils.get(&conveyor.coil_address) {
+        if conveyor_state.is_running != coil_state {
+            conveyor_state.is_running = coil_state;
+            println!("Conveyor {} set to: {}", conveyor.coil_address, coil_state);
         }
     }
 
-     if let Ok(holdings) = modbus_state.holding_registers.lock() {
-        for conveyor in conveyors.iter() {
-            if let Some(&holding_state) = holdings.get(&conveyor.holding_address) {
-                if conveyor_state.speed != holding_state as f32 {
-                    conveyor_state.speed = holding_state as f32;
-                    println!("Conveyor speed {} set to: {}", conveyor.holding_address, holding_state);
-                    break;
-                }
-            }
+    let speed = tags.read_value(
+        &banks.holding_registers,
+        TagKind::HoldingRegister,
+        conveyor.holding_address,
+    );
+    if let Some(speed) = speed {
+        if conveyor_state.speed != speed {
+            conveyor_state.speed = speed;
+            println!("Conveyor speed {} set to: {}", conveyor.holding_address, speed);
         }
     }
 }
@@ -168,16 +613,16 @@ pub struct ConveyorPlugin;
 
 impl Plugin for ConveyorPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(ConveyorState::default())
-            .add_plugins(RapierPhysicsPlugin::<ConveyorPhysicsHook>::pixels_per_meter(100.0))
+        app.register_type::<Conveyor>()
+            .register_type::<ConveyorState>()
             .add_systems(
                 Update,
-                (
-                    sync_conveyor_to_modbus,
-                    sync_modbus_to_conveyor,
-                    handle_conveyor_input,
-                )
-                    .chain(),
+                (handle_conveyor_input, add_conveyor_belt, animate_conveyor_belts),
+            )
+            .add_systems(FixedUpdate, monitor_conveyor_transfers.after(sync_tags))
+            .add_systems(
+                FixedPostUpdate,
+                update_belt_surfaces.before(PhysicsSet::SyncBackend),
             );
     }
 }
@@ -0,0 +1,141 @@
+// counter.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    modbus::ModbusState,
+    sensor::{GlobalSensorState, SensorFaults},
+    tag_sync::sync_tags,
+};
+
+// >>> Components <<<
+/// Totalizer counting each time a sensor trips.
+///
+/// The 32-bit count spans two input registers, high word first.
+#[derive(Component, Debug, Clone)]
+pub struct Counter {
+    pub sensor_tag: String,
+    pub count_input_address: u16, // High word; the low word follows it
+    pub reset_coil_address: u16,
+    pub count: u32,
+    last_triggered: bool,
+}
+
+impl Counter {
+    /// Creates a counter for a sensor.
+    ///
+    /// # Parameters
+    /// * `sensor_tag` - Tag of the sensor whose trips are counted
+    /// * `count_input_address` - First of the two input registers holding the count
+    /// * `reset_coil_address` - Coil that clears the count
+    ///
+    /// # Return
+    /// The counter component
+    pub fn new(sensor_tag: &str, count_input_address: u16, reset_coil_address: u16) -> Self {
+        Self {
+            sensor_tag: sensor_tag.to_string(),
+            count_input_address,
+            reset_coil_address,
+            count: 0,
+            last_triggered: false,
+        }
+    }
+
+    /// The count split into its high and low register words.
+    pub fn registers(&self) -> [u16; 2] {
+        [(self.count >> 16) as u16, self.count as u16]
+    }
+}
+
+// >>> Systems <<<
+/// Counts rising edges of each counter's sensor output, as reported over
+/// Modbus before the sensor's polarity is applied.
+pub fn count_sensor_trips(
+    mut counters: Query<&mut Counter>,
+    global_state: Res<GlobalSensorState>,
+    faults: Res<SensorFaults>,
+) {
+    for mut counter in counters.iter_mut() {
+        let polarity = global_state.polarity(&counter.sensor_tag);
+        let is_triggered = faults.apply(
+            &counter.sensor_tag,
+            polarity,
+            global_state.is_triggered(&counter.sensor_tag),
+        );
+
+        if is_triggered != counter.last_triggered {
+            counter.last_triggered = is_triggered;
+            if is_triggered {
+                counter.count = counter.count.wrapping_add(1);
+            }
+        }
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn register_counters(
+    counters: Query<&Counter, Added<Counter>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for counter in counters.iter() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(counter.reset_coil_address, false);
+        }
+
+        info!(
+            "Registered counter for {} at input registers 0x{:04X}-0x{:04X}",
+            counter.sensor_tag,
+            counter.count_input_address,
+            counter.count_input_address.wrapping_add(1)
+        );
+    }
+}
+
+/// Clears counters whose reset coil was written; the coil resets itself.
+pub fn sync_modbus_to_counters(mut counters: Query<&mut Counter>, modbus_state: Res<ModbusState>) {
+    let Ok(mut coils) = modbus_state.coils.lock() else {
+        return;
+    };
+
+    for mut counter in counters.iter_mut() {
+        if coils.get(&counter.reset_coil_address) == Some(&true) {
+            coils.insert(counter.reset_coil_address, false);
+            counter.count = 0;
+            info!("Counter for {} reset", counter.sensor_tag);
+        }
+    }
+}
+
+pub fn sync_counters_to_modbus(
+    counters: Query<&Counter, Changed<Counter>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for counter in counters.iter() {
+            let [high, low] = counter.registers();
+            inputs.insert(counter.count_input_address, high);
+            inputs.insert(counter.count_input_address.wrapping_add(1), low);
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct CounterPlugin;
+
+impl Plugin for CounterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_counters,
+                sync_modbus_to_counters,
+                count_sensor_trips,
+                sync_counters_to_modbus,
+            )
+                .chain()
+                .after(sync_tags),
+        );
+    }
+}
@@ -0,0 +1,309 @@
+// diverter.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    arbitration::WriteOrigin,
+    bottle::Bottle,
+    modbus::ModbusState,
+    mode::{OperatingMode, local_control_allowed, remote_control_allowed},
+    pneumatics::Pneumatics,
+    station::StationId,
+    tags::TagKind,
+};
+
+// >>> Constants <<<
+const DIVERTER_ZONE_SIZE: Vec2 = Vec2::new(20.0, 40.0);
+const DIVERTER_COLOR: Color = Color::srgb(0.9, 0.5, 0.1);
+const KICK_UP_SPEED: f32 = 300.0;
+const DEFAULT_GRAVITY: f32 = 981.0; // Rapier's 9.81 m/s^2 at 100 pixels per meter
+const LANE_WALL_THICKNESS: f32 = 5.0;
+const LANE_WALL_HEIGHT: f32 = 60.0;
+const LANE_COLOR: Color = Color::srgb(0.35, 0.35, 0.35);
+
+// >>> Components <<<
+#[derive(Component)]
+pub struct Diverter {
+    pub lane_holding_address: u16,
+    pub target_lane: u16, // 1..N, anything else lets bottles pass
+}
+
+#[derive(Component)]
+pub struct DiverterLane {
+    pub lane: u16,
+    pub counter_input_address: u16,
+    pub count: u16,
+}
+
+/// Marks a bottle the diverter has kicked towards a lane.
+#[derive(Component)]
+pub struct Diverted;
+
+/// Marks a bottle already counted by a lane.
+#[derive(Component)]
+pub struct Sorted;
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct DiverterBundle {
+    diverter: Diverter,
+    collider: Collider,
+    collider_sensor: bevy_rapier2d::geometry::Sensor,
+    active_events: ActiveEvents,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl Diverter {
+    /// Creates a diverter that kicks arriving bottles into the selected lane.
+    ///
+    /// # Parameters
+    /// * `lane_holding_address` - Holding register selecting the target lane
+    /// * `position` - Position of the diverter
+    ///
+    /// # Return
+    /// The diverter bundle
+    pub fn new(lane_holding_address: u16, position: Vec2) -> DiverterBundle {
+        DiverterBundle {
+            diverter: Diverter {
+                lane_holding_address,
+                target_lane: 0,
+            },
+            collider: Collider::cuboid(DIVERTER_ZONE_SIZE.x / 2.0, DIVERTER_ZONE_SIZE.y / 2.0),
+            collider_sensor: bevy_rapier2d::geometry::Sensor,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            sprite: Sprite::from_color(DIVERTER_COLOR, DIVERTER_ZONE_SIZE),
+            transform: Transform::from_translation(position.extend(0.0)),
+        }
+    }
+}
+
+/// Spawns a lane bin with its counting zone.
+///
+/// # Parameters
+/// * `lane` - Lane number, as written to the diverter's holding register
+/// * `counter_input_address` - Input register counting bottles sorted into the lane
+/// * `position` - Center of the lane's floor
+/// * `width` - Inner width of the lane
+///
+/// # Return
+/// The bin and counting zone entities
+pub fn spawn_diverter_lane(
+    commands: &mut Commands,
+    lane: u16,
+    counter_input_address: u16,
+    position: Vec2,
+    width: f32,
+) -> [Entity; 2] {
+    // Bin walls and floor
+    let bin = commands.spawn((
+        Collider::compound(vec![
+            (
+                Vec2::new(-width / 2.0, LANE_WALL_HEIGHT / 2.0),
+                0.0,
+                Collider::cuboid(LANE_WALL_THICKNESS / 2.0, LANE_WALL_HEIGHT / 2.0),
+            ),
+            (
+                Vec2::ZERO,
+                0.0,
+                Collider::cuboid(width / 2.0, LANE_WALL_THICKNESS / 2.0),
+            ),
+            (
+                Vec2::new(width / 2.0, LANE_WALL_HEIGHT / 2.0),
+                0.0,
+                Collider::cuboid(LANE_WALL_THICKNESS / 2.0, LANE_WALL_HEIGHT / 2.0),
+            ),
+        ]),
+        Sprite::from_color(LANE_COLOR, Vec2::new(width, LANE_WALL_THICKNESS)),
+        Transform::from_translation(position.extend(0.0)),
+    )).id();
+
+    // Counting zone
+    let counter = commands.spawn((
+        DiverterLane {
+            lane,
+            counter_input_address,
+            count: 0,
+        },
+        Collider::cuboid(width / 2.0, LANE_WALL_HEIGHT / 2.0),
+        bevy_rapier2d::geometry::Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        Transform::from_translation((position + Vec2::new(0.0, LANE_WALL_HEIGHT / 2.0)).extend(0.0)),
+    )).id();
+
+    [bin, counter]
+}
+
+// >>> Systems <<<
+pub fn handle_diverter_input(
+    mut diverters: Query<(&mut Diverter, &StationId)>,
+    lanes: Query<&StationId, With<DiverterLane>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mode: Option<Res<OperatingMode>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyD) && local_control_allowed(mode.as_deref()) {
+        let origin = WriteOrigin::hmi("local controls");
+        for (mut diverter, station) in diverters.iter_mut() {
+            let lane_count = lanes.iter().filter(|lane_station| *lane_station == station).count() as u16;
+            diverter.target_lane = (diverter.target_lane + 1) % (lane_count + 1);
+            info!("Diverter target lane: {}", diverter.target_lane);
+            let (address, lane) = (diverter.lane_holding_address, diverter.target_lane);
+            modbus_state.write(TagKind::HoldingRegister, address, lane, &origin);
+        }
+    }
+}
+
+pub fn divert_bottles(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    diverters: Query<(&Diverter, &Transform, &StationId)>,
+    lanes: Query<(&DiverterLane, &Transform, &StationId)>,
+    mut bottles: Query<&mut Velocity, (With<Bottle>, Without<Diverted>)>,
+    rapier_config: Query<&RapierConfiguration>,
+    mut pneumatics: Option<ResMut<Pneumatics>>,
+) {
+    let gravity = rapier_config
+        .iter()
+        .next()
+        .map_or(DEFAULT_GRAVITY, |config| -config.gravity.y);
+
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(e1, e2, _) = collision_event else {
+            continue;
+        };
+
+        let ((diverter, diverter_transform, station), bottle_entity) = if let Ok(diverter) = diverters.get(*e1) {
+            (diverter, *e2)
+        } else if let Ok(diverter) = diverters.get(*e2) {
+            (diverter, *e1)
+        } else {
+            continue;
+        };
+
+        let Ok(mut velocity) = bottles.get_mut(bottle_entity) else {
+            continue;
+        };
+
+        // Bottles without a target lane pass through to the outfeed
+        let Some((_, lane_transform, _)) = lanes
+            .iter()
+            .find(|(lane, _, lane_station)| lane.lane == diverter.target_lane && *lane_station == station)
+        else {
+            continue;
+        };
+
+        // Without air the kicker cannot fire and the bottle passes
+        if let Some(pneumatics) = pneumatics.as_deref_mut() {
+            if pneumatics.is_low() {
+                warn!("Diverter kick failed on low air pressure");
+                continue;
+            }
+            pneumatics.stroke();
+        }
+        commands.entity(bottle_entity).insert(Diverted);
+
+        // Ballistic throw from the diverter to the lane floor
+        let start = diverter_transform.translation.truncate();
+        let target = lane_transform.translation.truncate();
+        let drop = (start.y - target.y).max(0.0);
+        let flight_time = (KICK_UP_SPEED + (KICK_UP_SPEED.powi(2) + 2.0 * gravity * drop).sqrt()) / gravity;
+
+        velocity.linvel = Vec2::new((target.x - start.x) / flight_time, KICK_UP_SPEED);
+        velocity.angvel = 0.0;
+
+        info!("Diverted bottle to lane {}", diverter.target_lane);
+    }
+}
+
+pub fn count_sorted_bottles(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut lanes: Query<&mut DiverterLane>,
+    bottles: Query<(), (With<Bottle>, Without<Sorted>)>,
+) {
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(e1, e2, _) = collision_event else {
+            continue;
+        };
+
+        let (lane_entity, bottle_entity) = if lanes.contains(*e1) {
+            (*e1, *e2)
+        } else if lanes.contains(*e2) {
+            (*e2, *e1)
+        } else {
+            continue;
+        };
+
+        if !bottles.contains(bottle_entity) {
+            continue;
+        }
+
+        let mut lane = lanes.get_mut(lane_entity).unwrap();
+        lane.count = lane.count.wrapping_add(1);
+        commands.entity(bottle_entity).insert(Sorted);
+
+        info!("Lane {} count: {}", lane.lane, lane.count);
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_diverters_to_modbus(
+    diverters: Query<&Diverter, Changed<Diverter>>,
+    lanes: Query<&DiverterLane, Changed<DiverterLane>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+        for diverter in diverters.iter() {
+            holdings.insert(diverter.lane_holding_address, diverter.target_lane);
+        }
+    }
+
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for lane in lanes.iter() {
+            inputs.insert(lane.counter_input_address, lane.count);
+        }
+    }
+}
+
+pub fn sync_modbus_to_diverters(
+    mut diverters: Query<&mut Diverter>,
+    modbus_state: Res<ModbusState>,
+    mode: Option<Res<OperatingMode>>,
+) {
+    if !remote_control_allowed(mode.as_deref()) {
+        return;
+    }
+
+    if let Ok(holdings) = modbus_state.holding_registers.lock() {
+        for mut diverter in diverters.iter_mut() {
+            if let Some(&holding_state) = holdings.get(&diverter.lane_holding_address) {
+                if diverter.target_lane != holding_state {
+                    diverter.target_lane = holding_state;
+                    info!("Diverter target lane set to: {}", holding_state);
+                }
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct DiverterPlugin;
+
+impl Plugin for DiverterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, handle_diverter_input).add_systems(
+            FixedUpdate,
+            (
+                sync_diverters_to_modbus,
+                sync_modbus_to_diverters,
+                divert_bottles,
+                count_sorted_bottles,
+            )
+                .chain(),
+        );
+    }
+}
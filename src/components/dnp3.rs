@@ -0,0 +1,1136 @@
+// dnp3.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Minimal DNP3 outstation over TCP (IEEE 1815): link and transport framing,
+// static and class 0 reads, and binary and analog output commands.
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, bail};
+use bevy::prelude::*;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::oneshot,
+};
+
+use super::{
+    arbitration::WriteOrigin,
+    modbus::{ModbusServerHandle, ModbusState},
+    tags::{RegisterType, Tag, TagKind, TagRegistry},
+};
+
+// >>> Constants <<<
+const DNP3_IP: &str = "0.0.0.0";
+const DNP3_PORT: u16 = 20000;
+const DEFAULT_OUTSTATION_ADDRESS: u16 = 10;
+const SELECT_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_FRAGMENT_SIZE: usize = 2048;
+
+const LINK_START: [u8; 2] = [0x05, 0x64];
+const LINK_HEADER_LENGTH: usize = 10;
+const LINK_BLOCK_SIZE: usize = 16;
+const LINK_MAX_USER_DATA: usize = 250;
+const LINK_PRIMARY: u8 = 0x40;
+const LINK_FUNCTION_MASK: u8 = 0x0F;
+
+const LINK_RESET_LINK_STATES: u8 = 0x00;
+const LINK_TEST_LINK_STATES: u8 = 0x02;
+const LINK_CONFIRMED_USER_DATA: u8 = 0x03;
+const LINK_UNCONFIRMED_USER_DATA: u8 = 0x04;
+const LINK_REQUEST_LINK_STATUS: u8 = 0x09;
+const LINK_ACK: u8 = 0x00;
+const LINK_STATUS: u8 = 0x0B;
+
+const TRANSPORT_FIN: u8 = 0x80;
+const TRANSPORT_FIR: u8 = 0x40;
+const TRANSPORT_SEQUENCE_MASK: u8 = 0x3F;
+
+const APP_FIR: u8 = 0x80;
+const APP_FIN: u8 = 0x40;
+const APP_CON: u8 = 0x20;
+const APP_SEQUENCE_MASK: u8 = 0x0F;
+
+const FUNCTION_CONFIRM: u8 = 0x00;
+const FUNCTION_READ: u8 = 0x01;
+const FUNCTION_WRITE: u8 = 0x02;
+const FUNCTION_SELECT: u8 = 0x03;
+const FUNCTION_OPERATE: u8 = 0x04;
+const FUNCTION_DIRECT_OPERATE: u8 = 0x05;
+const FUNCTION_DIRECT_OPERATE_NO_ACK: u8 = 0x06;
+const FUNCTION_ENABLE_UNSOLICITED: u8 = 0x14;
+const FUNCTION_DISABLE_UNSOLICITED: u8 = 0x15;
+const FUNCTION_RESPONSE: u8 = 0x81;
+const RESPONSE_HEADER_LENGTH: usize = 4; // Control, function and both IIN octets
+const OBJECT_HEADER_LENGTH: usize = 7; // Group, variation, qualifier, start and stop
+
+const IIN1_DEVICE_RESTART: u8 = 0x80;
+const IIN2_NO_FUNCTION_CODE_SUPPORT: u8 = 0x01;
+const IIN2_OBJECT_UNKNOWN: u8 = 0x02;
+const IIN2_PARAMETER_ERROR: u8 = 0x04;
+
+const QUALIFIER_RANGE_8: u8 = 0x00;
+const QUALIFIER_RANGE_16: u8 = 0x01;
+const QUALIFIER_ALL: u8 = 0x06;
+const QUALIFIER_COUNT_8: u8 = 0x07;
+const QUALIFIER_COUNT_16: u8 = 0x08;
+const QUALIFIER_INDEX_8: u8 = 0x17;
+const QUALIFIER_INDEX_16: u8 = 0x28;
+
+const GROUP_BINARY_INPUT: u8 = 1;
+const GROUP_BINARY_OUTPUT: u8 = 10;
+const GROUP_BINARY_COMMAND: u8 = 12;
+const GROUP_ANALOG_INPUT: u8 = 30;
+const GROUP_ANALOG_OUTPUT: u8 = 40;
+const GROUP_ANALOG_COMMAND: u8 = 41;
+const GROUP_CLASS: u8 = 60;
+const GROUP_INTERNAL_INDICATIONS: u8 = 80;
+
+const FLAG_ONLINE: u8 = 0x01;
+const FLAG_STATE: u8 = 0x80;
+
+const STATUS_SUCCESS: u8 = 0;
+const STATUS_NO_SELECT: u8 = 2;
+const STATUS_FORMAT_ERROR: u8 = 3;
+const STATUS_NOT_SUPPORTED: u8 = 4;
+
+// >>> Addresses <<<
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dnp3PointType {
+    BinaryInput,
+    BinaryOutput,
+    AnalogInput,
+    AnalogOutput,
+}
+
+impl Dnp3PointType {
+    /// The point type a tag is served as. Outputs can also be commanded.
+    pub fn of(kind: TagKind) -> Self {
+        match kind {
+            TagKind::DiscreteInput => Dnp3PointType::BinaryInput,
+            TagKind::Coil => Dnp3PointType::BinaryOutput,
+            TagKind::InputRegister => Dnp3PointType::AnalogInput,
+            TagKind::HoldingRegister => Dnp3PointType::AnalogOutput,
+        }
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            Dnp3PointType::BinaryInput => "BI",
+            Dnp3PointType::BinaryOutput => "BO",
+            Dnp3PointType::AnalogInput => "AI",
+            Dnp3PointType::AnalogOutput => "AO",
+        }
+    }
+}
+
+/// Point a tag is served as: its type and its index among points of that type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dnp3Address {
+    pub point_type: Dnp3PointType,
+    pub index: u16,
+}
+
+impl Dnp3Address {
+    /// Parses `BI0`, `BO3`, `AI2` or `AO1`.
+    pub fn parse(address: &str) -> Option<Self> {
+        let address = address.trim().to_ascii_uppercase();
+        let point_type = [
+            Dnp3PointType::BinaryInput,
+            Dnp3PointType::BinaryOutput,
+            Dnp3PointType::AnalogInput,
+            Dnp3PointType::AnalogOutput,
+        ]
+        .into_iter()
+        .find(|point_type| address.starts_with(point_type.prefix()))?;
+
+        Some(Self {
+            point_type,
+            index: address[2..].parse().ok()?,
+        })
+    }
+}
+
+impl fmt::Display for Dnp3Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.point_type.prefix(), self.index)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Dnp3Mapping {
+    pub tag: Tag,
+    pub register_type: RegisterType,
+    pub address: Dnp3Address,
+}
+
+impl Dnp3Mapping {
+    fn state(&self, modbus_state: &ModbusState) -> Option<bool> {
+        self.tag.read(modbus_state).map(|value| value != 0)
+    }
+
+    /// Reads an analog point's raw count from every register of its type.
+    fn count(&self, modbus_state: &ModbusState) -> Option<f64> {
+        let registers = match self.tag.kind {
+            TagKind::InputRegister => &modbus_state.input_registers,
+            _ => &modbus_state.holding_registers,
+        }
+        .lock()
+        .ok()?;
+        let words = (0..self.register_type.words())
+            .map(|i| registers.get(&self.tag.address.wrapping_add(i)).copied())
+            .collect::<Option<Vec<u16>>>()?;
+        Some(self.register_type.decode(&words))
+    }
+
+    fn write_count(&self, modbus_state: &ModbusState, count: f64, origin: &WriteOrigin) {
+        for (i, word) in (0..).zip(self.register_type.encode(count)) {
+            modbus_state.write(
+                self.tag.kind,
+                self.tag.address.wrapping_add(i),
+                word,
+                origin,
+            );
+        }
+    }
+}
+
+/// Lays every registered tag out as DNP3 points.
+///
+/// Discrete inputs become binary inputs, coils binary outputs, input
+/// registers analog inputs and holding registers analog outputs, numbered in
+/// registration order. Analog points carry the raw count of the tag's register
+/// type. Overrides of the tag's point type take precedence.
+///
+/// # Parameters
+/// * `tags` - Tags to map
+/// * `overrides` - Explicit points by tag name
+///
+/// # Return
+/// The tag mappings
+pub fn build_layout(tags: &TagRegistry, overrides: &[(String, Dnp3Address)]) -> Vec<Dnp3Mapping> {
+    let mut next_index: HashMap<Dnp3PointType, u16> = HashMap::new();
+
+    tags.iter()
+        .map(|tag| {
+            let point_type = Dnp3PointType::of(tag.kind);
+            let overridden = overrides
+                .iter()
+                .find(|(name, _)| *name == tag.name)
+                .map(|(_, address)| *address)
+                .filter(|address| {
+                    let fits = address.point_type == point_type;
+                    if !fits {
+                        warn!(
+                            "Ignoring DNP3 point {address} for {} {}",
+                            tag.kind.abbreviation(),
+                            tag.name
+                        );
+                    }
+                    fits
+                });
+            let address = overridden.unwrap_or_else(|| {
+                let index = next_index.entry(point_type).or_default();
+                *index += 1;
+                Dnp3Address {
+                    point_type,
+                    index: *index - 1,
+                }
+            });
+
+            Dnp3Mapping {
+                tag: tag.clone(),
+                register_type: tags.register_type(tag.kind, tag.address),
+                address,
+            }
+        })
+        .collect()
+}
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct Dnp3Settings {
+    pub port: u16,
+    pub outstation_address: u16,
+    pub overrides: Vec<(String, Dnp3Address)>,
+}
+
+/// Point layout shared with the server thread.
+#[derive(Resource, Clone, Default)]
+pub struct Dnp3Layout {
+    mappings: Arc<Mutex<Vec<Dnp3Mapping>>>,
+}
+
+impl Dnp3Layout {
+    pub fn mappings(&self) -> Vec<Dnp3Mapping> {
+        self.mappings
+            .lock()
+            .map(|mappings| mappings.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replaces the points served, e.g. once the tags changed.
+    pub fn set(&self, mappings: Vec<Dnp3Mapping>) {
+        if let Ok(mut shared) = self.mappings.lock() {
+            *shared = mappings;
+        }
+    }
+}
+
+// >>> Link Layer <<<
+struct LinkFrame {
+    control: u8,
+    destination: u16,
+    source: u16,
+    user_data: Vec<u8>,
+}
+
+/// DNP3's CRC-16, sent low byte first.
+fn crc(bytes: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in bytes {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA6BC
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn link_frame(control: u8, destination: u16, source: u16, user_data: &[u8]) -> Vec<u8> {
+    let mut frame = vec![
+        LINK_START[0],
+        LINK_START[1],
+        (user_data.len() + 5) as u8,
+        control,
+    ];
+    frame.extend_from_slice(&destination.to_le_bytes());
+    frame.extend_from_slice(&source.to_le_bytes());
+    frame.extend_from_slice(&crc(&frame).to_le_bytes());
+
+    // User data follows in blocks of 16 bytes, each with its own CRC
+    for block in user_data.chunks(LINK_BLOCK_SIZE) {
+        frame.extend_from_slice(block);
+        frame.extend_from_slice(&crc(block).to_le_bytes());
+    }
+    frame
+}
+
+async fn read_link_frame(stream: &mut TcpStream) -> anyhow::Result<Option<LinkFrame>> {
+    let mut header = [0u8; LINK_HEADER_LENGTH];
+    if stream.read_exact(&mut header).await.is_err() {
+        return Ok(None);
+    }
+    if header[..2] != LINK_START {
+        bail!("Invalid link frame start {:02x?}", &header[..2]);
+    }
+    if u16::from_le_bytes([header[8], header[9]]) != crc(&header[..8]) {
+        bail!("Link header CRC mismatch");
+    }
+
+    let length = (header[2] as usize)
+        .checked_sub(5)
+        .context("Short link frame")?;
+    let mut body = vec![0u8; length + 2 * length.div_ceil(LINK_BLOCK_SIZE)];
+    stream.read_exact(&mut body).await?;
+
+    let mut user_data = Vec::with_capacity(length);
+    for block in body.chunks(LINK_BLOCK_SIZE + 2) {
+        let (data, block_crc) = block.split_at(block.len() - 2);
+        if u16::from_le_bytes([block_crc[0], block_crc[1]]) != crc(data) {
+            bail!("Link block CRC mismatch");
+        }
+        user_data.extend_from_slice(data);
+    }
+
+    Ok(Some(LinkFrame {
+        control: header[3],
+        destination: u16::from_le_bytes([header[4], header[5]]),
+        source: u16::from_le_bytes([header[6], header[7]]),
+        user_data,
+    }))
+}
+
+// >>> Association <<<
+/// Objects of the last select, which the next operate must repeat.
+struct Selection {
+    sequence: u8,
+    objects: Vec<u8>,
+    time: Instant,
+}
+
+/// State of one master's connection.
+struct Association {
+    outstation_address: u16,
+    restarted: bool,   // Device restart indication not yet cleared by the master
+    fragment: Vec<u8>, // Request received so far
+    transport_sequence: u8,
+    selection: Option<Selection>,
+    pending: VecDeque<Vec<u8>>, // Response fragments held until the master confirms the last
+}
+
+impl Association {
+    fn new(outstation_address: u16) -> Self {
+        Self {
+            outstation_address,
+            restarted: true,
+            fragment: Vec::new(),
+            transport_sequence: 0,
+            selection: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Collects a transport segment, returning the request once its last
+    /// segment arrived.
+    fn reassemble(&mut self, segment: &[u8]) -> Option<Vec<u8>> {
+        let (&header, data) = segment.split_first()?;
+        if header & TRANSPORT_FIR != 0 {
+            self.fragment.clear();
+        }
+        self.fragment.extend_from_slice(data);
+        if self.fragment.len() > MAX_FRAGMENT_SIZE {
+            self.fragment.clear();
+            return None;
+        }
+
+        (header & TRANSPORT_FIN != 0).then(|| std::mem::take(&mut self.fragment))
+    }
+
+    /// Splits a response into transport segments, one link frame each.
+    fn segment(&mut self, response: &[u8], master: u16) -> Vec<u8> {
+        let segments: Vec<&[u8]> = response.chunks(LINK_MAX_USER_DATA - 1).collect();
+        let mut frames = Vec::new();
+
+        for (i, data) in segments.iter().enumerate() {
+            let mut header = self.transport_sequence;
+            if i == 0 {
+                header |= TRANSPORT_FIR;
+            }
+            if i == segments.len() - 1 {
+                header |= TRANSPORT_FIN;
+            }
+            self.transport_sequence = (self.transport_sequence + 1) & TRANSPORT_SEQUENCE_MASK;
+
+            let mut user_data = vec![header];
+            user_data.extend_from_slice(data);
+            let control = LINK_PRIMARY | LINK_UNCONFIRMED_USER_DATA;
+            frames.extend(link_frame(
+                control,
+                master,
+                self.outstation_address,
+                &user_data,
+            ));
+        }
+        frames
+    }
+
+    /// Takes the last selection if an operate with `objects` completes it.
+    fn take_selection(&mut self, sequence: u8, objects: &[u8]) -> bool {
+        self.selection.take().is_some_and(|selection| {
+            (selection.sequence + 1) & APP_SEQUENCE_MASK == sequence
+                && selection.objects == objects
+                && selection.time.elapsed() < SELECT_TIMEOUT
+        })
+    }
+
+    /// Takes the next fragment of a multi-fragment response once the master
+    /// confirmed the one sent before it.
+    fn next_fragment(&mut self, sequence: u8) -> Option<Vec<u8>> {
+        let next = self.pending.front()?;
+        if next[0] & APP_SEQUENCE_MASK != sequence.wrapping_add(1) & APP_SEQUENCE_MASK {
+            return None;
+        }
+        self.pending.pop_front()
+    }
+}
+
+// >>> Objects <<<
+/// How a group and variation encodes each point.
+#[derive(Clone, Copy)]
+enum Encoding {
+    PackedBits,
+    FlaggedBits,
+    Int32 { has_flags: bool },
+    Int16 { has_flags: bool },
+    Float32,
+}
+
+impl Encoding {
+    /// Bytes each point takes; packed bits take one per eight points.
+    fn size(&self) -> usize {
+        match *self {
+            Encoding::PackedBits | Encoding::FlaggedBits => 1,
+            Encoding::Int32 { has_flags } => 4 + has_flags as usize,
+            Encoding::Int16 { has_flags } => 2 + has_flags as usize,
+            Encoding::Float32 => 5,
+        }
+    }
+
+    fn push(
+        &self,
+        mapping: Option<&Dnp3Mapping>,
+        modbus_state: &ModbusState,
+        objects: &mut Vec<u8>,
+    ) {
+        // Indices without a tag are reported offline
+        let online = |is_online: bool| if is_online { FLAG_ONLINE } else { 0 };
+
+        if let Encoding::FlaggedBits = self {
+            let state = mapping.and_then(|mapping| mapping.state(modbus_state));
+            let state_flag = if state == Some(true) { FLAG_STATE } else { 0 };
+            objects.push(online(state.is_some()) | state_flag);
+            return;
+        }
+
+        let count = mapping.and_then(|mapping| mapping.count(modbus_state));
+        let value = count.unwrap_or_default();
+        match *self {
+            Encoding::Int32 { has_flags } | Encoding::Int16 { has_flags } if has_flags => {
+                objects.push(online(count.is_some()));
+            }
+            Encoding::Float32 => objects.push(online(count.is_some())),
+            _ => {}
+        }
+        // Float to integer casts saturate
+        match self {
+            Encoding::Int32 { .. } => {
+                objects.extend_from_slice(&(value.round() as i32).to_le_bytes())
+            }
+            Encoding::Int16 { .. } => {
+                objects.extend_from_slice(&(value.round() as i16).to_le_bytes())
+            }
+            Encoding::Float32 => objects.extend_from_slice(&(value as f32).to_le_bytes()),
+            Encoding::PackedBits | Encoding::FlaggedBits => {}
+        }
+    }
+}
+
+fn read_uint(bytes: &[u8], at: usize, width: usize) -> Result<usize, u8> {
+    let bytes = bytes.get(at..at + width).ok_or(IIN2_PARAMETER_ERROR)?;
+    Ok(bytes
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| (value << 8) | byte as usize))
+}
+
+/// Encodes the points of one group over a range of indices, or all of them
+/// when no range is given, under start-stop headers. Ranges too long for one
+/// fragment are split over several headers.
+///
+/// # Return
+/// The objects under each header, or the IIN2 bit of why they cannot be read
+fn static_objects(
+    group: u8,
+    variation: u8,
+    range: Option<(u16, u16)>,
+    mappings: &[Dnp3Mapping],
+    modbus_state: &ModbusState,
+) -> Result<Vec<Vec<u8>>, u8> {
+    use Dnp3PointType::*;
+
+    let (point_type, encoding, variation) = match (group, variation) {
+        (GROUP_BINARY_INPUT, 1) => (BinaryInput, Encoding::PackedBits, 1),
+        (GROUP_BINARY_INPUT, 0 | 2) => (BinaryInput, Encoding::FlaggedBits, 2),
+        (GROUP_BINARY_OUTPUT, 1) => (BinaryOutput, Encoding::PackedBits, 1),
+        (GROUP_BINARY_OUTPUT, 0 | 2) => (BinaryOutput, Encoding::FlaggedBits, 2),
+        (GROUP_ANALOG_INPUT, 0 | 1) => (AnalogInput, Encoding::Int32 { has_flags: true }, 1),
+        (GROUP_ANALOG_INPUT, 2) => (AnalogInput, Encoding::Int16 { has_flags: true }, 2),
+        (GROUP_ANALOG_INPUT, 3) => (AnalogInput, Encoding::Int32 { has_flags: false }, 3),
+        (GROUP_ANALOG_INPUT, 4) => (AnalogInput, Encoding::Int16 { has_flags: false }, 4),
+        (GROUP_ANALOG_INPUT, 5) => (AnalogInput, Encoding::Float32, 5),
+        (GROUP_ANALOG_OUTPUT, 0 | 1) => (AnalogOutput, Encoding::Int32 { has_flags: true }, 1),
+        (GROUP_ANALOG_OUTPUT, 2) => (AnalogOutput, Encoding::Int16 { has_flags: true }, 2),
+        (GROUP_ANALOG_OUTPUT, 3) => (AnalogOutput, Encoding::Float32, 3),
+        _ => return Err(IIN2_OBJECT_UNKNOWN),
+    };
+
+    let points: Vec<&Dnp3Mapping> = mappings
+        .iter()
+        .filter(|mapping| mapping.address.point_type == point_type)
+        .collect();
+    let Some(last) = points.iter().map(|mapping| mapping.address.index).max() else {
+        // A type without points answers a read of all of them with nothing
+        return match range {
+            Some(_) => Err(IIN2_PARAMETER_ERROR),
+            None => Ok(Vec::new()),
+        };
+    };
+    let (start, stop) = range.unwrap_or((0, last));
+    if start > stop || stop > last {
+        return Err(IIN2_PARAMETER_ERROR);
+    }
+
+    let point = |index: u16| {
+        points
+            .iter()
+            .find(|mapping| mapping.address.index == index)
+            .copied()
+    };
+    let block = |start: u16, stop: u16| {
+        let mut objects = vec![group, variation, QUALIFIER_RANGE_16];
+        objects.extend_from_slice(&start.to_le_bytes());
+        objects.extend_from_slice(&stop.to_le_bytes());
+
+        if let Encoding::PackedBits = encoding {
+            let mut packed = vec![0u8; (stop - start) as usize / 8 + 1];
+            for (i, index) in (start..=stop).enumerate() {
+                if point(index).and_then(|mapping| mapping.state(modbus_state)) == Some(true) {
+                    packed[i / 8] |= 1 << (i % 8);
+                }
+            }
+            objects.extend(packed);
+        } else {
+            for index in start..=stop {
+                encoding.push(point(index), modbus_state, &mut objects);
+            }
+        }
+        objects
+    };
+
+    let room =
+        (MAX_FRAGMENT_SIZE - RESPONSE_HEADER_LENGTH - OBJECT_HEADER_LENGTH) / encoding.size();
+    let points_per_block = match encoding {
+        Encoding::PackedBits => room * 8,
+        _ => room,
+    };
+    let mut blocks = Vec::new();
+    let mut first = start;
+    loop {
+        let block_stop = (first as usize + points_per_block - 1).min(stop as usize) as u16;
+        blocks.push(block(first, block_stop));
+        if block_stop == stop {
+            return Ok(blocks);
+        }
+        first = block_stop + 1;
+    }
+}
+
+/// Answers a read of static points and class 0 polls. No events are
+/// buffered, so event class polls read nothing.
+fn read(
+    objects: &[u8],
+    mappings: &[Dnp3Mapping],
+    modbus_state: &ModbusState,
+) -> Result<Vec<Vec<u8>>, u8> {
+    let mut response = Vec::new();
+    let mut cursor = 0;
+
+    while cursor < objects.len() {
+        let header = objects
+            .get(cursor..cursor + 3)
+            .ok_or(IIN2_PARAMETER_ERROR)?;
+        let (group, variation, qualifier) = (header[0], header[1], header[2]);
+        cursor += 3;
+
+        let range = match qualifier {
+            QUALIFIER_ALL => None,
+            QUALIFIER_RANGE_8 | QUALIFIER_RANGE_16 => {
+                let width = if qualifier == QUALIFIER_RANGE_8 { 1 } else { 2 };
+                let start = read_uint(objects, cursor, width)? as u16;
+                let stop = read_uint(objects, cursor + width, width)? as u16;
+                cursor += 2 * width;
+                Some((start, stop))
+            }
+            QUALIFIER_COUNT_8 | QUALIFIER_COUNT_16 => {
+                let width = if qualifier == QUALIFIER_COUNT_8 { 1 } else { 2 };
+                let count = read_uint(objects, cursor, width)? as u16;
+                cursor += width;
+                if count == 0 {
+                    continue;
+                }
+                Some((0, count - 1))
+            }
+            _ => return Err(IIN2_PARAMETER_ERROR),
+        };
+
+        match (group, variation) {
+            (GROUP_CLASS, 1) => {
+                for (group, variation) in [
+                    (GROUP_BINARY_INPUT, 2),
+                    (GROUP_BINARY_OUTPUT, 2),
+                    (GROUP_ANALOG_INPUT, 1),
+                    (GROUP_ANALOG_OUTPUT, 1),
+                ] {
+                    response.extend(static_objects(
+                        group,
+                        variation,
+                        None,
+                        mappings,
+                        modbus_state,
+                    )?);
+                }
+            }
+            (GROUP_CLASS, 2..=4) => {}
+            _ => response.extend(static_objects(
+                group,
+                variation,
+                range,
+                mappings,
+                modbus_state,
+            )?),
+        }
+    }
+    Ok(response)
+}
+
+/// State a control relay output block's code sets the output to. Pulses
+/// latch, as the plant's command coils reset themselves.
+fn command_state(code: u8) -> Option<bool> {
+    match (code >> 6, code & 0x0F) {
+        (1, _) => Some(true),      // Close
+        (2, _) => Some(false),     // Trip
+        (0, 1 | 3) => Some(true),  // Pulse on, latch on
+        (0, 2 | 4) => Some(false), // Pulse off, latch off
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ControlMode {
+    Select,
+    Operate,
+    Refuse(u8),
+}
+
+/// Checks one control against the layout and carries it out when operating.
+///
+/// # Return
+/// The control's status code
+fn command(
+    group: u8,
+    variation: u8,
+    index: u16,
+    object: &[u8],
+    mappings: &[Dnp3Mapping],
+    modbus_state: &ModbusState,
+    mode: ControlMode,
+) -> u8 {
+    let point_type = match group {
+        GROUP_BINARY_COMMAND => Dnp3PointType::BinaryOutput,
+        _ => Dnp3PointType::AnalogOutput,
+    };
+    let Some(mapping) = mappings
+        .iter()
+        .find(|mapping| mapping.address == Dnp3Address { point_type, index })
+    else {
+        return STATUS_NOT_SUPPORTED;
+    };
+    let origin = WriteOrigin::remote("dnp3");
+
+    if group == GROUP_BINARY_COMMAND {
+        let Some(state) = command_state(object[0]) else {
+            return STATUS_NOT_SUPPORTED;
+        };
+        // A count of zero asks for no operation
+        if mode == ControlMode::Operate && object[1] > 0 {
+            mapping.tag.write(modbus_state, state as u16, &origin);
+        }
+        return STATUS_SUCCESS;
+    }
+
+    let count = match variation {
+        1 => i32::from_le_bytes([object[0], object[1], object[2], object[3]]) as f64,
+        2 => i16::from_le_bytes([object[0], object[1]]) as f64,
+        3 => f32::from_le_bytes([object[0], object[1], object[2], object[3]]) as f64,
+        _ => f64::from_le_bytes(object[..8].try_into().unwrap_or_default()),
+    };
+    if !count.is_finite() {
+        return STATUS_FORMAT_ERROR;
+    }
+    if mode == ControlMode::Operate {
+        mapping.write_count(modbus_state, count, &origin);
+    }
+    STATUS_SUCCESS
+}
+
+/// Runs the controls of a select or operate request.
+///
+/// # Return
+/// The request's objects with each control's status, or the IIN2 bit of why
+/// they cannot be parsed
+fn control(
+    objects: &[u8],
+    mappings: &[Dnp3Mapping],
+    modbus_state: &ModbusState,
+    mode: ControlMode,
+) -> Result<Vec<u8>, u8> {
+    let mut echo = objects.to_vec();
+    let mut cursor = 0;
+
+    while cursor < objects.len() {
+        let header = objects
+            .get(cursor..cursor + 3)
+            .ok_or(IIN2_PARAMETER_ERROR)?;
+        let (group, variation, qualifier) = (header[0], header[1], header[2]);
+        let size = match (group, variation) {
+            (GROUP_BINARY_COMMAND, 1) => 11,
+            (GROUP_ANALOG_COMMAND, 1 | 3) => 5,
+            (GROUP_ANALOG_COMMAND, 2) => 3,
+            (GROUP_ANALOG_COMMAND, 4) => 9,
+            _ => return Err(IIN2_OBJECT_UNKNOWN),
+        };
+        let width = match qualifier {
+            QUALIFIER_INDEX_8 => 1,
+            QUALIFIER_INDEX_16 => 2,
+            _ => return Err(IIN2_PARAMETER_ERROR),
+        };
+        let count = read_uint(objects, cursor + 3, width)?;
+        cursor += 3 + width;
+
+        for _ in 0..count {
+            let index = read_uint(objects, cursor, width)? as u16;
+            let object = objects
+                .get(cursor + width..cursor + width + size)
+                .ok_or(IIN2_PARAMETER_ERROR)?;
+            // The status is the last byte of every control object
+            echo[cursor + width + size - 1] = match mode {
+                ControlMode::Refuse(status) => status,
+                _ => command(
+                    group,
+                    variation,
+                    index,
+                    object,
+                    mappings,
+                    modbus_state,
+                    mode,
+                ),
+            };
+            cursor += width + size;
+        }
+    }
+    Ok(echo)
+}
+
+/// Handles the master clearing the device restart indication.
+fn write_indications(objects: &[u8], association: &mut Association) -> Result<Vec<u8>, u8> {
+    match objects {
+        [
+            GROUP_INTERNAL_INDICATIONS,
+            1,
+            QUALIFIER_RANGE_8,
+            7,
+            7,
+            value,
+        ] if value & 1 == 0 => {
+            association.restarted = false;
+            Ok(Vec::new())
+        }
+        [GROUP_INTERNAL_INDICATIONS, ..] => Err(IIN2_PARAMETER_ERROR),
+        _ => Err(IIN2_OBJECT_UNKNOWN),
+    }
+}
+
+// >>> Protocol <<<
+/// Packs a response's objects into fragments of at most [`MAX_FRAGMENT_SIZE`]
+/// bytes. Each fragment takes the next sequence number, and all but the last
+/// ask the master to confirm them before the next is sent.
+fn fragments(blocks: Vec<Vec<u8>>, sequence: u8, iin1: u8, iin2: u8) -> Vec<Vec<u8>> {
+    let mut bodies: Vec<Vec<u8>> = Vec::new();
+    for block in blocks {
+        match bodies.last_mut() {
+            Some(body)
+                if RESPONSE_HEADER_LENGTH + body.len() + block.len() <= MAX_FRAGMENT_SIZE =>
+            {
+                body.extend(block)
+            }
+            _ => bodies.push(block),
+        }
+    }
+    if bodies.is_empty() {
+        bodies.push(Vec::new());
+    }
+
+    let count = bodies.len();
+    (0u8..)
+        .zip(bodies)
+        .map(|(i, body)| {
+            let mut control = sequence.wrapping_add(i) & APP_SEQUENCE_MASK;
+            if i == 0 {
+                control |= APP_FIR;
+            }
+            if i as usize == count - 1 {
+                control |= APP_FIN;
+            } else {
+                control |= APP_CON;
+            }
+            let mut fragment = vec![control, FUNCTION_RESPONSE, iin1, iin2];
+            fragment.extend(body);
+            fragment
+        })
+        .collect()
+}
+
+fn handle_request(
+    request: &[u8],
+    association: &mut Association,
+    layout: &Dnp3Layout,
+    modbus_state: &ModbusState,
+) -> Option<Vec<u8>> {
+    let [control_field, function, objects @ ..] = request else {
+        return None;
+    };
+    let sequence = control_field & APP_SEQUENCE_MASK;
+    let mappings = layout.mappings();
+
+    let result = match *function {
+        FUNCTION_CONFIRM => return association.next_fragment(sequence),
+        FUNCTION_DIRECT_OPERATE_NO_ACK => {
+            let _ = control(objects, &mappings, modbus_state, ControlMode::Operate);
+            return None;
+        }
+        FUNCTION_READ => read(objects, &mappings, modbus_state),
+        FUNCTION_WRITE => write_indications(objects, association).map(|objects| vec![objects]),
+        FUNCTION_SELECT => {
+            let result = control(objects, &mappings, modbus_state, ControlMode::Select);
+            association.selection = result.is_ok().then(|| Selection {
+                sequence,
+                objects: objects.to_vec(),
+                time: Instant::now(),
+            });
+            result.map(|objects| vec![objects])
+        }
+        FUNCTION_OPERATE => {
+            let mode = if association.take_selection(sequence, objects) {
+                ControlMode::Operate
+            } else {
+                ControlMode::Refuse(STATUS_NO_SELECT)
+            };
+            control(objects, &mappings, modbus_state, mode).map(|objects| vec![objects])
+        }
+        FUNCTION_DIRECT_OPERATE => control(objects, &mappings, modbus_state, ControlMode::Operate)
+            .map(|objects| vec![objects]),
+        // Nothing is ever reported unsolicited
+        FUNCTION_ENABLE_UNSOLICITED | FUNCTION_DISABLE_UNSOLICITED => Ok(Vec::new()),
+        _ => Err(IIN2_NO_FUNCTION_CODE_SUPPORT),
+    };
+
+    let (blocks, iin2) = match result {
+        Ok(blocks) => (blocks, 0),
+        Err(iin2) => (Vec::new(), iin2),
+    };
+    let iin1 = if association.restarted {
+        IIN1_DEVICE_RESTART
+    } else {
+        0
+    };
+
+    // A new request abandons whatever is left of the last response
+    association.pending = fragments(blocks, sequence, iin1, iin2).into();
+    association.pending.pop_front()
+}
+
+async fn serve_connection(
+    mut stream: TcpStream,
+    outstation_address: u16,
+    layout: Dnp3Layout,
+    modbus_state: ModbusState,
+) -> anyhow::Result<()> {
+    let mut association = Association::new(outstation_address);
+
+    while let Some(frame) = read_link_frame(&mut stream).await? {
+        // Frames to other outstations and broadcasts are not answered
+        if frame.destination != outstation_address || frame.control & LINK_PRIMARY == 0 {
+            continue;
+        }
+
+        let reply = match frame.control & LINK_FUNCTION_MASK {
+            LINK_RESET_LINK_STATES | LINK_TEST_LINK_STATES => {
+                link_frame(LINK_ACK, frame.source, outstation_address, &[])
+            }
+            LINK_REQUEST_LINK_STATUS => {
+                link_frame(LINK_STATUS, frame.source, outstation_address, &[])
+            }
+            function @ (LINK_CONFIRMED_USER_DATA | LINK_UNCONFIRMED_USER_DATA) => {
+                let mut reply = Vec::new();
+                if function == LINK_CONFIRMED_USER_DATA {
+                    reply = link_frame(LINK_ACK, frame.source, outstation_address, &[]);
+                }
+                if let Some(request) = association.reassemble(&frame.user_data) {
+                    if let Some(response) =
+                        handle_request(&request, &mut association, &layout, &modbus_state)
+                    {
+                        reply.extend(association.segment(&response, frame.source));
+                    }
+                }
+                reply
+            }
+            other => bail!("Unsupported link function {other:#04x}"),
+        };
+        stream.write_all(&reply).await?;
+    }
+    Ok(())
+}
+
+// >>> Server <<<
+/// The running DNP3 outstation thread. Shutting it down releases the port.
+#[derive(Resource)]
+pub struct Dnp3ServerHandle(pub ModbusServerHandle);
+
+impl Dnp3ServerHandle {
+    /// Starts serving the layout on the given port, or on a free one picked
+    /// by the OS if it is 0, e.g. in tests.
+    ///
+    /// # Parameters
+    /// * `modbus_state` - Register tables backing the points
+    /// * `layout` - Points served, shared with the simulation
+    /// * `port` - The port the outstation listens on
+    /// * `outstation_address` - Link address the outstation answers to
+    pub fn start(
+        modbus_state: ModbusState,
+        layout: Dnp3Layout,
+        port: u16,
+        outstation_address: u16,
+    ) -> Self {
+        let socket_addr: SocketAddr = format!("{}:{}", DNP3_IP, port).parse().unwrap();
+        // Bound before the thread starts so the port picked is known here
+        let listener = std::net::TcpListener::bind(socket_addr)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener));
+        let port = match &listener {
+            Ok(listener) => listener.local_addr().map_or(port, |addr| addr.port()),
+            Err(err) => {
+                eprintln!("DNP3 outstation failed to bind {socket_addr}: {err}");
+                port
+            }
+        };
+
+        Self(ModbusServerHandle::spawn(port, move |shutdown_signal| {
+            serve(
+                listener.ok(),
+                outstation_address,
+                layout,
+                modbus_state,
+                shutdown_signal,
+            )
+        }))
+    }
+
+    pub fn port(&self) -> u16 {
+        self.0.port()
+    }
+}
+
+async fn serve(
+    listener: Option<std::net::TcpListener>,
+    outstation_address: u16,
+    layout: Dnp3Layout,
+    modbus_state: ModbusState,
+    mut shutdown_signal: oneshot::Receiver<()>,
+) {
+    let Some(listener) = listener.and_then(|listener| TcpListener::from_std(listener).ok()) else {
+        return;
+    };
+    let Ok(socket_addr) = listener.local_addr() else {
+        return;
+    };
+    println!("DNP3 outstation {outstation_address} running on {socket_addr}");
+
+    // Connection tasks end with the runtime once the thread returns
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(_) => continue,
+            },
+            _ = &mut shutdown_signal => return,
+        };
+        let layout = layout.clone();
+        let state = modbus_state.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve_connection(stream, outstation_address, layout, state).await {
+                eprintln!("DNP3 connection {peer}: {err}");
+            }
+        });
+    }
+}
+
+// >>> Systems <<<
+fn start_dnp3_server(
+    mut commands: Commands,
+    settings: Res<Dnp3Settings>,
+    layout: Res<Dnp3Layout>,
+    modbus_state: Res<ModbusState>,
+) {
+    commands.insert_resource(Dnp3ServerHandle::start(
+        modbus_state.clone(),
+        layout.clone(),
+        settings.port,
+        settings.outstation_address,
+    ));
+}
+
+pub fn sync_dnp3_layout(
+    tags: Res<TagRegistry>,
+    settings: Res<Dnp3Settings>,
+    layout: Res<Dnp3Layout>,
+) {
+    if tags.is_changed() || settings.is_changed() {
+        let mappings = build_layout(&tags, &settings.overrides);
+        for mapping in mappings.iter() {
+            debug!("DNP3 {} -> {}", mapping.address, mapping.tag.name);
+        }
+        layout.set(mappings);
+    }
+}
+
+// >>> Plugin <<<
+/// Serves the tag registry as a DNP3 outstation to SCADA masters, for
+/// electrical-sector training where DNP3 rather than Modbus is the norm.
+pub struct Dnp3Plugin {
+    pub port: u16,
+    pub outstation_address: u16,
+    overrides: Vec<(String, String)>,
+}
+
+impl Default for Dnp3Plugin {
+    fn default() -> Self {
+        Self {
+            port: DNP3_PORT,
+            outstation_address: DEFAULT_OUTSTATION_ADDRESS,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl Dnp3Plugin {
+    /// Serves a tag as an explicit point, e.g. `map("conveyor.run", "BO4")`.
+    pub fn map(mut self, tag: &str, address: &str) -> Self {
+        self.overrides.push((tag.to_string(), address.to_string()));
+        self
+    }
+}
+
+impl Plugin for Dnp3Plugin {
+    fn build(&self, app: &mut App) {
+        let overrides = self
+            .overrides
+            .iter()
+            .filter_map(|(tag, address)| match Dnp3Address::parse(address) {
+                Some(parsed) => Some((tag.clone(), parsed)),
+                None => {
+                    warn!("Ignoring invalid DNP3 point {address} for tag {tag}");
+                    None
+                }
+            })
+            .collect();
+
+        app.insert_resource(Dnp3Settings {
+            port: self.port,
+            outstation_address: self.outstation_address,
+            overrides,
+        })
+        .init_resource::<Dnp3Layout>()
+        .add_systems(Startup, start_dnp3_server)
+        .add_systems(Update, sync_dnp3_layout);
+    }
+}
@@ -0,0 +1,195 @@
+// energy.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    bottle::{Bottle, BottlePhysics, BottlePosition, BottleSpec, FillLevel},
+    conveyor::{ConveyorShape, ConveyorState},
+    heater::Heater,
+    modbus::ModbusState,
+    overload::{belt_mass, motor_load},
+    simulation::{ResetSimulation, SimTime},
+};
+
+// >>> Constants <<<
+pub const ENERGY_WORDS: u16 = 2; // Input registers per energy total, high word first
+const FULL_POWER: f32 = 100.0; // Heater power setting, in percent, drawing the rated power
+
+// >>> Components <<<
+/// Power meter on a conveyor motor or heater, totalizing the energy it uses.
+///
+/// A conveyor draws its rated power running at `rated_speed` with its motor
+/// at rated load, i.e. carrying `rated_mass`, and proportionally less when
+/// slower or lighter; a heater draws its rated power at full power. The
+/// instantaneous power is published in W on `power_input_address` and the
+/// total in Wh across `ENERGY_WORDS` input registers from
+/// `energy_input_address`.
+#[derive(Component, Debug, Clone)]
+pub struct EnergyMeter {
+    pub power_input_address: u16,
+    pub energy_input_address: u16, // First register of the energy total
+    pub rated_power: f32,          // W
+    pub rated_speed: f32,          // Belt speed drawing the rated power
+    pub rated_mass: f32,           // Mass on the belt drawing the rated power
+    pub power: f32,                // W
+    pub energy: f64,               // Wh
+}
+
+impl EnergyMeter {
+    /// Creates a meter with nothing used yet.
+    ///
+    /// # Parameters
+    /// * `power_input_address` - Input register holding the power in W
+    /// * `energy_input_address` - First input register of the energy total in Wh
+    /// * `rated_power` - Power in W drawn at rated speed and load, or full heater power
+    ///
+    /// # Return
+    /// The energy meter component, rated for the default belt speed and mass
+    pub fn new(power_input_address: u16, energy_input_address: u16, rated_power: f32) -> Self {
+        Self {
+            power_input_address,
+            energy_input_address,
+            rated_power,
+            rated_speed: 100.0,
+            rated_mass: 10.0,
+            power: 0.0,
+            energy: 0.0,
+        }
+    }
+
+    /// Energy used so far, in kWh.
+    pub fn kilowatt_hours(&self) -> f64 {
+        self.energy / 1000.0
+    }
+
+    /// The energy total split into its input register words, high word first.
+    pub fn energy_words(&self) -> [u16; ENERGY_WORDS as usize] {
+        let energy = self.energy.clamp(0.0, u32::MAX as f64) as u32;
+        [(energy >> 16) as u16, energy as u16]
+    }
+
+    fn power_register(&self) -> u16 {
+        self.power.round().clamp(0.0, u16::MAX as f32) as u16
+    }
+}
+
+// >>> Systems <<<
+pub fn reset_energy_meters(
+    mut reset_events: EventReader<ResetSimulation>,
+    mut meters: Query<&mut EnergyMeter>,
+) {
+    if reset_events.read().count() == 0 {
+        return;
+    }
+    for mut meter in meters.iter_mut() {
+        meter.power = 0.0;
+        meter.energy = 0.0;
+    }
+}
+
+pub fn register_energy_meters(
+    meters: Query<&EnergyMeter, Added<EnergyMeter>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for meter in meters.iter() {
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(meter.power_input_address, 0);
+            for i in 0..ENERGY_WORDS {
+                inputs.insert(meter.energy_input_address + i, 0);
+            }
+        }
+
+        info!(
+            "Registered energy meter on power input register: {:x?}",
+            meter.power_input_address
+        );
+    }
+}
+
+/// Works out each conveyor motor's power from its belt speed and the mass it
+/// carries.
+pub fn meter_conveyors(
+    mut meters: Query<(
+        &mut EnergyMeter,
+        &ConveyorState,
+        &Transform,
+        &Sprite,
+        &ConveyorShape,
+    )>,
+    bottles: Query<(&Transform, &BottlePosition, &BottleSpec, &FillLevel), With<Bottle>>,
+    physics: Res<BottlePhysics>,
+) {
+    for (mut meter, conveyor_state, transform, sprite, shape) in meters.iter_mut() {
+        let power = if conveyor_state.is_moving() {
+            let mass = belt_mass(transform, sprite, shape, &bottles, &physics);
+            let speed = conveyor_state.speed / meter.rated_speed.max(f32::EPSILON);
+            meter.rated_power * speed * motor_load(mass, meter.rated_mass) / 100.0
+        } else {
+            0.0
+        };
+
+        if meter.power != power {
+            meter.power = power;
+        }
+    }
+}
+
+pub fn meter_heaters(mut meters: Query<(&mut EnergyMeter, &Heater), Without<ConveyorState>>) {
+    for (mut meter, heater) in meters.iter_mut() {
+        let power = meter.rated_power * (heater.power as f32).min(FULL_POWER) / FULL_POWER;
+        if meter.power != power {
+            meter.power = power;
+        }
+    }
+}
+
+pub fn totalize_energy(time: Res<SimTime>, mut meters: Query<&mut EnergyMeter>) {
+    let hours = time.delta_secs_f64() / 3600.0;
+
+    for mut meter in meters.iter_mut() {
+        if meter.power > 0.0 {
+            meter.energy += meter.power as f64 * hours;
+        }
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_energy_meters_to_modbus(
+    meters: Query<&EnergyMeter, Changed<EnergyMeter>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if meters.is_empty() {
+        return;
+    }
+
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for meter in meters.iter() {
+            inputs.insert(meter.power_input_address, meter.power_register());
+            for (i, word) in meter.energy_words().into_iter().enumerate() {
+                inputs.insert(meter.energy_input_address + i as u16, word);
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Power and energy metering of conveyor motors and heaters.
+pub struct EnergyPlugin;
+
+impl Plugin for EnergyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_energy_meters,
+                reset_energy_meters,
+                (meter_conveyors, meter_heaters),
+                totalize_energy,
+                sync_energy_meters_to_modbus,
+            )
+                .chain(),
+        );
+    }
+}
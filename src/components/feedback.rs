@@ -0,0 +1,138 @@
+// feedback.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    accumulation::Accumulated,
+    bottle::{Bottle, BottlePosition, BottleSpec},
+    conveyor::{ConveyorShape, ConveyorState, SpeedRipple, stands_on_belt},
+    modbus::ModbusState,
+    stopper::Stopped,
+    valve::{FlowMeter, measure_valve_flow},
+};
+
+// >>> Components <<<
+/// Input register publishing the value a device actually achieves, as opposed
+/// to the setpoint written to its holding register.
+///
+/// Conveyors report the speed the bottles riding on the belt move along it in
+/// pixels per second and valves their measured flow in balls per minute, each
+/// multiplied by `scale`.
+#[derive(Component, Debug, Clone)]
+pub struct Feedback {
+    pub input_address: u16,
+    pub scale: f32,
+}
+
+impl Feedback {
+    pub fn new(input_address: u16, scale: f32) -> Self {
+        Self {
+            input_address,
+            scale,
+        }
+    }
+
+    /// Register value for a process value, saturating at the register's range.
+    pub fn register(&self, value: f32) -> u16 {
+        (value * self.scale).round().clamp(0.0, u16::MAX as f32) as u16
+    }
+}
+
+// >>> Systems <<<
+pub fn register_feedback(
+    feedback: Query<&Feedback, Added<Feedback>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for feedback in feedback.iter() {
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(feedback.input_address, 0);
+        }
+
+        info!(
+            "Registered feedback on input register: {:x?}",
+            feedback.input_address
+        );
+    }
+}
+
+// >>> Modbus Synchronization <<<
+/// Publishes each belt's speed as measured from the bottles riding on it,
+/// which lags the surface while they are picked up and drops when they are
+/// held back. Bottles a stopper or accumulation lets the belt slip under are
+/// left out. A belt with nothing on it reads the speed its surface is driven
+/// at, as a tachometer on its drum would.
+pub fn sync_conveyor_feedback_to_modbus(
+    conveyors: Query<(
+        &Feedback,
+        &ConveyorState,
+        &SpeedRipple,
+        &ConveyorShape,
+        &Transform,
+        &GlobalTransform,
+        &Sprite,
+    )>,
+    bottles: Query<
+        (&Transform, &BottlePosition, &BottleSpec, &Velocity),
+        (With<Bottle>, Without<Accumulated>, Without<Stopped>),
+    >,
+    modbus_state: Res<ModbusState>,
+) {
+    let Ok(mut inputs) = modbus_state.input_registers.lock() else {
+        return;
+    };
+
+    for (feedback, conveyor_state, ripple, shape, transform, global_transform, sprite) in
+        conveyors.iter()
+    {
+        // Direction of travel at positive speed, wherever the bottle is on the belt
+        let forward = shape.surface_motion(global_transform, 1.0);
+        let speeds: Vec<f32> = bottles
+            .iter()
+            .filter_map(|(bottle_transform, position, spec, velocity)| {
+                let center = position.world_center(bottle_transform);
+                stands_on_belt(transform, sprite, shape, center, spec.height)
+                    .then(|| velocity.linvel.dot(forward.velocity_at(center)))
+            })
+            .collect();
+
+        let speed = if speeds.is_empty() {
+            conveyor_state.surface_speed(ripple)
+        } else {
+            speeds.iter().sum::<f32>() / speeds.len() as f32
+        };
+        inputs.insert(feedback.input_address, feedback.register(speed));
+    }
+}
+
+pub fn sync_valve_feedback_to_modbus(
+    valves: Query<(&Feedback, &FlowMeter)>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for (feedback, flow_meter) in valves.iter() {
+            let flow = flow_meter.rate() * 60.0;
+            inputs.insert(feedback.input_address, feedback.register(flow));
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct FeedbackPlugin;
+
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_feedback,
+                sync_conveyor_feedback_to_modbus,
+                sync_valve_feedback_to_modbus,
+            )
+                .chain()
+                .after(measure_valve_flow),
+        );
+    }
+}
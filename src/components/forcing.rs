@@ -0,0 +1,59 @@
+// forcing.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{modbus::ModbusState, sensor::GlobalSensorState, tag_sync::sync_tags};
+
+// >>> Events <<<
+/// One or more forces were released, so their true values must be shown again.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ForcesReleased;
+
+// >>> Systems <<<
+/// Holds forced coils before the plant reads them and forced discrete inputs
+/// after the plant writes them.
+pub fn apply_io_forces(modbus_state: Res<ModbusState>) {
+    modbus_state.apply_forces();
+}
+
+/// Writes every sensor's own output again over its released force.
+pub fn republish_released_sensors(
+    mut released_events: EventReader<ForcesReleased>,
+    mut global_state: ResMut<GlobalSensorState>,
+) {
+    if released_events.read().count() > 0 {
+        global_state.republish_all();
+    }
+}
+
+// >>> Input System <<<
+pub fn handle_force_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    modbus_state: Res<ModbusState>,
+    mut released_events: EventWriter<ForcesReleased>,
+) {
+    if keyboard.just_pressed(KeyCode::F4) {
+        let released = modbus_state.release_all();
+        if released > 0 {
+            released_events.write(ForcesReleased);
+        }
+        info!("Released {released} forces");
+    }
+}
+
+// >>> Plugin <<<
+/// Forcing of coils and discrete inputs for commissioning, set from the
+/// device popup and released there or all at once (F4).
+pub struct ForcingPlugin;
+
+impl Plugin for ForcingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ForcesReleased>()
+            .add_systems(Update, handle_force_input)
+            .add_systems(FixedPreUpdate, apply_io_forces)
+            .add_systems(FixedUpdate, republish_released_sensors.before(sync_tags))
+            .add_systems(FixedPostUpdate, apply_io_forces);
+    }
+}
@@ -0,0 +1,248 @@
+// gallery.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use anyhow::Context;
+use bevy::prelude::*;
+
+use super::simulation::{SimState, SimulationError};
+use crate::scenario::{LoadScenario, Scenario};
+
+// >>> Constants <<<
+const MENU_COLOR: Color = Color::srgba(0.05, 0.05, 0.1, 0.95);
+const MENU_TITLE_FONT_SIZE: f32 = 22.0;
+const MENU_FONT_SIZE: f32 = 14.0;
+const MENU_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+// >>> Bundled Scenarios <<<
+/// A scenario file built into the binary, loadable without the file on disk.
+pub struct BundledScenario {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub contents: &'static str,
+}
+
+impl BundledScenario {
+    pub fn scenario(&self) -> anyhow::Result<Scenario> {
+        Scenario::parse(self.contents)
+            .with_context(|| format!("Bundled scenario {} is invalid", self.name))
+    }
+}
+
+/// The exercises listed in the start-up menu, in menu order.
+pub const GALLERY: &[BundledScenario] = &[
+    BundledScenario {
+        name: "filling_line",
+        title: "Filling line",
+        description: "Run the belt, stop each bottle under the valve and fill it",
+        contents: include_str!("../../docs/scenarios/filling_line.toml"),
+    },
+    BundledScenario {
+        name: "sortation",
+        title: "Sortation",
+        description: "Scan each bottle's barcode and divert it to its lane",
+        contents: include_str!("../../docs/scenarios/sortation.toml"),
+    },
+    BundledScenario {
+        name: "tank_level",
+        title: "Tank level",
+        description: "Keep the supply tank above its low level while filling",
+        contents: include_str!("../../docs/scenarios/tank_level.toml"),
+    },
+    BundledScenario {
+        name: "capping",
+        title: "Capping",
+        description: "Feed caps from the hopper, cap each bottle and inspect it",
+        contents: include_str!("../../docs/scenarios/capping.toml"),
+    },
+    BundledScenario {
+        name: "catch_the_drop",
+        title: "Catch the drop",
+        description: "Time the valve against the belt so the drops land in the bottles",
+        contents: include_str!("../../docs/scenarios/catch_the_drop.toml"),
+    },
+];
+
+/// Looks up a bundled scenario by its name, e.g. `sortation`.
+pub fn bundled_scenario(name: &str) -> Option<&'static BundledScenario> {
+    GALLERY.iter().find(|entry| entry.name == name)
+}
+
+// >>> States <<<
+/// Whether the scenario menu is open. The simulation is paused while it is,
+/// unless it already stopped on an error.
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    Menu,
+    Running,
+}
+
+// >>> Components <<<
+#[derive(Component)]
+pub struct ScenarioMenu;
+
+// >>> Systems <<<
+/// Shows the menu over the plant and pauses the simulation behind it.
+pub fn open_scenario_menu(
+    mut commands: Commands,
+    state: Res<State<SimState>>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    // A plant stopped on an error stays stopped
+    if *state.get() != SimState::Error {
+        next_state.set(SimState::Paused);
+    }
+
+    let entries: Vec<String> = GALLERY
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| format!("{}  {}\n     {}", index + 1, entry.title, entry.description))
+        .collect();
+
+    commands
+        .spawn((
+            ScenarioMenu,
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(12.0),
+                        padding: UiRect::all(Val::Px(20.0)),
+                        ..default()
+                    },
+                    BackgroundColor(MENU_COLOR),
+                ))
+                .with_children(|panel| {
+                    panel.spawn((
+                        Text::new("Choose an exercise"),
+                        TextFont {
+                            font_size: MENU_TITLE_FONT_SIZE,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                    panel.spawn((
+                        Text::new(entries.join("\n\n")),
+                        TextFont {
+                            font_size: MENU_FONT_SIZE,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                    panel.spawn((
+                        Text::new("Esc: keep the current scenario    F11: reopen this menu"),
+                        TextFont {
+                            font_size: MENU_FONT_SIZE,
+                            ..default()
+                        },
+                        TextColor(Color::srgb(0.6, 0.6, 0.6)),
+                    ));
+                });
+        });
+}
+
+pub fn close_scenario_menu(
+    mut commands: Commands,
+    menus: Query<Entity, With<ScenarioMenu>>,
+    state: Res<State<SimState>>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    for menu in menus.iter() {
+        commands.entity(menu).despawn();
+    }
+    if *state.get() == SimState::Paused {
+        next_state.set(SimState::Running);
+    }
+}
+
+// >>> Input System <<<
+/// Loads the exercise picked with its number key, or returns to the running
+/// plant on Escape. F11 reopens the menu.
+pub fn handle_menu_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut load_events: EventWriter<LoadScenario>,
+    mut error_events: EventWriter<SimulationError>,
+) {
+    match state.get() {
+        AppState::Running => {
+            if keyboard.just_pressed(KeyCode::F11) {
+                next_state.set(AppState::Menu);
+            }
+        }
+        AppState::Menu => {
+            if keyboard.just_pressed(KeyCode::Escape) {
+                next_state.set(AppState::Running);
+                return;
+            }
+
+            let Some(entry) = MENU_KEYS
+                .iter()
+                .position(|key| keyboard.just_pressed(*key))
+                .and_then(|index| GALLERY.get(index))
+            else {
+                return;
+            };
+            match entry.scenario() {
+                Ok(scenario) => {
+                    load_events.write(LoadScenario(scenario));
+                }
+                Err(err) => {
+                    error_events.write(SimulationError(format!("{err:#}")));
+                }
+            }
+            next_state.set(AppState::Running);
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Start-up menu listing the bundled exercises, reopened with F11, so a
+/// scenario can be picked without hunting for its file.
+pub struct GalleryPlugin {
+    pub open_on_start: bool,
+}
+
+impl Default for GalleryPlugin {
+    fn default() -> Self {
+        Self {
+            open_on_start: true,
+        }
+    }
+}
+
+impl Plugin for GalleryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_state(if self.open_on_start {
+            AppState::Menu
+        } else {
+            AppState::Running
+        })
+        .add_event::<LoadScenario>()
+        .add_systems(OnEnter(AppState::Menu), open_scenario_menu)
+        .add_systems(OnExit(AppState::Menu), close_scenario_menu)
+        .add_systems(Update, handle_menu_input);
+    }
+}
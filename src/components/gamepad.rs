@@ -0,0 +1,18 @@
+// gamepad.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+// >>> Bindings <<<
+// Face buttons by position, so the layout is the same on every brand of pad
+pub const CONVEYOR_TOGGLE: GamepadButton = GamepadButton::South;
+pub const VALVE_TOGGLE: GamepadButton = GamepadButton::East;
+pub const BOTTLE_SPAWN: GamepadButton = GamepadButton::West;
+pub const SPEED_UP: GamepadButton = GamepadButton::DPadUp;
+pub const SPEED_DOWN: GamepadButton = GamepadButton::DPadDown;
+
+/// Whether the button was pressed this frame on any connected gamepad.
+pub fn just_pressed(gamepads: &Query<&Gamepad>, button: GamepadButton) -> bool {
+    gamepads.iter().any(|gamepad| gamepad.just_pressed(button))
+}
@@ -0,0 +1,311 @@
+// gantry.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    bottle::{Bottle, BottlePosition, BottleSpec},
+    modbus::ModbusState,
+    simulation::SimTime,
+    tags::{TagKind, TagRegistry},
+};
+
+// >>> Constants <<<
+const HEAD_SIZE: Vec2 = Vec2::new(30.0, 12.0);
+const ARM_SIZE: Vec2 = Vec2::new(8.0, 60.0);
+const HEAD_COLOR: Color = Color::srgb(0.95, 0.7, 0.1);
+const GRIPPING_COLOR: Color = Color::srgb(0.2, 0.8, 0.2);
+const ARM_COLOR: Color = Color::srgb(0.5, 0.5, 0.55);
+const GRIP_RANGE: f32 = 10.0; // How far below the head a bottle's top may be to be picked
+
+// >>> Components <<<
+/// Two-axis pick-and-place robot with a vacuum gripper.
+///
+/// Targets are in pixels from the bottom-left corner of the gantry's reach.
+#[derive(Component)]
+pub struct Gantry {
+    pub x_holding_address: u16,
+    pub y_holding_address: u16,
+    pub busy_discrete_address: u16,
+    pub in_position_discrete_address: u16,
+    pub gripper_coil_address: u16,
+    pub origin: Vec2,
+    pub reach: Vec2,
+    pub speed: f32,
+    pub target: Vec2,
+    pub position: Vec2,
+    pub is_gripper_on: bool,
+    held: Option<(Entity, Vec2)>, // Bottle and its offset from the head
+}
+
+impl Gantry {
+    pub fn is_in_position(&self) -> bool {
+        self.position == self.target
+    }
+
+    pub fn is_holding(&self) -> bool {
+        self.held.is_some()
+    }
+
+    fn head(&self) -> Vec2 {
+        self.origin + self.position
+    }
+}
+
+/// Marks a bottle a gantry has picked off the line.
+#[derive(Component)]
+pub struct Handled;
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct GantryBundle {
+    gantry: Gantry,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl Gantry {
+    /// Creates a gantry parked at the top-left of its reach.
+    ///
+    /// # Parameters
+    /// * `x_holding_address` - Holding register with the target X position
+    /// * `y_holding_address` - Holding register with the target Y position
+    /// * `busy_discrete_address` - Discrete input set while moving
+    /// * `in_position_discrete_address` - Discrete input set once the target is reached
+    /// * `gripper_coil_address` - Coil switching the vacuum gripper
+    /// * `origin` - Bottom-left corner of the reach
+    /// * `reach` - Width and height the head can travel
+    /// * `speed` - Travel speed in pixels per second
+    ///
+    /// # Return
+    /// The gantry bundle
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        x_holding_address: u16,
+        y_holding_address: u16,
+        busy_discrete_address: u16,
+        in_position_discrete_address: u16,
+        gripper_coil_address: u16,
+        origin: Vec2,
+        reach: Vec2,
+        speed: f32,
+    ) -> GantryBundle {
+        let home = Vec2::new(0.0, reach.y);
+
+        GantryBundle {
+            gantry: Gantry {
+                x_holding_address,
+                y_holding_address,
+                busy_discrete_address,
+                in_position_discrete_address,
+                gripper_coil_address,
+                origin,
+                reach,
+                speed,
+                target: home,
+                position: home,
+                is_gripper_on: false,
+                held: None,
+            },
+            sprite: Sprite::from_color(HEAD_COLOR, HEAD_SIZE),
+            transform: Transform::from_translation((origin + home).extend(1.0)),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_gantries(
+    mut commands: Commands,
+    gantries: Query<(Entity, &Gantry), Added<Gantry>>,
+    modbus_state: Res<ModbusState>,
+    tags: Res<TagRegistry>,
+) {
+    for (entity, gantry) in gantries.iter() {
+        if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+            let (x, y) = (gantry.x_holding_address, gantry.y_holding_address);
+            tags.insert_value(&mut holdings, TagKind::HoldingRegister, x, gantry.target.x);
+            tags.insert_value(&mut holdings, TagKind::HoldingRegister, y, gantry.target.y);
+        }
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(gantry.gripper_coil_address, gantry.is_gripper_on);
+        }
+        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+            discretes.insert(gantry.busy_discrete_address, !gantry.is_in_position());
+            discretes.insert(gantry.in_position_discrete_address, gantry.is_in_position());
+        }
+
+        // The arm rises from the head towards the rail
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Sprite::from_color(ARM_COLOR, ARM_SIZE),
+                Transform::from_xyz(0.0, (HEAD_SIZE.y + ARM_SIZE.y) / 2.0, -0.1),
+            ));
+        });
+
+        info!(
+            "Registered gantry on target holding registers: {:x?}, {:x?}",
+            gantry.x_holding_address, gantry.y_holding_address
+        );
+    }
+}
+
+pub fn move_gantries(time: Res<SimTime>, mut gantries: Query<(&mut Gantry, &mut Transform)>) {
+    for (mut gantry, mut transform) in gantries.iter_mut() {
+        if gantry.is_in_position() {
+            continue;
+        }
+
+        let step = gantry.speed * time.delta_secs();
+        gantry.position = gantry.position.move_towards(gantry.target, step);
+        transform.translation = gantry.head().extend(transform.translation.z);
+    }
+}
+
+/// Picks up a bottle beneath the head while the gripper is on and drops it
+/// once the gripper is switched off.
+pub fn operate_grippers(
+    mut commands: Commands,
+    mut gantries: Query<&mut Gantry>,
+    bottles: Query<
+        (Entity, &Transform, &BottlePosition, &BottleSpec),
+        (With<Bottle>, Without<Handled>),
+    >,
+) {
+    for mut gantry in gantries.iter_mut() {
+        let head = gantry.head();
+
+        match gantry.held {
+            Some((bottle, _)) if !gantry.is_gripper_on => {
+                gantry.held = None;
+                if let Ok(mut entity) = commands.get_entity(bottle) {
+                    entity.insert((RigidBody::Dynamic, Velocity::zero()));
+                }
+                info!("Gantry released bottle {:?}", bottle);
+            }
+            None if gantry.is_gripper_on => {
+                let picked = bottles.iter().find(|(_, transform, position, spec)| {
+                    let center = position.world_center(transform);
+                    let top = center.y + spec.height / 2.0;
+                    (center.x - head.x).abs() < spec.width / 2.0
+                        && (head.y - HEAD_SIZE.y / 2.0 - top).abs() < GRIP_RANGE
+                });
+
+                if let Some((bottle, transform, ..)) = picked {
+                    gantry.held = Some((bottle, transform.translation.truncate() - head));
+                    commands.entity(bottle).insert((
+                        Handled,
+                        RigidBody::KinematicPositionBased,
+                        Velocity::zero(),
+                    ));
+                    info!("Gantry picked bottle {:?}", bottle);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Carries held bottles along with the head.
+pub fn carry_held_bottles(
+    mut gantries: Query<&mut Gantry>,
+    mut bottles: Query<&mut Transform, (With<Bottle>, With<Handled>)>,
+) {
+    for mut gantry in gantries.iter_mut() {
+        let Some((bottle, offset)) = gantry.held else {
+            continue;
+        };
+
+        // The bottle was taken off the line while held
+        let Ok(mut transform) = bottles.get_mut(bottle) else {
+            gantry.held = None;
+            continue;
+        };
+
+        let position = gantry.head() + offset;
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+pub fn update_gantry_visuals(mut gantries: Query<(&Gantry, &mut Sprite), Changed<Gantry>>) {
+    for (gantry, mut sprite) in gantries.iter_mut() {
+        sprite.color = if gantry.is_holding() {
+            GRIPPING_COLOR
+        } else {
+            HEAD_COLOR
+        };
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_modbus_to_gantries(
+    mut gantries: Query<&mut Gantry>,
+    modbus_state: Res<ModbusState>,
+    tags: Res<TagRegistry>,
+) {
+    if let Ok(holdings) = modbus_state.holding_registers.lock() {
+        for mut gantry in gantries.iter_mut() {
+            let x = tags.read_value(
+                &holdings,
+                TagKind::HoldingRegister,
+                gantry.x_holding_address,
+            );
+            let y = tags.read_value(
+                &holdings,
+                TagKind::HoldingRegister,
+                gantry.y_holding_address,
+            );
+            if let (Some(x), Some(y)) = (x, y) {
+                let target = Vec2::new(x, y).clamp(Vec2::ZERO, gantry.reach);
+                if gantry.target != target {
+                    gantry.target = target;
+                    info!("Gantry target set to: ({}, {})", target.x, target.y);
+                }
+            }
+        }
+    }
+
+    if let Ok(coils) = modbus_state.coils.lock() {
+        for mut gantry in gantries.iter_mut() {
+            if let Some(&coil_state) = coils.get(&gantry.gripper_coil_address) {
+                if gantry.is_gripper_on != coil_state {
+                    gantry.is_gripper_on = coil_state;
+                    info!("Gantry gripper set to: {}", coil_state);
+                }
+            }
+        }
+    }
+}
+
+pub fn sync_gantries_to_modbus(
+    gantries: Query<&Gantry, Changed<Gantry>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+        for gantry in gantries.iter() {
+            discretes.insert(gantry.busy_discrete_address, !gantry.is_in_position());
+            discretes.insert(gantry.in_position_discrete_address, gantry.is_in_position());
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct GantryPlugin;
+
+impl Plugin for GantryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_gantry_visuals).add_systems(
+            FixedUpdate,
+            (
+                register_gantries,
+                sync_modbus_to_gantries,
+                move_gantries,
+                operate_grippers,
+                carry_held_bottles,
+                sync_gantries_to_modbus,
+            )
+                .chain(),
+        );
+    }
+}
@@ -0,0 +1,154 @@
+// guide.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// >>> Constants <<<
+const GUIDE_RAIL_COLOR: Color = Color::srgb(0.7, 0.7, 0.75);
+const CHUTE_COLOR: Color = Color::srgb(0.45, 0.4, 0.35);
+const DEFAULT_THICKNESS: f32 = 5.0; // Width of a polyline's walls
+
+// >>> Components <<<
+/// Fixed rail keeping bottles in line on the belt.
+#[derive(Component)]
+pub struct GuideRail;
+
+/// Fixed walls funnelling bottles or balls onto the line.
+#[derive(Component)]
+pub struct Chute;
+
+/// Outline of a guide rail or chute around its position, before its rotation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GuideShape {
+    /// Solid rectangle of the given width and height.
+    Rectangle { size: [f32; 2] },
+    /// Walls joining the points in order, e.g. the sides of a funnel.
+    Polyline {
+        points: Vec<[f32; 2]>,
+        #[serde(default = "default_thickness")]
+        thickness: f32,
+    },
+}
+
+fn default_thickness() -> f32 {
+    DEFAULT_THICKNESS
+}
+
+impl GuideShape {
+    /// Rectangles making up the shape, as their center, angle and size.
+    fn parts(&self) -> Vec<(Vec2, f32, Vec2)> {
+        match self {
+            GuideShape::Rectangle { size } => vec![(Vec2::ZERO, 0.0, Vec2::from(*size))],
+            GuideShape::Polyline { points, thickness } => points
+                .windows(2)
+                .map(|pair| {
+                    let (start, end) = (Vec2::from(pair[0]), Vec2::from(pair[1]));
+                    let wall = end - start;
+                    // Overlap the walls at the corners so nothing slips between them
+                    let size = Vec2::new(wall.length() + thickness, *thickness);
+                    ((start + end) / 2.0, wall.to_angle(), size)
+                })
+                .collect(),
+        }
+    }
+}
+
+// >>> Spawning <<<
+/// Spawns a guide rail along the belt.
+///
+/// # Parameters
+/// * `shape` - Outline of the rail
+/// * `position` - Position of the rail
+/// * `rotation` - Counter-clockwise rotation, in degrees
+/// * `friction` - Friction of the rail against the bottles sliding along it
+///
+/// # Return
+/// The rail's entity, or `None` if its shape has no walls
+pub fn spawn_guide_rail(
+    commands: &mut Commands,
+    shape: &GuideShape,
+    position: Vec2,
+    rotation: f32,
+    friction: f32,
+) -> Option<Entity> {
+    let rail = spawn_walls(
+        commands,
+        shape,
+        position,
+        rotation,
+        friction,
+        GUIDE_RAIL_COLOR,
+    )?;
+    commands.entity(rail).insert(GuideRail);
+    Some(rail)
+}
+
+/// Spawns a chute funnelling items onto the line.
+///
+/// # Parameters
+/// * `shape` - Outline of the chute
+/// * `position` - Position of the chute
+/// * `rotation` - Counter-clockwise rotation, in degrees
+/// * `friction` - Friction of the chute against the items sliding down it
+///
+/// # Return
+/// The chute's entity, or `None` if its shape has no walls
+pub fn spawn_chute(
+    commands: &mut Commands,
+    shape: &GuideShape,
+    position: Vec2,
+    rotation: f32,
+    friction: f32,
+) -> Option<Entity> {
+    let chute = spawn_walls(commands, shape, position, rotation, friction, CHUTE_COLOR)?;
+    commands.entity(chute).insert(Chute);
+    Some(chute)
+}
+
+/// Spawns a fixed collider with a wall sprite for each part of the shape.
+fn spawn_walls(
+    commands: &mut Commands,
+    shape: &GuideShape,
+    position: Vec2,
+    rotation: f32,
+    friction: f32,
+    color: Color,
+) -> Option<Entity> {
+    let parts = shape.parts();
+    if parts.is_empty() {
+        return None;
+    }
+
+    let collider = Collider::compound(
+        parts
+            .iter()
+            .map(|&(center, angle, size)| {
+                (center, angle, Collider::cuboid(size.x / 2.0, size.y / 2.0))
+            })
+            .collect(),
+    );
+    let walls = commands
+        .spawn((
+            collider,
+            Friction::coefficient(friction),
+            Transform::from_translation(position.extend(0.0))
+                .with_rotation(Quat::from_rotation_z(rotation.to_radians())),
+            Visibility::default(),
+        ))
+        .with_children(|parent| {
+            for (center, angle, size) in parts {
+                parent.spawn((
+                    Sprite::from_color(color, size),
+                    Transform::from_translation(center.extend(0.0))
+                        .with_rotation(Quat::from_rotation_z(angle)),
+                ));
+            }
+        })
+        .id();
+
+    Some(walls)
+}
@@ -0,0 +1,193 @@
+// heater.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    modbus::ModbusState,
+    simulation::{SimRng, SimTime},
+};
+
+// >>> Constants <<<
+const HEATER_SIZE: Vec2 = Vec2::new(50.0, 70.0);
+const HEATER_COLD_COLOR: Color = Color::srgb(0.3, 0.35, 0.45);
+const HEATER_HOT_COLOR: Color = Color::srgb(0.95, 0.3, 0.1);
+const MAX_POWER: u16 = 100; // Percent
+
+// >>> Components <<<
+/// First-order thermal response of a heated vessel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThermalModel {
+    pub ambient: f32,       // °C, approached with the heater off
+    pub gain: f32,          // °C above ambient settled at full power
+    pub time_constant: f32, // Seconds to cover 63% of a step
+    pub noise: f32,         // Peak °C of measurement noise
+}
+
+impl Default for ThermalModel {
+    fn default() -> Self {
+        Self {
+            ambient: 20.0,
+            gain: 180.0,
+            time_constant: 60.0,
+            noise: 0.5,
+        }
+    }
+}
+
+impl ThermalModel {
+    /// Temperature the vessel settles at for a power setting.
+    pub fn steady_state(&self, power: u16) -> f32 {
+        self.ambient + self.gain * power.min(MAX_POWER) as f32 / MAX_POWER as f32
+    }
+}
+
+/// Heater whose power is set by a holding register and whose temperature is
+/// reported in tenths of a degree Celsius.
+#[derive(Component, Debug, Clone)]
+pub struct Heater {
+    pub power_holding_address: u16,
+    pub temperature_input_address: u16,
+    pub model: ThermalModel,
+    pub power: u16, // Percent
+    pub temperature: f32,
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct HeaterBundle {
+    heater: Heater,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl Heater {
+    /// Creates a heater starting at ambient temperature.
+    ///
+    /// # Parameters
+    /// * `power_holding_address` - Holding register setting power in percent
+    /// * `temperature_input_address` - Input register reporting temperature in 0.1 °C
+    /// * `model` - Thermal response of the heated vessel
+    /// * `position` - Position of the heater
+    ///
+    /// # Return
+    /// The heater bundle
+    pub fn new(
+        power_holding_address: u16,
+        temperature_input_address: u16,
+        model: ThermalModel,
+        position: Vec2,
+    ) -> HeaterBundle {
+        HeaterBundle {
+            heater: Heater {
+                power_holding_address,
+                temperature_input_address,
+                temperature: model.ambient,
+                model,
+                power: 0,
+            },
+            sprite: Sprite::from_color(HEATER_COLD_COLOR, HEATER_SIZE),
+            transform: Transform::from_translation(position.extend(0.0)),
+        }
+    }
+
+    /// Temperature in tenths of a degree, as reported over Modbus.
+    pub fn register_value(temperature: f32) -> u16 {
+        (temperature * 10.0).round().clamp(0.0, u16::MAX as f32) as u16
+    }
+}
+
+// >>> Systems <<<
+pub fn register_heaters(heaters: Query<&Heater, Added<Heater>>, modbus_state: Res<ModbusState>) {
+    for heater in heaters.iter() {
+        if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+            holdings.insert(heater.power_holding_address, heater.power);
+        }
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(
+                heater.temperature_input_address,
+                Heater::register_value(heater.temperature),
+            );
+        }
+
+        info!(
+            "Registered heater on power holding register: {:x?}",
+            heater.power_holding_address
+        );
+    }
+}
+
+/// Moves each heater's temperature toward the steady state of its power
+/// setting; with the power off it cools back to ambient.
+pub fn simulate_heaters(time: Res<SimTime>, mut heaters: Query<&mut Heater>) {
+    let delta = time.delta_secs();
+
+    for mut heater in heaters.iter_mut() {
+        let target = heater.model.steady_state(heater.power);
+        let response = 1.0 - (-delta / heater.model.time_constant.max(f32::EPSILON)).exp();
+        heater.temperature += (target - heater.temperature) * response;
+    }
+}
+
+pub fn update_heater_visuals(mut heaters: Query<(&Heater, &mut Sprite), Changed<Heater>>) {
+    for (heater, mut sprite) in heaters.iter_mut() {
+        let heat =
+            (heater.temperature - heater.model.ambient) / heater.model.gain.max(f32::EPSILON);
+        sprite.color = HEATER_COLD_COLOR.mix(&HEATER_HOT_COLOR, heat.clamp(0.0, 1.0));
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_modbus_to_heaters(mut heaters: Query<&mut Heater>, modbus_state: Res<ModbusState>) {
+    if let Ok(holdings) = modbus_state.holding_registers.lock() {
+        for mut heater in heaters.iter_mut() {
+            if let Some(&power) = holdings.get(&heater.power_holding_address) {
+                let power = power.min(MAX_POWER);
+                if heater.power != power {
+                    heater.power = power;
+                    info!("Heater power set to: {}%", power);
+                }
+            }
+        }
+    }
+}
+
+/// Publishes each heater's temperature with measurement noise.
+pub fn sync_heaters_to_modbus(
+    heaters: Query<&Heater>,
+    modbus_state: Res<ModbusState>,
+    mut rng: ResMut<SimRng>,
+) {
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for heater in heaters.iter() {
+            let noise = heater.model.noise.abs();
+            let reading = heater.temperature + rng.random_range(-noise..=noise);
+            inputs.insert(
+                heater.temperature_input_address,
+                Heater::register_value(reading),
+            );
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct HeaterPlugin;
+
+impl Plugin for HeaterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_heater_visuals).add_systems(
+            FixedUpdate,
+            (
+                register_heaters,
+                sync_modbus_to_heaters,
+                simulate_heaters,
+                sync_heaters_to_modbus,
+            )
+                .chain(),
+        );
+    }
+}
@@ -0,0 +1,228 @@
+// hmi.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::window::{WindowRef, WindowResolution};
+
+use super::{
+    arbitration::provenance_line, modbus::ModbusState, overlay::register_line, tags::TagRegistry,
+};
+
+// >>> Constants <<<
+const HMI_WINDOW_TITLE: &str = "ModuSim HMI";
+const HMI_WINDOW_SIZE: Vec2 = Vec2::new(800.0, 600.0);
+const REGISTER_PANEL_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+const REGISTER_PANEL_FONT_SIZE: f32 = 12.0;
+
+// >>> Components <<<
+/// Second OS window showing the HMI panels, apart from the process graphics.
+#[derive(Component)]
+pub struct HmiWindow;
+
+#[derive(Component)]
+pub struct HmiCamera;
+
+/// UI root drawn in the HMI window while it is open, and over the process
+/// graphics otherwise.
+#[derive(Component)]
+pub struct HmiPanel;
+
+/// Panel listing the connected Modbus clients and every tag's live value,
+/// shown in the HMI window only.
+#[derive(Component)]
+pub struct RegisterPanel;
+
+#[derive(Component)]
+pub struct RegisterPanelText;
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct HmiSettings {
+    pub detached: bool, // Whether the HMI window is open
+}
+
+// >>> Systems <<<
+/// Opens or closes the HMI window as the settings change, moving the HMI
+/// panels over to it and back.
+pub fn apply_hmi_window(
+    mut commands: Commands,
+    settings: Res<HmiSettings>,
+    windows: Query<Entity, With<HmiWindow>>,
+    cameras: Query<Entity, With<HmiCamera>>,
+    register_panels: Query<Entity, With<RegisterPanel>>,
+    panels: Query<Entity, (With<HmiPanel>, Without<RegisterPanel>)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if !settings.detached {
+        for entity in windows
+            .iter()
+            .chain(cameras.iter())
+            .chain(register_panels.iter())
+        {
+            commands.entity(entity).despawn();
+        }
+        for panel in panels.iter() {
+            commands.entity(panel).remove::<UiTargetCamera>();
+        }
+        return;
+    }
+    if !windows.is_empty() {
+        return;
+    }
+
+    let window = commands
+        .spawn((
+            HmiWindow,
+            Window {
+                title: HMI_WINDOW_TITLE.to_string(),
+                resolution: WindowResolution::new(HMI_WINDOW_SIZE.x, HMI_WINDOW_SIZE.y),
+                ..default()
+            },
+        ))
+        .id();
+    let camera = commands
+        .spawn((
+            HmiCamera,
+            Camera2d,
+            Camera {
+                target: RenderTarget::Window(WindowRef::Entity(window)),
+                ..default()
+            },
+        ))
+        .id();
+
+    commands
+        .spawn((
+            HmiPanel,
+            RegisterPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(0.0),
+                left: Val::Px(0.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(REGISTER_PANEL_COLOR),
+            UiTargetCamera(camera),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                RegisterPanelText,
+                Text::new(""),
+                TextFont {
+                    font_size: REGISTER_PANEL_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+    for panel in panels.iter() {
+        commands.entity(panel).insert(UiTargetCamera(camera));
+    }
+    info!("Opened HMI window");
+}
+
+/// Moves the HMI panels back over the process graphics when the HMI window
+/// is closed by hand.
+pub fn detect_closed_hmi_window(
+    mut settings: ResMut<HmiSettings>,
+    mut closed: RemovedComponents<HmiWindow>,
+) {
+    if closed.read().count() > 0 && settings.detached {
+        settings.detached = false;
+        info!("Closed HMI window");
+    }
+}
+
+/// Describes the connected Modbus clients, e.g. `127.0.0.1:50312  12s  340 requests`.
+fn client_lines(modbus_state: &ModbusState) -> Vec<String> {
+    let Ok(sessions) = modbus_state.sessions.lock() else {
+        return Vec::new();
+    };
+
+    let limit = sessions
+        .policy()
+        .max_clients
+        .map_or(String::new(), |max_clients| format!("/{max_clients}"));
+    let mut lines = vec![format!("Modbus clients {}{limit}", sessions.len())];
+    if sessions.refused > 0 {
+        lines[0] += &format!(" ({} refused)", sessions.refused);
+    }
+    for session in sessions.iter() {
+        let mut line = format!(
+            "  {}  {}s  {} requests",
+            session.address,
+            session.connected_at.elapsed().as_secs(),
+            session.requests
+        );
+        if session.is_read_only {
+            line += "  read-only";
+        }
+        lines.push(line);
+    }
+    lines.push(String::new());
+    lines
+}
+
+pub fn update_register_panel(
+    tags: Res<TagRegistry>,
+    modbus_state: Res<ModbusState>,
+    mut texts: Query<&mut Text, With<RegisterPanelText>>,
+) {
+    if texts.is_empty() {
+        return;
+    }
+
+    let mut lines = client_lines(&modbus_state);
+    lines.extend(tags.iter().map(|tag| {
+        let mut line = format!("{}  {}", tag.name, register_line(tag, &modbus_state));
+        if let Some(scaled) = tags.scaled_line(tag, &modbus_state) {
+            line += &format!(" ({scaled})");
+        }
+        match provenance_line(tag, &modbus_state) {
+            Some(provenance) => format!("{line}  {provenance}"),
+            None => line,
+        }
+    }));
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+
+// >>> Input System <<<
+pub fn handle_hmi_input(mut settings: ResMut<HmiSettings>, keyboard: Res<ButtonInput<KeyCode>>) {
+    if keyboard.just_pressed(KeyCode::F8) {
+        settings.detached = !settings.detached;
+    }
+}
+
+// >>> Plugin <<<
+/// Detachable HMI window, toggled with F8, taking the HMI panels and a live
+/// register list off the process graphics for a second monitor.
+#[derive(Default)]
+pub struct HmiPlugin {
+    pub detached: bool, // Open the HMI window on startup
+}
+
+impl Plugin for HmiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HmiSettings {
+            detached: self.detached,
+        })
+        .add_systems(
+            Update,
+            (
+                handle_hmi_input,
+                detect_closed_hmi_window,
+                apply_hmi_window,
+                update_register_panel,
+            )
+                .chain(),
+        );
+    }
+}
@@ -0,0 +1,265 @@
+// hopper.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    alarm::{Alarm, AlarmEvent},
+    modbus::ModbusState,
+    simulation::SimTime,
+    valve::{BallPool, spawn_ball},
+};
+
+// >>> Constants <<<
+const HOPPER_SIZE: Vec2 = Vec2::new(60.0, 40.0);
+const HOPPER_COLOR: Color = Color::srgb(0.5, 0.5, 0.55);
+const HOPPER_EMPTY_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+pub const CAP_WIDTH: f32 = 40.0;
+pub const CAP_HEIGHT: f32 = 10.0;
+pub const CAP_COLOR: Color = Color::srgb(0.2, 0.4, 0.9);
+
+// >>> Components <<<
+#[derive(Component)]
+pub struct Cap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HopperPart {
+    Ball,
+    Cap,
+}
+
+#[derive(Component)]
+pub struct Hopper {
+    pub part: HopperPart,
+    pub gate_coil_address: u16,
+    pub refill_coil_address: u16,
+    pub low_level_discrete_address: u16,
+    pub empty_discrete_address: u16,
+    pub capacity: u32,
+    pub low_level: u32,
+    pub inventory: u32,
+    pub is_gate_open: bool,
+    release_timer: Timer,
+}
+
+impl Hopper {
+    pub fn is_low(&self) -> bool {
+        self.inventory <= self.low_level
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inventory == 0
+    }
+
+    pub fn refill(&mut self) {
+        self.inventory = self.capacity;
+    }
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct HopperBundle {
+    hopper: Hopper,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl Hopper {
+    /// Creates a gravity-fed hopper holding a finite supply of parts.
+    ///
+    /// # Parameters
+    /// * `part` - Kind of part released by the hopper
+    /// * `gate_coil_address` - Coil opening the release gate
+    /// * `refill_coil_address` - Command coil refilling the hopper (auto-resets)
+    /// * `low_level_discrete_address` - Discrete input set at or below `low_level`
+    /// * `empty_discrete_address` - Discrete input set when the hopper is empty
+    /// * `capacity` - Parts held when full
+    /// * `low_level` - Inventory at which the low-level input is raised
+    /// * `release_interval` - Seconds between parts while the gate is open
+    /// * `position` - Position of the hopper
+    ///
+    /// # Return
+    /// The hopper bundle
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        part: HopperPart,
+        gate_coil_address: u16,
+        refill_coil_address: u16,
+        low_level_discrete_address: u16,
+        empty_discrete_address: u16,
+        capacity: u32,
+        low_level: u32,
+        release_interval: f32,
+        position: Vec2,
+    ) -> HopperBundle {
+        HopperBundle {
+            hopper: Hopper {
+                part,
+                gate_coil_address,
+                refill_coil_address,
+                low_level_discrete_address,
+                empty_discrete_address,
+                capacity,
+                low_level,
+                inventory: capacity,
+                is_gate_open: false,
+                release_timer: Timer::from_seconds(release_interval, TimerMode::Repeating),
+            },
+            sprite: Sprite::from_color(HOPPER_COLOR, HOPPER_SIZE),
+            transform: Transform::from_translation(position.extend(0.0)),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_hoppers(query: Query<&Hopper, Added<Hopper>>, modbus_state: Res<ModbusState>) {
+    for hopper in query.iter() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(hopper.gate_coil_address, hopper.is_gate_open);
+            coils.insert(hopper.refill_coil_address, false);
+        }
+
+        info!(
+            "Registered {:?} hopper on gate coil: {:x?}",
+            hopper.part, hopper.gate_coil_address
+        );
+    }
+}
+
+pub fn handle_hopper_input(mut hoppers: Query<&mut Hopper>, keyboard: Res<ButtonInput<KeyCode>>) {
+    if keyboard.just_pressed(KeyCode::KeyH) {
+        for mut hopper in hoppers.iter_mut() {
+            hopper.refill();
+            info!("{:?} hopper refilled to {}", hopper.part, hopper.inventory);
+        }
+    }
+}
+
+pub fn release_parts(
+    time: Res<SimTime>,
+    mut commands: Commands,
+    mut hoppers: Query<(&mut Hopper, &Transform)>,
+    mut ball_pool: ResMut<BallPool>,
+) {
+    for (mut hopper, transform) in hoppers.iter_mut() {
+        if !hopper.is_gate_open || hopper.is_empty() {
+            continue;
+        }
+
+        hopper.release_timer.tick(time.delta());
+        if !hopper.release_timer.just_finished() {
+            continue;
+        }
+
+        let position = transform.translation.truncate() - Vec2::new(0.0, HOPPER_SIZE.y);
+        match hopper.part {
+            HopperPart::Ball => {
+                spawn_ball(&mut commands, &mut ball_pool, position);
+            }
+            HopperPart::Cap => spawn_cap(&mut commands, position),
+        }
+
+        hopper.inventory -= 1;
+        if hopper.is_empty() {
+            warn!("{:?} hopper is empty!", hopper.part);
+        }
+    }
+}
+
+pub fn spawn_cap(commands: &mut Commands, position: Vec2) {
+    commands.spawn((
+        RigidBody::Dynamic,
+        Collider::cuboid(CAP_WIDTH / 2.0, CAP_HEIGHT / 2.0),
+        Sprite::from_color(CAP_COLOR, Vec2::new(CAP_WIDTH, CAP_HEIGHT)),
+        Transform::from_translation(position.extend(0.0)),
+        Cap,
+    ));
+}
+
+pub fn monitor_hopper_alarms(
+    hoppers: Query<&Hopper>,
+    changed: Query<(), Changed<Hopper>>,
+    mut alarm_events: EventWriter<AlarmEvent>,
+    mut was_empty: Local<bool>,
+) {
+    if changed.is_empty() {
+        return;
+    }
+
+    let is_empty = hoppers.iter().any(|hopper| hopper.is_empty());
+    if *was_empty != is_empty {
+        *was_empty = is_empty;
+        alarm_events.write(if is_empty {
+            AlarmEvent::Raise(Alarm::HopperEmpty)
+        } else {
+            AlarmEvent::Clear(Alarm::HopperEmpty)
+        });
+    }
+}
+
+pub fn update_hopper_visuals(mut hoppers: Query<(&Hopper, &mut Sprite), Changed<Hopper>>) {
+    for (hopper, mut sprite) in hoppers.iter_mut() {
+        sprite.color = if hopper.is_empty() {
+            HOPPER_EMPTY_COLOR
+        } else {
+            HOPPER_COLOR
+        };
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_hoppers_to_modbus(
+    hoppers: Query<&Hopper, Changed<Hopper>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+        for hopper in hoppers.iter() {
+            discretes.insert(hopper.low_level_discrete_address, hopper.is_low());
+            discretes.insert(hopper.empty_discrete_address, hopper.is_empty());
+        }
+    }
+}
+
+pub fn sync_modbus_to_hoppers(mut hoppers: Query<&mut Hopper>, modbus_state: Res<ModbusState>) {
+    if let Ok(mut coils) = modbus_state.coils.lock() {
+        for mut hopper in hoppers.iter_mut() {
+            if let Some(&coil_state) = coils.get(&hopper.gate_coil_address) {
+                if hopper.is_gate_open != coil_state {
+                    hopper.is_gate_open = coil_state;
+                    info!("{:?} hopper gate set to: {}", hopper.part, coil_state);
+                }
+            }
+
+            // Refill is a command coil and resets itself once handled
+            if coils.get(&hopper.refill_coil_address) == Some(&true) {
+                coils.insert(hopper.refill_coil_address, false);
+                hopper.refill();
+                info!("{:?} hopper refilled to {}", hopper.part, hopper.inventory);
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct HopperPlugin;
+
+impl Plugin for HopperPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (handle_hopper_input, update_hopper_visuals))
+            .add_systems(
+                FixedUpdate,
+                (
+                    register_hoppers,
+                    sync_modbus_to_hoppers,
+                    release_parts,
+                    sync_hoppers_to_modbus,
+                    monitor_hopper_alarms,
+                )
+                    .chain(),
+            );
+    }
+}
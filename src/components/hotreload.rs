@@ -0,0 +1,81 @@
+// hotreload.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use bevy::prelude::*;
+
+use crate::scenario::{ReloadScenario, Scenario};
+
+// >>> Constants <<<
+const POLL_INTERVAL: f32 = 0.5; // Seconds between checks of the file's modification time
+
+// >>> Resources <<<
+/// Scenario file being watched for edits.
+#[derive(Resource)]
+pub struct ScenarioWatcher {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+    timer: Timer,
+}
+
+impl ScenarioWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = Self::modified_time(&path);
+        Self {
+            path,
+            modified,
+            timer: Timer::from_seconds(POLL_INTERVAL, TimerMode::Repeating),
+        }
+    }
+
+    fn modified_time(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+}
+
+// >>> Systems <<<
+/// Reloads the scenario whenever its file is saved. Polls on real time, so a
+/// slowed-down simulation does not delay reloads.
+pub fn watch_scenario_file(
+    time: Res<Time<Real>>,
+    mut watcher: ResMut<ScenarioWatcher>,
+    mut reload_events: EventWriter<ReloadScenario>,
+) {
+    if !watcher.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let modified = ScenarioWatcher::modified_time(&watcher.path);
+    if modified.is_none() || modified == watcher.modified {
+        return;
+    }
+    watcher.modified = modified;
+
+    // Editors may save a file halfway; keep the running plant until it parses
+    match Scenario::from_toml(&watcher.path) {
+        Ok(scenario) => {
+            reload_events.write(ReloadScenario(scenario));
+        }
+        Err(err) => warn!("Not reloading {}: {err:#}", watcher.path.display()),
+    }
+}
+
+// >>> Plugin <<<
+/// Watches a scenario file and applies each saved edit to the running plant,
+/// so lesson layouts can be tuned without restarting or reconnecting the PLC.
+pub struct HotReloadPlugin {
+    pub path: PathBuf,
+}
+
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ScenarioWatcher::new(self.path.clone()))
+            .add_event::<ReloadScenario>()
+            .add_systems(Update, watch_scenario_file);
+    }
+}
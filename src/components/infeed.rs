@@ -0,0 +1,183 @@
+// infeed.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    bottle::{BottlePool, BottleSpawner, ProductSelection, spawn_infeed_bottle},
+    conveyor::{Conveyor, ConveyorShape, belt_infeed},
+    modbus::ModbusState,
+    station::StationId,
+};
+
+// >>> Components <<<
+/// PLC control of the bottle infeed of the conveyor it is attached to, apart
+/// from the conveyor's run coil.
+///
+/// Turning `spawn_coil_address` on puts one bottle on the infeed and resets
+/// the coil. `enable_coil_address` turns timed spawning on or off, and
+/// `interval_holding_address` sets the time between timed bottles in ms; a
+/// zero interval is rejected and bounced back to the current one.
+#[derive(Component, Debug, Clone)]
+pub struct InfeedControl {
+    pub spawn_coil_address: u16,
+    pub enable_coil_address: u16,
+    pub interval_holding_address: u16,
+    pub is_enabled: bool, // Last value exchanged with the enable coil
+    pub interval: u16,    // Last value exchanged with the interval register, ms
+}
+
+impl InfeedControl {
+    /// Creates an infeed control for an enabled infeed.
+    ///
+    /// # Parameters
+    /// * `spawn_coil_address` - Coil that spawns one bottle, reset once it has
+    /// * `enable_coil_address` - Coil that turns timed spawning on or off
+    /// * `interval_holding_address` - Holding register holding the spawn interval in ms
+    ///
+    /// # Return
+    /// The infeed control component
+    pub fn new(
+        spawn_coil_address: u16,
+        enable_coil_address: u16,
+        interval_holding_address: u16,
+    ) -> Self {
+        Self {
+            spawn_coil_address,
+            enable_coil_address,
+            interval_holding_address,
+            is_enabled: true,
+            interval: 0,
+        }
+    }
+}
+
+fn interval_millis(bottle_spawner: &BottleSpawner) -> u16 {
+    (bottle_spawner.interval() * 1000.0)
+        .round()
+        .clamp(1.0, u16::MAX as f32) as u16
+}
+
+// >>> Systems <<<
+pub fn register_infeed_controls(
+    mut controls: Query<(&mut InfeedControl, &BottleSpawner), Added<InfeedControl>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for (mut control, bottle_spawner) in controls.iter_mut() {
+        control.is_enabled = bottle_spawner.is_enabled;
+        control.interval = interval_millis(bottle_spawner);
+
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(control.spawn_coil_address, false);
+            coils.insert(control.enable_coil_address, control.is_enabled);
+        }
+        if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+            holdings.insert(control.interval_holding_address, control.interval);
+        }
+
+        info!(
+            "Registered infeed control on spawn coil: {:x?}",
+            control.spawn_coil_address
+        );
+    }
+}
+
+// >>> Modbus Synchronization <<<
+/// Publishes infeed settings changed locally, e.g. by a recipe or a reset.
+pub fn sync_infeed_to_modbus(
+    mut controls: Query<(&mut InfeedControl, &BottleSpawner)>,
+    modbus_state: Res<ModbusState>,
+) {
+    for (mut control, bottle_spawner) in controls.iter_mut() {
+        if control.is_enabled != bottle_spawner.is_enabled {
+            control.is_enabled = bottle_spawner.is_enabled;
+            if let Ok(mut coils) = modbus_state.coils.lock() {
+                coils.insert(control.enable_coil_address, control.is_enabled);
+            }
+        }
+
+        let interval = interval_millis(bottle_spawner);
+        if control.interval != interval {
+            control.interval = interval;
+            if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+                holdings.insert(control.interval_holding_address, interval);
+            }
+        }
+    }
+}
+
+pub fn sync_modbus_to_infeed(
+    mut commands: Commands,
+    mut controls: Query<
+        (
+            &mut InfeedControl,
+            &mut BottleSpawner,
+            &Transform,
+            &Sprite,
+            &ConveyorShape,
+            &StationId,
+        ),
+        With<Conveyor>,
+    >,
+    modbus_state: Res<ModbusState>,
+    product_selection: Res<ProductSelection>,
+    mut pool: ResMut<BottlePool>,
+) {
+    for (mut control, mut bottle_spawner, transform, sprite, shape, station) in controls.iter_mut()
+    {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            if let Some(&coil_state) = coils.get(&control.enable_coil_address) {
+                if control.is_enabled != coil_state {
+                    control.is_enabled = coil_state;
+                    bottle_spawner.is_enabled = coil_state;
+                    info!("Infeed enabled set to: {}", coil_state);
+                }
+            }
+
+            if coils.get(&control.spawn_coil_address) == Some(&true) {
+                coils.insert(control.spawn_coil_address, false);
+                spawn_infeed_bottle(
+                    &mut commands,
+                    &mut pool,
+                    belt_infeed(transform, sprite, shape),
+                    *station,
+                    product_selection.selected_spec(),
+                );
+                info!("Spawned a bottle on command");
+            }
+        }
+
+        if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+            if let Some(&holding_state) = holdings.get(&control.interval_holding_address) {
+                if control.interval != holding_state {
+                    if holding_state == 0 {
+                        holdings.insert(control.interval_holding_address, control.interval);
+                    } else {
+                        control.interval = holding_state;
+                        bottle_spawner.set_interval(holding_state as f32 / 1000.0);
+                        info!("Infeed interval set to: {} ms", holding_state);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Spawn command, spawn interval and enable registers of the bottle infeeds.
+pub struct InfeedPlugin;
+
+impl Plugin for InfeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_infeed_controls,
+                sync_infeed_to_modbus,
+                sync_modbus_to_infeed,
+            )
+                .chain(),
+        );
+    }
+}
@@ -0,0 +1,399 @@
+// inspector.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use bevy::ecs::world::{EntityMut, EntityRef};
+use bevy::prelude::*;
+use bevy::reflect::{GetPath, ReflectRef, TypeRegistry};
+
+use super::{gallery::AppState, tags::DeviceName};
+
+// >>> Constants <<<
+const INSPECTOR_COLOR: Color = Color::srgba(0.1, 0.15, 0.1, 0.9);
+const INSPECTOR_FONT_SIZE: f32 = 12.0;
+// Components derived from others every frame, so editing them does nothing
+const DERIVED_COMPONENTS: [&str; 3] = ["GlobalTransform", "InheritedVisibility", "ViewVisibility"];
+
+// >>> Component Fields <<<
+/// A number or flag of one of an entity's reflected components, by its path,
+/// e.g. `Transform.translation.x` or `FillLevel.0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InspectedField {
+    pub path: String,
+    pub value: String,
+    pub is_read_only: bool, // Addresses, which only a scenario load registers
+}
+
+/// Lists the numbers and flags of an entity's components registered for
+/// reflection, sorted by component.
+pub fn inspect(registry: &TypeRegistry, entity: EntityRef) -> Vec<InspectedField> {
+    let mut components: Vec<(&str, &dyn Reflect)> = registry
+        .iter()
+        .filter(|registration| {
+            let name = registration.type_info().type_path_table().short_path();
+            !DERIVED_COMPONENTS.contains(&name)
+        })
+        .filter_map(|registration| {
+            let component = registration.data::<ReflectComponent>()?.reflect(entity)?;
+            Some((
+                registration.type_info().type_path_table().short_path(),
+                component,
+            ))
+        })
+        .collect();
+    components.sort_by_key(|(name, _)| *name);
+
+    let mut fields = Vec::new();
+    for (name, component) in components {
+        collect_fields(name, component.as_partial_reflect(), &mut fields);
+    }
+    fields
+}
+
+/// Steps one of an entity's fields in place: a number by a step in proportion
+/// to its size, or a flag by toggling it.
+///
+/// # Parameters
+/// * `registry` - The app's type registry
+/// * `entity` - The inspected entity
+/// * `path` - Path of the field, as listed by [`inspect`]
+/// * `steps` - Steps up, or down if negative
+///
+/// # Return
+/// Why the field could not be stepped, if it could not
+pub fn adjust(
+    registry: &TypeRegistry,
+    mut entity: EntityMut,
+    path: &str,
+    steps: i64,
+) -> anyhow::Result<()> {
+    if path.ends_with("_address") {
+        anyhow::bail!("Addresses are only changed by loading a scenario");
+    }
+    let (name, field_path) = path
+        .split_once('.')
+        .with_context(|| format!("No field {path}"))?;
+    let reflect_component = registry
+        .iter()
+        .filter(|registration| registration.type_info().type_path_table().short_path() == name)
+        .find_map(|registration| registration.data::<ReflectComponent>())
+        .with_context(|| format!("No component {name}"))?;
+    let mut component = reflect_component
+        .reflect_mut(entity.reborrow())
+        .with_context(|| format!("No component {name}"))?;
+    let field = component
+        .reflect_path_mut(format!(".{field_path}").as_str())
+        .map_err(|err| anyhow!("No field {path}: {err}"))?;
+    step_field(field, steps).with_context(|| format!("Field {path} not stepped"))
+}
+
+fn collect_fields(path: &str, value: &dyn PartialReflect, fields: &mut Vec<InspectedField>) {
+    // Rotations only make sense stepped as a whole
+    if value.try_downcast_ref::<Quat>().is_some() {
+        return;
+    }
+    if let Some(text) = leaf_value(value) {
+        fields.push(InspectedField {
+            path: path.to_string(),
+            value: text,
+            is_read_only: path.ends_with("_address"),
+        });
+        return;
+    }
+
+    match value.reflect_ref() {
+        ReflectRef::Struct(value) => {
+            for i in 0..value.field_len() {
+                if let (Some(name), Some(field)) = (value.name_at(i), value.field_at(i)) {
+                    collect_fields(&format!("{path}.{name}"), field, fields);
+                }
+            }
+        }
+        ReflectRef::TupleStruct(value) => {
+            for i in 0..value.field_len() {
+                if let Some(field) = value.field(i) {
+                    collect_fields(&format!("{path}.{i}"), field, fields);
+                }
+            }
+        }
+        ReflectRef::Tuple(value) => {
+            for i in 0..value.field_len() {
+                if let Some(field) = value.field(i) {
+                    collect_fields(&format!("{path}.{i}"), field, fields);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The value of a number or flag, or `None` for anything else.
+fn leaf_value(value: &dyn PartialReflect) -> Option<String> {
+    let float = |value: f64| format!("{value:.3}");
+    value
+        .try_downcast_ref::<f32>()
+        .map(|value| float(*value as f64))
+        .or_else(|| value.try_downcast_ref::<f64>().map(|value| float(*value)))
+        .or_else(|| {
+            let value = value.try_downcast_ref::<Duration>()?;
+            Some(format!("{}s", float(value.as_secs_f64())))
+        })
+        .or_else(|| value.try_downcast_ref::<bool>().map(bool::to_string))
+        .or_else(|| value.try_downcast_ref::<u8>().map(u8::to_string))
+        .or_else(|| value.try_downcast_ref::<u16>().map(u16::to_string))
+        .or_else(|| value.try_downcast_ref::<u32>().map(u32::to_string))
+        .or_else(|| value.try_downcast_ref::<u64>().map(u64::to_string))
+        .or_else(|| value.try_downcast_ref::<usize>().map(usize::to_string))
+        .or_else(|| value.try_downcast_ref::<i32>().map(i32::to_string))
+        .or_else(|| value.try_downcast_ref::<i64>().map(i64::to_string))
+}
+
+fn step_field(value: &mut dyn PartialReflect, steps: i64) -> anyhow::Result<()> {
+    if let Some(value) = value.try_downcast_mut::<f32>() {
+        *value += steps as f32 * step(*value as f64) as f32;
+    } else if let Some(value) = value.try_downcast_mut::<f64>() {
+        *value += steps as f64 * step(*value);
+    } else if let Some(value) = value.try_downcast_mut::<Duration>() {
+        let seconds = value.as_secs_f64();
+        *value = Duration::try_from_secs_f64(seconds + steps as f64 * step(seconds))?;
+    } else if let Some(value) = value.try_downcast_mut::<bool>() {
+        *value ^= steps % 2 != 0;
+    } else if let Some(value) = value.try_downcast_mut::<u8>() {
+        step_integer(value, steps)?;
+    } else if let Some(value) = value.try_downcast_mut::<u16>() {
+        step_integer(value, steps)?;
+    } else if let Some(value) = value.try_downcast_mut::<u32>() {
+        step_integer(value, steps)?;
+    } else if let Some(value) = value.try_downcast_mut::<u64>() {
+        step_integer(value, steps)?;
+    } else if let Some(value) = value.try_downcast_mut::<usize>() {
+        step_integer(value, steps)?;
+    } else if let Some(value) = value.try_downcast_mut::<i32>() {
+        step_integer(value, steps)?;
+    } else if let Some(value) = value.try_downcast_mut::<i64>() {
+        step_integer(value, steps)?;
+    } else {
+        anyhow::bail!("Not a number or flag");
+    }
+    Ok(())
+}
+
+/// Steps an integer by whole units, refusing to leave its type's range, e.g.
+/// a count going negative.
+fn step_integer<T: Copy + TryFrom<i64> + TryInto<i64>>(
+    value: &mut T,
+    steps: i64,
+) -> anyhow::Result<()> {
+    let current: i64 = (*value)
+        .try_into()
+        .map_err(|_| anyhow!("Value out of range"))?;
+    *value = current
+        .checked_add(steps)
+        .and_then(|stepped| T::try_from(stepped).ok())
+        .context("Value out of range")?;
+    Ok(())
+}
+
+/// A tenth of the value's order of magnitude, e.g. 10 for 960 and 0.1 for 2.
+fn step(value: f64) -> f64 {
+    if value == 0.0 {
+        return 0.1;
+    }
+    10f64.powf(value.abs().log10().floor() - 1.0).max(0.01)
+}
+
+// >>> Components <<<
+#[derive(Component)]
+pub struct InspectorPanel;
+
+#[derive(Component)]
+pub struct InspectorText;
+
+// >>> Resources <<<
+/// Entity last middle-clicked and the field selected for editing.
+#[derive(Resource, Default)]
+pub struct Inspector {
+    pub entity: Option<Entity>,
+    pub selected: usize,
+}
+
+// >>> Systems <<<
+pub fn setup_inspector_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            InspectorPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(40.0),
+                left: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(INSPECTOR_COLOR),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                InspectorText,
+                Text::new(""),
+                TextFont {
+                    font_size: INSPECTOR_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// Middle-clicking an entity inspects it, or the entity it is part of for a
+/// part such as a bottle's sprite or a curved conveyor's belt segment.
+pub fn handle_inspector_clicks(
+    mut click_events: EventReader<Pointer<Click>>,
+    parents: Query<&ChildOf>,
+    mut inspector: ResMut<Inspector>,
+) {
+    for click in click_events.read() {
+        if click.button != PointerButton::Middle {
+            continue;
+        }
+
+        let mut entity = click.target;
+        while let Ok(child_of) = parents.get(entity) {
+            entity = child_of.parent();
+        }
+        info!("Inspecting {entity}");
+        inspector.entity = Some(entity);
+        inspector.selected = 0;
+    }
+}
+
+/// Shows the inspected entity's fields as they change, marking the selected
+/// one.
+pub fn update_inspector_panel(
+    inspector: Res<Inspector>,
+    registry: Res<AppTypeRegistry>,
+    entities: Query<EntityRef, (Without<InspectorPanel>, Without<InspectorText>)>,
+    mut panels: Query<&mut Visibility, With<InspectorPanel>>,
+    mut texts: Query<&mut Text, With<InspectorText>>,
+) {
+    // The inspected entity may have been despawned, e.g. a bottle leaving the line
+    let Some(entity) = inspector
+        .entity
+        .and_then(|entity| entities.get(entity).ok())
+    else {
+        for mut visibility in panels.iter_mut() {
+            visibility.set_if_neq(Visibility::Hidden);
+        }
+        return;
+    };
+
+    let title = match entity.get::<DeviceName>() {
+        Some(name) => format!("{} ({})", name.0, entity.id()),
+        None => entity.id().to_string(),
+    };
+    let mut lines = vec![title];
+    for (i, field) in inspect(&registry.read(), entity).iter().enumerate() {
+        let marker = if i == inspector.selected { ">" } else { " " };
+        let lock = if field.is_read_only {
+            " (read-only)"
+        } else {
+            ""
+        };
+        lines.push(format!("{marker} {} = {}{lock}", field.path, field.value));
+    }
+    let contents = lines.join("\n");
+    for mut text in texts.iter_mut() {
+        if text.0 != contents {
+            text.0.clone_from(&contents);
+        }
+    }
+    for mut visibility in panels.iter_mut() {
+        visibility.set_if_neq(Visibility::Visible);
+    }
+}
+
+// >>> Input System <<<
+/// Tab selects the next field (Shift+Tab the one before), `+` and `-` step
+/// it in place (ten steps with Ctrl) and Escape closes the inspector. Keys
+/// are left to the exercise menu while it is open.
+pub fn handle_inspector_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    app_state: Option<Res<State<AppState>>>,
+    mut inspector: ResMut<Inspector>,
+    registry: Res<AppTypeRegistry>,
+    mut entities: Query<EntityMut>,
+) {
+    if app_state.is_some_and(|state| *state.get() == AppState::Menu) {
+        return;
+    }
+    let Some(mut entity) = inspector
+        .entity
+        .and_then(|entity| entities.get_mut(entity).ok())
+    else {
+        return;
+    };
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        inspector.entity = None;
+        return;
+    }
+
+    let registry = registry.read();
+    let fields = inspect(&registry, entity.as_readonly());
+    if fields.is_empty() {
+        return;
+    }
+    let is_shifted = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if keyboard.just_pressed(KeyCode::Tab) {
+        inspector.selected = if is_shifted {
+            (inspector.selected + fields.len() - 1) % fields.len()
+        } else {
+            (inspector.selected + 1) % fields.len()
+        };
+    }
+
+    let direction = if keyboard.any_just_pressed([KeyCode::Equal, KeyCode::NumpadAdd]) {
+        1
+    } else if keyboard.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        -1
+    } else {
+        return;
+    };
+    let is_coarse = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let steps = if is_coarse { direction * 10 } else { direction };
+
+    let Some(field) = fields.get(inspector.selected) else {
+        return;
+    };
+    match adjust(&registry, entity.reborrow(), &field.path, steps) {
+        Ok(()) => info!("Inspector stepped {} of {}", field.path, entity.id()),
+        Err(err) => warn!("Not applying {}: {err:#}", field.path),
+    }
+}
+
+// >>> Plugin <<<
+/// Live property inspector: middle-click any entity, a device or a bottle or
+/// ball on the line, to show its components' numbers and flags and step its
+/// sizes, speeds and timers in place.
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inspector>()
+            .add_event::<Pointer<Click>>()
+            .add_systems(Startup, setup_inspector_panel)
+            .add_systems(
+                Update,
+                (
+                    handle_inspector_clicks,
+                    handle_inspector_input,
+                    update_inspector_panel,
+                )
+                    .chain(),
+            );
+    }
+}
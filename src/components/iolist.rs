@@ -0,0 +1,241 @@
+// iolist.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use bevy::prelude::*;
+
+use super::tags::{DeviceName, RegisterMap, RegisterType, Scaling, Tag, TagRegistry};
+
+// >>> Constants <<<
+const IO_LIST_PATH: &str = "io_list.csv";
+
+// >>> I/O List <<<
+/// One row of the I/O list handed to whoever programs the PLC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IoListEntry {
+    pub device: String,
+    pub tag: Tag,
+    pub scaling: Option<Scaling>,
+    pub register_type: RegisterType,
+}
+
+impl IoListEntry {
+    /// Value type as the PLC sees it.
+    pub fn data_type(&self) -> &'static str {
+        if self.tag.kind.is_bit() {
+            "bool"
+        } else {
+            self.register_type.description()
+        }
+    }
+
+    /// Direction from the PLC's point of view.
+    pub fn direction(&self) -> &'static str {
+        if self.tag.kind.is_writable() {
+            "write"
+        } else {
+            "read"
+        }
+    }
+
+    fn fields(&self) -> [String; 9] {
+        let (scale, offset, unit) = match &self.scaling {
+            Some(scaling) => (
+                scaling.scale.to_string(),
+                scaling.offset.to_string(),
+                scaling.unit.clone(),
+            ),
+            None => Default::default(),
+        };
+        [
+            self.device.clone(),
+            self.tag.name.clone(),
+            self.tag.kind.description().to_string(),
+            self.tag.address.to_string(),
+            self.data_type().to_string(),
+            self.direction().to_string(),
+            scale,
+            offset,
+            unit,
+        ]
+    }
+}
+
+const HEADER: [&str; 9] = [
+    "Device",
+    "Tag",
+    "Table",
+    "Address",
+    "Type",
+    "Direction",
+    "Scale",
+    "Offset",
+    "Unit",
+];
+
+/// File format of an exported I/O list, chosen by the file's extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoListFormat {
+    Csv,
+    Markdown,
+}
+
+impl IoListFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("md" | "markdown") => IoListFormat::Markdown,
+            _ => IoListFormat::Csv,
+        }
+    }
+}
+
+/// Lists every registered tag with the device it belongs to, sorted by table
+/// and address.
+///
+/// Tags not backing a device, such as the alarm word, are listed under their
+/// name's first part, e.g. `alarm`.
+pub fn io_list<'a>(
+    tags: &TagRegistry,
+    devices: impl Iterator<Item = (&'a DeviceName, &'a RegisterMap)>,
+) -> Vec<IoListEntry> {
+    let owners: HashMap<&str, &str> = devices
+        .flat_map(|(DeviceName(device), RegisterMap(map))| {
+            map.iter()
+                .map(move |tag| (tag.name.as_str(), device.as_str()))
+        })
+        .collect();
+
+    let mut entries: Vec<IoListEntry> = tags
+        .iter()
+        .map(|tag| {
+            let device = owners.get(tag.name.as_str()).copied().unwrap_or_else(|| {
+                tag.name
+                    .split_once('.')
+                    .map_or(tag.name.as_str(), |(prefix, _)| prefix)
+            });
+            IoListEntry {
+                device: device.to_string(),
+                tag: tag.clone(),
+                scaling: tags.scaling(tag.kind, tag.address).cloned(),
+                register_type: tags.register_type(tag.kind, tag.address),
+            }
+        })
+        .collect();
+    entries.sort_by_key(|entry| (entry.tag.kind, entry.tag.address));
+    entries
+}
+
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Formats the I/O list as CSV with a header row.
+pub fn to_csv(entries: &[IoListEntry]) -> String {
+    let mut csv = HEADER.join(",") + "\n";
+    for entry in entries {
+        let fields = entry.fields().map(|field| csv_field(&field));
+        csv += &(fields.join(",") + "\n");
+    }
+    csv
+}
+
+/// Formats the I/O list as a Markdown table.
+pub fn to_markdown(entries: &[IoListEntry]) -> String {
+    let mut markdown = format!("| {} |\n", HEADER.join(" | "));
+    markdown += &format!("|{}\n", " --- |".repeat(HEADER.len()));
+    for entry in entries {
+        let fields = entry.fields().map(|field| field.replace('|', "\\|"));
+        markdown += &format!("| {} |\n", fields.join(" | "));
+    }
+    markdown
+}
+
+fn write_io_list(path: &Path, entries: &[IoListEntry]) -> anyhow::Result<()> {
+    let contents = match IoListFormat::from_path(path) {
+        IoListFormat::Csv => to_csv(entries),
+        IoListFormat::Markdown => to_markdown(entries),
+    };
+    std::fs::write(path, contents).with_context(|| format!("writing {}", path.display()))
+}
+
+// >>> Events <<<
+/// Writes the loaded scenario's I/O list to a file.
+#[derive(Event)]
+pub struct ExportIoList(pub PathBuf);
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct IoListSettings {
+    pub path: PathBuf, // Used by the keyboard shortcut
+}
+
+// >>> Systems <<<
+pub fn handle_io_list_input(
+    settings: Res<IoListSettings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut export_events: EventWriter<ExportIoList>,
+) {
+    if keyboard.just_pressed(KeyCode::F3) {
+        export_events.write(ExportIoList(settings.path.clone()));
+    }
+}
+
+pub fn export_io_list(
+    mut export_events: EventReader<ExportIoList>,
+    tags: Res<TagRegistry>,
+    devices: Query<(&DeviceName, &RegisterMap)>,
+) {
+    for ExportIoList(path) in export_events.read() {
+        let entries = io_list(&tags, devices.iter());
+        match write_io_list(path, &entries) {
+            Ok(()) => info!("Exported {} tags to {}", entries.len(), path.display()),
+            Err(err) => warn!("Failed to export I/O list: {err:#}"),
+        }
+    }
+}
+
+pub fn export_io_list_on_start(
+    settings: Res<IoListSettings>,
+    mut export_events: EventWriter<ExportIoList>,
+) {
+    export_events.write(ExportIoList(settings.path.clone()));
+}
+
+// >>> Plugin <<<
+/// Exports the loaded scenario's tags, addresses and directions as an I/O
+/// list, to CSV or to a Markdown table for a `.md` path. F3 exports to `path`.
+pub struct IoListPlugin {
+    pub path: PathBuf,
+    pub export_on_start: bool,
+}
+
+impl Default for IoListPlugin {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from(IO_LIST_PATH),
+            export_on_start: false,
+        }
+    }
+}
+
+impl Plugin for IoListPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(IoListSettings {
+            path: self.path.clone(),
+        })
+        .add_event::<ExportIoList>()
+        .add_systems(Update, (handle_io_list_input, export_io_list).chain());
+
+        if self.export_on_start {
+            app.add_systems(PostStartup, export_io_list_on_start);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+// jam.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    accumulation::Accumulated,
+    alarm::{Alarm, AlarmEvent},
+    bottle::{Bottle, BottlePool, BottlePosition, BottleSpec},
+    conveyor::{ConveyorShape, ConveyorState, stands_on_belt},
+    diverter::{Diverted, Sorted},
+    gantry::Handled,
+    modbus::ModbusState,
+    simulation::SimTime,
+    station::StationId,
+    stopper::Stopped,
+    valve::{ValvePosition, ValveState},
+};
+
+// >>> Constants <<<
+const DEFAULT_STALL_TIME: f32 = 3.0; // Seconds
+const STALLED_SPEED: f32 = 2.0; // Bottles slower than this are not moving
+const TOPPLE_ANGLE: f32 = FRAC_PI_4;
+const CLEAR_JAM_COIL: u16 = 0x000C;
+
+// >>> Components <<<
+/// Marks a bottle that has stalled or toppled on the running conveyor.
+#[derive(Component)]
+pub struct Jammed;
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct JamDetection {
+    pub stall_time: f32,
+    pub manual_clear: bool, // Jams stop the line and latch until cleared
+    pub clear_coil_address: u16,
+    stalled_for: HashMap<Entity, f32>,
+}
+
+impl Default for JamDetection {
+    fn default() -> Self {
+        Self {
+            stall_time: DEFAULT_STALL_TIME,
+            manual_clear: true,
+            clear_coil_address: CLEAR_JAM_COIL,
+            stalled_for: HashMap::new(),
+        }
+    }
+}
+
+// >>> Systems <<<
+/// Counts how long each bottle on a running belt has stood still, and jams
+/// those stalled too long or toppled. Bottles still falling onto the belt or
+/// standing under an open valve to be filled are not stalled.
+pub fn detect_jams(
+    time: Res<SimTime>,
+    mut commands: Commands,
+    mut jam_detection: ResMut<JamDetection>,
+    conveyors: Query<(
+        &ConveyorState,
+        &StationId,
+        &Transform,
+        &Sprite,
+        &ConveyorShape,
+    )>,
+    valves: Query<(&ValveState, &ValvePosition, &StationId)>,
+    bottles: Query<
+        (
+            Entity,
+            &Transform,
+            &BottlePosition,
+            &BottleSpec,
+            &Velocity,
+            &StationId,
+            Has<Jammed>,
+        ),
+        (
+            With<Bottle>,
+            Without<Accumulated>,
+            Without<Diverted>,
+            Without<Sorted>,
+            Without<Handled>,
+            Without<Stopped>,
+        ),
+    >,
+) {
+    let delta = time.delta_secs();
+    let stall_time = jam_detection.stall_time;
+    let manual_clear = jam_detection.manual_clear;
+
+    jam_detection
+        .stalled_for
+        .retain(|entity, _| bottles.contains(*entity));
+
+    let running_stations: HashSet<StationId> = conveyors
+        .iter()
+        .filter(|(conveyor_state, ..)| conveyor_state.is_running)
+        .map(|(_, station, ..)| *station)
+        .collect();
+
+    for (entity, transform, position, spec, velocity, station, is_jammed) in bottles.iter() {
+        let (_, _, angle) = transform.rotation.to_euler(EulerRot::XYZ);
+        let is_toppled = angle.abs() > TOPPLE_ANGLE;
+
+        let center = position.world_center(transform);
+        let is_on_belt = conveyors
+            .iter()
+            .filter(|(_, conveyor_station, ..)| *conveyor_station == station)
+            .any(|(_, _, conveyor_transform, sprite, shape)| {
+                stands_on_belt(conveyor_transform, sprite, shape, center, spec.height)
+            });
+        let is_filling = valves
+            .iter()
+            .any(|(valve_state, ValvePosition(mouth), valve_station)| {
+                valve_station == station
+                    && valve_state.is_open
+                    && (mouth.x - center.x).abs() < spec.width / 2.0
+            });
+        let is_stalled = running_stations.contains(station)
+            && is_on_belt
+            && !is_filling
+            && velocity.linvel.x.abs() < STALLED_SPEED;
+
+        let stalled_for = jam_detection.stalled_for.entry(entity).or_insert(0.0);
+        *stalled_for = if is_stalled { *stalled_for + delta } else { 0.0 };
+
+        let should_jam = is_toppled || *stalled_for >= stall_time;
+        if should_jam && !is_jammed {
+            commands.entity(entity).insert(Jammed);
+            warn!(
+                "Bottle {:?} jammed ({})",
+                entity,
+                if is_toppled { "toppled" } else { "stalled" }
+            );
+        } else if !should_jam && is_jammed && !manual_clear && !is_stalled {
+            commands.entity(entity).remove::<Jammed>();
+        }
+    }
+}
+
+pub fn handle_jams(
+    jam_detection: Res<JamDetection>,
+    mut conveyors: Query<(&mut ConveyorState, &StationId)>,
+    jammed: Query<&StationId, With<Jammed>>,
+    mut alarm_events: EventWriter<AlarmEvent>,
+    mut was_jammed: Local<bool>,
+) {
+    let is_jammed = !jammed.is_empty();
+
+    // A latched jam holds its station's line until it is cleared
+    if is_jammed && jam_detection.manual_clear {
+        let jammed_stations: HashSet<StationId> = jammed.iter().copied().collect();
+        for (mut conveyor_state, station) in conveyors.iter_mut() {
+            if conveyor_state.is_running && jammed_stations.contains(station) {
+                conveyor_state.is_running = false;
+                info!("Conveyor stopped by jam");
+            }
+        }
+    }
+
+    if *was_jammed != is_jammed {
+        *was_jammed = is_jammed;
+        alarm_events.write(if is_jammed {
+            AlarmEvent::Raise(Alarm::JamDetected)
+        } else {
+            AlarmEvent::Clear(Alarm::JamDetected)
+        });
+    }
+}
+
+/// Removes jammed bottles from the line, as an operator would.
+fn clear_jams(
+    commands: &mut Commands,
+    pool: &mut BottlePool,
+    jammed: &Query<(Entity, &BottleSpec, &BottlePosition), With<Jammed>>,
+) {
+    for (entity, spec, BottlePosition(position)) in jammed.iter() {
+        pool.release(commands, entity, spec, *position);
+    }
+    info!("Jam cleared");
+}
+
+pub fn handle_jam_input(
+    mut commands: Commands,
+    mut pool: ResMut<BottlePool>,
+    jammed: Query<(Entity, &BottleSpec, &BottlePosition), With<Jammed>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyJ) && !jammed.is_empty() {
+        clear_jams(&mut commands, &mut pool, &jammed);
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn register_jam_detection(jam_detection: Res<JamDetection>, modbus_state: Res<ModbusState>) {
+    if jam_detection.is_added() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(jam_detection.clear_coil_address, false);
+        }
+    }
+}
+
+pub fn sync_modbus_to_jams(
+    mut commands: Commands,
+    mut pool: ResMut<BottlePool>,
+    jam_detection: Res<JamDetection>,
+    jammed: Query<(Entity, &BottleSpec, &BottlePosition), With<Jammed>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut coils) = modbus_state.coils.lock() {
+        // Clear jam is a command coil and resets itself once handled
+        if coils.get(&jam_detection.clear_coil_address) == Some(&true) {
+            coils.insert(jam_detection.clear_coil_address, false);
+            if !jammed.is_empty() {
+                clear_jams(&mut commands, &mut pool, &jammed);
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct JamPlugin;
+
+impl Plugin for JamPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(JamDetection::default())
+            .add_systems(Update, handle_jam_input)
+            .add_systems(
+                FixedUpdate,
+                (
+                    register_jam_detection,
+                    sync_modbus_to_jams,
+                    detect_jams,
+                    handle_jams,
+                )
+                    .chain(),
+            );
+    }
+}
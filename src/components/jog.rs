@@ -0,0 +1,144 @@
+// jog.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    bottle::BottleSpawner,
+    conveyor::ConveyorState,
+    modbus::ModbusState,
+    mode::{OperatingMode, remote_control_allowed},
+    overload::stop_tripped_conveyors,
+    tag_sync::sync_tags,
+    watchdog::enforce_safe_states,
+};
+
+// >>> Components <<<
+/// Jog and maintenance controls of the conveyor it is attached to, as on a
+/// drive's local control station.
+///
+/// While the belt is stopped, holding `forward_coil_address` on drives it
+/// forward at `jog_speed` and `reverse_coil_address` backward; releasing the
+/// coil stops it again, and holding both stops it. Setting the run coil takes
+/// over from a jog, and a tripped overload or watchdog stops it.
+/// `maintenance_coil_address` holds the conveyor's timed bottle spawning off,
+/// so the belt can be jogged or run empty.
+#[derive(Component, Debug, Clone)]
+pub struct JogControl {
+    pub forward_coil_address: u16,
+    pub reverse_coil_address: u16,
+    pub maintenance_coil_address: u16,
+    pub jog_speed: f32, // Pixels per second
+    pub is_maintenance: bool,
+}
+
+impl JogControl {
+    /// Creates jog controls outside maintenance mode.
+    ///
+    /// # Parameters
+    /// * `forward_coil_address` - Coil jogging the belt forward while held on
+    /// * `reverse_coil_address` - Coil jogging the belt backward while held on
+    /// * `maintenance_coil_address` - Coil holding timed bottle spawning off
+    /// * `jog_speed` - Belt speed while jogging, in pixels per second
+    ///
+    /// # Return
+    /// The jog control component
+    pub fn new(
+        forward_coil_address: u16,
+        reverse_coil_address: u16,
+        maintenance_coil_address: u16,
+        jog_speed: f32,
+    ) -> Self {
+        Self {
+            forward_coil_address,
+            reverse_coil_address,
+            maintenance_coil_address,
+            jog_speed,
+            is_maintenance: false,
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_jog_controls(
+    controls: Query<&JogControl, Added<JogControl>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for control in controls.iter() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(control.forward_coil_address, false);
+            coils.insert(control.reverse_coil_address, false);
+            coils.insert(control.maintenance_coil_address, control.is_maintenance);
+        }
+
+        info!(
+            "Registered jog control on forward coil: {:x?}",
+            control.forward_coil_address
+        );
+    }
+}
+
+// >>> Modbus Synchronization <<<
+/// Jogs stopped belts while a jog coil is held, and holds spawning off in
+/// maintenance mode.
+pub fn sync_modbus_to_jog_controls(
+    mut controls: Query<(
+        &mut JogControl,
+        &mut ConveyorState,
+        Option<&mut BottleSpawner>,
+    )>,
+    modbus_state: Res<ModbusState>,
+    mode: Option<Res<OperatingMode>>,
+) {
+    if !remote_control_allowed(mode.as_deref()) {
+        return;
+    }
+    let Ok(coils) = modbus_state.coils.lock() else {
+        return;
+    };
+
+    for (mut control, mut conveyor_state, bottle_spawner) in controls.iter_mut() {
+        let is_held = |address| coils.get(&address) == Some(&true);
+        let jog = match (
+            is_held(control.forward_coil_address),
+            is_held(control.reverse_coil_address),
+        ) {
+            _ if conveyor_state.is_running => 0.0,
+            (true, false) => control.jog_speed,
+            (false, true) => -control.jog_speed,
+            _ => 0.0,
+        };
+        // A tripped overload or watchdog stops the jog again afterwards
+        if conveyor_state.jog != jog {
+            conveyor_state.jog = jog;
+        }
+
+        let is_maintenance = is_held(control.maintenance_coil_address);
+        if control.is_maintenance != is_maintenance {
+            control.is_maintenance = is_maintenance;
+            // Chained conveyors take their bottles from upstream instead
+            if let Some(mut bottle_spawner) = bottle_spawner {
+                bottle_spawner.is_held = is_maintenance;
+            }
+            info!("Conveyor maintenance mode set to: {}", is_maintenance);
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Jog-forward, jog-reverse and maintenance-mode coils of the conveyors.
+pub struct JogPlugin;
+
+impl Plugin for JogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (register_jog_controls, sync_modbus_to_jog_controls)
+                .chain()
+                .after(sync_tags)
+                .before(stop_tripped_conveyors)
+                .before(enforce_safe_states),
+        );
+    }
+}
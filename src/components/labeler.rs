@@ -0,0 +1,242 @@
+// labeler.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    arbitration::WriteOrigin,
+    bottle::{Bottle, BottlePosition, BottleSpec},
+    modbus::ModbusState,
+    mode::{OperatingMode, local_control_allowed, remote_control_allowed},
+    simulation::SimRng,
+    tags::TagKind,
+};
+
+// >>> Constants <<<
+const LABEL_HEIGHT: f32 = 30.0;
+const LABEL_COLOR: Color = Color::srgb(0.9, 0.8, 0.2);
+const LABEL_SKEW: f32 = 0.35; // Radians
+const LABELER_ZONE_SIZE: Vec2 = Vec2::new(20.0, 20.0);
+
+// >>> Components <<<
+#[derive(Component)]
+pub struct Labeler {
+    pub coil_address: u16,
+    pub defect_probability: f32, // 0.0 - 1.0
+    pub is_enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelQuality {
+    Good,
+    Skewed,
+    Missing,
+}
+
+/// Result of a bottle passing through a labeler.
+#[derive(Component)]
+pub struct Label {
+    pub quality: LabelQuality,
+}
+
+impl Label {
+    pub fn is_present(&self) -> bool {
+        self.quality != LabelQuality::Missing
+    }
+
+    pub fn is_good(&self) -> bool {
+        self.quality == LabelQuality::Good
+    }
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct LabelerBundle {
+    labeler: Labeler,
+    collider: Collider,
+    collider_sensor: bevy_rapier2d::geometry::Sensor,
+    active_events: ActiveEvents,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl Labeler {
+    /// Creates a labeling station that labels bottles passing through its zone.
+    ///
+    /// # Parameters
+    /// * `coil_address` - Modbus coil energizing the labeler
+    /// * `defect_probability` - Chance (0.0 - 1.0) of applying a skewed or missing label
+    /// * `position` - Position of the labeler
+    ///
+    /// # Return
+    /// The labeler bundle
+    pub fn new(coil_address: u16, defect_probability: f32, position: Vec2) -> LabelerBundle {
+        LabelerBundle {
+            labeler: Labeler {
+                coil_address,
+                defect_probability: defect_probability.clamp(0.0, 1.0),
+                is_enabled: false,
+            },
+            collider: Collider::cuboid(LABELER_ZONE_SIZE.x / 2.0, LABELER_ZONE_SIZE.y / 2.0),
+            collider_sensor: bevy_rapier2d::geometry::Sensor,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            sprite: Sprite::from_color(Color::srgb(0.4, 0.4, 0.4), LABELER_ZONE_SIZE),
+            transform: Transform::from_translation(position.extend(0.0)),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn handle_labeler_input(
+    mut labelers: Query<&mut Labeler>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mode: Option<Res<OperatingMode>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyB) && local_control_allowed(mode.as_deref()) {
+        let origin = WriteOrigin::hmi("local controls");
+        for mut labeler in labelers.iter_mut() {
+            labeler.is_enabled = !labeler.is_enabled;
+            info!("Labeler {}: {}", labeler.coil_address, labeler.is_enabled);
+            let is_enabled = labeler.is_enabled as u16;
+            modbus_state.write(TagKind::Coil, labeler.coil_address, is_enabled, &origin);
+        }
+    }
+}
+
+/// Marks a bottle with a label of the given quality and draws it.
+///
+/// # Parameters
+/// * `bottle` - The bottle's entity commands
+/// * `quality` - Quality of the applied label
+/// * `position` - The bottle's collider offset
+/// * `spec` - Variant of the bottle
+pub fn attach_label(
+    bottle: &mut EntityCommands,
+    quality: LabelQuality,
+    position: Vec2,
+    spec: &BottleSpec,
+) {
+    bottle.insert(Label { quality });
+
+    if quality != LabelQuality::Missing {
+        let rotation = if quality == LabelQuality::Skewed {
+            LABEL_SKEW
+        } else {
+            0.0
+        };
+        let label_width = spec.width * 0.8;
+
+        bottle.with_children(|parent| {
+            parent.spawn((
+                Transform::from_translation(position.extend(0.1))
+                    .with_rotation(Quat::from_rotation_z(rotation)),
+                GlobalTransform::default(),
+                Sprite {
+                    color: LABEL_COLOR,
+                    custom_size: Some(Vec2::new(label_width, LABEL_HEIGHT)),
+                    ..Default::default()
+                },
+            ));
+        });
+    }
+}
+
+pub fn apply_labels(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    labelers: Query<&Labeler>,
+    bottles: Query<(&BottlePosition, &BottleSpec), (With<Bottle>, Without<Label>)>,
+    mut rng: ResMut<SimRng>,
+) {
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(e1, e2, _) = collision_event else {
+            continue;
+        };
+
+        let (labeler, bottle_entity) = if let Ok(labeler) = labelers.get(*e1) {
+            (labeler, *e2)
+        } else if let Ok(labeler) = labelers.get(*e2) {
+            (labeler, *e1)
+        } else {
+            continue;
+        };
+
+        if !labeler.is_enabled {
+            continue;
+        }
+
+        let Ok((BottlePosition(position), spec)) = bottles.get(bottle_entity) else {
+            continue;
+        };
+
+        let quality = if rng.random::<f32>() < labeler.defect_probability {
+            if rng.random::<bool>() {
+                LabelQuality::Skewed
+            } else {
+                LabelQuality::Missing
+            }
+        } else {
+            LabelQuality::Good
+        };
+
+        attach_label(&mut commands.entity(bottle_entity), quality, *position, spec);
+
+        info!("Labeler {} applied label: {:?}", labeler.coil_address, quality);
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_labelers_to_modbus(
+    labelers: Query<&Labeler, Changed<Labeler>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut coils) = modbus_state.coils.lock() {
+        for labeler in labelers.iter() {
+            coils.insert(labeler.coil_address, labeler.is_enabled);
+        }
+    }
+}
+
+pub fn sync_modbus_to_labelers(
+    mut labelers: Query<&mut Labeler>,
+    modbus_state: Res<ModbusState>,
+    mode: Option<Res<OperatingMode>>,
+) {
+    if !remote_control_allowed(mode.as_deref()) {
+        return;
+    }
+
+    if let Ok(coils) = modbus_state.coils.lock() {
+        for mut labeler in labelers.iter_mut() {
+            if let Some(&coil_state) = coils.get(&labeler.coil_address) {
+                if labeler.is_enabled != coil_state {
+                    labeler.is_enabled = coil_state;
+                    info!("Labeler {} set to: {}", labeler.coil_address, coil_state);
+                }
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct LabelerPlugin;
+
+impl Plugin for LabelerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, handle_labeler_input).add_systems(
+            FixedUpdate,
+            (
+                sync_labelers_to_modbus,
+                sync_modbus_to_labelers,
+                apply_labels,
+            )
+                .chain(),
+        );
+    }
+}
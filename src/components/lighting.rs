@@ -0,0 +1,181 @@
+// lighting.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::modbus::ModbusState;
+
+use bevy::prelude::*;
+
+// >>> Constants <<<
+const LIGHTING_OVERLAY_Z: f32 = 50.0;
+
+// >>> Components <<<
+#[derive(Component)]
+pub struct Lighting {
+    pub reduced_coil_address: u16,
+    pub emergency_coil_address: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LightingMode {
+    #[default]
+    Normal,
+    Reduced,
+    Emergency,
+}
+
+impl LightingMode {
+    /// Color laid over the whole scene for this lighting mode.
+    pub fn tint(&self) -> Color {
+        match self {
+            LightingMode::Normal => Color::NONE,
+            LightingMode::Reduced => Color::srgba(0.0, 0.0, 0.0, 0.45),
+            LightingMode::Emergency => Color::srgba(0.6, 0.0, 0.0, 0.35),
+        }
+    }
+
+    /// Probability that a vision inspection falsely rejects a good bottle.
+    pub fn false_reject_rate(&self) -> f32 {
+        match self {
+            LightingMode::Normal => 0.0,
+            LightingMode::Reduced => 0.05,
+            LightingMode::Emergency => 0.25,
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            LightingMode::Normal => LightingMode::Reduced,
+            LightingMode::Reduced => LightingMode::Emergency,
+            LightingMode::Emergency => LightingMode::Normal,
+        }
+    }
+
+    // Emergency lighting takes priority over reduced lighting
+    fn from_coils(reduced: bool, emergency: bool) -> Self {
+        if emergency {
+            LightingMode::Emergency
+        } else if reduced {
+            LightingMode::Reduced
+        } else {
+            LightingMode::Normal
+        }
+    }
+}
+
+// >>> Resources <<<
+#[derive(Resource, Default)]
+pub struct LightingState {
+    pub mode: LightingMode,
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct LightingBundle {
+    lighting: Lighting,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl Lighting {
+    /// Creates the scene lighting overlay.
+    ///
+    /// # Parameters
+    /// * `reduced_coil_address` - Coil selecting reduced lighting
+    /// * `emergency_coil_address` - Coil selecting emergency lighting
+    /// * `size` - Size of the area covered by the overlay
+    ///
+    /// # Return
+    /// The lighting bundle
+    pub fn new(reduced_coil_address: u16, emergency_coil_address: u16, size: Vec2) -> LightingBundle {
+        LightingBundle {
+            lighting: Lighting {
+                reduced_coil_address,
+                emergency_coil_address,
+            },
+            sprite: Sprite::from_color(LightingMode::Normal.tint(), size),
+            transform: Transform::from_xyz(0.0, 0.0, LIGHTING_OVERLAY_Z),
+        }
+    }
+}
+
+// >>> Input System <<<
+pub fn handle_lighting_input(
+    mut lighting_state: ResMut<LightingState>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyL) {
+        lighting_state.mode = lighting_state.mode.next();
+        info!("Lighting: {:?}", lighting_state.mode);
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_lighting_to_modbus(
+    lights: Query<&Lighting>,
+    lighting_state: Res<LightingState>,
+    modbus_state: Res<ModbusState>,
+) {
+    if lighting_state.is_changed() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            for lighting in lights.iter() {
+                coils.insert(
+                    lighting.reduced_coil_address,
+                    lighting_state.mode == LightingMode::Reduced,
+                );
+                coils.insert(
+                    lighting.emergency_coil_address,
+                    lighting_state.mode == LightingMode::Emergency,
+                );
+            }
+        }
+    }
+}
+
+pub fn sync_modbus_to_lighting(
+    lights: Query<&Lighting>,
+    mut lighting_state: ResMut<LightingState>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(coils) = modbus_state.coils.lock() {
+        for lighting in lights.iter() {
+            let reduced = coils.get(&lighting.reduced_coil_address).copied();
+            let emergency = coils.get(&lighting.emergency_coil_address).copied();
+
+            if let (Some(reduced), Some(emergency)) = (reduced, emergency) {
+                let mode = LightingMode::from_coils(reduced, emergency);
+                if lighting_state.mode != mode {
+                    lighting_state.mode = mode;
+                    info!("Lighting set to: {:?}", mode);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// >>> Visual System <<<
+pub fn update_lighting_visuals(
+    lighting_state: Res<LightingState>,
+    mut lights: Query<&mut Sprite, With<Lighting>>,
+) {
+    if lighting_state.is_changed() {
+        for mut sprite in lights.iter_mut() {
+            sprite.color = lighting_state.mode.tint();
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LightingState::default())
+            .add_systems(Update, (handle_lighting_input, update_lighting_visuals).chain())
+            .add_systems(
+                FixedUpdate,
+                (sync_lighting_to_modbus, sync_modbus_to_lighting).chain(),
+            );
+    }
+}
@@ -0,0 +1,419 @@
+// logger.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use bevy::prelude::*;
+
+use super::{
+    hmi::HmiPanel,
+    iolist::csv_field,
+    modbus::ModbusState,
+    simulation::SimTime,
+    tags::{Tag, TagRegistry},
+};
+
+// >>> Constants <<<
+const LOG_PATH: &str = "data_log.csv";
+const DEFAULT_RATE: f32 = 10.0; // Samples per second of simulation time
+const LOGGER_PANEL_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+const RECORDING_COLOR: Color = Color::srgb(0.7, 0.15, 0.15);
+const STOPPED_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+const LOGGER_FONT_SIZE: f32 = 12.0;
+
+// >>> Components <<<
+/// Panel to start and stop the data logger and pick its tags, shown with F12.
+#[derive(Component)]
+pub struct LoggerPanel;
+
+/// Button starting and stopping the recording.
+#[derive(Component)]
+pub struct LoggerToggle;
+
+#[derive(Component)]
+pub struct LoggerToggleText;
+
+/// Column of the panel listing the tags that can be logged.
+#[derive(Component)]
+pub struct LoggerTagList;
+
+/// Button selecting a tag for logging, by name.
+#[derive(Component)]
+pub struct LoggerTagEntry(pub String);
+
+// >>> Resources <<<
+/// Time series of selected tags, appended to a CSV file while recording.
+///
+/// Each row holds the simulation time in seconds and the value of every
+/// logged tag, scaled like the HMI shows it. Recording into a file whose
+/// header lists other tags moves on to a numbered file beside it, e.g.
+/// `data_log-2.csv`, rather than mixing columns.
+#[derive(Resource)]
+pub struct DataLogger {
+    pub path: PathBuf,
+    pub interval: f32,         // Seconds of simulation time between rows
+    pub selected: Vec<String>, // Tags logged, every tag if empty
+    pub is_recording: bool,
+    file: Option<LogFile>,
+}
+
+/// The file being recorded into.
+struct LogFile {
+    file: File,
+    path: PathBuf,
+    columns: Vec<Tag>,
+    next_sample_at: f32,
+    rows: u64,
+}
+
+impl DataLogger {
+    pub fn is_selected(&self, name: &str) -> bool {
+        self.selected.iter().any(|selected| selected == name)
+    }
+
+    /// Adds a tag to the logged ones, or removes it if it already is.
+    pub fn toggle_tag(&mut self, name: &str) {
+        match self.selected.iter().position(|selected| selected == name) {
+            Some(index) => {
+                self.selected.remove(index);
+            }
+            None => self.selected.push(name.to_string()),
+        }
+    }
+
+    /// File being recorded into and the rows written to it so far.
+    pub fn recording(&self) -> Option<(&Path, u64)> {
+        self.file.as_ref().map(|log| (log.path.as_path(), log.rows))
+    }
+}
+
+// >>> Logging <<<
+/// Opens the first of `path` and its numbered siblings that is new, empty or
+/// already logging the same columns, writing the header to a fresh file.
+fn open_log(path: &Path, header: &str) -> anyhow::Result<(PathBuf, File)> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    for n in 1.. {
+        let candidate = match n {
+            1 => path.to_path_buf(),
+            _ => path
+                .with_file_name(format!("{stem}-{n}"))
+                .with_extension(path.extension().unwrap_or_default()),
+        };
+        let existing_header = match File::open(&candidate) {
+            Ok(file) => BufReader::new(file).lines().next().transpose()?,
+            Err(_) => None,
+        };
+        if existing_header
+            .as_deref()
+            .is_some_and(|line| line != header)
+        {
+            continue;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&candidate)
+            .with_context(|| format!("Failed to open {}", candidate.display()))?;
+        if existing_header.is_none() {
+            writeln!(file, "{header}")?;
+        }
+        return Ok((candidate, file));
+    }
+    unreachable!()
+}
+
+/// Opens the log file with the selected tags as its columns.
+fn start_log(logger: &DataLogger, tags: &TagRegistry, now: f32) -> anyhow::Result<LogFile> {
+    let columns: Vec<Tag> = if logger.selected.is_empty() {
+        tags.iter().cloned().collect()
+    } else {
+        logger
+            .selected
+            .iter()
+            .filter_map(|name| {
+                let tag = tags.get(name).cloned();
+                if tag.is_none() {
+                    warn!("Not logging unknown tag {name}");
+                }
+                tag
+            })
+            .collect()
+    };
+
+    let header = std::iter::once("time".to_string())
+        .chain(columns.iter().map(|tag| csv_field(&tag.name)))
+        .collect::<Vec<_>>()
+        .join(",");
+    let (path, file) = open_log(&logger.path, &header)?;
+    info!("Logging {} tags to {}", columns.len(), path.display());
+
+    Ok(LogFile {
+        file,
+        path,
+        columns,
+        next_sample_at: now,
+        rows: 0,
+    })
+}
+
+// >>> Systems <<<
+/// Opens or closes the log file as recording starts and stops, and appends a
+/// row every `interval` while it runs.
+pub fn write_data_log(
+    time: Res<SimTime>,
+    mut logger: ResMut<DataLogger>,
+    tags: Res<TagRegistry>,
+    modbus_state: Res<ModbusState>,
+) {
+    let now = time.elapsed_secs();
+    let logger = &mut *logger;
+
+    if !logger.is_recording {
+        if let Some(log) = logger.file.take() {
+            info!("Logged {} rows to {}", log.rows, log.path.display());
+        }
+        return;
+    }
+    if logger.file.is_none() {
+        match start_log(logger, &tags, now) {
+            Ok(log) => logger.file = Some(log),
+            Err(err) => {
+                error!("Data logger not started: {err:#}");
+                logger.is_recording = false;
+                return;
+            }
+        }
+    }
+
+    let Some(log) = logger.file.as_mut() else {
+        return;
+    };
+    if now < log.next_sample_at {
+        return;
+    }
+    log.next_sample_at = (log.next_sample_at + logger.interval).max(now);
+
+    let mut row = format!("{now:.3}");
+    for tag in &log.columns {
+        row.push(',');
+        if let Some(value) = tags.tag_value(tag, &modbus_state) {
+            row += &value.to_string();
+        }
+    }
+    if let Err(err) = writeln!(log.file, "{row}") {
+        error!(
+            "Data logger stopped: failed to write {}: {err}",
+            log.path.display()
+        );
+        logger.is_recording = false;
+        return;
+    }
+    log.rows += 1;
+}
+
+pub fn setup_logger_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            LoggerPanel,
+            HmiPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(36.0),
+                right: Val::Px(0.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(LOGGER_PANEL_COLOR),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    LoggerToggle,
+                    Button,
+                    Node {
+                        padding: UiRect::all(Val::Px(4.0)),
+                        ..default()
+                    },
+                    BackgroundColor(STOPPED_COLOR),
+                ))
+                .with_children(|button| {
+                    button.spawn((
+                        LoggerToggleText,
+                        Text::new(""),
+                        TextFont {
+                            font_size: LOGGER_FONT_SIZE,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                });
+            // Long tag lists wrap into further columns
+            parent.spawn((
+                LoggerTagList,
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    flex_wrap: FlexWrap::Wrap,
+                    max_height: Val::Vh(60.0),
+                    column_gap: Val::Px(12.0),
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Lists the registered tags in the panel, rebuilding the list only when
+/// tags come or go so clicks are not lost.
+pub fn update_logger_tag_list(
+    mut commands: Commands,
+    tags: Res<TagRegistry>,
+    lists: Query<Entity, With<LoggerTagList>>,
+    mut listed: Local<Vec<String>>,
+) {
+    if tags.iter().map(|tag| &tag.name).eq(listed.iter()) {
+        return;
+    }
+    *listed = tags.iter().map(|tag| tag.name.clone()).collect();
+
+    for list in lists.iter() {
+        commands
+            .entity(list)
+            .despawn_related::<Children>()
+            .with_children(|parent| {
+                for name in listed.iter() {
+                    parent.spawn((
+                        LoggerTagEntry(name.clone()),
+                        Button,
+                        Text::new(""),
+                        TextFont {
+                            font_size: LOGGER_FONT_SIZE,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                }
+            });
+    }
+}
+
+pub fn update_logger_panel(
+    logger: Res<DataLogger>,
+    mut toggles: Query<&mut BackgroundColor, With<LoggerToggle>>,
+    mut toggle_texts: Query<&mut Text, With<LoggerToggleText>>,
+    mut entries: Query<(&LoggerTagEntry, &mut Text), Without<LoggerToggleText>>,
+) {
+    let header = match logger.recording() {
+        Some((path, rows)) => format!("Recording: {rows} rows to {}", path.display()),
+        None => format!("Start recording to {}", logger.path.display()),
+    };
+    for mut text in toggle_texts.iter_mut() {
+        if text.0 != header {
+            text.0 = header.clone();
+        }
+    }
+    for mut background in toggles.iter_mut() {
+        background.set_if_neq(BackgroundColor(if logger.is_recording {
+            RECORDING_COLOR
+        } else {
+            STOPPED_COLOR
+        }));
+    }
+
+    for (LoggerTagEntry(name), mut text) in entries.iter_mut() {
+        let mark = if logger.is_selected(name) { "x" } else { " " };
+        let line = format!("[{mark}] {name}");
+        if text.0 != line {
+            text.0 = line;
+        }
+    }
+}
+
+// >>> Input System <<<
+/// F12 shows or hides the logger panel. Clicking its button starts or stops
+/// the recording, and clicking a tag selects it while the logger is stopped.
+pub fn handle_logger_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut logger: ResMut<DataLogger>,
+    mut panels: Query<&mut Visibility, With<LoggerPanel>>,
+    toggles: Query<&Interaction, (Changed<Interaction>, With<LoggerToggle>)>,
+    entries: Query<(&Interaction, &LoggerTagEntry), Changed<Interaction>>,
+) {
+    if keyboard.just_pressed(KeyCode::F12) {
+        for mut visibility in panels.iter_mut() {
+            visibility.toggle_visible_hidden();
+        }
+    }
+
+    if toggles
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        logger.is_recording = !logger.is_recording;
+    }
+
+    for (interaction, LoggerTagEntry(name)) in entries.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if logger.is_recording {
+            warn!("Stop the data logger before changing its tags");
+            continue;
+        }
+        logger.toggle_tag(name);
+    }
+}
+
+// >>> Plugin <<<
+/// Samples the selected tags `rate` times a second of simulation time into a
+/// CSV file at `path`, for plotting loop responses or checking timing. With
+/// no tags given every tag is logged; the HMI panel, shown with F12, starts
+/// and stops the recording and picks the tags.
+pub struct DataLoggerPlugin {
+    pub path: PathBuf,
+    pub rate: f32,
+    pub tags: Vec<String>,
+    pub record_on_start: bool,
+}
+
+impl Default for DataLoggerPlugin {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from(LOG_PATH),
+            rate: DEFAULT_RATE,
+            tags: Vec::new(),
+            record_on_start: false,
+        }
+    }
+}
+
+impl Plugin for DataLoggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DataLogger {
+            path: self.path.clone(),
+            interval: 1.0 / self.rate.max(f32::EPSILON),
+            selected: self.tags.clone(),
+            is_recording: self.record_on_start,
+            file: None,
+        })
+        .add_systems(Startup, setup_logger_panel)
+        .add_systems(
+            Update,
+            (
+                handle_logger_input,
+                update_logger_tag_list,
+                update_logger_panel,
+            )
+                .chain(),
+        )
+        // After the tick's I/O is published
+        .add_systems(FixedPostUpdate, write_data_log);
+    }
+}
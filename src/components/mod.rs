@@ -2,8 +2,77 @@
 // Copyright (C) 2025 deciphr
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod accumulation;
+pub mod actuator;
+pub mod alarm;
+pub mod arbitration;
+pub mod barcode;
 pub mod bottle;
+pub mod capper;
+pub mod controls;
 pub mod conveyor;
+pub mod counter;
+pub mod diverter;
+pub mod dnp3;
+pub mod energy;
+pub mod feedback;
+pub mod forcing;
+pub mod gallery;
+pub mod gamepad;
+pub mod gantry;
+pub mod guide;
+pub mod heater;
+pub mod hmi;
+pub mod hopper;
+pub mod hotreload;
+pub mod infeed;
+pub mod inspector;
+pub mod iolist;
+pub mod jam;
+pub mod jog;
+pub mod labeler;
+pub mod lighting;
+pub mod logger;
+pub mod mode;
+pub mod oee;
+pub mod outfeed;
+pub mod overlay;
+pub mod overload;
+pub mod palletizer;
+pub mod physics;
+pub mod pid;
+pub mod pneumatics;
+pub mod profiler;
+pub mod recipe;
+pub mod report;
+pub mod restapi;
+pub mod s7;
+pub mod score;
 pub mod sensor;
+pub mod simulation;
+pub mod snapshot;
+pub mod sound;
+pub mod spill;
+pub mod station;
+pub mod stopper;
+pub mod stats;
+pub mod tank;
+pub mod tag_sync;
+pub mod tags;
+pub mod timeline;
+pub mod tipping;
+pub mod tracking;
+#[cfg(feature = "tls")]
+pub mod modbus_tls;
+#[cfg(feature = "trends")]
+pub mod trends;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod turntable;
 pub mod valve;
-pub mod modbus;
\ No newline at end of file
+pub mod variability;
+pub mod vision;
+pub mod watchdog;
+pub mod webapi;
+pub mod modbus;
+pub mod modbus_client;
\ No newline at end of file
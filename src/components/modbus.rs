@@ -4,36 +4,118 @@
 
 // Bevy implementation of: https://github.com/slowtec/tokio-modbus/blob/main/examples/tcp-server.rs
 use std::{
-    collections::HashMap,
-    net::SocketAddr,
-    sync::{Arc, Mutex},
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    ops::RangeInclusive,
+    pin::Pin,
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
+use anyhow::ensure;
 use bevy::prelude::*;
-use tokio::net::TcpListener;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::{net::TcpListener, sync::oneshot};
 use tokio_modbus::{
     prelude::*,
     server::tcp::{Server, accept_tcp_connection},
 };
 
-const MODBUS_IP: &str = "0.0.0.0";
+use super::{
+    arbitration::{Arbitration, WriteOrigin},
+    tags::TagKind,
+};
+
+pub(crate) const MODBUS_IP: &str = "0.0.0.0";
 const MODBUS_PORT: u16 = 5502;
+const MAX_PENDING_WRITES: usize = 10_000; // Writes kept until taken, e.g. by the session report
+const MAX_READ_BITS: usize = 2000; // Quantity limits of the Modbus application protocol
+const MAX_READ_REGISTERS: usize = 125;
+const MAX_WRITE_REGISTERS: usize = 123;
+const MAX_IMPAIRMENT_DELAY: f32 = 60.0; // Seconds, well past any PLC's comms timeout
 
-pub struct ModbusPlugin;
+pub struct ModbusPlugin {
+    pub port: u16,
+}
+
+impl Default for ModbusPlugin {
+    fn default() -> Self {
+        Self { port: MODBUS_PORT }
+    }
+}
 
 impl Plugin for ModbusPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ModbusState::default())
-            .add_systems(Startup, start_modbus_server);
+            .insert_resource(ModbusSettings { port: self.port })
+            .add_event::<ModbusServerCommand>()
+            .add_systems(Startup, start_modbus_server)
+            .add_systems(
+                Update,
+                (
+                    handle_modbus_server_commands,
+                    share_network_impairment,
+                    share_client_policy,
+                ),
+            )
+            .add_systems(Last, shutdown_modbus_server_on_exit);
     }
 }
 
+#[derive(Resource)]
+pub struct ModbusSettings {
+    pub port: u16,
+}
+
+/// Requests to stop the Modbus server or bind it again, e.g. on another port.
+#[derive(Event, Debug, Clone, Copy)]
+pub enum ModbusServerCommand {
+    Shutdown,
+    Restart { port: Option<u16> },
+}
+
 #[derive(Resource, Default, Clone)]
 pub struct ModbusState {
     pub coils: Arc<Mutex<HashMap<u16, bool>>>,
     pub discrete_inputs: Arc<Mutex<HashMap<u16, bool>>>,
     pub input_registers: Arc<Mutex<HashMap<u16, u16>>>,
     pub holding_registers: Arc<Mutex<HashMap<u16, u16>>>,
+    pub forces: Arc<Mutex<HashMap<(TagKind, u16), Force>>>,
+    pub writes: Arc<Mutex<Vec<RegisterWrite>>>, // Client writes not yet taken
+    pub arbitration: Arc<Mutex<Arbitration>>,
+    pub impairment: Arc<Mutex<NetworkImpairment>>,
+    pub sessions: Arc<Mutex<ClientSessions>>,
+    generation: Arc<AtomicU64>, // Counts writes to the coils and holding registers
+}
+
+/// The coils, discrete inputs and holding registers locked together, so the
+/// plant's devices can all be synced under a single lock of each bank.
+pub struct RegisterBanks<'a> {
+    pub coils: MutexGuard<'a, HashMap<u16, bool>>,
+    pub discrete_inputs: MutexGuard<'a, HashMap<u16, bool>>,
+    pub holding_registers: MutexGuard<'a, HashMap<u16, u16>>,
+}
+
+/// A coil or holding register written by a Modbus client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterWrite {
+    pub kind: TagKind,
+    pub address: u16,
+    pub value: u16, // 0 or 1 for coils
+}
+
+/// A coil or discrete input held at `value`, whatever the PLC or the plant
+/// writes to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Force {
+    pub value: bool,
+    pub underlying: bool, // Last value written to it while forced, restored on release
 }
 
 impl ModbusState {
@@ -43,34 +125,561 @@ impl ModbusState {
             discrete_inputs: Arc::new(Mutex::new(HashMap::new())),
             input_registers: Arc::new(Mutex::new(HashMap::new())),
             holding_registers: Arc::new(Mutex::new(HashMap::new())),
+            forces: Arc::new(Mutex::new(HashMap::new())),
+            writes: Arc::new(Mutex::new(Vec::new())),
+            arbitration: Arc::new(Mutex::new(Arbitration::default())),
+            impairment: Arc::new(Mutex::new(NetworkImpairment::default())),
+            sessions: Arc::new(Mutex::new(ClientSessions::default())),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Writes a registered coil or holding register on behalf of `origin`,
+    /// unless arbitration holds the write off.
+    ///
+    /// # Return
+    /// Whether the address is a registered coil or holding register
+    pub fn write(&self, kind: TagKind, address: u16, value: u16, origin: &WriteOrigin) -> bool {
+        match kind {
+            TagKind::Coil => {
+                let (Ok(mut coils), Ok(mut arbitration)) =
+                    (self.coils.lock(), self.arbitration.lock())
+                else {
+                    return false;
+                };
+                let Some(coil) = coils.get_mut(&address) else {
+                    return false;
+                };
+                if arbitration.admit(kind, address, *coil as u16, (value != 0) as u16, origin) {
+                    *coil = value != 0;
+                    self.mark_written();
+                }
+                true
+            }
+            TagKind::HoldingRegister => {
+                let (Ok(mut holdings), Ok(mut arbitration)) =
+                    (self.holding_registers.lock(), self.arbitration.lock())
+                else {
+                    return false;
+                };
+                let Some(holding) = holdings.get_mut(&address) else {
+                    return false;
+                };
+                if arbitration.admit(kind, address, *holding, value, origin) {
+                    *holding = value;
+                    self.mark_written();
+                }
+                true
+            }
+            TagKind::DiscreteInput | TagKind::InputRegister => false,
+        }
+    }
+
+    /// Locks the coils, discrete inputs and holding registers, always in that
+    /// order so no two callers can deadlock on them.
+    ///
+    /// # Return
+    /// The locked banks, or `None` if one of the locks is poisoned
+    pub fn lock_banks(&self) -> Option<RegisterBanks<'_>> {
+        Some(RegisterBanks {
+            coils: self.coils.lock().ok()?,
+            discrete_inputs: self.discrete_inputs.lock().ok()?,
+            holding_registers: self.holding_registers.lock().ok()?,
+        })
+    }
+
+    /// Counts a write to the coils or holding registers, made while holding
+    /// the written bank's lock so the count never runs ahead of the values.
+    fn mark_written(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of writes to the coils and holding registers from outside the
+    /// tag-sync stage: by clients, through [`ModbusState::write`] or by
+    /// forcing. It only changes under the written bank's lock, so it can be
+    /// compared with a count taken earlier to skip reading back unchanged banks.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    fn log_writes(&self, kind: TagKind, address: u16, values: impl Iterator<Item = u16>) {
+        if let Ok(mut writes) = self.writes.lock() {
+            for (i, value) in values.enumerate() {
+                if writes.len() >= MAX_PENDING_WRITES {
+                    break;
+                }
+                writes.push(RegisterWrite {
+                    kind,
+                    address: address + i as u16,
+                    value,
+                });
+            }
+        }
+    }
+
+    /// Takes the client writes since the last call, oldest first.
+    pub fn take_writes(&self) -> Vec<RegisterWrite> {
+        self.writes
+            .lock()
+            .map(|mut writes| std::mem::take(&mut *writes))
+            .unwrap_or_default()
+    }
+
+    fn bit_table(&self, kind: TagKind) -> Option<&Arc<Mutex<HashMap<u16, bool>>>> {
+        match kind {
+            TagKind::Coil => Some(&self.coils),
+            TagKind::DiscreteInput => Some(&self.discrete_inputs),
+            TagKind::InputRegister | TagKind::HoldingRegister => None,
+        }
+    }
+
+    /// Forces a registered coil or discrete input to `value` until released.
+    ///
+    /// # Return
+    /// Whether the address can be forced
+    pub fn force(&self, kind: TagKind, address: u16, value: bool) -> bool {
+        let Some(table) = self.bit_table(kind) else {
+            return false;
+        };
+        let (Ok(mut forces), Ok(mut table)) = (self.forces.lock(), table.lock()) else {
+            return false;
+        };
+        let Some(current) = table.insert(address, value) else {
+            table.remove(&address);
+            return false;
+        };
+        self.mark_written();
+
+        let underlying = forces
+            .get(&(kind, address))
+            .map_or(current, |force| force.underlying);
+        forces.insert((kind, address), Force { value, underlying });
+        true
+    }
+
+    /// Releases a force, restoring the value last written underneath it.
+    pub fn release(&self, kind: TagKind, address: u16) -> bool {
+        let Ok(mut forces) = self.forces.lock() else {
+            return false;
+        };
+        let Some(force) = forces.remove(&(kind, address)) else {
+            return false;
+        };
+
+        if let Some(Ok(mut table)) = self.bit_table(kind).map(|table| table.lock()) {
+            table.insert(address, force.underlying);
+            self.mark_written();
+        }
+        true
+    }
+
+    /// Releases every force.
+    ///
+    /// # Return
+    /// How many forces were released
+    pub fn release_all(&self) -> usize {
+        let forced: Vec<(TagKind, u16)> = match self.forces.lock() {
+            Ok(forces) => forces.keys().copied().collect(),
+            Err(_) => return 0,
+        };
+        forced
+            .into_iter()
+            .filter(|(kind, address)| self.release(*kind, *address))
+            .count()
+    }
+
+    /// Drops an address from its table along with any force on it, e.g. when
+    /// the device it belonged to is despawned.
+    pub fn remove(&self, kind: TagKind, address: u16) {
+        if let Ok(mut forces) = self.forces.lock() {
+            forces.remove(&(kind, address));
+        }
+        match kind {
+            TagKind::Coil => {
+                if let Ok(mut coils) = self.coils.lock() {
+                    coils.remove(&address);
+                    self.mark_written();
+                }
+            }
+            TagKind::DiscreteInput => {
+                if let Ok(mut discrete_inputs) = self.discrete_inputs.lock() {
+                    discrete_inputs.remove(&address);
+                }
+            }
+            TagKind::InputRegister => {
+                if let Ok(mut input_registers) = self.input_registers.lock() {
+                    input_registers.remove(&address);
+                }
+            }
+            TagKind::HoldingRegister => {
+                if let Ok(mut holding_registers) = self.holding_registers.lock() {
+                    holding_registers.remove(&address);
+                    self.mark_written();
+                }
+            }
+        }
+    }
+
+    pub fn forced(&self, kind: TagKind, address: u16) -> Option<bool> {
+        let forces = self.forces.lock().ok()?;
+        forces.get(&(kind, address)).map(|force| force.value)
+    }
+
+    pub fn is_forcing(&self) -> bool {
+        self.forces.lock().is_ok_and(|forces| !forces.is_empty())
+    }
+
+    /// Writes the forced values over whatever was written since, keeping that
+    /// as the value to restore on release.
+    pub fn apply_forces(&self) {
+        let Ok(mut forces) = self.forces.lock() else {
+            return;
+        };
+
+        for ((kind, address), force) in forces.iter_mut() {
+            let Some(Ok(mut table)) = self.bit_table(*kind).map(|table| table.lock()) else {
+                continue;
+            };
+            if let Some(value) = table.get_mut(address) {
+                if *value != force.value {
+                    force.underlying = *value;
+                    *value = force.value;
+                    self.mark_written();
+                }
+            }
+        }
+    }
+}
+
+/// Impairments of the link between the PLC and the Modbus server, so a PLC's
+/// timeouts and retries can be exercised against a local server. Each
+/// request draws its fate independently, from an unseeded generator as the
+/// server runs on its own thread. Everything is off by default.
+#[derive(Resource, Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkImpairment {
+    pub latency: f32,        // Seconds each request waits before it is handled
+    pub jitter: f32,         // Seconds the latency varies by either way
+    pub drop_requests: f32,  // Chance a request is lost and never handled
+    pub drop_responses: f32, // Chance a request is handled but its response lost
+    pub exceptions: f32,     // Chance a request is refused with a server busy exception
+}
+
+/// What becomes of one request under the impairments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestFate {
+    Respond,
+    DropRequest,
+    DropResponse,
+    Refuse,
+}
+
+impl NetworkImpairment {
+    pub fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Each setting by the name it has in a scenario.
+    pub fn settings(&self) -> [(&'static str, f32); 5] {
+        [
+            ("latency", self.latency),
+            ("jitter", self.jitter),
+            ("drop_requests", self.drop_requests),
+            ("drop_responses", self.drop_responses),
+            ("exceptions", self.exceptions),
+        ]
+    }
+
+    /// Checks the delays are seconds up to a minute and the chances are
+    /// between 0 and 1, so the server can draw from them.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (setting, value) in self.settings() {
+            ensure!(
+                value.is_finite() && value >= 0.0,
+                "Network {setting} must be at least 0"
+            );
         }
+        ensure!(
+            self.latency + self.jitter <= MAX_IMPAIRMENT_DELAY,
+            "Network latency and jitter must add up to at most {MAX_IMPAIRMENT_DELAY} seconds"
+        );
+        for (setting, chance) in [
+            ("drop_requests", self.drop_requests),
+            ("drop_responses", self.drop_responses),
+            ("exceptions", self.exceptions),
+        ] {
+            ensure!(
+                chance <= 1.0,
+                "Network {setting} must be a chance from 0 to 1"
+            );
+        }
+        Ok(())
+    }
+
+    /// Draws how long a request waits before it is handled.
+    pub fn delay(&self, rng: &mut impl Rng) -> Duration {
+        let jitter = if self.jitter > 0.0 {
+            rng.random_range(-self.jitter..=self.jitter)
+        } else {
+            0.0
+        };
+        Duration::from_secs_f32((self.latency + jitter).max(0.0))
     }
+
+    /// Draws what becomes of a request.
+    pub fn fate(&self, rng: &mut impl Rng) -> RequestFate {
+        let draw = rng.random::<f32>();
+        if draw < self.drop_requests {
+            RequestFate::DropRequest
+        } else if draw < self.drop_requests + self.drop_responses {
+            RequestFate::DropResponse
+        } else if draw < self.drop_requests + self.drop_responses + self.exceptions {
+            RequestFate::Refuse
+        } else {
+            RequestFate::Respond
+        }
+    }
+}
+
+/// Limits on the Modbus server's clients. A lowered limit refuses new
+/// connections but keeps the clients already connected.
+#[derive(Resource, Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientPolicy {
+    pub max_clients: Option<usize>, // Connections beyond it are refused
+    pub read_only: Vec<IpAddr>,     // Clients whose writes are refused
+}
+
+/// A Modbus client connected to the server.
+#[derive(Debug, Clone)]
+pub struct ClientSession {
+    pub address: SocketAddr,
+    pub connected_at: Instant,
+    pub requests: u64,
+    pub is_read_only: bool,
+}
+
+/// Clients connected to the Modbus server, shared with it through
+/// [`ModbusState`].
+#[derive(Debug, Default)]
+pub struct ClientSessions {
+    policy: ClientPolicy,
+    sessions: BTreeMap<u64, ClientSession>, // By connection order
+    next_id: u64,
+    pub refused: u64, // Connections refused over the limit
 }
 
-struct BevyService {
+impl ClientSessions {
+    pub fn policy(&self) -> &ClientPolicy {
+        &self.policy
+    }
+
+    /// Applies a policy, marking connected clients read-only or writable by
+    /// their address.
+    pub fn set_policy(&mut self, policy: ClientPolicy) {
+        for session in self.sessions.values_mut() {
+            session.is_read_only = policy.read_only.contains(&session.address.ip());
+        }
+        self.policy = policy;
+    }
+
+    /// Opens a session for a new connection, unless the server is full.
+    ///
+    /// # Return
+    /// The session's id, or `None` if the connection is refused
+    pub fn open(&mut self, address: SocketAddr) -> Option<u64> {
+        if self
+            .policy
+            .max_clients
+            .is_some_and(|max_clients| self.sessions.len() >= max_clients)
+        {
+            self.refused += 1;
+            return None;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.sessions.insert(
+            id,
+            ClientSession {
+                address,
+                connected_at: Instant::now(),
+                requests: 0,
+                is_read_only: self.policy.read_only.contains(&address.ip()),
+            },
+        );
+        Some(id)
+    }
+
+    pub fn close(&mut self, id: u64) -> Option<ClientSession> {
+        self.sessions.remove(&id)
+    }
+
+    /// Counts a request served to a session.
+    ///
+    /// # Return
+    /// Whether the client is read-only
+    pub fn count_request(&mut self, id: u64) -> bool {
+        self.sessions.get_mut(&id).is_some_and(|session| {
+            session.requests += 1;
+            session.is_read_only
+        })
+    }
+
+    /// Connected clients, in connection order.
+    pub fn iter(&self) -> impl Iterator<Item = &ClientSession> {
+        self.sessions.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+}
+
+/// Closes a client's session once the last of its connection's services is
+/// dropped.
+struct SessionGuard {
+    id: u64,
+    sessions: Arc<Mutex<ClientSessions>>,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            if let Some(session) = sessions.close(self.id) {
+                println!(
+                    "Modbus client {} disconnected after {} requests",
+                    session.address, session.requests
+                );
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct BevyService {
     state: ModbusState,
+    origin: WriteOrigin, // The connected client
+    session: Arc<SessionGuard>,
 }
 
 impl tokio_modbus::server::Service for BevyService {
     type Request = Request<'static>;
-    type Response = Response;
+    type Response = Option<Response>; // None sends no response at all
     type Exception = ExceptionCode;
-    type Future = std::future::Ready<Result<Self::Response, Self::Exception>>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Exception>> + Send>>;
 
     fn call(&self, req: Self::Request) -> Self::Future {
-        let res = match req {
+        let is_read_only = self
+            .state
+            .sessions
+            .lock()
+            .is_ok_and(|mut sessions| sessions.count_request(self.session.id));
+        let impairment = self
+            .state
+            .impairment
+            .lock()
+            .map(|impairment| impairment.clone())
+            .unwrap_or_default();
+        if !impairment.is_active() {
+            return Box::pin(std::future::ready(self.handle(req, is_read_only).map(Some)));
+        }
+
+        let mut rng = rand::rng();
+        let delay = impairment.delay(&mut rng);
+        let fate = impairment.fate(&mut rng);
+        let service = self.clone();
+        Box::pin(async move {
+            tokio::time::sleep(delay).await;
+            match fate {
+                RequestFate::Respond => service.handle(req, is_read_only).map(Some),
+                RequestFate::DropRequest => Ok(None),
+                RequestFate::DropResponse => service.handle(req, is_read_only).map(|_| None),
+                RequestFate::Refuse => Err(ExceptionCode::ServerDeviceBusy),
+            }
+        })
+    }
+}
+
+impl BevyService {
+    /// Opens a session for a newly connected client and the service answering
+    /// it, unless the server is full.
+    ///
+    /// # Parameters
+    /// * `state` - The register tables served
+    /// * `addr` - The client's address
+    /// * `protocol` - Names the client's writes, e.g. `modbus`
+    pub(crate) fn connect(state: &ModbusState, addr: SocketAddr, protocol: &str) -> Option<Self> {
+        let Some(id) = state
+            .sessions
+            .lock()
+            .ok()
+            .and_then(|mut sessions| sessions.open(addr))
+        else {
+            println!("Modbus client {addr} refused, the server is full");
+            return None;
+        };
+        println!("Modbus client {addr} connected");
+
+        Some(Self {
+            state: state.clone(),
+            origin: WriteOrigin::remote(format!("{protocol} {addr}")),
+            session: Arc::new(SessionGuard {
+                id,
+                sessions: state.sessions.clone(),
+            }),
+        })
+    }
+
+    fn handle(&self, req: Request<'static>, is_read_only: bool) -> Result<Response, ExceptionCode> {
+        let is_write = matches!(
+            req,
+            Request::WriteSingleCoil(..)
+                | Request::WriteSingleRegister(..)
+                | Request::WriteMultipleRegisters(..)
+        );
+        if is_read_only && is_write {
+            println!(
+                "SERVER: Exception::IllegalFunction - Write by read-only client {}",
+                self.origin.writer
+            );
+            return Err(ExceptionCode::IllegalFunction);
+        }
+
+        match req {
             Request::ReadCoils(addr, cnt) => {
+                let forces = self.state.forces.lock().unwrap();
                 let coils = self.state.coils.lock().unwrap();
-                discrete_read(&coils, addr, cnt).map(Response::ReadCoils)
+                discrete_read(&coils, &forces, TagKind::Coil, addr, cnt).map(Response::ReadCoils)
             }
             Request::WriteSingleCoil(addr, value) => {
+                let mut forces = self.state.forces.lock().unwrap();
                 let mut coils = self.state.coils.lock().unwrap();
-                coil_write(&mut coils, addr, std::slice::from_ref(&value))
-                    .map(|_| Response::WriteSingleCoil(addr, value))
+                let mut arbitration = self.state.arbitration.lock().unwrap();
+                coil_write(
+                    &mut coils,
+                    &mut forces,
+                    &mut arbitration,
+                    &self.origin,
+                    addr,
+                    std::slice::from_ref(&value),
+                )
+                .inspect(|_| {
+                    self.state.mark_written();
+                    self.state
+                        .log_writes(TagKind::Coil, addr, std::iter::once(value as u16))
+                })
+                .map(|_| Response::WriteSingleCoil(addr, value))
             }
             Request::ReadDiscreteInputs(addr, cnt) => {
+                let forces = self.state.forces.lock().unwrap();
                 let discrete_inputs = self.state.discrete_inputs.lock().unwrap();
-                discrete_read(&discrete_inputs, addr, cnt).map(Response::ReadDiscreteInputs)
+                discrete_read(&discrete_inputs, &forces, TagKind::DiscreteInput, addr, cnt)
+                    .map(Response::ReadDiscreteInputs)
             }
             Request::ReadInputRegisters(addr, cnt) => {
                 let input_registers = self.state.input_registers.lock().unwrap();
@@ -82,13 +691,37 @@ impl tokio_modbus::server::Service for BevyService {
             }
             Request::WriteMultipleRegisters(addr, values) => {
                 let mut holding_registers = self.state.holding_registers.lock().unwrap();
-                register_write(&mut holding_registers, addr, &values)
-                    .map(|_| Response::WriteMultipleRegisters(addr, values.len() as u16))
+                let mut arbitration = self.state.arbitration.lock().unwrap();
+                register_write(
+                    &mut holding_registers,
+                    &mut arbitration,
+                    &self.origin,
+                    addr,
+                    &values,
+                )
+                .inspect(|_| {
+                    self.state.mark_written();
+                    self.state
+                        .log_writes(TagKind::HoldingRegister, addr, values.iter().copied())
+                })
+                .map(|_| Response::WriteMultipleRegisters(addr, values.len() as u16))
             }
             Request::WriteSingleRegister(addr, value) => {
                 let mut holding_registers = self.state.holding_registers.lock().unwrap();
-                register_write(&mut holding_registers, addr, std::slice::from_ref(&value))
-                    .map(|_| Response::WriteSingleRegister(addr, value))
+                let mut arbitration = self.state.arbitration.lock().unwrap();
+                register_write(
+                    &mut holding_registers,
+                    &mut arbitration,
+                    &self.origin,
+                    addr,
+                    std::slice::from_ref(&value),
+                )
+                .inspect(|_| {
+                    self.state.mark_written();
+                    self.state
+                        .log_writes(TagKind::HoldingRegister, addr, std::iter::once(value))
+                })
+                .map(|_| Response::WriteSingleRegister(addr, value))
             }
             _ => {
                 println!(
@@ -96,39 +729,79 @@ impl tokio_modbus::server::Service for BevyService {
                 );
                 Err(ExceptionCode::IllegalFunction)
             }
-        };
-        std::future::ready(res)
+        }
     }
 }
 
-fn discrete_read(bools: &HashMap<u16, bool>, addr: u16, cnt: u16) -> Result<Vec<bool>, ExceptionCode> {
-    for reg_addr in addr..addr + cnt {
+/// The addresses a request for `cnt` items from `addr` spans, checked
+/// against the protocol's quantity limit before the table is looked at.
+fn request_range(addr: u16, cnt: usize, max: usize) -> Result<RangeInclusive<u16>, ExceptionCode> {
+    if cnt == 0 || cnt > max {
+        println!("SERVER: Exception::IllegalDataValue - Quantity {cnt} outside 1..={max}");
+        return Err(ExceptionCode::IllegalDataValue);
+    }
+    match u16::try_from(addr as usize + cnt - 1) {
+        Ok(last) => Ok(addr..=last),
+        Err(_) => {
+            println!("SERVER: Exception::IllegalDataAddress");
+            Err(ExceptionCode::IllegalDataAddress)
+        }
+    }
+}
+
+/// Reads coils or discrete inputs, forced ones at their forced value even if
+/// the plant wrote them since the last tick.
+fn discrete_read(
+    bools: &HashMap<u16, bool>,
+    forces: &HashMap<(TagKind, u16), Force>,
+    kind: TagKind,
+    addr: u16,
+    cnt: u16,
+) -> Result<Vec<bool>, ExceptionCode> {
+    let range = request_range(addr, cnt as usize, MAX_READ_BITS)?;
+    for reg_addr in range.clone() {
         if !bools.contains_key(&reg_addr) {
             println!("SERVER: Exception::IllegalDataAddress");
             return Err(ExceptionCode::IllegalDataAddress);
         }
     }
-    Ok((addr..addr + cnt)
-        .map(|reg_addr| bools[&reg_addr])
+    Ok(range
+        .map(|reg_addr| match forces.get(&(kind, reg_addr)) {
+            Some(force) => force.value,
+            None => bools[&reg_addr],
+        })
         .collect())
 }
 
+/// Writes coils, leaving forced ones at their forced value and keeping the
+/// write as the value to restore on release.
 fn coil_write(
     coils: &mut HashMap<u16, bool>,
+    forces: &mut HashMap<(TagKind, u16), Force>,
+    arbitration: &mut Arbitration,
+    origin: &WriteOrigin,
     addr: u16,
     values: &[bool],
 ) -> Result<(), ExceptionCode> {
-    for i in 0..values.len() {
-        let reg_addr = addr + i as u16;
+    let range = request_range(addr, values.len(), 1)?;
+    for reg_addr in range.clone() {
         if !coils.contains_key(&reg_addr) {
             println!("SERVER: Exception::IllegalDataAddress");
             return Err(ExceptionCode::IllegalDataAddress);
         }
     }
 
-    for (i, &value) in values.iter().enumerate() {
-        let reg_addr = addr + i as u16;
-        coils.insert(reg_addr, value);
+    // Writes held off by arbitration are dropped without an exception
+    for (reg_addr, &value) in range.zip(values) {
+        let current = coils[&reg_addr] as u16;
+        if arbitration.admit(TagKind::Coil, reg_addr, current, value as u16, origin) {
+            match forces.get_mut(&(TagKind::Coil, reg_addr)) {
+                Some(force) => force.underlying = value,
+                None => {
+                    coils.insert(reg_addr, value);
+                }
+            }
+        }
     }
 
     Ok(())
@@ -138,58 +811,210 @@ fn register_read(
     addr: u16,
     cnt: u16,
 ) -> Result<Vec<u16>, ExceptionCode> {
-    for reg_addr in addr..addr + cnt {
+    let range = request_range(addr, cnt as usize, MAX_READ_REGISTERS)?;
+    for reg_addr in range.clone() {
         if !registers.contains_key(&reg_addr) {
             println!("SERVER: Exception::IllegalDataAddress");
             return Err(ExceptionCode::IllegalDataAddress);
         }
     }
 
-    Ok((addr..addr + cnt)
-        .map(|reg_addr| registers[&reg_addr])
-        .collect())
+    Ok(range.map(|reg_addr| registers[&reg_addr]).collect())
 }
 
 fn register_write(
     registers: &mut HashMap<u16, u16>,
+    arbitration: &mut Arbitration,
+    origin: &WriteOrigin,
     addr: u16,
     values: &[u16],
 ) -> Result<(), ExceptionCode> {
-    for i in 0..values.len() {
-        let reg_addr = addr + i as u16;
+    let range = request_range(addr, values.len(), MAX_WRITE_REGISTERS)?;
+    for reg_addr in range.clone() {
         if !registers.contains_key(&reg_addr) {
             println!("SERVER: Exception::IllegalDataAddress");
             return Err(ExceptionCode::IllegalDataAddress);
         }
     }
 
-    for (i, &value) in values.iter().enumerate() {
-        let reg_addr = addr + i as u16;
-        registers.insert(reg_addr, value);
+    for (reg_addr, &value) in range.zip(values) {
+        let current = registers[&reg_addr];
+        if arbitration.admit(TagKind::HoldingRegister, reg_addr, current, value, origin) {
+            registers.insert(reg_addr, value);
+        }
     }
 
     Ok(())
 }
 
-fn start_modbus_server(modbus_state: Res<ModbusState>) {
-    let state = modbus_state.clone();
-    std::thread::spawn(move || {
-        let rt = tokio::runtime::Runtime::new().unwrap();
-        rt.block_on(async move {
-            let socket_addr: SocketAddr = format!("{}:{}", MODBUS_IP, MODBUS_PORT).parse().unwrap();
-            let listener = TcpListener::bind(socket_addr).await.unwrap();
-            let server = Server::new(listener);
-            let new_service = |_addr| {
-                Ok(Some(BevyService {
-                    state: state.clone(),
-                }))
-            };
-            let on_connected = |stream, socket_addr| async move {
-                accept_tcp_connection(stream, socket_addr, new_service)
-            };
-            let on_process_error = |err| eprintln!("{err}");
-            println!("Modbus server running on {socket_addr}");
-            let _ = server.serve(&on_connected, on_process_error).await;
+/// The running Modbus server thread. Shutting it down releases the port.
+#[derive(Resource)]
+pub struct ModbusServerHandle {
+    port: u16,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ModbusServerHandle {
+    /// Starts serving the register tables on the given port, or on a free
+    /// one picked by the OS if it is 0, e.g. in tests.
+    pub fn start(state: ModbusState, port: u16) -> Self {
+        let socket_addr: SocketAddr = format!("{}:{}", MODBUS_IP, port).parse().unwrap();
+        // Bound before the thread starts so the port picked is known here
+        let listener = std::net::TcpListener::bind(socket_addr)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener));
+        let port = match &listener {
+            Ok(listener) => listener.local_addr().map_or(port, |addr| addr.port()),
+            Err(err) => {
+                eprintln!("Modbus server failed to bind {socket_addr}: {err}");
+                port
+            }
+        };
+
+        Self::spawn(port, move |shutdown_signal| {
+            serve(state, listener.ok(), shutdown_signal)
+        })
+    }
+
+    /// Runs a server on its own thread until it is shut down.
+    ///
+    /// # Parameters
+    /// * `port` - The port the server listens on
+    /// * `serve` - Serves until the shutdown signal fires
+    pub(crate) fn spawn<F, Fut>(port: u16, serve: F) -> Self
+    where
+        F: FnOnce(oneshot::Receiver<()>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()>,
+    {
+        let (shutdown, shutdown_signal) = oneshot::channel();
+        let thread = std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(serve(shutdown_signal));
         });
-    });
+
+        Self {
+            port,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Stops the server and waits for its thread, dropping every client connection.
+    pub fn shutdown(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+            println!("Modbus server on port {} stopped", self.port);
+        }
+    }
+
+    /// Stops the server and binds it again, optionally on a different port.
+    pub fn restart(&mut self, state: ModbusState, port: Option<u16>) {
+        self.shutdown();
+        *self = Self::start(state, port.unwrap_or(self.port));
+    }
+}
+
+impl Drop for ModbusServerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+async fn serve(
+    state: ModbusState,
+    listener: Option<std::net::TcpListener>,
+    shutdown_signal: oneshot::Receiver<()>,
+) {
+    let Some(listener) = listener.and_then(|listener| TcpListener::from_std(listener).ok()) else {
+        return;
+    };
+    let Ok(socket_addr) = listener.local_addr() else {
+        return;
+    };
+    let server = Server::new(listener);
+    let new_service = |addr| Ok(BevyService::connect(&state, addr, "modbus"));
+    let on_connected = |stream, socket_addr| async move {
+        accept_tcp_connection(stream, socket_addr, new_service)
+    };
+    let on_process_error = |err| eprintln!("{err}");
+    println!("Modbus server running on {socket_addr}");
+
+    // Dropping the server future closes the listener; connection tasks end with the runtime
+    tokio::select! {
+        _ = server.serve(&on_connected, on_process_error) => {}
+        _ = shutdown_signal => {}
+    }
+}
+
+fn start_modbus_server(
+    mut commands: Commands,
+    settings: Res<ModbusSettings>,
+    modbus_state: Res<ModbusState>,
+) {
+    commands.insert_resource(ModbusServerHandle::start(modbus_state.clone(), settings.port));
+}
+
+pub fn handle_modbus_server_commands(
+    mut server_commands: EventReader<ModbusServerCommand>,
+    handle: Option<ResMut<ModbusServerHandle>>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Some(mut handle) = handle else {
+        return;
+    };
+
+    for command in server_commands.read() {
+        match *command {
+            ModbusServerCommand::Shutdown => handle.shutdown(),
+            ModbusServerCommand::Restart { port } => {
+                handle.restart(modbus_state.clone(), port);
+            }
+        }
+    }
+}
+
+/// Hands the impairments set by the scenario, the timeline or the REST API
+/// to the server.
+pub fn share_network_impairment(
+    impairment: Option<Res<NetworkImpairment>>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Some(impairment) = impairment.filter(|impairment| impairment.is_changed()) else {
+        return;
+    };
+    if let Ok(mut shared) = modbus_state.impairment.lock() {
+        *shared = impairment.clone();
+    }
+}
+
+/// Hands the scenario's client limits to the server.
+pub fn share_client_policy(policy: Option<Res<ClientPolicy>>, modbus_state: Res<ModbusState>) {
+    let Some(policy) = policy.filter(|policy| policy.is_changed()) else {
+        return;
+    };
+    if let Ok(mut sessions) = modbus_state.sessions.lock() {
+        sessions.set_policy(policy.clone());
+    }
+}
+
+pub fn shutdown_modbus_server_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    handle: Option<ResMut<ModbusServerHandle>>,
+) {
+    if exit_events.read().next().is_some() {
+        if let Some(mut handle) = handle {
+            handle.shutdown();
+        }
+    }
 }
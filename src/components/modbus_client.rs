@@ -0,0 +1,269 @@
+// modbus_client.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{Context as _, anyhow};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use tokio_modbus::{
+    client::{Context, Reader, Writer, rtu, tcp},
+    prelude::Slave,
+};
+
+use super::{
+    arbitration::WriteOrigin,
+    modbus::ModbusState,
+    tags::{Tag, TagKind, TagRegistry},
+};
+
+// >>> Constants <<<
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+// >>> Configuration <<<
+/// How to reach the external device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "transport", rename_all = "snake_case")]
+pub enum ClientTransport {
+    Tcp { address: String },
+    Rtu { path: String, baud_rate: u32 },
+}
+
+/// Which way a mapped value flows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingDirection {
+    Read,  // Remote value drives a local coil or holding register
+    Write, // Local tag value is written to a remote coil or holding register
+}
+
+/// Ties a remote address to a local tag.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientMapping {
+    pub tag: String,
+    pub table: TagKind,
+    pub address: u16,
+    pub direction: MappingDirection,
+}
+
+/// Remote device ModuSim polls as a Modbus client, loadable from TOML.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModbusClientConfig {
+    #[serde(flatten)]
+    pub transport: ClientTransport,
+    #[serde(default = "default_unit_id")]
+    pub unit_id: u8,
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    pub mappings: Vec<ClientMapping>,
+}
+
+fn default_unit_id() -> u8 {
+    1
+}
+
+fn default_poll_interval_ms() -> u64 {
+    100
+}
+
+impl ModbusClientConfig {
+    pub fn from_toml(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: Self = toml::from_str(&contents)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Rejects mappings that could never transfer a value.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for mapping in &self.mappings {
+            if mapping.direction == MappingDirection::Write && !mapping.table.is_writable() {
+                anyhow::bail!(
+                    "Mapping for {} writes to a read-only remote {:?}",
+                    mapping.tag,
+                    mapping.table
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct ModbusClientSettings {
+    pub config: ModbusClientConfig,
+}
+
+#[derive(Resource, Default)]
+pub struct ModbusClientState {
+    tags: Arc<Mutex<Vec<Tag>>>,
+}
+
+// >>> Client <<<
+async fn connect(config: &ModbusClientConfig) -> anyhow::Result<Context> {
+    let slave = Slave(config.unit_id);
+    match &config.transport {
+        ClientTransport::Tcp { address } => {
+            let socket_addr = tokio::net::lookup_host(address)
+                .await?
+                .next()
+                .ok_or_else(|| anyhow!("Could not resolve {address}"))?;
+            Ok(tcp::connect_slave(socket_addr, slave).await?)
+        }
+        ClientTransport::Rtu { path, baud_rate } => {
+            let serial = tokio_serial::SerialStream::open(&tokio_serial::new(path, *baud_rate))?;
+            Ok(rtu::attach_slave(serial, slave))
+        }
+    }
+}
+
+async fn read_remote(context: &mut Context, table: TagKind, address: u16) -> anyhow::Result<u16> {
+    let value = match table {
+        TagKind::Coil => context
+            .read_coils(address, 1)
+            .await?
+            .map(|bits| bits.first().map(|&bit| bit as u16)),
+        TagKind::DiscreteInput => context
+            .read_discrete_inputs(address, 1)
+            .await?
+            .map(|bits| bits.first().map(|&bit| bit as u16)),
+        TagKind::InputRegister => context
+            .read_input_registers(address, 1)
+            .await?
+            .map(|words| words.first().copied()),
+        TagKind::HoldingRegister => context
+            .read_holding_registers(address, 1)
+            .await?
+            .map(|words| words.first().copied()),
+    };
+    value
+        .map_err(|code| anyhow!("Remote exception {code:?} reading {address:#06x}"))?
+        .ok_or_else(|| anyhow!("Remote sent no value reading {address:#06x}"))
+}
+
+async fn write_remote(
+    context: &mut Context,
+    table: TagKind,
+    address: u16,
+    value: u16,
+) -> anyhow::Result<()> {
+    let result = match table {
+        TagKind::Coil => context.write_single_coil(address, value != 0).await?,
+        TagKind::HoldingRegister => context.write_single_register(address, value).await?,
+        TagKind::DiscreteInput | TagKind::InputRegister => {
+            anyhow::bail!("Remote {table:?} {address:#06x} is read-only")
+        }
+    };
+    result.map_err(|code| anyhow!("Remote exception {code:?} writing {address:#06x}"))
+}
+
+/// Transfers every mapping once.
+async fn poll(
+    context: &mut Context,
+    config: &ModbusClientConfig,
+    tags: &Mutex<Vec<Tag>>,
+    modbus_state: &ModbusState,
+) -> anyhow::Result<()> {
+    let origin = WriteOrigin::remote("modbus client");
+    for mapping in &config.mappings {
+        let tag = tags
+            .lock()
+            .map_err(|err| anyhow!("{err}"))?
+            .iter()
+            .find(|tag| tag.name == mapping.tag)
+            .cloned();
+        let Some(tag) = tag else {
+            continue; // Tags appear once the scenario has spawned
+        };
+
+        match mapping.direction {
+            MappingDirection::Read => {
+                let value = read_remote(context, mapping.table, mapping.address).await?;
+                if tag.read(modbus_state) != Some(value) && !tag.write(modbus_state, value, &origin)
+                {
+                    eprintln!("Modbus client cannot write read-only tag {}", tag.name);
+                }
+            }
+            MappingDirection::Write => {
+                if let Some(value) = tag.read(modbus_state) {
+                    write_remote(context, mapping.table, mapping.address, value).await?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_client(config: ModbusClientConfig, tags: Arc<Mutex<Vec<Tag>>>, modbus_state: ModbusState) {
+    let mut interval = tokio::time::interval(Duration::from_millis(config.poll_interval_ms.max(1)));
+
+    loop {
+        let mut context = match connect(&config).await {
+            Ok(context) => context,
+            Err(err) => {
+                eprintln!("Modbus client failed to connect: {err}");
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+        println!("Modbus client connected to {:?}", config.transport);
+
+        loop {
+            interval.tick().await;
+            if let Err(err) = poll(&mut context, &config, &tags, &modbus_state).await {
+                eprintln!("Modbus client poll failed, reconnecting: {err}");
+                break;
+            }
+        }
+
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+// >>> Systems <<<
+fn start_modbus_client(
+    settings: Res<ModbusClientSettings>,
+    client_state: Res<ModbusClientState>,
+    modbus_state: Res<ModbusState>,
+) {
+    let config = settings.config.clone();
+    let tags = client_state.tags.clone();
+    let state = modbus_state.clone();
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(run_client(config, tags, state));
+    });
+}
+
+pub fn sync_modbus_client_tags(tags: Res<TagRegistry>, client_state: Res<ModbusClientState>) {
+    if tags.is_changed() {
+        if let Ok(mut shared) = client_state.tags.lock() {
+            *shared = tags.iter().cloned().collect();
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Polls an external Modbus device and maps its registers onto plant tags,
+/// e.g. so a physical push-button box can drive the simulated line.
+pub struct ModbusClientPlugin {
+    pub config: ModbusClientConfig,
+}
+
+impl Plugin for ModbusClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ModbusClientSettings {
+            config: self.config.clone(),
+        })
+        .init_resource::<ModbusClientState>()
+        .add_systems(Startup, start_modbus_client)
+        .add_systems(Update, sync_modbus_client_tags);
+    }
+}
@@ -0,0 +1,278 @@
+// modbus_tls.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+    fs::File,
+    future::Future,
+    io::{self, BufReader},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll, ready},
+    time::Duration,
+};
+
+use anyhow::Context;
+use bevy::prelude::*;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpListener, TcpStream},
+    sync::oneshot,
+    time::{Timeout, timeout},
+};
+use tokio_modbus::server::tcp::Server;
+use tokio_rustls::{
+    Accept, TlsAcceptor,
+    rustls::{
+        RootCertStore, ServerConfig, pki_types::CertificateDer, server::WebPkiClientVerifier,
+    },
+    server::TlsStream,
+};
+
+use super::modbus::{BevyService, MODBUS_IP, ModbusServerHandle, ModbusState};
+
+// >>> Constants <<<
+const MODBUS_TLS_PORT: u16 = 5802; // Unprivileged stand-in for Modbus/TCP Security's 802
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// >>> Certificates <<<
+/// Certificates of the secure Modbus listener, as PEM files.
+#[derive(Debug, Clone)]
+pub struct TlsCertificates {
+    pub cert: PathBuf,              // The server's certificate chain
+    pub key: PathBuf,               // The server's private key
+    pub client_ca: Option<PathBuf>, // CA clients must present a certificate from, if any
+}
+
+impl TlsCertificates {
+    /// Builds the TLS configuration of the listener, requiring client
+    /// certificates when a client CA is given, as Modbus/TCP Security does.
+    pub fn server_config(&self) -> anyhow::Result<Arc<ServerConfig>> {
+        let certs = load_certs(&self.cert)?;
+        let key = rustls_pemfile::private_key(&mut open(&self.key)?)
+            .with_context(|| format!("Failed to read {}", self.key.display()))?
+            .with_context(|| format!("No private key in {}", self.key.display()))?;
+
+        let builder = match &self.client_ca {
+            Some(client_ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in load_certs(client_ca)? {
+                    roots.add(cert)?;
+                }
+                ServerConfig::builder()
+                    .with_client_cert_verifier(WebPkiClientVerifier::builder(roots.into()).build()?)
+            }
+            None => ServerConfig::builder().with_no_client_auth(),
+        };
+        Ok(Arc::new(builder.with_single_cert(certs, key)?))
+    }
+}
+
+fn open(path: &Path) -> anyhow::Result<BufReader<File>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    Ok(BufReader::new(file))
+}
+
+fn load_certs(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let certs = rustls_pemfile::certs(&mut open(path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates in {}", path.display());
+    }
+    Ok(certs)
+}
+
+// >>> Streams <<<
+/// A client's TLS stream, finishing the handshake on first use. The handshake
+/// then runs in the client's own task, so a client stalling in it holds up
+/// no one else's connection.
+enum HandshakingStream {
+    Handshaking {
+        accept: Pin<Box<Timeout<Accept<TcpStream>>>>,
+        socket_addr: SocketAddr,
+    },
+    Ready(Box<TlsStream<TcpStream>>),
+}
+
+impl HandshakingStream {
+    fn new(acceptor: &TlsAcceptor, stream: TcpStream, socket_addr: SocketAddr) -> Self {
+        Self::Handshaking {
+            accept: Box::pin(timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream))),
+            socket_addr,
+        }
+    }
+
+    /// Drives the handshake, failing the connection if it fails or times out.
+    fn poll_handshake(
+        &mut self,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<io::Result<&mut TlsStream<TcpStream>>> {
+        if let Self::Handshaking {
+            accept,
+            socket_addr,
+        } = self
+        {
+            let stream = match ready!(accept.as_mut().poll(cx)) {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(err)) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        err.kind(),
+                        format!("TLS handshake with {socket_addr} failed: {err}"),
+                    )));
+                }
+                Err(_) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("TLS handshake with {socket_addr} timed out"),
+                    )));
+                }
+            };
+            *self = Self::Ready(Box::new(stream));
+        }
+
+        match self {
+            Self::Ready(stream) => Poll::Ready(Ok(stream)),
+            Self::Handshaking { .. } => unreachable!("The handshake finished above"),
+        }
+    }
+}
+
+impl AsyncRead for HandshakingStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let stream = ready!(self.get_mut().poll_handshake(cx))?;
+        Pin::new(stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for HandshakingStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let stream = ready!(self.get_mut().poll_handshake(cx))?;
+        Pin::new(stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        let stream = ready!(self.get_mut().poll_handshake(cx))?;
+        Pin::new(stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        let stream = ready!(self.get_mut().poll_handshake(cx))?;
+        Pin::new(stream).poll_shutdown(cx)
+    }
+}
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct ModbusTlsSettings {
+    pub port: u16,
+    pub config: Arc<ServerConfig>,
+}
+
+/// The running secure Modbus server thread.
+#[derive(Resource)]
+pub struct ModbusTlsServerHandle(pub ModbusServerHandle);
+
+// >>> Server <<<
+async fn serve_tls(
+    state: ModbusState,
+    port: u16,
+    config: Arc<ServerConfig>,
+    shutdown_signal: oneshot::Receiver<()>,
+) {
+    let socket_addr: SocketAddr = format!("{}:{}", MODBUS_IP, port).parse().unwrap();
+    let listener = match TcpListener::bind(socket_addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Secure Modbus server failed to bind {socket_addr}: {err}");
+            return;
+        }
+    };
+    let server = Server::new(listener);
+    let acceptor = TlsAcceptor::from(config);
+    let on_connected = |stream, socket_addr| {
+        // Only the session limit is checked here; the handshake runs in the
+        // client's own task, and failing it ends that task and its session
+        let service = BevyService::connect(&state, socket_addr, "modbus tls");
+        let stream = HandshakingStream::new(&acceptor, stream, socket_addr);
+        async move { Ok(service.map(|service| (service, stream))) }
+    };
+    let on_process_error = |err| eprintln!("{err}");
+    println!("Secure Modbus server running on {socket_addr}");
+
+    tokio::select! {
+        _ = server.serve(&on_connected, on_process_error) => {}
+        _ = shutdown_signal => {}
+    }
+}
+
+// >>> Systems <<<
+fn start_modbus_tls_server(
+    mut commands: Commands,
+    settings: Res<ModbusTlsSettings>,
+    modbus_state: Res<ModbusState>,
+) {
+    let (state, port, config) = (modbus_state.clone(), settings.port, settings.config.clone());
+    commands.insert_resource(ModbusTlsServerHandle(ModbusServerHandle::spawn(
+        port,
+        move |shutdown_signal| serve_tls(state, port, config, shutdown_signal),
+    )));
+}
+
+pub fn shutdown_modbus_tls_server_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    handle: Option<ResMut<ModbusTlsServerHandle>>,
+) {
+    if exit_events.read().next().is_some() {
+        if let Some(mut handle) = handle {
+            handle.0.shutdown();
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// TLS-wrapped Modbus listener, serving the same register tables as the
+/// plain Modbus server for labs on secure industrial communications. Needs
+/// the `tls` feature.
+pub struct ModbusTlsPlugin {
+    pub port: u16,
+    pub certificates: TlsCertificates,
+}
+
+impl ModbusTlsPlugin {
+    pub fn new(certificates: TlsCertificates) -> Self {
+        Self {
+            port: MODBUS_TLS_PORT,
+            certificates,
+        }
+    }
+}
+
+impl Plugin for ModbusTlsPlugin {
+    fn build(&self, app: &mut App) {
+        let config = match self.certificates.server_config() {
+            Ok(config) => config,
+            Err(err) => {
+                error!("Not starting the secure Modbus server: {err:#}");
+                return;
+            }
+        };
+
+        app.insert_resource(ModbusTlsSettings {
+            port: self.port,
+            config,
+        })
+        .add_systems(Startup, start_modbus_tls_server)
+        .add_systems(Last, shutdown_modbus_tls_server_on_exit);
+    }
+}
@@ -0,0 +1,227 @@
+// mode.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{arbitration::WriteOrigin, hmi::HmiPanel, modbus::ModbusState, tags::TagKind};
+
+// >>> Constants <<<
+const AUTO_COLOR: Color = Color::srgb(0.1, 0.5, 0.2);
+const MANUAL_COLOR: Color = Color::srgb(0.6, 0.4, 0.1);
+const MODE_FONT_SIZE: f32 = 16.0;
+
+// >>> Resources <<<
+/// Auto/manual selector deciding who drives the actuators, inserted by
+/// scenarios with a `[mode]` table.
+///
+/// In auto the PLC's writes drive conveyors, valves, labelers and diverters,
+/// and local keyboard, gamepad and click controls are rejected; the I/O can
+/// still be forced from the device popup. In manual the local controls drive
+/// them and Modbus writes to their registers are ignored. The mode is on a
+/// coil, ON in auto, that the PLC can write and the operator can toggle with
+/// O or by clicking the mode panel.
+#[derive(Resource, Debug, Clone)]
+pub struct OperatingMode {
+    pub auto_coil_address: u16,
+    pub is_auto: bool,
+}
+
+impl OperatingMode {
+    /// Whether Modbus writes drive the actuators.
+    pub fn allows_remote(&self) -> bool {
+        self.is_auto
+    }
+
+    /// Whether the keyboard, gamepad and clicks drive the actuators.
+    pub fn allows_local(&self) -> bool {
+        !self.is_auto
+    }
+
+    pub fn description(&self) -> &'static str {
+        if self.is_auto { "AUTO" } else { "MANUAL" }
+    }
+}
+
+/// Whether local controls may drive the actuators, warning when they are
+/// rejected. Without a mode selector both local and remote control apply.
+pub fn local_control_allowed(mode: Option<&OperatingMode>) -> bool {
+    let is_allowed = mode.is_none_or(OperatingMode::allows_local);
+    if !is_allowed {
+        warn!("Local control rejected in auto mode; force the I/O to override the PLC");
+    }
+    is_allowed
+}
+
+/// Whether Modbus writes may drive the actuators.
+pub fn remote_control_allowed(mode: Option<&OperatingMode>) -> bool {
+    mode.is_none_or(OperatingMode::allows_remote)
+}
+
+// >>> Components <<<
+#[derive(Component)]
+pub struct ModePanel;
+
+#[derive(Component)]
+pub struct ModePanelText;
+
+// >>> Systems <<<
+pub fn register_operating_mode(mode: Option<Res<OperatingMode>>, modbus_state: Res<ModbusState>) {
+    let Some(mode) = mode else {
+        return;
+    };
+    if !mode.is_added() {
+        return;
+    }
+
+    if let Ok(mut coils) = modbus_state.coils.lock() {
+        coils.insert(mode.auto_coil_address, mode.is_auto);
+    }
+
+    info!(
+        "Registered operating mode on auto coil: {:x?}",
+        mode.auto_coil_address
+    );
+}
+
+pub fn setup_mode_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            ModePanel,
+            HmiPanel,
+            Button,
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(0.0),
+                right: Val::Px(0.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(MANUAL_COLOR),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ModePanelText,
+                Text::new(""),
+                TextFont {
+                    font_size: MODE_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// Shows the mode while the scenario has a selector.
+pub fn update_mode_panel(
+    mode: Option<Res<OperatingMode>>,
+    mut panels: Query<(&mut Visibility, &mut BackgroundColor), With<ModePanel>>,
+    mut texts: Query<&mut Text, With<ModePanelText>>,
+) {
+    for (mut visibility, mut background) in panels.iter_mut() {
+        visibility.set_if_neq(if mode.is_some() {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        });
+        if let Some(mode) = &mode {
+            background.set_if_neq(BackgroundColor(if mode.is_auto {
+                AUTO_COLOR
+            } else {
+                MANUAL_COLOR
+            }));
+        }
+    }
+
+    let Some(mode) = mode else {
+        return;
+    };
+    for mut text in texts.iter_mut() {
+        text.0 = format!("Mode: {}", mode.description());
+    }
+}
+
+// >>> Input System <<<
+pub fn handle_mode_input(
+    mode: Option<ResMut<OperatingMode>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    panels: Query<&Interaction, (Changed<Interaction>, With<ModePanel>)>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Some(mut mode) = mode else {
+        return;
+    };
+
+    let is_clicked = panels
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed);
+    if keyboard.just_pressed(KeyCode::KeyO) || is_clicked {
+        mode.is_auto = !mode.is_auto;
+        info!("Operating mode: {}", mode.description());
+
+        let origin = WriteOrigin::hmi(if is_clicked {
+            "mouse"
+        } else {
+            "local controls"
+        });
+        let is_auto = mode.is_auto as u16;
+        modbus_state.write(TagKind::Coil, mode.auto_coil_address, is_auto, &origin);
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_modbus_to_operating_mode(
+    mode: Option<ResMut<OperatingMode>>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Some(mut mode) = mode else {
+        return;
+    };
+
+    if let Ok(coils) = modbus_state.coils.lock() {
+        if let Some(&coil_state) = coils.get(&mode.auto_coil_address) {
+            if mode.is_auto != coil_state {
+                mode.is_auto = coil_state;
+                info!("Operating mode set to: {}", mode.description());
+            }
+        }
+    }
+}
+
+pub fn sync_operating_mode_to_modbus(
+    mode: Option<Res<OperatingMode>>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Some(mode) = mode else {
+        return;
+    };
+    if !mode.is_changed() {
+        return;
+    }
+
+    if let Ok(mut coils) = modbus_state.coils.lock() {
+        coils.insert(mode.auto_coil_address, mode.is_auto);
+    }
+}
+
+// >>> Plugin <<<
+/// Auto/manual mode selector arbitrating between the PLC and the local
+/// controls, on a coil and a clickable panel.
+pub struct ModePlugin;
+
+impl Plugin for ModePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_mode_panel)
+            .add_systems(Update, (handle_mode_input, update_mode_panel).chain())
+            .add_systems(
+                FixedUpdate,
+                (
+                    register_operating_mode,
+                    sync_operating_mode_to_modbus,
+                    sync_modbus_to_operating_mode,
+                )
+                    .chain(),
+            );
+    }
+}
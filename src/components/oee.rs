@@ -0,0 +1,300 @@
+// oee.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    conveyor::ConveyorState,
+    hmi::HmiPanel,
+    labeler::LabelQuality,
+    modbus::ModbusState,
+    outfeed::BottleCompleted,
+    simulation::{ResetSimulation, SimTime},
+};
+
+// >>> Constants <<<
+const FULL_SCALE: f32 = 10000.0; // Register value of 100 percent
+const OEE_PANEL_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+const OEE_FONT_SIZE: f32 = 16.0;
+
+// >>> Components <<<
+/// Overall equipment effectiveness of the line, measured from the first time
+/// a conveyor starts.
+///
+/// Availability is the share of that time any conveyor was moving,
+/// performance the bottles completed against what the ideal cycle time allows
+/// in the moving time, and quality the share of completed bottles that are
+/// good. Each is published in hundredths of a percent, e.g. 9250 for 92.5 %.
+#[derive(Component, Debug, Clone)]
+pub struct OeeMonitor {
+    pub availability_input_address: u16,
+    pub performance_input_address: u16,
+    pub quality_input_address: u16,
+    pub oee_input_address: u16,
+    pub ideal_cycle_time: f32, // Seconds per bottle at full speed
+    pub min_fill: u32,         // Balls a good bottle holds at least
+    pub require_cap: bool,
+    pub require_label: bool,
+    pub planned_time: f32, // Seconds since a conveyor first started
+    pub run_time: f32,     // Seconds a conveyor was moving
+    pub total: u32,
+    pub good: u32,
+}
+
+impl OeeMonitor {
+    /// Creates an OEE monitor with nothing measured yet.
+    ///
+    /// # Parameters
+    /// * `availability_input_address` - Input register holding the availability
+    /// * `performance_input_address` - Input register holding the performance
+    /// * `quality_input_address` - Input register holding the quality
+    /// * `oee_input_address` - Input register holding the OEE
+    /// * `ideal_cycle_time` - Seconds per bottle at full speed
+    ///
+    /// # Return
+    /// The OEE monitor, counting every completed bottle as good
+    pub fn new(
+        availability_input_address: u16,
+        performance_input_address: u16,
+        quality_input_address: u16,
+        oee_input_address: u16,
+        ideal_cycle_time: f32,
+    ) -> Self {
+        Self {
+            availability_input_address,
+            performance_input_address,
+            quality_input_address,
+            oee_input_address,
+            ideal_cycle_time,
+            min_fill: 0,
+            require_cap: false,
+            require_label: false,
+            planned_time: 0.0,
+            run_time: 0.0,
+            total: 0,
+            good: 0,
+        }
+    }
+
+    /// Forgets everything measured, as if the line had never started.
+    pub fn reset(&mut self) {
+        self.planned_time = 0.0;
+        self.run_time = 0.0;
+        self.total = 0;
+        self.good = 0;
+    }
+
+    pub fn is_good(&self, bottle: &BottleCompleted) -> bool {
+        bottle.fill_level >= self.min_fill
+            && (!self.require_cap || bottle.is_capped)
+            && (!self.require_label || bottle.label == Some(LabelQuality::Good))
+    }
+
+    pub fn availability(&self) -> f32 {
+        if self.planned_time > 0.0 {
+            self.run_time / self.planned_time
+        } else {
+            0.0
+        }
+    }
+
+    pub fn performance(&self) -> f32 {
+        if self.run_time > 0.0 {
+            (self.ideal_cycle_time * self.total as f32 / self.run_time).min(1.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn quality(&self) -> f32 {
+        if self.total > 0 {
+            self.good as f32 / self.total as f32
+        } else {
+            0.0
+        }
+    }
+
+    pub fn oee(&self) -> f32 {
+        self.availability() * self.performance() * self.quality()
+    }
+}
+
+#[derive(Component)]
+pub struct OeePanel;
+
+#[derive(Component)]
+pub struct OeePanelText;
+
+fn register_value(ratio: f32) -> u16 {
+    (ratio * FULL_SCALE).round().clamp(0.0, FULL_SCALE) as u16
+}
+
+// >>> Systems <<<
+pub fn register_oee_monitors(
+    monitors: Query<&OeeMonitor, Added<OeeMonitor>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for monitor in monitors.iter() {
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(monitor.availability_input_address, 0);
+            inputs.insert(monitor.performance_input_address, 0);
+            inputs.insert(monitor.quality_input_address, 0);
+            inputs.insert(monitor.oee_input_address, 0);
+        }
+
+        info!(
+            "Registered OEE monitor on input register: {:x?}",
+            monitor.oee_input_address
+        );
+    }
+}
+
+pub fn reset_oee_monitors(
+    mut reset_events: EventReader<ResetSimulation>,
+    mut monitors: Query<&mut OeeMonitor>,
+) {
+    if reset_events.read().count() == 0 {
+        return;
+    }
+    for mut monitor in monitors.iter_mut() {
+        monitor.reset();
+    }
+}
+
+/// Times the line's planned and moving time and counts the bottles it
+/// completes.
+pub fn update_oee(
+    time: Res<SimTime>,
+    mut completed_events: EventReader<BottleCompleted>,
+    conveyors: Query<&ConveyorState>,
+    mut monitors: Query<&mut OeeMonitor>,
+) {
+    let delta = time.delta_secs();
+    let is_moving = conveyors.iter().any(ConveyorState::is_moving);
+    let completed: Vec<&BottleCompleted> = completed_events.read().collect();
+
+    for mut monitor in monitors.iter_mut() {
+        if monitor.planned_time > 0.0 || is_moving {
+            monitor.planned_time += delta;
+        }
+        if is_moving {
+            monitor.run_time += delta;
+        }
+
+        for bottle in &completed {
+            monitor.total += 1;
+            if monitor.is_good(bottle) {
+                monitor.good += 1;
+            }
+        }
+    }
+}
+
+pub fn setup_oee_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            OeePanel,
+            HmiPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(36.0),
+                right: Val::Px(0.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(OEE_PANEL_COLOR),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                OeePanelText,
+                Text::new(""),
+                TextFont {
+                    font_size: OEE_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// Shows the OEE and its factors while the scenario has a monitor.
+pub fn update_oee_panel(
+    monitors: Query<&OeeMonitor>,
+    mut panels: Query<&mut Visibility, With<OeePanel>>,
+    mut texts: Query<&mut Text, With<OeePanelText>>,
+) {
+    let monitor = monitors.iter().next();
+    for mut visibility in panels.iter_mut() {
+        visibility.set_if_neq(if monitor.is_some() {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        });
+    }
+
+    let Some(monitor) = monitor else {
+        return;
+    };
+    for mut text in texts.iter_mut() {
+        text.0 = format!(
+            "OEE {:.1} %\nAvailability {:.1} %\nPerformance {:.1} %\nQuality {:.1} %",
+            monitor.oee() * 100.0,
+            monitor.availability() * 100.0,
+            monitor.performance() * 100.0,
+            monitor.quality() * 100.0,
+        );
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_oee_to_modbus(
+    monitors: Query<&OeeMonitor, Changed<OeeMonitor>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if monitors.is_empty() {
+        return;
+    }
+
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for monitor in monitors.iter() {
+            inputs.insert(
+                monitor.availability_input_address,
+                register_value(monitor.availability()),
+            );
+            inputs.insert(
+                monitor.performance_input_address,
+                register_value(monitor.performance()),
+            );
+            inputs.insert(
+                monitor.quality_input_address,
+                register_value(monitor.quality()),
+            );
+            inputs.insert(monitor.oee_input_address, register_value(monitor.oee()));
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Overall equipment effectiveness against an ideal cycle time, on input
+/// registers and an on-screen panel.
+pub struct OeePlugin;
+
+impl Plugin for OeePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BottleCompleted>()
+            .add_systems(Startup, setup_oee_panel)
+            .add_systems(Update, update_oee_panel)
+            .add_systems(
+                FixedUpdate,
+                (
+                    register_oee_monitors,
+                    reset_oee_monitors,
+                    update_oee,
+                    sync_oee_to_modbus,
+                )
+                    .chain(),
+            );
+    }
+}
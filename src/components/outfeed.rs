@@ -0,0 +1,166 @@
+// outfeed.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    bottle::{Bottle, BottlePool, BottlePosition, BottleSpec, Capped, FillLevel},
+    diverter::Diverted,
+    labeler::{Label, LabelQuality},
+    modbus::ModbusState,
+    simulation::{ResetSimulation, SimTime},
+    station::StationId,
+};
+
+// >>> Constants <<<
+const OUTFEED_ZONE_SIZE: Vec2 = Vec2::new(20.0, 40.0);
+const OUTFEED_COLOR: Color = Color::srgb(0.2, 0.6, 0.3);
+
+// >>> Components <<<
+/// End of the line, where finished bottles leave the simulation.
+#[derive(Component)]
+pub struct Outfeed {
+    pub counter_input_address: u16,
+    pub count: u16,
+}
+
+// >>> Events <<<
+/// A bottle reached the outfeed and was taken off the line.
+#[derive(Event, Debug, Clone)]
+pub struct BottleCompleted {
+    pub variant: String,
+    pub fill_level: u32,
+    pub label: Option<LabelQuality>,
+    pub is_capped: bool,
+    pub completed_at: f32, // Seconds of simulation time
+    pub station: StationId,
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct OutfeedBundle {
+    outfeed: Outfeed,
+    collider: Collider,
+    collider_sensor: bevy_rapier2d::geometry::Sensor,
+    active_events: ActiveEvents,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl Outfeed {
+    /// Creates an outfeed that removes and counts bottles reaching it.
+    ///
+    /// # Parameters
+    /// * `counter_input_address` - Input register counting completed bottles
+    /// * `position` - Position of the outfeed
+    ///
+    /// # Return
+    /// The outfeed bundle
+    pub fn new(counter_input_address: u16, position: Vec2) -> OutfeedBundle {
+        OutfeedBundle {
+            outfeed: Outfeed {
+                counter_input_address,
+                count: 0,
+            },
+            collider: Collider::cuboid(OUTFEED_ZONE_SIZE.x / 2.0, OUTFEED_ZONE_SIZE.y / 2.0),
+            collider_sensor: bevy_rapier2d::geometry::Sensor,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            sprite: Sprite::from_color(OUTFEED_COLOR, OUTFEED_ZONE_SIZE),
+            transform: Transform::from_translation(position.extend(0.0)),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn reset_outfeeds(
+    mut reset_events: EventReader<ResetSimulation>,
+    mut outfeeds: Query<&mut Outfeed>,
+) {
+    if reset_events.read().count() == 0 {
+        return;
+    }
+    for mut outfeed in outfeeds.iter_mut() {
+        outfeed.count = 0;
+    }
+}
+
+pub fn complete_bottles(
+    time: Res<SimTime>,
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut outfeeds: Query<&mut Outfeed>,
+    bottles: Query<
+        (
+            &BottleSpec,
+            &BottlePosition,
+            &FillLevel,
+            &StationId,
+            Option<&Label>,
+            Has<Capped>,
+        ),
+        (With<Bottle>, Without<Diverted>),
+    >,
+    mut pool: ResMut<BottlePool>,
+    mut completed_events: EventWriter<BottleCompleted>,
+) {
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(e1, e2, _) = collision_event else {
+            continue;
+        };
+
+        let (outfeed_entity, bottle_entity) = if outfeeds.contains(*e1) {
+            (*e1, *e2)
+        } else if outfeeds.contains(*e2) {
+            (*e2, *e1)
+        } else {
+            continue;
+        };
+
+        let Ok((spec, BottlePosition(position), fill_level, station, label, is_capped)) =
+            bottles.get(bottle_entity)
+        else {
+            continue;
+        };
+
+        let mut outfeed = outfeeds.get_mut(outfeed_entity).unwrap();
+        outfeed.count = outfeed.count.wrapping_add(1);
+
+        completed_events.write(BottleCompleted {
+            variant: spec.name.clone(),
+            fill_level: fill_level.0,
+            label: label.map(|label| label.quality),
+            is_capped,
+            completed_at: time.elapsed_secs(),
+            station: *station,
+        });
+        pool.release(&mut commands, bottle_entity, spec, *position);
+
+        info!("Outfeed count: {}", outfeed.count);
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_outfeeds_to_modbus(
+    outfeeds: Query<&Outfeed, Changed<Outfeed>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for outfeed in outfeeds.iter() {
+            inputs.insert(outfeed.counter_input_address, outfeed.count);
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct OutfeedPlugin;
+
+impl Plugin for OutfeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BottleCompleted>().add_systems(
+            FixedUpdate,
+            (reset_outfeeds, complete_bottles, sync_outfeeds_to_modbus).chain(),
+        );
+    }
+}
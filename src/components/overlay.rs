@@ -0,0 +1,353 @@
+// overlay.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use super::{
+    hmi::HmiPanel,
+    modbus::ModbusState,
+    simulation::{SimError, SimState},
+    tags::{DeviceName, RegisterMap, Tag, TagRegistry},
+};
+
+// >>> Constants <<<
+const OVERLAY_Z: f32 = 60.0; // Above the lighting overlay
+const REGISTER_LABEL_OFFSET: Vec2 = Vec2::new(0.0, -20.0);
+const REGISTER_LABEL_COLOR: Color = Color::srgb(1.0, 0.9, 0.3);
+pub const FORCED_COLOR: Color = Color::srgb(1.0, 0.3, 0.9); // Labels of devices with forced I/O
+const REGISTER_FONT_SIZE: f32 = 10.0;
+const NAME_LABEL_OFFSET: Vec2 = Vec2::new(0.0, 20.0);
+const NAME_LABEL_COLOR: Color = Color::WHITE;
+const NAME_FONT_SIZE: f32 = 12.0;
+const WARNING_COLOR: Color = Color::srgb(0.6, 0.1, 0.1);
+const WARNING_FONT_SIZE: f32 = 14.0;
+const ERROR_SCREEN_COLOR: Color = Color::srgba(0.3, 0.0, 0.0, 0.9);
+const ERROR_FONT_SIZE: f32 = 18.0;
+
+// >>> Components <<<
+/// Floating text listing the live registers of its owning device.
+#[derive(Component)]
+pub struct RegisterLabel {
+    pub owner: Entity,
+}
+
+/// Floating text naming its owning device.
+#[derive(Component)]
+pub struct NameLabel {
+    pub owner: Entity,
+}
+
+/// Panel listing addresses claimed by more than one tag.
+#[derive(Component)]
+pub struct AddressWarning;
+
+#[derive(Component)]
+pub struct AddressWarningText;
+
+/// Screen covering the plant while the simulation is stopped on an error.
+#[derive(Component)]
+pub struct ErrorScreen;
+
+// >>> Resources <<<
+/// Debug overlays drawn over the plant.
+#[derive(Resource, Default)]
+pub struct OverlaySettings {
+    pub show_registers: bool,
+    pub show_names: bool,
+}
+
+// >>> Systems <<<
+/// Gives each device with registers a hidden label.
+pub fn spawn_register_labels(
+    mut commands: Commands,
+    devices: Query<Entity, (Added<RegisterMap>, With<Transform>)>,
+) {
+    for owner in devices.iter() {
+        commands.spawn((
+            RegisterLabel { owner },
+            Text2d::new(""),
+            TextFont {
+                font_size: REGISTER_FONT_SIZE,
+                ..default()
+            },
+            TextColor(REGISTER_LABEL_COLOR),
+            Anchor::TopCenter,
+            Transform::from_xyz(0.0, 0.0, OVERLAY_Z),
+            Visibility::Hidden,
+        ));
+    }
+}
+
+/// Writes each label's register values and moves it under its device. Labels
+/// out of view keep their last text, so large scenes only lay out the few on
+/// screen, and a label's text is only replaced when a value changed.
+pub fn update_register_labels(
+    mut commands: Commands,
+    mut labels: Query<(
+        Entity,
+        &RegisterLabel,
+        &mut Text2d,
+        &mut TextColor,
+        &mut Transform,
+        &mut Visibility,
+        &ViewVisibility,
+    )>,
+    devices: Query<(&RegisterMap, &GlobalTransform)>,
+    settings: Res<OverlaySettings>,
+    modbus_state: Res<ModbusState>,
+) {
+    for (entity, label, mut text, mut color, mut transform, mut visibility, view_visibility) in
+        labels.iter_mut()
+    {
+        // The device was despawned, e.g. by a scenario reload
+        let Ok((register_map, device_transform)) = devices.get(label.owner) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        if !settings.show_registers {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        }
+        visibility.set_if_neq(Visibility::Visible);
+
+        let position = device_transform.translation().truncate() + REGISTER_LABEL_OFFSET;
+        transform.translation = position.extend(OVERLAY_Z);
+        // A label never written yet has no size to be seen by
+        if !view_visibility.get() && !text.0.is_empty() {
+            continue;
+        }
+
+        let lines: Vec<String> = register_map
+            .0
+            .iter()
+            .map(|tag| register_line(tag, &modbus_state))
+            .collect();
+        let lines = lines.join("\n");
+        if text.0 != lines {
+            text.0 = lines;
+        }
+
+        let is_forced = register_map
+            .0
+            .iter()
+            .any(|tag| modbus_state.forced(tag.kind, tag.address).is_some());
+        color.set_if_neq(TextColor(if is_forced {
+            FORCED_COLOR
+        } else {
+            REGISTER_LABEL_COLOR
+        }));
+    }
+}
+
+/// Gives each named device a hidden label.
+pub fn spawn_name_labels(
+    mut commands: Commands,
+    devices: Query<(Entity, &DeviceName), (Added<DeviceName>, With<Transform>)>,
+) {
+    for (owner, name) in devices.iter() {
+        commands.spawn((
+            NameLabel { owner },
+            Text2d::new(name.0.clone()),
+            TextFont {
+                font_size: NAME_FONT_SIZE,
+                ..default()
+            },
+            TextColor(NAME_LABEL_COLOR),
+            Anchor::BottomCenter,
+            Transform::from_xyz(0.0, 0.0, OVERLAY_Z),
+            Visibility::Hidden,
+        ));
+    }
+}
+
+/// Keeps each name label above its device.
+pub fn update_name_labels(
+    mut commands: Commands,
+    mut labels: Query<(Entity, &NameLabel, &mut Transform, &mut Visibility)>,
+    devices: Query<&GlobalTransform, With<DeviceName>>,
+    settings: Res<OverlaySettings>,
+) {
+    for (entity, label, mut transform, mut visibility) in labels.iter_mut() {
+        let Ok(device_transform) = devices.get(label.owner) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        if !settings.show_names {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        }
+        visibility.set_if_neq(Visibility::Visible);
+
+        let position = device_transform.translation().truncate() + NAME_LABEL_OFFSET;
+        transform.translation = position.extend(OVERLAY_Z);
+    }
+}
+
+pub fn setup_address_warning(mut commands: Commands) {
+    commands
+        .spawn((
+            AddressWarning,
+            HmiPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(0.0),
+                left: Val::Px(0.0),
+                width: Val::Percent(100.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(WARNING_COLOR),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                AddressWarningText,
+                Text::new(""),
+                TextFont {
+                    font_size: WARNING_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// Lists address collisions on screen until a scenario without any is loaded.
+pub fn update_address_warning(
+    tags: Res<TagRegistry>,
+    mut panels: Query<&mut Visibility, With<AddressWarning>>,
+    mut texts: Query<&mut Text, With<AddressWarningText>>,
+) {
+    if !tags.is_changed() {
+        return;
+    }
+
+    let collisions = tags.collisions();
+    for mut visibility in panels.iter_mut() {
+        visibility.set_if_neq(if collisions.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Visible
+        });
+    }
+
+    for mut text in texts.iter_mut() {
+        text.0 = collisions
+            .iter()
+            .map(|collision| format!("Address collision on {collision}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
+
+pub fn show_error_screen(mut commands: Commands, error: Option<Res<SimError>>) {
+    let message = error.map_or_else(String::new, |error| error.0.clone());
+
+    commands
+        .spawn((
+            ErrorScreen,
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            BackgroundColor(ERROR_SCREEN_COLOR),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(format!("Simulation stopped\n\n{message}")),
+                TextFont {
+                    font_size: ERROR_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                TextLayout::new_with_justify(JustifyText::Center),
+            ));
+            parent.spawn((
+                Text::new("Load another scenario to continue, e.g. from the F11 menu"),
+                TextFont {
+                    font_size: WARNING_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+pub fn hide_error_screen(mut commands: Commands, screens: Query<Entity, With<ErrorScreen>>) {
+    for screen in screens.iter() {
+        commands.entity(screen).despawn();
+    }
+}
+
+/// Formats a tag as e.g. `coil 0x0001=ON` or `hold 0x0001=1`, marking
+/// forced values.
+pub fn register_line(tag: &Tag, modbus_state: &ModbusState) -> String {
+    let value = match tag.read(modbus_state) {
+        Some(0) if tag.kind.is_bit() => "OFF".to_string(),
+        Some(_) if tag.kind.is_bit() => "ON".to_string(),
+        Some(value) => value.to_string(),
+        None => "?".to_string(),
+    };
+    let forced = if modbus_state.forced(tag.kind, tag.address).is_some() {
+        " FORCED"
+    } else {
+        ""
+    };
+    format!(
+        "{} 0x{:04X}={}{}",
+        tag.kind.abbreviation(),
+        tag.address,
+        value,
+        forced
+    )
+}
+
+// >>> Input System <<<
+pub fn handle_overlay_input(
+    mut settings: ResMut<OverlaySettings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard.just_pressed(KeyCode::F1) {
+        settings.show_registers = !settings.show_registers;
+        info!("Register overlay: {}", settings.show_registers);
+    }
+    if keyboard.just_pressed(KeyCode::F2) {
+        settings.show_names = !settings.show_names;
+        info!("Device names: {}", settings.show_names);
+    }
+}
+
+// >>> Plugin <<<
+/// Debug overlays showing every device's register addresses and live values
+/// (F1) and its name (F2), a panel listing address collisions, and the
+/// error screen shown while the simulation is stopped on an error.
+pub struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OverlaySettings>()
+            .add_systems(Startup, setup_address_warning)
+            .add_systems(OnEnter(SimState::Error), show_error_screen)
+            .add_systems(OnExit(SimState::Error), hide_error_screen)
+            .add_systems(
+                Update,
+                (
+                    handle_overlay_input,
+                    (spawn_register_labels, update_register_labels).chain(),
+                    (spawn_name_labels, update_name_labels).chain(),
+                    update_address_warning,
+                )
+                    .chain(),
+            );
+    }
+}
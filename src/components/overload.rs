@@ -0,0 +1,263 @@
+// overload.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    alarm::{Alarm, AlarmEvent},
+    bottle::{Bottle, BottlePhysics, BottlePosition, BottleSpec, FillLevel},
+    conveyor::{ConveyorShape, ConveyorState, stands_on_belt},
+    modbus::ModbusState,
+    simulation::SimTime,
+    tag_sync::sync_tags,
+};
+
+// >>> Constants <<<
+const NO_LOAD: f32 = 30.0; // Percent of rated current drawn by the empty running belt
+const FILL_MASS_PER_BALL: f32 = 0.02; // Mass of the liquid each ball in a bottle stands for
+
+// >>> Components <<<
+/// Motor protection of a conveyor, modelled on a thermal overload relay.
+///
+/// The motor current rises with the mass of the bottles on the belt, from
+/// [`NO_LOAD`] percent of rated current when empty to 100 percent at
+/// `rated_mass`. Above `trip_load` percent for `trip_delay` seconds the relay
+/// trips and holds the belt stopped until its reset coil is written.
+#[derive(Component, Debug, Clone)]
+pub struct MotorProtection {
+    pub load_input_address: u16,
+    pub fault_discrete_address: u16,
+    pub reset_coil_address: u16,
+    pub rated_mass: f32,
+    pub trip_load: f32,  // Percent of rated current
+    pub trip_delay: f32, // Seconds
+    pub load: f32,
+    pub is_tripped: bool,
+    overloaded_for: f32,
+}
+
+impl MotorProtection {
+    /// Creates an untripped overload relay.
+    ///
+    /// # Parameters
+    /// * `load_input_address` - Input register holding the motor load in percent of rated current
+    /// * `fault_discrete_address` - Discrete input set while the relay is tripped
+    /// * `reset_coil_address` - Coil that resets the tripped relay
+    /// * `rated_mass` - Mass on the belt at which the motor draws its rated current
+    /// * `trip_load` - Load in percent above which the relay starts timing
+    /// * `trip_delay` - Seconds above `trip_load` before the relay trips
+    ///
+    /// # Return
+    /// The motor protection component
+    pub fn new(
+        load_input_address: u16,
+        fault_discrete_address: u16,
+        reset_coil_address: u16,
+        rated_mass: f32,
+        trip_load: f32,
+        trip_delay: f32,
+    ) -> Self {
+        Self {
+            load_input_address,
+            fault_discrete_address,
+            reset_coil_address,
+            rated_mass,
+            trip_load,
+            trip_delay,
+            load: 0.0,
+            is_tripped: false,
+            overloaded_for: 0.0,
+        }
+    }
+
+    /// Motor load in percent of rated current while driving `mass`.
+    pub fn load_for(&self, mass: f32) -> f32 {
+        motor_load(mass, self.rated_mass)
+    }
+}
+
+/// Load in percent of rated current of a belt motor driving `mass`, rated
+/// for `rated_mass`.
+pub fn motor_load(mass: f32, rated_mass: f32) -> f32 {
+    NO_LOAD + (100.0 - NO_LOAD) * mass / rated_mass.max(f32::EPSILON)
+}
+
+/// Mass of the bottles, and the liquid in them, standing on a belt.
+pub fn belt_mass(
+    transform: &Transform,
+    sprite: &Sprite,
+    shape: &ConveyorShape,
+    bottles: &Query<(&Transform, &BottlePosition, &BottleSpec, &FillLevel), With<Bottle>>,
+    physics: &BottlePhysics,
+) -> f32 {
+    bottles
+        .iter()
+        .filter(|(bottle_transform, position, spec, _)| {
+            let center = position.world_center(bottle_transform);
+            stands_on_belt(transform, sprite, shape, center, spec.height)
+        })
+        .map(|(_, _, spec, fill_level)| {
+            physics.mass.unwrap_or(spec.mass) + fill_level.0 as f32 * FILL_MASS_PER_BALL
+        })
+        .sum()
+}
+
+// >>> Systems <<<
+pub fn register_motor_protection(
+    motors: Query<&MotorProtection, Added<MotorProtection>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for motor in motors.iter() {
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(motor.load_input_address, 0);
+        }
+        if let Ok(mut discrete_inputs) = modbus_state.discrete_inputs.lock() {
+            discrete_inputs.insert(motor.fault_discrete_address, false);
+        }
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(motor.reset_coil_address, false);
+        }
+
+        info!(
+            "Registered motor protection on load input register: {:x?}",
+            motor.load_input_address
+        );
+    }
+}
+
+/// Works out each moving belt's motor load from the bottles standing on it
+/// and trips the relays overloaded for too long.
+pub fn update_motor_loads(
+    time: Res<SimTime>,
+    mut motors: Query<(
+        &mut MotorProtection,
+        &ConveyorState,
+        &Transform,
+        &Sprite,
+        &ConveyorShape,
+    )>,
+    bottles: Query<(&Transform, &BottlePosition, &BottleSpec, &FillLevel), With<Bottle>>,
+    physics: Res<BottlePhysics>,
+) {
+    for (mut motor, conveyor_state, transform, sprite, shape) in motors.iter_mut() {
+        let load = if conveyor_state.is_moving() {
+            motor.load_for(belt_mass(transform, sprite, shape, &bottles, &physics))
+        } else {
+            0.0
+        };
+
+        if motor.load != load {
+            motor.load = load;
+        }
+
+        if motor.is_tripped {
+            continue;
+        }
+        if load > motor.trip_load {
+            motor.overloaded_for += time.delta_secs();
+        } else if motor.overloaded_for > 0.0 {
+            motor.overloaded_for = 0.0;
+        }
+        if motor.overloaded_for >= motor.trip_delay {
+            motor.is_tripped = true;
+            motor.overloaded_for = 0.0;
+            warn!(
+                "Conveyor motor {} overload tripped",
+                motor.load_input_address
+            );
+        }
+    }
+}
+
+/// Holds the belts of tripped motors stopped, even while the run coil or a
+/// jog coil is set.
+pub fn stop_tripped_conveyors(mut conveyors: Query<(&MotorProtection, &mut ConveyorState)>) {
+    for (motor, mut conveyor_state) in conveyors.iter_mut() {
+        if motor.is_tripped && conveyor_state.is_driven() {
+            conveyor_state.is_running = false;
+            conveyor_state.jog = 0.0;
+        }
+    }
+}
+
+pub fn monitor_overload_alarms(
+    motors: Query<&MotorProtection>,
+    mut alarm_events: EventWriter<AlarmEvent>,
+    mut was_tripped: Local<bool>,
+) {
+    let is_tripped = motors.iter().any(|motor| motor.is_tripped);
+    if *was_tripped != is_tripped {
+        *was_tripped = is_tripped;
+        alarm_events.write(if is_tripped {
+            AlarmEvent::Raise(Alarm::MotorOverload)
+        } else {
+            AlarmEvent::Clear(Alarm::MotorOverload)
+        });
+    }
+}
+
+// >>> Modbus Synchronization <<<
+/// Resets tripped relays whose reset coil was written; the coil resets itself.
+/// The belt stays stopped until its run coil is written again.
+pub fn sync_modbus_to_motor_protection(
+    mut motors: Query<&mut MotorProtection>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Ok(mut coils) = modbus_state.coils.lock() else {
+        return;
+    };
+
+    for mut motor in motors.iter_mut() {
+        if coils.get(&motor.reset_coil_address) == Some(&true) {
+            coils.insert(motor.reset_coil_address, false);
+            if motor.is_tripped {
+                motor.is_tripped = false;
+                info!("Conveyor motor {} overload reset", motor.load_input_address);
+            }
+        }
+    }
+}
+
+pub fn sync_motor_protection_to_modbus(
+    motors: Query<&MotorProtection, Changed<MotorProtection>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if motors.is_empty() {
+        return;
+    }
+
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for motor in motors.iter() {
+            let load = motor.load.round().clamp(0.0, u16::MAX as f32) as u16;
+            inputs.insert(motor.load_input_address, load);
+        }
+    }
+    if let Ok(mut discrete_inputs) = modbus_state.discrete_inputs.lock() {
+        for motor in motors.iter() {
+            discrete_inputs.insert(motor.fault_discrete_address, motor.is_tripped);
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Conveyor motor load monitoring with an overload trip.
+pub struct OverloadPlugin;
+
+impl Plugin for OverloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_motor_protection,
+                sync_modbus_to_motor_protection,
+                update_motor_loads,
+                stop_tripped_conveyors,
+                monitor_overload_alarms,
+                sync_motor_protection_to_modbus,
+            )
+                .chain()
+                .after(sync_tags),
+        );
+    }
+}
@@ -0,0 +1,285 @@
+// palletizer.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    bottle::{BOTTLE_HEIGHT, BOTTLE_WIDTH, BottleSpec, ProductSelection},
+    modbus::ModbusState,
+    outfeed::{BottleCompleted, complete_bottles},
+    station::StationId,
+};
+
+// >>> Constants <<<
+const SLOT_SIZE: Vec2 = Vec2::new(BOTTLE_WIDTH * 0.6, BOTTLE_HEIGHT * 0.4); // Bottles are drawn scaled down
+const SLOT_GAP: f32 = 2.0;
+const PALLET_HEIGHT: f32 = 12.0;
+const PALLET_COLOR: Color = Color::srgb(0.55, 0.4, 0.25);
+
+// >>> Components <<<
+/// Arrangement of bottles in each pallet layer, selected by register value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PalletPattern {
+    /// Three bottles per layer.
+    #[default]
+    Row3,
+    /// Four bottles per layer.
+    Row4,
+    /// Four and three bottles in alternating, half-offset layers.
+    Interlocked,
+}
+
+impl PalletPattern {
+    fn from_register(value: u16) -> Self {
+        match value {
+            1 => PalletPattern::Row4,
+            2 => PalletPattern::Interlocked,
+            _ => PalletPattern::Row3,
+        }
+    }
+
+    fn register(&self) -> u16 {
+        match self {
+            PalletPattern::Row3 => 0,
+            PalletPattern::Row4 => 1,
+            PalletPattern::Interlocked => 2,
+        }
+    }
+
+    /// Bottles in the given layer.
+    pub fn layer_size(&self, layer: u16) -> u16 {
+        match self {
+            PalletPattern::Row3 => 3,
+            PalletPattern::Row4 => 4,
+            PalletPattern::Interlocked if layer % 2 == 0 => 4,
+            PalletPattern::Interlocked => 3,
+        }
+    }
+
+    /// Centre of a slot, relative to the centre of the pallet's top.
+    fn slot_position(&self, layer: u16, slot: u16) -> Vec2 {
+        let count = self.layer_size(layer) as f32;
+        let pitch = SLOT_SIZE.x + SLOT_GAP;
+        let x = (slot as f32 - (count - 1.0) / 2.0) * pitch;
+        let y = (layer as f32 + 0.5) * (SLOT_SIZE.y + SLOT_GAP);
+        Vec2::new(x, y)
+    }
+}
+
+/// End-of-line palletizer stacking completed bottles of its station.
+#[derive(Component)]
+pub struct Palletizer {
+    pub pattern_holding_address: u16,
+    pub layer_complete_discrete_address: u16,
+    pub pallet_complete_discrete_address: u16,
+    pub clear_coil_address: u16,
+    pub layers: u16,
+    pub pattern: PalletPattern, // Used for the pallet being built
+    pub layer: u16,
+    pub slot: u16,
+}
+
+impl Palletizer {
+    pub fn is_pallet_complete(&self) -> bool {
+        self.layer >= self.layers
+    }
+
+    /// Set once a layer is finished, until the first bottle of the next one.
+    pub fn is_layer_complete(&self) -> bool {
+        self.layer > 0 && self.slot == 0
+    }
+
+    fn is_empty(&self) -> bool {
+        self.layer == 0 && self.slot == 0
+    }
+}
+
+/// A bottle stacked on a pallet.
+#[derive(Component)]
+pub struct PalletizedBottle;
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct PalletizerBundle {
+    palletizer: Palletizer,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl Palletizer {
+    /// Creates a palletizer with an empty pallet.
+    ///
+    /// # Parameters
+    /// * `pattern_holding_address` - Holding register selecting the layer pattern
+    /// * `layer_complete_discrete_address` - Discrete input set when a layer is finished
+    /// * `pallet_complete_discrete_address` - Discrete input set when the pallet is full
+    /// * `clear_coil_address` - Command coil taking away the pallet (auto-resets)
+    /// * `layers` - Layers on a full pallet
+    /// * `position` - Centre of the pallet
+    ///
+    /// # Return
+    /// The palletizer bundle
+    pub fn new(
+        pattern_holding_address: u16,
+        layer_complete_discrete_address: u16,
+        pallet_complete_discrete_address: u16,
+        clear_coil_address: u16,
+        layers: u16,
+        position: Vec2,
+    ) -> PalletizerBundle {
+        let width = PalletPattern::Row4.layer_size(0) as f32 * (SLOT_SIZE.x + SLOT_GAP);
+
+        PalletizerBundle {
+            palletizer: Palletizer {
+                pattern_holding_address,
+                layer_complete_discrete_address,
+                pallet_complete_discrete_address,
+                clear_coil_address,
+                layers,
+                pattern: PalletPattern::default(),
+                layer: 0,
+                slot: 0,
+            },
+            sprite: Sprite::from_color(PALLET_COLOR, Vec2::new(width, PALLET_HEIGHT)),
+            transform: Transform::from_translation(position.extend(0.0)),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_palletizers(
+    palletizers: Query<&Palletizer, Added<Palletizer>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for palletizer in palletizers.iter() {
+        if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+            holdings.insert(
+                palletizer.pattern_holding_address,
+                palletizer.pattern.register(),
+            );
+        }
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(palletizer.clear_coil_address, false);
+        }
+
+        info!(
+            "Registered palletizer on pattern holding register: {:x?}",
+            palletizer.pattern_holding_address
+        );
+    }
+}
+
+/// Stacks each completed bottle onto the next free slot of its station's pallet.
+pub fn stack_completed_bottles(
+    mut commands: Commands,
+    mut completed_events: EventReader<BottleCompleted>,
+    mut palletizers: Query<(Entity, &mut Palletizer, &StationId)>,
+    product_selection: Res<ProductSelection>,
+) {
+    for bottle in completed_events.read() {
+        let Some((entity, mut palletizer, _)) = palletizers
+            .iter_mut()
+            .find(|(_, _, station)| **station == bottle.station)
+        else {
+            continue;
+        };
+
+        if palletizer.is_pallet_complete() {
+            warn!("Pallet full, bottle not stacked");
+            continue;
+        }
+
+        let color = product_selection
+            .catalog
+            .iter()
+            .find(|spec| spec.name == bottle.variant)
+            .map_or(BottleSpec::default().color, |spec| spec.color);
+        let (layer, slot) = (palletizer.layer, palletizer.slot);
+        let position =
+            palletizer.pattern.slot_position(layer, slot) + Vec2::Y * PALLET_HEIGHT / 2.0;
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                PalletizedBottle,
+                Sprite::from_color(color, SLOT_SIZE),
+                Transform::from_translation(position.extend(0.1)),
+            ));
+        });
+
+        palletizer.slot += 1;
+        if palletizer.slot >= palletizer.pattern.layer_size(layer) {
+            palletizer.slot = 0;
+            palletizer.layer += 1;
+            info!("Pallet layer {} complete", palletizer.layer);
+        }
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_modbus_to_palletizers(
+    mut commands: Commands,
+    mut palletizers: Query<(Entity, &mut Palletizer)>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(holdings) = modbus_state.holding_registers.lock() {
+        for (_, mut palletizer) in palletizers.iter_mut() {
+            // The pattern can only change between pallets
+            if let Some(&pattern) = holdings.get(&palletizer.pattern_holding_address) {
+                let pattern = PalletPattern::from_register(pattern);
+                if palletizer.pattern != pattern && palletizer.is_empty() {
+                    palletizer.pattern = pattern;
+                    info!("Pallet pattern set to: {:?}", pattern);
+                }
+            }
+        }
+    }
+
+    if let Ok(mut coils) = modbus_state.coils.lock() {
+        for (entity, mut palletizer) in palletizers.iter_mut() {
+            // Clear is a command coil and resets itself once handled
+            if coils.get(&palletizer.clear_coil_address) == Some(&true) {
+                coils.insert(palletizer.clear_coil_address, false);
+                palletizer.layer = 0;
+                palletizer.slot = 0;
+                commands.entity(entity).despawn_related::<Children>();
+                info!("Pallet cleared");
+            }
+        }
+    }
+}
+
+pub fn sync_palletizers_to_modbus(
+    palletizers: Query<&Palletizer, Changed<Palletizer>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+        for palletizer in palletizers.iter() {
+            discretes.insert(
+                palletizer.layer_complete_discrete_address,
+                palletizer.is_layer_complete(),
+            );
+            discretes.insert(
+                palletizer.pallet_complete_discrete_address,
+                palletizer.is_pallet_complete(),
+            );
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct PalletizerPlugin;
+
+impl Plugin for PalletizerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BottleCompleted>().add_systems(
+            FixedUpdate,
+            (
+                register_palletizers,
+                sync_modbus_to_palletizers,
+                stack_completed_bottles.after(complete_bottles),
+                sync_palletizers_to_modbus,
+            )
+                .chain(),
+        );
+    }
+}
@@ -0,0 +1,228 @@
+// physics.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::num::NonZeroUsize;
+
+use anyhow::ensure;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{accumulation::Accumulated, simulation::FIXED_TIMESTEP_HZ, stopper::Stopped};
+
+// >>> Constants <<<
+const DEFAULT_PIXELS_PER_METER: f32 = 100.0;
+const STANDARD_GRAVITY: f32 = 9.81; // Meters per second squared
+
+// >>> Components <<<
+/// How a collider's surface moves under the items touching it, e.g. a running
+/// belt or a turning table. The component owning the collider keeps it up to
+/// date before each physics step, and [`PhysicsHooks`] carries the items along
+/// with it. Child colliders without a motion of their own move with their
+/// parent's, so a table can be built from several. Each collider also needs
+/// `ActiveHooks::MODIFY_SOLVER_CONTACTS`.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SurfaceMotion {
+    #[default]
+    Still,
+    Linear(Vec2), // Pixels per second
+    Around {
+        center: Vec2,
+        speed: f32, // Pixels per second along the circle, counter-clockwise when positive
+    },
+    Rotating {
+        center: Vec2,
+        angular_speed: f32, // Radians per second, counter-clockwise when positive
+    },
+}
+
+impl SurfaceMotion {
+    /// Velocity of the surface at a world-space point on it.
+    pub fn velocity_at(&self, point: Vec2) -> Vec2 {
+        match *self {
+            SurfaceMotion::Still => Vec2::ZERO,
+            SurfaceMotion::Linear(velocity) => velocity,
+            SurfaceMotion::Around { center, speed } => {
+                (point - center).normalize_or_zero().perp() * speed
+            }
+            SurfaceMotion::Rotating {
+                center,
+                angular_speed,
+            } => (point - center).perp() * angular_speed,
+        }
+    }
+}
+
+// >>> Resources <<<
+/// Rapier's world settings, from the scenario's `[physics]` table.
+///
+/// The defaults are the ones the plant always ran with, so an unset field
+/// leaves the physics as before.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhysicsSettings {
+    pub gravity: [f32; 2], // Meters per second squared
+    pub pixels_per_meter: f32,
+    pub ccd: bool, // Continuous collision detection on the bodies asking for it, e.g. bottles
+    pub max_ccd_substeps: usize,
+    pub solver_iterations: usize,
+    pub substeps: usize, // Physics steps per fixed tick
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            gravity: [0.0, -STANDARD_GRAVITY],
+            pixels_per_meter: DEFAULT_PIXELS_PER_METER,
+            ccd: true,
+            max_ccd_substeps: 1,
+            solver_iterations: 4,
+            substeps: 1,
+        }
+    }
+}
+
+impl PhysicsSettings {
+    /// Gravity in pixels per second squared, as Rapier takes it.
+    pub fn scaled_gravity(&self) -> Vec2 {
+        Vec2::from(self.gravity) * self.pixels_per_meter
+    }
+
+    /// Checks the settings are ones Rapier can step with.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.gravity.iter().all(|component| component.is_finite()),
+            "Physics gravity must be finite"
+        );
+        ensure!(
+            self.pixels_per_meter.is_finite() && self.pixels_per_meter > 0.0,
+            "Physics pixels_per_meter must be positive"
+        );
+        ensure!(
+            self.solver_iterations >= 1,
+            "Physics solver_iterations must be at least 1"
+        );
+        ensure!(self.substeps >= 1, "Physics substeps must be at least 1");
+        Ok(())
+    }
+}
+
+// >>> Physics Hooks <<<
+/// Rapier's contact hooks: items touching a collider with a
+/// [`SurfaceMotion`] move with its surface. Components add their own moving
+/// surfaces through that component rather than through hooks of their own.
+#[derive(SystemParam)]
+pub struct PhysicsHooks<'w, 's> {
+    surfaces: Query<'w, 's, &'static SurfaceMotion>,
+    parents: Query<'w, 's, &'static ChildOf>,
+    slipping: Query<'w, 's, (), Or<(With<Accumulated>, With<Stopped>)>>,
+}
+
+impl PhysicsHooks<'_, '_> {
+    /// Motion of the surface a collider belongs to: its own, or else its
+    /// parent's, e.g. one of the pockets of a table.
+    fn surface(&self, collider: Entity) -> Option<&SurfaceMotion> {
+        self.surfaces.get(collider).ok().or_else(|| {
+            let parent = self.parents.get(collider).ok()?.parent();
+            self.surfaces.get(parent).ok()
+        })
+    }
+}
+
+impl BevyPhysicsHooks for PhysicsHooks<'_, '_> {
+    fn modify_solver_contacts(&self, context: ContactModificationContextView) {
+        // Each contact moves with the surface of whichever collider has one,
+        // so every belt and table carries items at its own speed
+        let Some(surface) = self
+            .surface(context.collider1())
+            .or_else(|| self.surface(context.collider2()))
+        else {
+            return;
+        };
+
+        // Surfaces slip under accumulated and stopped bottles instead of pushing them
+        let is_slipping = self.slipping.contains(context.collider1())
+            || self.slipping.contains(context.collider2());
+
+        for solver_contact in &mut *context.raw.solver_contacts {
+            let velocity = if is_slipping {
+                Vec2::ZERO
+            } else {
+                surface.velocity_at(Vec2::new(solver_contact.point.x, solver_contact.point.y))
+            };
+            solver_contact.tangent_velocity.x = velocity.x;
+            solver_contact.tangent_velocity.y = velocity.y;
+        }
+    }
+}
+
+// >>> Systems <<<
+/// Applies the settings to the Rapier context whenever they change, e.g. on
+/// loading a scenario.
+pub fn apply_physics_settings(
+    settings: Res<PhysicsSettings>,
+    mut configurations: Query<&mut RapierConfiguration>,
+    mut simulations: Query<&mut RapierContextSimulation>,
+    mut timestep: ResMut<TimestepMode>,
+) {
+    for mut configuration in configurations.iter_mut() {
+        configuration.gravity = settings.scaled_gravity();
+    }
+    for mut simulation in simulations.iter_mut() {
+        let parameters = &mut simulation.integration_parameters;
+        parameters.length_unit = settings.pixels_per_meter;
+        parameters.max_ccd_substeps = settings.max_ccd_substeps;
+        parameters.num_solver_iterations =
+            NonZeroUsize::new(settings.solver_iterations).unwrap_or(NonZeroUsize::MIN);
+    }
+    *timestep = TimestepMode::Fixed {
+        dt: (1.0 / FIXED_TIMESTEP_HZ) as f32,
+        substeps: settings.substeps.max(1),
+    };
+
+    info!(
+        "Physics set to gravity {:?} m/s^2 at {} pixels per meter",
+        settings.gravity, settings.pixels_per_meter
+    );
+}
+
+/// Turns continuous collision detection off on new bodies while the settings
+/// have it off.
+pub fn apply_ccd_setting(mut bodies: Query<&mut Ccd, Added<Ccd>>, settings: Res<PhysicsSettings>) {
+    if settings.ccd {
+        return;
+    }
+    for mut ccd in bodies.iter_mut() {
+        ccd.enabled = false;
+    }
+}
+
+// >>> Plugin <<<
+/// Rapier, stepped in the fixed timestep with the [`PhysicsHooks`] moving
+/// surfaces, and its settings from the scenario.
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        // The scenario's settings replace the defaults once loaded
+        app.add_plugins(
+            RapierPhysicsPlugin::<PhysicsHooks>::pixels_per_meter(DEFAULT_PIXELS_PER_METER)
+                .in_fixed_schedule(),
+        )
+        .insert_resource(TimestepMode::Fixed {
+            dt: (1.0 / FIXED_TIMESTEP_HZ) as f32,
+            substeps: 1,
+        })
+        .init_resource::<PhysicsSettings>()
+        .add_systems(
+            FixedPostUpdate,
+            (
+                apply_physics_settings.run_if(resource_changed::<PhysicsSettings>),
+                apply_ccd_setting,
+            )
+                .before(PhysicsSet::SyncBackend),
+        );
+    }
+}
@@ -0,0 +1,293 @@
+// pid.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    heater::{Heater, simulate_heaters, sync_modbus_to_heaters},
+    modbus::ModbusState,
+    simulation::SimTime,
+    tank::{Tank, fill_tanks, sync_modbus_to_tanks},
+};
+
+// >>> Constants <<<
+const GAIN_SCALE: f32 = 100.0; // Gain registers hold hundredths
+const MAX_OUTPUT: f32 = 100.0; // Percent
+const PUMP_CYCLE_TIME: f32 = 2.0; // Seconds per time-proportioned pump cycle
+
+// >>> Components <<<
+/// Whether the simulation closes the loop itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PidMode {
+    /// The controller is idle and the device follows its own registers.
+    #[default]
+    Manual,
+    /// The controller drives the device toward the setpoint.
+    Auto,
+}
+
+impl PidMode {
+    fn from_register(value: u16) -> Self {
+        if value == 0 {
+            PidMode::Manual
+        } else {
+            PidMode::Auto
+        }
+    }
+
+    fn register(&self) -> u16 {
+        match self {
+            PidMode::Manual => 0,
+            PidMode::Auto => 1,
+        }
+    }
+}
+
+/// Built-in PID loop for the heater or tank it is attached to, so the plant
+/// behaves as intended with no PLC connected.
+///
+/// The setpoint is in the units of the device's process register (0.1 °C for
+/// heaters, 0.1% of capacity for tanks). The P, I and D gains, in hundredths,
+/// occupy three consecutive holding registers. The output is 0-100%.
+#[derive(Component, Debug, Clone)]
+pub struct PidController {
+    pub setpoint_holding_address: u16,
+    pub gains_holding_address: u16, // Kp; Ki and Kd follow it
+    pub mode_holding_address: u16,
+    pub setpoint: u16,
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+    pub mode: PidMode,
+    integral: f32,
+    last_measurement: Option<f32>,
+    cycle_elapsed: f32,
+}
+
+impl PidController {
+    /// Creates a controller, initially in manual mode.
+    ///
+    /// # Parameters
+    /// * `setpoint_holding_address` - Holding register with the setpoint
+    /// * `gains_holding_address` - First of three holding registers with the gains
+    /// * `mode_holding_address` - Holding register selecting manual (0) or auto (1)
+    ///
+    /// # Return
+    /// The PID controller component
+    pub fn new(
+        setpoint_holding_address: u16,
+        gains_holding_address: u16,
+        mode_holding_address: u16,
+    ) -> Self {
+        Self {
+            setpoint_holding_address,
+            gains_holding_address,
+            mode_holding_address,
+            setpoint: 0,
+            kp: 1.0,
+            ki: 0.0,
+            kd: 0.0,
+            mode: PidMode::Manual,
+            integral: 0.0,
+            last_measurement: None,
+            cycle_elapsed: 0.0,
+        }
+    }
+
+    pub fn with_setpoint(mut self, setpoint: u16) -> Self {
+        self.setpoint = setpoint;
+        self
+    }
+
+    pub fn with_gains(mut self, kp: f32, ki: f32, kd: f32) -> Self {
+        self.kp = kp;
+        self.ki = ki;
+        self.kd = kd;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: PidMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Clears the integral and derivative history for a bumpless restart.
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.last_measurement = None;
+        self.cycle_elapsed = 0.0;
+    }
+
+    /// Advances the loop by one step.
+    ///
+    /// # Parameters
+    /// * `measurement` - Process value, in the units of the setpoint
+    /// * `delta` - Seconds since the last step
+    ///
+    /// # Return
+    /// The controller output in percent
+    pub fn update(&mut self, measurement: f32, delta: f32) -> f32 {
+        let error = self.setpoint as f32 - measurement;
+
+        // Differentiate the measurement so setpoint changes don't kick the output
+        let derivative = match self.last_measurement {
+            Some(last) if delta > 0.0 => -(measurement - last) / delta,
+            _ => 0.0,
+        };
+        self.last_measurement = Some(measurement);
+
+        let integral = self.integral + error * delta;
+        let output = self.kp * error + self.ki * integral + self.kd * derivative;
+
+        // Only integrate while the output isn't saturated, to avoid windup
+        if (0.0..=MAX_OUTPUT).contains(&output) {
+            self.integral = integral;
+        }
+        output.clamp(0.0, MAX_OUTPUT)
+    }
+
+    fn registers(&self) -> [u16; 3] {
+        [self.kp, self.ki, self.kd].map(|gain| (gain * GAIN_SCALE).round() as u16)
+    }
+}
+
+// >>> Systems <<<
+pub fn register_pid_controllers(
+    controllers: Query<&PidController, Added<PidController>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for controller in controllers.iter() {
+        if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+            holdings.insert(controller.setpoint_holding_address, controller.setpoint);
+            for (offset, gain) in controller.registers().into_iter().enumerate() {
+                holdings.insert(
+                    controller.gains_holding_address.wrapping_add(offset as u16),
+                    gain,
+                );
+            }
+            holdings.insert(controller.mode_holding_address, controller.mode.register());
+        }
+
+        info!(
+            "Registered PID controller on setpoint holding register: {:x?}",
+            controller.setpoint_holding_address
+        );
+    }
+}
+
+/// Drives heater power from its controller while in auto.
+pub fn run_heater_loops(
+    time: Res<SimTime>,
+    mut heaters: Query<(&mut Heater, &mut PidController)>,
+    modbus_state: Res<ModbusState>,
+) {
+    for (mut heater, mut controller) in heaters.iter_mut() {
+        if controller.mode != PidMode::Auto {
+            continue;
+        }
+
+        let measurement = Heater::register_value(heater.temperature) as f32;
+        let power = controller.update(measurement, time.delta_secs()).round() as u16;
+        if heater.power != power {
+            heater.power = power;
+        }
+
+        // Keep the register in step so the heater doesn't revert to it
+        if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+            holdings.insert(heater.power_holding_address, power);
+        }
+    }
+}
+
+/// Runs a tank's on/off pump from its controller while in auto, switching it
+/// on for the controller's share of each pump cycle.
+pub fn run_tank_loops(
+    time: Res<SimTime>,
+    mut tanks: Query<(&mut Tank, &mut PidController)>,
+    modbus_state: Res<ModbusState>,
+) {
+    let delta = time.delta_secs();
+
+    for (mut tank, mut controller) in tanks.iter_mut() {
+        if controller.mode != PidMode::Auto {
+            continue;
+        }
+
+        let output = controller.update(tank.level_register() as f32, delta);
+        controller.cycle_elapsed = (controller.cycle_elapsed + delta) % PUMP_CYCLE_TIME;
+        let is_pump_on = controller.cycle_elapsed < PUMP_CYCLE_TIME * output / MAX_OUTPUT;
+        if tank.is_pump_on != is_pump_on {
+            tank.is_pump_on = is_pump_on;
+        }
+
+        // Keep the coil in step so the tank doesn't revert to it
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(tank.pump_coil_address, is_pump_on);
+        }
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_modbus_to_pid_controllers(
+    mut controllers: Query<&mut PidController>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Ok(holdings) = modbus_state.holding_registers.lock() else {
+        return;
+    };
+
+    for mut controller in controllers.iter_mut() {
+        if let Some(&setpoint) = holdings.get(&controller.setpoint_holding_address) {
+            if controller.setpoint != setpoint {
+                controller.setpoint = setpoint;
+            }
+        }
+
+        let gains = [0, 1, 2].map(|offset| {
+            holdings
+                .get(&controller.gains_holding_address.wrapping_add(offset))
+                .map(|&gain| gain as f32 / GAIN_SCALE)
+        });
+        if let [Some(kp), Some(ki), Some(kd)] = gains {
+            if (controller.kp, controller.ki, controller.kd) != (kp, ki, kd) {
+                controller.kp = kp;
+                controller.ki = ki;
+                controller.kd = kd;
+            }
+        }
+
+        if let Some(&mode) = holdings.get(&controller.mode_holding_address) {
+            let mode = PidMode::from_register(mode);
+            if controller.mode != mode {
+                controller.mode = mode;
+                controller.reset();
+                info!("PID controller mode set to: {:?}", mode);
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct PidPlugin;
+
+impl Plugin for PidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                (register_pid_controllers, sync_modbus_to_pid_controllers).chain(),
+                run_heater_loops
+                    .after(sync_modbus_to_pid_controllers)
+                    .after(sync_modbus_to_heaters)
+                    .before(simulate_heaters),
+                run_tank_loops
+                    .after(sync_modbus_to_pid_controllers)
+                    .after(sync_modbus_to_tanks)
+                    .before(fill_tanks),
+            ),
+        );
+    }
+}
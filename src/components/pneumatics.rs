@@ -0,0 +1,200 @@
+// pneumatics.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    alarm::{Alarm, AlarmEvent},
+    modbus::ModbusState,
+    simulation::SimTime,
+};
+
+// >>> Resources <<<
+/// Compressed air header shared by the plant's pneumatic actuators.
+///
+/// Every stroke of a stopper pin, diverter kick or valve actuator draws
+/// `stroke_consumption` from the header, and it leaks down slowly; the
+/// compressor coil pumps it back up to the supply pressure. Actuators slow
+/// down as the pressure sags, and below `low_pressure` stoppers and diverters
+/// stop firing and valve actuators stall until their stroke times out. The
+/// pressure is published in kPa.
+#[derive(Resource, Debug, Clone)]
+pub struct Pneumatics {
+    pub compressor_coil_address: u16,
+    pub pressure_input_address: u16,
+    pub supply_pressure: f32,    // kPa the compressor cuts out at
+    pub low_pressure: f32,       // kPa below which actuators stop working
+    pub compressor_rate: f32,    // kPa per second while the compressor runs
+    pub leak_rate: f32,          // kPa per second lost to leaks
+    pub stroke_consumption: f32, // kPa drawn by each actuator stroke
+    pub pressure: f32,           // kPa
+    pub is_compressor_running: bool,
+}
+
+impl Pneumatics {
+    /// Creates an air header at full supply pressure.
+    ///
+    /// # Parameters
+    /// * `compressor_coil_address` - Coil that runs the compressor
+    /// * `pressure_input_address` - Input register holding the header pressure in kPa
+    /// * `supply_pressure` - Pressure in kPa the compressor pumps up to
+    /// * `low_pressure` - Pressure in kPa below which actuators stop working
+    ///
+    /// # Return
+    /// The pneumatics resource, with default compressor, leak and stroke rates
+    pub fn new(
+        compressor_coil_address: u16,
+        pressure_input_address: u16,
+        supply_pressure: f32,
+        low_pressure: f32,
+    ) -> Self {
+        Self {
+            compressor_coil_address,
+            pressure_input_address,
+            supply_pressure,
+            low_pressure,
+            compressor_rate: 50.0,
+            leak_rate: 2.0,
+            stroke_consumption: 30.0,
+            pressure: supply_pressure,
+            is_compressor_running: false,
+        }
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.pressure < self.low_pressure
+    }
+
+    /// Share of full speed the actuators move at, 0 below the low pressure.
+    pub fn speed_factor(&self) -> f32 {
+        if self.is_low() || self.supply_pressure <= 0.0 {
+            0.0
+        } else {
+            (self.pressure / self.supply_pressure).min(1.0)
+        }
+    }
+
+    /// Draws the air of one actuator stroke from the header.
+    pub fn stroke(&mut self) {
+        self.pressure = (self.pressure - self.stroke_consumption).max(0.0);
+    }
+
+    fn pressure_register(&self) -> u16 {
+        self.pressure.round().clamp(0.0, u16::MAX as f32) as u16
+    }
+}
+
+// >>> Systems <<<
+pub fn register_pneumatics(pneumatics: Option<Res<Pneumatics>>, modbus_state: Res<ModbusState>) {
+    let Some(pneumatics) = pneumatics else {
+        return;
+    };
+    if !pneumatics.is_added() {
+        return;
+    }
+
+    if let Ok(mut coils) = modbus_state.coils.lock() {
+        coils.insert(pneumatics.compressor_coil_address, false);
+    }
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        inputs.insert(
+            pneumatics.pressure_input_address,
+            pneumatics.pressure_register(),
+        );
+    }
+
+    info!(
+        "Registered pneumatics on compressor coil: {:x?}",
+        pneumatics.compressor_coil_address
+    );
+}
+
+/// Pumps the header up while the compressor runs and bleeds it through leaks.
+pub fn update_air_pressure(time: Res<SimTime>, pneumatics: Option<ResMut<Pneumatics>>) {
+    let Some(mut pneumatics) = pneumatics else {
+        return;
+    };
+    let delta = time.delta_secs();
+
+    let mut pressure = pneumatics.pressure - pneumatics.leak_rate * delta;
+    if pneumatics.is_compressor_running {
+        pressure = (pressure + pneumatics.compressor_rate * delta).min(pneumatics.supply_pressure);
+    }
+    pneumatics.pressure = pressure.max(0.0);
+}
+
+pub fn monitor_air_pressure_alarm(
+    pneumatics: Option<Res<Pneumatics>>,
+    mut alarm_events: EventWriter<AlarmEvent>,
+    mut was_low: Local<bool>,
+) {
+    let is_low = pneumatics.is_some_and(|pneumatics| pneumatics.is_low());
+    if *was_low != is_low {
+        *was_low = is_low;
+        alarm_events.write(if is_low {
+            AlarmEvent::Raise(Alarm::LowAirPressure)
+        } else {
+            AlarmEvent::Clear(Alarm::LowAirPressure)
+        });
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_modbus_to_pneumatics(
+    pneumatics: Option<ResMut<Pneumatics>>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Some(mut pneumatics) = pneumatics else {
+        return;
+    };
+
+    if let Ok(coils) = modbus_state.coils.lock() {
+        if let Some(&coil_state) = coils.get(&pneumatics.compressor_coil_address) {
+            if pneumatics.is_compressor_running != coil_state {
+                pneumatics.is_compressor_running = coil_state;
+                info!("Compressor running set to: {}", coil_state);
+            }
+        }
+    }
+}
+
+pub fn sync_pneumatics_to_modbus(
+    pneumatics: Option<Res<Pneumatics>>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Some(pneumatics) = pneumatics else {
+        return;
+    };
+    if !pneumatics.is_changed() {
+        return;
+    }
+
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        inputs.insert(
+            pneumatics.pressure_input_address,
+            pneumatics.pressure_register(),
+        );
+    }
+}
+
+// >>> Plugin <<<
+/// Compressed air supply of the plant's pneumatic actuators, inserted by
+/// scenarios with a `[pneumatics]` table.
+pub struct PneumaticsPlugin;
+
+impl Plugin for PneumaticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_pneumatics,
+                sync_modbus_to_pneumatics,
+                update_air_pressure,
+                monitor_air_pressure_alarm,
+                sync_pneumatics_to_modbus,
+            )
+                .chain(),
+        );
+    }
+}
@@ -0,0 +1,226 @@
+// profiler.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use bevy::ecs::{schedule::ScheduleConfigs, system::ScheduleSystem};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::PhysicsSet;
+
+use super::{bottle::Bottle, sensor::Sensor, valve::Ball};
+
+// >>> Constants <<<
+pub const SENSOR_FEEDBACK: &str = "sensor feedback";
+pub const MODBUS_SYNC: &str = "Modbus sync";
+pub const PHYSICS: &str = "physics";
+const SMOOTHING: f32 = 0.05; // Weight of the latest tick in the averages
+const PROFILER_PANEL_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.7);
+const PROFILER_FONT_SIZE: f32 = 14.0;
+
+// >>> Components <<<
+#[derive(Component)]
+pub struct ProfilerPanel;
+
+#[derive(Component)]
+pub struct ProfilerPanelText;
+
+// >>> Resources <<<
+/// Wall time of a profiled section, summed over each fixed tick.
+#[derive(Debug, Clone, Default)]
+pub struct SectionTime {
+    started: Option<Instant>,
+    tick: Duration,
+    pub total: Duration, // Since the profiler started
+    pub average: f32,    // Milliseconds per tick
+    pub peak: f32,       // Milliseconds, in the slowest tick
+}
+
+/// Time spent in the profiled sections of the fixed timestep, e.g. sensor
+/// feedback, Modbus sync and the physics step.
+#[derive(Resource, Default)]
+pub struct SystemProfile {
+    sections: BTreeMap<&'static str, SectionTime>,
+    pub ticks: u64,
+    pub frame_time: f32, // Milliseconds per rendered frame, averaged
+    pub is_visible: bool,
+}
+
+impl SystemProfile {
+    pub fn begin(&mut self, section: &'static str) {
+        self.sections.entry(section).or_default().started = Some(Instant::now());
+    }
+
+    pub fn end(&mut self, section: &'static str) {
+        let time = self.sections.entry(section).or_default();
+        if let Some(started) = time.started.take() {
+            time.tick += started.elapsed();
+        }
+    }
+
+    pub fn section(&self, section: &str) -> Option<&SectionTime> {
+        self.sections.get(section)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &SectionTime)> {
+        self.sections.iter().map(|(name, time)| (*name, time))
+    }
+
+    /// Folds the tick just run into the averages.
+    fn finish_tick(&mut self) {
+        self.ticks += 1;
+        for time in self.sections.values_mut() {
+            let tick = std::mem::take(&mut time.tick);
+            let millis = tick.as_secs_f32() * 1000.0;
+            time.total += tick;
+            time.average = if self.ticks == 1 {
+                millis
+            } else {
+                time.average + (millis - time.average) * SMOOTHING
+            };
+            time.peak = time.peak.max(millis);
+        }
+    }
+}
+
+/// Counts the wall time of `systems` toward `section` of the profile. Use it
+/// in place of the systems when adding them, e.g. inside a chain.
+pub fn profiled<M>(
+    section: &'static str,
+    systems: impl IntoScheduleConfigs<ScheduleSystem, M>,
+) -> ScheduleConfigs<ScheduleSystem> {
+    (
+        move |profile: Option<ResMut<SystemProfile>>| {
+            if let Some(mut profile) = profile {
+                profile.begin(section);
+            }
+        },
+        systems,
+        move |profile: Option<ResMut<SystemProfile>>| {
+            if let Some(mut profile) = profile {
+                profile.end(section);
+            }
+        },
+    )
+        .chain()
+}
+
+// >>> Systems <<<
+pub fn finish_profiled_tick(mut profile: ResMut<SystemProfile>) {
+    profile.finish_tick();
+}
+
+pub fn setup_profiler_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            ProfilerPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(36.0),
+                left: Val::Percent(40.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(PROFILER_PANEL_COLOR),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ProfilerPanelText,
+                Text::new(""),
+                TextFont {
+                    font_size: PROFILER_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// Lists each section's average and peak time per tick under the frame time
+/// and the entity counts driving them.
+pub fn update_profiler_panel(
+    time: Res<Time<Real>>,
+    mut profile: ResMut<SystemProfile>,
+    mut panels: Query<&mut Visibility, With<ProfilerPanel>>,
+    mut texts: Query<&mut Text, With<ProfilerPanelText>>,
+    bottles: Query<(), With<Bottle>>,
+    balls: Query<(), With<Ball>>,
+    sensors: Query<(), With<Sensor>>,
+) {
+    let frame_time = time.delta_secs() * 1000.0;
+    profile.frame_time += (frame_time - profile.frame_time) * SMOOTHING;
+
+    for mut visibility in panels.iter_mut() {
+        visibility.set_if_neq(if profile.is_visible {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        });
+    }
+    if !profile.is_visible {
+        return;
+    }
+
+    let mut lines = vec![
+        format!("frame {:.2} ms", profile.frame_time),
+        format!(
+            "{} bottles, {} balls, {} sensors",
+            bottles.iter().count(),
+            balls.iter().count(),
+            sensors.iter().count()
+        ),
+    ];
+    for (name, section) in profile.iter() {
+        lines.push(format!(
+            "{name}: {:.3} ms (peak {:.3})",
+            section.average, section.peak
+        ));
+    }
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+
+// >>> Input System <<<
+pub fn handle_profiler_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut profile: ResMut<SystemProfile>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyK) {
+        profile.is_visible = !profile.is_visible;
+    }
+}
+
+// >>> Plugin <<<
+/// Times the sensor feedback, Modbus sync and physics sections of every fixed
+/// tick, for judging where the time goes in large scenes. K shows the averages
+/// over the plant.
+pub struct ProfilerPlugin;
+
+impl Plugin for ProfilerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SystemProfile>()
+            .add_systems(Startup, setup_profiler_panel)
+            .add_systems(
+                Update,
+                (handle_profiler_input, update_profiler_panel).chain(),
+            )
+            // Rapier runs its sets in order, so the step sits between them
+            .add_systems(
+                FixedPostUpdate,
+                (
+                    (|mut profile: ResMut<SystemProfile>| profile.begin(PHYSICS))
+                        .after(PhysicsSet::SyncBackend)
+                        .before(PhysicsSet::StepSimulation),
+                    (|mut profile: ResMut<SystemProfile>| profile.end(PHYSICS))
+                        .after(PhysicsSet::StepSimulation)
+                        .before(PhysicsSet::Writeback),
+                ),
+            )
+            .add_systems(FixedLast, finish_profiled_tick);
+    }
+}
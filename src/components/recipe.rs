@@ -0,0 +1,260 @@
+// recipe.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use super::{
+    bottle::BottleSpawner,
+    conveyor::ConveyorState,
+    modbus::ModbusState,
+    station::StationId,
+    valve::{BallSpawner, ValveState},
+};
+
+// >>> Constants <<<
+const RECIPE_SELECT_HOLDING: u16 = 0x0004;
+const BATCH_START_COIL: u16 = 0x000A;
+const BATCH_COMPLETE_DISCRETE: u16 = 0x000B;
+const BATCH_COUNT_INPUT: u16 = 0x0004;
+
+// >>> Resources <<<
+#[derive(Debug, Clone)]
+pub struct Recipe {
+    pub name: String,
+    pub fill_volume: u32, // Balls dispensed per bottle
+    pub spawn_interval: f32,
+    pub conveyor_speed: f32,
+    pub target_count: u16,
+}
+
+#[derive(Resource)]
+pub struct RecipeBook {
+    pub recipes: Vec<Recipe>,
+    pub selected: usize,
+    pub select_holding_address: u16,
+    pub start_coil_address: u16,
+    pub complete_discrete_address: u16,
+    pub count_input_address: u16,
+}
+
+impl Default for RecipeBook {
+    fn default() -> Self {
+        Self {
+            recipes: vec![
+                Recipe {
+                    name: "standard".to_string(),
+                    fill_volume: 10,
+                    spawn_interval: 2.0,
+                    conveyor_speed: 100.0,
+                    target_count: 10,
+                },
+                Recipe {
+                    name: "sample".to_string(),
+                    fill_volume: 6,
+                    spawn_interval: 3.0,
+                    conveyor_speed: 80.0,
+                    target_count: 5,
+                },
+                Recipe {
+                    name: "rush".to_string(),
+                    fill_volume: 12,
+                    spawn_interval: 1.0,
+                    conveyor_speed: 150.0,
+                    target_count: 20,
+                },
+            ],
+            selected: 0,
+            select_holding_address: RECIPE_SELECT_HOLDING,
+            start_coil_address: BATCH_START_COIL,
+            complete_discrete_address: BATCH_COMPLETE_DISCRETE,
+            count_input_address: BATCH_COUNT_INPUT,
+        }
+    }
+}
+
+impl RecipeBook {
+    pub fn selected_recipe(&self) -> Option<&Recipe> {
+        self.recipes.get(self.selected)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct BatchState {
+    pub recipe: Option<Recipe>,
+    pub count: u16,
+    pub is_complete: bool,
+    last_start: bool,
+    open_valves: HashSet<Entity>,
+}
+
+impl BatchState {
+    pub fn is_active(&self) -> bool {
+        self.recipe.is_some() && !self.is_complete
+    }
+}
+
+// >>> Systems <<<
+/// Starts a batch on every station.
+fn start_batch(
+    recipe: Recipe,
+    batch_state: &mut BatchState,
+    conveyors: &mut Query<(&mut ConveyorState, &mut BottleSpawner)>,
+) {
+    for (mut conveyor_state, mut bottle_spawner) in conveyors.iter_mut() {
+        conveyor_state.speed = recipe.conveyor_speed;
+        conveyor_state.is_running = true;
+        bottle_spawner.set_interval(recipe.spawn_interval);
+    }
+
+    info!(
+        "Batch started: {} ({} bottles)",
+        recipe.name, recipe.target_count
+    );
+
+    batch_state.recipe = Some(recipe);
+    batch_state.count = 0;
+    batch_state.is_complete = false;
+}
+
+pub fn handle_recipe_input(
+    recipe_book: Res<RecipeBook>,
+    mut batch_state: ResMut<BatchState>,
+    mut conveyors: Query<(&mut ConveyorState, &mut BottleSpawner)>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyR) {
+        if let Some(recipe) = recipe_book.selected_recipe() {
+            start_batch(recipe.clone(), &mut batch_state, &mut conveyors);
+        }
+    }
+}
+
+pub fn run_batch(
+    mut batch_state: ResMut<BatchState>,
+    mut conveyors: Query<(&mut ConveyorState, &StationId)>,
+    mut valves: Query<(Entity, &mut ValveState, &BallSpawner, &StationId)>,
+) {
+    let Some(fill_volume) = batch_state.recipe.as_ref().map(|recipe| recipe.fill_volume) else {
+        return;
+    };
+
+    if !batch_state.is_active() {
+        return;
+    }
+
+    for (entity, mut valve_state, ball_spawner, station) in valves.iter_mut() {
+        // Close the valve once the recipe's fill volume has been dispensed
+        if valve_state.is_open && ball_spawner.dispensed >= fill_volume {
+            valve_state.is_open = false;
+            for (mut conveyor_state, _) in conveyors.iter_mut().filter(|(_, id)| *id == station) {
+                conveyor_state.is_running = true;
+            }
+        }
+
+        // Every completed fill on any station counts towards the batch
+        let was_open = batch_state.open_valves.contains(&entity);
+        if was_open && !valve_state.is_open && !batch_state.is_complete {
+            batch_state.count += 1;
+
+            let target_count = batch_state.recipe.as_ref().map_or(0, |recipe| recipe.target_count);
+            if batch_state.count >= target_count {
+                batch_state.is_complete = true;
+                for (mut conveyor_state, _) in conveyors.iter_mut() {
+                    conveyor_state.is_running = false;
+                }
+                info!("Batch complete: {} bottles", batch_state.count);
+            }
+        }
+
+        if was_open != valve_state.is_open {
+            if valve_state.is_open {
+                batch_state.open_valves.insert(entity);
+            } else {
+                batch_state.open_valves.remove(&entity);
+            }
+        }
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_recipes_to_modbus(
+    recipe_book: Res<RecipeBook>,
+    batch_state: Res<BatchState>,
+    modbus_state: Res<ModbusState>,
+) {
+    if recipe_book.is_changed() {
+        if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+            holdings.insert(recipe_book.select_holding_address, recipe_book.selected as u16);
+        }
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.entry(recipe_book.start_coil_address).or_insert(false);
+        }
+    }
+
+    if recipe_book.is_changed() || batch_state.is_changed() {
+        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+            discretes.insert(recipe_book.complete_discrete_address, batch_state.is_complete);
+        }
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(recipe_book.count_input_address, batch_state.count);
+        }
+    }
+}
+
+pub fn sync_modbus_to_recipes(
+    mut recipe_book: ResMut<RecipeBook>,
+    mut batch_state: ResMut<BatchState>,
+    mut conveyors: Query<(&mut ConveyorState, &mut BottleSpawner)>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(holdings) = modbus_state.holding_registers.lock() {
+        if let Some(&holding_state) = holdings.get(&recipe_book.select_holding_address) {
+            let selected = holding_state as usize;
+            if recipe_book.selected != selected && selected < recipe_book.recipes.len() {
+                recipe_book.selected = selected;
+                info!("Recipe selected: {}", recipe_book.recipes[selected].name);
+            }
+        }
+    }
+
+    let start = modbus_state
+        .coils
+        .lock()
+        .ok()
+        .and_then(|coils| coils.get(&recipe_book.start_coil_address).copied())
+        .unwrap_or(false);
+
+    // Batches start on the rising edge of the start coil
+    if start && !batch_state.last_start {
+        if let Some(recipe) = recipe_book.selected_recipe() {
+            start_batch(recipe.clone(), &mut batch_state, &mut conveyors);
+        }
+    }
+
+    if batch_state.last_start != start {
+        batch_state.last_start = start;
+    }
+}
+
+// >>> Plugin <<<
+pub struct RecipePlugin;
+
+impl Plugin for RecipePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RecipeBook::default())
+            .insert_resource(BatchState::default())
+            .add_systems(Update, handle_recipe_input)
+            .add_systems(
+                FixedUpdate,
+                (
+                    sync_recipes_to_modbus,
+                    sync_modbus_to_recipes,
+                    run_batch,
+                )
+                    .chain(),
+            );
+    }
+}
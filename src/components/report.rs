@@ -0,0 +1,492 @@
+// report.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use bevy::prelude::*;
+use serde::Serialize;
+
+use super::{
+    alarm::{Alarm, AlarmEvent, AlarmState},
+    conveyor::ConveyorState,
+    energy::EnergyMeter,
+    iolist::csv_field,
+    modbus::ModbusState,
+    stats::ProductionStats,
+    tags::{DeviceName, TagKind, TagRegistry},
+};
+
+// >>> Constants <<<
+const REPORT_PATH: &str = "session_report.csv";
+
+// >>> Session Log <<<
+/// An alarm raised during the session.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlarmRecord {
+    pub alarm: String,
+    pub bit: u16,
+    pub priority: u8,
+    pub raised_at: f32,          // Seconds since startup
+    pub cleared_at: Option<f32>, // None while still active
+}
+
+/// A stretch of time a conveyor stood still.
+#[derive(Debug, Clone, Serialize)]
+pub struct DowntimeRecord {
+    pub device: String,
+    pub stopped_at: f32,
+    pub restarted_at: Option<f32>, // None while still stopped
+    pub duration: f32,
+    pub cause: Option<String>, // Most urgent alarm active when it stopped
+}
+
+/// Energy used by one metered device.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnergyRecord {
+    pub device: String,
+    pub energy: f64,     // kWh
+    pub peak_power: f32, // W
+}
+
+/// Client writes to one coil or holding register.
+#[derive(Debug, Clone, Serialize)]
+pub struct WriteSummary {
+    pub table: TagKind,
+    pub address: u16,
+    pub tag: Option<String>,
+    pub writes: u32,
+    pub first_at: f32,
+    pub last_at: f32,
+    pub last_value: u16,
+}
+
+/// Alarms, downtime, energy use and client writes recorded since startup.
+#[derive(Resource, Default, Debug)]
+pub struct SessionLog {
+    pub alarms: Vec<AlarmRecord>,
+    pub downtime: Vec<DowntimeRecord>,
+    pub energy: BTreeMap<String, EnergyRecord>, // By device, kept across scenario reloads
+    pub writes: BTreeMap<(TagKind, u16), WriteSummary>,
+    open_alarms: HashMap<Alarm, usize>,    // Index into `alarms`
+    open_downtime: HashMap<Entity, usize>, // Index into `downtime`, by conveyor
+}
+
+impl SessionLog {
+    pub fn raise_alarm(&mut self, alarm: Alarm, now: f32) {
+        if self.open_alarms.contains_key(&alarm) {
+            return;
+        }
+        self.open_alarms.insert(alarm, self.alarms.len());
+        self.alarms.push(AlarmRecord {
+            alarm: alarm.description().to_string(),
+            bit: alarm.bit(),
+            priority: alarm.priority(),
+            raised_at: now,
+            cleared_at: None,
+        });
+    }
+
+    pub fn clear_alarm(&mut self, alarm: Alarm, now: f32) {
+        if let Some(index) = self.open_alarms.remove(&alarm) {
+            self.alarms[index].cleared_at = Some(now);
+        }
+    }
+
+    pub fn record_write(&mut self, kind: TagKind, address: u16, value: u16, now: f32) {
+        let summary = self.writes.entry((kind, address)).or_insert(WriteSummary {
+            table: kind,
+            address,
+            tag: None,
+            writes: 0,
+            first_at: now,
+            last_at: now,
+            last_value: value,
+        });
+        summary.writes += 1;
+        summary.last_at = now;
+        summary.last_value = value;
+    }
+
+    /// Adds `power` drawn for `delta` seconds to a device's energy use.
+    pub fn record_energy(&mut self, device: &str, power: f32, delta: f32) {
+        let record = self
+            .energy
+            .entry(device.to_string())
+            .or_insert_with(|| EnergyRecord {
+                device: device.to_string(),
+                energy: 0.0,
+                peak_power: 0.0,
+            });
+        record.energy += power as f64 * delta as f64 / 3_600_000.0;
+        record.peak_power = record.peak_power.max(power);
+    }
+
+    /// Total downtime of each conveyor, in seconds.
+    pub fn downtime_by_device(&self) -> BTreeMap<String, f32> {
+        let mut totals = BTreeMap::new();
+        for record in &self.downtime {
+            *totals.entry(record.device.clone()).or_insert(0.0) += record.duration;
+        }
+        totals
+    }
+}
+
+// >>> Report <<<
+#[derive(Debug, Clone, Serialize)]
+pub struct ProductionSummary {
+    pub completed: u32,
+    pub capped: u32,
+    pub good_labels: u32,
+    pub throughput_per_minute: f32,
+    pub by_variant: BTreeMap<String, u32>,
+}
+
+/// Everything an instructor needs to assess a run, as exported to a file.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionReport {
+    pub duration: f32, // Seconds since startup
+    pub production: ProductionSummary,
+    pub alarms: Vec<AlarmRecord>,
+    pub downtime: Vec<DowntimeRecord>,
+    pub downtime_by_device: BTreeMap<String, f32>,
+    pub energy: Vec<EnergyRecord>,
+    pub writes: Vec<WriteSummary>,
+}
+
+impl SessionReport {
+    /// Assembles the report, naming written registers after their tags.
+    pub fn new(
+        duration: f32,
+        stats: &ProductionStats,
+        log: &SessionLog,
+        tags: &TagRegistry,
+    ) -> Self {
+        let writes = log
+            .writes
+            .values()
+            .map(|summary| WriteSummary {
+                tag: tags
+                    .iter()
+                    .find(|tag| tag.kind == summary.table && tag.address == summary.address)
+                    .map(|tag| tag.name.clone()),
+                ..summary.clone()
+            })
+            .collect();
+
+        Self {
+            duration,
+            production: ProductionSummary {
+                completed: stats.completed,
+                capped: stats.capped,
+                good_labels: stats.good_labels,
+                throughput_per_minute: stats.throughput_per_minute(),
+                by_variant: stats.by_variant.clone(),
+            },
+            alarms: log.alarms.clone(),
+            downtime: log.downtime.clone(),
+            downtime_by_device: log.downtime_by_device(),
+            energy: log.energy.values().cloned().collect(),
+            writes,
+        }
+    }
+
+    /// Formats the report as CSV, one table per section separated by blank
+    /// lines.
+    pub fn to_csv(&self) -> String {
+        let production = &self.production;
+        let mut csv = String::from("Duration,Completed,Capped,Good labels,Throughput per minute\n");
+        csv += &csv_row(&[
+            format!("{:.1}", self.duration),
+            production.completed.to_string(),
+            production.capped.to_string(),
+            production.good_labels.to_string(),
+            format!("{:.1}", production.throughput_per_minute),
+        ]);
+
+        csv += "\nVariant,Completed\n";
+        for (variant, completed) in &production.by_variant {
+            csv += &csv_row(&[variant.clone(), completed.to_string()]);
+        }
+
+        csv += "\nAlarm,Bit,Priority,Raised at,Cleared at\n";
+        for alarm in &self.alarms {
+            csv += &csv_row(&[
+                alarm.alarm.clone(),
+                alarm.bit.to_string(),
+                alarm.priority.to_string(),
+                format!("{:.1}", alarm.raised_at),
+                seconds(alarm.cleared_at),
+            ]);
+        }
+
+        csv += "\nDevice,Stopped at,Started at,Downtime,Cause\n";
+        for record in &self.downtime {
+            csv += &csv_row(&[
+                record.device.clone(),
+                format!("{:.1}", record.stopped_at),
+                seconds(record.restarted_at),
+                format!("{:.1}", record.duration),
+                record.cause.clone().unwrap_or_default(),
+            ]);
+        }
+
+        csv += "\nDevice,Total downtime\n";
+        for (device, total) in &self.downtime_by_device {
+            csv += &csv_row(&[device.clone(), format!("{total:.1}")]);
+        }
+
+        csv += "\nDevice,Energy (kWh),Peak power (W)\n";
+        for record in &self.energy {
+            csv += &csv_row(&[
+                record.device.clone(),
+                format!("{:.3}", record.energy),
+                format!("{:.0}", record.peak_power),
+            ]);
+        }
+
+        csv += "\nTable,Address,Tag,Writes,First at,Last at,Last value\n";
+        for summary in &self.writes {
+            csv += &csv_row(&[
+                summary.table.description().to_string(),
+                summary.address.to_string(),
+                summary.tag.clone().unwrap_or_default(),
+                summary.writes.to_string(),
+                format!("{:.1}", summary.first_at),
+                format!("{:.1}", summary.last_at),
+                summary.last_value.to_string(),
+            ]);
+        }
+        csv
+    }
+}
+
+fn csv_row(fields: &[String]) -> String {
+    let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    fields.join(",") + "\n"
+}
+
+fn seconds(value: Option<f32>) -> String {
+    value.map_or(String::new(), |value| format!("{value:.1}"))
+}
+
+/// File format of an exported report, chosen by the file's extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => ReportFormat::Json,
+            _ => ReportFormat::Csv,
+        }
+    }
+}
+
+fn write_report(path: &Path, report: &SessionReport) -> anyhow::Result<()> {
+    let contents = match ReportFormat::from_path(path) {
+        ReportFormat::Csv => report.to_csv(),
+        ReportFormat::Json => serde_json::to_string_pretty(report)?,
+    };
+    std::fs::write(path, contents).with_context(|| format!("writing {}", path.display()))
+}
+
+fn export(path: &Path, report: &SessionReport) {
+    match write_report(path, report) {
+        Ok(()) => info!("Exported session report to {}", path.display()),
+        Err(err) => warn!("Failed to export session report: {err:#}"),
+    }
+}
+
+// >>> Events <<<
+/// Writes the session report to a file.
+#[derive(Event)]
+pub struct ExportReport(pub PathBuf);
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct ReportSettings {
+    pub path: PathBuf, // Used by the keyboard shortcut and on exit
+}
+
+// >>> Systems <<<
+pub fn record_alarm_history(
+    time: Res<Time>,
+    mut alarm_events: EventReader<AlarmEvent>,
+    mut log: ResMut<SessionLog>,
+) {
+    let now = time.elapsed_secs();
+    for alarm_event in alarm_events.read() {
+        match *alarm_event {
+            AlarmEvent::Raise(alarm) => log.raise_alarm(alarm, now),
+            AlarmEvent::Clear(alarm) => log.clear_alarm(alarm, now),
+        }
+    }
+}
+
+/// Times each conveyor's stops, noting the most urgent alarm active when it
+/// stopped.
+pub fn record_downtime(
+    time: Res<Time>,
+    conveyors: Query<(Entity, &ConveyorState, Option<&DeviceName>)>,
+    alarm_state: Option<Res<AlarmState>>,
+    mut log: ResMut<SessionLog>,
+) {
+    let now = time.elapsed_secs();
+    let log = &mut *log;
+
+    for (entity, conveyor_state, name) in conveyors.iter() {
+        let open = log.open_downtime.get(&entity).copied();
+        match (open, conveyor_state.is_moving()) {
+            (None, false) => {
+                let cause = alarm_state.as_ref().and_then(|alarm_state| {
+                    alarm_state
+                        .entries()
+                        .iter()
+                        .find(|entry| entry.is_active)
+                        .map(|entry| entry.alarm.description().to_string())
+                });
+                log.open_downtime.insert(entity, log.downtime.len());
+                log.downtime.push(DowntimeRecord {
+                    device: name.map_or("conveyor".to_string(), |name| name.0.clone()),
+                    stopped_at: now,
+                    restarted_at: None,
+                    duration: 0.0,
+                    cause,
+                });
+            }
+            (Some(index), false) => {
+                log.downtime[index].duration = now - log.downtime[index].stopped_at;
+            }
+            (Some(index), true) => {
+                log.open_downtime.remove(&entity);
+                log.downtime[index].restarted_at = Some(now);
+                log.downtime[index].duration = now - log.downtime[index].stopped_at;
+            }
+            (None, true) => {}
+        }
+    }
+
+    // Conveyors despawned by a scenario reload stop counting
+    let (downtime, open_downtime) = (&mut log.downtime, &mut log.open_downtime);
+    open_downtime.retain(|entity, index| {
+        let exists = conveyors.contains(*entity);
+        if !exists {
+            downtime[*index].restarted_at = Some(now);
+        }
+        exists
+    });
+}
+
+pub fn record_energy_use(
+    time: Res<Time>,
+    meters: Query<(&EnergyMeter, Option<&DeviceName>)>,
+    mut log: ResMut<SessionLog>,
+) {
+    let delta = time.delta_secs();
+    for (meter, name) in meters.iter() {
+        let device = name.map_or("meter", |name| name.0.as_str());
+        log.record_energy(device, meter.power, delta);
+    }
+}
+
+pub fn record_register_writes(
+    time: Res<Time>,
+    modbus_state: Res<ModbusState>,
+    mut log: ResMut<SessionLog>,
+) {
+    let now = time.elapsed_secs();
+    for write in modbus_state.take_writes() {
+        log.record_write(write.kind, write.address, write.value, now);
+    }
+}
+
+pub fn handle_report_input(
+    settings: Res<ReportSettings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut export_events: EventWriter<ExportReport>,
+) {
+    if keyboard.just_pressed(KeyCode::F6) {
+        export_events.write(ExportReport(settings.path.clone()));
+    }
+}
+
+pub fn export_report(
+    time: Res<Time>,
+    mut export_events: EventReader<ExportReport>,
+    stats: Res<ProductionStats>,
+    log: Res<SessionLog>,
+    tags: Res<TagRegistry>,
+) {
+    for ExportReport(path) in export_events.read() {
+        export(
+            path,
+            &SessionReport::new(time.elapsed_secs(), &stats, &log, &tags),
+        );
+    }
+}
+
+pub fn export_report_on_exit(
+    time: Res<Time>,
+    mut exit_events: EventReader<AppExit>,
+    settings: Res<ReportSettings>,
+    stats: Res<ProductionStats>,
+    log: Res<SessionLog>,
+    tags: Res<TagRegistry>,
+) {
+    if exit_events.read().count() > 0 {
+        export(
+            &settings.path,
+            &SessionReport::new(time.elapsed_secs(), &stats, &log, &tags),
+        );
+    }
+}
+
+// >>> Plugin <<<
+/// Records alarms, conveyor downtime, energy use and Modbus client writes over
+/// the session, and exports them with the production totals as a report, to CSV
+/// or to JSON for a `.json` path. F6 exports to `path`.
+pub struct ReportPlugin {
+    pub path: PathBuf,
+    pub export_on_exit: bool,
+}
+
+impl Default for ReportPlugin {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from(REPORT_PATH),
+            export_on_exit: false,
+        }
+    }
+}
+
+impl Plugin for ReportPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ReportSettings {
+            path: self.path.clone(),
+        })
+        .init_resource::<SessionLog>()
+        .init_resource::<ProductionStats>()
+        .add_event::<AlarmEvent>()
+        .add_event::<ExportReport>()
+        .add_systems(
+            FixedUpdate,
+            (
+                record_alarm_history,
+                record_downtime,
+                record_energy_use,
+                record_register_writes,
+            ),
+        )
+        .add_systems(Update, (handle_report_input, export_report).chain());
+
+        if self.export_on_exit {
+            app.add_systems(Last, export_report_on_exit);
+        }
+    }
+}
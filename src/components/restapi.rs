@@ -0,0 +1,391 @@
+// restapi.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+    collections::BTreeMap,
+    io::Read,
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, Sender, channel},
+    },
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::{
+    actuator::{ValveActuator, ValveFaults},
+    modbus::{ModbusState, NetworkImpairment},
+    sensor::{FaultKind, Sensor, SensorFaults},
+    simulation::{ResetSimulation, SimState},
+    tags::{DeviceName, Tag, TagRegistry},
+};
+use crate::scenario::{LoadScenario, Scenario};
+
+// >>> Constants <<<
+const REST_API_IP: &str = "0.0.0.0";
+const REST_API_PORT: u16 = 8081;
+
+// >>> Messages <<<
+/// Body of `POST /faults`, e.g. `{"sensor": "bottle_sensor", "kind": "stuck_on"}`,
+/// or `{"valve": "valve"}` to seize a valve's actuator.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum FaultRequest {
+    Sensor { sensor: String, kind: FaultKind },
+    Valve { valve: String },
+}
+
+#[derive(Debug, Serialize)]
+struct RegisterDump {
+    coils: BTreeMap<u16, bool>,
+    discrete_inputs: BTreeMap<u16, bool>,
+    input_registers: BTreeMap<u16, u16>,
+    holding_registers: BTreeMap<u16, u16>,
+    tags: BTreeMap<String, u16>,
+}
+
+/// Requests that need the Bevy world rather than the register tables.
+enum RestCommand {
+    LoadScenario(Box<Scenario>),
+    Pause,
+    Resume,
+    Reset,
+    InjectFault(FaultRequest),
+    ClearFault(String),
+    ClearFaults,
+    ImpairNetwork(NetworkImpairment),
+    RestoreNetwork,
+}
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct RestApiSettings {
+    pub port: u16,
+}
+
+#[derive(Resource)]
+pub struct RestApiState {
+    tags: Arc<Mutex<Vec<Tag>>>,
+    sensors: Arc<Mutex<Vec<String>>>, // Sensor tags faults can be injected into
+    valves: Arc<Mutex<Vec<String>>>,  // Names of the valves with an actuator to seize
+    commands: Mutex<Receiver<RestCommand>>,
+    sender: Sender<RestCommand>,
+}
+
+impl Default for RestApiState {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            tags: Arc::new(Mutex::new(Vec::new())),
+            sensors: Arc::new(Mutex::new(Vec::new())),
+            valves: Arc::new(Mutex::new(Vec::new())),
+            commands: Mutex::new(receiver),
+            sender,
+        }
+    }
+}
+
+// >>> Server <<<
+fn dump_registers(tags: &Mutex<Vec<Tag>>, modbus_state: &ModbusState) -> RegisterDump {
+    fn sorted<V: Copy>(table: &Mutex<std::collections::HashMap<u16, V>>) -> BTreeMap<u16, V> {
+        table
+            .lock()
+            .map(|table| table.iter().map(|(&address, &value)| (address, value)).collect())
+            .unwrap_or_default()
+    }
+
+    let tags = tags
+        .lock()
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|tag| Some((tag.name.clone(), tag.read(modbus_state)?)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    RegisterDump {
+        coils: sorted(&modbus_state.coils),
+        discrete_inputs: sorted(&modbus_state.discrete_inputs),
+        input_registers: sorted(&modbus_state.input_registers),
+        holding_registers: sorted(&modbus_state.holding_registers),
+        tags,
+    }
+}
+
+fn is_known(names: &Mutex<Vec<String>>, name: &str) -> bool {
+    names
+        .lock()
+        .is_ok_and(|names| names.iter().any(|known| known == name))
+}
+
+/// Maps a request onto a command or a register dump.
+///
+/// # Return
+/// The HTTP status code and JSON body of the reply
+fn route(
+    method: &Method,
+    url: &str,
+    body: &str,
+    tags: &Mutex<Vec<Tag>>,
+    sensors: &Mutex<Vec<String>>,
+    valves: &Mutex<Vec<String>>,
+    modbus_state: &ModbusState,
+    world_commands: &Sender<RestCommand>,
+) -> (u16, serde_json::Value) {
+    let path = url.split('?').next().unwrap_or(url).trim_end_matches('/');
+
+    let command = match (method, path) {
+        (Method::Get, "/registers") => {
+            return (200, json!(dump_registers(tags, modbus_state)));
+        }
+        (Method::Post, "/scenario") => match Scenario::parse(body) {
+            Ok(scenario) => RestCommand::LoadScenario(Box::new(scenario)),
+            Err(err) => return (400, json!({ "error": format!("Invalid scenario: {err}") })),
+        },
+        (Method::Post, "/pause") => RestCommand::Pause,
+        (Method::Post, "/resume") => RestCommand::Resume,
+        (Method::Post, "/reset") => RestCommand::Reset,
+        (Method::Post, "/faults") => match serde_json::from_str::<FaultRequest>(body) {
+            Ok(FaultRequest::Sensor { ref sensor, .. }) if !is_known(sensors, sensor) => {
+                return (404, json!({ "error": "No such sensor" }));
+            }
+            Ok(FaultRequest::Valve { ref valve }) if !is_known(valves, valve) => {
+                return (404, json!({ "error": "No such valve actuator" }));
+            }
+            Ok(fault) => RestCommand::InjectFault(fault),
+            Err(err) => return (400, json!({ "error": format!("Invalid fault: {err}") })),
+        },
+        (Method::Delete, "/faults") => RestCommand::ClearFaults,
+        (Method::Delete, path) if path.starts_with("/faults/") => {
+            RestCommand::ClearFault(path["/faults/".len()..].to_string())
+        }
+        (Method::Post, "/network") => match serde_json::from_str::<NetworkImpairment>(body) {
+            Ok(impairment) => match impairment.validate() {
+                Ok(()) => RestCommand::ImpairNetwork(impairment),
+                Err(err) => return (400, json!({ "error": format!("Invalid impairment: {err}") })),
+            },
+            Err(err) => return (400, json!({ "error": format!("Invalid impairment: {err}") })),
+        },
+        (Method::Delete, "/network") => RestCommand::RestoreNetwork,
+        _ => return (404, json!({ "error": format!("No route for {method} {path}") })),
+    };
+
+    match world_commands.send(command) {
+        Ok(()) => (202, json!({ "status": "accepted" })),
+        Err(err) => (503, json!({ "error": err.to_string() })),
+    }
+}
+
+fn handle_request(
+    mut request: Request,
+    tags: &Mutex<Vec<Tag>>,
+    sensors: &Mutex<Vec<String>>,
+    valves: &Mutex<Vec<String>>,
+    modbus_state: &ModbusState,
+    world_commands: &Sender<RestCommand>,
+) -> std::io::Result<()> {
+    let mut body = String::new();
+    let (status, reply) = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => route(
+            request.method(),
+            request.url(),
+            &body,
+            tags,
+            sensors,
+            valves,
+            modbus_state,
+            world_commands,
+        ),
+        Err(err) => (400, json!({ "error": err.to_string() })),
+    };
+
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    request.respond(
+        Response::from_string(reply.to_string())
+            .with_status_code(status)
+            .with_header(content_type),
+    )
+}
+
+// >>> Systems <<<
+fn start_rest_api_server(
+    settings: Res<RestApiSettings>,
+    rest_api_state: Res<RestApiState>,
+    modbus_state: Res<ModbusState>,
+) {
+    let socket_addr = format!("{}:{}", REST_API_IP, settings.port);
+    let tags = rest_api_state.tags.clone();
+    let sensors = rest_api_state.sensors.clone();
+    let valves = rest_api_state.valves.clone();
+    let sender = rest_api_state.sender.clone();
+    let state = modbus_state.clone();
+    std::thread::spawn(move || {
+        let server = match Server::http(&socket_addr) {
+            Ok(server) => server,
+            Err(err) => {
+                eprintln!("REST API failed to bind {socket_addr}: {err}");
+                return;
+            }
+        };
+        println!("REST API running on http://{socket_addr}");
+
+        for request in server.incoming_requests() {
+            if let Err(err) = handle_request(request, &tags, &sensors, &valves, &state, &sender) {
+                eprintln!("REST API response failed: {err}");
+            }
+        }
+    });
+}
+
+pub fn sync_rest_api_tags(tags: Res<TagRegistry>, rest_api_state: Res<RestApiState>) {
+    if tags.is_changed() {
+        if let Ok(mut shared) = rest_api_state.tags.lock() {
+            *shared = tags.iter().cloned().collect();
+        }
+    }
+}
+
+/// Shares the sensor tags with the server thread whenever sensors are
+/// spawned or despawned, so faults on unknown sensors are refused.
+pub fn sync_rest_api_sensors(
+    sensors: Query<&Sensor>,
+    added: Query<(), Added<Sensor>>,
+    mut removed: RemovedComponents<Sensor>,
+    rest_api_state: Res<RestApiState>,
+) {
+    if added.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+    if let Ok(mut shared) = rest_api_state.sensors.lock() {
+        *shared = sensors
+            .iter()
+            .map(|sensor| sensor.sensor_tag.clone())
+            .collect();
+    }
+}
+
+/// Shares the names of the valves with an actuator with the server thread,
+/// so seizing an unknown valve is refused.
+pub fn sync_rest_api_valves(
+    valves: Query<&DeviceName, With<ValveActuator>>,
+    added: Query<(), Added<ValveActuator>>,
+    mut removed: RemovedComponents<ValveActuator>,
+    rest_api_state: Res<RestApiState>,
+) {
+    if added.is_empty() && removed.read().count() == 0 {
+        return;
+    }
+    if let Ok(mut shared) = rest_api_state.valves.lock() {
+        *shared = valves.iter().map(|DeviceName(name)| name.clone()).collect();
+    }
+}
+
+/// Runs in `Update` so pause and resume are handled while the fixed timestep is stopped.
+pub fn apply_rest_api_commands(
+    rest_api_state: Res<RestApiState>,
+    state: Res<State<SimState>>,
+    mut next_state: ResMut<NextState<SimState>>,
+    mut faults: ResMut<SensorFaults>,
+    mut valve_faults: ResMut<ValveFaults>,
+    mut network: Option<ResMut<NetworkImpairment>>,
+    mut load_events: EventWriter<LoadScenario>,
+    mut reset_events: EventWriter<ResetSimulation>,
+) {
+    let Ok(receiver) = rest_api_state.commands.lock() else {
+        return;
+    };
+
+    for command in receiver.try_iter() {
+        match command {
+            RestCommand::LoadScenario(scenario) => {
+                info!("Loading scenario {} via REST API", scenario.name);
+                load_events.write(LoadScenario(*scenario));
+            }
+            RestCommand::Pause => {
+                if *state.get() == SimState::Running {
+                    next_state.set(SimState::Paused);
+                    info!("Simulation paused via REST API");
+                }
+            }
+            RestCommand::Resume => {
+                if *state.get() == SimState::Paused {
+                    next_state.set(SimState::Running);
+                    info!("Simulation resumed via REST API");
+                }
+            }
+            RestCommand::Reset => {
+                reset_events.write(ResetSimulation);
+            }
+            RestCommand::InjectFault(FaultRequest::Sensor { sensor, kind }) => {
+                info!("Injected {:?} fault into {}", kind, sensor);
+                faults.inject(&sensor, kind);
+            }
+            RestCommand::InjectFault(FaultRequest::Valve { valve }) => {
+                info!("Seized the actuator of {}", valve);
+                valve_faults.seize(&valve);
+            }
+            RestCommand::ClearFault(name) => {
+                let had_sensor_fault = faults.clear(&name);
+                if valve_faults.free(&name) || had_sensor_fault {
+                    info!("Cleared fault on {}", name);
+                }
+            }
+            RestCommand::ClearFaults => {
+                faults.clear_all();
+                valve_faults.clear_all();
+                info!("Cleared all sensor and valve faults");
+            }
+            RestCommand::ImpairNetwork(impairment) => match network.as_deref_mut() {
+                Some(network) => {
+                    info!("Impaired the Modbus network: {:?}", impairment);
+                    *network = impairment;
+                }
+                None => warn!("No scenario loaded to impair the network of"),
+            },
+            RestCommand::RestoreNetwork => {
+                if let Some(network) = network.as_deref_mut() {
+                    *network = NetworkImpairment::default();
+                    info!("Restored the Modbus network");
+                }
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Serves an HTTP API for orchestration: load scenarios, pause, resume,
+/// reset, inject sensor and valve faults, impair the Modbus network and dump
+/// every register as JSON.
+pub struct RestApiPlugin {
+    pub port: u16,
+}
+
+impl Default for RestApiPlugin {
+    fn default() -> Self {
+        Self {
+            port: REST_API_PORT,
+        }
+    }
+}
+
+impl Plugin for RestApiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RestApiSettings { port: self.port })
+            .init_resource::<RestApiState>()
+            .add_event::<LoadScenario>()
+            .add_systems(Startup, start_rest_api_server)
+            .add_systems(
+                Update,
+                (
+                    sync_rest_api_tags,
+                    sync_rest_api_sensors,
+                    sync_rest_api_valves,
+                    apply_rest_api_commands,
+                ),
+            );
+    }
+}
@@ -0,0 +1,664 @@
+// s7.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Minimal S7comm server over ISO-on-TCP (RFC 1006): connection setup,
+// communication negotiation, and Read/Write Var jobs.
+use std::{
+    fmt,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, bail};
+use bevy::prelude::*;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use super::{
+    arbitration::WriteOrigin,
+    modbus::ModbusState,
+    tags::{Tag, TagKind, TagRegistry},
+};
+
+// >>> Constants <<<
+const S7_IP: &str = "0.0.0.0";
+const S7_PORT: u16 = 102;
+const DEFAULT_DB_NUMBER: u16 = 1;
+const MAX_PDU_SIZE: u16 = 480;
+
+const COTP_CONNECTION_REQUEST: u8 = 0xE0;
+const COTP_CONNECTION_CONFIRM: u8 = 0xD0;
+const COTP_DISCONNECT_REQUEST: u8 = 0x80;
+const COTP_DATA: u8 = 0xF0;
+
+const S7_PROTOCOL_ID: u8 = 0x32;
+const ROSCTR_JOB: u8 = 0x01;
+const ROSCTR_ACK_DATA: u8 = 0x03;
+
+const FUNCTION_SETUP_COMMUNICATION: u8 = 0xF0;
+const FUNCTION_READ_VAR: u8 = 0x04;
+const FUNCTION_WRITE_VAR: u8 = 0x05;
+
+const AREA_INPUTS: u8 = 0x81;
+const AREA_OUTPUTS: u8 = 0x82;
+const AREA_DATA_BLOCK: u8 = 0x84;
+
+const TRANSPORT_BIT: u8 = 0x01;
+const DATA_TRANSPORT_BIT: u8 = 0x03;
+const DATA_TRANSPORT_BYTE: u8 = 0x04;
+const DATA_TRANSPORT_OCTET: u8 = 0x09;
+
+const RETURN_SUCCESS: u8 = 0xFF;
+const RETURN_ADDRESS_OUT_OF_RANGE: u8 = 0x05;
+const RETURN_DATA_TYPE_NOT_SUPPORTED: u8 = 0x06;
+const RETURN_OBJECT_DOES_NOT_EXIST: u8 = 0x0A;
+
+const ERROR_FUNCTION_NOT_IMPLEMENTED: u16 = 0x8104;
+const ERROR_PDU_SIZE: u16 = 0x8500; // The reply would not fit in a PDU
+
+// >>> Addresses <<<
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum S7Area {
+    Inputs,
+    Outputs,
+    DataBlock(u16),
+}
+
+impl S7Area {
+    fn from_code(code: u8, db_number: u16) -> Option<Self> {
+        match code {
+            AREA_INPUTS => Some(S7Area::Inputs),
+            AREA_OUTPUTS => Some(S7Area::Outputs),
+            AREA_DATA_BLOCK => Some(S7Area::DataBlock(db_number)),
+            _ => None,
+        }
+    }
+}
+
+/// Location of a tag in the S7 address space. Bit tags carry a bit number,
+/// word tags occupy two bytes big-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct S7Address {
+    pub area: S7Area,
+    pub byte: u16,
+    pub bit: Option<u8>,
+}
+
+impl S7Address {
+    /// Parses STEP 7 notation: `I0.0`, `Q1.3`, `IW4`, `QW2`, `DB1.DBX0.0` or `DB1.DBW4`.
+    pub fn parse(address: &str) -> Option<Self> {
+        let address = address.trim().to_ascii_uppercase();
+
+        let (area, rest) = if let Some(rest) = address.strip_prefix("DB") {
+            let (db_number, rest) = rest.split_once(".DB")?;
+            (S7Area::DataBlock(db_number.parse().ok()?), rest.to_string())
+        } else if let Some(rest) = address.strip_prefix('I') {
+            (S7Area::Inputs, format!("X{rest}"))
+        } else if let Some(rest) = address.strip_prefix('Q') {
+            (S7Area::Outputs, format!("X{rest}"))
+        } else {
+            return None;
+        };
+
+        if let Some(word) = rest.strip_prefix("XW").or_else(|| rest.strip_prefix('W')) {
+            return Some(Self {
+                area,
+                byte: word.parse().ok()?,
+                bit: None,
+            });
+        }
+
+        let (byte, bit) = rest.strip_prefix('X')?.split_once('.')?;
+        let bit: u8 = bit.parse().ok()?;
+        (bit < 8).then_some(Self {
+            area,
+            byte: byte.parse().ok()?,
+            bit: Some(bit),
+        })
+    }
+}
+
+impl fmt::Display for S7Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.area, self.bit) {
+            (S7Area::Inputs, Some(bit)) => write!(f, "I{}.{}", self.byte, bit),
+            (S7Area::Inputs, None) => write!(f, "IW{}", self.byte),
+            (S7Area::Outputs, Some(bit)) => write!(f, "Q{}.{}", self.byte, bit),
+            (S7Area::Outputs, None) => write!(f, "QW{}", self.byte),
+            (S7Area::DataBlock(db), Some(bit)) => write!(f, "DB{}.DBX{}.{}", db, self.byte, bit),
+            (S7Area::DataBlock(db), None) => write!(f, "DB{}.DBW{}", db, self.byte),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct S7Mapping {
+    pub tag: Tag,
+    pub address: S7Address,
+}
+
+/// Lays every registered tag out in the S7 address space.
+///
+/// Discrete inputs become process inputs, coils become process outputs, and
+/// registers are packed as words into the data block. Overrides take precedence.
+///
+/// # Parameters
+/// * `tags` - Tags to map
+/// * `db_number` - Data block holding register tags
+/// * `overrides` - Explicit addresses by tag name
+///
+/// # Return
+/// The tag mappings
+pub fn build_layout(
+    tags: &TagRegistry,
+    db_number: u16,
+    overrides: &[(String, S7Address)],
+) -> Vec<S7Mapping> {
+    let mut next_input_bit = 0u16;
+    let mut next_output_bit = 0u16;
+    let mut next_db_byte = 0u16;
+
+    tags.iter()
+        .map(|tag| {
+            let address = match overrides.iter().find(|(name, _)| *name == tag.name) {
+                Some((_, address)) => *address,
+                None => match tag.kind {
+                    TagKind::DiscreteInput => {
+                        next_input_bit += 1;
+                        bit_address(S7Area::Inputs, next_input_bit - 1)
+                    }
+                    TagKind::Coil => {
+                        next_output_bit += 1;
+                        bit_address(S7Area::Outputs, next_output_bit - 1)
+                    }
+                    TagKind::InputRegister | TagKind::HoldingRegister => {
+                        next_db_byte += 2;
+                        S7Address {
+                            area: S7Area::DataBlock(db_number),
+                            byte: next_db_byte - 2,
+                            bit: None,
+                        }
+                    }
+                },
+            };
+
+            S7Mapping {
+                tag: tag.clone(),
+                address,
+            }
+        })
+        .collect()
+}
+
+fn bit_address(area: S7Area, index: u16) -> S7Address {
+    S7Address {
+        area,
+        byte: index / 8,
+        bit: Some((index % 8) as u8),
+    }
+}
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct S7Settings {
+    pub port: u16,
+    pub db_number: u16,
+    pub overrides: Vec<(String, S7Address)>,
+}
+
+/// Tag layout shared with the server thread.
+#[derive(Resource, Clone, Default)]
+pub struct S7Layout {
+    mappings: Arc<Mutex<Vec<S7Mapping>>>,
+}
+
+impl S7Layout {
+    pub fn mappings(&self) -> Vec<S7Mapping> {
+        self.mappings
+            .lock()
+            .map(|mappings| mappings.clone())
+            .unwrap_or_default()
+    }
+}
+
+// >>> Memory Access <<<
+fn read_area(
+    mappings: &[S7Mapping],
+    modbus_state: &ModbusState,
+    area: S7Area,
+    start: usize,
+    length: usize,
+) -> Vec<u8> {
+    let mut bytes = vec![0u8; length];
+
+    for mapping in mappings.iter().filter(|mapping| mapping.address.area == area) {
+        let Some(value) = mapping.tag.read(modbus_state) else {
+            continue;
+        };
+        let offset = mapping.address.byte as usize;
+
+        match mapping.address.bit {
+            Some(bit) => {
+                if (start..start + length).contains(&offset) && value != 0 {
+                    bytes[offset - start] |= 1 << bit;
+                }
+            }
+            None => {
+                for (i, byte) in value.to_be_bytes().into_iter().enumerate() {
+                    if (start..start + length).contains(&(offset + i)) {
+                        bytes[offset + i - start] = byte;
+                    }
+                }
+            }
+        }
+    }
+
+    bytes
+}
+
+fn write_area(
+    mappings: &[S7Mapping],
+    modbus_state: &ModbusState,
+    area: S7Area,
+    start: usize,
+    bytes: &[u8],
+) {
+    let range = start..start + bytes.len();
+    let origin = WriteOrigin::remote("s7");
+
+    for mapping in mappings.iter().filter(|mapping| mapping.address.area == area) {
+        let offset = mapping.address.byte as usize;
+
+        match mapping.address.bit {
+            Some(bit) if range.contains(&offset) => {
+                let value = (bytes[offset - start] >> bit) & 1;
+                mapping.tag.write(modbus_state, value as u16, &origin);
+            }
+            None if range.contains(&offset) && range.contains(&(offset + 1)) => {
+                let value = u16::from_be_bytes([bytes[offset - start], bytes[offset + 1 - start]]);
+                mapping.tag.write(modbus_state, value, &origin);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_bit(
+    mappings: &[S7Mapping],
+    modbus_state: &ModbusState,
+    area: S7Area,
+    byte: usize,
+    bit: u8,
+    value: bool,
+) {
+    // Only the addressed bit is written so neighbouring tags keep their values
+    let origin = WriteOrigin::remote("s7");
+    for mapping in mappings.iter().filter(|mapping| {
+        mapping.address.area == area
+            && mapping.address.byte as usize == byte
+            && mapping.address.bit == Some(bit)
+    }) {
+        mapping.tag.write(modbus_state, value as u16, &origin);
+    }
+}
+
+fn area_exists(mappings: &[S7Mapping], area: S7Area) -> bool {
+    match area {
+        S7Area::Inputs | S7Area::Outputs => true,
+        S7Area::DataBlock(_) => mappings.iter().any(|mapping| mapping.address.area == area),
+    }
+}
+
+// >>> Protocol <<<
+struct ItemRequest {
+    transport: u8,
+    count: u16,
+    area: Option<S7Area>,
+    start_bit: usize,
+}
+
+impl ItemRequest {
+    fn byte_length(&self) -> Option<usize> {
+        let size = match self.transport {
+            0x02 | 0x03 => 1, // BYTE, CHAR
+            0x04 | 0x05 => 2, // WORD, INT
+            0x06..=0x08 => 4, // DWORD, DINT, REAL
+            _ => return None,
+        };
+        Some(self.count as usize * size)
+    }
+}
+
+fn parse_items(params: &[u8]) -> anyhow::Result<Vec<ItemRequest>> {
+    let count = *params.get(1).context("Missing item count")? as usize;
+
+    (0..count)
+        .map(|i| {
+            let item = params
+                .get(2 + i * 12..14 + i * 12)
+                .context("Truncated item")?;
+            let db_number = u16::from_be_bytes([item[6], item[7]]);
+
+            Ok(ItemRequest {
+                transport: item[3],
+                count: u16::from_be_bytes([item[4], item[5]]),
+                area: S7Area::from_code(item[8], db_number),
+                start_bit: u32::from_be_bytes([0, item[9], item[10], item[11]]) as usize,
+            })
+        })
+        .collect()
+}
+
+fn read_var(
+    params: &[u8],
+    mappings: &[S7Mapping],
+    modbus_state: &ModbusState,
+) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let items = parse_items(params)?;
+    let mut data = Vec::new();
+
+    for (i, item) in items.iter().enumerate() {
+        match item.area.filter(|&area| area_exists(mappings, area)) {
+            None => data.extend_from_slice(&[RETURN_OBJECT_DOES_NOT_EXIST, 0x00, 0x00, 0x00]),
+            Some(area) if item.transport == TRANSPORT_BIT => {
+                let byte = read_area(mappings, modbus_state, area, item.start_bit / 8, 1)[0];
+                let value = (byte >> (item.start_bit % 8)) & 1;
+                data.extend_from_slice(&[RETURN_SUCCESS, DATA_TRANSPORT_BIT, 0x00, 0x01, value]);
+            }
+            Some(area) => match item.byte_length() {
+                // Checked before reading, as a client picks the count
+                Some(length) if length > MAX_PDU_SIZE as usize => {
+                    data.extend_from_slice(&[RETURN_ADDRESS_OUT_OF_RANGE, 0x00, 0x00, 0x00])
+                }
+                Some(length) => {
+                    let bytes = read_area(mappings, modbus_state, area, item.start_bit / 8, length);
+                    data.extend_from_slice(&[RETURN_SUCCESS, DATA_TRANSPORT_BYTE]);
+                    data.extend_from_slice(&u16::try_from(length * 8)?.to_be_bytes());
+                    data.extend_from_slice(&bytes);
+                }
+                None => data.extend_from_slice(&[RETURN_DATA_TYPE_NOT_SUPPORTED, 0x00, 0x00, 0x00]),
+            },
+        }
+
+        // Items are word aligned, except the last
+        if data.len() % 2 == 1 && i + 1 < items.len() {
+            data.push(0x00);
+        }
+    }
+
+    Ok((vec![FUNCTION_READ_VAR, items.len() as u8], data))
+}
+
+fn write_var(
+    params: &[u8],
+    data: &[u8],
+    mappings: &[S7Mapping],
+    modbus_state: &ModbusState,
+) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let items = parse_items(params)?;
+    let mut return_codes = Vec::with_capacity(items.len());
+    let mut offset = 0;
+
+    for item in items.iter() {
+        let header = data.get(offset..offset + 4).context("Truncated write data")?;
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let byte_length = match header[1] {
+            DATA_TRANSPORT_BIT | DATA_TRANSPORT_BYTE => length.div_ceil(8),
+            DATA_TRANSPORT_OCTET => length,
+            _ => length,
+        };
+        let values = data
+            .get(offset + 4..offset + 4 + byte_length)
+            .context("Truncated write data")?;
+        offset += 4 + byte_length + byte_length % 2;
+
+        let return_code = match item.area.filter(|&area| area_exists(mappings, area)) {
+            None => RETURN_OBJECT_DOES_NOT_EXIST,
+            Some(_) if values.is_empty() => RETURN_ADDRESS_OUT_OF_RANGE,
+            Some(area) if item.transport == TRANSPORT_BIT => {
+                write_bit(
+                    mappings,
+                    modbus_state,
+                    area,
+                    item.start_bit / 8,
+                    (item.start_bit % 8) as u8,
+                    values[0] & 1 == 1,
+                );
+                RETURN_SUCCESS
+            }
+            Some(area) => {
+                write_area(mappings, modbus_state, area, item.start_bit / 8, values);
+                RETURN_SUCCESS
+            }
+        };
+        return_codes.push(return_code);
+    }
+
+    Ok((vec![FUNCTION_WRITE_VAR, items.len() as u8], return_codes))
+}
+
+fn ack_data(pdu_ref: [u8; 2], params: &[u8], data: &[u8], error: u16) -> anyhow::Result<Vec<u8>> {
+    // Replies over the PDU size are refused whole rather than cut short
+    if 12 + params.len() + data.len() > MAX_PDU_SIZE as usize {
+        return ack_data(pdu_ref, &[], &[], ERROR_PDU_SIZE);
+    }
+
+    let mut pdu = vec![S7_PROTOCOL_ID, ROSCTR_ACK_DATA, 0x00, 0x00, pdu_ref[0], pdu_ref[1]];
+    pdu.extend_from_slice(&u16::try_from(params.len())?.to_be_bytes());
+    pdu.extend_from_slice(&u16::try_from(data.len())?.to_be_bytes());
+    pdu.extend_from_slice(&error.to_be_bytes());
+    pdu.extend_from_slice(params);
+    pdu.extend_from_slice(data);
+    Ok(pdu)
+}
+
+fn handle_s7_pdu(
+    pdu: &[u8],
+    layout: &S7Layout,
+    modbus_state: &ModbusState,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    if pdu.len() < 10 || pdu[0] != S7_PROTOCOL_ID {
+        bail!("Invalid S7 header");
+    }
+    if pdu[1] != ROSCTR_JOB {
+        return Ok(None);
+    }
+
+    let pdu_ref = [pdu[4], pdu[5]];
+    let param_length = u16::from_be_bytes([pdu[6], pdu[7]]) as usize;
+    let data_length = u16::from_be_bytes([pdu[8], pdu[9]]) as usize;
+    let params = pdu
+        .get(10..10 + param_length)
+        .context("Truncated S7 parameters")?;
+    let data = pdu
+        .get(10 + param_length..10 + param_length + data_length)
+        .context("Truncated S7 data")?;
+    let mappings = layout.mappings();
+
+    let reply = match params.first() {
+        Some(&FUNCTION_SETUP_COMMUNICATION) => {
+            let mut params = params.to_vec();
+            if let Some(pdu_size) = params.get_mut(6..8) {
+                let requested = u16::from_be_bytes([pdu_size[0], pdu_size[1]]);
+                pdu_size.copy_from_slice(&requested.min(MAX_PDU_SIZE).to_be_bytes());
+            }
+            ack_data(pdu_ref, &params, &[], 0)?
+        }
+        Some(&FUNCTION_READ_VAR) => {
+            let (params, data) = read_var(params, &mappings, modbus_state)?;
+            ack_data(pdu_ref, &params, &data, 0)?
+        }
+        Some(&FUNCTION_WRITE_VAR) => {
+            let (params, data) = write_var(params, data, &mappings, modbus_state)?;
+            ack_data(pdu_ref, &params, &data, 0)?
+        }
+        _ => ack_data(pdu_ref, &[], &[], ERROR_FUNCTION_NOT_IMPLEMENTED)?,
+    };
+
+    Ok(Some(reply))
+}
+
+fn handle_tpdu(
+    tpdu: &[u8],
+    layout: &S7Layout,
+    modbus_state: &ModbusState,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let header_length = *tpdu.first().context("Empty COTP frame")? as usize + 1;
+    let pdu_type = *tpdu.get(1).context("Short COTP frame")?;
+
+    match pdu_type {
+        COTP_CONNECTION_REQUEST => {
+            let header = tpdu.get(..header_length).context("Short COTP frame")?;
+            if header.len() < 7 {
+                bail!("Short connection request");
+            }
+
+            // Confirm with the caller's reference and echo its TSAP/TPDU size parameters
+            let mut confirm = vec![0x00, COTP_CONNECTION_CONFIRM, header[4], header[5], 0x00, 0x01, 0x00];
+            confirm.extend_from_slice(&header[7..]);
+            confirm[0] = (confirm.len() - 1) as u8;
+            Ok(Some(confirm))
+        }
+        COTP_DATA => {
+            let pdu = tpdu.get(header_length..).context("Short COTP frame")?;
+            Ok(handle_s7_pdu(pdu, layout, modbus_state)?.map(|reply| {
+                let mut frame = vec![0x02, COTP_DATA, 0x80];
+                frame.extend(reply);
+                frame
+            }))
+        }
+        COTP_DISCONNECT_REQUEST => Ok(None),
+        other => bail!("Unsupported COTP PDU type {other:#04x}"),
+    }
+}
+
+async fn serve_connection(
+    mut stream: TcpStream,
+    layout: S7Layout,
+    modbus_state: ModbusState,
+) -> anyhow::Result<()> {
+    loop {
+        let mut header = [0u8; 4];
+        if stream.read_exact(&mut header).await.is_err() {
+            return Ok(());
+        }
+        if header[0] != 0x03 {
+            bail!("Invalid TPKT version {}", header[0]);
+        }
+
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        if length < 7 {
+            bail!("Short TPKT frame");
+        }
+        let mut tpdu = vec![0u8; length - 4];
+        stream.read_exact(&mut tpdu).await?;
+
+        if let Some(reply) = handle_tpdu(&tpdu, &layout, &modbus_state)? {
+            let mut frame = vec![0x03, 0x00];
+            let length = u16::try_from(reply.len() + 4).context("S7 reply too long")?;
+            frame.extend_from_slice(&length.to_be_bytes());
+            frame.extend(reply);
+            stream.write_all(&frame).await?;
+        }
+    }
+}
+
+// >>> Systems <<<
+fn start_s7_server(settings: Res<S7Settings>, layout: Res<S7Layout>, modbus_state: Res<ModbusState>) {
+    let port = settings.port;
+    let layout = layout.clone();
+    let state = modbus_state.clone();
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let socket_addr: SocketAddr = format!("{}:{}", S7_IP, port).parse().unwrap();
+            let listener = match TcpListener::bind(socket_addr).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    eprintln!("S7 server failed to bind {socket_addr}: {err}");
+                    return;
+                }
+            };
+            println!("S7 server running on {socket_addr}");
+
+            loop {
+                let Ok((stream, peer)) = listener.accept().await else {
+                    continue;
+                };
+                let layout = layout.clone();
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = serve_connection(stream, layout, state).await {
+                        eprintln!("S7 connection {peer}: {err}");
+                    }
+                });
+            }
+        });
+    });
+}
+
+pub fn sync_s7_layout(tags: Res<TagRegistry>, settings: Res<S7Settings>, layout: Res<S7Layout>) {
+    if tags.is_changed() || settings.is_changed() {
+        let mappings = build_layout(&tags, settings.db_number, &settings.overrides);
+        for mapping in mappings.iter() {
+            debug!("S7 {} -> {}", mapping.address, mapping.tag.name);
+        }
+        if let Ok(mut shared) = layout.mappings.lock() {
+            *shared = mappings;
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Serves the tag registry to S7 clients such as TIA Portal or PLCSIM.
+pub struct S7Plugin {
+    pub port: u16,
+    pub db_number: u16,
+    overrides: Vec<(String, String)>,
+}
+
+impl Default for S7Plugin {
+    fn default() -> Self {
+        Self {
+            port: S7_PORT,
+            db_number: DEFAULT_DB_NUMBER,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl S7Plugin {
+    /// Places a tag at an explicit S7 address, e.g. `map("conveyor.speed", "DB1.DBW10")`.
+    pub fn map(mut self, tag: &str, address: &str) -> Self {
+        self.overrides.push((tag.to_string(), address.to_string()));
+        self
+    }
+}
+
+impl Plugin for S7Plugin {
+    fn build(&self, app: &mut App) {
+        let overrides = self
+            .overrides
+            .iter()
+            .filter_map(|(tag, address)| match S7Address::parse(address) {
+                Some(parsed) => Some((tag.clone(), parsed)),
+                None => {
+                    warn!("Ignoring invalid S7 address {address} for tag {tag}");
+                    None
+                }
+            })
+            .collect();
+
+        app.insert_resource(S7Settings {
+            port: self.port,
+            db_number: self.db_number,
+            overrides,
+        })
+        .init_resource::<S7Layout>()
+        .add_systems(Startup, start_s7_server)
+        .add_systems(Update, sync_s7_layout);
+    }
+}
@@ -0,0 +1,183 @@
+// score.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use super::{
+    bottle::{Bottle, BottlePosition, BottleSpec},
+    modbus::ModbusState,
+    valve::Ball,
+};
+
+// >>> Constants <<<
+const SCORE_LINE_COLOR: Color = Color::srgba(1.0, 0.8, 0.2, 0.5);
+const SCORE_LINE_THICKNESS: f32 = 2.0;
+
+// >>> Components <<<
+/// Scoring line for dropped liquid, just below the rims of the bottles
+/// passing under a valve. Each ball falling through the line counts as a hit
+/// if it is inside a bottle, and as a miss otherwise.
+#[derive(Component)]
+pub struct DropScore {
+    pub hits_input_address: u16,
+    pub misses_input_address: u16,
+    pub reset_coil_address: u16,
+    pub hits: u16,
+    pub misses: u16,
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct DropScoreBundle {
+    score: DropScore,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl DropScore {
+    /// Creates a scoring line with no drops scored.
+    ///
+    /// # Parameters
+    /// * `hits_input_address` - Input register holding the balls caught by a bottle
+    /// * `misses_input_address` - Input register holding the balls that missed
+    /// * `reset_coil_address` - Coil that clears both counts
+    /// * `position` - Centre of the line
+    /// * `width` - Width of the line
+    ///
+    /// # Return
+    /// The drop score bundle
+    pub fn new(
+        hits_input_address: u16,
+        misses_input_address: u16,
+        reset_coil_address: u16,
+        position: Vec2,
+        width: f32,
+    ) -> DropScoreBundle {
+        DropScoreBundle {
+            score: DropScore {
+                hits_input_address,
+                misses_input_address,
+                reset_coil_address,
+                hits: 0,
+                misses: 0,
+            },
+            sprite: Sprite::from_color(SCORE_LINE_COLOR, Vec2::new(width, SCORE_LINE_THICKNESS)),
+            transform: Transform::from_translation(position.extend(-0.2)),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_drop_scores(
+    scores: Query<&DropScore, Added<DropScore>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for score in scores.iter() {
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(score.hits_input_address, score.hits);
+            inputs.insert(score.misses_input_address, score.misses);
+        }
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(score.reset_coil_address, false);
+        }
+
+        info!(
+            "Registered drop score on hits input register: {:x?}",
+            score.hits_input_address
+        );
+    }
+}
+
+/// Scores each ball falling through a scoring line, by whether a bottle
+/// holds the point where it crossed.
+pub fn score_falling_balls(
+    mut scores: Query<(&mut DropScore, &Transform, &Sprite)>,
+    balls: Query<(Entity, &Transform), With<Ball>>,
+    bottles: Query<(&Transform, &BottlePosition, &BottleSpec), With<Bottle>>,
+    mut last_heights: Local<HashMap<Entity, f32>>,
+) {
+    let mut heights = HashMap::new();
+
+    for (ball, ball_transform) in balls.iter() {
+        let point = ball_transform.translation.truncate();
+        heights.insert(ball, point.y);
+        // Balls just dispensed have no height to cross from yet
+        let Some(&last_height) = last_heights.get(&ball) else {
+            continue;
+        };
+
+        for (mut score, transform, sprite) in scores.iter_mut() {
+            let line = transform.translation.truncate();
+            let half_width = sprite.custom_size.unwrap_or_default().x / 2.0;
+            if last_height < line.y || point.y >= line.y || (point.x - line.x).abs() > half_width {
+                continue;
+            }
+
+            let is_caught = bottles
+                .iter()
+                .any(|(transform, position, spec)| position.contains(transform, spec, point));
+            if is_caught {
+                score.hits = score.hits.saturating_add(1);
+            } else {
+                score.misses = score.misses.saturating_add(1);
+            }
+        }
+    }
+
+    // Balls taken out of play are forgotten, so a pooled ball starts afresh
+    *last_heights = heights;
+}
+
+// >>> Modbus Synchronization <<<
+/// Clears drop scores whose reset coil was written; the coil resets itself.
+pub fn sync_modbus_to_drop_scores(
+    mut scores: Query<&mut DropScore>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Ok(mut coils) = modbus_state.coils.lock() else {
+        return;
+    };
+
+    for mut score in scores.iter_mut() {
+        if coils.get(&score.reset_coil_address) == Some(&true) {
+            coils.insert(score.reset_coil_address, false);
+            score.hits = 0;
+            score.misses = 0;
+            info!("Drop score {} reset", score.hits_input_address);
+        }
+    }
+}
+
+pub fn sync_drop_scores_to_modbus(
+    scores: Query<&DropScore, Changed<DropScore>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for score in scores.iter() {
+            inputs.insert(score.hits_input_address, score.hits);
+            inputs.insert(score.misses_input_address, score.misses);
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Hit and miss counts of liquid dropped into moving bottles.
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_drop_scores,
+                sync_modbus_to_drop_scores,
+                score_falling_balls,
+                sync_drop_scores_to_modbus,
+            )
+                .chain(),
+        );
+    }
+}
@@ -2,31 +2,188 @@
 // Copyright (C) 2025 deciphr
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::any::TypeId;
+use std::{
+    any::TypeId,
+    collections::{HashMap, HashSet},
+};
 
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use super::{
-    modbus::ModbusState,
-    bottle::Bottle,
+    alarm::{Alarm, AlarmEvent},
+    modbus::{ModbusState, RegisterBanks},
+    bottle::{Bottle, BottleSpec},
+    profiler::{SENSOR_FEEDBACK, profiled},
     conveyor::ConveyorState,
+    hopper::Cap,
+    simulation::{SimRng, SimTime},
+    station::StationId,
     valve::{Ball, ValveState},
+    variability::VariabilityConfig,
 };
 
+// >>> Constants <<<
+const MISS_INJECTION_COIL: u16 = 0x0005;
+const SENSOR_SIZE: f32 = 20.0; // Default detection box
+const SEGMENT_THICKNESS: f32 = 2.0; // Drawn width of segment sensors
+pub const SENSOR_GROUP: Group = Group::GROUP_32;
+pub const SCENERY_GROUP: Group = Group::GROUP_31; // Colliders no sensor detects
+const MAX_DETECTABLE_TYPES: usize = 30; // Groups left for item types
+
 // >>> Components <<<
 #[derive(Component)]
 pub struct Sensor {
     pub modbus_address: u16,
     pub sensor_tag: String,
     pub sensor_item: TypeId, // Item the sensor should detect
+    pub miss_rate: f32,      // Chance of an item passing undetected while miss injection is on
+    pub variant: Option<String>, // Only detect bottles of this variant
+    pub output_mode: SensorOutputMode,
+    pub polarity: SensorPolarity,
+    pub distance: Option<DistanceOutput>, // Analog output besides the trip
+}
+
+impl Sensor {
+    pub fn accepts_variant(&self, spec: &BottleSpec) -> bool {
+        self.variant.as_ref().is_none_or(|variant| *variant == spec.name)
+    }
+
+    /// Whether the sensor detects an item of the given component type, and
+    /// bottle variant for bottles.
+    pub fn detects(&self, item: TypeId, spec: Option<&BottleSpec>) -> bool {
+        item == self.sensor_item && spec.is_none_or(|spec| self.accepts_variant(spec))
+    }
+}
+
+/// Analog output measuring the distance from a sensor to the nearest item it
+/// detects along its axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceOutput {
+    pub input_address: u16,
+    pub range: f32,      // Reported while nothing is in range
+    pub direction: Vec2, // Axis the sensor looks along
+    pub distance: u16,   // Pixels
+}
+
+/// Detection area of a sensor around its position, before its rotation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SensorShape {
+    /// Rectangle of the given width and height.
+    Cuboid { size: [f32; 2] },
+    /// Circle, drawn as its bounding square.
+    Circle { radius: f32 },
+    /// Line of the given length along the sensor's x axis, e.g. a light beam.
+    Segment { length: f32 },
+}
+
+impl Default for SensorShape {
+    fn default() -> Self {
+        SensorShape::Cuboid {
+            size: [SENSOR_SIZE, SENSOR_SIZE],
+        }
+    }
+}
+
+impl SensorShape {
+    pub fn collider(&self) -> Collider {
+        match *self {
+            SensorShape::Cuboid { size } => Collider::cuboid(size[0] / 2.0, size[1] / 2.0),
+            SensorShape::Circle { radius } => Collider::ball(radius),
+            SensorShape::Segment { length } => {
+                let end = Vec2::new(length / 2.0, 0.0);
+                Collider::segment(-end, end)
+            }
+        }
+    }
+
+    /// Size of the sprite marking the sensor.
+    pub fn sprite_size(&self) -> Vec2 {
+        match *self {
+            SensorShape::Cuboid { size } => Vec2::from(size),
+            SensorShape::Circle { radius } => Vec2::splat(radius * 2.0),
+            SensorShape::Segment { length } => Vec2::new(length, SEGMENT_THICKNESS),
+        }
+    }
+}
+
+/// How a sensor's output follows the presence of an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SensorOutputMode {
+    /// On while an item is present.
+    #[default]
+    Momentary,
+    /// On once an item has been present for `ms`.
+    OnDelay { ms: u32 },
+    /// On while an item is present and for `ms` after it leaves.
+    OffDelay { ms: u32 },
+    /// On for `ms` each time an item arrives.
+    Pulse { ms: u32 },
+    /// On when an item arrives, until `reset_coil` is written.
+    Latched { reset_coil: u16 },
+}
+
+impl SensorOutputMode {
+    /// Output for the sensor's detection history.
+    pub fn output(&self, state: &SensorState) -> bool {
+        let elapsed = |ms: u32| ms as f32 / 1000.0;
+        match *self {
+            SensorOutputMode::Momentary => state.is_tripped(),
+            SensorOutputMode::OnDelay { ms } => state.detected && state.since_rise >= elapsed(ms),
+            SensorOutputMode::OffDelay { ms } => {
+                state.is_tripped() || state.since_fall < elapsed(ms)
+            }
+            SensorOutputMode::Pulse { ms } => (0.0..elapsed(ms)).contains(&state.since_rise),
+            SensorOutputMode::Latched { .. } => state.latched && state.since_rise >= 0.0,
+        }
+    }
+}
+
+/// How a sensor's contact is wired to its discrete input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorPolarity {
+    /// The input is on while the sensor is tripped.
+    #[default]
+    NormallyOpen,
+    /// The input is on while the sensor is clear, so a broken wire reads as tripped.
+    NormallyClosed,
+}
+
+impl SensorPolarity {
+    /// Converts between the sensor's output and the level on its wire. The
+    /// conversion is its own inverse.
+    pub fn wire(&self, value: bool) -> bool {
+        match self {
+            SensorPolarity::NormallyOpen => value,
+            SensorPolarity::NormallyClosed => !value,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct SensorState {
-    pub triggered: bool,
+    pub triggered: bool, // Output, as reported over Modbus
     pub last_triggered: bool,
     pub changed: bool,
+    pub detected: bool, // Whether an item is present
+    pub since_rise: f32, // Seconds since an item was last detected, negative while the trip lags
+    pub since_fall: f32, // Seconds since an item last left
+    pub latched: bool,
+    pub missed_items: HashSet<Entity>,
+    pub modbus_address: u16, // The sensor's discrete input, published to without a lookup
+    pub polarity: SensorPolarity,
+}
+
+impl SensorState {
+    /// Whether an item is present and its trip has come through.
+    pub fn is_tripped(&self) -> bool {
+        self.detected && self.since_rise >= 0.0
+    }
 }
 
 impl Default for SensorState {
@@ -35,22 +192,147 @@ impl Default for SensorState {
             triggered: false,
             last_triggered: false,
             changed: false,
+            detected: false,
+            since_rise: f32::INFINITY,
+            since_fall: f32::INFINITY,
+            latched: false,
+            missed_items: HashSet::new(),
+            modbus_address: 0,
+            polarity: SensorPolarity::default(),
+        }
+    }
+}
+
+/// Failure mode forced onto a sensor's output regardless of what it detects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultKind {
+    StuckOn,
+    StuckOff,
+    /// Broken wire, the input reads 0 whatever the sensor's polarity.
+    WireBreak,
+    /// Shorted wire, the input reads 1 whatever the sensor's polarity.
+    Short,
+}
+
+impl FaultKind {
+    /// Output a sensor with this fault reports, before its polarity is applied.
+    pub fn value(&self, polarity: SensorPolarity) -> bool {
+        match self {
+            FaultKind::StuckOn => true,
+            FaultKind::StuckOff => false,
+            FaultKind::WireBreak => polarity.wire(false),
+            FaultKind::Short => polarity.wire(true),
         }
     }
 }
 
 // >>> Resources <<<
+/// Faults injected into sensors, keyed by sensor tag.
+#[derive(Resource, Default)]
+pub struct SensorFaults {
+    faults: HashMap<String, FaultKind>,
+}
+
+impl SensorFaults {
+    pub fn inject(&mut self, sensor_tag: &str, kind: FaultKind) {
+        self.faults.insert(sensor_tag.to_string(), kind);
+    }
+
+    /// # Return
+    /// Whether the sensor had a fault
+    pub fn clear(&mut self, sensor_tag: &str) -> bool {
+        self.faults.remove(sensor_tag).is_some()
+    }
+
+    pub fn clear_all(&mut self) {
+        self.faults.clear();
+    }
+
+    pub fn get(&self, sensor_tag: &str) -> Option<FaultKind> {
+        self.faults.get(sensor_tag).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faults.is_empty()
+    }
+
+    /// Sensor output after applying any fault, before its polarity is applied.
+    pub fn apply(&self, sensor_tag: &str, polarity: SensorPolarity, triggered: bool) -> bool {
+        self.get(sensor_tag)
+            .map_or(triggered, |kind| kind.value(polarity))
+    }
+}
+
+/// Collision group of each item type sensors can detect. A sensor only
+/// interacts with the group of its item, so Rapier filters what it sees.
+#[derive(Resource, Default)]
+pub struct DetectableGroups {
+    groups: HashMap<TypeId, Group>,
+}
+
+impl DetectableGroups {
+    /// Group of the item type, empty if it was never registered.
+    pub fn group(&self, item: TypeId) -> Group {
+        self.groups.get(&item).copied().unwrap_or(Group::NONE)
+    }
+
+    /// # Return
+    /// The item type's new group, or `None` if it already has one or every
+    /// group is taken
+    fn register(&mut self, item: TypeId) -> Option<Group> {
+        if self.groups.contains_key(&item) {
+            return None;
+        }
+        if self.groups.len() >= MAX_DETECTABLE_TYPES {
+            warn!("No collision group left for another detectable item type");
+            return None;
+        }
+
+        let group = Group::from_bits_truncate(1 << self.groups.len());
+        self.groups.insert(item, group);
+        Some(group)
+    }
+}
+
+#[derive(Resource)]
+pub struct MissInjection {
+    pub enabled: bool,
+    pub coil_address: u16,
+}
+
+impl Default for MissInjection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            coil_address: MISS_INJECTION_COIL,
+        }
+    }
+}
 
 #[derive(Resource, Default)]
 pub struct GlobalSensorState {
     states: std::collections::HashMap<String, SensorState>,
+    entities: HashMap<Entity, String>, // Tag of each sensor entity, to forget it once despawned
 }
 
 impl GlobalSensorState {
-    pub fn add_sensor(&mut self, sensor_tag: String, state: SensorState) {
+    pub fn add_sensor(&mut self, entity: Entity, sensor_tag: String, state: SensorState) {
+        self.entities.insert(entity, sensor_tag.clone());
         self.states.insert(sensor_tag, state);
     }
 
+    /// Forgets a despawned sensor, unless another sensor has taken its tag
+    /// since, e.g. after reloading the scenario.
+    pub fn remove_sensor(&mut self, entity: Entity) {
+        let Some(sensor_tag) = self.entities.remove(&entity) else {
+            return;
+        };
+        if !self.entities.values().any(|tag| *tag == sensor_tag) {
+            self.states.remove(&sensor_tag);
+        }
+    }
+
     pub fn _get_state(&self, sensor_tag: &str) -> Option<&SensorState> {
         self.states.get(sensor_tag)
     }
@@ -66,6 +348,33 @@ impl GlobalSensorState {
             .map_or(false, |state| state.triggered)
     }
 
+    /// Records an item arriving at or leaving the sensor.
+    ///
+    /// # Return
+    /// Whether the sensor went from clear to detecting
+    pub fn set_detected(&mut self, sensor_tag: &str, detected: bool) -> bool {
+        let Some(state) = self.get_state_mut(sensor_tag) else {
+            return false;
+        };
+
+        let is_rise = detected && !state.detected;
+        if is_rise {
+            state.since_rise = 0.0;
+            state.latched = true;
+        } else if !detected && state.detected {
+            state.since_fall = 0.0;
+        }
+        state.detected = detected;
+        is_rise
+    }
+
+    /// Holds back the trip of the item just detected by `seconds`.
+    pub fn delay_trip(&mut self, sensor_tag: &str, seconds: f32) {
+        if let Some(state) = self.get_state_mut(sensor_tag) {
+            state.since_rise = -seconds;
+        }
+    }
+
     pub fn set_triggered(&mut self, sensor_tag: &str, triggered: bool) {
         if let Some(state) = self.get_state_mut(sensor_tag) {
             state.last_triggered = state.triggered;
@@ -87,6 +396,28 @@ impl GlobalSensorState {
         }
     }
 
+    /// Marks every sensor changed, so its output is written to Modbus again,
+    /// e.g. over a released force.
+    pub fn republish_all(&mut self) {
+        for state in self.states.values_mut() {
+            state.changed = true;
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut SensorState)> {
+        self.states
+            .iter_mut()
+            .map(|(tag, state)| (tag.as_str(), state))
+    }
+
+    /// Polarity of the sensor with the given tag.
+    pub fn polarity(&self, sensor_tag: &str) -> SensorPolarity {
+        self.states
+            .get(sensor_tag)
+            .map(|state| state.polarity)
+            .unwrap_or_default()
+    }
+
     // Get all sensors that have changed
     pub fn get_changed_sensors(&self) -> Vec<String> {
         self.states
@@ -95,6 +426,31 @@ impl GlobalSensorState {
             .map(|(tag, _)| tag.clone())
             .collect()
     }
+
+    /// Decides whether an item passes the sensor undetected. Missed items are
+    /// remembered so their exit from the sensor is ignored as well.
+    pub fn filter_missed(
+        &mut self,
+        sensor_tag: &str,
+        item: Entity,
+        is_started: bool,
+        miss_rate: f32,
+        rng: &mut SimRng,
+    ) -> bool {
+        let Some(state) = self.get_state_mut(sensor_tag) else {
+            return false;
+        };
+
+        if is_started {
+            if miss_rate > 0.0 && rng.random::<f32>() < miss_rate {
+                state.missed_items.insert(item);
+                return true;
+            }
+            false
+        } else {
+            state.missed_items.remove(&item)
+        }
+    }
 }
 
 // >>> Bundles <<<
@@ -103,6 +459,7 @@ pub struct SensorBundle {
     sensor: Sensor,
     collider: Collider,
     collider_sensor: bevy_rapier2d::geometry::Sensor,
+    collision_groups: CollisionGroups,
     active_events: ActiveEvents,
     sprite: Sprite,
     transform: Transform,
@@ -117,6 +474,8 @@ impl Sensor {
     /// * `sensor_item` - TypeId of the component the sensor should detect
     /// * `position` - Position of the sensor
     /// * `color` - Color of the sensor
+    /// * `shape` - Detection area of the sensor
+    /// * `rotation` - Counter-clockwise rotation of the sensor in degrees
     ///
     /// # Return
     /// The sensor bundle
@@ -126,38 +485,150 @@ impl Sensor {
         sensor_item: TypeId,
         position: Vec2,
         color: Color,
+        shape: SensorShape,
+        rotation: f32,
     ) -> SensorBundle {
         SensorBundle {
             sensor: Sensor {
                 sensor_tag,
                 modbus_address,
                 sensor_item,
+                miss_rate: 0.0,
+                variant: None,
+                output_mode: SensorOutputMode::default(),
+                polarity: SensorPolarity::default(),
+                distance: None,
             },
-            collider: Collider::cuboid(10.0, 10.0),
+            collider: shape.collider(),
             collider_sensor: bevy_rapier2d::geometry::Sensor,
-            sprite: Sprite::from_color(color, Vec2::new(20.0, 20.0)),
+            // Detects nothing until its item's group is known
+            collision_groups: CollisionGroups::new(SENSOR_GROUP, Group::NONE),
+            sprite: Sprite::from_color(color, shape.sprite_size()),
             active_events: ActiveEvents::COLLISION_EVENTS,
-            transform: Transform::from_translation(position.extend(0.0)),
+            transform: Transform::from_translation(position.extend(0.0))
+                .with_rotation(Quat::from_rotation_z(rotation.to_radians())),
+        }
+    }
+}
+
+impl SensorBundle {
+    /// Sets the chance of an item passing undetected while miss injection is enabled.
+    pub fn with_miss_rate(mut self, miss_rate: f32) -> Self {
+        self.sensor.miss_rate = miss_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Restricts the sensor to bottles of the named variant.
+    pub fn with_variant(mut self, variant: &str) -> Self {
+        self.sensor.variant = Some(variant.to_string());
+        self
+    }
+
+    /// Sets how the sensor's output follows detected items.
+    pub fn with_output_mode(mut self, output_mode: SensorOutputMode) -> Self {
+        self.sensor.output_mode = output_mode;
+        self
+    }
+
+    /// Sets how the sensor is wired to its discrete input.
+    pub fn with_polarity(mut self, polarity: SensorPolarity) -> Self {
+        self.sensor.polarity = polarity;
+        self
+    }
+
+    /// Adds an input register reporting the distance to the nearest detected
+    /// item along `direction`, up to `range`.
+    pub fn with_distance(mut self, input_address: u16, range: f32, direction: Vec2) -> Self {
+        self.sensor.distance = Some(DistanceOutput {
+            input_address,
+            range,
+            direction: direction.normalize_or(Vec2::NEG_X),
+            distance: range.round() as u16,
+        });
+        self
+    }
+}
+
+/// Puts new colliders into the scenery group, which sensors ignore. Items
+/// join their own groups in [`assign_item_group`].
+pub fn assign_scenery_groups(
+    mut commands: Commands,
+    colliders: Query<Entity, (Added<Collider>, Without<CollisionGroups>)>,
+) {
+    for entity in colliders.iter() {
+        commands
+            .entity(entity)
+            .insert(CollisionGroups::new(SCENERY_GROUP, Group::ALL));
+    }
+}
+
+/// Moves new items of type `T` into the type's group. Pooled items rejoin
+/// it each time they are put back into play.
+pub fn assign_item_group<T: Component>(
+    mut commands: Commands,
+    mut items: Query<(Entity, Option<&mut CollisionGroups>), Added<T>>,
+    detectable: Res<DetectableGroups>,
+) {
+    let group = detectable.group(TypeId::of::<T>());
+    for (entity, groups) in items.iter_mut() {
+        match groups {
+            Some(mut groups) => {
+                groups.memberships.remove(SCENERY_GROUP);
+                groups.memberships.insert(group);
+            }
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(CollisionGroups::new(group, Group::ALL));
+            }
         }
     }
 }
 
+/// Lets each new sensor interact with the group of the item it detects.
+pub fn assign_sensor_groups(
+    mut sensors: Query<(&Sensor, &mut CollisionGroups), Added<Sensor>>,
+    detectable: Res<DetectableGroups>,
+) {
+    for (sensor, mut groups) in sensors.iter_mut() {
+        groups.filters = detectable.group(sensor.sensor_item);
+    }
+}
+
 pub fn register_sensors(
-    query: Query<&Sensor, Added<Sensor>>,
+    query: Query<(Entity, &Sensor), Added<Sensor>>,
     mut global_state: ResMut<GlobalSensorState>,
     modbus_state: Res<ModbusState>,
 ) {
-    for sensor in query.iter() {
-        let initial_state = SensorState::default();
-        global_state.add_sensor(sensor.sensor_tag.clone(), initial_state.clone());
+    for (entity, sensor) in query.iter() {
+        let initial_state = SensorState {
+            modbus_address: sensor.modbus_address,
+            polarity: sensor.polarity,
+            ..default()
+        };
+        let sensor_tag = sensor.sensor_tag.clone();
+        global_state.add_sensor(entity, sensor_tag, initial_state.clone());
+
+        if let SensorOutputMode::Latched { reset_coil } = sensor.output_mode {
+            if let Ok(mut coils) = modbus_state.coils.lock() {
+                coils.insert(reset_coil, false);
+            }
+        }
+
+        if let Some(output) = &sensor.distance {
+            if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+                inputs.insert(output.input_address, output.distance);
+            }
+        }
 
         if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
-            discretes.insert(sensor.modbus_address, initial_state.triggered);
+            let initial_value = sensor.polarity.wire(initial_state.triggered);
+            discretes.insert(sensor.modbus_address, initial_value);
 
             #[cfg(debug_assertions)]
             info!(
                 "Initialized {}'s Modbus\n\tAddress: {:x?}\n\tInitial Value: {}",
-                sensor.sensor_tag, sensor.modbus_address, initial_state.triggered
+                sensor.sensor_tag, sensor.modbus_address, initial_value
             );
         } else {
             warn!(
@@ -170,108 +641,353 @@ pub fn register_sensors(
     }
 }
 
+/// Forgets despawned sensors, so their outputs are no longer published.
+pub fn unregister_sensors(
+    mut removed: RemovedComponents<Sensor>,
+    mut global_state: ResMut<GlobalSensorState>,
+) {
+    for entity in removed.read() {
+        global_state.remove_sensor(entity);
+    }
+}
+
+/// Drives the conveyors and valves of the station a sensor belongs to.
+fn set_station_actuators(
+    conveyors: &mut Query<(&mut ConveyorState, &StationId)>,
+    valves: &mut Query<(&mut ValveState, &StationId)>,
+    station: StationId,
+    is_running: bool,
+    is_open: bool,
+) {
+    for (mut conveyor_state, _) in conveyors.iter_mut().filter(|(_, id)| **id == station) {
+        conveyor_state.is_running = is_running;
+    }
+    for (mut valve_state, _) in valves.iter_mut().filter(|(_, id)| **id == station) {
+        valve_state.is_open = is_open;
+    }
+}
+
 pub fn handle_sensor_feedback_prefiltered(
     mut collision_events: EventReader<CollisionEvent>,
 
-    sensor_query: Query<&Sensor>,
-    bottle_query: Query<(), With<Bottle>>,
-    ball_query: Query<Entity, With<Ball>>,
+    sensor_query: Query<(&Sensor, &StationId)>,
+    specs: Query<&BottleSpec>,
+    mut conveyors: Query<(&mut ConveyorState, &StationId)>,
+    mut valves: Query<(&mut ValveState, &StationId)>,
 
     mut global_state: ResMut<GlobalSensorState>,
-    mut conveyor_state: ResMut<ConveyorState>,
-    mut valve_state: ResMut<ValveState>,
+    miss_injection: Res<MissInjection>,
+    variability: Res<VariabilityConfig>,
+    mut rng: ResMut<SimRng>,
 ) {
-    let bottle_type_id = TypeId::of::<Bottle>();
-    let ball_type_id = TypeId::of::<Ball>();
-
     for collision_event in collision_events.read() {
-        let (sensor_entity, other_entity, is_started) = match collision_event {
-            CollisionEvent::Started(e1, e2, _) => {
-                if sensor_query.contains(*e1) {
-                    (*e1, *e2, true)
-                } else if sensor_query.contains(*e2) {
-                    (*e2, *e1, true)
-                } else {
-                    continue;
-                }
-            }
-            CollisionEvent::Stopped(e1, e2, _) => {
-                if sensor_query.contains(*e1) {
-                    (*e1, *e2, false)
-                } else if sensor_query.contains(*e2) {
-                    (*e2, *e1, false)
-                } else {
-                    continue;
-                }
-            }
+        let (e1, e2, is_started) = match collision_event {
+            CollisionEvent::Started(e1, e2, _) => (*e1, *e2, true),
+            CollisionEvent::Stopped(e1, e2, _) => (*e1, *e2, false),
+        };
+
+        // Collision groups only let a sensor meet items of the type it detects
+        let Some(((sensor, station), other_entity)) = sensor_query
+            .get(e1)
+            .map(|sensor| (sensor, e2))
+            .or_else(|_| sensor_query.get(e2).map(|sensor| (sensor, e1)))
+            .ok()
+        else {
+            continue;
+        };
+        if specs
+            .get(other_entity)
+            .is_ok_and(|spec| !sensor.accepts_variant(spec))
+        {
+            continue;
+        }
+        let item = sensor.sensor_item;
+
+        let miss_rate = if miss_injection.enabled {
+            sensor.miss_rate
+        } else {
+            0.0
         };
 
-        // Handle sensor logic
-        let sensor = sensor_query.get(sensor_entity).unwrap();
+        if global_state.filter_missed(
+            &sensor.sensor_tag,
+            other_entity,
+            is_started,
+            miss_rate,
+            &mut rng,
+        ) {
+            if is_started {
+                info!("Sensor {} missed an item!", sensor.sensor_tag);
+            }
+            continue;
+        }
+
+        let is_rise = global_state.set_detected(&sensor.sensor_tag, is_started);
+        if is_rise && variability.sensor_delay_jitter > 0.0 {
+            global_state.delay_trip(&sensor.sensor_tag, variability.sensor_delay(&mut rng));
+        }
 
-        if sensor.sensor_item == bottle_type_id && bottle_query.contains(other_entity) {
-            global_state.set_triggered(&sensor.sensor_tag, is_started);
+        if item == TypeId::of::<Bottle>() {
             if is_started {
                 info!("Sensor {} triggered by bottle!", sensor.sensor_tag);
-                conveyor_state.is_running = false;
-                valve_state.is_open = true;
+                set_station_actuators(&mut conveyors, &mut valves, *station, false, true);
             } else {
                 info!(
                     "Sensor {} no longer triggered by bottle!",
                     sensor.sensor_tag
                 );
             }
-        } else if sensor.sensor_item == ball_type_id && ball_query.contains(other_entity) {
-            global_state.set_triggered(&sensor.sensor_tag, is_started);
+        } else if item == TypeId::of::<Ball>() {
             if is_started {
                 info!("Sensor {} triggered by ball!", sensor.sensor_tag);
-                valve_state.is_open = false;
-                conveyor_state.is_running = true;
+                set_station_actuators(&mut conveyors, &mut valves, *station, true, false);
             } else {
                 info!("Sensor {} no longer triggered by ball!", sensor.sensor_tag);
             }
+        } else if is_started {
+            info!("Sensor {} triggered by cap!", sensor.sensor_tag);
+        } else {
+            info!("Sensor {} no longer triggered by cap!", sensor.sensor_tag);
+        }
+    }
+}
+
+/// Advances sensor timers and derives each output from its mode.
+pub fn update_sensor_outputs(
+    time: Res<SimTime>,
+    sensors: Query<&Sensor>,
+    mut global_state: ResMut<GlobalSensorState>,
+) {
+    let delta = time.delta_secs();
+
+    for sensor in sensors.iter() {
+        let Some(state) = global_state.get_state_mut(&sensor.sensor_tag) else {
+            continue;
+        };
+        state.since_rise += delta;
+        state.since_fall += delta;
+
+        let triggered = sensor.output_mode.output(state);
+        if triggered != state.triggered {
+            global_state.set_triggered(&sensor.sensor_tag, triggered);
+        }
+    }
+}
+
+/// Casts a ray along each distance sensor's axis to the nearest item it
+/// detects. Its collision groups see through everything else in the way.
+pub fn measure_sensor_distances(
+    rapier_context: ReadRapierContext,
+    mut sensors: Query<(&mut Sensor, &CollisionGroups, &GlobalTransform)>,
+    specs: Query<&BottleSpec>,
+) {
+    let Ok(context) = rapier_context.single() else {
+        return;
+    };
+
+    for (mut sensor, groups, transform) in sensors.iter_mut() {
+        let Some(output) = &sensor.distance else {
+            continue;
+        };
+
+        let origin = transform.translation().truncate();
+        let direction = (transform.rotation() * output.direction.extend(0.0)).truncate();
+        let is_accepted = |entity| {
+            specs
+                .get(entity)
+                .map_or(true, |spec| sensor.accepts_variant(spec))
+        };
+        let filter = QueryFilter::new()
+            .exclude_sensors()
+            .groups(*groups)
+            .predicate(&is_accepted);
+        let distance = context
+            .cast_ray(origin, direction, output.range, true, filter)
+            .map_or(output.range, |(_, distance)| distance)
+            .round() as u16;
+
+        if distance != output.distance {
+            if let Some(output) = sensor.distance.as_mut() {
+                output.distance = distance;
+            }
         }
     }
 }
 
 // >>> Modbus Synchronization <<<
-pub fn sync_sensors_to_modbus(
+/// Releases latched sensors whose reset coil was written; the coil resets itself.
+pub fn sync_modbus_to_sensor_latches(
     sensors: Query<&Sensor>,
     mut global_state: ResMut<GlobalSensorState>,
     modbus_state: Res<ModbusState>,
 ) {
-    let changed_sensors = global_state.get_changed_sensors();
+    let Ok(mut coils) = modbus_state.coils.lock() else {
+        return;
+    };
 
-    if !changed_sensors.is_empty() {
-        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
-            for sensor_tag in &changed_sensors {
-                if let Some(sensor) = sensors.iter().find(|s| s.sensor_tag == *sensor_tag) {
-                    let is_triggered = global_state.is_triggered(sensor_tag);
-                    discretes.insert(sensor.modbus_address, is_triggered);
+    for sensor in sensors.iter() {
+        let SensorOutputMode::Latched { reset_coil } = sensor.output_mode else {
+            continue;
+        };
+
+        if coils.get(&reset_coil) == Some(&true) {
+            coils.insert(reset_coil, false);
+            if let Some(state) = global_state.get_state_mut(&sensor.sensor_tag) {
+                state.latched = false;
+            }
+            info!("Sensor {} latch reset", sensor.sensor_tag);
+        }
+    }
+}
+
+/// Publishes the sensors changed since the last sync, or every sensor once a
+/// fault is injected or cleared, as part of the tag-sync stage.
+pub fn write_sensor_inputs(
+    banks: &mut RegisterBanks,
+    global_state: &mut GlobalSensorState,
+    faults: &Res<SensorFaults>,
+) {
+    // Injecting or clearing a fault changes what every sensor reports
+    let is_republished = faults.is_changed();
+
+    for (sensor_tag, state) in global_state.iter_mut() {
+        if !state.changed && !is_republished {
+            continue;
+        }
+
+        let is_triggered = faults.apply(sensor_tag, state.polarity, state.triggered);
+        let value = state.polarity.wire(is_triggered);
+        banks.discrete_inputs.insert(state.modbus_address, value);
+
+        // Clear the changed flag after processing
+        state.changed = false;
+
+        info!("Updated {}'s Modbus state to: {}", sensor_tag, value)
+    }
+}
+
+pub fn sync_sensor_distances_to_modbus(
+    sensors: Query<&Sensor, Changed<Sensor>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for sensor in sensors.iter() {
+            if let Some(output) = &sensor.distance {
+                inputs.insert(output.input_address, output.distance);
+            }
+        }
+    }
+}
 
-                    // Clear the changed flag after processing
-                    global_state.clear_changed(sensor_tag);
+pub fn monitor_sensor_faults(
+    faults: Res<SensorFaults>,
+    mut alarm_events: EventWriter<AlarmEvent>,
+) {
+    if !faults.is_changed() {
+        return;
+    }
+
+    if faults.is_empty() {
+        alarm_events.write(AlarmEvent::Clear(Alarm::SensorFault));
+    } else {
+        alarm_events.write(AlarmEvent::Raise(Alarm::SensorFault));
+    }
+}
+
+pub fn handle_miss_injection_input(
+    mut miss_injection: ResMut<MissInjection>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyM) {
+        miss_injection.enabled = !miss_injection.enabled;
+        info!("Sensor miss injection: {}", miss_injection.enabled);
+    }
+}
+
+pub fn sync_miss_injection_to_modbus(
+    miss_injection: Res<MissInjection>,
+    modbus_state: Res<ModbusState>,
+) {
+    if miss_injection.is_changed() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(miss_injection.coil_address, miss_injection.enabled);
+        }
+    }
+}
 
-                    info!("Updated {}'s Modbus state to: {}", sensor_tag, is_triggered)
-                }
+pub fn sync_modbus_to_miss_injection(
+    mut miss_injection: ResMut<MissInjection>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(coils) = modbus_state.coils.lock() {
+        if let Some(&coil_state) = coils.get(&miss_injection.coil_address) {
+            if miss_injection.enabled != coil_state {
+                miss_injection.enabled = coil_state;
+                info!("Sensor miss injection set to: {}", coil_state);
             }
         }
     }
 }
 
 // >>> Plugin <<<
+/// Registers a component as an item type sensors can detect, giving it a
+/// collision group of its own. The component must be on the item's collider.
+pub trait RegisterDetectable {
+    fn register_detectable<T: Component>(&mut self) -> &mut Self;
+}
+
+impl RegisterDetectable for App {
+    fn register_detectable<T: Component>(&mut self) -> &mut Self {
+        let registered = self
+            .world_mut()
+            .get_resource_or_init::<DetectableGroups>()
+            .register(TypeId::of::<T>());
+        if registered.is_some() {
+            self.add_systems(
+                FixedPostUpdate,
+                assign_item_group::<T>
+                    .after(assign_scenery_groups)
+                    .before(PhysicsSet::SyncBackend),
+            );
+        }
+        self
+    }
+}
+
 pub struct SensorPlugin;
 
 impl Plugin for SensorPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(GlobalSensorState::default())
+            .insert_resource(MissInjection::default())
+            .init_resource::<SensorFaults>()
+            .init_resource::<DetectableGroups>()
+            .register_detectable::<Bottle>()
+            .register_detectable::<Ball>()
+            .register_detectable::<Cap>()
+            .add_systems(Update, handle_miss_injection_input)
+            // Before Rapier sees the new colliders
+            .add_systems(
+                FixedPostUpdate,
+                (assign_scenery_groups, assign_sensor_groups).before(PhysicsSet::SyncBackend),
+            )
             .add_systems(
-                Update,
+                FixedUpdate,
                 (
                     register_sensors,
-                    handle_sensor_feedback_prefiltered,
-                    sync_sensors_to_modbus,
-                ),
+                    unregister_sensors,
+                    profiled(SENSOR_FEEDBACK, handle_sensor_feedback_prefiltered),
+                    sync_modbus_to_sensor_latches,
+                    update_sensor_outputs,
+                    measure_sensor_distances,
+                    sync_sensor_distances_to_modbus,
+                    monitor_sensor_faults,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                (sync_miss_injection_to_modbus, sync_modbus_to_miss_injection).chain(),
             );
     }
 }
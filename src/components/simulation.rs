@@ -0,0 +1,342 @@
+// simulation.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+
+use bevy::app::{RunFixedMainLoop, RunFixedMainLoopSystem};
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use rand::{SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    actuator::ValveActuator,
+    bottle::{Bottle, BottlePool, BottlePosition, BottleSpawner, BottleSpec, SerialCounter},
+    conveyor::{Conveyor, ConveyorState, write_conveyor_registers},
+    hopper::Cap,
+    modbus::ModbusState,
+    recipe::BatchState,
+    tags::TagRegistry,
+    valve::{
+        Ball, BallLimitPolicy, BallPool, BallSpawner, ProportionalValve, Valve, ValveState,
+        write_valve_registers,
+    },
+};
+
+// >>> Constants <<<
+pub const FIXED_TIMESTEP_HZ: f64 = 60.0;
+pub const DEFAULT_SEED: u64 = 0x4d6f_6475_5369_6d00;
+const MIN_SPEED: f32 = 0.125; // Slowest time scale of the simulation
+const MAX_SPEED: f32 = 8.0; // Fastest time scale of the simulation
+
+// >>> States <<<
+/// Lifecycle of the simulation. The plant only ticks, i.e. `FixedUpdate`
+/// systems and the simulated clock only run, while it is `Running`.
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SimState {
+    /// Spawning the plant, at start-up or while a scenario is loaded
+    #[default]
+    Loading,
+    Running,
+    Paused,
+    /// Stopped on an error until another scenario is loaded
+    Error,
+}
+
+// >>> Resources <<<
+/// Random source for every stochastic process in the plant.
+///
+/// Runs seeded with the same value replay identically given the same Modbus inputs.
+#[derive(Resource, Deref, DerefMut)]
+pub struct SimRng(StdRng);
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+/// Clock every timer in the plant runs on, advanced once per fixed timestep.
+///
+/// It stands still while the simulation is paused and runs faster or slower
+/// with its speed, whatever the render frame rate, so spawn intervals,
+/// sensor timers and machine cycles all keep simulated time. Read it from
+/// `FixedUpdate` systems.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SimTime {
+    delta: Duration,
+    elapsed: Duration,
+    ticks: u64,
+}
+
+impl SimTime {
+    /// Moves the clock on by one fixed timestep.
+    pub fn advance(&mut self, delta: Duration) {
+        self.delta = delta;
+        self.elapsed += delta;
+        self.ticks += 1;
+    }
+
+    /// Length of the current timestep.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_secs(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn delta_secs_f64(&self) -> f64 {
+        self.delta.as_secs_f64()
+    }
+
+    /// Simulated time since startup.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    pub fn elapsed_secs_f64(&self) -> f64 {
+        self.elapsed.as_secs_f64()
+    }
+
+    /// Fixed timesteps run since startup.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+}
+
+/// Region products may occupy and caps on how many may exist at once.
+///
+/// Items leaving the region are removed from the simulation.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldBounds {
+    pub min_x: f32,
+    pub max_x: f32,
+    pub min_y: f32,
+    pub max_balls: usize,
+    pub ball_policy: BallLimitPolicy, // What happens once `max_balls` is reached
+}
+
+impl Default for WorldBounds {
+    fn default() -> Self {
+        Self {
+            min_x: -700.0,
+            max_x: 700.0,
+            min_y: -500.0,
+            max_balls: 100,
+            ball_policy: BallLimitPolicy::default(),
+        }
+    }
+}
+
+impl WorldBounds {
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min_x && point.x <= self.max_x && point.y >= self.min_y
+    }
+}
+
+/// Message of the error that stopped the simulation, shown while it is in
+/// [`SimState::Error`].
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SimError(pub String);
+
+// >>> Events <<<
+/// Clears every product off the line and returns actuators, and the coils
+/// and holding registers commanding them, to their defaults.
+#[derive(Event)]
+pub struct ResetSimulation;
+
+/// Stops the simulation with an error, e.g. a scenario that failed to load.
+#[derive(Event)]
+pub struct SimulationError(pub String);
+
+// >>> Systems <<<
+pub fn advance_sim_time(fixed_time: Res<Time<Fixed>>, mut sim_time: ResMut<SimTime>) {
+    sim_time.advance(fixed_time.delta());
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn reset_simulation(
+    mut commands: Commands,
+    mut reset_events: EventReader<ResetSimulation>,
+    bottles: Query<(Entity, &BottleSpec, &BottlePosition), With<Bottle>>,
+    balls: Query<Entity, With<Ball>>,
+    caps: Query<Entity, With<Cap>>,
+    mut bottle_pool: ResMut<BottlePool>,
+    mut ball_pool: ResMut<BallPool>,
+    mut serial_counter: ResMut<SerialCounter>,
+    mut conveyors: Query<(&Conveyor, &mut ConveyorState, &mut BottleSpawner)>,
+    mut valves: Query<(
+        &Valve,
+        &mut ValveState,
+        &mut BallSpawner,
+        Option<&mut ValveActuator>,
+        Option<&ProportionalValve>,
+    )>,
+    mut batch_state: ResMut<BatchState>,
+    modbus_state: Res<ModbusState>,
+    tags: Res<TagRegistry>,
+) {
+    if reset_events.read().count() == 0 {
+        return;
+    }
+
+    for (entity, spec, BottlePosition(position)) in bottles.iter() {
+        bottle_pool.release(&mut commands, entity, spec, *position);
+    }
+    for entity in balls.iter() {
+        ball_pool.release(&mut commands, entity);
+    }
+    for entity in caps.iter() {
+        commands.entity(entity).despawn();
+    }
+    // Serials start over with the line, as a PLC's tracking does
+    *serial_counter = SerialCounter::default();
+
+    // The defaults go straight to the outputs too, or the old commands
+    // still in them would be read back into the devices on the next tick
+    let mut banks = modbus_state.lock_banks();
+    for (conveyor, mut conveyor_state, mut bottle_spawner) in conveyors.iter_mut() {
+        *conveyor_state = ConveyorState::default();
+        // The infeed profile comes from the scenario and outlives the reset
+        let profile = bottle_spawner.profile().clone();
+        *bottle_spawner = BottleSpawner::default();
+        bottle_spawner.set_profile(profile);
+        if let Some(banks) = banks.as_mut() {
+            write_conveyor_registers(banks, &tags, conveyor, &conveyor_state);
+        }
+    }
+    for (valve, mut valve_state, mut ball_spawner, actuator, proportional) in valves.iter_mut() {
+        *valve_state = ValveState::default();
+        *ball_spawner = BallSpawner::default();
+        if let Some(mut actuator) = actuator {
+            actuator.reset();
+        }
+        if let Some(banks) = banks.as_mut() {
+            write_valve_registers(banks, &tags, valve, &valve_state, proportional);
+        }
+    }
+    *batch_state = BatchState::default();
+
+    info!("Simulation reset");
+}
+
+/// Starts the plant once it has been spawned, unless something else, e.g.
+/// the start-up menu or an error, already asked for another state.
+pub fn finish_loading(mut next_state: ResMut<NextState<SimState>>) {
+    if matches!(*next_state, NextState::Unchanged) {
+        next_state.set(SimState::Running);
+    }
+}
+
+pub fn handle_simulation_errors(
+    mut commands: Commands,
+    mut error_events: EventReader<SimulationError>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    let Some(SimulationError(message)) = error_events.read().last() else {
+        return;
+    };
+
+    error!("Simulation stopped: {message}");
+    commands.insert_resource(SimError(message.clone()));
+    next_state.set(SimState::Error);
+}
+
+/// Holds virtual time still, so the simulation's speed and anything animated
+/// in `Update` stop with the plant.
+pub fn pause_simulation(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+    info!("Simulation paused");
+}
+
+pub fn resume_simulation(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+    info!("Simulation resumed");
+}
+
+// >>> Input System <<<
+/// Pauses and resumes the simulation, and halves or doubles its speed.
+pub fn handle_time_input(
+    mut time: ResMut<Time<Virtual>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    state: Res<State<SimState>>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Pause) {
+        match state.get() {
+            SimState::Running => next_state.set(SimState::Paused),
+            SimState::Paused => next_state.set(SimState::Running),
+            SimState::Loading | SimState::Error => {}
+        }
+    }
+
+    let speed = if keyboard.just_pressed(KeyCode::BracketLeft) {
+        time.relative_speed() / 2.0
+    } else if keyboard.just_pressed(KeyCode::BracketRight) {
+        time.relative_speed() * 2.0
+    } else {
+        return;
+    };
+    time.set_relative_speed(speed.clamp(MIN_SPEED, MAX_SPEED));
+    info!("Simulation speed: {}x", time.relative_speed());
+}
+
+// >>> Plugin <<<
+/// Runs the simulation on a fixed timestep with a seeded random source and a
+/// simulated clock that pauses and scales with virtual time. The fixed
+/// timestep only runs while the [`SimState`] is `Running`.
+pub struct SimulationPlugin {
+    pub seed: u64,
+}
+
+impl Default for SimulationPlugin {
+    fn default() -> Self {
+        Self { seed: DEFAULT_SEED }
+    }
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        info!("Simulation seed: {}", self.seed);
+
+        if !app.is_plugin_added::<StatesPlugin>() {
+            app.add_plugins(StatesPlugin);
+        }
+
+        app.insert_resource(Time::<Fixed>::from_hz(FIXED_TIMESTEP_HZ))
+            .insert_resource(SimRng::new(self.seed))
+            .init_resource::<SimTime>()
+            .init_resource::<WorldBounds>()
+            // Register tables exist even when the Modbus server is disabled
+            .init_resource::<ModbusState>()
+            .init_resource::<TagRegistry>()
+            .init_state::<SimState>()
+            .add_event::<ResetSimulation>()
+            .add_event::<SimulationError>()
+            // Every fixed-timestep schedule waits while loading, paused or stopped
+            .configure_sets(
+                RunFixedMainLoop,
+                RunFixedMainLoopSystem::FixedMainLoop.run_if(in_state(SimState::Running)),
+            )
+            .add_systems(PostStartup, finish_loading)
+            .add_systems(OnEnter(SimState::Paused), pause_simulation)
+            .add_systems(OnExit(SimState::Paused), resume_simulation)
+            .add_systems(FixedFirst, advance_sim_time)
+            .add_systems(
+                Update,
+                (
+                    handle_time_input,
+                    handle_simulation_errors,
+                    finish_loading.run_if(in_state(SimState::Loading)),
+                ),
+            )
+            .add_systems(FixedUpdate, reset_simulation);
+    }
+}
@@ -0,0 +1,464 @@
+// snapshot.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Context;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bottle::{Bottle, BottlePool, BottlePosition, BottleSpec, Capped, FillLevel, ProductSelection},
+    capper::attach_cap,
+    conveyor::{Conveyor, ConveyorState},
+    counter::Counter,
+    diverter::{Diverted, DiverterLane, Sorted},
+    hopper::{Cap, Hopper},
+    jam::Jammed,
+    labeler::{Label, LabelQuality, attach_label},
+    modbus::ModbusState,
+    outfeed::Outfeed,
+    station::StationId,
+    valve::{Ball, BallPool, Valve, ValveState, spawn_ball},
+};
+use crate::scenario::Scenario;
+
+// >>> Constants <<<
+const SNAPSHOT_PATH: &str = "snapshot.json";
+
+// >>> Snapshot <<<
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConveyorSnapshot {
+    pub is_running: bool,
+    pub speed: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValveSnapshot {
+    pub is_open: bool,
+    pub spawn_rate: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BottleSnapshot {
+    pub station: u16,
+    pub variant: String,
+    pub collider_offset: [f32; 2],
+    pub translation: [f32; 2],
+    pub rotation: f32, // Radians
+    pub linvel: [f32; 2],
+    pub angvel: f32,
+    pub fill_level: u32,
+    pub label: Option<LabelQuality>,
+    pub is_capped: bool,
+    pub is_diverted: bool,
+    pub is_sorted: bool,
+    pub is_jammed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BallSnapshot {
+    pub translation: [f32; 2],
+    pub linvel: [f32; 2],
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegisterSnapshot {
+    pub coils: BTreeMap<u16, bool>,
+    pub discrete_inputs: BTreeMap<u16, bool>,
+    pub input_registers: BTreeMap<u16, u16>,
+    pub holding_registers: BTreeMap<u16, u16>,
+}
+
+/// State of a running plant: its products, device states and register tables.
+///
+/// Devices are keyed by their first Modbus address, so a snapshot can only be
+/// restored onto the scenario it was taken from; restoring it onto another is
+/// refused.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub scenario: String,
+    pub conveyors: BTreeMap<u16, ConveyorSnapshot>, // By run coil
+    pub valves: BTreeMap<u16, ValveSnapshot>,       // By open coil
+    pub hoppers: BTreeMap<u16, u32>,                // Inventory by gate coil
+    pub counters: BTreeMap<u16, u16>,               // Lane and outfeed counts by input register
+    #[serde(default)]
+    pub totals: BTreeMap<u16, u32>, // Counter totals by first input register
+    pub bottles: Vec<BottleSnapshot>,
+    pub balls: Vec<BallSnapshot>,
+    pub registers: RegisterSnapshot,
+}
+
+impl Snapshot {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn to_file(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+// >>> Events <<<
+/// Writes the plant's state to a file.
+#[derive(Event)]
+pub struct SaveSnapshot(pub PathBuf);
+
+/// Replaces the plant's state with one previously saved.
+#[derive(Event)]
+pub struct RestoreSnapshot(pub PathBuf);
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct SnapshotSettings {
+    pub path: PathBuf, // Used by the keyboard shortcuts
+}
+
+// >>> Systems <<<
+pub fn handle_snapshot_input(
+    settings: Res<SnapshotSettings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut save_events: EventWriter<SaveSnapshot>,
+    mut restore_events: EventWriter<RestoreSnapshot>,
+) {
+    if keyboard.just_pressed(KeyCode::F5) {
+        save_events.write(SaveSnapshot(settings.path.clone()));
+    }
+
+    if keyboard.just_pressed(KeyCode::F9) {
+        restore_events.write(RestoreSnapshot(settings.path.clone()));
+    }
+}
+
+fn dump_table<V: Copy>(table: &Mutex<HashMap<u16, V>>) -> BTreeMap<u16, V> {
+    table
+        .lock()
+        .map(|table| table.iter().map(|(&address, &value)| (address, value)).collect())
+        .unwrap_or_default()
+}
+
+fn load_table<V: Copy>(table: &Mutex<HashMap<u16, V>>, values: &BTreeMap<u16, V>) {
+    if let Ok(mut table) = table.lock() {
+        *table = values.iter().map(|(&address, &value)| (address, value)).collect();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn save_snapshot(
+    mut save_events: EventReader<SaveSnapshot>,
+    scenario: Option<Res<Scenario>>,
+    modbus_state: Res<ModbusState>,
+    conveyors: Query<(&Conveyor, &ConveyorState)>,
+    valves: Query<(&Valve, &ValveState)>,
+    hoppers: Query<&Hopper>,
+    lanes: Query<&DiverterLane>,
+    outfeeds: Query<&Outfeed>,
+    totals: Query<&Counter>,
+    bottles: Query<
+        (
+            &Transform,
+            &Velocity,
+            &BottlePosition,
+            &BottleSpec,
+            &FillLevel,
+            &StationId,
+            Option<&Label>,
+            (Has<Capped>, Has<Diverted>, Has<Sorted>, Has<Jammed>),
+        ),
+        With<Bottle>,
+    >,
+    balls: Query<(&Transform, &Velocity), With<Ball>>,
+) {
+    for SaveSnapshot(path) in save_events.read() {
+        let snapshot = Snapshot {
+            scenario: scenario.as_ref().map_or_else(String::new, |scenario| scenario.name.clone()),
+            conveyors: conveyors
+                .iter()
+                .map(|(conveyor, state)| {
+                    (
+                        conveyor.coil_address,
+                        ConveyorSnapshot {
+                            is_running: state.is_running,
+                            speed: state.speed,
+                        },
+                    )
+                })
+                .collect(),
+            valves: valves
+                .iter()
+                .map(|(valve, state)| {
+                    (
+                        valve.coil_address,
+                        ValveSnapshot {
+                            is_open: state.is_open,
+                            spawn_rate: state.spawn_rate,
+                        },
+                    )
+                })
+                .collect(),
+            hoppers: hoppers
+                .iter()
+                .map(|hopper| (hopper.gate_coil_address, hopper.inventory))
+                .collect(),
+            counters: lanes
+                .iter()
+                .map(|lane| (lane.counter_input_address, lane.count))
+                .chain(outfeeds.iter().map(|outfeed| (outfeed.counter_input_address, outfeed.count)))
+                .collect(),
+            totals: totals
+                .iter()
+                .map(|counter| (counter.count_input_address, counter.count))
+                .collect(),
+            bottles: bottles
+                .iter()
+                .map(
+                    |(
+                        transform,
+                        velocity,
+                        BottlePosition(offset),
+                        spec,
+                        fill_level,
+                        station,
+                        label,
+                        (is_capped, is_diverted, is_sorted, is_jammed),
+                    )| {
+                        let (_, _, rotation) = transform.rotation.to_euler(EulerRot::XYZ);
+                        BottleSnapshot {
+                            station: station.0,
+                            variant: spec.name.clone(),
+                            collider_offset: offset.to_array(),
+                            translation: transform.translation.truncate().to_array(),
+                            rotation,
+                            linvel: velocity.linvel.to_array(),
+                            angvel: velocity.angvel,
+                            fill_level: fill_level.0,
+                            label: label.map(|label| label.quality),
+                            is_capped,
+                            is_diverted,
+                            is_sorted,
+                            is_jammed,
+                        }
+                    },
+                )
+                .collect(),
+            balls: balls
+                .iter()
+                .map(|(transform, velocity)| BallSnapshot {
+                    translation: transform.translation.truncate().to_array(),
+                    linvel: velocity.linvel.to_array(),
+                })
+                .collect(),
+            registers: RegisterSnapshot {
+                coils: dump_table(&modbus_state.coils),
+                discrete_inputs: dump_table(&modbus_state.discrete_inputs),
+                input_registers: dump_table(&modbus_state.input_registers),
+                holding_registers: dump_table(&modbus_state.holding_registers),
+            },
+        };
+
+        match snapshot.to_file(path) {
+            Ok(()) => info!(
+                "Saved snapshot to {} ({} bottles, {} balls)",
+                path.display(),
+                snapshot.bottles.len(),
+                snapshot.balls.len()
+            ),
+            Err(err) => warn!("Failed to save snapshot: {err:#}"),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn restore_snapshot(
+    mut commands: Commands,
+    mut restore_events: EventReader<RestoreSnapshot>,
+    scenario: Option<Res<Scenario>>,
+    modbus_state: Res<ModbusState>,
+    product_selection: Res<ProductSelection>,
+    mut conveyors: Query<(&Conveyor, &mut ConveyorState)>,
+    mut valves: Query<(&Valve, &mut ValveState)>,
+    mut hoppers: Query<&mut Hopper>,
+    mut lanes: Query<&mut DiverterLane>,
+    mut outfeeds: Query<&mut Outfeed>,
+    mut totals: Query<&mut Counter>,
+    bottles: Query<(Entity, &BottleSpec, &BottlePosition), With<Bottle>>,
+    balls: Query<Entity, With<Ball>>,
+    caps: Query<Entity, With<Cap>>,
+    mut bottle_pool: ResMut<BottlePool>,
+    mut ball_pool: ResMut<BallPool>,
+) {
+    let Some(RestoreSnapshot(path)) = restore_events.read().last() else {
+        return;
+    };
+
+    let snapshot = match Snapshot::from_file(path) {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            warn!("Failed to restore snapshot: {err:#}");
+            return;
+        }
+    };
+
+    // Devices are keyed by address, so another scenario's would land on the wrong ones
+    let running = scenario.map_or_else(String::new, |scenario| scenario.name.clone());
+    if running != snapshot.scenario {
+        warn!(
+            "Refusing to restore a snapshot of scenario {} onto {}",
+            snapshot.scenario, running
+        );
+        return;
+    }
+
+    // Clear the line
+    for (entity, spec, BottlePosition(position)) in bottles.iter() {
+        bottle_pool.release(&mut commands, entity, spec, *position);
+    }
+    for entity in balls.iter() {
+        ball_pool.release(&mut commands, entity);
+    }
+    for entity in caps.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    // Devices
+    for (conveyor, mut state) in conveyors.iter_mut() {
+        if let Some(saved) = snapshot.conveyors.get(&conveyor.coil_address) {
+            state.is_running = saved.is_running;
+            state.speed = saved.speed;
+        }
+    }
+    for (valve, mut state) in valves.iter_mut() {
+        if let Some(saved) = snapshot.valves.get(&valve.coil_address) {
+            state.is_open = saved.is_open;
+            state.spawn_rate = saved.spawn_rate;
+        }
+    }
+    for mut hopper in hoppers.iter_mut() {
+        if let Some(&inventory) = snapshot.hoppers.get(&hopper.gate_coil_address) {
+            hopper.inventory = inventory;
+        }
+    }
+    for mut lane in lanes.iter_mut() {
+        if let Some(&count) = snapshot.counters.get(&lane.counter_input_address) {
+            lane.count = count;
+        }
+    }
+    for mut outfeed in outfeeds.iter_mut() {
+        if let Some(&count) = snapshot.counters.get(&outfeed.counter_input_address) {
+            outfeed.count = count;
+        }
+    }
+    for mut counter in totals.iter_mut() {
+        if let Some(&count) = snapshot.totals.get(&counter.count_input_address) {
+            counter.count = count;
+        }
+    }
+
+    // Products
+    for saved in &snapshot.bottles {
+        let spec = product_selection
+            .catalog
+            .iter()
+            .find(|spec| spec.name == saved.variant)
+            .cloned()
+            .unwrap_or_else(|| {
+                warn!("Unknown bottle variant {} in snapshot", saved.variant);
+                BottleSpec::default()
+            });
+        let position = Vec2::from(saved.collider_offset);
+
+        let entity = bottle_pool.acquire(&mut commands, position, spec.clone());
+        let mut bottle = commands.entity(entity);
+        bottle.insert((
+            Transform::from_translation(Vec2::from(saved.translation).extend(0.0))
+                .with_rotation(Quat::from_rotation_z(saved.rotation)),
+            Velocity {
+                linvel: Vec2::from(saved.linvel),
+                angvel: saved.angvel,
+            },
+            FillLevel(saved.fill_level),
+            SolverGroups::new(Group::GROUP_1, Group::GROUP_2),
+            StationId(saved.station),
+        ));
+        if let Some(quality) = saved.label {
+            attach_label(&mut bottle, quality, position, &spec);
+        }
+        if saved.is_capped {
+            attach_cap(&mut bottle, position, &spec);
+        }
+        if saved.is_diverted {
+            bottle.insert(Diverted);
+        }
+        if saved.is_sorted {
+            bottle.insert(Sorted);
+        }
+        if saved.is_jammed {
+            bottle.insert(Jammed);
+        }
+    }
+
+    for saved in &snapshot.balls {
+        let entity = spawn_ball(&mut commands, &mut ball_pool, Vec2::from(saved.translation));
+        commands.entity(entity).insert(Velocity::linear(Vec2::from(saved.linvel)));
+    }
+
+    // Register tables last, so they match the restored devices
+    load_table(&modbus_state.coils, &snapshot.registers.coils);
+    load_table(&modbus_state.discrete_inputs, &snapshot.registers.discrete_inputs);
+    load_table(&modbus_state.input_registers, &snapshot.registers.input_registers);
+    load_table(&modbus_state.holding_registers, &snapshot.registers.holding_registers);
+
+    info!("Restored snapshot from {}", path.display());
+}
+
+pub fn restore_snapshot_on_start(
+    settings: Res<SnapshotSettings>,
+    mut restore_events: EventWriter<RestoreSnapshot>,
+) {
+    restore_events.write(RestoreSnapshot(settings.path.clone()));
+}
+
+// >>> Plugin <<<
+/// Saves the plant's state to a JSON file and restores it, so every trainee
+/// can start an exercise from identical conditions.
+///
+/// F5 saves to `path` and F9 restores from it. Random draws are not part of
+/// the snapshot and continue from the running generator.
+pub struct SnapshotPlugin {
+    pub path: PathBuf,
+    pub restore_on_start: bool,
+}
+
+impl Default for SnapshotPlugin {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from(SNAPSHOT_PATH),
+            restore_on_start: false,
+        }
+    }
+}
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SnapshotSettings {
+            path: self.path.clone(),
+        })
+        .add_event::<SaveSnapshot>()
+        .add_event::<RestoreSnapshot>()
+        .add_systems(Update, handle_snapshot_input)
+        .add_systems(FixedUpdate, (save_snapshot, restore_snapshot).chain());
+
+        if self.restore_on_start {
+            app.add_systems(PostStartup, restore_snapshot_on_start);
+        }
+    }
+}
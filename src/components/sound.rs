@@ -0,0 +1,265 @@
+// sound.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bevy::audio::{Pitch, Volume};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    alarm::{AlarmEvent, AlarmState, handle_alarm_events},
+    lighting::{LightingMode, LightingState},
+    modbus::ModbusState,
+    sensor::{GlobalSensorState, update_sensor_outputs},
+    tag_sync::sync_tags,
+    tags::TagKind,
+    valve::ValveState,
+};
+
+// >>> Constants <<<
+const DEFAULT_MUTE_COIL: u16 = 0x0010;
+const CUE_VOLUME: f32 = 0.3;
+
+// >>> Sounds <<<
+/// Events the plant makes a sound for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundCue {
+    ValveOpen,
+    ValveClose,
+    SensorTrip,
+    AlarmRaised,
+    EmergencyStop,
+}
+
+impl SoundCue {
+    /// Frequency in Hz and length of the tone played for the cue.
+    fn tone(&self) -> (f32, Duration) {
+        match self {
+            SoundCue::ValveOpen => (660.0, Duration::from_millis(80)),
+            SoundCue::ValveClose => (440.0, Duration::from_millis(80)),
+            SoundCue::SensorTrip => (1320.0, Duration::from_millis(20)),
+            SoundCue::AlarmRaised => (880.0, Duration::from_millis(400)),
+            SoundCue::EmergencyStop => (220.0, Duration::from_millis(900)),
+        }
+    }
+}
+
+// >>> Resources <<<
+/// Master mute, toggled with N or the mute coil.
+#[derive(Resource, Default)]
+pub struct SoundSettings {
+    pub is_muted: bool,
+}
+
+/// Sound settings of the scenario, from its `[sound]` table.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundConfig {
+    pub mute_coil: u16, // Registered as the `sound.mute` tag
+}
+
+impl Default for SoundConfig {
+    fn default() -> Self {
+        Self {
+            mute_coil: DEFAULT_MUTE_COIL,
+        }
+    }
+}
+
+/// Generated tones for each cue, so no audio files need shipping.
+#[derive(Resource)]
+pub struct SoundCues {
+    tones: HashMap<SoundCue, Handle<Pitch>>,
+}
+
+impl SoundCues {
+    fn play(&self, commands: &mut Commands, settings: &SoundSettings, cue: SoundCue) {
+        if settings.is_muted {
+            return;
+        }
+
+        if let Some(tone) = self.tones.get(&cue) {
+            commands.spawn((
+                AudioPlayer(tone.clone()),
+                PlaybackSettings::DESPAWN.with_volume(Volume::Linear(CUE_VOLUME)),
+            ));
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn setup_sound_cues(mut commands: Commands, mut pitches: ResMut<Assets<Pitch>>) {
+    let tones = [
+        SoundCue::ValveOpen,
+        SoundCue::ValveClose,
+        SoundCue::SensorTrip,
+        SoundCue::AlarmRaised,
+        SoundCue::EmergencyStop,
+    ]
+    .into_iter()
+    .map(|cue| {
+        let (frequency, duration) = cue.tone();
+        (cue, pitches.add(Pitch::new(frequency, duration)))
+    })
+    .collect();
+    commands.insert_resource(SoundCues { tones });
+}
+
+/// Serves the mute coil at the scenario's address, dropping the one it
+/// replaces.
+pub fn register_mute_coil(
+    config: Res<SoundConfig>,
+    settings: Res<SoundSettings>,
+    modbus_state: Res<ModbusState>,
+    mut last_address: Local<Option<u16>>,
+) {
+    if let Some(address) = last_address.filter(|&address| address != config.mute_coil) {
+        modbus_state.remove(TagKind::Coil, address);
+    }
+    *last_address = Some(config.mute_coil);
+
+    if let Ok(mut coils) = modbus_state.coils.lock() {
+        coils.insert(config.mute_coil, settings.is_muted);
+    }
+}
+
+pub fn play_valve_sounds(
+    mut commands: Commands,
+    valves: Query<(Entity, &ValveState), Changed<ValveState>>,
+    mut last_open: Local<HashMap<Entity, bool>>,
+    cues: Res<SoundCues>,
+    settings: Res<SoundSettings>,
+) {
+    for (entity, valve_state) in valves.iter() {
+        // Only the spawn rate may have changed; newly spawned valves stay silent
+        let was_open = last_open.insert(entity, valve_state.is_open);
+        match was_open {
+            Some(false) if valve_state.is_open => {
+                cues.play(&mut commands, &settings, SoundCue::ValveOpen)
+            }
+            Some(true) if !valve_state.is_open => {
+                cues.play(&mut commands, &settings, SoundCue::ValveClose)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Clicks when any sensor output switched on this step. Runs before the
+/// sensors are synced to Modbus, which clears their changed flags.
+pub fn play_sensor_sounds(
+    mut commands: Commands,
+    global_state: Res<GlobalSensorState>,
+    cues: Res<SoundCues>,
+    settings: Res<SoundSettings>,
+) {
+    let tripped = global_state
+        .get_changed_sensors()
+        .iter()
+        .any(|sensor_tag| global_state.is_triggered(sensor_tag));
+    if tripped {
+        cues.play(&mut commands, &settings, SoundCue::SensorTrip);
+    }
+}
+
+/// Sounds alarms that aren't already active. Runs before the alarm events are
+/// handled, while the alarm state still tells new alarms apart.
+pub fn play_alarm_sounds(
+    mut commands: Commands,
+    mut alarm_events: EventReader<AlarmEvent>,
+    alarm_state: Res<AlarmState>,
+    cues: Res<SoundCues>,
+    settings: Res<SoundSettings>,
+) {
+    let raised = alarm_events.read().any(|alarm_event| match *alarm_event {
+        AlarmEvent::Raise(alarm) => !alarm_state.is_active(alarm),
+        AlarmEvent::Clear(_) => false,
+    });
+    if raised {
+        cues.play(&mut commands, &settings, SoundCue::AlarmRaised);
+    }
+}
+
+/// Emergency lighting is the plant's E-stop indication.
+pub fn play_emergency_stop_sounds(
+    mut commands: Commands,
+    lighting_state: Res<LightingState>,
+    mut last_mode: Local<LightingMode>,
+    cues: Res<SoundCues>,
+    settings: Res<SoundSettings>,
+) {
+    if lighting_state.mode == *last_mode {
+        return;
+    }
+
+    if lighting_state.mode == LightingMode::Emergency {
+        cues.play(&mut commands, &settings, SoundCue::EmergencyStop);
+    }
+    *last_mode = lighting_state.mode;
+}
+
+// >>> Input System <<<
+pub fn handle_sound_input(
+    mut settings: ResMut<SoundSettings>,
+    config: Res<SoundConfig>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyN) {
+        settings.is_muted = !settings.is_muted;
+        info!("Sound muted: {}", settings.is_muted);
+
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(config.mute_coil, settings.is_muted);
+        }
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_modbus_to_sound(
+    mut settings: ResMut<SoundSettings>,
+    config: Res<SoundConfig>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(coils) = modbus_state.coils.lock() {
+        if let Some(&coil_state) = coils.get(&config.mute_coil) {
+            if settings.is_muted != coil_state {
+                settings.is_muted = coil_state;
+                info!("Sound muted set to: {}", coil_state);
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Audible feedback for valves, sensors, alarms and the E-stop. Needs Bevy's
+/// audio plugin, which `DefaultPlugins` includes.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SoundSettings>()
+            .init_resource::<SoundConfig>()
+            .add_systems(Startup, setup_sound_cues)
+            .add_systems(Update, handle_sound_input)
+            .add_systems(
+                FixedUpdate,
+                (
+                    register_mute_coil.run_if(resource_changed::<SoundConfig>),
+                    sync_modbus_to_sound,
+                    (
+                        play_valve_sounds,
+                        play_sensor_sounds
+                            .after(update_sensor_outputs)
+                            .before(sync_tags),
+                        play_alarm_sounds.before(handle_alarm_events),
+                        play_emergency_stop_sounds,
+                    ),
+                )
+                    .chain(),
+            );
+    }
+}
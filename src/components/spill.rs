@@ -0,0 +1,216 @@
+// spill.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    alarm::{Alarm, AlarmEvent},
+    modbus::ModbusState,
+    simulation::ResetSimulation,
+    valve::{Ball, BallPool},
+};
+
+// >>> Constants <<<
+const SPILL_ZONE_COLOR: Color = Color::srgba(0.3, 0.5, 0.9, 0.2);
+
+// >>> Components <<<
+/// Floor area catching liquid that missed the bottles, counting each ball
+/// landing in it.
+#[derive(Component)]
+pub struct SpillZone {
+    pub count_input_address: u16,
+    pub reset_coil_address: u16,
+    pub threshold: u16, // Spilled balls at which the spill alarm is raised
+    pub count: u16,
+}
+
+impl SpillZone {
+    pub fn is_over_threshold(&self) -> bool {
+        self.count >= self.threshold
+    }
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct SpillZoneBundle {
+    zone: SpillZone,
+    collider: Collider,
+    collider_sensor: bevy_rapier2d::geometry::Sensor,
+    active_events: ActiveEvents,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl SpillZone {
+    /// Creates an empty spill zone.
+    ///
+    /// # Parameters
+    /// * `count_input_address` - Input register holding the spilled ball count
+    /// * `reset_coil_address` - Coil that clears the count
+    /// * `threshold` - Spilled balls at which the spill alarm is raised
+    /// * `position` - Centre of the zone
+    /// * `size` - Size of the zone
+    ///
+    /// # Return
+    /// The spill zone bundle
+    pub fn new(
+        count_input_address: u16,
+        reset_coil_address: u16,
+        threshold: u16,
+        position: Vec2,
+        size: Vec2,
+    ) -> SpillZoneBundle {
+        SpillZoneBundle {
+            zone: SpillZone {
+                count_input_address,
+                reset_coil_address,
+                threshold,
+                count: 0,
+            },
+            collider: Collider::cuboid(size.x / 2.0, size.y / 2.0),
+            collider_sensor: bevy_rapier2d::geometry::Sensor,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            sprite: Sprite::from_color(SPILL_ZONE_COLOR, size),
+            transform: Transform::from_translation(position.extend(-0.2)),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_spill_zones(
+    zones: Query<&SpillZone, Added<SpillZone>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for zone in zones.iter() {
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(zone.count_input_address, zone.count);
+        }
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(zone.reset_coil_address, false);
+        }
+
+        info!(
+            "Registered spill zone on count input register: {:x?}",
+            zone.count_input_address
+        );
+    }
+}
+
+/// Empties every spill zone on a reset, clearing the spill alarm with them.
+pub fn reset_spill_zones(
+    mut reset_events: EventReader<ResetSimulation>,
+    mut zones: Query<&mut SpillZone>,
+) {
+    if reset_events.read().count() == 0 {
+        return;
+    }
+    for mut zone in zones.iter_mut() {
+        zone.count = 0;
+    }
+}
+
+/// Counts each ball landing in a spill zone and takes it out of play, as the
+/// liquid soaks into the floor.
+pub fn catch_spilled_balls(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut zones: Query<&mut SpillZone>,
+    balls: Query<(), With<Ball>>,
+    mut pool: ResMut<BallPool>,
+) {
+    let mut spilled = Vec::new();
+
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(e1, e2, _) = collision_event else {
+            continue;
+        };
+
+        let (zone_entity, ball) = if zones.contains(*e1) {
+            (*e1, *e2)
+        } else if zones.contains(*e2) {
+            (*e2, *e1)
+        } else {
+            continue;
+        };
+
+        // A ball can touch two zones before it is released
+        if !balls.contains(ball) || spilled.contains(&ball) {
+            continue;
+        }
+        let Ok(mut zone) = zones.get_mut(zone_entity) else {
+            continue;
+        };
+
+        zone.count = zone.count.saturating_add(1);
+        spilled.push(ball);
+        pool.release(&mut commands, ball);
+    }
+}
+
+pub fn monitor_spill_alarms(
+    zones: Query<&SpillZone>,
+    mut alarm_events: EventWriter<AlarmEvent>,
+    mut was_spilled: Local<bool>,
+) {
+    let is_spilled = zones.iter().any(SpillZone::is_over_threshold);
+    if *was_spilled != is_spilled {
+        *was_spilled = is_spilled;
+        alarm_events.write(if is_spilled {
+            AlarmEvent::Raise(Alarm::Spill)
+        } else {
+            AlarmEvent::Clear(Alarm::Spill)
+        });
+    }
+}
+
+// >>> Modbus Synchronization <<<
+/// Clears spill zones whose reset coil was written; the coil resets itself.
+pub fn sync_modbus_to_spill_zones(
+    mut zones: Query<&mut SpillZone>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Ok(mut coils) = modbus_state.coils.lock() else {
+        return;
+    };
+
+    for mut zone in zones.iter_mut() {
+        if coils.get(&zone.reset_coil_address) == Some(&true) {
+            coils.insert(zone.reset_coil_address, false);
+            zone.count = 0;
+            info!("Spill zone {} reset", zone.count_input_address);
+        }
+    }
+}
+
+pub fn sync_spill_zones_to_modbus(
+    zones: Query<&SpillZone, Changed<SpillZone>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for zone in zones.iter() {
+            inputs.insert(zone.count_input_address, zone.count);
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct SpillPlugin;
+
+impl Plugin for SpillPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_spill_zones,
+                reset_spill_zones,
+                sync_modbus_to_spill_zones,
+                catch_spilled_balls,
+                monitor_spill_alarms,
+                sync_spill_zones_to_modbus,
+            )
+                .chain(),
+        );
+    }
+}
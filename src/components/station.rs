@@ -0,0 +1,13 @@
+// station.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+// >>> Components <<<
+/// Copy of the line an entity belongs to.
+///
+/// Devices only act on products and actuators of their own station, so several
+/// lines can run side by side in one app.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct StationId(pub u16);
@@ -0,0 +1,74 @@
+// stats.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use super::{labeler::LabelQuality, outfeed::BottleCompleted, simulation::ResetSimulation};
+
+// >>> Resources <<<
+/// Production totals gathered from bottles leaving the line.
+#[derive(Resource, Default, Debug)]
+pub struct ProductionStats {
+    pub completed: u32,
+    pub capped: u32,
+    pub good_labels: u32,
+    pub by_variant: BTreeMap<String, u32>,
+    pub first_completed_at: Option<f32>,
+    pub last_completed_at: Option<f32>,
+}
+
+impl ProductionStats {
+    pub fn record(&mut self, bottle: &BottleCompleted) {
+        self.completed += 1;
+        if bottle.is_capped {
+            self.capped += 1;
+        }
+        if bottle.label == Some(LabelQuality::Good) {
+            self.good_labels += 1;
+        }
+        *self.by_variant.entry(bottle.variant.clone()).or_insert(0) += 1;
+
+        self.first_completed_at.get_or_insert(bottle.completed_at);
+        self.last_completed_at = Some(bottle.completed_at);
+    }
+
+    /// Bottles completed per minute between the first and last completion.
+    pub fn throughput_per_minute(&self) -> f32 {
+        match (self.first_completed_at, self.last_completed_at) {
+            (Some(first), Some(last)) if last > first => {
+                (self.completed - 1) as f32 * 60.0 / (last - first)
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+// >>> Systems <<<
+/// Tallies each completed bottle, starting over on a reset.
+pub fn record_completed_bottles(
+    mut reset_events: EventReader<ResetSimulation>,
+    mut completed_events: EventReader<BottleCompleted>,
+    mut stats: ResMut<ProductionStats>,
+) {
+    if reset_events.read().count() > 0 {
+        *stats = ProductionStats::default();
+    }
+    for bottle in completed_events.read() {
+        stats.record(bottle);
+    }
+}
+
+// >>> Plugin <<<
+/// Tallies production statistics from [`BottleCompleted`] events.
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProductionStats>()
+            .add_event::<BottleCompleted>()
+            .add_systems(FixedUpdate, record_completed_bottles);
+    }
+}
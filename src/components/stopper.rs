@@ -0,0 +1,225 @@
+// stopper.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{
+    bottle::{Bottle, BottlePosition, BottleSpec},
+    modbus::ModbusState,
+    pneumatics::Pneumatics,
+    station::StationId,
+};
+
+// >>> Constants <<<
+const PIN_SIZE: Vec2 = Vec2::new(6.0, 40.0);
+const PIN_COLOR: Color = Color::srgb(0.7, 0.7, 0.75);
+const HOLD_RANGE: f32 = 3.0; // How close to the pin a bottle must be to count as held
+
+// >>> Components <<<
+/// Retractable pin stopping bottles at a workstation. The belt runs on under
+/// a held bottle until the pin is retracted.
+#[derive(Component)]
+pub struct Stopper {
+    pub extend_coil_address: u16,
+    pub present_discrete_address: u16,
+    pub position: Vec2, // Centre of the extended pin
+    pub is_extended: bool,
+    pub is_occupied: bool,
+}
+
+/// Marks a bottle held against an extended stopper.
+#[derive(Component)]
+pub struct Stopped;
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct StopperBundle {
+    stopper: Stopper,
+    collider: Collider,
+    disabled: ColliderDisabled,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl Stopper {
+    /// Creates a retracted stopper.
+    ///
+    /// # Parameters
+    /// * `extend_coil_address` - Coil raising the pin into the bottles' path
+    /// * `present_discrete_address` - Discrete input set while a bottle is held
+    /// * `position` - Centre of the pin when extended
+    ///
+    /// # Return
+    /// The stopper bundle
+    pub fn new(
+        extend_coil_address: u16,
+        present_discrete_address: u16,
+        position: Vec2,
+    ) -> StopperBundle {
+        StopperBundle {
+            stopper: Stopper {
+                extend_coil_address,
+                present_discrete_address,
+                position,
+                is_extended: false,
+                is_occupied: false,
+            },
+            collider: Collider::cuboid(PIN_SIZE.x / 2.0, PIN_SIZE.y / 2.0),
+            disabled: ColliderDisabled,
+            sprite: Sprite::from_color(PIN_COLOR, PIN_SIZE),
+            transform: Transform::from_translation(Stopper::pin_position(position, false)),
+        }
+    }
+
+    // A retracted pin is drawn sunk behind the belt
+    fn pin_position(position: Vec2, is_extended: bool) -> Vec3 {
+        if is_extended {
+            position.extend(0.5)
+        } else {
+            (position - Vec2::Y * PIN_SIZE.y).extend(-0.5)
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_stoppers(
+    stoppers: Query<&Stopper, Added<Stopper>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for stopper in stoppers.iter() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(stopper.extend_coil_address, stopper.is_extended);
+        }
+        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+            discretes.insert(stopper.present_discrete_address, stopper.is_occupied);
+        }
+
+        info!(
+            "Registered stopper on extend coil: {:x?}",
+            stopper.extend_coil_address
+        );
+    }
+}
+
+/// Raises or lowers the pin, making it solid only while extended.
+pub fn actuate_stoppers(
+    mut commands: Commands,
+    mut stoppers: Query<(Entity, &Stopper, &mut Transform), Changed<Stopper>>,
+) {
+    for (entity, stopper, mut transform) in stoppers.iter_mut() {
+        transform.translation = Stopper::pin_position(stopper.position, stopper.is_extended);
+        if stopper.is_extended {
+            commands.entity(entity).remove::<ColliderDisabled>();
+        } else {
+            commands.entity(entity).insert(ColliderDisabled);
+        }
+    }
+}
+
+/// Marks the bottle resting against each extended pin, so the belt slips
+/// under it rather than it being reported as jammed.
+pub fn hold_bottles(
+    mut commands: Commands,
+    mut stoppers: Query<(&mut Stopper, &StationId)>,
+    bottles: Query<
+        (
+            Entity,
+            &Transform,
+            &BottlePosition,
+            &BottleSpec,
+            &StationId,
+            Has<Stopped>,
+        ),
+        With<Bottle>,
+    >,
+) {
+    let mut occupied = vec![false; stoppers.iter().count()];
+
+    for (entity, transform, position, spec, bottle_station, was_stopped) in bottles.iter() {
+        let center = position.world_center(transform);
+        let front = center.x + spec.width / 2.0;
+
+        let held_by = stoppers.iter().position(|(stopper, station)| {
+            let pin_back = stopper.position.x - PIN_SIZE.x / 2.0;
+            station == bottle_station
+                && stopper.is_extended
+                && (pin_back - front).abs() < HOLD_RANGE
+                && (center.y - stopper.position.y).abs() < (spec.height + PIN_SIZE.y) / 2.0
+        });
+        if let Some(index) = held_by {
+            occupied[index] = true;
+        }
+
+        let is_stopped = held_by.is_some();
+        if is_stopped != was_stopped {
+            if is_stopped {
+                commands.entity(entity).insert(Stopped);
+            } else {
+                commands.entity(entity).remove::<Stopped>();
+            }
+        }
+    }
+
+    for ((mut stopper, _), is_occupied) in stoppers.iter_mut().zip(occupied) {
+        if stopper.is_occupied != is_occupied {
+            stopper.is_occupied = is_occupied;
+        }
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_modbus_to_stoppers(
+    mut stoppers: Query<&mut Stopper>,
+    modbus_state: Res<ModbusState>,
+    mut pneumatics: Option<ResMut<Pneumatics>>,
+) {
+    if let Ok(coils) = modbus_state.coils.lock() {
+        for mut stopper in stoppers.iter_mut() {
+            if let Some(&coil_state) = coils.get(&stopper.extend_coil_address) {
+                if stopper.is_extended != coil_state {
+                    // Extending takes air; the pin retracts on its spring
+                    if let (true, Some(pneumatics)) = (coil_state, pneumatics.as_deref_mut()) {
+                        if pneumatics.is_low() {
+                            continue;
+                        }
+                        pneumatics.stroke();
+                    }
+                    stopper.is_extended = coil_state;
+                    info!("Stopper extended set to: {}", coil_state);
+                }
+            }
+        }
+    }
+}
+
+pub fn sync_stoppers_to_modbus(
+    stoppers: Query<&Stopper, Changed<Stopper>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+        for stopper in stoppers.iter() {
+            discretes.insert(stopper.present_discrete_address, stopper.is_occupied);
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct StopperPlugin;
+
+impl Plugin for StopperPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_stoppers,
+                sync_modbus_to_stoppers,
+                actuate_stoppers,
+                hold_bottles,
+                sync_stoppers_to_modbus,
+            )
+                .chain(),
+        );
+    }
+}
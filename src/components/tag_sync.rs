@@ -0,0 +1,83 @@
+// tag_sync.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    conveyor::{Conveyor, ConveyorState, read_conveyor_registers, write_conveyor_registers},
+    modbus::ModbusState,
+    mode::{OperatingMode, remote_control_allowed},
+    profiler::{MODBUS_SYNC, profiled},
+    sensor::{GlobalSensorState, SensorFaults, update_sensor_outputs, write_sensor_inputs},
+    tags::TagRegistry,
+    valve::{ProportionalValve, Valve, ValveState, read_valve_registers, write_valve_registers},
+};
+
+// >>> Modbus Synchronization <<<
+/// Syncs the conveyors, valves and sensors with the register banks, locking
+/// each bank once per tick rather than once per device type, so a client
+/// polling fast contends for the locks as little as possible.
+///
+/// The plant's changes since the last tick are published first, then the
+/// client's writes are read back, so the plant never reverts what it just
+/// published. Reading back is skipped while nothing has written the coils or
+/// holding registers since the last tick.
+#[allow(clippy::too_many_arguments)]
+pub fn sync_tags(
+    mut conveyors: Query<(&Conveyor, &mut ConveyorState)>,
+    mut valves: Query<(&Valve, &mut ValveState, Option<&ProportionalValve>)>,
+    mut global_state: ResMut<GlobalSensorState>,
+    faults: Res<SensorFaults>,
+    modbus_state: Res<ModbusState>,
+    mode: Option<Res<OperatingMode>>,
+    tags: Res<TagRegistry>,
+    mut last_generation: Local<Option<u64>>,
+) {
+    let Some(mut banks) = modbus_state.lock_banks() else {
+        return;
+    };
+
+    // Outbound: only what changed, judged against this system's last run
+    for (conveyor, conveyor_state) in conveyors.iter_mut() {
+        if conveyor_state.is_changed() {
+            write_conveyor_registers(&mut banks, &tags, conveyor, &conveyor_state);
+        }
+    }
+    for (valve, valve_state, proportional) in valves.iter_mut() {
+        if valve_state.is_changed() {
+            write_valve_registers(&mut banks, &tags, valve, &valve_state, proportional);
+        }
+    }
+    write_sensor_inputs(&mut banks, &mut global_state, &faults);
+
+    // Inbound, once written to or when remote control is allowed again
+    if !remote_control_allowed(mode.as_deref()) {
+        return;
+    }
+    let generation = modbus_state.generation();
+    let is_mode_changed = mode.as_ref().is_some_and(|mode| mode.is_changed());
+    if last_generation.replace(generation) == Some(generation) && !is_mode_changed {
+        return;
+    }
+    for (conveyor, mut conveyor_state) in conveyors.iter_mut() {
+        read_conveyor_registers(&banks, &tags, conveyor, &mut conveyor_state);
+    }
+    for (valve, mut valve_state, proportional) in valves.iter_mut() {
+        read_valve_registers(&mut banks, &tags, valve, &mut valve_state, proportional);
+    }
+}
+
+// >>> Plugin <<<
+/// The tag-sync stage of every fixed tick, between the plant's sensing and
+/// the systems acting on the client's writes.
+pub struct TagSyncPlugin;
+
+impl Plugin for TagSyncPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            profiled(MODBUS_SYNC, sync_tags).after(update_sensor_outputs),
+        );
+    }
+}
@@ -0,0 +1,517 @@
+// tags.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{arbitration::WriteOrigin, modbus::ModbusState};
+
+// >>> Tags <<<
+/// Modbus table a tag lives in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagKind {
+    Coil,
+    DiscreteInput,
+    InputRegister,
+    HoldingRegister,
+}
+
+impl TagKind {
+    /// Whether controllers may write the tag.
+    pub fn is_writable(&self) -> bool {
+        matches!(self, TagKind::Coil | TagKind::HoldingRegister)
+    }
+
+    /// Whether the tag holds a single bit rather than a 16-bit word.
+    pub fn is_bit(&self) -> bool {
+        matches!(self, TagKind::Coil | TagKind::DiscreteInput)
+    }
+
+    /// Short table name for on-screen display.
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            TagKind::Coil => "coil",
+            TagKind::DiscreteInput => "disc",
+            TagKind::InputRegister => "input",
+            TagKind::HoldingRegister => "hold",
+        }
+    }
+
+    /// Full table name for exported documents.
+    pub fn description(&self) -> &'static str {
+        match self {
+            TagKind::Coil => "Coil",
+            TagKind::DiscreteInput => "Discrete input",
+            TagKind::InputRegister => "Input register",
+            TagKind::HoldingRegister => "Holding register",
+        }
+    }
+}
+
+/// A named plant signal and the Modbus address backing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub kind: TagKind,
+    pub address: u16,
+}
+
+impl Tag {
+    /// Reads the tag's current value; bits read as 0 or 1.
+    pub fn read(&self, modbus_state: &ModbusState) -> Option<u16> {
+        match self.kind {
+            TagKind::Coil => modbus_state
+                .coils
+                .lock()
+                .ok()?
+                .get(&self.address)
+                .map(|&value| value as u16),
+            TagKind::DiscreteInput => modbus_state
+                .discrete_inputs
+                .lock()
+                .ok()?
+                .get(&self.address)
+                .map(|&value| value as u16),
+            TagKind::InputRegister => modbus_state
+                .input_registers
+                .lock()
+                .ok()?
+                .get(&self.address)
+                .copied(),
+            TagKind::HoldingRegister => modbus_state
+                .holding_registers
+                .lock()
+                .ok()?
+                .get(&self.address)
+                .copied(),
+        }
+    }
+
+    /// Writes a writable tag whose address has been registered, on behalf of
+    /// `origin`. Arbitration may hold the value off, see
+    /// [`WriteSource`](super::arbitration::WriteSource).
+    ///
+    /// # Return
+    /// Whether the tag could be written
+    pub fn write(&self, modbus_state: &ModbusState, value: u16, origin: &WriteOrigin) -> bool {
+        modbus_state.write(self.kind, self.address, value, origin)
+    }
+}
+
+/// Linear scaling between a value in the plant's own units, e.g. pixels per
+/// second, and the raw count in its register: `raw = value * scale + offset`.
+/// Raw counts saturate at the register's range.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scaling {
+    pub scale: f32,  // Counts per unit
+    pub offset: f32, // Counts at zero
+    pub unit: String,
+}
+
+impl Default for Scaling {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            offset: 0.0,
+            unit: String::new(),
+        }
+    }
+}
+
+impl Scaling {
+    /// Converts a value to the raw count published in the register.
+    pub fn encode(&self, value: f32) -> f64 {
+        (value * self.scale + self.offset) as f64
+    }
+
+    /// Converts a raw count written to the register back to a value.
+    pub fn decode(&self, count: f64) -> f32 {
+        (count as f32 - self.offset) / self.scale
+    }
+
+    /// Formats a raw count as its value and unit, e.g. `12.5 mm/s`.
+    pub fn display(&self, count: f64) -> String {
+        let value = format!("{:.2}", self.decode(count));
+        let value = value.trim_end_matches('0').trim_end_matches('.');
+        if self.unit.is_empty() {
+            value.to_string()
+        } else {
+            format!("{value} {}", self.unit)
+        }
+    }
+}
+
+/// How a raw count is laid out in registers. Signed types are two's
+/// complement; 32-bit types take two consecutive registers, high word first
+/// like the plant's counters and energy totals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterType {
+    #[default]
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+}
+
+impl RegisterType {
+    /// Registers a value of the type takes.
+    pub fn words(&self) -> u16 {
+        match self {
+            RegisterType::U16 | RegisterType::I16 => 1,
+            RegisterType::U32 | RegisterType::I32 | RegisterType::F32 => 2,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            RegisterType::U16 => "u16",
+            RegisterType::I16 => "i16",
+            RegisterType::U32 => "u32",
+            RegisterType::I32 => "i32",
+            RegisterType::F32 => "f32",
+        }
+    }
+
+    /// Lays a raw count out in registers. Integer counts are rounded and
+    /// saturate at the type's range.
+    pub fn encode(&self, count: f64) -> Vec<u16> {
+        let split = |long: u32| vec![(long >> 16) as u16, long as u16];
+        // Float to integer casts saturate
+        match self {
+            RegisterType::U16 => vec![count.round() as u16],
+            RegisterType::I16 => vec![count.round() as i16 as u16],
+            RegisterType::U32 => split(count.round() as u32),
+            RegisterType::I32 => split(count.round() as i32 as u32),
+            RegisterType::F32 => split((count as f32).to_bits()),
+        }
+    }
+
+    /// Reads a raw count back from its registers; missing words read as 0.
+    pub fn decode(&self, words: &[u16]) -> f64 {
+        let word = |i: usize| words.get(i).copied().unwrap_or_default();
+        let long = ((word(0) as u32) << 16) | word(1) as u32;
+        match self {
+            RegisterType::U16 => word(0) as f64,
+            RegisterType::I16 => word(0) as i16 as f64,
+            RegisterType::U32 => long as f64,
+            RegisterType::I32 => long as i32 as f64,
+            RegisterType::F32 => f32::from_bits(long) as f64,
+        }
+    }
+}
+
+/// Tags of one table claiming the same address, which would silently
+/// overwrite each other's values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressCollision {
+    pub kind: TagKind,
+    pub address: u16,
+    pub names: Vec<String>,
+}
+
+impl fmt::Display for AddressCollision {
+    /// Formats as e.g. `coil 0x0007: cap_hopper.gate, vision.trigger`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} 0x{:04X}: {}",
+            self.kind.abbreviation(),
+            self.address,
+            self.names.join(", ")
+        )
+    }
+}
+
+// >>> Components <<<
+/// Tags backed by a device's registers, as spawned from the scenario.
+#[derive(Component, Debug, Clone, Default)]
+pub struct RegisterMap(pub Vec<Tag>);
+
+/// Name of a device, prefixing its tags (e.g. `bench2.conveyor`).
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct DeviceName(pub String);
+
+// >>> Resources <<<
+/// Names for the plant's Modbus addresses, shared by every protocol front end.
+#[derive(Resource, Default)]
+pub struct TagRegistry {
+    tags: Vec<Tag>,
+    duplicates: Vec<Tag>, // Refused for reusing a registered name
+    scalings: HashMap<(TagKind, u16), Scaling>,
+    register_types: HashMap<(TagKind, u16), RegisterType>,
+}
+
+impl TagRegistry {
+    /// Registers a tag. A name already registered for another address keeps
+    /// its first tag, and the new one is refused and listed in
+    /// [`TagRegistry::duplicates`].
+    ///
+    /// # Return
+    /// The tag's address, so registration can wrap constructor arguments
+    pub fn register(&mut self, name: &str, kind: TagKind, address: u16) -> u16 {
+        let tag = Tag {
+            name: name.to_string(),
+            kind,
+            address,
+        };
+
+        match self.get(name) {
+            Some(existing) if *existing == tag => {}
+            Some(existing) => {
+                warn!(
+                    "Tag {name} is already registered at {} 0x{:04X}, refusing {} 0x{:04X}",
+                    existing.kind.abbreviation(),
+                    existing.address,
+                    kind.abbreviation(),
+                    address
+                );
+                self.duplicates.push(tag);
+            }
+            None => self.tags.push(tag),
+        }
+        address
+    }
+
+    pub fn coil(&mut self, name: &str, address: u16) -> u16 {
+        self.register(name, TagKind::Coil, address)
+    }
+
+    pub fn discrete_input(&mut self, name: &str, address: u16) -> u16 {
+        self.register(name, TagKind::DiscreteInput, address)
+    }
+
+    pub fn input_register(&mut self, name: &str, address: u16) -> u16 {
+        self.register(name, TagKind::InputRegister, address)
+    }
+
+    pub fn holding_register(&mut self, name: &str, address: u16) -> u16 {
+        self.register(name, TagKind::HoldingRegister, address)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// Scales the register behind a registered tag, for every tag sharing its
+    /// address.
+    ///
+    /// # Return
+    /// Whether the tag is a registered input or holding register
+    pub fn set_scaling(&mut self, name: &str, scaling: Scaling) -> bool {
+        let Some(tag) = self.get(name).filter(|tag| !tag.kind.is_bit()) else {
+            return false;
+        };
+        self.scalings.insert((tag.kind, tag.address), scaling);
+        true
+    }
+
+    pub fn scaling(&self, kind: TagKind, address: u16) -> Option<&Scaling> {
+        self.scalings.get(&(kind, address))
+    }
+
+    /// Lays out the register at an address as `register_type`, for every tag
+    /// sharing it.
+    pub fn set_register_type(&mut self, kind: TagKind, address: u16, register_type: RegisterType) {
+        self.register_types.insert((kind, address), register_type);
+    }
+
+    /// Layout of the register at an address, a plain u16 unless a device
+    /// declared otherwise.
+    pub fn register_type(&self, kind: TagKind, address: u16) -> RegisterType {
+        self.register_types
+            .get(&(kind, address))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Registers for a value published at an address, in its register type
+    /// and unscaled unless the scenario scales it.
+    pub fn encode(&self, kind: TagKind, address: u16, value: f32) -> Vec<u16> {
+        let count = match self.scaling(kind, address) {
+            Some(scaling) => scaling.encode(value),
+            None => value as f64,
+        };
+        self.register_type(kind, address).encode(count)
+    }
+
+    /// Value for the registers written at an address, in its register type
+    /// and unscaled unless the scenario scales it.
+    pub fn decode(&self, kind: TagKind, address: u16, words: &[u16]) -> f32 {
+        let count = self.register_type(kind, address).decode(words);
+        match self.scaling(kind, address) {
+            Some(scaling) => scaling.decode(count),
+            None => count as f32,
+        }
+    }
+
+    /// Reads a value from a register table, e.g. the holding registers.
+    ///
+    /// # Return
+    /// The value, if every register it takes is registered
+    pub fn read_value(
+        &self,
+        registers: &HashMap<u16, u16>,
+        kind: TagKind,
+        address: u16,
+    ) -> Option<f32> {
+        let words = (0..self.register_type(kind, address).words())
+            .map(|i| registers.get(&address.wrapping_add(i)).copied())
+            .collect::<Option<Vec<u16>>>()?;
+        Some(self.decode(kind, address, &words))
+    }
+
+    /// Current value of a tag, decoded and scaled like the HMI shows it; bits
+    /// read as 0 or 1.
+    pub fn tag_value(&self, tag: &Tag, modbus_state: &ModbusState) -> Option<f32> {
+        let registers = match tag.kind {
+            TagKind::Coil | TagKind::DiscreteInput => {
+                return tag.read(modbus_state).map(f32::from);
+            }
+            TagKind::InputRegister => &modbus_state.input_registers,
+            TagKind::HoldingRegister => &modbus_state.holding_registers,
+        };
+        self.read_value(&registers.lock().ok()?, tag.kind, tag.address)
+    }
+
+    /// Publishes a value to a register table, e.g. the holding registers.
+    pub fn insert_value(
+        &self,
+        registers: &mut HashMap<u16, u16>,
+        kind: TagKind,
+        address: u16,
+        value: f32,
+    ) {
+        for (i, word) in (0..).zip(self.encode(kind, address, value)) {
+            registers.insert(address.wrapping_add(i), word);
+        }
+    }
+
+    /// Writes a value to registered holding registers on behalf of `origin`,
+    /// see [`ModbusState::write`].
+    ///
+    /// # Return
+    /// Whether every register it takes could be written
+    pub fn write_value(
+        &self,
+        modbus_state: &ModbusState,
+        kind: TagKind,
+        address: u16,
+        value: f32,
+        origin: &WriteOrigin,
+    ) -> bool {
+        (0..)
+            .zip(self.encode(kind, address, value))
+            .all(|(i, word)| modbus_state.write(kind, address.wrapping_add(i), word, origin))
+    }
+
+    /// Describes a scaled or typed tag's value, e.g. `12.5 mm/s` or `-40`.
+    pub fn scaled_line(&self, tag: &Tag, modbus_state: &ModbusState) -> Option<String> {
+        let scaling = self.scaling(tag.kind, tag.address);
+        let register_type = self.register_type(tag.kind, tag.address);
+        if scaling.is_none() && register_type == RegisterType::U16 {
+            return None;
+        }
+
+        let words = (0..register_type.words())
+            .map(|i| {
+                let word = Tag {
+                    address: tag.address.wrapping_add(i),
+                    ..tag.clone()
+                };
+                word.read(modbus_state)
+            })
+            .collect::<Option<Vec<u16>>>()?;
+        let count = register_type.decode(&words);
+        Some(match scaling {
+            Some(scaling) => scaling.display(count),
+            None => count.to_string(),
+        })
+    }
+
+    /// Tags in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = &Tag> {
+        self.tags.iter()
+    }
+
+    /// Tags refused for reusing the name of a registered tag, in registration
+    /// order.
+    pub fn duplicates(&self) -> &[Tag] {
+        &self.duplicates
+    }
+
+    /// Addresses served from the register banks that no tag claims, e.g.
+    /// inserted directly by a plugin, in table and address order. The
+    /// collision check cannot see them, so a tag may share one unawares.
+    pub fn untagged(&self, modbus_state: &ModbusState) -> Vec<(TagKind, u16)> {
+        let claimed: HashSet<(TagKind, u16)> = self
+            .tags
+            .iter()
+            .flat_map(|tag| {
+                (0..self.register_type(tag.kind, tag.address).words())
+                    .map(|i| (tag.kind, tag.address.wrapping_add(i)))
+            })
+            .collect();
+
+        let mut served = Vec::new();
+        if let Ok(coils) = modbus_state.coils.lock() {
+            served.extend(coils.keys().map(|&address| (TagKind::Coil, address)));
+        }
+        if let Ok(discrete_inputs) = modbus_state.discrete_inputs.lock() {
+            served.extend(
+                discrete_inputs
+                    .keys()
+                    .map(|&address| (TagKind::DiscreteInput, address)),
+            );
+        }
+        if let Ok(input_registers) = modbus_state.input_registers.lock() {
+            served.extend(
+                input_registers
+                    .keys()
+                    .map(|&address| (TagKind::InputRegister, address)),
+            );
+        }
+        if let Ok(holding_registers) = modbus_state.holding_registers.lock() {
+            served.extend(
+                holding_registers
+                    .keys()
+                    .map(|&address| (TagKind::HoldingRegister, address)),
+            );
+        }
+
+        served.retain(|served| !claimed.contains(served));
+        served.sort();
+        served
+    }
+
+    /// Addresses claimed by more than one tag of the same table, in table and
+    /// address order.
+    pub fn collisions(&self) -> Vec<AddressCollision> {
+        let mut claims: BTreeMap<(TagKind, u16), Vec<String>> = BTreeMap::new();
+        for tag in &self.tags {
+            for i in 0..self.register_type(tag.kind, tag.address).words() {
+                claims
+                    .entry((tag.kind, tag.address.wrapping_add(i)))
+                    .or_default()
+                    .push(tag.name.clone());
+            }
+        }
+
+        claims
+            .into_iter()
+            .filter(|(_, names)| names.len() > 1)
+            .map(|((kind, address), names)| AddressCollision {
+                kind,
+                address,
+                names,
+            })
+            .collect()
+    }
+}
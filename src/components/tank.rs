@@ -0,0 +1,257 @@
+// tank.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use super::{
+    modbus::ModbusState,
+    simulation::SimTime,
+    station::StationId,
+    valve::{BallDispensed, BallSpawner, Valve, WATER_COLOR, spawn_balls},
+};
+
+// >>> Constants <<<
+const TANK_SIZE: Vec2 = Vec2::new(70.0, 90.0);
+const TANK_COLOR: Color = Color::srgb(0.35, 0.35, 0.4);
+const TANK_LEVEL_SCALE: f32 = 1000.0; // Level register value of a full tank
+
+// >>> Components <<<
+/// Supply tank feeding its station's filling valves.
+///
+/// Level is measured in balls' worth of liquid; every ball a valve dispenses
+/// drains one.
+#[derive(Component)]
+pub struct Tank {
+    pub pump_coil_address: u16,
+    pub level_input_address: u16,
+    pub low_level_discrete_address: u16,
+    pub capacity: f32,
+    pub low_level: f32,
+    pub pump_rate: f32, // Balls' worth per second
+    pub level: f32,
+    pub is_pump_on: bool,
+}
+
+impl Tank {
+    pub fn is_low(&self) -> bool {
+        self.level <= self.low_level
+    }
+
+    /// Level in tenths of a percent of capacity, as reported over Modbus.
+    pub fn level_register(&self) -> u16 {
+        (self.fraction() * TANK_LEVEL_SCALE).round() as u16
+    }
+
+    fn fraction(&self) -> f32 {
+        if self.capacity > 0.0 {
+            (self.level / self.capacity).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Liquid drawn inside a tank, scaled to its level.
+#[derive(Component)]
+pub struct TankLiquid;
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct TankBundle {
+    tank: Tank,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl Tank {
+    /// Creates a full supply tank.
+    ///
+    /// # Parameters
+    /// * `pump_coil_address` - Coil running the fill pump
+    /// * `level_input_address` - Input register reporting level in 0.1% of capacity
+    /// * `low_level_discrete_address` - Discrete input set at or below `low_level`
+    /// * `capacity` - Balls' worth of liquid held when full
+    /// * `low_level` - Level at or below which dispensing is blocked
+    /// * `pump_rate` - Balls' worth pumped in per second
+    /// * `position` - Position of the tank
+    ///
+    /// # Return
+    /// The tank bundle
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        pump_coil_address: u16,
+        level_input_address: u16,
+        low_level_discrete_address: u16,
+        capacity: f32,
+        low_level: f32,
+        pump_rate: f32,
+        position: Vec2,
+    ) -> TankBundle {
+        TankBundle {
+            tank: Tank {
+                pump_coil_address,
+                level_input_address,
+                low_level_discrete_address,
+                capacity,
+                low_level,
+                pump_rate,
+                level: capacity,
+                is_pump_on: false,
+            },
+            sprite: Sprite::from_color(TANK_COLOR, TANK_SIZE),
+            transform: Transform::from_translation(position.extend(0.0)),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_tanks(
+    mut commands: Commands,
+    tanks: Query<(Entity, &Tank), Added<Tank>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for (entity, tank) in tanks.iter() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(tank.pump_coil_address, tank.is_pump_on);
+        }
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(tank.level_input_address, tank.level_register());
+        }
+        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+            discretes.insert(tank.low_level_discrete_address, tank.is_low());
+        }
+
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                TankLiquid,
+                Sprite {
+                    color: WATER_COLOR,
+                    custom_size: Some(TANK_SIZE * 0.9),
+                    anchor: Anchor::BottomCenter,
+                    ..Default::default()
+                },
+                Transform::from_xyz(0.0, -TANK_SIZE.y * 0.45, 0.1)
+                    .with_scale(Vec3::new(1.0, tank.fraction(), 1.0)),
+            ));
+        });
+
+        info!("Registered tank on pump coil: {:x?}", tank.pump_coil_address);
+    }
+}
+
+pub fn fill_tanks(time: Res<SimTime>, mut tanks: Query<&mut Tank>) {
+    for mut tank in tanks.iter_mut() {
+        if tank.is_pump_on && tank.level < tank.capacity {
+            tank.level = (tank.level + tank.pump_rate * time.delta_secs()).min(tank.capacity);
+        }
+    }
+}
+
+/// Blocks dispensing at stations whose supply tank is low.
+pub fn interlock_tank_valves(
+    tanks: Query<(&Tank, &StationId)>,
+    mut valves: Query<(&mut BallSpawner, &StationId), With<Valve>>,
+) {
+    for (mut ball_spawner, valve_station) in valves.iter_mut() {
+        let is_blocked = tanks
+            .iter()
+            .any(|(tank, tank_station)| tank_station == valve_station && tank.is_low());
+        if ball_spawner.is_blocked != is_blocked {
+            ball_spawner.is_blocked = is_blocked;
+            if is_blocked {
+                warn!("Tank low, dispensing blocked");
+            }
+        }
+    }
+}
+
+/// Draws each dispensed ball out of its station's tank.
+pub fn drain_tanks(
+    mut dispensed_events: EventReader<BallDispensed>,
+    valves: Query<&StationId, With<Valve>>,
+    mut tanks: Query<(&mut Tank, &StationId)>,
+) {
+    for dispensed in dispensed_events.read() {
+        let Ok(valve_station) = valves.get(dispensed.valve) else {
+            continue;
+        };
+
+        if let Some((mut tank, _)) = tanks
+            .iter_mut()
+            .find(|(_, tank_station)| *tank_station == valve_station)
+        {
+            tank.level = (tank.level - 1.0).max(0.0);
+        }
+    }
+}
+
+pub fn update_tank_visuals(
+    tanks: Query<(&Tank, &Children), Changed<Tank>>,
+    mut liquids: Query<&mut Transform, With<TankLiquid>>,
+) {
+    for (tank, children) in tanks.iter() {
+        for &child in children {
+            if let Ok(mut transform) = liquids.get_mut(child) {
+                transform.scale.y = tank.fraction();
+            }
+        }
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_modbus_to_tanks(mut tanks: Query<&mut Tank>, modbus_state: Res<ModbusState>) {
+    if let Ok(coils) = modbus_state.coils.lock() {
+        for mut tank in tanks.iter_mut() {
+            if let Some(&coil_state) = coils.get(&tank.pump_coil_address) {
+                if tank.is_pump_on != coil_state {
+                    tank.is_pump_on = coil_state;
+                    info!("Tank pump set to: {}", coil_state);
+                }
+            }
+        }
+    }
+}
+
+pub fn sync_tanks_to_modbus(tanks: Query<&Tank, Changed<Tank>>, modbus_state: Res<ModbusState>) {
+    if tanks.is_empty() {
+        return;
+    }
+
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for tank in tanks.iter() {
+            inputs.insert(tank.level_input_address, tank.level_register());
+        }
+    }
+
+    if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+        for tank in tanks.iter() {
+            discretes.insert(tank.low_level_discrete_address, tank.is_low());
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct TankPlugin;
+
+impl Plugin for TankPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_tank_visuals).add_systems(
+            FixedUpdate,
+            (
+                (
+                    register_tanks,
+                    sync_modbus_to_tanks,
+                    fill_tanks,
+                    interlock_tank_valves,
+                )
+                    .chain()
+                    .before(spawn_balls),
+                (drain_tanks, sync_tanks_to_modbus)
+                    .chain()
+                    .after(spawn_balls),
+            ),
+        );
+    }
+}
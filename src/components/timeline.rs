@@ -0,0 +1,332 @@
+// timeline.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, anyhow, bail};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    actuator::ValveFaults,
+    arbitration::WriteOrigin,
+    modbus::{ModbusState, NetworkImpairment},
+    sensor::{FaultKind, SensorFaults},
+    simulation::{ResetSimulation, SimTime},
+    tags::TagRegistry,
+};
+use crate::scenario::Scenario;
+
+// >>> Timeline <<<
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineAction {
+    Set { tag: String, value: f32 }, // In the tag's engineering units
+    InjectFault { sensor: String, kind: FaultKind },
+    SeizeValve { valve: String },
+    ClearFault { name: String }, // Sensor or valve
+    ClearFaults,
+    ImpairNetwork(NetworkImpairment),
+    RestoreNetwork,
+}
+
+/// A scripted action, written in scenario files as e.g. `at 30s: set valve.spawn_rate=2`.
+///
+/// Actions are `set <tag>=<value>`, `inject fault <sensor> <stuck_on|stuck_off|wire_break|short>`,
+/// `inject fault <valve> seized`, `clear fault <sensor|valve>`, `clear faults`,
+/// `impair network <setting>=<value>...` with the settings of [`NetworkImpairment`], and
+/// `restore network`. A `set` value is in the tag's units, converted through its scaling and
+/// register type like an HMI write, so it may be fractional, e.g. `0.5`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimelineEntry {
+    pub at: f32, // Seconds of simulation time since the scenario started
+    pub action: TimelineAction,
+}
+
+fn parse_fault_kind(kind: &str) -> anyhow::Result<FaultKind> {
+    match kind {
+        "stuck_on" => Ok(FaultKind::StuckOn),
+        "stuck_off" => Ok(FaultKind::StuckOff),
+        "wire_break" => Ok(FaultKind::WireBreak),
+        "short" => Ok(FaultKind::Short),
+        _ => bail!(
+            "Unknown fault {kind}, expected stuck_on, stuck_off, wire_break or short, or seized for a valve"
+        ),
+    }
+}
+
+fn parse_impairment(settings: &[&str]) -> anyhow::Result<NetworkImpairment> {
+    let mut impairment = NetworkImpairment::default();
+    for setting in settings {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected <setting>=<value> in {setting}"))?;
+        let value: f32 = value
+            .parse()
+            .with_context(|| format!("Invalid {key} {value}"))?;
+        match key {
+            "latency" => impairment.latency = value,
+            "jitter" => impairment.jitter = value,
+            "drop_requests" => impairment.drop_requests = value,
+            "drop_responses" => impairment.drop_responses = value,
+            "exceptions" => impairment.exceptions = value,
+            _ => bail!(
+                "Unknown network setting {key}, expected latency, jitter, drop_requests, drop_responses or exceptions"
+            ),
+        }
+    }
+    impairment.validate()?;
+    Ok(impairment)
+}
+
+impl FromStr for TimelineAction {
+    type Err = anyhow::Error;
+
+    fn from_str(action: &str) -> anyhow::Result<Self> {
+        let words: Vec<&str> = action.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["set", assignment] => {
+                let (tag, value) = assignment
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Expected <tag>=<value> in {action}"))?;
+                let value: f32 = value
+                    .parse()
+                    .with_context(|| format!("Invalid value {value}"))?;
+                if !value.is_finite() {
+                    bail!("Invalid value {value}");
+                }
+                Ok(TimelineAction::Set {
+                    tag: tag.to_string(),
+                    value,
+                })
+            }
+            ["inject", "fault", valve, "seized"] => Ok(TimelineAction::SeizeValve {
+                valve: valve.to_string(),
+            }),
+            ["inject", "fault", sensor, kind] => Ok(TimelineAction::InjectFault {
+                sensor: sensor.to_string(),
+                kind: parse_fault_kind(kind)?,
+            }),
+            ["clear", "fault", name] => Ok(TimelineAction::ClearFault {
+                name: name.to_string(),
+            }),
+            ["clear", "faults"] => Ok(TimelineAction::ClearFaults),
+            ["impair", "network", settings @ ..] => {
+                Ok(TimelineAction::ImpairNetwork(parse_impairment(settings)?))
+            }
+            ["restore", "network"] => Ok(TimelineAction::RestoreNetwork),
+            _ => bail!("Unknown timeline action {action}"),
+        }
+    }
+}
+
+impl fmt::Display for TimelineAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimelineAction::Set { tag, value } => write!(f, "set {tag}={value}"),
+            TimelineAction::InjectFault { sensor, kind } => {
+                let kind = match kind {
+                    FaultKind::StuckOn => "stuck_on",
+                    FaultKind::StuckOff => "stuck_off",
+                    FaultKind::WireBreak => "wire_break",
+                    FaultKind::Short => "short",
+                };
+                write!(f, "inject fault {sensor} {kind}")
+            }
+            TimelineAction::SeizeValve { valve } => write!(f, "inject fault {valve} seized"),
+            TimelineAction::ClearFault { name } => write!(f, "clear fault {name}"),
+            TimelineAction::ClearFaults => write!(f, "clear faults"),
+            TimelineAction::ImpairNetwork(impairment) => {
+                write!(f, "impair network")?;
+                for (key, value) in impairment.settings() {
+                    if value != 0.0 {
+                        write!(f, " {key}={value}")?;
+                    }
+                }
+                Ok(())
+            }
+            TimelineAction::RestoreNetwork => write!(f, "restore network"),
+        }
+    }
+}
+
+impl FromStr for TimelineEntry {
+    type Err = anyhow::Error;
+
+    fn from_str(entry: &str) -> anyhow::Result<Self> {
+        let (time, action) = entry
+            .trim()
+            .strip_prefix("at ")
+            .and_then(|entry| entry.split_once(':'))
+            .ok_or_else(|| anyhow!("Expected \"at <seconds>s: <action>\", got {entry}"))?;
+
+        let time = time.trim();
+        let at: f32 = time
+            .strip_suffix('s')
+            .unwrap_or(time)
+            .parse()
+            .with_context(|| format!("Invalid time {time}"))?;
+        if !at.is_finite() || at < 0.0 {
+            bail!("Invalid time {time}");
+        }
+
+        Ok(Self {
+            at,
+            action: action.trim().parse()?,
+        })
+    }
+}
+
+impl fmt::Display for TimelineEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at {}s: {}", self.at, self.action)
+    }
+}
+
+impl TryFrom<String> for TimelineEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(entry: String) -> anyhow::Result<Self> {
+        entry.parse()
+    }
+}
+
+impl From<TimelineEntry> for String {
+    fn from(entry: TimelineEntry) -> Self {
+        entry.to_string()
+    }
+}
+
+// >>> Resources <<<
+/// Progress through the running scenario's timeline.
+#[derive(Resource, Default)]
+pub struct TimelineState {
+    entries: Vec<TimelineEntry>, // Ordered by time
+    next: usize,
+    pub elapsed: f32,
+}
+
+impl TimelineState {
+    pub fn new(mut entries: Vec<TimelineEntry>) -> Self {
+        entries.sort_by(|a, b| a.at.total_cmp(&b.at));
+        Self {
+            entries,
+            next: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.entries.len()
+    }
+
+    fn restart(&mut self) {
+        self.next = 0;
+        self.elapsed = 0.0;
+    }
+}
+
+// >>> Systems <<<
+/// Restarts the timeline whenever a scenario is loaded or the simulation is reset.
+pub fn load_timeline(
+    scenario: Option<Res<Scenario>>,
+    mut reset_events: EventReader<ResetSimulation>,
+    mut timeline: ResMut<TimelineState>,
+) {
+    if let Some(scenario) = scenario.filter(|scenario| scenario.is_changed()) {
+        *timeline = TimelineState::new(scenario.timeline.clone());
+        reset_events.clear();
+    } else if reset_events.read().count() > 0 {
+        timeline.restart();
+    }
+}
+
+fn apply_action(
+    action: &TimelineAction,
+    tags: &TagRegistry,
+    modbus_state: &ModbusState,
+    faults: &mut SensorFaults,
+    valve_faults: &mut ValveFaults,
+    network: Option<&mut NetworkImpairment>,
+) {
+    match action {
+        TimelineAction::Set { tag, value } => match tags.get(tag) {
+            Some(tag)
+                if tags.write_value(
+                    modbus_state,
+                    tag.kind,
+                    tag.address,
+                    *value,
+                    &WriteOrigin::logic("timeline"),
+                ) => {}
+            Some(_) => warn!("Timeline cannot write tag {tag}"),
+            None => warn!("Timeline references unknown tag {tag}"),
+        },
+        TimelineAction::InjectFault { sensor, kind } => faults.inject(sensor, *kind),
+        TimelineAction::SeizeValve { valve } => valve_faults.seize(valve),
+        TimelineAction::ClearFault { name } => {
+            faults.clear(name);
+            valve_faults.free(name);
+        }
+        TimelineAction::ClearFaults => {
+            faults.clear_all();
+            valve_faults.clear_all();
+        }
+        TimelineAction::ImpairNetwork(impairment) => match network {
+            Some(network) => *network = impairment.clone(),
+            None => warn!("Timeline cannot impair the network without a scenario"),
+        },
+        TimelineAction::RestoreNetwork => {
+            if let Some(network) = network {
+                *network = NetworkImpairment::default();
+            }
+        }
+    }
+}
+
+pub fn run_timeline(
+    time: Res<SimTime>,
+    mut timeline: ResMut<TimelineState>,
+    tags: Res<TagRegistry>,
+    modbus_state: Res<ModbusState>,
+    mut faults: ResMut<SensorFaults>,
+    mut valve_faults: ResMut<ValveFaults>,
+    mut network: Option<ResMut<NetworkImpairment>>,
+) {
+    if timeline.is_finished() {
+        return;
+    }
+
+    timeline.elapsed += time.delta_secs();
+
+    while let Some(entry) = timeline.entries.get(timeline.next).cloned() {
+        if entry.at > timeline.elapsed {
+            break;
+        }
+
+        info!("Timeline: {}", entry);
+        apply_action(
+            &entry.action,
+            &tags,
+            &modbus_state,
+            &mut faults,
+            &mut valve_faults,
+            network.as_deref_mut(),
+        );
+        timeline.next += 1;
+    }
+}
+
+// >>> Plugin <<<
+/// Runs the actions scripted in the scenario's `timeline` at their set times,
+/// so an exercise can evolve without an instructor stepping in.
+pub struct TimelinePlugin;
+
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimelineState>()
+            .add_systems(FixedUpdate, (load_timeline, run_timeline).chain());
+    }
+}
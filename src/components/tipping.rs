@@ -0,0 +1,79 @@
+// tipping.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    alarm::{Alarm, AlarmEvent},
+    bottle::{Bottle, BottlePhysics},
+};
+
+// >>> Components <<<
+/// Marks a bottle leaning further from upright than the tip angle.
+#[derive(Component)]
+pub struct Tipped;
+
+// >>> Events <<<
+/// A bottle tipped over.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BottleTipped {
+    pub bottle: Entity,
+}
+
+// >>> Systems <<<
+/// Marks bottles leaning past the tip angle, and unmarks those set upright
+/// again, e.g. by a gantry.
+pub fn detect_tipped_bottles(
+    mut commands: Commands,
+    bottles: Query<(Entity, &Transform, Has<Tipped>), With<Bottle>>,
+    physics: Res<BottlePhysics>,
+    mut tipped_events: EventWriter<BottleTipped>,
+) {
+    let tip_angle = physics.tip_angle.to_radians();
+
+    for (entity, transform, was_tipped) in bottles.iter() {
+        let lean = (transform.rotation * Vec3::Y).angle_between(Vec3::Y);
+        let is_tipped = lean > tip_angle;
+        if is_tipped == was_tipped {
+            continue;
+        }
+
+        if is_tipped {
+            commands.entity(entity).insert(Tipped);
+            tipped_events.write(BottleTipped { bottle: entity });
+            warn!("Bottle tipped over");
+        } else {
+            commands.entity(entity).remove::<Tipped>();
+        }
+    }
+}
+
+pub fn monitor_tipping_alarms(
+    tipped: Query<(), (With<Bottle>, With<Tipped>)>,
+    mut alarm_events: EventWriter<AlarmEvent>,
+    mut was_tipped: Local<bool>,
+) {
+    let is_tipped = !tipped.is_empty();
+    if *was_tipped != is_tipped {
+        *was_tipped = is_tipped;
+        alarm_events.write(if is_tipped {
+            AlarmEvent::Raise(Alarm::BottleTipped)
+        } else {
+            AlarmEvent::Clear(Alarm::BottleTipped)
+        });
+    }
+}
+
+// >>> Plugin <<<
+/// Detects bottles tipping over and raises an alarm while any lies on the line.
+pub struct TippingPlugin;
+
+impl Plugin for TippingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BottleTipped>().add_systems(
+            FixedUpdate,
+            (detect_tipped_bottles, monitor_tipping_alarms).chain(),
+        );
+    }
+}
@@ -0,0 +1,361 @@
+// tracking.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+
+use super::{
+    barcode::{BarcodeScanner, SERIAL_WORDS},
+    bottle::{Bottle, BottlePosition, Capped, FillLevel, SerialNumber},
+    diverter::Diverted,
+    hmi::HmiPanel,
+    labeler::Label,
+    modbus::ModbusState,
+    simulation::ResetSimulation,
+    station::StationId,
+    tags::DeviceName,
+    tipping::Tipped,
+};
+
+// >>> Constants <<<
+pub const STATUS_OCCUPIED: u16 = 1 << 0;
+pub const STATUS_FILLED: u16 = 1 << 1;
+pub const STATUS_CAPPED: u16 = 1 << 2;
+pub const STATUS_LABELED: u16 = 1 << 3;
+pub const STATUS_REJECTED: u16 = 1 << 4;
+pub const STATUS_TIPPED: u16 = 1 << 5;
+
+const TRACKING_ZONE_COLOR: Color = Color::srgba(0.9, 0.8, 0.2, 0.15);
+const TRACKING_PANEL_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+const TRACKING_FONT_SIZE: f32 = 14.0;
+
+// >>> Components <<<
+/// The bottle tracked in a zone, with its attributes as status bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackedBottle {
+    pub serial: u32,
+    pub status: u16,
+}
+
+/// Stretch of the line whose occupant is tracked, the reference a PLC's
+/// shift register tracking is checked against.
+///
+/// The occupant is the bottle furthest along the line whose centre is inside
+/// the zone. Its serial is published across `SERIAL_WORDS` input registers
+/// from `serial_input_address`, high word first, and its attributes as the
+/// `STATUS_*` bits of the status register; both read 0 while the zone is
+/// empty.
+#[derive(Component)]
+pub struct TrackingZone {
+    pub serial_input_address: u16,
+    pub status_input_address: u16,
+    pub size: Vec2,
+    pub occupant: Option<TrackedBottle>,
+}
+
+#[derive(Component)]
+pub struct TrackingPanel;
+
+#[derive(Component)]
+pub struct TrackingPanelText;
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct TrackingZoneBundle {
+    zone: TrackingZone,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl TrackingZone {
+    /// Creates an empty tracking zone.
+    ///
+    /// # Parameters
+    /// * `serial_input_address` - First input register of the occupant's serial number
+    /// * `status_input_address` - Input register holding the occupant's status bits
+    /// * `position` - Centre of the zone
+    /// * `size` - Size of the zone
+    ///
+    /// # Return
+    /// The tracking zone bundle
+    pub fn new(
+        serial_input_address: u16,
+        status_input_address: u16,
+        position: Vec2,
+        size: Vec2,
+    ) -> TrackingZoneBundle {
+        TrackingZoneBundle {
+            zone: TrackingZone {
+                serial_input_address,
+                status_input_address,
+                size,
+                occupant: None,
+            },
+            sprite: Sprite::from_color(TRACKING_ZONE_COLOR, size),
+            transform: Transform::from_translation(position.extend(-0.2)),
+        }
+    }
+
+    pub fn contains(&self, transform: &Transform, point: Vec2) -> bool {
+        let offset = point - transform.translation.truncate();
+        offset.x.abs() <= self.size.x / 2.0 && offset.y.abs() <= self.size.y / 2.0
+    }
+}
+
+/// Describes a status word, e.g. `filled capped`.
+pub fn status_text(status: u16) -> String {
+    let attributes = [
+        (STATUS_FILLED, "filled"),
+        (STATUS_CAPPED, "capped"),
+        (STATUS_LABELED, "labeled"),
+        (STATUS_REJECTED, "rejected"),
+        (STATUS_TIPPED, "tipped"),
+    ];
+    let text: Vec<&str> = attributes
+        .iter()
+        .filter(|(bit, _)| status & bit != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if text.is_empty() {
+        "empty bottle".to_string()
+    } else {
+        text.join(" ")
+    }
+}
+
+fn bottle_status(
+    fill_level: &FillLevel,
+    label: Option<&Label>,
+    is_capped: bool,
+    is_diverted: bool,
+    is_tipped: bool,
+) -> u16 {
+    let mut status = STATUS_OCCUPIED;
+    if fill_level.0 > 0 {
+        status |= STATUS_FILLED;
+    }
+    if is_capped {
+        status |= STATUS_CAPPED;
+    }
+    if label.is_some_and(Label::is_present) {
+        status |= STATUS_LABELED;
+    }
+    if is_diverted {
+        status |= STATUS_REJECTED;
+    }
+    if is_tipped {
+        status |= STATUS_TIPPED;
+    }
+    status
+}
+
+// >>> Resources <<<
+#[derive(Resource, Default)]
+pub struct TrackingSettings {
+    pub show_panel: bool,
+}
+
+// >>> Systems <<<
+pub fn register_tracking_zones(
+    zones: Query<&TrackingZone, Added<TrackingZone>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for zone in zones.iter() {
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            for i in 0..SERIAL_WORDS {
+                inputs.insert(zone.serial_input_address + i, 0);
+            }
+            inputs.insert(zone.status_input_address, 0);
+        }
+
+        info!(
+            "Registered tracking zone on status input register: {:x?}",
+            zone.status_input_address
+        );
+    }
+}
+
+/// Finds the bottle occupying each zone of its station and its attributes.
+/// A reset empties every zone, as it takes all bottles off the line.
+pub fn update_tracking_zones(
+    mut reset_events: EventReader<ResetSimulation>,
+    mut zones: Query<(&mut TrackingZone, &Transform, &StationId)>,
+    bottles: Query<
+        (
+            &Transform,
+            &BottlePosition,
+            &StationId,
+            &SerialNumber,
+            &FillLevel,
+            Option<&Label>,
+            Has<Capped>,
+            Has<Diverted>,
+            Has<Tipped>,
+        ),
+        With<Bottle>,
+    >,
+) {
+    let is_reset = reset_events.read().count() > 0;
+
+    for (mut zone, zone_transform, zone_station) in zones.iter_mut() {
+        let occupant = bottles
+            .iter()
+            .filter(|_| !is_reset)
+            .filter(|(transform, position, station, ..)| {
+                *station == zone_station
+                    && zone.contains(zone_transform, position.world_center(transform))
+            })
+            .max_by(|(a, a_position, ..), (b, b_position, ..)| {
+                let a = a_position.world_center(a);
+                let b = b_position.world_center(b);
+                a.x.total_cmp(&b.x)
+            })
+            .map(
+                |(_, _, _, serial, fill_level, label, is_capped, is_diverted, is_tipped)| {
+                    TrackedBottle {
+                        serial: serial.0,
+                        status: bottle_status(fill_level, label, is_capped, is_diverted, is_tipped),
+                    }
+                },
+            );
+
+        if zone.occupant != occupant {
+            zone.occupant = occupant;
+        }
+    }
+}
+
+pub fn setup_tracking_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            TrackingPanel,
+            HmiPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(36.0),
+                left: Val::Px(0.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(TRACKING_PANEL_COLOR),
+            Visibility::Hidden,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TrackingPanelText,
+                Text::new(""),
+                TextFont {
+                    font_size: TRACKING_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+/// Lists every zone with its occupant, in line order per station.
+pub fn update_tracking_panel(
+    settings: Res<TrackingSettings>,
+    zones: Query<(&TrackingZone, &Transform, &StationId, Option<&DeviceName>)>,
+    mut panels: Query<&mut Visibility, With<TrackingPanel>>,
+    mut texts: Query<&mut Text, With<TrackingPanelText>>,
+) {
+    for mut visibility in panels.iter_mut() {
+        visibility.set_if_neq(if settings.show_panel {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        });
+    }
+    if !settings.show_panel {
+        return;
+    }
+
+    let mut rows: Vec<_> = zones.iter().collect();
+    rows.sort_by(|(_, a, a_station, _), (_, b, b_station, _)| {
+        a_station
+            .0
+            .cmp(&b_station.0)
+            .then(a.translation.x.total_cmp(&b.translation.x))
+    });
+
+    let lines: Vec<String> = rows
+        .iter()
+        .map(|(zone, _, _, name)| {
+            let name = name.map_or("zone", |name| name.0.as_str());
+            match zone.occupant {
+                Some(bottle) => {
+                    format!("{name}  #{}  {}", bottle.serial, status_text(bottle.status))
+                }
+                None => format!("{name}  -"),
+            }
+        })
+        .collect();
+
+    for mut text in texts.iter_mut() {
+        text.0 = if lines.is_empty() {
+            "No tracking zones".to_string()
+        } else {
+            lines.join("\n")
+        };
+    }
+}
+
+// >>> Input System <<<
+pub fn handle_tracking_input(
+    mut settings: ResMut<TrackingSettings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard.just_pressed(KeyCode::F7) {
+        settings.show_panel = !settings.show_panel;
+        info!("Tracking panel: {}", settings.show_panel);
+    }
+}
+
+// >>> Modbus Synchronization <<<
+pub fn sync_tracking_zones_to_modbus(
+    zones: Query<&TrackingZone, Changed<TrackingZone>>,
+    modbus_state: Res<ModbusState>,
+) {
+    if zones.is_empty() {
+        return;
+    }
+
+    if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+        for zone in zones.iter() {
+            let serial = zone.occupant.map(|bottle| bottle.serial);
+            for (i, word) in BarcodeScanner::serial_words(serial).into_iter().enumerate() {
+                inputs.insert(zone.serial_input_address + i as u16, word);
+            }
+            inputs.insert(
+                zone.status_input_address,
+                zone.occupant.map_or(0, |bottle| bottle.status),
+            );
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Tracks which bottle occupies each zone along the line, on input registers
+/// and a panel toggled with F7.
+pub struct TrackingPlugin;
+
+impl Plugin for TrackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrackingSettings>()
+            .add_systems(Startup, setup_tracking_panel)
+            .add_systems(
+                Update,
+                (handle_tracking_input, update_tracking_panel).chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    register_tracking_zones,
+                    update_tracking_zones,
+                    sync_tracking_zones_to_modbus,
+                )
+                    .chain(),
+            );
+    }
+}
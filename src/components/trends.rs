@@ -0,0 +1,205 @@
+// trends.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::{BTreeMap, VecDeque};
+
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPlugin, egui};
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+
+use super::{modbus::ModbusState, simulation::SimTime, tags::TagRegistry};
+
+// >>> Constants <<<
+const DEFAULT_WINDOW: f32 = 30.0; // Seconds shown while live
+const MAX_WINDOW: f32 = 300.0; // Seconds of history kept
+const TREND_PANEL_SIZE: [f32; 2] = [640.0, 320.0];
+
+// >>> Resources <<<
+/// Rolling history of the trended tags, sampled every fixed timestep.
+#[derive(Resource)]
+pub struct Trends {
+    pub selected: Vec<String>,
+    pub window: f32, // Seconds of history shown while live
+    pub is_paused: bool,
+    pub is_visible: bool,
+    history: BTreeMap<String, VecDeque<[f64; 2]>>, // Time and value, by tag
+    reset_view: bool,                              // Back to the live window
+}
+
+impl Trends {
+    pub fn is_selected(&self, name: &str) -> bool {
+        self.selected.iter().any(|selected| selected == name)
+    }
+
+    /// Adds a tag to the chart, or removes it and its history if it already is.
+    pub fn toggle_tag(&mut self, name: &str) {
+        match self.selected.iter().position(|selected| selected == name) {
+            Some(index) => {
+                self.selected.remove(index);
+                self.history.remove(name);
+            }
+            None => self.selected.push(name.to_string()),
+        }
+    }
+
+    /// Samples of a tag, oldest first, as seconds and value.
+    pub fn history(&self, name: &str) -> impl Iterator<Item = [f64; 2]> + '_ {
+        self.history.get(name).into_iter().flatten().copied()
+    }
+}
+
+// >>> Systems <<<
+/// Appends the selected tags' values to their history while the chart is
+/// live, dropping samples older than the longest window.
+pub fn sample_trends(
+    time: Res<SimTime>,
+    mut trends: ResMut<Trends>,
+    tags: Res<TagRegistry>,
+    modbus_state: Res<ModbusState>,
+) {
+    if trends.is_paused {
+        return;
+    }
+
+    let now = time.elapsed_secs_f64();
+    let trends = &mut *trends;
+    for name in &trends.selected {
+        let Some(value) = tags
+            .get(name)
+            .and_then(|tag| tags.tag_value(tag, &modbus_state))
+        else {
+            continue;
+        };
+
+        let samples = trends.history.entry(name.clone()).or_default();
+        // The clock starts over when the simulation is reset
+        if samples.back().is_some_and(|&[time, _]| time > now) {
+            samples.clear();
+        }
+        samples.push_back([now, value as f64]);
+        while samples
+            .front()
+            .is_some_and(|&[time, _]| time < now - MAX_WINDOW as f64)
+        {
+            samples.pop_front();
+        }
+    }
+}
+
+/// Draws the chart window: the tag list to pick from on the left and the
+/// trends of the picked tags on the right.
+pub fn draw_trends(
+    mut contexts: EguiContexts,
+    time: Res<SimTime>,
+    mut trends: ResMut<Trends>,
+    tags: Res<TagRegistry>,
+) {
+    if !trends.is_visible {
+        return;
+    }
+
+    let trends = &mut *trends;
+    let mut is_open = true;
+    egui::Window::new("Trends")
+        .open(&mut is_open)
+        .default_size(TREND_PANEL_SIZE)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                let label = if trends.is_paused { "Resume" } else { "Pause" };
+                if ui.button(label).clicked() {
+                    trends.is_paused = !trends.is_paused;
+                    trends.reset_view |= !trends.is_paused;
+                }
+                ui.add(
+                    egui::Slider::new(&mut trends.window, 1.0..=MAX_WINDOW)
+                        .text("window (s)")
+                        .logarithmic(true),
+                );
+                if trends.is_paused {
+                    ui.label("Drag to pan, scroll to zoom, double-click to fit");
+                }
+            });
+
+            egui::SidePanel::left("trend_tags")
+                .resizable(true)
+                .show_inside(ui, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for tag in tags.iter() {
+                            let mut is_selected = trends.is_selected(&tag.name);
+                            if ui.checkbox(&mut is_selected, &tag.name).changed() {
+                                trends.toggle_tag(&tag.name);
+                            }
+                        }
+                    });
+                });
+
+            // While live the chart follows the latest window; paused, it
+            // holds still for panning and zooming through the history
+            let now = time.elapsed_secs_f64();
+            let start = now - trends.window as f64;
+            let mut plot = Plot::new("trend_plot")
+                .legend(Legend::default())
+                .x_axis_label("time (s)")
+                .allow_drag(trends.is_paused)
+                .allow_zoom(trends.is_paused)
+                .allow_scroll(trends.is_paused);
+            if !trends.is_paused {
+                plot = plot.include_x(start).include_x(now);
+            }
+            if std::mem::take(&mut trends.reset_view) {
+                plot = plot.reset();
+            }
+
+            plot.show(ui, |plot_ui| {
+                for name in &trends.selected {
+                    let points: PlotPoints = trends
+                        .history(name)
+                        .filter(|&[time, _]| trends.is_paused || time >= start)
+                        .collect();
+                    plot_ui.line(Line::new(name.clone(), points));
+                }
+            });
+        });
+
+    if !is_open {
+        trends.is_visible = false;
+    }
+}
+
+// >>> Input System <<<
+pub fn handle_trends_input(keyboard: Res<ButtonInput<KeyCode>>, mut trends: ResMut<Trends>) {
+    if keyboard.just_pressed(KeyCode::KeyT) {
+        trends.is_visible = !trends.is_visible;
+    }
+}
+
+// >>> Plugin <<<
+/// Live trend chart of selected tags, e.g. a conveyor's speed, a tank's level
+/// or a sensor's state, for tuning control loops by eye. T shows or hides
+/// it; pausing freezes the chart so it can be panned and zoomed.
+#[derive(Default)]
+pub struct TrendsPlugin {
+    pub tags: Vec<String>, // Trended from startup
+}
+
+impl Plugin for TrendsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin {
+                enable_multipass_for_primary_context: false,
+            });
+        }
+
+        app.insert_resource(Trends {
+            selected: self.tags.clone(),
+            window: DEFAULT_WINDOW,
+            is_paused: false,
+            is_visible: !self.tags.is_empty(),
+            history: BTreeMap::new(),
+            reset_view: false,
+        })
+        .add_systems(Update, (handle_trends_input, draw_trends).chain())
+        .add_systems(FixedPostUpdate, sample_trends);
+    }
+}
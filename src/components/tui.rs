@@ -0,0 +1,271 @@
+// tui.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event as TermEvent, KeyCode as TermKey, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color as TuiColor, Style, Stylize},
+    text::{Line, Span},
+    widgets::{
+        Block, Row, Table,
+        canvas::{Canvas, Rectangle},
+    },
+};
+
+use super::{
+    bottle::{Bottle, BottlePosition},
+    conveyor::{Conveyor, ConveyorShape, ConveyorState},
+    modbus::ModbusState,
+    overlay::register_line,
+    sensor::Sensor,
+    simulation::WorldBounds,
+    tags::{DeviceName, TagRegistry},
+    valve::{Valve, ValveState},
+};
+
+// >>> Constants <<<
+const REDRAW_INTERVAL: f32 = 0.1; // Seconds between redraws, kept low for SSH links
+const VIEW_HEIGHT: f32 = 720.0; // World height shown, as the plant is laid out for a 1280x720 window
+const LED_ON: &str = "●";
+const LED_OFF: &str = "○";
+
+// >>> Resources <<<
+/// Terminal the frontend draws to, restored to a normal shell when dropped.
+pub struct TuiTerminal(DefaultTerminal);
+
+impl Drop for TuiTerminal {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+/// Redraw pacing and the first row of the register table shown.
+#[derive(Resource)]
+pub struct TuiSettings {
+    timer: Timer,
+    pub register_scroll: usize,
+}
+
+/// What one redraw shows, gathered from the world before the terminal is
+/// drawn.
+struct TuiView {
+    bounds: [f64; 4], // Left, right, bottom and top of the world shown
+    conveyors: Vec<(Rect, bool)>,
+    valves: Vec<(Vec2, bool)>,
+    bottles: Vec<Vec2>,
+    sensors: Vec<(String, Vec2, bool)>,
+    registers: Vec<(String, String)>,
+    register_scroll: usize,
+}
+
+// >>> Systems <<<
+/// Redraws the schematic line view, the sensor LEDs and the register table.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_tui(
+    mut terminal: NonSendMut<TuiTerminal>,
+    time: Res<Time<Real>>,
+    mut settings: ResMut<TuiSettings>,
+    bounds: Res<WorldBounds>,
+    conveyors: Query<(&Transform, &Sprite, &ConveyorShape, &ConveyorState), With<Conveyor>>,
+    valves: Query<(&Transform, &ValveState), With<Valve>>,
+    bottles: Query<(&Transform, &BottlePosition), With<Bottle>>,
+    sensors: Query<(&Sensor, &Transform, Option<&DeviceName>)>,
+    tags: Res<TagRegistry>,
+    modbus_state: Res<ModbusState>,
+) {
+    if !settings.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let discrete_inputs = modbus_state
+        .discrete_inputs
+        .lock()
+        .map(|inputs| inputs.clone())
+        .unwrap_or_default();
+
+    let view = TuiView {
+        bounds: [
+            bounds.min_x as f64,
+            bounds.max_x as f64,
+            -VIEW_HEIGHT as f64 / 2.0,
+            VIEW_HEIGHT as f64 / 2.0,
+        ],
+        conveyors: conveyors
+            .iter()
+            .flat_map(|(transform, sprite, shape, state)| {
+                let center = transform.translation.truncate();
+                let pieces = match shape {
+                    ConveyorShape::Straight => {
+                        let size = sprite.custom_size.unwrap_or_default();
+                        vec![Rect::from_center_size(center, size)]
+                    }
+                    ConveyorShape::Curved { .. } => shape
+                        .segments()
+                        .into_iter()
+                        .map(|(offset, _, size)| Rect::from_center_size(center + offset, size))
+                        .collect(),
+                };
+                pieces.into_iter().map(|piece| (piece, state.is_running))
+            })
+            .collect(),
+        valves: valves
+            .iter()
+            .map(|(transform, state)| (transform.translation.truncate(), state.is_open))
+            .collect(),
+        bottles: bottles
+            .iter()
+            .map(|(transform, position)| position.world_center(transform))
+            .collect(),
+        sensors: sensors
+            .iter()
+            .map(|(sensor, transform, name)| {
+                let name = name.map_or(sensor.sensor_tag.clone(), |name| name.0.clone());
+                let is_on = discrete_inputs
+                    .get(&sensor.modbus_address)
+                    .copied()
+                    .unwrap_or_default();
+                (name, transform.translation.truncate(), is_on)
+            })
+            .collect(),
+        registers: tags
+            .iter()
+            .map(|tag| {
+                let mut line = register_line(tag, &modbus_state);
+                if let Some(scaled) = tags.scaled_line(tag, &modbus_state) {
+                    line += &format!(" ({scaled})");
+                }
+                (tag.name.clone(), line)
+            })
+            .collect(),
+        register_scroll: settings.register_scroll,
+    };
+
+    if let Err(err) = terminal.0.draw(|frame| render(frame, &view)) {
+        warn!("Failed to draw the terminal view: {err}");
+    }
+}
+
+fn render(frame: &mut Frame, view: &TuiView) {
+    let [line_area, bottom_area] =
+        Layout::vertical([Constraint::Percentage(60), Constraint::Fill(1)]).areas(frame.area());
+    let [sensor_area, register_area] =
+        Layout::horizontal([Constraint::Length(32), Constraint::Fill(1)]).areas(bottom_area);
+
+    let [left, right, bottom, top] = view.bounds;
+    let canvas = Canvas::default()
+        .block(Block::bordered().title(" Line  (q quits, PgUp/PgDn scroll registers) "))
+        .x_bounds([left, right])
+        .y_bounds([bottom, top])
+        .paint(|ctx| {
+            for (piece, is_running) in &view.conveyors {
+                ctx.draw(&Rectangle {
+                    x: piece.min.x as f64,
+                    y: piece.min.y as f64,
+                    width: piece.width() as f64,
+                    height: piece.height() as f64,
+                    color: if *is_running {
+                        TuiColor::Green
+                    } else {
+                        TuiColor::Red
+                    },
+                });
+            }
+            ctx.layer();
+            for position in &view.bottles {
+                ctx.print(position.x as f64, position.y as f64, "o".white());
+            }
+            for (position, is_open) in &view.valves {
+                let marker = if *is_open {
+                    "V".cyan()
+                } else {
+                    "V".dark_gray()
+                };
+                ctx.print(position.x as f64, position.y as f64, marker);
+            }
+            for (_, position, is_on) in &view.sensors {
+                ctx.print(position.x as f64, position.y as f64, led(*is_on));
+            }
+        });
+    frame.render_widget(canvas, line_area);
+
+    let sensor_rows = view.sensors.iter().map(|(name, _, is_on)| {
+        Row::new(vec![Line::from(vec![
+            led(*is_on),
+            format!(" {name}").into(),
+        ])])
+    });
+    frame.render_widget(
+        Table::new(sensor_rows, [Constraint::Fill(1)]).block(Block::bordered().title(" Sensors ")),
+        sensor_area,
+    );
+
+    let register_rows = view
+        .registers
+        .iter()
+        .skip(view.register_scroll)
+        .map(|(name, value)| Row::new(vec![name.clone(), value.clone()]));
+    frame.render_widget(
+        Table::new(
+            register_rows,
+            [Constraint::Percentage(50), Constraint::Fill(1)],
+        )
+        .header(Row::new(vec!["Tag", "Register"]).style(Style::new().bold()))
+        .block(Block::bordered().title(" Registers ")),
+        register_area,
+    );
+}
+
+fn led(is_on: bool) -> Span<'static> {
+    if is_on {
+        LED_ON.green()
+    } else {
+        LED_OFF.dark_gray()
+    }
+}
+
+// >>> Input System <<<
+/// Reads the terminal's keys without blocking: q or Esc quits, Page Up and
+/// Page Down scroll the register table.
+pub fn handle_tui_input(mut settings: ResMut<TuiSettings>, mut exit_events: EventWriter<AppExit>) {
+    while event::poll(Duration::ZERO).unwrap_or(false) {
+        let Ok(TermEvent::Key(key)) = event::read() else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            TermKey::Char('q') | TermKey::Esc => {
+                exit_events.write(AppExit::Success);
+            }
+            TermKey::PageDown => settings.register_scroll += 10,
+            TermKey::PageUp => {
+                settings.register_scroll = settings.register_scroll.saturating_sub(10);
+            }
+            _ => {}
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Terminal frontend for running the simulation headless, e.g. over SSH on a
+/// server without a GPU: a schematic view of the line, the sensors' LEDs and
+/// every register, redrawn in place.
+pub struct TuiPlugin;
+
+impl Plugin for TuiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_non_send_resource(TuiTerminal(ratatui::init()))
+            .insert_resource(TuiSettings {
+                timer: Timer::from_seconds(REDRAW_INTERVAL, TimerMode::Repeating),
+                register_scroll: 0,
+            })
+            .add_systems(Update, (handle_tui_input, draw_tui).chain());
+    }
+}
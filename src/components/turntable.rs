@@ -0,0 +1,253 @@
+// turntable.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use super::{modbus::ModbusState, physics::SurfaceMotion, simulation::SimTime};
+
+// >>> Constants <<<
+const TABLE_COLOR: Color = Color::srgb(0.45, 0.45, 0.5);
+const MARK_COLOR: Color = Color::srgb(0.95, 0.7, 0.1);
+const MARK_SIZE: Vec2 = Vec2::new(12.0, 4.0);
+
+// >>> Components <<<
+/// Rotary indexing table turning the items on it through `positions` evenly
+/// spaced stations.
+///
+/// Each rising edge of the index coil while the table is in position turns it
+/// one station clockwise, so items on top move downstream. The in-position
+/// input is off while it turns, and the position register holds the station
+/// it last stopped at, counted from 0.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Turntable {
+    pub index_coil_address: u16,
+    pub in_position_discrete_address: u16,
+    pub position_input_address: u16,
+    pub positions: u16,
+    pub radius: f32,
+    pub speed: f32, // Radians per second while indexing
+    pub center: Vec2,
+    pub position: u16,
+    pub angle: f32, // Radians turned clockwise from station 0
+    travel: f32,    // Radians left to the next station
+    is_index_on: bool,
+}
+
+impl Turntable {
+    pub fn is_in_position(&self) -> bool {
+        self.travel == 0.0
+    }
+
+    /// Radians between neighbouring stations.
+    pub fn step(&self) -> f32 {
+        TAU / self.positions.max(1) as f32
+    }
+
+    fn surface_motion(&self) -> SurfaceMotion {
+        SurfaceMotion::Rotating {
+            center: self.center,
+            angular_speed: if self.is_in_position() {
+                0.0
+            } else {
+                -self.speed
+            },
+        }
+    }
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct TurntableBundle {
+    turntable: Turntable,
+    surface: SurfaceMotion,
+    collider: Collider,
+    active_hooks: ActiveHooks,
+    transform: Transform,
+}
+
+impl Turntable {
+    /// Creates a table standing in position at station 0.
+    ///
+    /// # Parameters
+    /// * `index_coil_address` - Coil whose rising edge turns the table one station
+    /// * `in_position_discrete_address` - Discrete input set while the table stands at a station
+    /// * `position_input_address` - Input register with the station last stopped at
+    /// * `positions` - Number of stations around the table
+    /// * `radius` - Radius of the disc
+    /// * `speed` - Turning speed in radians per second
+    /// * `center` - Centre of the disc
+    ///
+    /// # Return
+    /// The turntable bundle
+    pub fn new(
+        index_coil_address: u16,
+        in_position_discrete_address: u16,
+        position_input_address: u16,
+        positions: u16,
+        radius: f32,
+        speed: f32,
+        center: Vec2,
+    ) -> TurntableBundle {
+        TurntableBundle {
+            turntable: Turntable {
+                index_coil_address,
+                in_position_discrete_address,
+                position_input_address,
+                positions: positions.max(1),
+                radius,
+                speed,
+                center,
+                position: 0,
+                angle: 0.0,
+                travel: 0.0,
+                is_index_on: false,
+            },
+            surface: SurfaceMotion::default(),
+            collider: Collider::ball(radius),
+            active_hooks: ActiveHooks::MODIFY_SOLVER_CONTACTS,
+            transform: Transform::from_translation(center.extend(0.0)),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_turntables(
+    mut commands: Commands,
+    turntables: Query<(Entity, &Turntable), Added<Turntable>>,
+    modbus_state: Res<ModbusState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, turntable) in turntables.iter() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(turntable.index_coil_address, turntable.is_index_on);
+        }
+        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+            discretes.insert(
+                turntable.in_position_discrete_address,
+                turntable.is_in_position(),
+            );
+        }
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(turntable.position_input_address, turntable.position);
+        }
+
+        // A mark on the rim at each station turns with the disc
+        commands
+            .entity(entity)
+            .insert((
+                Mesh2d(meshes.add(Circle::new(turntable.radius))),
+                MeshMaterial2d(materials.add(ColorMaterial::from(TABLE_COLOR))),
+            ))
+            .with_children(|parent| {
+                for station in 0..turntable.positions {
+                    // Station 0 at the top, the others clockwise from it
+                    let angle = FRAC_PI_2 - station as f32 * turntable.step();
+                    let rim = Vec2::from_angle(angle) * (turntable.radius - MARK_SIZE.x / 2.0);
+                    parent.spawn((
+                        Sprite::from_color(MARK_COLOR, MARK_SIZE),
+                        Transform::from_translation(rim.extend(0.1))
+                            .with_rotation(Quat::from_rotation_z(angle)),
+                    ));
+                }
+            });
+
+        info!(
+            "Registered turntable on index coil: {:x?}",
+            turntable.index_coil_address
+        );
+    }
+}
+
+/// Turns indexing tables towards their next station, stopping exactly on it.
+pub fn index_turntables(
+    time: Res<SimTime>,
+    mut turntables: Query<(&mut Turntable, &mut SurfaceMotion, &mut Transform)>,
+) {
+    for (mut turntable, mut surface, mut transform) in turntables.iter_mut() {
+        if !turntable.is_in_position() {
+            let turn = (turntable.speed * time.delta_secs()).min(turntable.travel);
+            turntable.travel -= turn;
+            turntable.angle = (turntable.angle + turn).rem_euclid(TAU);
+            transform.rotation = Quat::from_rotation_z(-turntable.angle);
+
+            if turntable.is_in_position() {
+                turntable.position = (turntable.position + 1) % turntable.positions;
+                // Settle on the station itself rather than drifting by rounding
+                turntable.angle = turntable.position as f32 * turntable.step();
+                transform.rotation = Quat::from_rotation_z(-turntable.angle);
+                info!("Turntable indexed to position: {}", turntable.position);
+            }
+        }
+        surface.set_if_neq(turntable.surface_motion());
+    }
+}
+
+// >>> Modbus Synchronization <<<
+/// Starts an index on each rising edge of the index coil, ignoring edges
+/// while the table is still turning.
+pub fn sync_modbus_to_turntables(
+    mut turntables: Query<&mut Turntable>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Ok(coils) = modbus_state.coils.lock() else {
+        return;
+    };
+
+    for mut turntable in turntables.iter_mut() {
+        let Some(&coil_state) = coils.get(&turntable.index_coil_address) else {
+            continue;
+        };
+        if turntable.is_index_on == coil_state {
+            continue;
+        }
+
+        turntable.is_index_on = coil_state;
+        if coil_state && turntable.is_in_position() {
+            turntable.travel = turntable.step();
+            info!("Turntable indexing from position: {}", turntable.position);
+        }
+    }
+}
+
+pub fn sync_turntables_to_modbus(
+    turntables: Query<&Turntable, Changed<Turntable>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for turntable in turntables.iter() {
+        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+            discretes.insert(
+                turntable.in_position_discrete_address,
+                turntable.is_in_position(),
+            );
+        }
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(turntable.position_input_address, turntable.position);
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Rotary indexing tables, carrying the items on them around through the
+/// physics hooks' moving surfaces.
+pub struct TurntablePlugin;
+
+impl Plugin for TurntablePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Turntable>().add_systems(
+            FixedUpdate,
+            (
+                register_turntables,
+                sync_modbus_to_turntables,
+                index_turntables,
+                sync_turntables_to_modbus,
+            )
+                .chain(),
+        );
+    }
+}
@@ -2,17 +2,40 @@
 // Copyright (C) 2025 deciphr
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use super::modbus::ModbusState;
+use super::{
+    actuator::{ValveActuator, ValveTravel, is_passing},
+    alarm::{Alarm, AlarmEvent},
+    arbitration::WriteOrigin,
+    bottle::{Bottle, BottlePosition, BottleSpec, FillLevel},
+    conveyor::{Conveyor, ConveyorShape, belt_surface_height},
+    gamepad,
+    modbus::{ModbusState, RegisterBanks},
+    mode::{OperatingMode, local_control_allowed},
+    simulation::{SimTime, WorldBounds},
+    tag_sync::sync_tags,
+    tags::{TagKind, TagRegistry},
+};
 use std::time::Duration;
 
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 // >>> Constants <<<
 const DEFAULT_SPAWN_RATE: f32 = 1.0;
+const MIN_OPENING: f32 = 0.01; // Smallest fraction open that still passes liquid
+const BALL_POOL_SIZE: usize = 100; // Balls pre-warmed at startup, matching the default ball limit
+const BALL_LIFETIME: f32 = 30.0;
+const BALL_SETTLE_SPEED: f32 = 15.0; // Relative speed below which a ball has landed in a bottle
+const STREAM_WIDTH: f32 = 6.0; // Stream width per ball per second of set flow
+const STREAM_WIDTH_RANGE: (f32, f32) = (2.0, 16.0);
+const STREAM_Z: f32 = 0.5; // In front of the bottles it pours into
+pub const BALL_RADIUS: f32 = 10.0;
+pub const WATER_COLOR: Color = Color::srgb(0.3, 0.7, 1.0);
 
 // >>> Components <<<
-#[derive(Component)]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct Valve {
     pub coil_address: u16,
     pub holding_address: u16,
@@ -21,14 +44,90 @@ pub struct Valve {
 #[derive(Component)]
 pub struct ValvePosition(pub Vec2);
 
-#[derive(Component)]
+/// Analog control of a valve's opening from a holding register in percent,
+/// in place of its open coil. The set flow scales with the opening: a valve
+/// half open dispenses at half its rate and pours half as wide a stream.
+#[derive(Component, Debug, Clone)]
+pub struct ProportionalValve {
+    pub opening_holding_address: u16,
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct Ball;
 
-// >>> Resources <<<
-#[derive(Resource)]
+/// The valve a ball in play came from.
+#[derive(Component)]
+pub struct DispensedBy(pub Entity);
+
+/// How a valve's liquid is simulated and drawn.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiquidModel {
+    /// Discrete balls, each simulated until it settles in a bottle.
+    #[default]
+    Balls,
+    /// A continuous stream down to the bottle below, filling it directly.
+    /// Liquid with no bottle below still falls as balls, so spills show.
+    Stream,
+    /// The stream drawn over simulated balls.
+    StreamWithBalls,
+}
+
+impl LiquidModel {
+    pub fn has_stream(&self) -> bool {
+        matches!(self, LiquidModel::Stream | LiquidModel::StreamWithBalls)
+    }
+}
+
+/// Sprite drawing a valve's stream, a child of the valve.
+#[derive(Component)]
+pub struct LiquidStream;
+
+/// What happens once a ball limit is reached.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BallLimitPolicy {
+    /// Keep spawning and take the oldest balls out of play, loose balls
+    /// before those resting in a bottle.
+    #[default]
+    DespawnOldest,
+    /// Stop spawning until balls leave play.
+    RefuseSpawn,
+}
+
+/// Limits on the balls a valve keeps in play.
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BallLimit {
+    pub max_balls: Option<usize>, // Balls from this valve in play, within the plant-wide limit
+    pub lifetime: f32,            // Seconds before a ball is taken out of play
+    pub policy: BallLimitPolicy,
+}
+
+impl Default for BallLimit {
+    fn default() -> Self {
+        Self {
+            max_balls: None,
+            lifetime: BALL_LIFETIME,
+            policy: BallLimitPolicy::default(),
+        }
+    }
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct ValveState {
     pub is_open: bool,
-    pub spawn_rate: f32,
+    pub spawn_rate: f32, // Seconds between balls when fully open
+    pub opening: f32,    // Fraction open, always 1 for an on/off valve
+}
+
+impl ValveState {
+    /// Seconds between balls at the current opening.
+    pub fn ball_interval(&self) -> f32 {
+        self.spawn_rate / self.opening.max(MIN_OPENING)
+    }
 }
 
 impl Default for ValveState {
@@ -36,27 +135,90 @@ impl Default for ValveState {
         Self {
             is_open: false,
             spawn_rate: DEFAULT_SPAWN_RATE,
+            opening: 1.0,
         }
     }
 }
 
-#[derive(Resource)]
+#[derive(Component)]
 pub struct BallSpawner {
     timer: Timer,
+    pub dispensed: u32,   // Balls spawned since the valve last opened
+    pub is_blocked: bool, // Held by an interlock, e.g. an empty supply tank
 }
 
 impl Default for BallSpawner {
     fn default() -> Self {
         Self {
             timer: Timer::from_seconds(DEFAULT_SPAWN_RATE, TimerMode::Repeating),
+            dispensed: 0,
+            is_blocked: false,
         }
     }
 }
 
+/// Measures the rate balls actually leave the valve at, which falls short of
+/// the set rate while the valve is closed or blocked.
+#[derive(Component, Default)]
+pub struct FlowMeter {
+    since_last: Option<f32>, // Seconds since the last ball
+    interval: Option<f32>,   // Seconds between the last two balls
+}
+
+impl FlowMeter {
+    /// Balls per second, falling away once the next ball is overdue.
+    pub fn rate(&self) -> f32 {
+        match (self.interval, self.since_last) {
+            (Some(interval), Some(since_last)) => 1.0 / interval.max(since_last),
+            _ => 0.0,
+        }
+    }
+}
+
+// >>> Events <<<
+/// A valve released a ball.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BallDispensed {
+    pub valve: Entity,
+}
+
+// >>> Resources <<<
+/// Balls taken out of play, kept with their colliders disabled for reuse.
+#[derive(Resource, Default)]
+pub struct BallPool {
+    free: Vec<Entity>,
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+}
+
+impl BallPool {
+    /// Takes a ball out of play and parks it in the pool.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        commands
+            .entity(entity)
+            .remove::<(Ball, BallLifetime, DispensedBy)>()
+            .insert((RigidBodyDisabled, ColliderDisabled, Visibility::Hidden));
+        self.free.push(entity);
+    }
+
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+}
+
 // >>> Bundle <<<
 #[derive(Bundle)]
 pub struct ValveBundle {
     valve: Valve,
+    state: ValveState,
+    spawner: BallSpawner,
+    flow_meter: FlowMeter,
+    ball_limit: BallLimit,
+    liquid_model: LiquidModel,
     position: ValvePosition,
     sprite: Sprite,
     transform: Transform,
@@ -69,6 +231,11 @@ impl Valve {
                 coil_address,
                 holding_address,
             },
+            state: ValveState::default(),
+            spawner: BallSpawner::default(),
+            flow_meter: FlowMeter::default(),
+            ball_limit: BallLimit::default(),
+            liquid_model: LiquidModel::default(),
             position: ValvePosition(position),
             sprite: Sprite::from_color(
                 Color::srgb(0.8, 0.2, 0.2), // Red when closed (default)
@@ -79,131 +246,456 @@ impl Valve {
     }
 }
 
+impl ValveBundle {
+    /// Sets the valve's ball count limit, ball lifetime and limit policy.
+    pub fn with_ball_limit(mut self, ball_limit: BallLimit) -> Self {
+        self.ball_limit = ball_limit;
+        self
+    }
+
+    /// Sets whether the valve pours balls, a stream or both.
+    pub fn with_liquid_model(mut self, liquid_model: LiquidModel) -> Self {
+        self.liquid_model = liquid_model;
+        self
+    }
+}
+
 // >>> Input System <<<
+/// Keyboard and gamepad controls drive every station's valves, writing their
+/// coils as the HMI.
 pub fn handle_valve_input(
-    mut valve_state: ResMut<ValveState>,
+    mut valves: Query<(&Valve, &mut ValveState, Option<&ProportionalValve>)>,
     keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mode: Option<Res<OperatingMode>>,
+    modbus_state: Res<ModbusState>,
 ) {
-    if keyboard.just_pressed(KeyCode::KeyV) {
-        valve_state.is_open = !valve_state.is_open;
-        println!("Valve manually toggled to: {}", valve_state.is_open);
+    if (keyboard.just_pressed(KeyCode::KeyV)
+        || gamepad::just_pressed(&gamepads, gamepad::VALVE_TOGGLE))
+        && local_control_allowed(mode.as_deref())
+    {
+        let origin = WriteOrigin::hmi("local controls");
+        for (valve, mut valve_state, proportional) in valves.iter_mut() {
+            valve_state.is_open = !valve_state.is_open;
+            println!("Valve manually toggled to: {}", valve_state.is_open);
+            let is_open = valve_state.is_open as u16;
+            modbus_state.write(TagKind::Coil, valve.coil_address, is_open, &origin);
+            // A proportional valve toggles between shut and fully open
+            if let Some(proportional) = proportional {
+                valve_state.opening = 1.0;
+                let opening = is_open * 100;
+                let address = proportional.opening_holding_address;
+                modbus_state.write(TagKind::HoldingRegister, address, opening, &origin);
+            }
+        }
     }
 }
 
 // >>> Modbus Synchronization <<<
-pub fn sync_valves_to_modbus(
-    valves: Query<&Valve>,
-    valve_state: Res<ValveState>,
-    modbus_state: Res<ModbusState>,
+/// Publishes a valve's open state, spawn rate and, for a proportional valve,
+/// its opening, as part of the tag-sync stage.
+pub fn write_valve_registers(
+    banks: &mut RegisterBanks,
+    tags: &TagRegistry,
+    valve: &Valve,
+    valve_state: &ValveState,
+    proportional: Option<&ProportionalValve>,
 ) {
-    if valve_state.is_changed() {
-        if let Ok(mut coils) = modbus_state.coils.lock() {
-            for valve in valves.iter() {
-                coils.insert(valve.coil_address, valve_state.is_open);
-            }
-        }
-
-        if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
-            for valve in valves.iter() {
-                holdings.insert(valve.holding_address, valve_state.spawn_rate as u16);
-            }
-        }
+    banks.coils.insert(valve.coil_address, valve_state.is_open);
+    tags.insert_value(
+        &mut banks.holding_registers,
+        TagKind::HoldingRegister,
+        valve.holding_address,
+        valve_state.spawn_rate,
+    );
+    if let Some(proportional) = proportional {
+        let opening = if valve_state.is_open {
+            valve_state.opening * 100.0
+        } else {
+            0.0
+        };
+        tags.insert_value(
+            &mut banks.holding_registers,
+            TagKind::HoldingRegister,
+            proportional.opening_holding_address,
+            opening,
+        );
     }
 }
 
-pub fn sync_modbus_to_valves(
-    valves: Query<&Valve>,
-    mut valve_state: ResMut<ValveState>,
-    modbus_state: Res<ModbusState>,
+/// Follows the open coil of an on/off valve or the opening register of a
+/// proportional one, whose open coil then only reports whether it passes, as
+/// part of the tag-sync stage.
+pub fn read_valve_registers(
+    banks: &mut RegisterBanks,
+    tags: &TagRegistry,
+    valve: &Valve,
+    valve_state: &mut Mut<ValveState>,
+    proportional: Option<&ProportionalValve>,
 ) {
-    if let Ok(coils) = modbus_state.coils.lock() {
-        for valve in valves.iter() {
-            if let Some(&coil_state) = coils.get(&valve.coil_address) {
-                if valve_state.is_open != coil_state {
-                    valve_state.is_open = coil_state;
-                    break;
-                }
-            }
+    let holdings = &banks.holding_registers;
+    let spawn_rate = tags.read_value(holdings, TagKind::HoldingRegister, valve.holding_address);
+    if let Some(spawn_rate) = spawn_rate {
+        if valve_state.spawn_rate != spawn_rate {
+            valve_state.spawn_rate = spawn_rate;
         }
     }
 
-    if let Ok(holdings) = modbus_state.holding_registers.lock() {
-        for valve in valves.iter() {
-            if let Some(&holding_state) = holdings.get(&valve.holding_address) {
-                if valve_state.spawn_rate != holding_state as f32 {
-                    valve_state.spawn_rate = holding_state as f32;
-                    break;
-                }
+    let Some(proportional) = proportional else {
+        if let Some(&coil_state) = banks.coils.get(&valve.coil_address) {
+            if valve_state.is_open != coil_state {
+                valve_state.is_open = coil_state;
             }
         }
+        return;
+    };
+
+    let address = proportional.opening_holding_address;
+    if let Some(percent) = tags.read_value(holdings, TagKind::HoldingRegister, address) {
+        let opening = (percent / 100.0).clamp(0.0, 1.0);
+        let is_open = opening >= MIN_OPENING;
+        if valve_state.is_open != is_open {
+            valve_state.is_open = is_open;
+        }
+        if is_open && valve_state.opening != opening {
+            valve_state.opening = opening;
+        }
     }
+    banks.coils.insert(valve.coil_address, valve_state.is_open);
 }
 
 // >>> Visual System <<<
 pub fn update_valve_visuals(
-    valve_state: Res<ValveState>,
-    mut valves: Query<&mut Sprite, With<Valve>>,
+    mut valves: Query<
+        (&ValveState, Option<&ValveActuator>, &mut Sprite),
+        Or<(Changed<ValveState>, Changed<ValveActuator>)>,
+    >,
 ) {
-    if valve_state.is_changed() {
-        let color = if valve_state.is_open {
+    for (valve_state, actuator, mut sprite) in valves.iter_mut() {
+        let travel = actuator.map(|actuator| actuator.travel);
+        sprite.color = if matches!(travel, Some(ValveTravel::Opening | ValveTravel::Closing)) {
+            Color::srgb(0.9, 0.7, 0.1) // Amber while travelling
+        } else if valve_state.is_open {
             Color::srgb(0.2, 0.8, 0.2) // Green when open
         } else {
             Color::srgb(0.8, 0.2, 0.2) // Red when closed
         };
+    }
+}
 
-        for mut sprite in valves.iter_mut() {
-            sprite.color = color;
+/// Gives each valve pouring a stream its hidden stream sprite.
+pub fn spawn_liquid_streams(
+    mut commands: Commands,
+    valves: Query<(Entity, &LiquidModel), Added<LiquidModel>>,
+) {
+    for (entity, liquid_model) in valves.iter() {
+        if !liquid_model.has_stream() {
+            continue;
+        }
+
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                LiquidStream,
+                Sprite {
+                    color: WATER_COLOR,
+                    custom_size: Some(Vec2::ZERO),
+                    anchor: bevy::sprite::Anchor::TopCenter,
+                    ..default()
+                },
+                Transform::from_xyz(0.0, 0.0, STREAM_Z),
+                Visibility::Hidden,
+            ));
+        });
+    }
+}
+
+/// The bottle whose opening is below a point, and its liquid surface height.
+fn bottle_below<'a>(
+    point: Vec2,
+    bottles: impl Iterator<
+        Item = (
+            Entity,
+            &'a Transform,
+            &'a BottlePosition,
+            &'a BottleSpec,
+            &'a FillLevel,
+        ),
+    >,
+) -> Option<(Entity, f32)> {
+    bottles
+        .filter(|(_, transform, position, spec, _)| {
+            let center = position.world_center(transform);
+            (point.x - center.x).abs() < spec.width / 2.0 && center.y < point.y
+        })
+        .map(|(entity, transform, position, spec, fill_level)| {
+            (entity, position.liquid_surface(transform, spec, fill_level))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+/// Draws each open valve's stream down to the liquid in the bottle below, or
+/// to the belt, as wide as the set flow.
+pub fn update_liquid_streams(
+    valves: Query<
+        (
+            &ValveState,
+            Option<&ValveActuator>,
+            &BallSpawner,
+            &ValvePosition,
+            &Children,
+        ),
+        With<Valve>,
+    >,
+    mut streams: Query<(&mut Sprite, &mut Visibility), With<LiquidStream>>,
+    bottles: Query<(Entity, &Transform, &BottlePosition, &BottleSpec, &FillLevel), With<Bottle>>,
+    conveyors: Query<
+        (&Transform, &Sprite, &ConveyorShape),
+        (With<Conveyor>, Without<LiquidStream>),
+    >,
+    bounds: Res<WorldBounds>,
+) {
+    for (valve_state, actuator, ball_spawner, ValvePosition(mouth), children) in valves.iter() {
+        for &child in children {
+            let Ok((mut sprite, mut visibility)) = streams.get_mut(child) else {
+                continue;
+            };
+
+            if !is_passing(valve_state, actuator) || ball_spawner.is_blocked {
+                visibility.set_if_neq(Visibility::Hidden);
+                continue;
+            }
+            visibility.set_if_neq(Visibility::Visible);
+
+            let surface = bottle_below(*mouth, bottles.iter())
+                .map(|(_, surface)| surface)
+                .or_else(|| {
+                    conveyors
+                        .iter()
+                        .filter_map(|(transform, sprite, shape)| {
+                            belt_surface_height(transform, sprite, shape, mouth.x)
+                        })
+                        .filter(|height| *height < mouth.y)
+                        .max_by(f32::total_cmp)
+                })
+                .unwrap_or(bounds.min_y);
+
+            let rate = 1.0 / valve_state.ball_interval().max(0.1);
+            let width = (STREAM_WIDTH * rate).clamp(STREAM_WIDTH_RANGE.0, STREAM_WIDTH_RANGE.1);
+            sprite.custom_size = Some(Vec2::new(width, (mouth.y - surface).max(0.0)));
         }
     }
 }
 
 // >>> Ball Spawning System <<<
+/// Restarts the ball timer at the set rate. A PLC throttling a proportional
+/// valve moves its opening every scan, so an open one keeps its progress
+/// toward the next ball instead.
 pub fn update_ball_spawner_timer(
-    valve_state: Res<ValveState>,
-    mut ball_spawner: ResMut<BallSpawner>,
+    mut valves: Query<
+        (&ValveState, Option<&ProportionalValve>, &mut BallSpawner),
+        Changed<ValveState>,
+    >,
 ) {
-    if valve_state.is_changed() {
-        ball_spawner.timer.set_duration(Duration::from_secs_f32(valve_state.spawn_rate));
+    for (valve_state, proportional, mut ball_spawner) in valves.iter_mut() {
+        let progress = match proportional {
+            Some(_) if valve_state.is_open => ball_spawner.timer.fraction(),
+            _ => 0.0,
+        };
+        let interval = Duration::from_secs_f32(valve_state.ball_interval());
+        ball_spawner.timer.set_duration(interval);
         ball_spawner.timer.reset();
+        ball_spawner.timer.set_elapsed(interval.mul_f32(progress));
+
+        if !valve_state.is_open {
+            ball_spawner.dispensed = 0;
+        }
     }
 }
 
+/// Whether a refusing limit of `max_balls` holds back another ball.
+fn is_refused(policy: BallLimitPolicy, max_balls: Option<usize>, in_play: usize) -> bool {
+    policy == BallLimitPolicy::RefuseSpawn
+        && max_balls.is_some_and(|max_balls| in_play >= max_balls)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_balls(
-    time: Res<Time>,
-    valve_state: Res<ValveState>,
-    valves: Query<&ValvePosition, With<Valve>>,
-    mut ball_spawner: ResMut<BallSpawner>,
+    time: Res<SimTime>,
+    mut valves: Query<(
+        Entity,
+        &ValveState,
+        Option<&ValveActuator>,
+        &ValvePosition,
+        &BallLimit,
+        &LiquidModel,
+        &mut BallSpawner,
+    )>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut pool: ResMut<BallPool>,
+    bounds: Res<WorldBounds>,
+    balls: Query<Option<&DispensedBy>, With<Ball>>,
+    mut bottles: Query<
+        (
+            Entity,
+            &Transform,
+            &BottlePosition,
+            &BottleSpec,
+            &mut FillLevel,
+        ),
+        With<Bottle>,
+    >,
+    mut dispensed_events: EventWriter<BallDispensed>,
 ) {
-    if !valve_state.is_open {
-        return;
-    }
+    let mut in_play = balls.iter().count();
+
+    for (
+        entity,
+        valve_state,
+        actuator,
+        valve_position,
+        ball_limit,
+        liquid_model,
+        mut ball_spawner,
+    ) in valves.iter_mut()
+    {
+        if !is_passing(valve_state, actuator) || ball_spawner.is_blocked {
+            continue;
+        }
 
-    ball_spawner.timer.tick(time.delta());
+        ball_spawner.timer.tick(time.delta());
+
+        if ball_spawner.timer.just_finished() {
+            // A stream pours straight into the bottle below without simulating the ball
+            if *liquid_model == LiquidModel::Stream {
+                if let Some((bottle, _)) = bottle_below(valve_position.0, bottles.iter()) {
+                    if let Ok((.., mut fill_level)) = bottles.get_mut(bottle) {
+                        fill_level.0 += 1;
+                    }
+                    ball_spawner.dispensed += 1;
+                    dispensed_events.write(BallDispensed { valve: entity });
+                    continue;
+                }
+            }
+
+            let dispensed_in_play = balls
+                .iter()
+                .filter(|source| source.is_some_and(|DispensedBy(valve)| *valve == entity))
+                .count();
+            if is_refused(bounds.ball_policy, Some(bounds.max_balls), in_play)
+                || is_refused(ball_limit.policy, ball_limit.max_balls, dispensed_in_play)
+            {
+                continue;
+            }
 
-    if ball_spawner.timer.just_finished() {
-        for valve_position in valves.iter() {
-            spawn_ball(&mut commands, valve_position.0, &mut meshes, &mut materials);
+            let ball = spawn_ball(&mut commands, &mut pool, valve_position.0);
+            commands
+                .entity(ball)
+                .insert((BallLifetime::new(ball_limit.lifetime), DispensedBy(entity)));
+            in_play += 1;
+            ball_spawner.dispensed += 1;
+            dispensed_events.write(BallDispensed { valve: entity });
         }
     }
 }
-fn spawn_ball(
-    commands: &mut Commands,
-    position: Vec2,
-    meshes: &mut Assets<Mesh>,
-    materials: &mut Assets<ColorMaterial>,
+
+pub fn measure_valve_flow(
+    time: Res<SimTime>,
+    mut valves: Query<&mut FlowMeter>,
+    mut dispensed_events: EventReader<BallDispensed>,
 ) {
-    commands.spawn((
-        RigidBody::Dynamic,
-        Collider::ball(10.0),
-        Mesh2d(meshes.add(Circle::new(10.0))),
-        MeshMaterial2d(materials.add(ColorMaterial::from(Color::srgb(0.3, 0.7, 1.0)))),
+    for mut flow_meter in valves.iter_mut() {
+        if let Some(since_last) = flow_meter.since_last.as_mut() {
+            *since_last += time.delta_secs();
+        }
+    }
+
+    for dispensed in dispensed_events.read() {
+        if let Ok(mut flow_meter) = valves.get_mut(dispensed.valve) {
+            flow_meter.interval = flow_meter.since_last;
+            flow_meter.since_last = Some(0.0);
+        }
+    }
+}
+
+/// Puts a ball into play, recycling one from the pool if possible.
+pub fn spawn_ball(commands: &mut Commands, pool: &mut BallPool, position: Vec2) -> Entity {
+    let entity = match pool.free.pop() {
+        Some(entity) => {
+            commands
+                .entity(entity)
+                .remove::<(RigidBodyDisabled, ColliderDisabled)>();
+            entity
+        }
+        None => commands
+            .spawn((
+                RigidBody::Dynamic,
+                Collider::ball(BALL_RADIUS),
+                Mesh2d(pool.mesh.clone()),
+                MeshMaterial2d(pool.material.clone()),
+            ))
+            .id(),
+    };
+
+    commands.entity(entity).insert((
         Transform::from_translation(position.extend(0.0)),
+        Velocity::zero(),
+        Visibility::Visible,
         Ball,
-        BallLifetime::new(30.0),
+        BallLifetime::new(BALL_LIFETIME),
     ));
+    entity
+}
+
+/// Creates the shared ball mesh and fills the pool so the first fills don't spawn colliders.
+pub fn prewarm_ball_pool(
+    mut commands: Commands,
+    mut pool: ResMut<BallPool>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    pool.mesh = meshes.add(Circle::new(BALL_RADIUS));
+    pool.material = materials.add(ColorMaterial::from(WATER_COLOR));
+
+    for _ in 0..BALL_POOL_SIZE {
+        let entity = commands
+            .spawn((
+                RigidBody::Dynamic,
+                Collider::ball(BALL_RADIUS),
+                Mesh2d(pool.mesh.clone()),
+                MeshMaterial2d(pool.material.clone()),
+                Transform::default(),
+                Visibility::Hidden,
+                RigidBodyDisabled,
+                ColliderDisabled,
+            ))
+            .id();
+        pool.free.push(entity);
+    }
+}
+
+/// Merges balls that have come to rest inside a bottle into its liquid level,
+/// so filled bottles don't carry a pile of colliders down the line.
+pub fn merge_settled_balls(
+    mut commands: Commands,
+    mut pool: ResMut<BallPool>,
+    balls: Query<(Entity, &Transform, &Velocity), With<Ball>>,
+    mut bottles: Query<
+        (&Transform, &Velocity, &BottlePosition, &BottleSpec, &mut FillLevel),
+        With<Bottle>,
+    >,
+) {
+    for (ball, ball_transform, ball_velocity) in balls.iter() {
+        let point = ball_transform.translation.truncate();
+
+        for (transform, velocity, position, spec, mut fill_level) in bottles.iter_mut() {
+            let relative_speed = (ball_velocity.linvel - velocity.linvel).length();
+            if relative_speed < BALL_SETTLE_SPEED && position.contains(transform, spec, point) {
+                fill_level.0 += 1;
+                pool.release(&mut commands, ball);
+                break;
+            }
+        }
+    }
 }
 
 // >>> Ball Cleanup System <<<
@@ -218,43 +710,130 @@ impl BallLifetime {
             timer: Timer::from_seconds(lifetime_seconds, TimerMode::Once),
         }
     }
+
+    /// Seconds the ball has been in play.
+    pub fn age(&self) -> f32 {
+        self.timer.elapsed_secs()
+    }
 }
 
 pub fn cleanup_old_balls(
-    time: Res<Time>,
+    time: Res<SimTime>,
     mut commands: Commands,
+    mut pool: ResMut<BallPool>,
     mut balls: Query<(Entity, &mut BallLifetime), With<Ball>>,
 ) {
     for (entity, mut lifetime) in balls.iter_mut() {
         lifetime.timer.tick(time.delta());
         if lifetime.timer.finished() {
-            commands.entity(entity).despawn();
+            pool.release(&mut commands, entity);
         }
     }
 }
 
-pub fn cleanup_fallen_balls(
+pub fn cleanup_out_of_bounds_balls(
     mut commands: Commands,
+    mut pool: ResMut<BallPool>,
+    bounds: Res<WorldBounds>,
     balls: Query<(Entity, &Transform), With<Ball>>,
 ) {
-    const DESPAWN_Y_THRESHOLD: f32 = -500.0;
-
     for (entity, transform) in balls.iter() {
-        if transform.translation.y < DESPAWN_Y_THRESHOLD {
-            commands.entity(entity).despawn();
+        if !bounds.contains(transform.translation.truncate()) {
+            pool.release(&mut commands, entity);
         }
     }
 }
 
-pub fn limit_ball_count(mut commands: Commands, balls: Query<Entity, With<Ball>>) {
-    const MAX_BALLS: usize = 100;
+/// Takes the excess balls out of play, loose ones before those resting in a
+/// bottle and the oldest first, so limits don't drain liquid out of bottles.
+///
+/// # Parameters
+/// * `candidates` - Balls the limit applies to, whether each is in a bottle and its age
+/// * `max_balls` - Balls allowed to stay in play
+///
+/// # Return
+/// The balls to release
+fn excess_balls(mut candidates: Vec<(Entity, bool, f32)>, max_balls: usize) -> Vec<Entity> {
+    let excess = candidates.len().saturating_sub(max_balls);
+    candidates.sort_by(|(_, a_in_bottle, a_age), (_, b_in_bottle, b_age)| {
+        a_in_bottle.cmp(b_in_bottle).then(b_age.total_cmp(a_age))
+    });
+    candidates
+        .into_iter()
+        .take(excess)
+        .map(|(entity, _, _)| entity)
+        .collect()
+}
 
-    let ball_count = balls.iter().count();
-    if ball_count > MAX_BALLS {
-        let balls_to_remove = ball_count - MAX_BALLS;
-        for entity in balls.iter().take(balls_to_remove) {
-            commands.entity(entity).despawn();
+#[allow(clippy::too_many_arguments)]
+pub fn limit_ball_count(
+    mut commands: Commands,
+    mut pool: ResMut<BallPool>,
+    bounds: Res<WorldBounds>,
+    valves: Query<(Entity, &BallLimit)>,
+    balls: Query<(Entity, &Transform, &BallLifetime, Option<&DispensedBy>), With<Ball>>,
+    bottles: Query<(&Transform, &BottlePosition, &BottleSpec), With<Bottle>>,
+    mut alarm_events: EventWriter<AlarmEvent>,
+    mut was_exceeded: Local<bool>,
+) {
+    let candidates: Vec<(Entity, bool, f32, Option<Entity>)> = balls
+        .iter()
+        .map(|(entity, transform, lifetime, source)| {
+            let point = transform.translation.truncate();
+            let in_bottle = bottles
+                .iter()
+                .any(|(transform, position, spec)| position.contains(transform, spec, point));
+            (
+                entity,
+                in_bottle,
+                lifetime.age(),
+                source.map(|DispensedBy(valve)| *valve),
+            )
+        })
+        .collect();
+    let mut released = Vec::new();
+
+    for (valve, ball_limit) in valves.iter() {
+        let Some(max_balls) = ball_limit.max_balls else {
+            continue;
+        };
+        if ball_limit.policy != BallLimitPolicy::DespawnOldest {
+            continue;
         }
+
+        let dispensed = candidates
+            .iter()
+            .filter(|(_, _, _, source)| *source == Some(valve))
+            .map(|&(entity, in_bottle, age, _)| (entity, in_bottle, age))
+            .collect();
+        released.extend(excess_balls(dispensed, max_balls));
+    }
+
+    let remaining: Vec<(Entity, bool, f32)> = candidates
+        .iter()
+        .filter(|(entity, _, _, _)| !released.contains(entity))
+        .map(|&(entity, in_bottle, age, _)| (entity, in_bottle, age))
+        .collect();
+    let ball_count = remaining.len();
+    let is_exceeded = match bounds.ball_policy {
+        BallLimitPolicy::DespawnOldest => ball_count > bounds.max_balls,
+        BallLimitPolicy::RefuseSpawn => ball_count >= bounds.max_balls,
+    };
+    if bounds.ball_policy == BallLimitPolicy::DespawnOldest {
+        released.extend(excess_balls(remaining, bounds.max_balls));
+    }
+
+    for entity in released {
+        pool.release(&mut commands, entity);
+    }
+
+    if *was_exceeded != is_exceeded {
+        *was_exceeded = is_exceeded;
+        alarm_events.write(if is_exceeded {
+            AlarmEvent::Raise(Alarm::BallLimitExceeded)
+        } else {
+            AlarmEvent::Clear(Alarm::BallLimitExceeded)
+        });
     }
 }
 
@@ -263,22 +842,35 @@ pub struct ValvePlugin;
 
 impl Plugin for ValvePlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(ValveState::default())
-            .insert_resource(BallSpawner::default())
+        app.init_resource::<BallPool>()
+            .register_type::<Valve>()
+            .register_type::<ValveState>()
+            .register_type::<Ball>()
+            .add_event::<BallDispensed>()
+            .add_systems(Startup, prewarm_ball_pool)
             .add_systems(
                 Update,
                 (
                     handle_valve_input,
-                    sync_valves_to_modbus,
-                    sync_modbus_to_valves,
                     update_valve_visuals,
+                    spawn_liquid_streams,
+                    update_liquid_streams,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
                     update_ball_spawner_timer,
                     spawn_balls,
+                    measure_valve_flow,
+                    merge_settled_balls,
                     cleanup_old_balls,
-                    cleanup_fallen_balls,
+                    cleanup_out_of_bounds_balls,
                     limit_ball_count,
                 )
-                    .chain(),
+                    .chain()
+                    .after(sync_tags),
             );
     }
 }
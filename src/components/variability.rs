@@ -0,0 +1,121 @@
+// variability.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{conveyor::SpeedRipple, sensor::Sensor, simulation::SimRng, valve::Ball};
+
+// >>> Constants <<<
+const RIPPLE_SMOOTHING: f32 = 0.1; // Share of the way the ripple moves toward its target each step
+
+// >>> Resources <<<
+/// Process noise applied to an otherwise ideal plant, drawn from the seeded
+/// [`SimRng`] so noisy runs still replay identically. Everything is off by default.
+#[derive(Resource, Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VariabilityConfig {
+    pub spawn_jitter: f32,        // Fraction the bottle spawn interval varies by
+    pub ball_size_jitter: f32,    // Fraction the ball radius varies by
+    pub ball_mass_jitter: f32,    // Fraction the ball density varies by
+    pub sensor_delay_jitter: f32, // Seconds a sensor may lag an item reaching it
+    pub sensor_threshold_jitter: f32, // Fraction each sensor's detection area varies by
+    pub speed_ripple: f32,        // Fraction the conveyor speed ripples by
+}
+
+impl VariabilityConfig {
+    /// Draws a factor within `1 ± jitter`, without touching the random source when there is none.
+    pub fn factor(jitter: f32, rng: &mut SimRng) -> f32 {
+        if jitter > 0.0 {
+            1.0 + rng.random_range(-jitter..=jitter)
+        } else {
+            1.0
+        }
+    }
+
+    /// Draws how long a sensor trip lags the item reaching the sensor.
+    pub fn sensor_delay(&self, rng: &mut SimRng) -> f32 {
+        if self.sensor_delay_jitter > 0.0 {
+            rng.random_range(0.0..=self.sensor_delay_jitter)
+        } else {
+            0.0
+        }
+    }
+}
+
+// >>> Systems <<<
+/// Scales each new sensor's detection area by its own factor, so items trip
+/// it a little earlier or later than its nominal position, like an eye set
+/// to a slightly different sensitivity.
+pub fn vary_sensor_thresholds(
+    mut sensors: Query<&mut Transform, Added<Sensor>>,
+    variability: Res<VariabilityConfig>,
+    mut rng: ResMut<SimRng>,
+) {
+    if variability.sensor_threshold_jitter <= 0.0 {
+        return;
+    }
+
+    for mut transform in sensors.iter_mut() {
+        // The collider follows the transform's scale
+        let size = VariabilityConfig::factor(variability.sensor_threshold_jitter, &mut rng);
+        transform.scale = Vec3::new(size, size, 1.0);
+    }
+}
+
+/// Varies the size and mass of each ball as it leaves the valve or hopper.
+pub fn vary_balls(
+    mut commands: Commands,
+    mut balls: Query<(Entity, &mut Transform), Added<Ball>>,
+    variability: Res<VariabilityConfig>,
+    mut rng: ResMut<SimRng>,
+) {
+    if variability.ball_size_jitter <= 0.0 && variability.ball_mass_jitter <= 0.0 {
+        return;
+    }
+
+    for (entity, mut transform) in balls.iter_mut() {
+        // The collider follows the transform's scale
+        let size = VariabilityConfig::factor(variability.ball_size_jitter, &mut rng);
+        transform.scale = Vec3::new(size, size, 1.0);
+
+        let density = VariabilityConfig::factor(variability.ball_mass_jitter, &mut rng);
+        commands
+            .entity(entity)
+            .insert(ColliderMassProperties::Density(density));
+    }
+}
+
+/// Ripples each running belt's surface speed around its set speed.
+pub fn ripple_conveyor_speeds(
+    mut conveyors: Query<&mut SpeedRipple>,
+    variability: Res<VariabilityConfig>,
+    mut rng: ResMut<SimRng>,
+) {
+    for mut ripple in conveyors.iter_mut() {
+        if variability.speed_ripple <= 0.0 {
+            if ripple.0 != 1.0 {
+                ripple.0 = 1.0;
+            }
+            continue;
+        }
+
+        let target = VariabilityConfig::factor(variability.speed_ripple, &mut rng);
+        ripple.0 += (target - ripple.0) * RIPPLE_SMOOTHING;
+    }
+}
+
+// >>> Plugin <<<
+pub struct VariabilityPlugin;
+
+impl Plugin for VariabilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VariabilityConfig>().add_systems(
+            FixedUpdate,
+            (vary_balls, vary_sensor_thresholds, ripple_conveyor_speeds).chain(),
+        );
+    }
+}
@@ -0,0 +1,290 @@
+// vision.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+
+use super::{
+    bottle::{BOTTLE_WIDTH, Bottle, BottlePosition, BottleSpec, Capped, FillLevel},
+    labeler::Label,
+    lighting::LightingState,
+    modbus::ModbusState,
+    simulation::{SimRng, SimTime},
+    valve::Ball,
+};
+
+// >>> Constants <<<
+pub const RESULT_PASS: u16 = 0;
+pub const RESULT_FILL_LOW: u16 = 1 << 0;
+pub const RESULT_FILL_HIGH: u16 = 1 << 1;
+pub const RESULT_NO_CAP: u16 = 1 << 2;
+pub const RESULT_LABEL_DEFECT: u16 = 1 << 3;
+pub const RESULT_NO_BOTTLE: u16 = 1 << 4;
+
+const VISION_ZONE_HEIGHT: f32 = 20.0;
+const VISION_COLOR: Color = Color::srgb(0.6, 0.2, 0.8);
+
+// >>> Components <<<
+/// Checks evaluated by a vision sensor on each inspection.
+#[derive(Debug, Clone, Default)]
+pub struct VisionChecks {
+    pub fill_range: Option<(u32, u32)>, // Min/max balls inside the bottle
+    pub require_cap: bool,
+    pub require_label: bool,
+}
+
+#[derive(Component)]
+pub struct VisionSensor {
+    pub trigger_coil_address: u16,
+    pub pass_discrete_address: u16,
+    pub result_input_address: u16,
+    pub checks: VisionChecks,
+    pub processing_delay: f32, // Seconds
+    bottle_in_zone: Option<Entity>,
+    last_trigger: bool,
+    inspection: Option<(Option<Entity>, Timer)>,
+}
+
+// >>> Bundles <<<
+#[derive(Bundle)]
+pub struct VisionSensorBundle {
+    vision_sensor: VisionSensor,
+    collider: Collider,
+    collider_sensor: bevy_rapier2d::geometry::Sensor,
+    active_events: ActiveEvents,
+    sprite: Sprite,
+    transform: Transform,
+}
+
+impl VisionSensor {
+    /// Creates a camera-triggered inspection station.
+    ///
+    /// # Parameters
+    /// * `trigger_coil_address` - Coil whose rising edge starts an inspection
+    /// * `pass_discrete_address` - Discrete input set when the last inspection passed
+    /// * `result_input_address` - Input register holding the last result code
+    /// * `checks` - Checks to evaluate
+    /// * `processing_delay` - Seconds between trigger and result
+    /// * `position` - Position of the inspection zone
+    ///
+    /// # Return
+    /// The vision sensor bundle
+    pub fn new(
+        trigger_coil_address: u16,
+        pass_discrete_address: u16,
+        result_input_address: u16,
+        checks: VisionChecks,
+        processing_delay: f32,
+        position: Vec2,
+    ) -> VisionSensorBundle {
+        let zone_size = Vec2::new(BOTTLE_WIDTH + 20.0, VISION_ZONE_HEIGHT);
+
+        VisionSensorBundle {
+            vision_sensor: VisionSensor {
+                trigger_coil_address,
+                pass_discrete_address,
+                result_input_address,
+                checks,
+                processing_delay,
+                bottle_in_zone: None,
+                last_trigger: false,
+                inspection: None,
+            },
+            collider: Collider::cuboid(zone_size.x / 2.0, zone_size.y / 2.0),
+            collider_sensor: bevy_rapier2d::geometry::Sensor,
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            sprite: Sprite::from_color(VISION_COLOR.with_alpha(0.4), zone_size),
+            transform: Transform::from_translation(position.extend(0.0)),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_vision_sensors(
+    query: Query<&VisionSensor, Added<VisionSensor>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for vision_sensor in query.iter() {
+        if let Ok(mut coils) = modbus_state.coils.lock() {
+            coils.insert(vision_sensor.trigger_coil_address, false);
+        }
+        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+            discretes.insert(vision_sensor.pass_discrete_address, false);
+        }
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(vision_sensor.result_input_address, RESULT_PASS);
+        }
+
+        info!(
+            "Registered vision sensor on trigger coil: {:x?}",
+            vision_sensor.trigger_coil_address
+        );
+    }
+}
+
+pub fn track_vision_zones(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut vision_sensors: Query<&mut VisionSensor>,
+    bottles: Query<(), With<Bottle>>,
+) {
+    for collision_event in collision_events.read() {
+        let (e1, e2, is_started) = match collision_event {
+            CollisionEvent::Started(e1, e2, _) => (*e1, *e2, true),
+            CollisionEvent::Stopped(e1, e2, _) => (*e1, *e2, false),
+        };
+
+        let (sensor_entity, bottle_entity) = if vision_sensors.contains(e1) {
+            (e1, e2)
+        } else if vision_sensors.contains(e2) {
+            (e2, e1)
+        } else {
+            continue;
+        };
+
+        if !bottles.contains(bottle_entity) {
+            continue;
+        }
+
+        let mut vision_sensor = vision_sensors.get_mut(sensor_entity).unwrap();
+        if is_started {
+            vision_sensor.bottle_in_zone = Some(bottle_entity);
+        } else if vision_sensor.bottle_in_zone == Some(bottle_entity) {
+            vision_sensor.bottle_in_zone = None;
+        }
+    }
+}
+
+pub fn trigger_vision_sensors(
+    mut vision_sensors: Query<&mut VisionSensor>,
+    modbus_state: Res<ModbusState>,
+) {
+    if let Ok(coils) = modbus_state.coils.lock() {
+        for mut vision_sensor in vision_sensors.iter_mut() {
+            let trigger = coils
+                .get(&vision_sensor.trigger_coil_address)
+                .copied()
+                .unwrap_or(false);
+
+            if trigger && !vision_sensor.last_trigger && vision_sensor.inspection.is_none() {
+                let bottle = vision_sensor.bottle_in_zone;
+                let delay = vision_sensor.processing_delay;
+                vision_sensor.inspection = Some((bottle, Timer::from_seconds(delay, TimerMode::Once)));
+                info!(
+                    "Vision sensor {:x?} triggered",
+                    vision_sensor.trigger_coil_address
+                );
+            }
+
+            if vision_sensor.last_trigger != trigger {
+                vision_sensor.last_trigger = trigger;
+            }
+        }
+    }
+}
+
+pub fn process_vision_sensors(
+    time: Res<SimTime>,
+    mut vision_sensors: Query<&mut VisionSensor>,
+    bottles: Query<
+        (
+            &Transform,
+            &BottlePosition,
+            &BottleSpec,
+            &FillLevel,
+            Option<&Label>,
+            Has<Capped>,
+        ),
+        With<Bottle>,
+    >,
+    balls: Query<&Transform, With<Ball>>,
+    lighting_state: Res<LightingState>,
+    modbus_state: Res<ModbusState>,
+    mut rng: ResMut<SimRng>,
+) {
+    for mut vision_sensor in vision_sensors.iter_mut() {
+        let Some((bottle, timer)) = vision_sensor.inspection.as_mut() else {
+            continue;
+        };
+
+        timer.tick(time.delta());
+        if !timer.finished() {
+            continue;
+        }
+
+        let bottle = *bottle;
+        vision_sensor.inspection = None;
+
+        let mut result = match bottle.and_then(|entity| bottles.get(entity).ok()) {
+            Some((transform, position, spec, merged, label, is_capped)) => {
+                // Balls still settling count alongside those already merged into the liquid
+                let fill_level = merged.0
+                    + balls
+                        .iter()
+                        .filter(|ball| position.contains(transform, spec, ball.translation.truncate()))
+                        .count() as u32;
+
+                inspect(&vision_sensor.checks, fill_level, label, is_capped)
+            }
+            None => RESULT_NO_BOTTLE,
+        };
+
+        // Poor lighting makes good bottles look like label defects
+        if result == RESULT_PASS && rng.random::<f32>() < lighting_state.mode.false_reject_rate() {
+            result = RESULT_LABEL_DEFECT;
+        }
+
+        if let Ok(mut discretes) = modbus_state.discrete_inputs.lock() {
+            discretes.insert(vision_sensor.pass_discrete_address, result == RESULT_PASS);
+        }
+        if let Ok(mut inputs) = modbus_state.input_registers.lock() {
+            inputs.insert(vision_sensor.result_input_address, result);
+        }
+
+        info!(
+            "Vision sensor {:x?} result: {:#06b}",
+            vision_sensor.trigger_coil_address, result
+        );
+    }
+}
+
+fn inspect(checks: &VisionChecks, fill_level: u32, label: Option<&Label>, is_capped: bool) -> u16 {
+    let mut result = RESULT_PASS;
+
+    if let Some((min, max)) = checks.fill_range {
+        if fill_level < min {
+            result |= RESULT_FILL_LOW;
+        } else if fill_level > max {
+            result |= RESULT_FILL_HIGH;
+        }
+    }
+
+    if checks.require_cap && !is_capped {
+        result |= RESULT_NO_CAP;
+    }
+
+    if checks.require_label && !label.is_some_and(|label| label.is_good()) {
+        result |= RESULT_LABEL_DEFECT;
+    }
+
+    result
+}
+
+// >>> Plugin <<<
+pub struct VisionPlugin;
+
+impl Plugin for VisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_vision_sensors,
+                track_vision_zones,
+                trigger_vision_sensors,
+                process_vision_sensors,
+            )
+                .chain(),
+        );
+    }
+}
@@ -0,0 +1,195 @@
+// watchdog.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    alarm::{Alarm, AlarmEvent},
+    conveyor::ConveyorState,
+    modbus::ModbusState,
+    simulation::SimTime,
+    tag_sync::sync_tags,
+    valve::ValveState,
+};
+
+// >>> Components <<<
+/// Register the PLC must keep changing to show it is alive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Heartbeat {
+    /// A coil the PLC toggles.
+    Coil { address: u16 },
+    /// A holding register the PLC increments.
+    Register { address: u16 },
+}
+
+/// Comms watchdog tripping when the PLC's heartbeat stops changing.
+///
+/// The watchdog arms on the first heartbeat, so the plant can still be run by
+/// hand before a PLC connects.
+#[derive(Component)]
+pub struct Watchdog {
+    pub heartbeat: Heartbeat,
+    pub timeout: f32,     // Seconds the heartbeat may go unchanged
+    pub safe_state: bool, // Stop conveyors and close valves while tripped
+    pub is_armed: bool,
+    pub is_tripped: bool,
+    last_value: Option<u16>,
+    since_beat: f32,
+}
+
+impl Watchdog {
+    /// Creates a disarmed watchdog.
+    ///
+    /// # Parameters
+    /// * `heartbeat` - Coil or holding register the PLC keeps changing
+    /// * `timeout` - Seconds without a change before comms are considered lost
+    /// * `safe_state` - Whether to stop conveyors and close valves while tripped
+    ///
+    /// # Return
+    /// The watchdog
+    pub fn new(heartbeat: Heartbeat, timeout: f32, safe_state: bool) -> Self {
+        Self {
+            heartbeat,
+            timeout,
+            safe_state,
+            is_armed: false,
+            is_tripped: false,
+            last_value: None,
+            since_beat: 0.0,
+        }
+    }
+
+    /// Current heartbeat value, with a coil read as 0 or 1.
+    fn read(&self, modbus_state: &ModbusState) -> Option<u16> {
+        match self.heartbeat {
+            Heartbeat::Coil { address } => modbus_state
+                .coils
+                .lock()
+                .ok()
+                .and_then(|coils| coils.get(&address).map(|&coil| coil as u16)),
+            Heartbeat::Register { address } => modbus_state
+                .holding_registers
+                .lock()
+                .ok()
+                .and_then(|holdings| holdings.get(&address).copied()),
+        }
+    }
+}
+
+// >>> Systems <<<
+pub fn register_watchdogs(
+    watchdogs: Query<&Watchdog, Added<Watchdog>>,
+    modbus_state: Res<ModbusState>,
+) {
+    for watchdog in watchdogs.iter() {
+        match watchdog.heartbeat {
+            Heartbeat::Coil { address } => {
+                if let Ok(mut coils) = modbus_state.coils.lock() {
+                    coils.insert(address, false);
+                }
+            }
+            Heartbeat::Register { address } => {
+                if let Ok(mut holdings) = modbus_state.holding_registers.lock() {
+                    holdings.insert(address, 0);
+                }
+            }
+        }
+
+        info!("Registered watchdog on heartbeat: {:?}", watchdog.heartbeat);
+    }
+}
+
+/// Times the gap since each watchdog's heartbeat last changed.
+pub fn check_heartbeats(
+    time: Res<SimTime>,
+    mut watchdogs: Query<&mut Watchdog>,
+    modbus_state: Res<ModbusState>,
+) {
+    for mut watchdog in watchdogs.iter_mut() {
+        let value = watchdog.read(&modbus_state);
+        if value != watchdog.last_value {
+            if watchdog.last_value.is_some() && !watchdog.is_armed {
+                watchdog.is_armed = true;
+                info!("Watchdog armed");
+            }
+            watchdog.last_value = value;
+            watchdog.since_beat = 0.0;
+        } else {
+            watchdog.since_beat += time.delta_secs();
+        }
+
+        let is_tripped = watchdog.is_armed && watchdog.since_beat > watchdog.timeout;
+        if watchdog.is_tripped != is_tripped {
+            watchdog.is_tripped = is_tripped;
+            if is_tripped {
+                warn!("Watchdog tripped, no heartbeat for {}s", watchdog.timeout);
+            } else {
+                info!("Watchdog heartbeat resumed");
+            }
+        }
+    }
+}
+
+pub fn monitor_watchdog_alarms(
+    watchdogs: Query<&Watchdog>,
+    mut alarm_events: EventWriter<AlarmEvent>,
+    mut was_tripped: Local<bool>,
+) {
+    let is_tripped = watchdogs.iter().any(|watchdog| watchdog.is_tripped);
+    if *was_tripped != is_tripped {
+        *was_tripped = is_tripped;
+        alarm_events.write(if is_tripped {
+            AlarmEvent::Raise(Alarm::CommsLoss)
+        } else {
+            AlarmEvent::Clear(Alarm::CommsLoss)
+        });
+    }
+}
+
+/// Holds conveyors stopped and valves closed while a watchdog with a safe
+/// state is tripped, overriding whatever the outputs were last commanded to.
+pub fn enforce_safe_states(
+    watchdogs: Query<&Watchdog>,
+    mut conveyors: Query<&mut ConveyorState>,
+    mut valves: Query<&mut ValveState>,
+) {
+    if !watchdogs
+        .iter()
+        .any(|watchdog| watchdog.is_tripped && watchdog.safe_state)
+    {
+        return;
+    }
+
+    for mut conveyor_state in conveyors.iter_mut() {
+        if conveyor_state.is_driven() {
+            conveyor_state.is_running = false;
+            conveyor_state.jog = 0.0;
+        }
+    }
+    for mut valve_state in valves.iter_mut() {
+        if valve_state.is_open {
+            valve_state.is_open = false;
+        }
+    }
+}
+
+// >>> Plugin <<<
+pub struct WatchdogPlugin;
+
+impl Plugin for WatchdogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                register_watchdogs,
+                check_heartbeats,
+                monitor_watchdog_alarms,
+                enforce_safe_states.after(sync_tags),
+            )
+                .chain(),
+        );
+    }
+}
@@ -0,0 +1,287 @@
+// webapi.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, Sender, channel},
+    },
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::{
+    arbitration::WriteOrigin,
+    bottle::{BottlePool, BottleSpawner, ProductSelection, spawn_infeed_bottle},
+    conveyor::{Conveyor, ConveyorShape, belt_infeed},
+    modbus::ModbusState,
+    simulation::ResetSimulation,
+    station::StationId,
+    tags::{Tag, TagKind, TagRegistry},
+};
+
+// >>> Constants <<<
+const WEB_API_IP: &str = "0.0.0.0";
+const WEB_API_PORT: u16 = 8080;
+const STATE_INTERVAL: Duration = Duration::from_millis(100);
+
+// >>> Messages <<<
+/// Commands accepted from WebSocket clients, e.g. `{"command": "set_coil", "address": 0, "value": true}`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum WebCommand {
+    SetCoil { address: u16, value: bool },
+    SetRegister { address: u16, value: u16 },
+    SetTag { name: String, value: u16 },
+    SpawnBottle,
+    Reset,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WebMessage<'a> {
+    State { tags: &'a BTreeMap<String, u16> },
+    Ack,
+    Error { message: String },
+}
+
+/// Commands that need the Bevy world rather than the register tables.
+enum WorldCommand {
+    SpawnBottle,
+    Reset,
+}
+
+// >>> Resources <<<
+#[derive(Resource)]
+pub struct WebApiSettings {
+    pub port: u16,
+}
+
+#[derive(Resource)]
+pub struct WebApiState {
+    tags: Arc<Mutex<Vec<Tag>>>,
+    commands: Mutex<Receiver<WorldCommand>>,
+    sender: Sender<WorldCommand>,
+}
+
+impl Default for WebApiState {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            tags: Arc::new(Mutex::new(Vec::new())),
+            commands: Mutex::new(receiver),
+            sender,
+        }
+    }
+}
+
+// >>> Server <<<
+fn snapshot(tags: &Mutex<Vec<Tag>>, modbus_state: &ModbusState) -> BTreeMap<String, u16> {
+    let Ok(tags) = tags.lock() else {
+        return BTreeMap::new();
+    };
+
+    tags.iter()
+        .filter_map(|tag| Some((tag.name.clone(), tag.read(modbus_state)?)))
+        .collect()
+}
+
+fn apply_command(
+    command: WebCommand,
+    tags: &Mutex<Vec<Tag>>,
+    modbus_state: &ModbusState,
+    world_commands: &Sender<WorldCommand>,
+    origin: &WriteOrigin,
+) -> Result<(), String> {
+    match command {
+        WebCommand::SetCoil { address, value } => {
+            if !modbus_state.write(TagKind::Coil, address, value as u16, origin) {
+                return Err(format!("Illegal coil address {address:#06x}"));
+            }
+        }
+        WebCommand::SetRegister { address, value } => {
+            if !modbus_state.write(TagKind::HoldingRegister, address, value, origin) {
+                return Err(format!("Illegal holding register address {address:#06x}"));
+            }
+        }
+        WebCommand::SetTag { name, value } => {
+            let tag = tags
+                .lock()
+                .map_err(|err| err.to_string())?
+                .iter()
+                .find(|tag| tag.name == name)
+                .cloned()
+                .ok_or(format!("Unknown tag {name}"))?;
+            if !tag.write(modbus_state, value, origin) {
+                return Err(format!("Tag {name} is read-only"));
+            }
+        }
+        WebCommand::SpawnBottle => world_commands
+            .send(WorldCommand::SpawnBottle)
+            .map_err(|err| err.to_string())?,
+        WebCommand::Reset => world_commands
+            .send(WorldCommand::Reset)
+            .map_err(|err| err.to_string())?,
+    }
+    Ok(())
+}
+
+async fn serve_connection(
+    stream: TcpStream,
+    tags: Arc<Mutex<Vec<Tag>>>,
+    modbus_state: ModbusState,
+    world_commands: Sender<WorldCommand>,
+    origin: WriteOrigin,
+) -> anyhow::Result<()> {
+    let (mut outgoing, mut incoming) = tokio_tungstenite::accept_async(stream).await?.split();
+    let mut interval = tokio::time::interval(STATE_INTERVAL);
+    let mut last_state = BTreeMap::new();
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                // Only stream state when something changed
+                let state = snapshot(&tags, &modbus_state);
+                if state != last_state {
+                    let message = serde_json::to_string(&WebMessage::State { tags: &state })?;
+                    outgoing.send(Message::text(message)).await?;
+                    last_state = state;
+                }
+            }
+            message = incoming.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(err)) => return Err(err.into()),
+                };
+
+                let reply = match serde_json::from_str::<WebCommand>(&text) {
+                    Ok(command) => match apply_command(command, &tags, &modbus_state, &world_commands, &origin) {
+                        Ok(()) => WebMessage::Ack,
+                        Err(message) => WebMessage::Error { message },
+                    },
+                    Err(err) => WebMessage::Error { message: err.to_string() },
+                };
+                outgoing.send(Message::text(serde_json::to_string(&reply)?)).await?;
+            }
+        }
+    }
+}
+
+// >>> Systems <<<
+fn start_web_api_server(
+    settings: Res<WebApiSettings>,
+    web_api_state: Res<WebApiState>,
+    modbus_state: Res<ModbusState>,
+) {
+    let port = settings.port;
+    let tags = web_api_state.tags.clone();
+    let sender = web_api_state.sender.clone();
+    let state = modbus_state.clone();
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let socket_addr: SocketAddr = format!("{}:{}", WEB_API_IP, port).parse().unwrap();
+            let listener = match TcpListener::bind(socket_addr).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    eprintln!("Web API failed to bind {socket_addr}: {err}");
+                    return;
+                }
+            };
+            println!("Web API running on ws://{socket_addr}");
+
+            loop {
+                let Ok((stream, peer)) = listener.accept().await else {
+                    continue;
+                };
+                let tags = tags.clone();
+                let state = state.clone();
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let origin = WriteOrigin::remote(format!("web api {peer}"));
+                    if let Err(err) = serve_connection(stream, tags, state, sender, origin).await {
+                        eprintln!("Web API connection {peer}: {err}");
+                    }
+                });
+            }
+        });
+    });
+}
+
+pub fn sync_web_api_tags(tags: Res<TagRegistry>, web_api_state: Res<WebApiState>) {
+    if tags.is_changed() {
+        if let Ok(mut shared) = web_api_state.tags.lock() {
+            *shared = tags.iter().cloned().collect();
+        }
+    }
+}
+
+pub fn apply_web_api_commands(
+    mut commands: Commands,
+    web_api_state: Res<WebApiState>,
+    conveyors: Query<
+        (&Transform, &Sprite, &ConveyorShape, &StationId),
+        (With<Conveyor>, With<BottleSpawner>),
+    >,
+    product_selection: Res<ProductSelection>,
+    mut pool: ResMut<BottlePool>,
+    mut reset_events: EventWriter<ResetSimulation>,
+) {
+    let Ok(receiver) = web_api_state.commands.lock() else {
+        return;
+    };
+
+    for command in receiver.try_iter() {
+        match command {
+            WorldCommand::SpawnBottle => {
+                for (transform, sprite, shape, station) in conveyors.iter() {
+                    spawn_infeed_bottle(
+                        &mut commands,
+                        &mut pool,
+                        belt_infeed(transform, sprite, shape),
+                        *station,
+                        product_selection.selected_spec(),
+                    );
+                }
+                info!("Spawned a new bottle via web API");
+            }
+            WorldCommand::Reset => {
+                reset_events.write(ResetSimulation);
+            }
+        }
+    }
+}
+
+// >>> Plugin <<<
+/// Streams tag values to WebSocket clients as JSON and accepts commands from them.
+pub struct WebApiPlugin {
+    pub port: u16,
+}
+
+impl Default for WebApiPlugin {
+    fn default() -> Self {
+        Self {
+            port: WEB_API_PORT,
+        }
+    }
+}
+
+impl Plugin for WebApiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WebApiSettings { port: self.port })
+            .init_resource::<WebApiState>()
+            .add_systems(Startup, start_web_api_server)
+            .add_systems(Update, sync_web_api_tags)
+            .add_systems(FixedUpdate, apply_web_api_commands);
+    }
+}
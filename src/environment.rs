@@ -3,50 +3,1169 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use std::any::TypeId;
+use std::collections::HashMap;
 use bevy::prelude::*;
 
 use crate::components::{
-    conveyor::Conveyor,
-    bottle::{BOTTLE_HEIGHT, Bottle},
-    sensor::Sensor,
-    valve::{Ball, Valve},
+    accumulation::{AccumulationSettings, ConveyorZone},
+    actuator::ValveActuator,
+    alarm::AlarmState,
+    arbitration::ArbitrationSettings,
+    barcode::BarcodeScanner,
+    capper::{CapFeeder, Capper},
+    conveyor::{Conveyor, ConveyorTransfer},
+    counter::Counter,
+    diverter::{Diverter, spawn_diverter_lane},
+    energy::EnergyMeter,
+    feedback::Feedback,
+    gantry::Gantry,
+    guide::{spawn_chute, spawn_guide_rail},
+    heater::Heater,
+    hopper::{Cap, Hopper},
+    infeed::InfeedControl,
+    jam::JamDetection,
+    jog::JogControl,
+    labeler::Labeler,
+    lighting::Lighting,
+    modbus::ModbusState,
+    mode::OperatingMode,
+    oee::OeeMonitor,
+    outfeed::Outfeed,
+    overload::MotorProtection,
+    palletizer::Palletizer,
+    pid::PidController,
+    pneumatics::Pneumatics,
+    bottle::{Bottle, BottleSpawner, InfeedProfile, ProductSelection},
+    recipe::RecipeBook,
+    sensor::{MissInjection, Sensor, SensorOutputMode},
+    simulation::{ResetSimulation, SimState},
+    score::DropScore,
+    spill::SpillZone,
+    station::StationId,
+    stopper::Stopper,
+    tags::{DeviceName, RegisterMap, RegisterType, Tag, TagKind, TagRegistry},
+    tank::Tank,
+    tracking::TrackingZone,
+    turntable::Turntable,
+    valve::{Ball, ProportionalValve, Valve},
+    vision::{VisionChecks, VisionSensor},
+    watchdog::{Heartbeat, Watchdog},
 };
+use crate::machine::{MachineContext, MachineRegistry};
+use crate::scenario::{
+    EnergyConfig, FeedbackConfig, LAYOUT_SIZE, LoadScenario, PidConfig, ReloadScenario, Scenario, ScenarioEntity,
+    SensorItem,
+};
+
+// Scenario sections applied without rebuilding the plant
+const RESOURCE_SECTIONS: [&str; 7] = [
+    "name",
+    "bounds",
+    "bottle_physics",
+    "physics",
+    "variability",
+    "network",
+    "clients",
+];
+
+/// Places one copy of the line, shifting its positions and addresses and prefixing its tag names.
+pub(crate) struct StationLayout<'a> {
+    tags: &'a mut TagRegistry,
+    prefix: Option<String>,
+    offset: Vec2,
+    address_offset: u16,
+    registered: Vec<Tag>, // Tags of the device being spawned
+}
+
+impl StationLayout<'_> {
+    pub(crate) fn name(&self, name: &str) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("{prefix}.{name}"),
+            None => name.to_string(),
+        }
+    }
+
+    pub(crate) fn position(&self, position: [f32; 2]) -> Vec2 {
+        Vec2::from(position) + self.offset
+    }
+
+    fn register(&mut self, name: &str, kind: TagKind, address: u16) -> u16 {
+        let name = self.name(name);
+        let address = self.tags.register(&name, kind, address.wrapping_add(self.address_offset));
+        self.registered.push(Tag {
+            name,
+            kind,
+            address,
+        });
+        address
+    }
+
+    pub(crate) fn coil(&mut self, name: &str, address: u16) -> u16 {
+        self.register(name, TagKind::Coil, address)
+    }
+
+    pub(crate) fn discrete_input(&mut self, name: &str, address: u16) -> u16 {
+        self.register(name, TagKind::DiscreteInput, address)
+    }
+
+    pub(crate) fn input_register(&mut self, name: &str, address: u16) -> u16 {
+        self.register(name, TagKind::InputRegister, address)
+    }
+
+    pub(crate) fn holding_register(&mut self, name: &str, address: u16) -> u16 {
+        self.register(name, TagKind::HoldingRegister, address)
+    }
+
+    /// Registers a holding register laid out as `register_type` rather than
+    /// a plain u16.
+    pub(crate) fn typed_holding_register(
+        &mut self,
+        name: &str,
+        address: u16,
+        register_type: RegisterType,
+    ) -> u16 {
+        let address = self.holding_register(name, address);
+        self.tags
+            .set_register_type(TagKind::HoldingRegister, address, register_type);
+        address
+    }
+
+    /// Hands over the tags registered since the last call, for the device just built.
+    pub(crate) fn register_map(&mut self) -> RegisterMap {
+        RegisterMap(std::mem::take(&mut self.registered))
+    }
+}
+
+/// Builds a device's control loop and names its registers after the device.
+fn pid_controller(layout: &mut StationLayout<'_>, device: &str, pid: &PidConfig) -> PidController {
+    let gains_holding = layout.holding_register(&format!("{device}.pid.kp"), pid.gains_holding);
+    layout.holding_register(&format!("{device}.pid.ki"), pid.gains_holding.wrapping_add(1));
+    layout.holding_register(&format!("{device}.pid.kd"), pid.gains_holding.wrapping_add(2));
+
+    PidController::new(
+        layout.holding_register(&format!("{device}.pid.setpoint"), pid.setpoint_holding),
+        gains_holding,
+        layout.holding_register(&format!("{device}.pid.mode"), pid.mode_holding),
+    )
+    .with_setpoint(pid.setpoint)
+    .with_gains(pid.kp, pid.ki, pid.kd)
+    .with_mode(pid.mode)
+}
+
+fn process_feedback(layout: &mut StationLayout<'_>, tag: &str, feedback: &FeedbackConfig) -> Feedback {
+    Feedback::new(layout.input_register(tag, feedback.input), feedback.scale)
+}
+
+fn energy_meter(
+    layout: &mut StationLayout<'_>,
+    device: &str,
+    energy: &EnergyConfig,
+) -> EnergyMeter {
+    let energy_input = layout.input_register(&format!("{device}.energy_hi"), energy.energy_input);
+    layout.input_register(
+        &format!("{device}.energy_lo"),
+        energy.energy_input.wrapping_add(1),
+    );
+
+    let mut meter = EnergyMeter::new(
+        layout.input_register(&format!("{device}.power"), energy.power_input),
+        energy_input,
+        energy.rated_power,
+    );
+    meter.rated_speed = energy.rated_speed;
+    meter.rated_mass = energy.rated_mass;
+    meter
+}
+
+fn spawn_scenario(
+    commands: &mut Commands,
+    scenario: &Scenario,
+    tags: &mut TagRegistry,
+    machines: &MachineRegistry,
+    window_size: Vec2,
+) {
+    commands.insert_resource(scenario.bounds.clone());
+    commands.insert_resource(scenario.bottle_physics.clone());
+    commands.insert_resource(scenario.physics.clone());
+    commands.insert_resource(scenario.variability.clone());
+    commands.insert_resource(scenario.network.clone());
+    commands.insert_resource(scenario.clients.clone());
+    commands.insert_resource(scenario.sound.clone());
+    tags.coil("sound.mute", scenario.sound.mute_coil);
+
+    if scenario.stations.is_empty() {
+        let mut layout = StationLayout {
+            tags: &mut *tags,
+            prefix: None,
+            offset: Vec2::ZERO,
+            address_offset: 0,
+            registered: Vec::new(),
+        };
+        spawn_station(commands, scenario, &mut layout, machines, StationId(0));
+    }
+
+    for (id, station) in scenario.stations.iter().enumerate() {
+        let mut layout = StationLayout {
+            tags: &mut *tags,
+            prefix: Some(station.name.clone()),
+            offset: Vec2::from(station.offset),
+            address_offset: station.address_offset,
+            registered: Vec::new(),
+        };
+        spawn_station(
+            commands,
+            scenario,
+            &mut layout,
+            machines,
+            StationId(id as u16),
+        );
+        info!("Spawned station {}", station.name);
+    }
+
+    // Scene lighting is shared by every station
+    if let Some(lighting) = &scenario.lighting {
+        commands.spawn((
+            Lighting::new(
+                tags.coil("lighting.reduced", lighting.reduced_coil),
+                tags.coil("lighting.emergency", lighting.emergency_coil),
+                lighting.size.map_or(window_size, Vec2::from),
+            ),
+            ScenarioEntity,
+        ));
+    }
+
+    // One PLC drives every station, so its heartbeat is shared too
+    if let Some(watchdog) = &scenario.watchdog {
+        let heartbeat = match watchdog.heartbeat {
+            Heartbeat::Coil { address } => Heartbeat::Coil {
+                address: tags.coil("watchdog.heartbeat", address),
+            },
+            Heartbeat::Register { address } => Heartbeat::Register {
+                address: tags.holding_register("watchdog.heartbeat", address),
+            },
+        };
+        commands.spawn((
+            Watchdog::new(heartbeat, watchdog.timeout, watchdog.safe_state),
+            ScenarioEntity,
+        ));
+    }
+
+    // OEE is measured over the whole plant
+    if let Some(oee) = &scenario.oee {
+        let mut monitor = OeeMonitor::new(
+            tags.input_register("oee.availability", oee.availability_input),
+            tags.input_register("oee.performance", oee.performance_input),
+            tags.input_register("oee.quality", oee.quality_input),
+            tags.input_register("oee.oee", oee.oee_input),
+            oee.ideal_cycle_time,
+        );
+        monitor.min_fill = oee.min_fill;
+        monitor.require_cap = oee.require_cap;
+        monitor.require_label = oee.require_label;
+        commands.spawn((monitor, ScenarioEntity));
+    }
+
+    // Every station's actuators draw on the same air header
+    match &scenario.pneumatics {
+        Some(pneumatics) => {
+            let mut header = Pneumatics::new(
+                tags.coil("pneumatics.compressor", pneumatics.compressor_coil),
+                tags.input_register("pneumatics.pressure", pneumatics.pressure_input),
+                pneumatics.supply_pressure,
+                pneumatics.low_pressure,
+            );
+            header.compressor_rate = pneumatics.compressor_rate;
+            header.leak_rate = pneumatics.leak_rate;
+            header.stroke_consumption = pneumatics.stroke_consumption;
+            commands.insert_resource(header);
+        }
+        None => commands.remove_resource::<Pneumatics>(),
+    }
+
+    match &scenario.mode {
+        Some(mode) => commands.insert_resource(OperatingMode {
+            auto_coil_address: tags.coil("mode.auto", mode.auto_coil),
+            is_auto: mode.auto,
+        }),
+        None => commands.remove_resource::<OperatingMode>(),
+    }
+
+    match &scenario.arbitration {
+        Some(arbitration) => commands.insert_resource(ArbitrationSettings {
+            hmi_hold: arbitration.hmi_hold,
+        }),
+        None => commands.remove_resource::<ArbitrationSettings>(),
+    }
+
+    // A station's tags are scaled by their name without the station's prefix
+    for (name, scaling) in &scenario.scaling {
+        if scaling.scale == 0.0 {
+            warn!("Ignoring scaling of {name} with a zero scale");
+            continue;
+        }
+        let mut is_applied = tags.set_scaling(name, scaling.clone());
+        for station in &scenario.stations {
+            let prefixed = format!("{}.{name}", station.name);
+            is_applied |= tags.set_scaling(&prefixed, scaling.clone());
+        }
+        if !is_applied {
+            warn!("Scaling of {name} names no input or holding register");
+        }
+    }
+}
+
+/// Spawns the scenario's line as one station.
+fn spawn_station(
+    commands: &mut Commands,
+    scenario: &Scenario,
+    layout: &mut StationLayout<'_>,
+    machines: &MachineRegistry,
+    station: StationId,
+) {
+    // Chained conveyors start where the belt feeding them ends
+    let mut outfeeds: HashMap<&str, (Entity, Vec2)> = HashMap::new();
+    for conveyor in &scenario.conveyors {
+        let upstream = conveyor.upstream.as_deref().and_then(|name| {
+            let upstream = outfeeds.get(name).copied();
+            if upstream.is_none() {
+                warn!("Conveyor {} is chained to unknown conveyor {}", conveyor.name, name);
+            }
+            upstream
+        });
+        let [width, height] = conveyor.size;
+        let mut bundle = Conveyor::new(
+            layout.coil(&format!("{}.run", conveyor.name), conveyor.run_coil),
+            layout.typed_holding_register(
+                &format!("{}.speed", conveyor.name),
+                conveyor.speed_holding,
+                RegisterType::I16,
+            ),
+            layout.position(conveyor.position),
+            width,
+            height,
+        );
+        if conveyor.incline != 0.0 {
+            bundle = bundle.with_incline(conveyor.incline.to_radians());
+        }
+        if let Some(curve) = &conveyor.curve {
+            bundle = bundle.with_curve(
+                curve.radius,
+                curve.start.to_radians(),
+                curve.sweep.to_radians(),
+            );
+        }
+        if conveyor.infeed_profile != InfeedProfile::Steady {
+            bundle = bundle.with_infeed_profile(conveyor.infeed_profile.clone());
+        }
+        if let Some((_, outfeed)) = upstream {
+            bundle = bundle.with_infeed_at(outfeed + Vec2::new(conveyor.gap, 0.0));
+        }
+        let outfeed = bundle.outfeed();
+        let feedback = conveyor.speed_feedback.as_ref().map(|feedback| {
+            process_feedback(layout, &format!("{}.speed_feedback", conveyor.name), feedback)
+        });
+        let overload = conveyor.overload.as_ref().map(|overload| {
+            MotorProtection::new(
+                layout.input_register(&format!("{}.load", conveyor.name), overload.load_input),
+                layout.discrete_input(
+                    &format!("{}.overload", conveyor.name),
+                    overload.fault_discrete,
+                ),
+                layout.coil(
+                    &format!("{}.overload_reset", conveyor.name),
+                    overload.reset_coil,
+                ),
+                overload.rated_mass,
+                overload.trip_load,
+                overload.trip_delay,
+            )
+        });
+        let energy = conveyor
+            .energy
+            .as_ref()
+            .map(|energy| energy_meter(layout, &conveyor.name, energy));
+        let infeed = conveyor.infeed.as_ref().and_then(|infeed| {
+            if upstream.is_some() {
+                warn!(
+                    "Conveyor {} is chained and has no infeed to control",
+                    conveyor.name
+                );
+                return None;
+            }
+            Some(InfeedControl::new(
+                layout.coil(&format!("{}.spawn", conveyor.name), infeed.spawn_coil),
+                layout.coil(
+                    &format!("{}.infeed_enable", conveyor.name),
+                    infeed.enable_coil,
+                ),
+                layout.holding_register(
+                    &format!("{}.spawn_interval", conveyor.name),
+                    infeed.interval_holding,
+                ),
+            ))
+        });
+        let jog = conveyor.jog.as_ref().map(|jog| {
+            JogControl::new(
+                layout.coil(&format!("{}.jog_forward", conveyor.name), jog.forward_coil),
+                layout.coil(&format!("{}.jog_reverse", conveyor.name), jog.reverse_coil),
+                layout.coil(
+                    &format!("{}.maintenance", conveyor.name),
+                    jog.maintenance_coil,
+                ),
+                jog.speed,
+            )
+        });
+        let entity = commands
+            .spawn((
+                bundle,
+                station,
+                ScenarioEntity,
+                DeviceName(layout.name(&conveyor.name)),
+                layout.register_map(),
+            ))
+            .id();
+        if let Some(feedback) = feedback {
+            commands.entity(entity).insert(feedback);
+        }
+        if let Some(overload) = overload {
+            commands.entity(entity).insert(overload);
+        }
+        if let Some(energy) = energy {
+            commands.entity(entity).insert(energy);
+        }
+        if let Some(infeed) = infeed {
+            commands.entity(entity).insert(infeed);
+        }
+        if let Some(jog) = jog {
+            commands.entity(entity).insert(jog);
+        }
+
+        // Only the first conveyor of a chain takes new bottles
+        if let Some((upstream, _)) = upstream {
+            commands
+                .entity(upstream)
+                .insert(ConveyorTransfer { downstream: entity });
+            commands.entity(entity).remove::<BottleSpawner>();
+        }
+        outfeeds.insert(&conveyor.name, (entity, outfeed));
+    }
+
+    // Accumulation zones ahead of the filling station
+    for zone in &scenario.zones {
+        commands.spawn((
+            ConveyorZone::new(
+                zone.start_x + layout.offset.x,
+                zone.end_x + layout.offset.x,
+                zone.eye_y + layout.offset.y,
+                layout.discrete_input(&format!("{}.blocked", zone.name), zone.blocked_discrete),
+                layout.discrete_input(&format!("{}.running", zone.name), zone.running_discrete),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&zone.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for valve in &scenario.valves {
+        let mut entity = commands.spawn((
+            Valve::new(
+                layout.coil(&format!("{}.open", valve.name), valve.open_coil),
+                layout.holding_register(&format!("{}.spawn_rate", valve.name), valve.spawn_rate_holding),
+                layout.position(valve.position),
+            )
+            .with_ball_limit(valve.ball_limit.clone())
+            .with_liquid_model(valve.liquid),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&valve.name)),
+        ));
+        if let Some(feedback) = &valve.flow_feedback {
+            entity.insert(process_feedback(layout, &format!("{}.flow_feedback", valve.name), feedback));
+        }
+        if let Some(address) = valve.opening_holding {
+            entity.insert(ProportionalValve {
+                opening_holding_address: layout
+                    .holding_register(&format!("{}.opening", valve.name), address),
+            });
+        }
+        if let Some(travel) = &valve.travel {
+            entity.insert(ValveActuator::new(
+                layout.discrete_input(&format!("{}.opened", valve.name), travel.open_discrete),
+                layout.discrete_input(&format!("{}.closed", valve.name), travel.closed_discrete),
+                layout.discrete_input(
+                    &format!("{}.travel_fault", valve.name),
+                    travel.fault_discrete,
+                ),
+                travel.open_time,
+                travel.close_time,
+                travel.timeout,
+            ));
+        }
+        entity.insert(layout.register_map());
+    }
+
+    for tank in &scenario.tanks {
+        let mut entity = commands.spawn((
+            Tank::new(
+                layout.coil(&format!("{}.pump", tank.name), tank.pump_coil),
+                layout.input_register(&format!("{}.level", tank.name), tank.level_input),
+                layout.discrete_input(&format!("{}.low", tank.name), tank.low_discrete),
+                tank.capacity,
+                tank.low_level,
+                tank.pump_rate,
+                layout.position(tank.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&tank.name)),
+        ));
+        if let Some(pid) = &tank.pid {
+            entity.insert(pid_controller(layout, &tank.name, pid));
+        }
+        entity.insert(layout.register_map());
+    }
+
+    // Workstation stops along the belt
+    for stopper in &scenario.stoppers {
+        commands.spawn((
+            Stopper::new(
+                layout.coil(&format!("{}.extend", stopper.name), stopper.extend_coil),
+                layout.discrete_input(&format!("{}.present", stopper.name), stopper.present_discrete),
+                layout.position(stopper.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&stopper.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for spill_zone in &scenario.spill_zones {
+        commands.spawn((
+            SpillZone::new(
+                layout.input_register(
+                    &format!("{}.count", spill_zone.name),
+                    spill_zone.count_input,
+                ),
+                layout.coil(&format!("{}.reset", spill_zone.name), spill_zone.reset_coil),
+                spill_zone.threshold,
+                layout.position(spill_zone.position),
+                Vec2::from(spill_zone.size),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&spill_zone.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for score in &scenario.drop_scores {
+        commands.spawn((
+            DropScore::new(
+                layout.input_register(&format!("{}.hits", score.name), score.hits_input),
+                layout.input_register(&format!("{}.misses", score.name), score.misses_input),
+                layout.coil(&format!("{}.reset", score.name), score.reset_coil),
+                layout.position(score.position),
+                score.width,
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&score.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for zone in &scenario.tracking_zones {
+        let serial_input =
+            layout.input_register(&format!("{}.serial_hi", zone.name), zone.serial_input);
+        layout.input_register(&format!("{}.serial_lo", zone.name), zone.serial_input + 1);
+        commands.spawn((
+            TrackingZone::new(
+                serial_input,
+                layout.input_register(&format!("{}.status", zone.name), zone.status_input),
+                layout.position(zone.position),
+                Vec2::from(zone.size),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&zone.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for labeler in &scenario.labelers {
+        commands.spawn((
+            Labeler::new(
+                layout.coil(&format!("{}.enable", labeler.name), labeler.enable_coil),
+                labeler.defect_probability,
+                layout.position(labeler.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&labeler.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for sensor in &scenario.sensors {
+        let item = match sensor.item {
+            SensorItem::Bottle => TypeId::of::<Bottle>(),
+            SensorItem::Ball => TypeId::of::<Ball>(),
+            SensorItem::Cap => TypeId::of::<Cap>(),
+        };
+        let [r, g, b] = sensor.color;
+        let mut bundle = Sensor::new(
+            layout.name(&sensor.name),
+            layout.discrete_input(&sensor.name, sensor.discrete_input),
+            item,
+            layout.position(sensor.position),
+            Color::srgb(r, g, b),
+            sensor.shape,
+            sensor.rotation,
+        )
+        .with_miss_rate(sensor.miss_rate)
+        .with_output_mode(match sensor.output {
+            SensorOutputMode::Latched { reset_coil } => SensorOutputMode::Latched {
+                reset_coil: layout.coil(&format!("{}.reset", sensor.name), reset_coil),
+            },
+            output => output,
+        })
+        .with_polarity(sensor.polarity);
+        if let Some(variant) = &sensor.variant {
+            bundle = bundle.with_variant(variant);
+        }
+        if let Some(distance) = &sensor.distance {
+            bundle = bundle.with_distance(
+                layout.input_register(&format!("{}.distance", sensor.name), distance.input),
+                distance.range,
+                Vec2::from(distance.direction),
+            );
+        }
+        commands.spawn((
+            bundle,
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&sensor.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for counter in &scenario.counters {
+        let count_input =
+            layout.input_register(&format!("{}.count_high", counter.name), counter.count_input);
+        layout.input_register(
+            &format!("{}.count_low", counter.name),
+            counter.count_input.wrapping_add(1),
+        );
+        commands.spawn((
+            Counter::new(
+                &layout.name(&counter.sensor),
+                count_input,
+                layout.coil(&format!("{}.reset", counter.name), counter.reset_coil),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&counter.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for hopper in &scenario.hoppers {
+        commands.spawn((
+            Hopper::new(
+                hopper.part,
+                layout.coil(&format!("{}.gate", hopper.name), hopper.gate_coil),
+                layout.coil(&format!("{}.refill", hopper.name), hopper.refill_coil),
+                layout.discrete_input(&format!("{}.low", hopper.name), hopper.low_discrete),
+                layout.discrete_input(&format!("{}.empty", hopper.name), hopper.empty_discrete),
+                hopper.capacity,
+                hopper.low_level,
+                hopper.release_interval,
+                layout.position(hopper.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&hopper.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for feeder in &scenario.cap_feeders {
+        commands.spawn((
+            CapFeeder::new(
+                layout.discrete_input(&format!("{}.present", feeder.name), feeder.present_discrete),
+                feeder.capacity,
+                layout.position(feeder.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&feeder.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for capper in &scenario.cappers {
+        commands.spawn((
+            Capper::new(
+                layout.coil(&format!("{}.enable", capper.name), capper.enable_coil),
+                layout.position(capper.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&capper.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for heater in &scenario.heaters {
+        let mut entity = commands.spawn((
+            Heater::new(
+                layout.holding_register(&format!("{}.power", heater.name), heater.power_holding),
+                layout.input_register(&format!("{}.temperature", heater.name), heater.temperature_input),
+                heater.model.clone(),
+                layout.position(heater.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&heater.name)),
+        ));
+        if let Some(pid) = &heater.pid {
+            entity.insert(pid_controller(layout, &heater.name, pid));
+        }
+        if let Some(energy) = &heater.energy {
+            entity.insert(energy_meter(layout, &heater.name, energy));
+        }
+        entity.insert(layout.register_map());
+    }
+
+    for vision in &scenario.vision_sensors {
+        commands.spawn((
+            VisionSensor::new(
+                layout.coil(&format!("{}.trigger", vision.name), vision.trigger_coil),
+                layout.discrete_input(&format!("{}.pass", vision.name), vision.pass_discrete),
+                layout.input_register(&format!("{}.result", vision.name), vision.result_input),
+                VisionChecks {
+                    fill_range: vision.fill_range.map(|[min, max]| (min, max)),
+                    require_cap: vision.require_cap,
+                    require_label: vision.require_label,
+                },
+                vision.processing_delay,
+                layout.position(vision.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&vision.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for scanner in &scenario.barcode_scanners {
+        let serial_input =
+            layout.input_register(&format!("{}.serial_hi", scanner.name), scanner.serial_input);
+        layout.input_register(
+            &format!("{}.serial_lo", scanner.name),
+            scanner.serial_input + 1,
+        );
+        commands.spawn((
+            BarcodeScanner::new(
+                layout.coil(&format!("{}.trigger", scanner.name), scanner.trigger_coil),
+                layout.discrete_input(
+                    &format!("{}.read_ok", scanner.name),
+                    scanner.read_ok_discrete,
+                ),
+                serial_input,
+                scanner.no_read_rate,
+                layout.position(scanner.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&scanner.name)),
+            layout.register_map(),
+        ));
+    }
+
+    // Sortation at the end of the conveyor
+    for diverter in &scenario.diverters {
+        commands.spawn((
+            Diverter::new(
+                layout.holding_register(&format!("{}.lane", diverter.name), diverter.lane_holding),
+                layout.position(diverter.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&diverter.name)),
+            layout.register_map(),
+        ));
+    }
+
+    for lane in &scenario.lanes {
+        let [bin, counter] = spawn_diverter_lane(
+            commands,
+            lane.lane,
+            layout.input_register(&format!("lane{}.count", lane.lane), lane.counter_input),
+            layout.position(lane.position),
+            lane.width,
+        );
+        commands.entity(bin).insert((station, ScenarioEntity));
+        commands.entity(counter).insert((
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&format!("lane{}", lane.lane))),
+            layout.register_map(),
+        ));
+    }
+
+    // Finished bottles leave the line at the end of the conveyor
+    for outfeed in &scenario.outfeeds {
+        commands.spawn((
+            Outfeed::new(
+                layout.input_register(&format!("{}.count", outfeed.name), outfeed.counter_input),
+                layout.position(outfeed.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&outfeed.name)),
+            layout.register_map(),
+        ));
+    }
+
+    // Completed bottles are stacked onto a pallet
+    for palletizer in &scenario.palletizers {
+        commands.spawn((
+            Palletizer::new(
+                layout.holding_register(
+                    &format!("{}.pattern", palletizer.name),
+                    palletizer.pattern_holding,
+                ),
+                layout.discrete_input(
+                    &format!("{}.layer_complete", palletizer.name),
+                    palletizer.layer_complete_discrete,
+                ),
+                layout.discrete_input(
+                    &format!("{}.pallet_complete", palletizer.name),
+                    palletizer.pallet_complete_discrete,
+                ),
+                layout.coil(&format!("{}.clear", palletizer.name), palletizer.clear_coil),
+                palletizer.layers,
+                layout.position(palletizer.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&palletizer.name)),
+            layout.register_map(),
+        ));
+    }
+
+    // Pick-and-place robots past the end of the line
+    for gantry in &scenario.gantries {
+        commands.spawn((
+            Gantry::new(
+                layout.holding_register(&format!("{}.x", gantry.name), gantry.x_holding),
+                layout.holding_register(&format!("{}.y", gantry.name), gantry.y_holding),
+                layout.discrete_input(&format!("{}.busy", gantry.name), gantry.busy_discrete),
+                layout.discrete_input(
+                    &format!("{}.in_position", gantry.name),
+                    gantry.in_position_discrete,
+                ),
+                layout.coil(&format!("{}.gripper", gantry.name), gantry.gripper_coil),
+                layout.position(gantry.position),
+                Vec2::from(gantry.reach),
+                gantry.speed,
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&gantry.name)),
+            layout.register_map(),
+        ));
+    }
+
+    // Indexing tables carrying bottles between workstations
+    for turntable in &scenario.turntables {
+        commands.spawn((
+            Turntable::new(
+                layout.coil(&format!("{}.index", turntable.name), turntable.index_coil),
+                layout.discrete_input(
+                    &format!("{}.in_position", turntable.name),
+                    turntable.in_position_discrete,
+                ),
+                layout.input_register(
+                    &format!("{}.position", turntable.name),
+                    turntable.position_input,
+                ),
+                turntable.positions,
+                turntable.radius,
+                turntable.speed.to_radians(),
+                layout.position(turntable.position),
+            ),
+            station,
+            ScenarioEntity,
+            DeviceName(layout.name(&turntable.name)),
+            layout.register_map(),
+        ));
+    }
+
+    // Passive geometry guiding the products
+    for rail in &scenario.guide_rails {
+        let position = layout.position(rail.position);
+        match spawn_guide_rail(
+            commands,
+            &rail.shape,
+            position,
+            rail.rotation,
+            rail.friction,
+        ) {
+            Some(entity) => {
+                commands.entity(entity).insert((station, ScenarioEntity));
+            }
+            None => warn!("Guide rail {} has no walls", rail.name),
+        }
+    }
+
+    for chute in &scenario.chutes {
+        let position = layout.position(chute.position);
+        match spawn_chute(
+            commands,
+            &chute.shape,
+            position,
+            chute.rotation,
+            chute.friction,
+        ) {
+            Some(entity) => {
+                commands.entity(entity).insert((station, ScenarioEntity));
+            }
+            None => warn!("Chute {} has no walls", chute.name),
+        }
+    }
+
+    // Machine types registered by other crates
+    for machine in &scenario.machines {
+        let mut context = MachineContext::new(commands, layout, station, &machine.name);
+        if let Err(error) = machines.spawn(machine, &mut context) {
+            error!("Machine {} not spawned: {error:#}", machine.name);
+        }
+    }
+}
+
+/// Drops the registers of the devices about to be despawned from the Modbus
+/// tables, so an address the new plant leaves unused reads as unregistered
+/// instead of holding the old device's last value.
+fn clear_device_registers(
+    devices: &Query<&RegisterMap>,
+    tags: &TagRegistry,
+    modbus_state: &ModbusState,
+) {
+    for RegisterMap(map) in devices.iter() {
+        for tag in map {
+            let words = tags.register_type(tag.kind, tag.address).words();
+            for offset in 0..words {
+                modbus_state.remove(tag.kind, tag.address.wrapping_add(offset));
+            }
+        }
+    }
+}
+
+/// World area the camera shows: the window scaled to keep all of
+/// [`LAYOUT_SIZE`] in view.
+fn window_size(window: &Query<&Window>) -> Vec2 {
+    let Ok(size) = window.single().map(|window| window.size()) else {
+        return LAYOUT_SIZE;
+    };
+    if size.min_element() <= 0.0 {
+        return LAYOUT_SIZE;
+    }
+    size * (LAYOUT_SIZE / size).max_element()
+}
+
+pub fn setup_environment(
+    mut commands: Commands,
+    window: Query<&Window>,
+    scenario: Res<Scenario>,
+    mut tags: ResMut<TagRegistry>,
+    machines: Res<MachineRegistry>,
+) {
+    spawn_scenario(
+        &mut commands,
+        &scenario,
+        &mut tags,
+        &machines,
+        window_size(&window),
+    );
+    info!("Loaded scenario {}", scenario.name);
+}
+
+/// Tears down the running plant and spawns the requested scenario in its
+/// place, holding the simulation in [`SimState::Loading`] until it is spawned.
+#[allow(clippy::too_many_arguments)]
+pub fn load_scenario(
+    mut commands: Commands,
+    mut load_events: EventReader<LoadScenario>,
+    entities: Query<Entity, With<ScenarioEntity>>,
+    devices: Query<&RegisterMap>,
+    window: Query<&Window>,
+    mut tags: ResMut<TagRegistry>,
+    machines: Res<MachineRegistry>,
+    modbus_state: Res<ModbusState>,
+    mut reset_events: EventWriter<ResetSimulation>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    let Some(LoadScenario(scenario)) = load_events.read().last() else {
+        return;
+    };
+    next_state.set(SimState::Loading);
+
+    for entity in entities.iter() {
+        commands.entity(entity).despawn();
+    }
+    reset_events.write(ResetSimulation);
+
+    clear_device_registers(&devices, &tags, &modbus_state);
+    *tags = TagRegistry::default();
+    spawn_scenario(
+        &mut commands,
+        scenario,
+        &mut tags,
+        &machines,
+        window_size(&window),
+    );
+    commands.run_system_cached(register_control_tags);
+    commands.run_system_cached(validate_address_map);
+    commands.insert_resource(scenario.clone());
+
+    info!("Loaded scenario {}", scenario.name);
+}
+
+/// Applies an edited scenario without resetting the simulation. Sections
+/// backed by resources are applied in place; any other change rebuilds every
+/// device, dropping the old devices' registers. Products stay on the line and
+/// the Modbus server keeps its connections.
+#[allow(clippy::too_many_arguments)]
+pub fn reload_scenario(
+    mut commands: Commands,
+    mut reload_events: EventReader<ReloadScenario>,
+    scenario: Res<Scenario>,
+    entities: Query<Entity, With<ScenarioEntity>>,
+    devices: Query<&RegisterMap>,
+    window: Query<&Window>,
+    mut tags: ResMut<TagRegistry>,
+    machines: Res<MachineRegistry>,
+    modbus_state: Res<ModbusState>,
+) {
+    let Some(ReloadScenario(edited)) = reload_events.read().last() else {
+        return;
+    };
+
+    let changes = scenario.changed_sections(edited);
+    if changes.is_empty() {
+        return;
+    }
+    info!("Reloading scenario, changed: {}", changes.join(", "));
+
+    if changes
+        .iter()
+        .all(|section| RESOURCE_SECTIONS.contains(&section.as_str()))
+    {
+        commands.insert_resource(edited.bounds.clone());
+        commands.insert_resource(edited.bottle_physics.clone());
+        commands.insert_resource(edited.physics.clone());
+        commands.insert_resource(edited.variability.clone());
+        commands.insert_resource(edited.network.clone());
+        commands.insert_resource(edited.clients.clone());
+        commands.insert_resource(edited.clone());
+        return;
+    }
+
+    for entity in entities.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    clear_device_registers(&devices, &tags, &modbus_state);
+    *tags = TagRegistry::default();
+    spawn_scenario(
+        &mut commands,
+        edited,
+        &mut tags,
+        &machines,
+        window_size(&window),
+    );
+    commands.run_system_cached(register_control_tags);
+    commands.run_system_cached(validate_address_map);
+    commands.insert_resource(edited.clone());
+}
+
+/// Names the addresses of plant-wide modes that have no entity of their own.
+pub fn register_control_tags(
+    mut tags: ResMut<TagRegistry>,
+    miss_injection: Res<MissInjection>,
+    product_selection: Res<ProductSelection>,
+    accumulation: Res<AccumulationSettings>,
+    recipe_book: Res<RecipeBook>,
+    alarm_state: Res<AlarmState>,
+    jam_detection: Res<JamDetection>,
+) {
+    tags.coil("sensor.miss_injection", miss_injection.coil_address);
+    tags.holding_register("product.select", product_selection.holding_address);
+    tags.coil("accumulation.zone_control", accumulation.zone_control_coil_address);
+    tags.holding_register("recipe.select", recipe_book.select_holding_address);
+    tags.coil("recipe.start", recipe_book.start_coil_address);
+    tags.discrete_input("recipe.batch_complete", recipe_book.complete_discrete_address);
+    tags.input_register("recipe.batch_count", recipe_book.count_input_address);
+    tags.coil("alarm.ack", alarm_state.ack_coil_address);
+    for i in 0..alarm_state.word_count {
+        tags.input_register(&format!("alarm.word{i}"), alarm_state.word_input_address + i);
+    }
+    tags.coil("jam.clear", jam_detection.clear_coil_address);
+}
+
+/// Reports addresses claimed by more than one tag, and names registered
+/// twice. The scenario still loads, but the devices sharing an address will
+/// overwrite each other's values.
+pub fn validate_address_map(tags: Res<TagRegistry>) {
+    for collision in tags.collisions() {
+        error!("Address collision on {collision}");
+    }
+    for tag in tags.duplicates() {
+        error!(
+            "Duplicate tag {} on {} 0x{:04X}",
+            tag.name,
+            tag.kind.abbreviation(),
+            tag.address
+        );
+    }
+}
+
+/// Warns about addresses the plant serves without a tag once the devices of
+/// a newly registered plant have published theirs, as the collision check
+/// cannot see them.
+pub fn report_untagged_registers(tags: Res<TagRegistry>, modbus_state: Res<ModbusState>) {
+    for (kind, address) in tags.untagged(&modbus_state) {
+        warn!(
+            "{} 0x{:04X} is served without a tag, so collisions with it go unreported",
+            kind.abbreviation(),
+            address
+        );
+    }
+}
 
-pub const CONVEYOR_HEIGHT: f32 = 100.0;
-
-pub fn setup_environment(mut commands: Commands, window: Query<&Window>) {
-    let window = window.single().unwrap();
-    let width = window.resolution.width();
-
-    // Conveyor
-    let conveyor_width: f32 = width / 2.0;
-    commands.spawn(Conveyor::new(
-        0x0000,
-        0x0000,
-        Vec2::new(-conveyor_width / 2.0, -150.0),
-        conveyor_width,
-        CONVEYOR_HEIGHT
-    ));
-
-    // Water valve
-    commands.spawn(Valve::new(0x0001, 0x0001, Vec2::new(-30.0, 70.0)));
-
-    // Bottle sensor
-    commands.spawn(Sensor::new(
-        "bottle_sensor".to_string(),
-        0x0000,
-        TypeId::of::<Bottle>(),
-        Vec2::new(0.0, -CONVEYOR_HEIGHT),
-        Color::srgb(1.0, 0.0, 0.0),
-    ));
-
-    // Water sensor
-    commands.spawn(Sensor::new(
-        "water_sensor".to_string(),
-        0x0001,
-        TypeId::of::<Ball>(),
-        Vec2::new(0.0, -CONVEYOR_HEIGHT + BOTTLE_HEIGHT),
-        Color::srgb(0.0, 0.0, 1.0),
-    ));
+/// Spawns the plant described by the [`Scenario`] resource (the bottle
+/// filling line unless one is inserted first) and names its signals.
+pub struct EnvironmentPlugin;
 
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Scenario>()
+            .init_resource::<MachineRegistry>()
+            .add_event::<LoadScenario>()
+            .add_event::<ReloadScenario>()
+            .add_systems(
+                Startup,
+                (setup_environment, register_control_tags, validate_address_map).chain(),
+            )
+            // Outside the fixed timestep, so a scenario loads while paused or stopped
+            .add_systems(PreUpdate, (load_scenario, reload_scenario).chain())
+            .add_systems(
+                FixedLast,
+                report_untagged_registers.run_if(resource_changed::<TagRegistry>),
+            );
+    }
 }
@@ -0,0 +1,121 @@
+// lib.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Bottle filling plant simulator controllable over Modbus.
+//!
+//! Add [`ModuSimPlugins`] to a Bevy app for the full process model, or pick
+//! individual plugins from [`components`] to compose a custom simulator.
+
+use bevy::app::PluginGroupBuilder;
+use bevy::prelude::*;
+
+pub mod components;
+pub mod environment;
+pub mod machine;
+pub mod scenario;
+pub mod testing;
+
+use components::{
+    accumulation::AccumulationPlugin, actuator::ActuatorPlugin, alarm::AlarmPlugin,
+    arbitration::ArbitrationPlugin, barcode::BarcodePlugin, bottle::BottlePlugin,
+    capper::CapperPlugin, controls::ControlsPlugin, conveyor::ConveyorPlugin,
+    counter::CounterPlugin, diverter::DiverterPlugin, dnp3::Dnp3Plugin, energy::EnergyPlugin,
+    feedback::FeedbackPlugin, forcing::ForcingPlugin, gallery::GalleryPlugin, gantry::GantryPlugin,
+    heater::HeaterPlugin, hmi::HmiPlugin, hopper::HopperPlugin, infeed::InfeedPlugin,
+    inspector::InspectorPlugin, iolist::IoListPlugin, jam::JamPlugin, jog::JogPlugin,
+    labeler::LabelerPlugin, lighting::LightingPlugin, logger::DataLoggerPlugin,
+    modbus::ModbusPlugin, mode::ModePlugin, oee::OeePlugin, outfeed::OutfeedPlugin,
+    overlay::OverlayPlugin, overload::OverloadPlugin, palletizer::PalletizerPlugin,
+    physics::PhysicsPlugin, pid::PidPlugin, pneumatics::PneumaticsPlugin, profiler::ProfilerPlugin,
+    recipe::RecipePlugin, report::ReportPlugin, restapi::RestApiPlugin, s7::S7Plugin,
+    score::ScorePlugin, sensor::SensorPlugin, simulation::SimulationPlugin,
+    snapshot::SnapshotPlugin, sound::SoundPlugin, spill::SpillPlugin, stats::StatsPlugin,
+    stopper::StopperPlugin, tag_sync::TagSyncPlugin, tank::TankPlugin, timeline::TimelinePlugin,
+    tipping::TippingPlugin, tracking::TrackingPlugin, turntable::TurntablePlugin,
+    valve::ValvePlugin, variability::VariabilityPlugin, vision::VisionPlugin,
+    watchdog::WatchdogPlugin, webapi::WebApiPlugin,
+};
+
+/// Every ModuSim plugin, including the Modbus server. The S7 server, DNP3
+/// outstation, web API, REST API and scenario menu are included but disabled;
+/// turn them on with e.g. `enable::<S7Plugin>()`.
+///
+/// Spawn the plant itself with [`environment::EnvironmentPlugin`] or your own
+/// startup system. Individual plugins can be swapped out with `set` or
+/// removed with `disable`, e.g. to run without the Modbus server. Disable
+/// `SoundPlugin` in apps without Bevy's audio plugin.
+pub struct ModuSimPlugins;
+
+impl PluginGroup for ModuSimPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(SimulationPlugin::default())
+            .add(VariabilityPlugin)
+            .add(ModbusPlugin::default())
+            .add(ArbitrationPlugin)
+            .add(PhysicsPlugin)
+            .add(ConveyorPlugin)
+            .add(BottlePlugin)
+            .add(InfeedPlugin)
+            .add(ValvePlugin)
+            .add(ActuatorPlugin)
+            .add(PneumaticsPlugin)
+            .add(FeedbackPlugin)
+            .add(OverloadPlugin)
+            .add(JogPlugin)
+            .add(EnergyPlugin)
+            .add(SpillPlugin)
+            .add(ScorePlugin)
+            .add(TankPlugin)
+            .add(SensorPlugin)
+            .add(TagSyncPlugin)
+            .add(CounterPlugin)
+            .add(LightingPlugin)
+            .add(LabelerPlugin)
+            .add(VisionPlugin)
+            .add(BarcodePlugin)
+            .add(TrackingPlugin)
+            .add(HopperPlugin)
+            .add(CapperPlugin)
+            .add(HeaterPlugin)
+            .add(PidPlugin)
+            .add(StopperPlugin)
+            .add(AccumulationPlugin)
+            .add(DiverterPlugin)
+            .add(OutfeedPlugin)
+            .add(PalletizerPlugin)
+            .add(GantryPlugin)
+            .add(TurntablePlugin)
+            .add(StatsPlugin)
+            .add(OeePlugin)
+            .add(ReportPlugin::default())
+            .add(DataLoggerPlugin::default())
+            .add(RecipePlugin)
+            .add(AlarmPlugin)
+            .add(JamPlugin)
+            .add(TippingPlugin)
+            .add(WatchdogPlugin)
+            .add(SoundPlugin)
+            .add(OverlayPlugin)
+            .add(ProfilerPlugin)
+            .add(ModePlugin)
+            .add(ControlsPlugin)
+            .add(InspectorPlugin)
+            .add(HmiPlugin::default())
+            .add(ForcingPlugin)
+            .add(SnapshotPlugin::default())
+            .add(IoListPlugin::default())
+            .add(TimelinePlugin)
+            .add(S7Plugin::default())
+            .disable::<S7Plugin>()
+            .add(Dnp3Plugin::default())
+            .disable::<Dnp3Plugin>()
+            .add(WebApiPlugin::default())
+            .disable::<WebApiPlugin>()
+            .add(RestApiPlugin::default())
+            .disable::<RestApiPlugin>()
+            .add(GalleryPlugin::default())
+            .disable::<GalleryPlugin>()
+    }
+}
@@ -0,0 +1,196 @@
+// machine.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Extension point for machine types defined outside ModuSim.
+//!
+//! A crate adds a machine by implementing [`Machine`] and registering it from
+//! its own plugin with [`RegisterMachine::register_machine`]. Scenarios then
+//! place it with a `[[machines]]` table whose `kind` names the machine type;
+//! the table's other keys are parsed into the machine's [`Machine::Config`].
+//! Machines spawned through the [`MachineContext`] join the station layout,
+//! tag registry, I/O list, overlays and popups like any built-in device, and
+//! can raise [`Alarm::Machine`](crate::components::alarm::Alarm::Machine)
+//! alarms. Their behaviour is ordinary Bevy systems added by the same plugin.
+//!
+//! ```ignore
+//! struct Stamper;
+//!
+//! #[derive(Deserialize)]
+//! struct StamperConfig {
+//!     press_coil: u16,
+//!     position: [f32; 2],
+//! }
+//!
+//! impl Machine for Stamper {
+//!     const KIND: &'static str = "stamper";
+//!     type Config = StamperConfig;
+//!
+//!     fn spawn(config: &StamperConfig, context: &mut MachineContext) -> anyhow::Result<()> {
+//!         let press = context.coil("press", config.press_coil);
+//!         let position = context.position(config.position);
+//!         context.spawn((StamperPress(press), Transform::from_translation(position.extend(0.0))));
+//!         Ok(())
+//!     }
+//! }
+//!
+//! app.register_machine::<Stamper>();
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+
+use crate::components::{station::StationId, tags::DeviceName};
+use crate::environment::StationLayout;
+use crate::scenario::{MachineConfig, ScenarioEntity};
+
+// >>> Machines <<<
+/// A machine type that scenarios can place.
+pub trait Machine: 'static {
+    /// Name of the machine type in scenarios, e.g. `kind = "stamper"`.
+    const KIND: &'static str;
+
+    /// Settings of one machine, parsed from its `[[machines]]` table.
+    type Config: DeserializeOwned;
+
+    /// Registers the machine's tags and spawns its entities, once per station.
+    fn spawn(config: &Self::Config, context: &mut MachineContext) -> anyhow::Result<()>;
+}
+
+type SpawnMachine = fn(&MachineConfig, &mut MachineContext) -> anyhow::Result<()>;
+
+fn spawn_machine<M: Machine>(
+    config: &MachineConfig,
+    context: &mut MachineContext,
+) -> anyhow::Result<()> {
+    let settings: M::Config = toml::Value::Table(config.settings.clone())
+        .try_into()
+        .with_context(|| format!("Invalid settings for {} {}", M::KIND, config.name))?;
+    M::spawn(&settings, context)
+}
+
+/// Where a machine is being spawned: its station's layout, and the commands
+/// to spawn it with.
+pub struct MachineContext<'a, 'w, 's, 't> {
+    commands: &'a mut Commands<'w, 's>,
+    layout: &'a mut StationLayout<'t>,
+    station: StationId,
+    name: &'a str,
+}
+
+impl<'a, 'w, 's, 't> MachineContext<'a, 'w, 's, 't> {
+    pub(crate) fn new(
+        commands: &'a mut Commands<'w, 's>,
+        layout: &'a mut StationLayout<'t>,
+        station: StationId,
+        name: &'a str,
+    ) -> Self {
+        Self {
+            commands,
+            layout,
+            station,
+            name,
+        }
+    }
+
+    /// The machine's name, prefixed with its station's.
+    pub fn name(&self) -> String {
+        self.layout.name(self.name)
+    }
+
+    pub fn station(&self) -> StationId {
+        self.station
+    }
+
+    /// Shifts a scenario position onto the machine's station.
+    pub fn position(&self, position: [f32; 2]) -> Vec2 {
+        self.layout.position(position)
+    }
+
+    /// Registers the coil `<machine>.<tag>`, returning its station address.
+    pub fn coil(&mut self, tag: &str, address: u16) -> u16 {
+        self.layout.coil(&format!("{}.{}", self.name, tag), address)
+    }
+
+    /// Registers the discrete input `<machine>.<tag>`, returning its station address.
+    pub fn discrete_input(&mut self, tag: &str, address: u16) -> u16 {
+        self.layout
+            .discrete_input(&format!("{}.{}", self.name, tag), address)
+    }
+
+    /// Registers the input register `<machine>.<tag>`, returning its station address.
+    pub fn input_register(&mut self, tag: &str, address: u16) -> u16 {
+        self.layout
+            .input_register(&format!("{}.{}", self.name, tag), address)
+    }
+
+    /// Registers the holding register `<machine>.<tag>`, returning its station address.
+    pub fn holding_register(&mut self, tag: &str, address: u16) -> u16 {
+        self.layout
+            .holding_register(&format!("{}.{}", self.name, tag), address)
+    }
+
+    /// Spawns the machine's device, owning the tags registered so far. It is
+    /// named after the machine and torn down with the scenario.
+    pub fn spawn(&mut self, bundle: impl Bundle) -> EntityCommands<'_> {
+        let name = DeviceName(self.layout.name(self.name));
+        let register_map = self.layout.register_map();
+        self.commands
+            .spawn((bundle, self.station, ScenarioEntity, name, register_map))
+    }
+
+    /// Commands for any further entities, e.g. children of the device.
+    pub fn commands(&mut self) -> &mut Commands<'w, 's> {
+        self.commands
+    }
+}
+
+// >>> Resources <<<
+/// Machine types known to the scenario loader, by kind.
+#[derive(Resource, Default)]
+pub struct MachineRegistry {
+    kinds: HashMap<&'static str, SpawnMachine>,
+}
+
+impl MachineRegistry {
+    pub fn register<M: Machine>(&mut self) {
+        if self.kinds.insert(M::KIND, spawn_machine::<M>).is_some() {
+            warn!("Machine kind {} registered twice", M::KIND);
+        }
+    }
+
+    pub fn contains(&self, kind: &str) -> bool {
+        self.kinds.contains_key(kind)
+    }
+
+    /// Spawns a scenario's machine through its registered type.
+    pub(crate) fn spawn(
+        &self,
+        config: &MachineConfig,
+        context: &mut MachineContext,
+    ) -> anyhow::Result<()> {
+        let spawn = self
+            .kinds
+            .get(config.kind.as_str())
+            .with_context(|| format!("Unknown machine kind {}", config.kind))?;
+        spawn(config, context)
+    }
+}
+
+/// Registers machine types on an [`App`].
+pub trait RegisterMachine {
+    fn register_machine<M: Machine>(&mut self) -> &mut Self;
+}
+
+impl RegisterMachine for App {
+    fn register_machine<M: Machine>(&mut self) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<MachineRegistry>()
+            .register::<M>();
+        self
+    }
+}
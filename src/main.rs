@@ -2,34 +2,273 @@
 // Copyright (C) 2025 deciphr
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::path::Path;
 
+use bevy::picking::mesh_picking::MeshPickingPlugin;
 use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy::window::ExitCondition;
 
-mod components;
-mod environment;
-
-use components::modbus::{ModbusPlugin, ModbusState};
-use components::bottle::BottlePlugin;
-use components::conveyor::ConveyorPlugin;
-use components::sensor::SensorPlugin;
-use components::valve::ValvePlugin;
-use environment::setup_environment;
+use modusim::ModuSimPlugins;
+use modusim::components::bottle::ArrivalSchedule;
+use modusim::components::dnp3::Dnp3Plugin;
+use modusim::components::gallery::{GalleryPlugin, bundled_scenario};
+use modusim::components::hmi::HmiPlugin;
+use modusim::components::hotreload::HotReloadPlugin;
+use modusim::components::iolist::IoListPlugin;
+use modusim::components::logger::DataLoggerPlugin;
+use modusim::components::modbus::ModbusPlugin;
+use modusim::components::modbus_client::{ModbusClientConfig, ModbusClientPlugin};
+use modusim::components::report::ReportPlugin;
+use modusim::components::restapi::RestApiPlugin;
+use modusim::components::s7::S7Plugin;
+use modusim::components::webapi::WebApiPlugin;
+use modusim::components::simulation::{SimulationError, SimulationPlugin};
+use modusim::components::snapshot::SnapshotPlugin;
+use modusim::components::sound::SoundPlugin;
+use modusim::environment::EnvironmentPlugin;
+use modusim::scenario::{LAYOUT_SIZE, Scenario};
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        // .add_plugins(RapierDebugRenderPlugin::default())
-        .add_plugins(ModbusPlugin)
-        .add_plugins(ConveyorPlugin)
-        .add_plugins(BottlePlugin)
-        .add_plugins(ValvePlugin)
-        .add_plugins(SensorPlugin)
-        .init_resource::<ModbusState>()
-        .add_systems(Startup, setup_graphics)
-        .add_systems(Startup, setup_environment)
-        .run();
+    let mut app = App::new();
+
+    let mut simulation = SimulationPlugin::default();
+    if let Some(seed) = cli_arg("--seed") {
+        match seed.parse() {
+            Ok(seed) => simulation.seed = seed,
+            Err(err) => eprintln!("Invalid seed {seed}: {err}"),
+        }
+    }
+
+    let mut modbus = ModbusPlugin::default();
+    if let Some(port) = cli_arg("--modbus-port") {
+        match port.parse() {
+            Ok(port) => modbus.port = port,
+            Err(err) => eprintln!("Invalid Modbus port {port}: {err}"),
+        }
+    }
+
+    let mut snapshot = SnapshotPlugin::default();
+    if let Some(path) = cli_arg("--snapshot") {
+        snapshot.path = path.into();
+        snapshot.restore_on_start = true;
+    }
+
+    let mut io_list = IoListPlugin::default();
+    if let Some(path) = cli_arg("--export-io-list") {
+        io_list.path = path.into();
+        io_list.export_on_start = true;
+    }
+
+    let mut report = ReportPlugin::default();
+    if let Some(path) = cli_arg("--report") {
+        report.path = path.into();
+        report.export_on_exit = true;
+    }
+
+    let mut logger = DataLoggerPlugin::default();
+    if let Some(path) = cli_arg("--log") {
+        logger.path = path.into();
+        logger.record_on_start = true;
+    }
+    if let Some(tags) = cli_arg("--log-tags") {
+        logger.tags = tags.split(',').map(str::to_string).collect();
+    }
+    if let Some(rate) = cli_arg("--log-rate") {
+        match rate.parse() {
+            Ok(rate) => logger.rate = rate,
+            Err(err) => eprintln!("Invalid log rate {rate}: {err}"),
+        }
+    }
+
+    let hmi = HmiPlugin {
+        detached: cli_flag("--hmi-window"),
+    };
+
+    let mut plugins = ModuSimPlugins
+        .set(simulation)
+        .set(modbus)
+        .set(snapshot)
+        .set(io_list)
+        .set(report)
+        .set(logger)
+        .set(hmi);
+    if cli_flag("--s7") {
+        plugins = plugins.enable::<S7Plugin>();
+    }
+    if cli_flag("--dnp3") {
+        plugins = plugins.enable::<Dnp3Plugin>();
+    }
+    if cli_flag("--web-api") {
+        plugins = plugins.enable::<WebApiPlugin>();
+    }
+    if cli_flag("--rest-api") {
+        plugins = plugins.enable::<RestApiPlugin>();
+    }
+
+    // Closing the process view quits, even with the HMI window still open
+    let window = WindowPlugin {
+        exit_condition: ExitCondition::OnPrimaryClosed,
+        ..default()
+    };
+
+    if cfg!(feature = "tui") && cli_flag("--tui") {
+        #[cfg(feature = "tui")]
+        add_terminal_frontend(&mut app);
+        app.add_plugins(plugins.disable::<SoundPlugin>());
+    } else {
+        // Without a scenario on the command line, start at the exercise menu
+        let gallery = GalleryPlugin {
+            open_on_start: cli_arg("--scenario").is_none(),
+        };
+        plugins = plugins.set(gallery).enable::<GalleryPlugin>();
+        app.add_plugins(DefaultPlugins.set(window))
+            // Balls are meshes, which can only be clicked for the inspector with this on
+            .add_plugins(MeshPickingPlugin)
+            // .add_plugins(RapierDebugRenderPlugin::default())
+            .add_plugins(plugins)
+            .add_systems(Startup, setup_graphics);
+        #[cfg(feature = "trends")]
+        add_trend_chart(&mut app);
+    }
+    app.add_plugins(EnvironmentPlugin);
+
+    if let Some(path) = cli_arg("--scenario") {
+        // Bundled exercises are given by name, e.g. `--scenario sortation`
+        let bundled = bundled_scenario(&path);
+        let loaded = match bundled {
+            Some(entry) => entry.scenario(),
+            None => Scenario::from_toml(Path::new(&path)),
+        };
+        match loaded {
+            Ok(scenario) => {
+                app.insert_resource(scenario);
+            }
+            Err(err) => {
+                let message = format!("Failed to load scenario {path}: {err}");
+                eprintln!("{message}");
+                // Stop on the error screen rather than run the default line
+                app.world_mut().send_event(SimulationError(message));
+            }
+        }
+        if cli_flag("--watch") && bundled.is_none() {
+            app.add_plugins(HotReloadPlugin { path: path.into() });
+        }
+    }
+
+    if let Some(path) = cli_arg("--modbus-client") {
+        match ModbusClientConfig::from_toml(Path::new(&path)) {
+            Ok(config) => {
+                app.add_plugins(ModbusClientPlugin { config });
+            }
+            Err(err) => eprintln!("Failed to load Modbus client config {path}: {err}"),
+        }
+    }
+
+    if cfg!(feature = "tls") && cli_arg("--modbus-tls-cert").is_some() {
+        #[cfg(feature = "tls")]
+        add_modbus_tls(&mut app);
+    }
+
+    if let Some(path) = cli_arg("--arrivals") {
+        match ArrivalSchedule::from_csv(Path::new(&path)) {
+            Ok(schedule) => {
+                app.insert_resource(schedule);
+            }
+            Err(err) => eprintln!("Failed to load arrival schedule {path}: {err}"),
+        }
+    }
+
+    app.run();
+}
+
+fn cli_arg(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
+    }
+    None
+}
+
+fn cli_flag(name: &str) -> bool {
+    std::env::args().skip(1).any(|arg| arg == name)
+}
+
+/// Runs the plant without a window or GPU, drawn in the terminal instead.
+#[cfg(feature = "tui")]
+fn add_terminal_frontend(app: &mut App) {
+    use std::time::Duration;
+
+    use bevy::app::ScheduleRunnerPlugin;
+    use bevy::input::InputPlugin;
+    use modusim::components::tui::TuiPlugin;
+
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+            1.0 / 60.0,
+        ))),
+        TransformPlugin,
+        AssetPlugin::default(),
+        InputPlugin,
+        TuiPlugin,
+    ))
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>();
+
+    // Systems size the plant from the primary window
+    app.world_mut().spawn(Window::default());
+}
+
+/// Adds the trend chart, trending the tags given on the command line from
+/// startup.
+#[cfg(feature = "trends")]
+fn add_trend_chart(app: &mut App) {
+    use modusim::components::trends::TrendsPlugin;
+
+    let tags = cli_arg("--trends")
+        .map(|tags| tags.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+    app.add_plugins(TrendsPlugin { tags });
+}
+
+/// Serves the register tables over TLS as well, with the certificates given
+/// on the command line.
+#[cfg(feature = "tls")]
+fn add_modbus_tls(app: &mut App) {
+    use modusim::components::modbus_tls::{ModbusTlsPlugin, TlsCertificates};
+
+    let (Some(cert), Some(key)) = (cli_arg("--modbus-tls-cert"), cli_arg("--modbus-tls-key"))
+    else {
+        eprintln!("--modbus-tls-cert needs --modbus-tls-key");
+        return;
+    };
+    let mut tls = ModbusTlsPlugin::new(TlsCertificates {
+        cert: cert.into(),
+        key: key.into(),
+        client_ca: cli_arg("--modbus-tls-client-ca").map(Into::into),
+    });
+    if let Some(port) = cli_arg("--modbus-tls-port") {
+        match port.parse() {
+            Ok(port) => tls.port = port,
+            Err(err) => eprintln!("Invalid secure Modbus port {port}: {err}"),
+        }
+    }
+    app.add_plugins(tls);
 }
 
+/// Scales the view with the window, so the scenarios' layout fills it
+/// whatever its size.
 fn setup_graphics(mut commands: Commands) {
-    commands.spawn(Camera2d::default());
+    commands.spawn((
+        Camera2d,
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::AutoMin {
+                min_width: LAYOUT_SIZE.x,
+                min_height: LAYOUT_SIZE.y,
+            },
+            ..OrthographicProjection::default_2d()
+        }),
+    ));
 }
@@ -0,0 +1,1047 @@
+// scenario.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, ensure};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{
+    bottle::{BottlePhysics, InfeedProfile},
+    guide::GuideShape,
+    heater::ThermalModel,
+    hopper::HopperPart,
+    modbus::{ClientPolicy, NetworkImpairment},
+    physics::PhysicsSettings,
+    pid::PidMode,
+    sensor::{SensorOutputMode, SensorPolarity, SensorShape},
+    simulation::WorldBounds,
+    sound::SoundConfig,
+    tags::Scaling,
+    timeline::TimelineEntry,
+    valve::{BallLimit, LiquidModel},
+    variability::VariabilityConfig,
+    watchdog::Heartbeat,
+};
+
+// >>> Constants <<<
+/// World area the built-in scenarios are laid out in, kept in view by the
+/// camera whatever the window's size.
+pub const LAYOUT_SIZE: Vec2 = Vec2::new(1280.0, 720.0);
+
+// >>> Components <<<
+/// Marks entities spawned from a scenario so they can be torn down when another is loaded.
+#[derive(Component)]
+pub struct ScenarioEntity;
+
+// >>> Events <<<
+/// Replaces the running plant with a new scenario.
+#[derive(Event)]
+pub struct LoadScenario(pub Scenario);
+
+/// Applies an edited version of the running scenario, leaving products on the
+/// line.
+#[derive(Event)]
+pub struct ReloadScenario(pub Scenario);
+
+// >>> Devices <<<
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConveyorConfig {
+    pub name: String,
+    pub run_coil: u16,
+    pub speed_holding: u16,
+    #[serde(default)]
+    pub position: [f32; 2], // Unused when chained to an upstream conveyor
+    pub size: [f32; 2],
+    #[serde(default)]
+    pub incline: f32, // Degrees
+    #[serde(default)]
+    pub curve: Option<CurveConfig>,
+    #[serde(default)]
+    pub upstream: Option<String>, // Conveyor handing items onto this one
+    #[serde(default)]
+    pub gap: f32, // Pixels between the upstream conveyor's end and this one
+    #[serde(default)]
+    pub speed_feedback: Option<FeedbackConfig>, // Speed of the bottles on the belt in pixels per second
+    #[serde(default)]
+    pub overload: Option<OverloadConfig>,
+    #[serde(default)]
+    pub energy: Option<EnergyConfig>,
+    #[serde(default)]
+    pub infeed: Option<InfeedConfig>, // Only on conveyors without an upstream
+    #[serde(default)]
+    pub infeed_profile: InfeedProfile, // Only on conveyors without an upstream
+    #[serde(default)]
+    pub jog: Option<JogConfig>,
+}
+
+/// Jog and maintenance-mode coils of a conveyor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JogConfig {
+    pub forward_coil: u16,
+    pub reverse_coil: u16,
+    pub maintenance_coil: u16,
+    #[serde(default = "default_jog_speed")]
+    pub speed: f32, // Pixels per second
+}
+
+fn default_jog_speed() -> f32 {
+    30.0
+}
+
+/// Motor load monitoring of a conveyor, tripping it on overload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverloadConfig {
+    pub load_input: u16, // Percent of rated motor current
+    pub fault_discrete: u16,
+    pub reset_coil: u16,
+    #[serde(default = "default_rated_mass")]
+    pub rated_mass: f32, // Mass on the belt at 100 percent load
+    #[serde(default = "default_trip_load")]
+    pub trip_load: f32, // Percent
+    #[serde(default = "default_trip_delay")]
+    pub trip_delay: f32, // Seconds
+}
+
+fn default_rated_mass() -> f32 {
+    10.0
+}
+
+/// Power and energy metering of a conveyor motor or heater.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnergyConfig {
+    pub power_input: u16,  // W
+    pub energy_input: u16, // Wh, high word first across two registers
+    pub rated_power: f32,  // W at rated speed and mass, or at full heater power
+    #[serde(default = "default_rated_speed")]
+    pub rated_speed: f32, // Conveyors only, pixels per second
+    #[serde(default = "default_rated_mass")]
+    pub rated_mass: f32, // Conveyors only
+}
+
+fn default_rated_speed() -> f32 {
+    100.0
+}
+
+fn default_trip_load() -> f32 {
+    110.0
+}
+
+fn default_trip_delay() -> f32 {
+    2.0
+}
+
+/// PLC control of a conveyor's bottle infeed: a self-resetting coil spawning
+/// one bottle, a coil enabling timed spawning and the spawn interval in ms.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InfeedConfig {
+    pub spawn_coil: u16,
+    pub enable_coil: u16,
+    pub interval_holding: u16, // ms
+}
+
+/// Bends a conveyor into an arc around its position. Angles are in degrees.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurveConfig {
+    pub radius: f32,
+    pub start: f32,
+    pub sweep: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZoneConfig {
+    pub name: String,
+    pub start_x: f32,
+    pub end_x: f32,
+    pub eye_y: f32,
+    pub blocked_discrete: u16,
+    pub running_discrete: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValveConfig {
+    pub name: String,
+    pub open_coil: u16,
+    pub spawn_rate_holding: u16,
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub flow_feedback: Option<FeedbackConfig>, // Measured flow in balls per minute
+    #[serde(default)]
+    pub ball_limit: BallLimit,
+    #[serde(default)]
+    pub liquid: LiquidModel, // Balls, a drawn stream, or both
+    #[serde(default)]
+    pub travel: Option<ValveTravelConfig>, // Opens and closes instantly when unset
+    #[serde(default)]
+    pub opening_holding: Option<u16>, // Percent open, replacing the open coil
+}
+
+/// Actuator travel of a valve, with limit switches at both ends. Times are in seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValveTravelConfig {
+    pub open_time: f32,
+    pub close_time: f32,
+    #[serde(default = "default_travel_timeout")]
+    pub timeout: f32,
+    pub open_discrete: u16,
+    pub closed_discrete: u16,
+    pub fault_discrete: u16,
+}
+
+fn default_travel_timeout() -> f32 {
+    5.0
+}
+
+/// Input register reporting a device's actual process value, multiplied by `scale`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedbackConfig {
+    pub input: u16,
+    #[serde(default = "default_feedback_scale")]
+    pub scale: f32,
+}
+
+fn default_feedback_scale() -> f32 {
+    1.0
+}
+
+/// Built-in control loop for a heater or tank.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PidConfig {
+    pub setpoint_holding: u16,
+    pub gains_holding: u16, // Kp; Ki and Kd follow it
+    pub mode_holding: u16,
+    #[serde(default)]
+    pub setpoint: u16,
+    #[serde(default)]
+    pub kp: f32,
+    #[serde(default)]
+    pub ki: f32,
+    #[serde(default)]
+    pub kd: f32,
+    #[serde(default)]
+    pub mode: PidMode,
+}
+
+/// Supply tank feeding the station's valves, sized in balls' worth of liquid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TankConfig {
+    pub name: String,
+    pub pump_coil: u16,
+    pub level_input: u16,
+    pub low_discrete: u16,
+    pub capacity: f32,
+    pub low_level: f32,
+    pub pump_rate: f32,
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub pid: Option<PidConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelerConfig {
+    pub name: String,
+    pub enable_coil: u16,
+    pub defect_probability: f32,
+    pub position: [f32; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorItem {
+    Bottle,
+    Ball,
+    Cap,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorConfig {
+    pub name: String,
+    pub discrete_input: u16,
+    pub item: SensorItem,
+    pub position: [f32; 2],
+    pub color: [f32; 3],
+    #[serde(default)]
+    pub miss_rate: f32,
+    #[serde(default)]
+    pub variant: Option<String>,
+    #[serde(default)]
+    pub output: SensorOutputMode,
+    #[serde(default)]
+    pub polarity: SensorPolarity,
+    #[serde(default)]
+    pub distance: Option<DistanceConfig>,
+    #[serde(default)]
+    pub shape: SensorShape,
+    #[serde(default)]
+    pub rotation: f32, // Degrees, counter-clockwise
+}
+
+/// Input register reporting the distance in pixels from a sensor to the
+/// nearest item it detects along `direction`, up to `range`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DistanceConfig {
+    pub input: u16,
+    pub range: f32,
+    #[serde(default = "default_distance_direction")]
+    pub direction: [f32; 2], // Upstream along the belt by default
+}
+
+fn default_distance_direction() -> [f32; 2] {
+    [-1.0, 0.0]
+}
+
+/// Totalizer of a sensor's trips, reported across two input registers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CounterConfig {
+    pub name: String,
+    pub sensor: String,
+    pub count_input: u16, // High word; the low word follows it
+    pub reset_coil: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HopperConfig {
+    pub name: String,
+    pub part: HopperPart,
+    pub gate_coil: u16,
+    pub refill_coil: u16,
+    pub low_discrete: u16,
+    pub empty_discrete: u16,
+    pub capacity: u32,
+    pub low_level: u32,
+    pub release_interval: f32,
+    pub position: [f32; 2],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeaterConfig {
+    pub name: String,
+    pub power_holding: u16,
+    pub temperature_input: u16,
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub model: ThermalModel,
+    #[serde(default)]
+    pub pid: Option<PidConfig>,
+    #[serde(default)]
+    pub energy: Option<EnergyConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VisionConfig {
+    pub name: String,
+    pub trigger_coil: u16,
+    pub pass_discrete: u16,
+    pub result_input: u16,
+    #[serde(default)]
+    pub fill_range: Option<[u32; 2]>,
+    #[serde(default)]
+    pub require_cap: bool,
+    #[serde(default)]
+    pub require_label: bool,
+    pub processing_delay: f32,
+    pub position: [f32; 2],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BarcodeConfig {
+    pub name: String,
+    pub trigger_coil: u16,
+    pub read_ok_discrete: u16,
+    pub serial_input: u16, // First of two input registers, high word first
+    #[serde(default)]
+    pub no_read_rate: f32,
+    pub position: [f32; 2],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiverterConfig {
+    pub name: String,
+    pub lane_holding: u16,
+    pub position: [f32; 2],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LaneConfig {
+    pub lane: u16,
+    pub counter_input: u16,
+    pub position: [f32; 2],
+    pub width: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutfeedConfig {
+    pub name: String,
+    pub counter_input: u16,
+    pub position: [f32; 2],
+}
+
+/// Pick-and-place robot, with targets in pixels from the bottom-left of its reach.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GantryConfig {
+    pub name: String,
+    pub x_holding: u16,
+    pub y_holding: u16,
+    pub busy_discrete: u16,
+    pub in_position_discrete: u16,
+    pub gripper_coil: u16,
+    pub position: [f32; 2], // Bottom-left corner of the reach
+    pub reach: [f32; 2],
+    pub speed: f32,
+}
+
+/// Rotary indexing table turning bottles through evenly spaced stations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurntableConfig {
+    pub name: String,
+    pub index_coil: u16,
+    pub in_position_discrete: u16,
+    pub position_input: u16,
+    #[serde(default = "default_turntable_positions")]
+    pub positions: u16,
+    pub radius: f32,
+    #[serde(default = "default_turntable_speed")]
+    pub speed: f32, // Degrees per second
+    pub position: [f32; 2], // Centre of the disc
+}
+
+fn default_turntable_positions() -> u16 {
+    4
+}
+
+fn default_turntable_speed() -> f32 {
+    90.0
+}
+
+/// Passive geometry, a guide rail along the belt or a chute feeding it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuideConfig {
+    pub name: String,
+    pub shape: GuideShape,
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub rotation: f32, // Degrees, counter-clockwise
+    #[serde(default = "default_guide_friction")]
+    pub friction: f32,
+}
+
+fn default_guide_friction() -> f32 {
+    0.1
+}
+
+/// Retractable pin holding bottles at a workstation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StopperConfig {
+    pub name: String,
+    pub extend_coil: u16,
+    pub present_discrete: u16,
+    pub position: [f32; 2],
+}
+
+/// Floor area counting the liquid balls that miss the bottles.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpillZoneConfig {
+    pub name: String,
+    pub count_input: u16,
+    pub reset_coil: u16,
+    #[serde(default = "default_spill_threshold")]
+    pub threshold: u16, // Spilled balls at which the spill alarm is raised
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+}
+
+fn default_spill_threshold() -> u16 {
+    10
+}
+
+/// Scoring line counting the liquid balls that fall into, or past, the
+/// bottles passing under a valve.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DropScoreConfig {
+    pub name: String,
+    pub hits_input: u16,
+    pub misses_input: u16,
+    pub reset_coil: u16,
+    pub position: [f32; 2], // Centre of the line, just below the bottles' rims
+    pub width: f32,
+}
+
+/// Stretch of the line whose occupant is tracked for comparison with the
+/// PLC's tracking.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackingZoneConfig {
+    pub name: String,
+    pub serial_input: u16, // First of two input registers, high word first
+    pub status_input: u16,
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+}
+
+/// Chute stacking the caps that fall into it for the station's capper.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapFeederConfig {
+    pub name: String,
+    pub present_discrete: u16,
+    pub capacity: usize,
+    pub position: [f32; 2], // Centre of the outlet
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapperConfig {
+    pub name: String,
+    pub enable_coil: u16,
+    pub position: [f32; 2],
+}
+
+/// Stacks the station's completed bottles onto a pallet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PalletizerConfig {
+    pub name: String,
+    pub pattern_holding: u16,
+    pub layer_complete_discrete: u16,
+    pub pallet_complete_discrete: u16,
+    pub clear_coil: u16,
+    pub layers: u16,
+    pub position: [f32; 2],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LightingConfig {
+    pub reduced_coil: u16,
+    pub emergency_coil: u16,
+    #[serde(default)]
+    pub size: Option<[f32; 2]>, // Defaults to the window size
+}
+
+/// Plant-wide comms watchdog on a PLC heartbeat.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    pub heartbeat: Heartbeat,
+    pub timeout: f32, // Seconds
+    #[serde(default)]
+    pub safe_state: bool,
+}
+
+/// Plant-wide OEE measurement, published in hundredths of a percent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OeeConfig {
+    pub ideal_cycle_time: f32, // Seconds per bottle at full speed
+    pub availability_input: u16,
+    pub performance_input: u16,
+    pub quality_input: u16,
+    pub oee_input: u16,
+    #[serde(default)]
+    pub min_fill: u32, // Balls a good bottle holds at least
+    #[serde(default)]
+    pub require_cap: bool,
+    #[serde(default)]
+    pub require_label: bool,
+}
+
+/// Plant-wide compressed air supply of the pneumatic actuators, in kPa.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PneumaticsConfig {
+    pub compressor_coil: u16,
+    pub pressure_input: u16,
+    #[serde(default = "default_supply_pressure")]
+    pub supply_pressure: f32, // kPa
+    #[serde(default = "default_low_pressure")]
+    pub low_pressure: f32, // kPa below which actuators stop working
+    #[serde(default = "default_compressor_rate")]
+    pub compressor_rate: f32, // kPa per second
+    #[serde(default = "default_leak_rate")]
+    pub leak_rate: f32, // kPa per second
+    #[serde(default = "default_stroke_consumption")]
+    pub stroke_consumption: f32, // kPa per actuator stroke
+}
+
+fn default_supply_pressure() -> f32 {
+    600.0
+}
+
+fn default_low_pressure() -> f32 {
+    400.0
+}
+
+fn default_compressor_rate() -> f32 {
+    50.0
+}
+
+fn default_leak_rate() -> f32 {
+    2.0
+}
+
+fn default_stroke_consumption() -> f32 {
+    30.0
+}
+
+/// Auto/manual selector deciding whether the PLC or the local controls drive
+/// the actuators.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModeConfig {
+    pub auto_coil: u16,
+    #[serde(default)]
+    pub auto: bool, // Start in auto
+}
+
+/// Write priority between the HMI and remote clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArbitrationConfig {
+    pub hmi_hold: f64, // Seconds an HMI write holds off remote writes
+}
+
+/// A machine of a type registered by another crate, see [`crate::machine`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MachineConfig {
+    pub kind: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub settings: toml::Table, // Parsed into the machine type's own config
+}
+
+/// A copy of the line, shifted so several can run side by side.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StationConfig {
+    pub name: String, // Prefixed to the station's tag names
+    #[serde(default)]
+    pub offset: [f32; 2],
+    #[serde(default)]
+    pub address_offset: u16, // Added to every address of the station's devices
+}
+
+// >>> Scenario <<<
+/// Layout and I/O map of a plant, loadable from TOML.
+///
+/// The devices make up one line, instantiated once per station. Without
+/// stations the line runs once, unshifted and with unprefixed tags.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    pub name: String,
+    pub stations: Vec<StationConfig>,
+    pub conveyors: Vec<ConveyorConfig>,
+    pub zones: Vec<ZoneConfig>,
+    pub valves: Vec<ValveConfig>,
+    pub tanks: Vec<TankConfig>,
+    pub labelers: Vec<LabelerConfig>,
+    pub sensors: Vec<SensorConfig>,
+    pub counters: Vec<CounterConfig>,
+    pub hoppers: Vec<HopperConfig>,
+    pub cap_feeders: Vec<CapFeederConfig>,
+    pub cappers: Vec<CapperConfig>,
+    pub heaters: Vec<HeaterConfig>,
+    pub vision_sensors: Vec<VisionConfig>,
+    pub barcode_scanners: Vec<BarcodeConfig>,
+    pub diverters: Vec<DiverterConfig>,
+    pub lanes: Vec<LaneConfig>,
+    pub outfeeds: Vec<OutfeedConfig>,
+    pub stoppers: Vec<StopperConfig>,
+    pub spill_zones: Vec<SpillZoneConfig>,
+    pub drop_scores: Vec<DropScoreConfig>,
+    pub tracking_zones: Vec<TrackingZoneConfig>,
+    pub palletizers: Vec<PalletizerConfig>,
+    pub gantries: Vec<GantryConfig>,
+    pub turntables: Vec<TurntableConfig>,
+    pub guide_rails: Vec<GuideConfig>,
+    pub chutes: Vec<GuideConfig>,
+    pub machines: Vec<MachineConfig>,
+    pub lighting: Option<LightingConfig>,
+    pub watchdog: Option<WatchdogConfig>,
+    pub oee: Option<OeeConfig>,
+    pub pneumatics: Option<PneumaticsConfig>,
+    pub mode: Option<ModeConfig>,
+    pub arbitration: Option<ArbitrationConfig>,
+    pub scaling: BTreeMap<String, Scaling>, // By tag name, without a station's prefix
+    pub bounds: WorldBounds,
+    pub bottle_physics: BottlePhysics,
+    pub physics: PhysicsSettings,
+    pub variability: VariabilityConfig,
+    pub network: NetworkImpairment,
+    pub clients: ClientPolicy,
+    pub sound: SoundConfig,
+    pub timeline: Vec<TimelineEntry>,
+}
+
+impl Scenario {
+    pub fn from_toml(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        let scenario: Self = toml::from_str(contents)?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Rejects settings the plant cannot run with, rather than leaving them
+    /// to misbehave once spawned.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for turntable in &self.turntables {
+            ensure!(
+                turntable.radius > 0.0,
+                "Turntable {} needs a positive radius",
+                turntable.name
+            );
+            ensure!(
+                turntable.speed > 0.0,
+                "Turntable {} needs a positive speed",
+                turntable.name
+            );
+        }
+        let variability = &self.variability;
+        for (setting, jitter) in [
+            ("spawn_jitter", variability.spawn_jitter),
+            ("ball_size_jitter", variability.ball_size_jitter),
+            ("ball_mass_jitter", variability.ball_mass_jitter),
+            (
+                "sensor_threshold_jitter",
+                variability.sensor_threshold_jitter,
+            ),
+            ("speed_ripple", variability.speed_ripple),
+        ] {
+            ensure!(
+                (0.0..1.0).contains(&jitter),
+                "Variability {setting} must be a fraction from 0 up to 1"
+            );
+        }
+        ensure!(
+            variability.sensor_delay_jitter.is_finite() && variability.sensor_delay_jitter >= 0.0,
+            "Variability sensor_delay_jitter must be seconds of at least 0"
+        );
+        self.network.validate()?;
+        self.physics.validate()?;
+        for zone in &self.spill_zones {
+            ensure!(
+                zone.threshold >= 1,
+                "Spill zone {} needs a threshold of at least 1",
+                zone.name
+            );
+        }
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Top-level sections (e.g. `sensors`, `bounds`) that differ between two
+    /// scenarios.
+    pub fn changed_sections(&self, other: &Scenario) -> Vec<String> {
+        let (Ok(toml::Value::Table(old)), Ok(toml::Value::Table(new))) =
+            (toml::Value::try_from(self), toml::Value::try_from(other))
+        else {
+            return vec!["scenario".to_string()];
+        };
+
+        let mut sections: Vec<String> = old
+            .iter()
+            .filter(|(section, value)| new.get(*section) != Some(*value))
+            .map(|(section, _)| section.clone())
+            .collect();
+        sections.extend(
+            new.keys()
+                .filter(|section| !old.contains_key(*section))
+                .cloned(),
+        );
+        sections
+    }
+
+    /// A scenario with no devices.
+    pub fn empty(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            stations: Vec::new(),
+            conveyors: Vec::new(),
+            zones: Vec::new(),
+            valves: Vec::new(),
+            tanks: Vec::new(),
+            labelers: Vec::new(),
+            sensors: Vec::new(),
+            counters: Vec::new(),
+            hoppers: Vec::new(),
+            cap_feeders: Vec::new(),
+            cappers: Vec::new(),
+            heaters: Vec::new(),
+            vision_sensors: Vec::new(),
+            barcode_scanners: Vec::new(),
+            diverters: Vec::new(),
+            lanes: Vec::new(),
+            outfeeds: Vec::new(),
+            stoppers: Vec::new(),
+            spill_zones: Vec::new(),
+            drop_scores: Vec::new(),
+            tracking_zones: Vec::new(),
+            palletizers: Vec::new(),
+            gantries: Vec::new(),
+            turntables: Vec::new(),
+            guide_rails: Vec::new(),
+            chutes: Vec::new(),
+            machines: Vec::new(),
+            lighting: None,
+            watchdog: None,
+            oee: None,
+            pneumatics: None,
+            mode: None,
+            arbitration: None,
+            scaling: BTreeMap::new(),
+            bounds: WorldBounds::default(),
+            bottle_physics: BottlePhysics::default(),
+            physics: PhysicsSettings::default(),
+            variability: VariabilityConfig::default(),
+            network: NetworkImpairment::default(),
+            clients: ClientPolicy::default(),
+            sound: SoundConfig::default(),
+            timeline: Vec::new(),
+        }
+    }
+
+    /// The catch the drop exercise: bottles ride a belt under a valve high
+    /// above it, and the PLC must time each opening of the valve against the
+    /// belt speed so the balls land in the moving bottles. A scoring line
+    /// across the bottles' rims counts the hits and misses.
+    pub fn catch_the_drop() -> Self {
+        Self {
+            conveyors: vec![ConveyorConfig {
+                name: "conveyor".to_string(),
+                run_coil: 0x0000,
+                speed_holding: 0x0000,
+                position: [-40.0, -150.0],
+                size: [1200.0, 100.0],
+                incline: 0.0,
+                curve: None,
+                upstream: None,
+                gap: 0.0,
+                speed_feedback: None,
+                overload: None,
+                energy: None,
+                infeed: None,
+                infeed_profile: InfeedProfile::Steady,
+                jog: None,
+            }],
+            valves: vec![ValveConfig {
+                name: "valve".to_string(),
+                open_coil: 0x0001,
+                spawn_rate_holding: 0x0001,
+                position: [160.0, 250.0],
+                flow_feedback: None,
+                ball_limit: BallLimit {
+                    lifetime: 10.0, // Clears misses off the belt
+                    ..BallLimit::default()
+                },
+                liquid: LiquidModel::Balls,
+                travel: None,
+                opening_holding: None,
+            }],
+            sensors: vec![SensorConfig {
+                name: "bottle_sensor".to_string(),
+                discrete_input: 0x0000,
+                item: SensorItem::Bottle,
+                position: [-240.0, -100.0],
+                color: [1.0, 0.0, 0.0],
+                miss_rate: 0.0,
+                variant: None,
+                output: SensorOutputMode::Momentary,
+                polarity: SensorPolarity::NormallyOpen,
+                distance: None,
+                shape: SensorShape::default(),
+                rotation: 0.0,
+            }],
+            outfeeds: vec![OutfeedConfig {
+                name: "outfeed".to_string(),
+                counter_input: 0x0002,
+                position: [540.0, -80.0],
+            }],
+            drop_scores: vec![DropScoreConfig {
+                name: "score".to_string(),
+                hits_input: 0x0000,
+                misses_input: 0x0001,
+                reset_coil: 0x0002,
+                position: [-40.0, -10.0],
+                width: 1200.0,
+            }],
+            ..Self::empty("catch_the_drop")
+        }
+    }
+}
+
+impl Default for Scenario {
+    /// The bottle filling line, laid out in [`LAYOUT_SIZE`].
+    fn default() -> Self {
+        let zone_length = 180.0;
+        let conveyor_end = 320.0;
+        let lane_width = 90.0;
+
+        Self {
+            name: "filling_line".to_string(),
+            stations: Vec::new(),
+            conveyors: vec![ConveyorConfig {
+                name: "conveyor".to_string(),
+                run_coil: 0x0000,
+                speed_holding: 0x0000,
+                position: [-160.0, -150.0],
+                size: [960.0, 100.0],
+                incline: 0.0,
+                curve: None,
+                upstream: None,
+                gap: 0.0,
+                speed_feedback: Some(FeedbackConfig {
+                    input: 0x000B,
+                    scale: 1.0,
+                }),
+                overload: None,
+                energy: None,
+                infeed: None,
+                infeed_profile: InfeedProfile::Steady,
+                jog: None,
+            }],
+            zones: [-460.0, -280.0, -100.0]
+                .into_iter()
+                .enumerate()
+                .map(|(i, end_x)| ZoneConfig {
+                    name: format!("zone{}", i + 1),
+                    start_x: end_x - zone_length,
+                    end_x,
+                    eye_y: -90.0,
+                    blocked_discrete: 0x0005 + 2 * i as u16,
+                    running_discrete: 0x0006 + 2 * i as u16,
+                })
+                .collect(),
+            valves: vec![ValveConfig {
+                name: "valve".to_string(),
+                open_coil: 0x0001,
+                spawn_rate_holding: 0x0001,
+                position: [-30.0, 70.0],
+                flow_feedback: Some(FeedbackConfig {
+                    input: 0x000C,
+                    scale: 1.0,
+                }),
+                ball_limit: BallLimit::default(),
+                liquid: LiquidModel::default(),
+                travel: None,
+                opening_holding: None,
+            }],
+            tanks: Vec::new(),
+            labelers: vec![LabelerConfig {
+                name: "labeler".to_string(),
+                enable_coil: 0x0004,
+                defect_probability: 0.1,
+                position: [-250.0, -60.0],
+            }],
+            sensors: vec![
+                SensorConfig {
+                    name: "bottle_sensor".to_string(),
+                    discrete_input: 0x0000,
+                    item: SensorItem::Bottle,
+                    position: [0.0, -100.0],
+                    color: [1.0, 0.0, 0.0],
+                    miss_rate: 0.05,
+                    variant: None,
+                    output: SensorOutputMode::Momentary,
+                    polarity: SensorPolarity::NormallyOpen,
+                    distance: None,
+                    shape: SensorShape::default(),
+                    rotation: 0.0,
+                },
+                SensorConfig {
+                    name: "water_sensor".to_string(),
+                    discrete_input: 0x0001,
+                    item: SensorItem::Ball,
+                    position: [0.0, 0.0],
+                    color: [0.0, 0.0, 1.0],
+                    miss_rate: 0.0,
+                    variant: None,
+                    output: SensorOutputMode::Momentary,
+                    polarity: SensorPolarity::NormallyOpen,
+                    distance: None,
+                    shape: SensorShape::default(),
+                    rotation: 0.0,
+                },
+            ],
+            counters: vec![CounterConfig {
+                name: "bottle_counter".to_string(),
+                sensor: "bottle_sensor".to_string(),
+                count_input: 0x0007,
+                reset_coil: 0x000D,
+            }],
+            hoppers: vec![HopperConfig {
+                name: "cap_hopper".to_string(),
+                part: HopperPart::Cap,
+                gate_coil: 0x0007,
+                refill_coil: 0x0008,
+                low_discrete: 0x0003,
+                empty_discrete: 0x0004,
+                capacity: 20,
+                low_level: 5,
+                release_interval: 1.0,
+                position: [80.0, 70.0],
+            }],
+            cap_feeders: Vec::new(),
+            cappers: Vec::new(),
+            heaters: Vec::new(),
+            vision_sensors: vec![VisionConfig {
+                name: "vision".to_string(),
+                trigger_coil: 0x0006,
+                pass_discrete: 0x0002,
+                result_input: 0x0000,
+                fill_range: Some([6, 14]),
+                require_cap: false,
+                require_label: true,
+                processing_delay: 0.25,
+                position: [150.0, -80.0],
+            }],
+            barcode_scanners: Vec::new(),
+            diverters: vec![DiverterConfig {
+                name: "diverter".to_string(),
+                lane_holding: 0x0003,
+                position: [conveyor_end - 40.0, -80.0],
+            }],
+            lanes: (1..=3u16)
+                .map(|lane| LaneConfig {
+                    lane,
+                    counter_input: 0x0001 + (lane - 1),
+                    position: [conveyor_end + lane_width * (lane as f32 - 0.5) + 10.0, -300.0],
+                    width: lane_width - 10.0,
+                })
+                .collect(),
+            outfeeds: vec![OutfeedConfig {
+                name: "outfeed".to_string(),
+                counter_input: 0x0006,
+                position: [conveyor_end - 10.0, -80.0],
+            }],
+            stoppers: Vec::new(),
+            spill_zones: Vec::new(),
+            drop_scores: Vec::new(),
+            tracking_zones: Vec::new(),
+            palletizers: Vec::new(),
+            gantries: Vec::new(),
+            turntables: Vec::new(),
+            guide_rails: Vec::new(),
+            chutes: Vec::new(),
+            machines: Vec::new(),
+            lighting: Some(LightingConfig {
+                reduced_coil: 0x0002,
+                emergency_coil: 0x0003,
+                size: None,
+            }),
+            watchdog: None,
+            oee: None,
+            pneumatics: None,
+            mode: None,
+            arbitration: None,
+            scaling: BTreeMap::new(),
+            bounds: WorldBounds::default(),
+            bottle_physics: BottlePhysics::default(),
+            physics: PhysicsSettings::default(),
+            variability: VariabilityConfig::default(),
+            network: NetworkImpairment::default(),
+            clients: ClientPolicy::default(),
+            sound: SoundConfig::default(),
+            timeline: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+// testing.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::time::Duration;
+
+use anyhow::bail;
+use bevy::asset::AssetPlugin;
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::ModuSimPlugins;
+use crate::components::{
+    arbitration::WriteOrigin,
+    bottle::ArrivalSchedule,
+    modbus::{ModbusPlugin, ModbusState},
+    simulation::{DEFAULT_SEED, FIXED_TIMESTEP_HZ, SimulationPlugin},
+    sound::SoundPlugin,
+    tags::TagKind,
+};
+use crate::environment::EnvironmentPlugin;
+use crate::scenario::Scenario;
+
+// >>> Builder <<<
+/// Assembles the plant headlessly for integration tests.
+///
+/// No window is opened, no sound is played and no Modbus server is started;
+/// registers are read and written directly through the [`Simulation`].
+pub struct SimulationBuilder {
+    seed: u64,
+    with_environment: bool,
+    scenario: Option<Scenario>,
+    arrivals: Option<ArrivalSchedule>,
+}
+
+impl Default for SimulationBuilder {
+    fn default() -> Self {
+        Self {
+            seed: DEFAULT_SEED,
+            with_environment: true,
+            scenario: None,
+            arrivals: None,
+        }
+    }
+}
+
+impl SimulationBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Skips spawning the default plant, leaving an empty world to populate by hand.
+    pub fn without_environment(mut self) -> Self {
+        self.with_environment = false;
+        self
+    }
+
+    /// Spawns the given scenario instead of the default filling line.
+    pub fn scenario(mut self, scenario: Scenario) -> Self {
+        self.scenario = Some(scenario);
+        self
+    }
+
+    pub fn arrivals(mut self, schedule: ArrivalSchedule) -> Self {
+        self.arrivals = Some(schedule);
+        self
+    }
+
+    pub fn build(self) -> Simulation {
+        let mut app = App::new();
+
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            AssetPlugin::default(),
+            InputPlugin,
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / FIXED_TIMESTEP_HZ,
+        )))
+        .add_plugins(
+            ModuSimPlugins
+                .set(SimulationPlugin { seed: self.seed })
+                .disable::<ModbusPlugin>()
+                .disable::<SoundPlugin>(),
+        );
+
+        // Systems size the plant from the primary window
+        app.world_mut().spawn(Window::default());
+
+        if self.with_environment {
+            app.add_plugins(EnvironmentPlugin);
+        }
+        if let Some(scenario) = self.scenario {
+            app.insert_resource(scenario);
+        }
+        if let Some(schedule) = self.arrivals {
+            app.insert_resource(schedule);
+        }
+
+        app.finish();
+        app.cleanup();
+        app.update();
+
+        Simulation { app }
+    }
+}
+
+// >>> Simulation <<<
+/// A headless plant driven tick by tick.
+pub struct Simulation {
+    app: App,
+}
+
+impl Simulation {
+    /// Runs the given number of fixed timesteps.
+    pub fn advance(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.app.update();
+        }
+    }
+
+    /// Runs fixed timesteps until at least `seconds` of simulated time have passed.
+    pub fn advance_secs(&mut self, seconds: f32) {
+        self.advance((seconds as f64 * FIXED_TIMESTEP_HZ).ceil() as u32);
+    }
+
+    pub fn read_coil(&self, address: u16) -> Option<bool> {
+        let modbus_state = self.app.world().resource::<ModbusState>();
+        modbus_state.coils.lock().ok()?.get(&address).copied()
+    }
+
+    pub fn read_discrete_input(&self, address: u16) -> Option<bool> {
+        let modbus_state = self.app.world().resource::<ModbusState>();
+        modbus_state.discrete_inputs.lock().ok()?.get(&address).copied()
+    }
+
+    pub fn read_input_register(&self, address: u16) -> Option<u16> {
+        let modbus_state = self.app.world().resource::<ModbusState>();
+        modbus_state.input_registers.lock().ok()?.get(&address).copied()
+    }
+
+    pub fn read_holding(&self, address: u16) -> Option<u16> {
+        let modbus_state = self.app.world().resource::<ModbusState>();
+        modbus_state.holding_registers.lock().ok()?.get(&address).copied()
+    }
+
+    /// Writes a coil as a remote client, failing like the Modbus server on
+    /// unregistered addresses.
+    pub fn write_coil(&mut self, address: u16, value: bool) -> anyhow::Result<()> {
+        let modbus_state = self.app.world().resource::<ModbusState>();
+        let origin = WriteOrigin::remote("test");
+        if !modbus_state.write(TagKind::Coil, address, value as u16, &origin) {
+            bail!("Illegal coil address {address:#06x}");
+        }
+        Ok(())
+    }
+
+    /// Writes a holding register as a remote client, failing like the Modbus
+    /// server on unregistered addresses.
+    pub fn write_holding(&mut self, address: u16, value: u16) -> anyhow::Result<()> {
+        let modbus_state = self.app.world().resource::<ModbusState>();
+        let origin = WriteOrigin::remote("test");
+        if !modbus_state.write(TagKind::HoldingRegister, address, value, &origin) {
+            bail!("Illegal holding register address {address:#06x}");
+        }
+        Ok(())
+    }
+
+    /// Lists every entity carrying component `T`.
+    pub fn query_entities<T: Component>(&mut self) -> Vec<Entity> {
+        let world = self.app.world_mut();
+        let mut query = world.query_filtered::<Entity, With<T>>();
+        query.iter(world).collect()
+    }
+
+    pub fn resource<R: Resource>(&self) -> &R {
+        self.app.world().resource::<R>()
+    }
+
+    pub fn world(&self) -> &World {
+        self.app.world()
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+}
@@ -0,0 +1,293 @@
+// dnp3_outstation.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::net::SocketAddr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use modusim::components::dnp3::{Dnp3Layout, Dnp3ServerHandle, build_layout};
+use modusim::components::modbus::ModbusState;
+use modusim::components::tags::TagRegistry;
+
+const OUTSTATION: u16 = 10;
+const MASTER: u16 = 1;
+const LINK_BLOCK_SIZE: usize = 16;
+const MASTER_UNCONFIRMED_USER_DATA: u8 = 0xC4; // Direction and primary bits, function 4
+const MASTER_REQUEST_LINK_STATUS: u8 = 0xC9;
+
+const FIR: u8 = 0x80;
+const FIN: u8 = 0x40;
+const CON: u8 = 0x20;
+const RESPONSE: u8 = 0x81;
+const DEVICE_RESTART: u8 = 0x80;
+
+/// DNP3's CRC-16, as the masters compute it.
+fn crc(bytes: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in bytes {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA6BC
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn link_frame(control: u8, user_data: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x05, 0x64, (user_data.len() + 5) as u8, control];
+    frame.extend_from_slice(&OUTSTATION.to_le_bytes());
+    frame.extend_from_slice(&MASTER.to_le_bytes());
+    frame.extend_from_slice(&crc(&frame).to_le_bytes());
+    for block in user_data.chunks(LINK_BLOCK_SIZE) {
+        frame.extend_from_slice(block);
+        frame.extend_from_slice(&crc(block).to_le_bytes());
+    }
+    frame
+}
+
+/// Reads one link frame, checking every CRC in it.
+///
+/// # Return
+/// The control octet and the user data
+async fn read_link_frame(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let mut header = [0u8; 10];
+    stream.read_exact(&mut header).await.unwrap();
+    assert_eq!(header[..2], [0x05, 0x64]);
+    assert_eq!(
+        u16::from_le_bytes([header[8], header[9]]),
+        crc(&header[..8])
+    );
+    assert_eq!(u16::from_le_bytes([header[4], header[5]]), MASTER);
+    assert_eq!(u16::from_le_bytes([header[6], header[7]]), OUTSTATION);
+
+    let length = header[2] as usize - 5;
+    let mut body = vec![0u8; length + 2 * length.div_ceil(LINK_BLOCK_SIZE)];
+    stream.read_exact(&mut body).await.unwrap();
+    let mut user_data = Vec::new();
+    for block in body.chunks(LINK_BLOCK_SIZE + 2) {
+        let (data, block_crc) = block.split_at(block.len() - 2);
+        assert_eq!(u16::from_le_bytes([block_crc[0], block_crc[1]]), crc(data));
+        user_data.extend_from_slice(data);
+    }
+    (header[3], user_data)
+}
+
+/// Reads transport segments until one finishes the fragment.
+async fn read_fragment(stream: &mut TcpStream) -> Vec<u8> {
+    let mut fragment = Vec::new();
+    loop {
+        let (_, segment) = read_link_frame(stream).await;
+        let (&header, data) = segment.split_first().unwrap();
+        assert_eq!(
+            header & 0x40 != 0,
+            fragment.is_empty(),
+            "FIR on first segment only"
+        );
+        fragment.extend_from_slice(data);
+        if header & 0x80 != 0 {
+            return fragment;
+        }
+    }
+}
+
+/// Sends an application request in a single transport segment.
+async fn send(stream: &mut TcpStream, request: &[u8]) {
+    let mut segment = vec![0xC0]; // FIR and FIN, transport sequence 0
+    segment.extend_from_slice(request);
+    let frame = link_frame(MASTER_UNCONFIRMED_USER_DATA, &segment);
+    stream.write_all(&frame).await.unwrap();
+}
+
+async fn request(stream: &mut TcpStream, request: &[u8]) -> Vec<u8> {
+    send(stream, request).await;
+    read_fragment(stream).await
+}
+
+/// Serves a coil `run` at 1, a discrete input `sensor` at 2, an input
+/// register `level` at 3 reading 500 and a holding register `speed` at 4
+/// holding 100, as BO0, BI0, AI0 and AO0.
+fn start_outstation() -> (ModbusState, Dnp3ServerHandle) {
+    let mut tags = TagRegistry::default();
+    tags.coil("run", 1);
+    tags.discrete_input("sensor", 2);
+    tags.input_register("level", 3);
+    tags.holding_register("speed", 4);
+
+    let state = ModbusState::default();
+    state.coils.lock().unwrap().insert(1, false);
+    state.discrete_inputs.lock().unwrap().insert(2, true);
+    state.input_registers.lock().unwrap().insert(3, 500);
+    state.holding_registers.lock().unwrap().insert(4, 100);
+
+    start(&tags, &state)
+}
+
+fn start(tags: &TagRegistry, state: &ModbusState) -> (ModbusState, Dnp3ServerHandle) {
+    let layout = Dnp3Layout::default();
+    layout.set(build_layout(tags, &[]));
+    let handle = Dnp3ServerHandle::start(state.clone(), layout, 0, OUTSTATION);
+    (state.clone(), handle)
+}
+
+async fn connect(handle: &Dnp3ServerHandle) -> TcpStream {
+    let socket_addr = SocketAddr::from(([127, 0, 0, 1], handle.port()));
+    TcpStream::connect(socket_addr).await.unwrap()
+}
+
+/// Latch on for BO0 as a control relay output block, with the given status.
+fn latch_on(status: u8) -> Vec<u8> {
+    let mut objects = vec![12, 1, 0x17, 1, 0, 0x03, 1];
+    objects.extend_from_slice(&100u32.to_le_bytes());
+    objects.extend_from_slice(&0u32.to_le_bytes());
+    objects.push(status);
+    objects
+}
+
+#[test]
+fn crc_matches_the_standard_check_value() {
+    assert_eq!(crc(b"123456789"), 0xEA82);
+    assert_eq!(crc(&[]), 0xFFFF);
+}
+
+#[tokio::test]
+async fn link_frames_are_answered_with_valid_crcs() {
+    let (_state, handle) = start_outstation();
+    let mut stream = connect(&handle).await;
+
+    let frame = link_frame(MASTER_REQUEST_LINK_STATUS, &[]);
+    stream.write_all(&frame).await.unwrap();
+    let (control, user_data) = read_link_frame(&mut stream).await;
+    assert_eq!(control & 0x0F, 0x0B); // Link status
+    assert!(user_data.is_empty());
+}
+
+#[tokio::test]
+async fn corrupt_frames_drop_the_connection() {
+    let (_state, handle) = start_outstation();
+    let mut stream = connect(&handle).await;
+
+    let mut frame = link_frame(
+        MASTER_UNCONFIRMED_USER_DATA,
+        &[0xC0, 0xC0, 0x01, 60, 1, 0x06],
+    );
+    let last = frame.len() - 1;
+    frame[last] ^= 0xFF;
+    stream.write_all(&frame).await.unwrap();
+    assert_eq!(stream.read(&mut [0u8; 1]).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn requests_split_over_segments_are_reassembled() {
+    let (_state, handle) = start_outstation();
+    let mut stream = connect(&handle).await;
+
+    // A read of BI0 in two transport segments
+    let first = link_frame(MASTER_UNCONFIRMED_USER_DATA, &[0x40, 0xC1, 0x01, 1]);
+    let last = link_frame(MASTER_UNCONFIRMED_USER_DATA, &[0x81, 2, 0x00, 0, 0]);
+    stream.write_all(&first).await.unwrap();
+    stream.write_all(&last).await.unwrap();
+
+    let response = read_fragment(&mut stream).await;
+    assert_eq!(response[..2], [FIR | FIN | 1, RESPONSE]);
+    assert_eq!(response[4..], [1, 2, 0x01, 0, 0, 0, 0, 0x81]);
+}
+
+#[tokio::test]
+async fn class_0_polls_read_every_point() {
+    let (_state, handle) = start_outstation();
+    let mut stream = connect(&handle).await;
+
+    let response = request(&mut stream, &[0xC0, 0x01, 60, 1, 0x06]).await;
+    assert_eq!(response[..4], [FIR | FIN, RESPONSE, DEVICE_RESTART, 0]);
+
+    let mut expected = vec![1, 2, 0x01, 0, 0, 0, 0, 0x81];
+    expected.extend([10, 2, 0x01, 0, 0, 0, 0, 0x01]);
+    expected.extend([30, 1, 0x01, 0, 0, 0, 0, 0x01]);
+    expected.extend(500i32.to_le_bytes());
+    expected.extend([40, 1, 0x01, 0, 0, 0, 0, 0x01]);
+    expected.extend(100i32.to_le_bytes());
+    assert_eq!(response[4..], expected);
+}
+
+#[tokio::test]
+async fn select_before_operate_sets_the_output() {
+    let (state, handle) = start_outstation();
+    let mut stream = connect(&handle).await;
+
+    let mut select = vec![0xC1, 0x03];
+    select.extend(latch_on(0));
+    let response = request(&mut stream, &select).await;
+    assert_eq!(response[4..], latch_on(0));
+    assert!(!state.coils.lock().unwrap()[&1]);
+
+    let mut operate = vec![0xC2, 0x04];
+    operate.extend(latch_on(0));
+    let response = request(&mut stream, &operate).await;
+    assert_eq!(response[4..], latch_on(0));
+    assert!(state.coils.lock().unwrap()[&1]);
+}
+
+#[tokio::test]
+async fn operate_without_select_is_refused() {
+    let (state, handle) = start_outstation();
+    let mut stream = connect(&handle).await;
+
+    let mut operate = vec![0xC2, 0x04];
+    operate.extend(latch_on(0));
+    let response = request(&mut stream, &operate).await;
+    assert_eq!(response[4..], latch_on(2)); // No select
+    assert!(!state.coils.lock().unwrap()[&1]);
+}
+
+#[tokio::test]
+async fn unsupported_requests_are_rejected() {
+    let (_state, handle) = start_outstation();
+    let mut stream = connect(&handle).await;
+
+    // Cold restart, then a read of an object group never served
+    let response = request(&mut stream, &[0xC1, 0x0D]).await;
+    assert_eq!(response[3], 0x01); // No function code support
+    let response = request(&mut stream, &[0xC2, 0x01, 21, 1, 0x06]).await;
+    assert_eq!(response[3], 0x02); // Object unknown
+    assert_eq!(response.len(), 4);
+}
+
+#[tokio::test]
+async fn long_responses_span_confirmed_fragments() {
+    let mut tags = TagRegistry::default();
+    let state = ModbusState::default();
+    for address in 0..600 {
+        tags.input_register(&format!("level.{address}"), address);
+        state
+            .input_registers
+            .lock()
+            .unwrap()
+            .insert(address, address);
+    }
+    let (_state, handle) = start(&tags, &state);
+    let mut stream = connect(&handle).await;
+
+    // 600 flagged 32-bit analog inputs do not fit one fragment
+    let first = request(&mut stream, &[0xC3, 0x01, 30, 1, 0x06]).await;
+    assert_eq!(first[0], FIR | CON | 3);
+    assert!(first.len() <= 2048);
+    assert_eq!(first[4..7], [30, 1, 0x01]);
+    let first_stop = u16::from_le_bytes([first[9], first[10]]);
+    assert!(first_stop < 599);
+
+    // The rest only follows the master's confirm
+    send(&mut stream, &[0xC3, 0x00]).await;
+    let last = read_fragment(&mut stream).await;
+    assert_eq!(last[0], FIN | 4);
+    assert_eq!(last[4..7], [30, 1, 0x01]);
+    assert_eq!(u16::from_le_bytes([last[7], last[8]]), first_stop + 1);
+    assert_eq!(u16::from_le_bytes([last[9], last[10]]), 599);
+    let value = 11 + 5 * (598 - first_stop as usize);
+    assert_eq!(last[value..value + 5], [0x01, 87, 2, 0, 0]); // 599
+}
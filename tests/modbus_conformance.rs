@@ -0,0 +1,285 @@
+// modbus_conformance.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::borrow::Cow;
+use std::net::SocketAddr;
+
+use tokio_modbus::client::{Client, Context, Reader, Writer, tcp};
+use tokio_modbus::{ExceptionCode, Request, Response};
+
+use modusim::components::modbus::{ModbusServerHandle, ModbusState};
+use modusim::components::tags::TagKind;
+
+const BITS: u16 = 2000; // Most coils or discrete inputs one request may read
+const REGISTERS: u16 = 125; // Most registers one request may read
+const LAST_ADDRESS: u16 = 0xFFFF;
+
+/// Serves a register map on a free port: coils and discrete inputs 0-1999,
+/// input and holding registers 0-124, and the last address of every table.
+fn start_server() -> (ModbusState, ModbusServerHandle) {
+    let state = ModbusState::default();
+    {
+        let mut coils = state.coils.lock().unwrap();
+        let mut discrete_inputs = state.discrete_inputs.lock().unwrap();
+        for address in (0..BITS).chain([LAST_ADDRESS]) {
+            coils.insert(address, address % 3 == 0);
+            discrete_inputs.insert(address, address % 2 == 0);
+        }
+
+        let mut input_registers = state.input_registers.lock().unwrap();
+        let mut holding_registers = state.holding_registers.lock().unwrap();
+        for address in (0..REGISTERS).chain([LAST_ADDRESS]) {
+            input_registers.insert(address, address.wrapping_mul(2));
+            holding_registers.insert(address, address);
+        }
+    }
+
+    let handle = ModbusServerHandle::start(state.clone(), 0);
+    (state, handle)
+}
+
+async fn connect(handle: &ModbusServerHandle) -> Context {
+    let socket_addr = SocketAddr::from(([127, 0, 0, 1], handle.port()));
+    tcp::connect(socket_addr).await.unwrap()
+}
+
+#[tokio::test]
+async fn reads_round_trip_every_table() {
+    let (_state, handle) = start_server();
+    let mut ctx = connect(&handle).await;
+
+    let coils = ctx.read_coils(0, 6).await.unwrap();
+    assert_eq!(coils, Ok(vec![true, false, false, true, false, false]));
+    let discrete_inputs = ctx.read_discrete_inputs(1, 3).await.unwrap();
+    assert_eq!(discrete_inputs, Ok(vec![false, true, false]));
+    let input_registers = ctx.read_input_registers(10, 3).await.unwrap();
+    assert_eq!(input_registers, Ok(vec![20, 22, 24]));
+    let holding_registers = ctx.read_holding_registers(10, 3).await.unwrap();
+    assert_eq!(holding_registers, Ok(vec![10, 11, 12]));
+}
+
+#[tokio::test]
+async fn writes_round_trip_and_land_in_the_tables() {
+    let (state, handle) = start_server();
+    let mut ctx = connect(&handle).await;
+
+    assert_eq!(ctx.write_single_coil(1, true).await.unwrap(), Ok(()));
+    assert_eq!(ctx.read_coils(1, 1).await.unwrap(), Ok(vec![true]));
+    assert_eq!(ctx.write_single_register(5, 500).await.unwrap(), Ok(()));
+    assert_eq!(
+        ctx.read_holding_registers(5, 1).await.unwrap(),
+        Ok(vec![500])
+    );
+    let values = [7, 8, 9];
+    assert_eq!(
+        ctx.write_multiple_registers(20, &values).await.unwrap(),
+        Ok(())
+    );
+    assert_eq!(
+        ctx.read_holding_registers(20, 3).await.unwrap(),
+        Ok(values.to_vec())
+    );
+
+    assert!(state.coils.lock().unwrap()[&1]);
+    assert_eq!(state.holding_registers.lock().unwrap()[&21], 8);
+}
+
+#[tokio::test]
+async fn writes_to_forced_coils_keep_the_forced_value() {
+    let (state, handle) = start_server();
+    let mut ctx = connect(&handle).await;
+    assert!(state.force(TagKind::Coil, 1, true));
+
+    // The write lands underneath the force and comes back on release
+    assert_eq!(ctx.write_single_coil(1, false).await.unwrap(), Ok(()));
+    assert_eq!(ctx.read_coils(1, 1).await.unwrap(), Ok(vec![true]));
+    assert!(state.coils.lock().unwrap()[&1]);
+
+    state.release(TagKind::Coil, 1);
+    assert_eq!(ctx.read_coils(1, 1).await.unwrap(), Ok(vec![false]));
+}
+
+#[tokio::test]
+async fn forced_inputs_read_back_forced_between_ticks() {
+    let (state, handle) = start_server();
+    let mut ctx = connect(&handle).await;
+    assert!(state.force(TagKind::DiscreteInput, 0, false));
+
+    // The plant writing the input before forces are next applied
+    state.discrete_inputs.lock().unwrap().insert(0, true);
+    assert_eq!(
+        ctx.read_discrete_inputs(0, 1).await.unwrap(),
+        Ok(vec![false])
+    );
+}
+
+#[tokio::test]
+async fn quantities_are_held_to_the_protocol_limits() {
+    let (_state, handle) = start_server();
+    let mut ctx = connect(&handle).await;
+
+    let coils = ctx.read_coils(0, BITS).await.unwrap().unwrap();
+    assert_eq!(coils.len(), BITS as usize);
+    let discrete_inputs = ctx.read_discrete_inputs(0, BITS).await.unwrap().unwrap();
+    assert_eq!(discrete_inputs.len(), BITS as usize);
+    let input_registers = ctx.read_input_registers(0, REGISTERS).await.unwrap();
+    assert_eq!(input_registers.unwrap().len(), REGISTERS as usize);
+    let holding_registers = ctx.read_holding_registers(0, REGISTERS).await.unwrap();
+    assert_eq!(holding_registers.unwrap().len(), REGISTERS as usize);
+    assert_eq!(
+        ctx.write_multiple_registers(0, &[1; 123]).await.unwrap(),
+        Ok(())
+    );
+
+    let too_many = ExceptionCode::IllegalDataValue;
+    assert_eq!(ctx.read_coils(0, BITS + 1).await.unwrap(), Err(too_many));
+    assert_eq!(
+        ctx.read_discrete_inputs(0, BITS + 1).await.unwrap(),
+        Err(too_many)
+    );
+    assert_eq!(
+        ctx.read_input_registers(0, REGISTERS + 1).await.unwrap(),
+        Err(too_many)
+    );
+    assert_eq!(
+        ctx.read_holding_registers(0, REGISTERS + 1).await.unwrap(),
+        Err(too_many)
+    );
+    assert_eq!(
+        ctx.write_multiple_registers(0, &[2; 124]).await.unwrap(),
+        Err(too_many)
+    );
+}
+
+#[tokio::test]
+async fn zero_counts_are_rejected() {
+    let (_state, handle) = start_server();
+    let mut ctx = connect(&handle).await;
+
+    let zero = ExceptionCode::IllegalDataValue;
+    assert_eq!(ctx.read_coils(0, 0).await.unwrap(), Err(zero));
+    assert_eq!(ctx.read_discrete_inputs(0, 0).await.unwrap(), Err(zero));
+    assert_eq!(ctx.read_input_registers(0, 0).await.unwrap(), Err(zero));
+    assert_eq!(ctx.read_holding_registers(0, 0).await.unwrap(), Err(zero));
+    assert_eq!(
+        ctx.write_multiple_registers(0, &[]).await.unwrap(),
+        Err(zero)
+    );
+}
+
+#[tokio::test]
+async fn the_last_address_is_served_without_wrapping() {
+    let (_state, handle) = start_server();
+    let mut ctx = connect(&handle).await;
+
+    assert_eq!(
+        ctx.read_coils(LAST_ADDRESS, 1).await.unwrap(),
+        Ok(vec![true])
+    );
+    assert_eq!(
+        ctx.read_input_registers(LAST_ADDRESS, 1).await.unwrap(),
+        Ok(vec![LAST_ADDRESS.wrapping_mul(2)])
+    );
+    assert_eq!(
+        ctx.write_single_register(LAST_ADDRESS, 42).await.unwrap(),
+        Ok(())
+    );
+    assert_eq!(
+        ctx.read_holding_registers(LAST_ADDRESS, 1).await.unwrap(),
+        Ok(vec![42])
+    );
+
+    // Spans running past the end of the address space
+    let past_the_end = ExceptionCode::IllegalDataAddress;
+    assert_eq!(
+        ctx.read_coils(LAST_ADDRESS, 2).await.unwrap(),
+        Err(past_the_end)
+    );
+    assert_eq!(
+        ctx.read_discrete_inputs(LAST_ADDRESS, 2).await.unwrap(),
+        Err(past_the_end)
+    );
+    assert_eq!(
+        ctx.read_holding_registers(LAST_ADDRESS, 2).await.unwrap(),
+        Err(past_the_end)
+    );
+    assert_eq!(
+        ctx.write_multiple_registers(LAST_ADDRESS, &[1, 2])
+            .await
+            .unwrap(),
+        Err(past_the_end)
+    );
+
+    // The server is still answering
+    assert_eq!(ctx.read_coils(0, 1).await.unwrap(), Ok(vec![true]));
+}
+
+#[tokio::test]
+async fn unregistered_addresses_are_rejected_without_partial_writes() {
+    let (state, handle) = start_server();
+    let mut ctx = connect(&handle).await;
+
+    let illegal = ExceptionCode::IllegalDataAddress;
+    assert_eq!(ctx.read_coils(BITS, 1).await.unwrap(), Err(illegal));
+    assert_eq!(
+        ctx.read_discrete_inputs(BITS - 1, 2).await.unwrap(),
+        Err(illegal)
+    );
+    assert_eq!(
+        ctx.read_input_registers(REGISTERS, 1).await.unwrap(),
+        Err(illegal)
+    );
+    assert_eq!(
+        ctx.read_holding_registers(REGISTERS - 1, 2).await.unwrap(),
+        Err(illegal)
+    );
+    assert_eq!(
+        ctx.write_single_coil(BITS, true).await.unwrap(),
+        Err(illegal)
+    );
+    assert_eq!(
+        ctx.write_single_register(REGISTERS, 1).await.unwrap(),
+        Err(illegal)
+    );
+    assert_eq!(
+        ctx.write_multiple_registers(REGISTERS - 1, &[1, 2])
+            .await
+            .unwrap(),
+        Err(illegal)
+    );
+
+    let holding_registers = state.holding_registers.lock().unwrap();
+    assert_eq!(holding_registers[&(REGISTERS - 1)], REGISTERS - 1);
+    assert!(!holding_registers.contains_key(&REGISTERS));
+}
+
+#[tokio::test]
+async fn unsupported_function_codes_are_illegal() {
+    let (state, handle) = start_server();
+    let mut ctx = connect(&handle).await;
+
+    let illegal = ExceptionCode::IllegalFunction;
+    assert_eq!(
+        ctx.write_multiple_coils(0, &[false, true]).await.unwrap(),
+        Err(illegal)
+    );
+    assert_eq!(
+        ctx.masked_write_register(0, 0x00F0, 0x0001).await.unwrap(),
+        Err(illegal)
+    );
+    assert_eq!(
+        ctx.read_write_multiple_registers(0, 2, 0, &[1, 2])
+            .await
+            .unwrap(),
+        Err(illegal)
+    );
+    let custom: Result<Response, ExceptionCode> = ctx
+        .call(Request::Custom(0x41, Cow::Borrowed(&[0x00])))
+        .await
+        .unwrap();
+    assert_eq!(custom, Err(illegal));
+
+    assert!(!state.coils.lock().unwrap()[&1]);
+    assert_eq!(state.holding_registers.lock().unwrap()[&0], 0);
+}
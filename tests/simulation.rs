@@ -0,0 +1,1557 @@
+// simulation.rs
+// Copyright (C) 2025 deciphr
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::{
+    ActiveHooks, Ccd, Collider, CollisionGroups, RapierConfiguration, RapierContextSimulation,
+    RigidBody,
+};
+use serde::Deserialize;
+
+use modusim::components::{
+    arbitration::{ArbitrationSettings, WriteOrigin, WriteSource},
+    bottle::{
+        ArrivalSchedule, Bottle, BottlePool, BottlePosition, BottleSpec, Capped, FillLevel,
+        InfeedProfile, SerialCounter,
+    },
+    conveyor::{ConveyorShape, ConveyorState, belt_infeed, belt_outfeed},
+    gallery::GALLERY,
+    guide::{Chute, GuideRail, GuideShape},
+    inspector::{adjust, inspect},
+    jam::Jammed,
+    logger::DataLogger,
+    modbus::{ClientPolicy, ClientSessions, ModbusState, NetworkImpairment},
+    oee::OeeMonitor,
+    outfeed::Outfeed,
+    physics::{PhysicsSettings, SurfaceMotion},
+    sensor::{FaultKind, SCENERY_GROUP, Sensor, SensorFaults, SensorShape},
+    simulation::{ResetSimulation, SimState, SimTime, SimulationError},
+    snapshot::{RestoreSnapshot, SaveSnapshot, Snapshot},
+    spill::SpillZone,
+    station::StationId,
+    stats::ProductionStats,
+    tags::{DeviceName, Scaling, TagKind, TagRegistry},
+    timeline::{TimelineAction, TimelineEntry},
+    tracking::TrackingZone,
+    valve::{BallSpawner, ValveState},
+};
+use modusim::machine::{Machine, MachineContext, MachineRegistry};
+use modusim::scenario::{
+    CurveConfig, DistanceConfig, EnergyConfig, GuideConfig, InfeedConfig, JogConfig, LoadScenario,
+    MachineConfig, OeeConfig, ReloadScenario, Scenario, SensorItem, StationConfig,
+    TrackingZoneConfig, TurntableConfig, ValveTravelConfig,
+};
+use modusim::testing::{Simulation, SimulationBuilder};
+
+const CONVEYOR_COIL: u16 = 0x0000;
+const CONVEYOR_SPEED_HOLDING: u16 = 0x0000;
+const SPEED_FEEDBACK_INPUT: u16 = 0x000B;
+
+fn conveyor_state(sim: &mut Simulation) -> ConveyorState {
+    let world = sim.world_mut();
+    let mut query = world.query::<&ConveyorState>();
+    query.single(world).unwrap().clone()
+}
+
+fn bottle_positions(sim: &mut Simulation) -> Vec<Vec2> {
+    let world = sim.world_mut();
+    let mut query = world.query_filtered::<(&Transform, &BottlePosition), With<Bottle>>();
+    query
+        .iter(world)
+        .map(|(transform, position)| position.world_center(transform))
+        .collect()
+}
+
+#[test]
+fn conveyor_registers_are_published() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+
+    assert_eq!(sim.read_coil(CONVEYOR_COIL), Some(true));
+    assert_eq!(sim.read_holding(CONVEYOR_SPEED_HOLDING), Some(100));
+}
+
+#[test]
+fn holding_write_changes_conveyor_speed() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+
+    sim.write_holding(CONVEYOR_SPEED_HOLDING, 40).unwrap();
+    sim.advance(2);
+
+    assert_eq!(conveyor_state(&mut sim).speed, 40.0);
+}
+
+#[test]
+fn resets_return_the_outputs_to_their_defaults() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+    sim.write_coil(CONVEYOR_COIL, false).unwrap();
+    sim.write_holding(CONVEYOR_SPEED_HOLDING, 40).unwrap();
+    sim.advance(2);
+    assert!(!conveyor_state(&mut sim).is_running);
+
+    sim.world_mut().send_event(ResetSimulation);
+    sim.advance(10);
+    assert!(conveyor_state(&mut sim).is_running);
+    assert_eq!(conveyor_state(&mut sim).speed, 100.0);
+    assert_eq!(sim.read_coil(CONVEYOR_COIL), Some(true));
+    assert_eq!(sim.read_holding(CONVEYOR_SPEED_HOLDING), Some(100));
+}
+
+#[test]
+fn scaled_registers_are_published_in_their_units() {
+    let mut scenario = Scenario::default();
+    scenario.scaling.insert(
+        "conveyor.speed".to_string(),
+        Scaling {
+            scale: 10.0,
+            offset: 0.0,
+            unit: "mm/s".to_string(),
+        },
+    );
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(2);
+    assert_eq!(sim.read_holding(CONVEYOR_SPEED_HOLDING), Some(1000));
+
+    sim.write_holding(CONVEYOR_SPEED_HOLDING, 405).unwrap();
+    sim.advance(2);
+
+    assert_eq!(conveyor_state(&mut sim).speed, 40.5);
+}
+
+#[test]
+fn negative_speeds_round_trip_as_twos_complement() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+
+    sim.write_holding(CONVEYOR_SPEED_HOLDING, (-40i16) as u16)
+        .unwrap();
+    sim.advance(2);
+    assert_eq!(conveyor_state(&mut sim).speed, -40.0);
+
+    let world = sim.world_mut();
+    let mut query = world.query::<&mut ConveyorState>();
+    query.single_mut(world).unwrap().speed = -60.0;
+    sim.advance(2);
+    assert_eq!(
+        sim.read_holding(CONVEYOR_SPEED_HOLDING),
+        Some((-60i16) as u16)
+    );
+}
+
+#[test]
+fn belts_running_backwards_are_moving() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.write_holding(CONVEYOR_SPEED_HOLDING, (-40i16) as u16)
+        .unwrap();
+    sim.advance(2);
+
+    let state = conveyor_state(&mut sim);
+    assert!(state.is_moving());
+    assert_eq!(state.belt_speed(), -40.0);
+    let world = sim.world_mut();
+    let mut surfaces = world.query_filtered::<&SurfaceMotion, With<ConveyorState>>();
+    let SurfaceMotion::Linear(velocity) = *surfaces.single(world).unwrap() else {
+        panic!("A straight belt moves linearly");
+    };
+    assert!(velocity.x < 0.0);
+
+    sim.write_coil(CONVEYOR_COIL, false).unwrap();
+    sim.advance(2);
+    assert!(!conveyor_state(&mut sim).is_moving());
+}
+
+#[test]
+fn timeline_impairs_and_restores_the_network() {
+    assert!(Scenario::parse("[network]\nlatency = 1e30").is_err());
+    assert!(Scenario::parse("[network]\nexceptions = 1.5").is_err());
+
+    let mut scenario = Scenario::default();
+    scenario.timeline = vec![
+        "at 0.5s: impair network latency=0.2 drop_responses=0.1"
+            .parse()
+            .unwrap(),
+        "at 1s: restore network".parse().unwrap(),
+    ];
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+
+    sim.advance_secs(0.75);
+    assert_eq!(
+        *sim.resource::<NetworkImpairment>(),
+        NetworkImpairment {
+            latency: 0.2,
+            drop_responses: 0.1,
+            ..default()
+        }
+    );
+
+    sim.advance_secs(0.5);
+    assert!(!sim.resource::<NetworkImpairment>().is_active());
+}
+
+#[test]
+fn timeline_lines_parse_and_print_back() {
+    let lines = [
+        "at 0s: set valve.spawn_rate=0.5",
+        "at 2.5s: inject fault bottle_sensor wire_break",
+        "at 3s: inject fault valve seized",
+        "at 4s: clear fault bottle_sensor",
+        "at 5s: clear faults",
+        "at 6s: impair network latency=0.2 jitter=0.05",
+        "at 7s: restore network",
+    ];
+    for line in lines {
+        let entry: TimelineEntry = line.parse().unwrap();
+        assert_eq!(entry.to_string(), line);
+    }
+
+    let entry: TimelineEntry = "at 30: set conveyor.speed=-12.5".parse().unwrap();
+    assert_eq!(entry.at, 30.0);
+    assert_eq!(
+        entry.action,
+        TimelineAction::Set {
+            tag: "conveyor.speed".to_string(),
+            value: -12.5,
+        }
+    );
+}
+
+#[test]
+fn invalid_timeline_lines_are_rejected() {
+    let lines = [
+        "set valve.spawn_rate=2",
+        "at -1s: clear faults",
+        "at soon: clear faults",
+        "at 1s: set valve.spawn_rate",
+        "at 1s: set valve.spawn_rate=fast",
+        "at 1s: set valve.spawn_rate=inf",
+        "at 1s: inject fault bottle_sensor melted",
+        "at 1s: impair network latency=-1",
+        "at 1s: impair network latency=1e30",
+        "at 1s: impair network drop_requests=2",
+        "at 1s: impair network bandwidth=1",
+        "at 1s: open the valve",
+    ];
+    for line in lines {
+        assert!(line.parse::<TimelineEntry>().is_err(), "{line}");
+    }
+}
+
+#[test]
+fn timeline_sets_go_through_the_tags_scaling() {
+    let mut scenario = Scenario::default();
+    scenario.scaling.insert(
+        "conveyor.speed".to_string(),
+        Scaling {
+            scale: 10.0,
+            ..default()
+        },
+    );
+    scenario.timeline = vec!["at 0s: set conveyor.speed=-2.5".parse().unwrap()];
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(2);
+
+    assert_eq!(
+        sim.read_holding(CONVEYOR_SPEED_HOLDING),
+        Some((-25i16) as u16)
+    );
+}
+
+#[test]
+fn client_sessions_are_limited_and_read_only_by_address() {
+    let plc: std::net::SocketAddr = "192.168.0.10:50000".parse().unwrap();
+    let viewer: std::net::SocketAddr = "192.168.0.20:50001".parse().unwrap();
+    let mut sessions = ClientSessions::default();
+    sessions.set_policy(ClientPolicy {
+        max_clients: Some(2),
+        read_only: vec![viewer.ip()],
+    });
+
+    let plc_id = sessions.open(plc).unwrap();
+    let viewer_id = sessions.open(viewer).unwrap();
+    assert_eq!(sessions.open(viewer), None);
+    assert_eq!(sessions.refused, 1);
+
+    assert!(!sessions.count_request(plc_id));
+    assert!(sessions.count_request(viewer_id));
+    assert_eq!(
+        sessions.iter().map(|session| session.requests).sum::<u64>(),
+        2
+    );
+
+    sessions.close(plc_id);
+    assert!(sessions.open(plc).is_some());
+}
+
+#[test]
+fn coil_write_stops_conveyor() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+
+    sim.write_coil(CONVEYOR_COIL, false).unwrap();
+    sim.advance(2);
+
+    assert!(!conveyor_state(&mut sim).is_running);
+}
+
+#[test]
+fn tag_sync_publishes_plant_changes_before_reading_client_writes() {
+    const VALVE_SPAWN_RATE_HOLDING: u16 = 0x0001;
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+
+    // The plant stops the belt in the same tick the PLC changes the spawn rate
+    let world = sim.world_mut();
+    let mut conveyors = world.query::<&mut ConveyorState>();
+    conveyors.single_mut(world).unwrap().is_running = false;
+    sim.write_holding(VALVE_SPAWN_RATE_HOLDING, 3).unwrap();
+    sim.advance(1);
+
+    assert!(!conveyor_state(&mut sim).is_running);
+    assert_eq!(sim.read_coil(CONVEYOR_COIL), Some(false));
+    let world = sim.world_mut();
+    let mut valves = world.query::<&ValveState>();
+    assert_eq!(valves.single(world).unwrap().spawn_rate, 3.0);
+}
+
+#[test]
+fn only_writes_from_outside_the_plant_advance_the_register_generation() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+    let generation = sim.resource::<ModbusState>().generation();
+
+    // Publishing the plant's own changes is not a write to read back
+    let world = sim.world_mut();
+    let mut conveyors = world.query::<&mut ConveyorState>();
+    conveyors.single_mut(world).unwrap().is_running = false;
+    sim.advance(2);
+    assert_eq!(sim.read_coil(CONVEYOR_COIL), Some(false));
+    assert_eq!(sim.resource::<ModbusState>().generation(), generation);
+
+    sim.write_coil(CONVEYOR_COIL, true).unwrap();
+    assert_eq!(sim.resource::<ModbusState>().generation(), generation + 1);
+    sim.advance(1);
+    assert!(conveyor_state(&mut sim).is_running);
+}
+
+#[test]
+fn jog_coils_move_a_stopped_belt_and_maintenance_holds_spawning() {
+    let mut scenario = Scenario::default();
+    scenario.conveyors[0].jog = Some(JogConfig {
+        forward_coil: 0x0030,
+        reverse_coil: 0x0031,
+        maintenance_coil: 0x0032,
+        speed: 30.0,
+    });
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(2);
+
+    sim.write_coil(CONVEYOR_COIL, false).unwrap();
+    sim.write_coil(0x0032, true).unwrap();
+    sim.write_coil(0x0030, true).unwrap();
+    sim.advance(2);
+    let bottles = sim.query_entities::<Bottle>().len();
+    let state = conveyor_state(&mut sim);
+    assert!(!state.is_running);
+    assert_eq!(state.belt_speed(), 30.0);
+
+    sim.write_coil(0x0030, false).unwrap();
+    sim.write_coil(0x0031, true).unwrap();
+    sim.advance(2);
+    assert_eq!(conveyor_state(&mut sim).belt_speed(), -30.0);
+
+    // Running takes over from the jog; the run coil and speed are untouched
+    sim.write_coil(CONVEYOR_COIL, true).unwrap();
+    sim.advance(2);
+    assert_eq!(conveyor_state(&mut sim).belt_speed(), 100.0);
+    assert_eq!(sim.read_holding(CONVEYOR_SPEED_HOLDING), Some(100));
+
+    sim.advance_secs(10.0);
+    assert_eq!(sim.query_entities::<Bottle>().len(), bottles);
+    let tag = sim
+        .resource::<TagRegistry>()
+        .get("conveyor.maintenance")
+        .unwrap();
+    assert_eq!(tag.address, 0x0032);
+}
+
+#[test]
+fn proportional_valve_dispenses_in_proportion_to_its_opening() {
+    const OPENING_HOLDING: u16 = 0x0030;
+    let mut scenario = Scenario::default();
+    scenario.valves[0].opening_holding = Some(OPENING_HOLDING);
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(2);
+    let dispensed = |sim: &mut Simulation| {
+        let world = sim.world_mut();
+        let mut query = world.query::<&BallSpawner>();
+        query.single(world).unwrap().dispensed
+    };
+
+    // The open coil only reports the valve passing
+    assert_eq!(sim.read_holding(OPENING_HOLDING), Some(0));
+    sim.write_coil(0x0001, true).unwrap();
+    sim.advance_secs(3.0);
+    assert_eq!(sim.read_coil(0x0001), Some(false));
+    assert_eq!(dispensed(&mut sim), 0);
+
+    sim.write_holding(OPENING_HOLDING, 50).unwrap();
+    sim.advance_secs(10.2);
+    assert_eq!(sim.read_coil(0x0001), Some(true));
+    assert_eq!(dispensed(&mut sim), 5);
+
+    sim.write_holding(OPENING_HOLDING, 0).unwrap();
+    sim.advance(2);
+    assert_eq!(sim.read_coil(0x0001), Some(false));
+    sim.write_holding(OPENING_HOLDING, 100).unwrap();
+    sim.advance_secs(5.2);
+    assert_eq!(dispensed(&mut sim), 5);
+}
+
+#[test]
+fn unregistered_addresses_are_rejected() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+
+    assert!(sim.write_holding(0xFFFF, 1).is_err());
+    assert!(sim.write_coil(0xFFFF, true).is_err());
+    assert_eq!(sim.read_coil(0xFFFF), None);
+}
+
+#[test]
+fn writes_record_their_writer() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+
+    sim.write_coil(CONVEYOR_COIL, false).unwrap();
+    sim.advance(2);
+
+    let modbus_state = sim.resource::<ModbusState>();
+    let arbitration = modbus_state.arbitration.lock().unwrap();
+    let record = arbitration.record(TagKind::Coil, CONVEYOR_COIL).unwrap();
+    assert_eq!(record.origin, WriteOrigin::remote("test"));
+    assert_eq!(record.value, 0);
+}
+
+#[test]
+fn plant_changes_are_attributed_to_the_logic_unless_forced() {
+    const PLANT_HOLDING: u16 = 0x0F00;
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+
+    let modbus_state = sim.resource::<ModbusState>().clone();
+    modbus_state
+        .holding_registers
+        .lock()
+        .unwrap()
+        .insert(PLANT_HOLDING, 30);
+    assert!(modbus_state.force(TagKind::Coil, CONVEYOR_COIL, false));
+    sim.advance(1);
+
+    let arbitration = modbus_state.arbitration.lock().unwrap();
+    let record = arbitration
+        .record(TagKind::HoldingRegister, PLANT_HOLDING)
+        .unwrap();
+    assert_eq!(record.origin, WriteOrigin::logic("plant"));
+    assert_eq!(record.value, 30);
+    let record = arbitration.record(TagKind::Coil, CONVEYOR_COIL).unwrap();
+    assert_eq!(record.value, 1);
+}
+
+#[test]
+fn hmi_writes_hold_off_remote_writes() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.world_mut()
+        .insert_resource(ArbitrationSettings { hmi_hold: 1.0 });
+    sim.advance(2);
+
+    let modbus_state = sim.resource::<ModbusState>().clone();
+    modbus_state.write(TagKind::Coil, CONVEYOR_COIL, 0, &WriteOrigin::hmi("test"));
+    sim.write_coil(CONVEYOR_COIL, true).unwrap();
+    sim.advance(2);
+    assert!(!conveyor_state(&mut sim).is_running);
+
+    sim.advance_secs(1.0);
+    sim.write_coil(CONVEYOR_COIL, true).unwrap();
+    sim.advance(2);
+    assert!(conveyor_state(&mut sim).is_running);
+
+    let arbitration = modbus_state.arbitration.lock().unwrap();
+    let record = arbitration.record(TagKind::Coil, CONVEYOR_COIL).unwrap();
+    assert_eq!(record.origin.source, WriteSource::Remote);
+}
+
+#[test]
+fn bottles_spawn_on_the_infeed() {
+    let mut sim = SimulationBuilder::new().build();
+    assert!(sim.query_entities::<Bottle>().is_empty());
+
+    sim.advance_secs(2.5);
+
+    assert_eq!(sim.query_entities::<Bottle>().len(), 1);
+}
+
+#[test]
+fn bottles_spawn_on_command_with_the_infeed_disabled() {
+    let mut scenario = Scenario::default();
+    scenario.conveyors[0].infeed = Some(InfeedConfig {
+        spawn_coil: 0x0022,
+        enable_coil: 0x0023,
+        interval_holding: 0x001A,
+    });
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(2);
+    assert_eq!(sim.read_holding(0x001A), Some(2000));
+
+    sim.write_coil(0x0023, false).unwrap();
+    sim.advance_secs(3.0);
+    assert!(sim.query_entities::<Bottle>().is_empty());
+
+    sim.write_coil(0x0022, true).unwrap();
+    sim.advance(2);
+
+    assert_eq!(sim.query_entities::<Bottle>().len(), 1);
+    assert_eq!(sim.read_coil(0x0022), Some(false));
+}
+
+#[test]
+fn arrivals_due_together_all_spawn() {
+    let schedule = ArrivalSchedule::parse("time\n0.5\n0.5\n0.5\n3.0\n").unwrap();
+    let mut sim = SimulationBuilder::new().arrivals(schedule).build();
+
+    sim.advance_secs(1.0);
+    assert_eq!(sim.query_entities::<Bottle>().len(), 3);
+    sim.advance_secs(2.5);
+    assert_eq!(sim.query_entities::<Bottle>().len(), 4);
+    assert!(sim.resource::<ArrivalSchedule>().is_finished());
+}
+
+#[test]
+fn bursts_leave_a_gap_between_them() {
+    let mut scenario = Scenario::default();
+    scenario.conveyors[0].infeed_profile = InfeedProfile::Bursts { count: 2, gap: 5.0 };
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+
+    sim.advance_secs(4.5);
+    assert_eq!(sim.query_entities::<Bottle>().len(), 2);
+
+    sim.advance_secs(4.0);
+    assert_eq!(sim.query_entities::<Bottle>().len(), 2);
+
+    sim.advance_secs(1.0);
+    assert_eq!(sim.query_entities::<Bottle>().len(), 3);
+}
+
+#[test]
+fn negative_infeed_gaps_spawn_without_waiting() {
+    let mut scenario = Scenario::default();
+    scenario.conveyors[0].infeed_profile = InfeedProfile::Scripted {
+        gaps: vec![-1.0, 1.5],
+    };
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+
+    sim.advance_secs(2.0);
+    assert!(!sim.query_entities::<Bottle>().is_empty());
+}
+
+#[test]
+fn pooled_bottles_come_back_as_new() {
+    let mut sim = SimulationBuilder::new().build();
+    let world = sim.world_mut();
+    let spec = BottleSpec::default();
+    let position = Vec2::new(25.0, 200.0);
+
+    let released = world.resource_scope(|world, mut pool: Mut<BottlePool>| {
+        let mut commands = world.commands();
+        let bottle = pool.acquire(&mut commands, position, spec.clone());
+        commands.entity(bottle).insert((Capped, FillLevel(7)));
+        pool.release(&mut commands, bottle, &spec, position);
+        bottle
+    });
+    world.flush();
+    assert!(!world.entity(released).contains::<Bottle>());
+
+    // Another variant cannot reuse the collider, the same one a fraction off can
+    let mut tall = spec.clone();
+    tall.name = "tall".to_string();
+    let (other, reused) = world.resource_scope(|world, mut pool: Mut<BottlePool>| {
+        let mut commands = world.commands();
+        let other = pool.acquire(&mut commands, position, tall);
+        let reused = pool.acquire(&mut commands, position + Vec2::splat(0.2), spec.clone());
+        (other, reused)
+    });
+    world.flush();
+    assert_ne!(other, released);
+    assert_eq!(reused, released);
+
+    let bottle = world.entity(reused);
+    assert!(bottle.contains::<Bottle>());
+    assert!(!bottle.contains::<Capped>());
+    assert_eq!(bottle.get::<FillLevel>().unwrap().0, 0);
+    assert_eq!(bottle.get::<BottleSpec>(), Some(&spec));
+    assert_eq!(bottle.get::<BottlePosition>().unwrap().0, position);
+}
+
+#[test]
+fn bottles_falling_or_being_filled_do_not_jam() {
+    let mut scenario = Scenario::default();
+    scenario.conveyors[0].infeed = Some(InfeedConfig {
+        spawn_coil: 0x0022,
+        enable_coil: 0x0023,
+        interval_holding: 0x001A,
+    });
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.write_coil(0x0023, false).unwrap();
+
+    // Held still in the air above the belt, and on the belt under the valve
+    let mut spawn_held = |position: Vec2| {
+        let mut bottle = sim
+            .world_mut()
+            .spawn((Bottle::new(position, BottleSpec::default()), StationId(0)));
+        bottle.insert(RigidBody::Fixed);
+        bottle.id()
+    };
+    let airborne = spawn_held(Vec2::new(250.0, 200.0));
+    let filling = spawn_held(Vec2::new(-30.0, -50.0));
+
+    sim.write_coil(0x0001, true).unwrap();
+    sim.advance_secs(4.0);
+    assert!(sim.world().get::<Jammed>(airborne).is_none());
+    assert!(sim.world().get::<Jammed>(filling).is_none());
+
+    sim.write_coil(0x0001, false).unwrap();
+    sim.advance_secs(4.0);
+    assert!(sim.world().get::<Jammed>(airborne).is_none());
+    assert!(sim.world().get::<Jammed>(filling).is_some());
+}
+
+#[test]
+fn drops_missing_the_bottles_are_scored_as_misses() {
+    let mut sim = SimulationBuilder::new()
+        .scenario(Scenario::catch_the_drop())
+        .build();
+    sim.advance(2);
+
+    // The first bottle is still far upstream of the valve
+    sim.write_coil(0x0001, true).unwrap();
+    sim.advance_secs(3.0);
+    sim.write_coil(0x0001, false).unwrap();
+    sim.advance_secs(1.5);
+
+    assert_eq!(sim.read_input_register(0x0000), Some(0));
+    assert!(sim.read_input_register(0x0001).unwrap() > 0);
+
+    sim.write_coil(0x0002, true).unwrap();
+    sim.advance(2);
+
+    assert_eq!(sim.read_input_register(0x0001), Some(0));
+}
+
+#[test]
+fn bundled_scenarios_load_without_address_collisions() {
+    for entry in GALLERY {
+        let mut sim = SimulationBuilder::new()
+            .scenario(entry.scenario().unwrap())
+            .build();
+        sim.advance(2);
+
+        let collisions = sim.resource::<TagRegistry>().collisions();
+        assert!(collisions.is_empty(), "{}: {collisions:?}", entry.name);
+    }
+}
+
+#[test]
+fn duplicate_names_and_untagged_addresses_are_reported() {
+    let mut tags = TagRegistry::default();
+    tags.coil("run", 1);
+    tags.coil("run", 1);
+    assert!(tags.duplicates().is_empty());
+    tags.coil("run", 2);
+    assert_eq!(tags.get("run").unwrap().address, 1);
+    assert_eq!(tags.duplicates()[0].address, 2);
+
+    let modbus_state = ModbusState::default();
+    modbus_state
+        .coils
+        .lock()
+        .unwrap()
+        .extend([(1, false), (0x0010, false)]);
+    assert_eq!(tags.untagged(&modbus_state), vec![(TagKind::Coil, 0x0010)]);
+}
+
+#[test]
+fn the_mute_coil_is_a_tag_at_the_scenarios_address() {
+    let scenario = Scenario::parse("[sound]\nmute_coil = 200").unwrap();
+    let sim = SimulationBuilder::new().scenario(scenario).build();
+
+    let tag = sim.resource::<TagRegistry>().get("sound.mute").unwrap();
+    assert_eq!((tag.kind, tag.address), (TagKind::Coil, 200));
+}
+
+#[test]
+fn snapshots_round_trip_onto_their_own_scenario_only() {
+    let path = std::env::temp_dir().join(format!("modusim-snapshot-{}.json", std::process::id()));
+    let other_path = path.with_extension("other.json");
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance_secs(3.0);
+
+    sim.world_mut().send_event(SaveSnapshot(path.clone()));
+    sim.advance(1);
+    let snapshot = Snapshot::from_file(&path).unwrap();
+    assert!(snapshot.conveyors[&CONVEYOR_COIL].is_running);
+    let mut other = snapshot.clone();
+    other.scenario = "another_line".to_string();
+    other.to_file(&other_path).unwrap();
+
+    sim.write_coil(CONVEYOR_COIL, false).unwrap();
+    sim.advance(2);
+    assert!(!conveyor_state(&mut sim).is_running);
+
+    // A snapshot of another scenario leaves the plant alone
+    sim.world_mut()
+        .send_event(RestoreSnapshot(other_path.clone()));
+    sim.advance(1);
+    assert!(!conveyor_state(&mut sim).is_running);
+
+    sim.world_mut().send_event(RestoreSnapshot(path.clone()));
+    sim.advance(1);
+    assert!(conveyor_state(&mut sim).is_running);
+    assert_eq!(sim.read_coil(CONVEYOR_COIL), Some(true));
+    assert!(sim.query_entities::<Bottle>().len() >= snapshot.bottles.len());
+
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(other_path).unwrap();
+}
+
+#[test]
+fn sensor_thresholds_vary_within_the_jitter() {
+    assert!(Scenario::parse("[variability]\nspawn_jitter = 1.0").is_err());
+    assert!(Scenario::parse("[variability]\nspeed_ripple = -0.1").is_err());
+    assert!(Scenario::parse("[variability]\nsensor_delay_jitter = -1.0").is_err());
+
+    let scenario = Scenario::parse("[variability]\nsensor_threshold_jitter = 0.5").unwrap();
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(2);
+
+    let world = sim.world_mut();
+    let mut sensors = world.query_filtered::<&Transform, With<Sensor>>();
+    let scales: Vec<f32> = sensors
+        .iter(world)
+        .map(|transform| transform.scale.x)
+        .collect();
+    assert!(!scales.is_empty());
+    assert!(scales.iter().all(|scale| (0.5..=1.5).contains(scale)));
+    assert!(scales.iter().any(|&scale| scale != 1.0));
+}
+
+#[test]
+fn distance_sensor_measures_down_to_passing_bottles() {
+    let mut scenario = Scenario::default();
+    let mut height_sensor = scenario.sensors[0].clone();
+    height_sensor.name = "height_sensor".to_string();
+    height_sensor.discrete_input = 0x0030;
+    height_sensor.position = [-400.0, 100.0];
+    height_sensor.miss_rate = 0.0;
+    height_sensor.distance = Some(DistanceConfig {
+        input: 0x0030,
+        range: 300.0,
+        direction: [0.0, -1.0],
+    });
+    scenario.sensors.push(height_sensor);
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(2);
+    assert_eq!(sim.read_input_register(0x0030), Some(300));
+
+    let mut nearest = 300;
+    for _ in 0..600 {
+        sim.advance(1);
+        nearest = nearest.min(sim.read_input_register(0x0030).unwrap());
+    }
+
+    // Bottles stand on the belt 200 pixels below the sensor
+    assert!(nearest < 200, "nearest reading {nearest}");
+}
+
+#[test]
+fn despawned_sensors_are_no_longer_published() {
+    const SENSOR_INPUT: u16 = 0x0000;
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+    let world = sim.world_mut();
+    let mut sensors = world.query::<(Entity, &Sensor)>();
+    let (sensor, sensor_tag) = sensors
+        .iter(world)
+        .find(|(_, sensor)| sensor.modbus_address == SENSOR_INPUT)
+        .map(|(entity, sensor)| (entity, sensor.sensor_tag.clone()))
+        .unwrap();
+    let mut faults = world.resource_mut::<SensorFaults>();
+    faults.inject(&sensor_tag, FaultKind::Short);
+    sim.advance(1);
+    assert_eq!(sim.read_discrete_input(SENSOR_INPUT), Some(true));
+
+    // Clearing the fault republishes every sensor, but not a despawned one
+    sim.world_mut().despawn(sensor);
+    sim.advance(1);
+    let mut faults = sim.world_mut().resource_mut::<SensorFaults>();
+    faults.clear(&sensor_tag);
+    sim.advance(1);
+    assert_eq!(sim.read_discrete_input(SENSOR_INPUT), Some(true));
+}
+
+#[test]
+fn ball_sensor_sees_through_passing_bottles() {
+    let mut scenario = Scenario::default();
+    let mut ball_sensor = scenario.sensors[0].clone();
+    ball_sensor.name = "ball_sensor".to_string();
+    ball_sensor.item = SensorItem::Ball;
+    ball_sensor.discrete_input = 0x0030;
+    ball_sensor.position = [-400.0, -50.0];
+    ball_sensor.miss_rate = 0.0;
+    ball_sensor.distance = Some(DistanceConfig {
+        input: 0x0030,
+        range: 300.0,
+        direction: [0.0, -1.0],
+    });
+    scenario.sensors.push(ball_sensor);
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+
+    // The sensor sits inside the bottles' path, upstream of the valve
+    for _ in 0..600 {
+        sim.advance(1);
+        assert_eq!(sim.read_discrete_input(0x0030), Some(false));
+        assert_eq!(sim.read_input_register(0x0030), Some(300));
+    }
+
+    let bottle = sim.query_entities::<Bottle>()[0];
+    let groups = sim.world().get::<CollisionGroups>(bottle).unwrap();
+    assert!(!groups.memberships.contains(SCENERY_GROUP));
+}
+
+#[test]
+fn chained_belts_start_where_the_belt_before_them_ends() {
+    let mut scenario = Scenario::default();
+    let mut inclined = scenario.conveyors[0].clone();
+    inclined.name = "inclined".to_string();
+    inclined.run_coil = 0x0050;
+    inclined.speed_holding = 0x0050;
+    inclined.speed_feedback = None;
+    inclined.upstream = Some("conveyor".to_string());
+    inclined.gap = 10.0;
+    inclined.size = [400.0, 40.0];
+    inclined.incline = 15.0;
+    let mut curved = inclined.clone();
+    curved.name = "curved".to_string();
+    curved.run_coil = 0x0051;
+    curved.speed_holding = 0x0051;
+    curved.upstream = Some("inclined".to_string());
+    curved.incline = 0.0;
+    curved.curve = Some(CurveConfig {
+        radius: 200.0,
+        start: 180.0,
+        sweep: -90.0,
+    });
+    scenario.conveyors.extend([inclined, curved]);
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(1);
+
+    let world = sim.world_mut();
+    let mut conveyors = world.query::<(&DeviceName, &Transform, &Sprite, &ConveyorShape)>();
+    let mut ends = |name: &str| {
+        let (_, transform, sprite, shape) = conveyors
+            .iter(world)
+            .find(|(device, ..)| device.0 == name)
+            .unwrap();
+        (
+            belt_infeed(transform, sprite, shape),
+            belt_outfeed(transform, sprite, shape),
+        )
+    };
+    let (_, conveyor_end) = ends("conveyor");
+    let (inclined_start, inclined_end) = ends("inclined");
+    let (curved_start, _) = ends("curved");
+    assert!(inclined_start.distance(conveyor_end + Vec2::new(10.0, 0.0)) < 0.01);
+    assert!(curved_start.distance(inclined_end + Vec2::new(10.0, 0.0)) < 0.01);
+}
+
+#[test]
+fn guide_rail_across_the_belt_holds_the_bottles_back() {
+    let mut scenario = Scenario::default();
+    let mut downstream = scenario.sensors[0].clone();
+    downstream.name = "downstream".to_string();
+    downstream.discrete_input = 0x0030;
+    downstream.position = [-150.0, -50.0];
+    downstream.miss_rate = 0.0;
+    scenario.sensors.push(downstream);
+    // A rail turned upright stands across the belt, upstream of the sensor
+    scenario.guide_rails.push(GuideConfig {
+        name: "gate".to_string(),
+        shape: GuideShape::Rectangle {
+            size: [100.0, 10.0],
+        },
+        position: [-300.0, -50.0],
+        rotation: 90.0,
+        friction: 0.1,
+    });
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    assert_eq!(sim.query_entities::<GuideRail>().len(), 1);
+
+    // The empty belt reads its surface speed, the held bottles read their own
+    sim.advance(1);
+    assert_eq!(sim.read_input_register(SPEED_FEEDBACK_INPUT), Some(100));
+    for _ in 0..600 {
+        sim.advance(1);
+        assert_eq!(sim.read_discrete_input(0x0030), Some(false));
+    }
+    let held = sim
+        .query_entities::<Bottle>()
+        .into_iter()
+        .filter(|&bottle| sim.world().get::<Transform>(bottle).unwrap().translation.x < -300.0)
+        .count();
+    assert!(held > 0);
+    assert!(sim.read_input_register(SPEED_FEEDBACK_INPUT).unwrap() < 50);
+}
+
+#[test]
+fn polyline_chute_parses_from_toml() {
+    let scenario = Scenario::parse(
+        r#"
+        [[chutes]]
+        name = "slide"
+        shape = { kind = "polyline", points = [[-120.0, 120.0], [-20.0, 40.0], [-20.0, 0.0]] }
+        position = [0.0, 100.0]
+        "#,
+    )
+    .unwrap();
+    let chute = &scenario.chutes[0];
+    assert_eq!(chute.friction, 0.1);
+    assert_eq!(chute.rotation, 0.0);
+
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(1);
+    assert_eq!(sim.query_entities::<Chute>().len(), 1);
+}
+
+#[test]
+fn data_logger_records_selected_tags_to_csv() {
+    let directory = std::env::temp_dir().join(format!("modusim-log-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("data_log.csv");
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(directory.join("data_log-2.csv"));
+
+    let mut sim = SimulationBuilder::new().build();
+    {
+        let mut logger = sim.world_mut().resource_mut::<DataLogger>();
+        logger.path = path.clone();
+        logger.selected = vec!["conveyor.run".to_string(), "conveyor.speed".to_string()];
+        logger.is_recording = true;
+    }
+    sim.advance_secs(1.0);
+    sim.world_mut().resource_mut::<DataLogger>().is_recording = false;
+    sim.advance(1);
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    let mut lines = log.lines();
+    assert_eq!(lines.next(), Some("time,conveyor.run,conveyor.speed"));
+    let rows: Vec<&str> = lines.collect();
+    // 10 samples a second by default
+    assert!((10..=11).contains(&rows.len()), "{} rows", rows.len());
+    assert!(rows.iter().all(|row| row.ends_with(",1,100")), "{rows:?}");
+
+    // Other columns go to a file of their own
+    {
+        let mut logger = sim.world_mut().resource_mut::<DataLogger>();
+        logger.toggle_tag("conveyor.speed");
+        logger.is_recording = true;
+    }
+    sim.advance(2);
+    let recording = sim
+        .resource::<DataLogger>()
+        .recording()
+        .map(|(path, _)| path.to_owned());
+    assert_eq!(recording, Some(directory.join("data_log-2.csv")));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), log);
+}
+
+#[test]
+fn rotated_beam_sensor_reaches_down_to_the_bottles() {
+    let mut scenario = Scenario::default();
+    let beam = |name: &str, discrete_input: u16, x: f32, rotation: f32| {
+        let mut sensor = scenario.sensors[0].clone();
+        sensor.name = name.to_string();
+        sensor.discrete_input = discrete_input;
+        sensor.position = [x, 60.0];
+        sensor.miss_rate = 0.0;
+        sensor.shape = SensorShape::Segment { length: 200.0 };
+        sensor.rotation = rotation;
+        sensor
+    };
+    // Both beams are centred above the bottles' tops; only the upright one
+    // reaches down to them
+    let flat_beam = beam("flat_beam", 0x0030, -400.0, 0.0);
+    let upright_beam = beam("upright_beam", 0x0031, -300.0, 90.0);
+    scenario.sensors.extend([flat_beam, upright_beam]);
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+
+    let (mut flat_tripped, mut upright_tripped) = (false, false);
+    for _ in 0..600 {
+        sim.advance(1);
+        flat_tripped |= sim.read_discrete_input(0x0030).unwrap();
+        upright_tripped |= sim.read_discrete_input(0x0031).unwrap();
+    }
+
+    assert!(!flat_tripped);
+    assert!(upright_tripped);
+}
+
+#[test]
+fn empty_world_has_no_registers() {
+    let mut sim = SimulationBuilder::new().without_environment().build();
+    sim.advance(2);
+
+    assert_eq!(sim.read_coil(CONVEYOR_COIL), None);
+}
+
+#[test]
+fn plant_stands_still_while_paused() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+    assert_eq!(*sim.resource::<State<SimState>>().get(), SimState::Running);
+
+    sim.world_mut()
+        .resource_mut::<NextState<SimState>>()
+        .set(SimState::Paused);
+    sim.advance(1);
+    let ticks = sim.resource::<SimTime>().ticks();
+    sim.advance_secs(1.0);
+
+    assert_eq!(sim.resource::<SimTime>().ticks(), ticks);
+}
+
+#[test]
+fn simulation_error_stops_the_plant_until_a_scenario_loads() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.world_mut()
+        .send_event(SimulationError("Failed to load scenario".to_string()));
+    sim.advance(2);
+
+    assert_eq!(*sim.resource::<State<SimState>>().get(), SimState::Error);
+    let ticks = sim.resource::<SimTime>().ticks();
+    sim.advance_secs(1.0);
+    assert_eq!(sim.resource::<SimTime>().ticks(), ticks);
+
+    sim.world_mut()
+        .send_event(LoadScenario(Scenario::default()));
+    sim.advance(2);
+
+    assert_eq!(*sim.resource::<State<SimState>>().get(), SimState::Running);
+}
+
+#[test]
+fn loading_a_scenario_drops_the_old_plants_registers() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+
+    let mut scenario = Scenario::default();
+    scenario.conveyors[0].run_coil = 0x0030;
+    sim.world_mut().send_event(LoadScenario(scenario));
+    sim.advance(2);
+
+    assert_eq!(sim.read_coil(CONVEYOR_COIL), None);
+    assert!(sim.read_coil(0x0030).is_some());
+}
+
+#[test]
+fn runs_with_the_same_seed_are_identical() {
+    let mut first = SimulationBuilder::new().seed(7).build();
+    let mut second = SimulationBuilder::new().seed(7).build();
+
+    first.advance_secs(10.0);
+    second.advance_secs(10.0);
+
+    let first_positions = bottle_positions(&mut first);
+    assert!(!first_positions.is_empty());
+    assert_eq!(first_positions, bottle_positions(&mut second));
+}
+
+#[test]
+fn stations_run_independently_at_offset_addresses() {
+    let mut scenario = Scenario::default();
+    scenario.stations = vec![
+        StationConfig {
+            name: "bench1".to_string(),
+            offset: [0.0, 0.0],
+            address_offset: 0,
+        },
+        StationConfig {
+            name: "bench2".to_string(),
+            offset: [0.0, -400.0],
+            address_offset: 0x0100,
+        },
+    ];
+    scenario.bounds.min_y = -900.0;
+
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(2);
+
+    sim.write_coil(CONVEYOR_COIL + 0x0100, false).unwrap();
+    sim.advance(2);
+
+    let world = sim.world_mut();
+    let mut query = world.query::<(&ConveyorState, &StationId)>();
+    for (state, station) in query.iter(world) {
+        assert_eq!(state.is_running, *station == StationId(0));
+    }
+    assert_eq!(sim.read_coil(CONVEYOR_COIL), Some(true));
+}
+
+#[test]
+fn inspector_edits_components_in_place() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance_secs(3.0);
+
+    let conveyor = sim.query_entities::<ConveyorState>()[0];
+    let bottle = sim.query_entities::<Bottle>()[0];
+    let world = sim.world_mut();
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+
+    let fields = inspect(&registry, world.entity(bottle));
+    assert!(fields.iter().any(|field| field.path == "FillLevel.0"));
+    assert!(
+        fields
+            .iter()
+            .any(|field| field.path == "Transform.translation.x")
+    );
+
+    let fill_level = world.get::<FillLevel>(bottle).unwrap().0;
+    adjust(&registry, world.entity_mut(bottle).into(), "FillLevel.0", 3).unwrap();
+    assert_eq!(world.get::<FillLevel>(bottle).unwrap().0, fill_level + 3);
+
+    let speed = world.get::<ConveyorState>(conveyor).unwrap().speed;
+    adjust(
+        &registry,
+        world.entity_mut(conveyor).into(),
+        "ConveyorState.speed",
+        1,
+    )
+    .unwrap();
+    assert!(world.get::<ConveyorState>(conveyor).unwrap().speed > speed);
+
+    // Addresses stay as the scenario registered them
+    let address = adjust(
+        &registry,
+        world.entity_mut(conveyor).into(),
+        "Conveyor.coil_address",
+        1,
+    );
+    assert!(address.is_err());
+    let count = adjust(
+        &registry,
+        world.entity_mut(bottle).into(),
+        "FillLevel.0",
+        -1000,
+    );
+    assert!(count.is_err());
+    drop(registry);
+
+    // Edited in place rather than rebuilt
+    sim.advance(2);
+    assert_eq!(sim.query_entities::<ConveyorState>(), vec![conveyor]);
+}
+
+#[test]
+fn reloads_drop_the_registers_of_rebuilt_devices() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(2);
+
+    let mut edited = sim.resource::<Scenario>().clone();
+    edited.conveyors[0].run_coil = 0x0030;
+    sim.world_mut().send_event(ReloadScenario(edited));
+    sim.advance(2);
+
+    assert_eq!(sim.read_coil(CONVEYOR_COIL), None);
+    assert!(sim.read_coil(0x0030).is_some());
+}
+
+/// Stack light defined the way a crate outside ModuSim would.
+struct Beacon;
+
+#[derive(Component)]
+struct BeaconLamp(u16);
+
+#[derive(Deserialize)]
+struct BeaconConfig {
+    lamp_coil: u16,
+}
+
+impl Machine for Beacon {
+    const KIND: &'static str = "beacon";
+    type Config = BeaconConfig;
+
+    fn spawn(config: &BeaconConfig, context: &mut MachineContext) -> anyhow::Result<()> {
+        let lamp = context.coil("lamp", config.lamp_coil);
+        context.spawn(BeaconLamp(lamp));
+        Ok(())
+    }
+}
+
+#[test]
+fn registered_machines_spawn_from_the_scenario() {
+    let mut sim = SimulationBuilder::new().build();
+    sim.world_mut()
+        .resource_mut::<MachineRegistry>()
+        .register::<Beacon>();
+
+    let mut scenario = Scenario::default();
+    scenario.machines = vec![
+        MachineConfig {
+            kind: "beacon".to_string(),
+            name: "beacon".to_string(),
+            settings: toml::from_str("lamp_coil = 30").unwrap(),
+        },
+        MachineConfig {
+            kind: "unknown".to_string(),
+            name: "mystery".to_string(),
+            settings: toml::Table::new(),
+        },
+    ];
+    sim.world_mut().send_event(LoadScenario(scenario));
+    sim.advance(2);
+
+    let lamps = sim.query_entities::<BeaconLamp>();
+    assert_eq!(lamps.len(), 1);
+    let name = sim.world().get::<DeviceName>(lamps[0]).unwrap();
+    assert_eq!(name.0, "beacon");
+
+    let tag = sim.resource::<TagRegistry>().get("beacon.lamp").unwrap();
+    assert_eq!((tag.kind, tag.address), (TagKind::Coil, 30));
+}
+
+#[test]
+fn physics_settings_come_from_the_scenario() {
+    let scenario = Scenario {
+        physics: PhysicsSettings {
+            gravity: [0.0, -4.9],
+            pixels_per_meter: 50.0,
+            ccd: false,
+            solver_iterations: 8,
+            ..default()
+        },
+        ..default()
+    };
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(1);
+
+    let world = sim.world_mut();
+    let configuration = world.query::<&RapierConfiguration>().single(world).unwrap();
+    assert_eq!(configuration.gravity, Vec2::new(0.0, -245.0));
+    let simulation = world
+        .query::<&RapierContextSimulation>()
+        .single(world)
+        .unwrap();
+    let parameters = &simulation.integration_parameters;
+    assert_eq!(parameters.length_unit, 50.0);
+    assert_eq!(parameters.num_solver_iterations.get(), 8);
+
+    sim.world_mut()
+        .spawn(Bottle::new(Vec2::new(0.0, 100.0), BottleSpec::default()));
+    sim.advance(1);
+    let world = sim.world_mut();
+    let mut bodies = world.query::<&Ccd>();
+    assert!(bodies.iter(world).all(|ccd| !ccd.enabled));
+}
+
+#[test]
+fn unsteppable_physics_settings_are_rejected() {
+    let settings = [
+        "[physics]\npixels_per_meter = 0.0",
+        "[physics]\npixels_per_meter = -100.0",
+        "[physics]\ngravity = [0.0, inf]",
+        "[physics]\nsolver_iterations = 0",
+        "[physics]\nsubsteps = 0",
+    ];
+    for setting in settings {
+        assert!(Scenario::parse(setting).is_err(), "{setting}");
+    }
+    assert!(Scenario::parse("[physics]\npixels_per_meter = 50.0\nsubsteps = 2").is_ok());
+}
+
+#[test]
+fn colliders_with_surface_motion_carry_items_along() {
+    let mut sim = SimulationBuilder::new().without_environment().build();
+    sim.world_mut().spawn((
+        Collider::cuboid(400.0, 20.0),
+        SurfaceMotion::Linear(Vec2::new(-80.0, 0.0)),
+        ActiveHooks::MODIFY_SOLVER_CONTACTS,
+        Transform::from_xyz(0.0, -100.0, 0.0),
+    ));
+    sim.world_mut()
+        .spawn(Bottle::new(Vec2::new(0.0, -30.0), BottleSpec::default()));
+    sim.advance_secs(2.0);
+
+    let positions = bottle_positions(&mut sim);
+    assert_eq!(positions.len(), 1);
+    assert!(positions[0].x < -80.0, "bottle stayed at {}", positions[0]);
+}
+
+#[test]
+fn child_colliders_move_with_their_parents_surface() {
+    let mut sim = SimulationBuilder::new().without_environment().build();
+    sim.world_mut()
+        .spawn((
+            SurfaceMotion::Linear(Vec2::new(80.0, 0.0)),
+            Transform::from_xyz(0.0, -100.0, 0.0),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Collider::cuboid(400.0, 20.0),
+                ActiveHooks::MODIFY_SOLVER_CONTACTS,
+                Transform::default(),
+            ));
+        });
+    sim.world_mut()
+        .spawn(Bottle::new(Vec2::new(0.0, -30.0), BottleSpec::default()));
+    sim.advance_secs(2.0);
+
+    let positions = bottle_positions(&mut sim);
+    assert!(positions[0].x > 80.0, "bottle stayed at {}", positions[0]);
+}
+
+#[test]
+fn turntables_index_one_station_per_rising_edge() {
+    const INDEX_COIL: u16 = 0x0040;
+    const IN_POSITION_INPUT: u16 = 0x0040;
+    const POSITION_INPUT: u16 = 0x0040;
+
+    let mut scenario = Scenario::default();
+    scenario.turntables.push(TurntableConfig {
+        name: "turntable".to_string(),
+        index_coil: INDEX_COIL,
+        in_position_discrete: IN_POSITION_INPUT,
+        position_input: POSITION_INPUT,
+        positions: 4,
+        radius: 80.0,
+        speed: 90.0,
+        position: [450.0, 250.0],
+    });
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(1);
+    assert_eq!(sim.read_discrete_input(IN_POSITION_INPUT), Some(true));
+    assert_eq!(sim.read_input_register(POSITION_INPUT), Some(0));
+
+    // A bottle resting on top is carried downstream while the table turns
+    let bottle = sim
+        .world_mut()
+        .spawn(Bottle::new(Vec2::new(450.0, 381.0), BottleSpec::default()))
+        .id();
+    let center = |sim: &Simulation| {
+        let entity = sim.world().entity(bottle);
+        let position = entity.get::<BottlePosition>().unwrap();
+        position.world_center(entity.get::<Transform>().unwrap())
+    };
+    sim.advance(10);
+    let start = center(&sim);
+
+    sim.write_coil(INDEX_COIL, true).unwrap();
+    sim.advance(2);
+    assert_eq!(sim.read_discrete_input(IN_POSITION_INPUT), Some(false));
+    sim.advance_secs(1.0);
+    assert_eq!(sim.read_discrete_input(IN_POSITION_INPUT), Some(true));
+    assert_eq!(sim.read_input_register(POSITION_INPUT), Some(1));
+    let end = center(&sim);
+    assert!(end.x > start.x + 20.0, "bottle went from {start} to {end}");
+
+    // Holding the coil on doesn't index again; the next rising edge does
+    sim.advance_secs(1.0);
+    assert_eq!(sim.read_input_register(POSITION_INPUT), Some(1));
+    sim.write_coil(INDEX_COIL, false).unwrap();
+    sim.advance(1);
+    sim.write_coil(INDEX_COIL, true).unwrap();
+    sim.advance_secs(1.5);
+    assert_eq!(sim.read_input_register(POSITION_INPUT), Some(2));
+}
+
+#[test]
+fn turntables_need_a_positive_radius_and_speed() {
+    let turntable = |radius: f32, speed: f32| {
+        format!(
+            r#"
+            [[turntables]]
+            name = "turntable"
+            index_coil = 24
+            in_position_discrete = 24
+            position_input = 24
+            radius = {radius:?}
+            speed = {speed:?}
+            position = [450.0, -260.0]
+            "#
+        )
+    };
+    assert!(Scenario::parse(&turntable(80.0, 120.0)).is_ok());
+    assert!(Scenario::parse(&turntable(0.0, 120.0)).is_err());
+    assert!(Scenario::parse(&turntable(-80.0, 120.0)).is_err());
+    assert!(Scenario::parse(&turntable(80.0, 0.0)).is_err());
+    assert!(Scenario::parse(&turntable(80.0, -90.0)).is_err());
+}
+
+#[test]
+fn resets_restart_the_oee_measurement() {
+    let mut scenario = Scenario::default();
+    scenario.oee = Some(OeeConfig {
+        ideal_cycle_time: 1.0,
+        availability_input: 0x0050,
+        performance_input: 0x0051,
+        quality_input: 0x0052,
+        oee_input: 0x0053,
+        min_fill: 0,
+        require_cap: false,
+        require_label: false,
+    });
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    let monitor = |sim: &mut Simulation| {
+        let entity = sim.query_entities::<OeeMonitor>()[0];
+        sim.world().get::<OeeMonitor>(entity).unwrap().clone()
+    };
+    sim.advance_secs(2.0);
+    assert!(monitor(&mut sim).planned_time > 1.5);
+
+    sim.world_mut().send_event(ResetSimulation);
+    sim.advance(1);
+    let monitor = monitor(&mut sim);
+    assert!(monitor.planned_time < 0.1);
+    assert!(monitor.run_time < 0.1);
+    assert_eq!(monitor.total, 0);
+}
+
+#[test]
+fn resets_empty_the_tracking_zones_and_restart_serials() {
+    const STATUS_INPUT: u16 = 0x0062;
+
+    let mut scenario = Scenario::default();
+    scenario.tracking_zones.push(TrackingZoneConfig {
+        name: "line".to_string(),
+        serial_input: 0x0060,
+        status_input: STATUS_INPUT,
+        position: [0.0, 0.0],
+        size: [4000.0, 4000.0],
+    });
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    let occupant = |sim: &mut Simulation| {
+        let zone = sim.query_entities::<TrackingZone>()[0];
+        sim.world().get::<TrackingZone>(zone).unwrap().occupant
+    };
+    sim.advance_secs(5.0);
+    assert!(occupant(&mut sim).is_some());
+    assert_ne!(sim.read_input_register(STATUS_INPUT), Some(0));
+
+    sim.world_mut().send_event(ResetSimulation);
+    sim.advance(1);
+    assert_eq!(occupant(&mut sim), None);
+    assert_eq!(sim.read_input_register(STATUS_INPUT), Some(0));
+    assert!(sim.resource::<SerialCounter>().next <= 2);
+}
+
+#[test]
+fn resets_clear_the_energy_totals() {
+    const ENERGY_INPUT: u16 = 0x0071;
+
+    let mut scenario = Scenario::default();
+    scenario.conveyors[0].energy = Some(EnergyConfig {
+        power_input: 0x0070,
+        energy_input: ENERGY_INPUT,
+        rated_power: 3_600_000.0, // A watt-hour every millisecond at rated load
+        rated_speed: 100.0,
+        rated_mass: 10.0,
+    });
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance_secs(1.0);
+    let total = |sim: &Simulation| sim.read_input_register(ENERGY_INPUT + 1).unwrap();
+    assert!(total(&sim) > 100);
+
+    sim.world_mut().send_event(ResetSimulation);
+    sim.advance(1);
+    assert!(total(&sim) < 100);
+}
+
+#[test]
+fn resets_clear_the_outfeed_count_and_production_stats() {
+    const OUTFEED_COUNTER_INPUT: u16 = 0x0006;
+
+    let mut sim = SimulationBuilder::new().build();
+    sim.advance(1);
+    let outfeed = sim.query_entities::<Outfeed>()[0];
+    sim.world_mut().get_mut::<Outfeed>(outfeed).unwrap().count = 5;
+    sim.world_mut().resource_mut::<ProductionStats>().completed = 5;
+    sim.advance(1);
+    assert_eq!(sim.read_input_register(OUTFEED_COUNTER_INPUT), Some(5));
+
+    sim.world_mut().send_event(ResetSimulation);
+    sim.advance(1);
+    assert_eq!(sim.world().get::<Outfeed>(outfeed).unwrap().count, 0);
+    assert_eq!(sim.read_input_register(OUTFEED_COUNTER_INPUT), Some(0));
+    assert_eq!(sim.resource::<ProductionStats>().completed, 0);
+}
+
+#[test]
+fn spill_zones_need_a_threshold_and_clear_on_reset() {
+    let spill_zone = |threshold: u16| {
+        format!(
+            r#"
+            [[spill_zones]]
+            name = "spill"
+            count_input = 13
+            reset_coil = 28
+            threshold = {threshold}
+            position = [-30.0, -150.0]
+            size = [200.0, 20.0]
+            "#
+        )
+    };
+    assert!(Scenario::parse(&spill_zone(0)).is_err());
+    let scenario = Scenario::parse(&spill_zone(1)).unwrap();
+
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(1);
+    let zone = sim.query_entities::<SpillZone>()[0];
+    sim.world_mut().get_mut::<SpillZone>(zone).unwrap().count = 3;
+    sim.advance(1);
+    assert_eq!(sim.read_input_register(13), Some(3));
+
+    sim.world_mut().send_event(ResetSimulation);
+    sim.advance(1);
+    assert_eq!(sim.world().get::<SpillZone>(zone).unwrap().count, 0);
+    assert_eq!(sim.read_input_register(13), Some(0));
+}
+
+#[test]
+fn seized_valves_stop_moving_until_the_fault_clears() {
+    const OPENED_DISCRETE: u16 = 0x0040;
+    const CLOSED_DISCRETE: u16 = 0x0041;
+    let mut scenario = Scenario::default();
+    scenario.valves[0].travel = Some(ValveTravelConfig {
+        open_time: 0.5,
+        close_time: 0.5,
+        timeout: 5.0,
+        open_discrete: OPENED_DISCRETE,
+        closed_discrete: CLOSED_DISCRETE,
+        fault_discrete: 0x0042,
+    });
+    scenario.timeline = vec![
+        "at 0s: inject fault valve seized".parse().unwrap(),
+        "at 2s: clear fault valve".parse().unwrap(),
+    ];
+    let mut sim = SimulationBuilder::new().scenario(scenario).build();
+    sim.advance(2);
+
+    sim.write_coil(0x0001, true).unwrap();
+    sim.advance_secs(1.0);
+    assert_eq!(sim.read_discrete_input(CLOSED_DISCRETE), Some(true));
+    assert_eq!(sim.read_discrete_input(OPENED_DISCRETE), Some(false));
+
+    sim.advance_secs(1.5);
+    assert_eq!(sim.read_discrete_input(CLOSED_DISCRETE), Some(false));
+    assert_eq!(sim.read_discrete_input(OPENED_DISCRETE), Some(true));
+}